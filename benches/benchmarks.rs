@@ -1,4 +1,8 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pathfinding_rs::domains::{bitpackedgrid::BitPackedGrid, fixedgrid::FixedGrid};
+use pathfinding_rs::heuristics::distance::manhattan_distance;
+use pathfinding_rs::heuristics::octile::exact_octile_distance;
+use pathfinding_rs::search::{astar::astar, jps::JumpPointSearch, searcher::GridSearcher};
 
 fn fibonacci(n: u64) -> u64 {
     match n {
@@ -12,5 +16,38 @@ fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("fib 20", |b| b.iter(|| fibonacci(black_box(20))));
 }
 
-criterion_group!(benches, criterion_benchmark);
+/// Octile A-Star against Jump Point Search on the same queries of a Moving AI map
+fn jps_benchmark(c: &mut Criterion) {
+    let grid = BitPackedGrid::new_from_file("tests/map.map");
+    let free = grid.free_cells().collect::<Vec<_>>();
+    let queries = (0..20).map(|i| (free[i * 7919 % free.len()], free[(i * 104729 + 17) % free.len()])).collect::<Vec<_>>();
+    c.bench_function("octile astar map.map", |b| b.iter(|| {
+        for &(start, goal) in &queries {
+            black_box(astar(|n| grid.adjacent_octile(*n), start, |n| *n == goal, |n| exact_octile_distance(*n, goal)));
+        }
+    }));
+    let mut jps = JumpPointSearch::new(&grid);
+    c.bench_function("jps map.map", |b| b.iter(|| {
+        for &(start, goal) in &queries {
+            black_box(jps.search(start, goal));
+        }
+    }));
+}
+
+/// A-Star with hash maps against the reusable searcher on a small fixed grid
+fn small_grid_benchmark(c: &mut Criterion) {
+    let mut grid = FixedGrid::<64, 64>::new_free();
+    (4..60).for_each(|y| grid.set_bit_value((32, y), false));
+    let bitpacked = grid.to_bitpacked();
+    c.bench_function("astar 64x64", |b| b.iter(|| {
+        black_box(astar(|n| bitpacked.adjacent1(*n), (0, 32), |n| *n == (63, 32), |n| manhattan_distance(*n, (63, 32))))
+    }));
+    let mut searcher = GridSearcher::with_capacity(64 * 64);
+    let mut path = Vec::new();
+    c.bench_function("grid searcher 64x64", |b| b.iter(|| {
+        black_box(searcher.search_into(&grid, (0, 32), (63, 32), &mut path))
+    }));
+}
+
+criterion_group!(benches, criterion_benchmark, jps_benchmark, small_grid_benchmark);
 criterion_main!(benches);
\ No newline at end of file
@@ -0,0 +1,133 @@
+//! # Chokepoints
+//! Detection of articulation cells and bridges in the 4-connected free-space
+//! graph of a grid map. An articulation cell is a free cell whose removal
+//! disconnects its component, while a bridge is a pair of neighbouring cells
+//! whose connection is the only route between two parts of the map. These
+//! are the chokepoints of the map, useful for heuristics, map analysis and
+//! gameplay logic.
+//!
+//! This uses an iterative version of Tarjan's algorithm so that large open maps
+//! do not overflow the stack.
+
+use crate::domains::bitpackedgrid::BitPackedGrid;
+
+/// The chokepoints of a grid map
+/// ## Fields
+/// * `articulation_points` - Free cells whose removal disconnects the map in row order
+/// * `bridges` - Pairs of neighbouring free cells whose connection disconnects the map
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chokepoints {
+    pub articulation_points: Vec<(usize, usize)>,
+    pub bridges: Vec<((usize, usize), (usize, usize))>,
+}
+
+/// A frame of the depth first search
+struct Frame {
+    node: (usize, usize),
+    parent: Option<(usize, usize)>,
+    children: Vec<(usize, usize)>,
+}
+
+impl Chokepoints {
+    /// Finds the articulation cells and bridges of a grid map
+    /// ## Complexity
+    /// O(n) where n is the number of cells in the grid map
+    pub fn new(grid: &BitPackedGrid) -> Self {
+        let (width, height) = (grid.original_width, grid.original_height);
+        let mut discovery = vec![vec![usize::MAX; height]; width];
+        let mut low = vec![vec![usize::MAX; height]; width];
+        let mut articulation = vec![vec![false; height]; width];
+        let mut bridges = Vec::new();
+        let mut time = 0;
+        let children = |node: (usize, usize)| grid.adjacent(node, false).collect::<Vec<_>>();
+
+        for root in (0..height).flat_map(|y| (0..width).map(move |x| (x, y))) {
+            if !grid.get_bit_value(root) || discovery[root.0][root.1] != usize::MAX {
+                continue;
+            }
+            discovery[root.0][root.1] = time;
+            low[root.0][root.1] = time;
+            time += 1;
+            let mut root_children = 0;
+            let mut stack = vec![Frame { node: root, parent: None, children: children(root) }];
+            while let Some(frame) = stack.last_mut() {
+                let (x, y) = frame.node;
+                if let Some(child) = frame.children.pop() {
+                    if Some(child) == frame.parent {
+                        continue;
+                    }
+                    if discovery[child.0][child.1] == usize::MAX {
+                        discovery[child.0][child.1] = time;
+                        low[child.0][child.1] = time;
+                        time += 1;
+                        if frame.node == root {
+                            root_children += 1;
+                        }
+                        let parent = Some(frame.node);
+                        stack.push(Frame { node: child, parent, children: children(child) });
+                    } else {
+                        low[x][y] = low[x][y].min(discovery[child.0][child.1]);
+                    }
+                } else {
+                    let Frame { node, parent, .. } = stack.pop().unwrap();
+                    if let Some(parent) = parent {
+                        let (px, py) = parent;
+                        low[px][py] = low[px][py].min(low[x][y]);
+                        if low[x][y] > discovery[px][py] {
+                            bridges.push((parent, node));
+                        }
+                        if parent != root && low[x][y] >= discovery[px][py] {
+                            articulation[px][py] = true;
+                        }
+                    }
+                }
+            }
+            if root_children > 1 {
+                articulation[root.0][root.1] = true;
+            }
+        }
+
+        let mut articulation_points = (0..width)
+            .flat_map(|x| (0..height).map(move |y| (x, y)))
+            .filter(|(x, y)| articulation[*x][*y])
+            .collect::<Vec<_>>();
+        articulation_points.sort_by_key(|(x, y)| (*y, *x));
+        Chokepoints { articulation_points, bridges }
+    }
+
+    /// Checks if a cell is an articulation cell
+    pub fn is_articulation_point(&self, node: (usize, usize)) -> bool {
+        self.articulation_points.contains(&node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chokepoints_corridor() {
+        let grid = BitPackedGrid::new_from_string("..@@..\n......\n..@@..\n".to_string());
+        let chokepoints = Chokepoints::new(&grid);
+        assert_eq!(chokepoints.articulation_points, vec![(1, 1), (2, 1), (3, 1), (4, 1)]);
+        assert_eq!(chokepoints.bridges.len(), 3);
+        assert!(chokepoints.bridges.iter().any(|&(a, b)| [(a, b), (b, a)].contains(&((2, 1), (3, 1)))));
+    }
+
+    #[test]
+    fn test_chokepoints_open() {
+        let grid = BitPackedGrid::new_from_string("...\n...\n...\n".to_string());
+        let chokepoints = Chokepoints::new(&grid);
+        assert!(chokepoints.articulation_points.is_empty());
+        assert!(chokepoints.bridges.is_empty());
+    }
+
+    #[test]
+    fn test_chokepoints_dead_end() {
+        let grid = BitPackedGrid::new_from_string("...\n...\n@.@\n".to_string());
+        let chokepoints = Chokepoints::new(&grid);
+        assert_eq!(chokepoints.articulation_points, vec![(1, 1)]);
+        assert!(chokepoints.is_articulation_point((1, 1)));
+        assert_eq!(chokepoints.bridges.len(), 1);
+    }
+}
@@ -0,0 +1,129 @@
+//! # Expansion Diffs
+//! Comparison of the nodes two searches expand on the same instance, split
+//! into those only the first expands, those only the second expands and those
+//! both expand. Drawing the three sets over the map shows where a better
+//! heuristic or pruning rule saves its work, such as the regions behind an
+//! obstacle that A-Star explores and a jump point search skips.
+
+use std::collections::HashSet;
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+
+/// The colour of cells only the first search expanded
+const ONLY_A: (u8, u8, u8) = (230, 120, 20);
+/// The colour of cells only the second search expanded
+const ONLY_B: (u8, u8, u8) = (40, 110, 220);
+/// The colour of cells both searches expanded
+const BOTH: (u8, u8, u8) = (150, 150, 150);
+
+/// The difference between the expanded sets of two searches
+/// ## Fields
+/// * `only_a` - The cells expanded by the first search but not the second
+/// * `only_b` - The cells expanded by the second search but not the first
+/// * `both` - The cells expanded by both searches
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExpansionDiff {
+    pub only_a: HashSet<Cell>,
+    pub only_b: HashSet<Cell>,
+    pub both: HashSet<Cell>,
+}
+
+impl ExpansionDiff {
+    /// Splits two expanded sets into their differences and intersection
+    pub fn new(a: &HashSet<Cell>, b: &HashSet<Cell>) -> Self {
+        ExpansionDiff {
+            only_a: a.difference(b).copied().collect(),
+            only_b: b.difference(a).copied().collect(),
+            both: a.intersection(b).copied().collect(),
+        }
+    }
+
+    /// Runs two searches on the same instance and compares their expansions
+    /// ## Arguments
+    /// * `a` - The first search, inserting each node it expands into the set
+    /// * `b` - The second search, inserting each node it expands into the set
+    /// ## Example
+    /// ```ignore
+    /// let diff = ExpansionDiff::compare(
+    ///     |set| { astar_with_expanded_set(expander, start, goal, Some(set), zero); },
+    ///     |set| { astar_with_expanded_set(expander, start, goal, Some(set), manhattan); },
+    /// );
+    /// ```
+    pub fn compare(a: impl FnOnce(&mut HashSet<Cell>), b: impl FnOnce(&mut HashSet<Cell>)) -> Self {
+        let (mut expanded_a, mut expanded_b) = (HashSet::new(), HashSet::new());
+        a(&mut expanded_a);
+        b(&mut expanded_b);
+        ExpansionDiff::new(&expanded_a, &expanded_b)
+    }
+
+    /// The number of nodes each search expanded
+    pub fn expanded(&self) -> (usize, usize) {
+        (self.only_a.len() + self.both.len(), self.only_b.len() + self.both.len())
+    }
+
+    /// Prints the grid map with cells only the first search expanded as `a`,
+    /// only the second as `b` and both as `+`
+    pub fn print_cells(&self, grid: &BitPackedGrid) -> String {
+        let mut s = String::new();
+        for y in 0..grid.original_height {
+            for x in 0..grid.original_width {
+                let c = match (x, y) {
+                    n if self.both.contains(&n) => '+',
+                    n if self.only_a.contains(&n) => 'a',
+                    n if self.only_b.contains(&n) => 'b',
+                    n if grid.get_bit_value(n) => '.',
+                    _ => '@',
+                };
+                s.push(c);
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Plots the three sets in different colours over the grid map, with an
+    /// optional path drawn over them
+    #[cfg(feature = "viz")]
+    pub fn plot_cells(&self, grid: &BitPackedGrid, filename: &str, path: Option<Vec<Cell>>) {
+        use crate::domains::Annotation;
+        let highlight = |cells: &HashSet<Cell>, colour| Annotation::Highlight { cells: cells.iter().copied().collect(), colour };
+        let mut annotations = vec![highlight(&self.only_a, ONLY_A), highlight(&self.only_b, ONLY_B), highlight(&self.both, BOTH)];
+        if let Some(path) = path {
+            annotations.push(Annotation::Highlight { cells: path, colour: (220, 20, 20) });
+        }
+        grid.plot_annotated(filename, None, None, &annotations);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::distance::manhattan_distance;
+    use crate::search::astar::astar_with_expanded_set;
+
+    #[test]
+    fn test_expansion_diff() {
+        let grid = BitPackedGrid::new_from_string(".....\n.....\n.....\n".to_string());
+        let expander = |n: &Cell| grid.adjacent1(*n).filter(|(n, _)| grid.bounds_check(*n)).collect::<Vec<_>>();
+        let (start, goal) = ((0, 1), (4, 1));
+        let diff = ExpansionDiff::compare(
+            |set| { astar_with_expanded_set(expander, start, |n| *n == goal, Some(set), |_| 0); },
+            |set| { astar_with_expanded_set(expander, start, |n| *n == goal, Some(set), |n| manhattan_distance(*n, goal)); },
+        );
+        // The informed search expands a subset of the uninformed one
+        assert!(diff.only_b.is_empty());
+        assert!(diff.both.contains(&start) && diff.both.contains(&goal));
+        let (a, b) = diff.expanded();
+        assert!(b < a && a <= grid.free_cells().count());
+        let printed = diff.print_cells(&grid);
+        assert_eq!(printed.matches('+').count(), b);
+        assert_eq!(printed.matches('a').count(), a - b);
+
+        #[cfg(feature = "viz")]
+        {
+            let file = std::env::temp_dir().join("expansion_diff.png");
+            diff.plot_cells(&grid, file.to_str().unwrap(), Some(vec![start, goal]));
+            assert!(file.exists());
+        }
+    }
+}
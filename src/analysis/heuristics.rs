@@ -0,0 +1,170 @@
+//! # Heuristic Accuracy
+//! Compares heuristic estimates against true distances to help choose a
+//! heuristic for a map. For every sampled goal the true distance to each
+//! reachable cell is found with a backward Dijkstra search, and each heuristic
+//! is scored on sampled start cells by the ratio of its estimate to the true
+//! distance. A perfect heuristic has a ratio of 1, an admissible heuristic
+//! never exceeds 1 and lower ratios lead to more expansions.
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use rand::seq::SliceRandom;
+
+use crate::domains::{bitpackedgrid::BitPackedGrid, csrgraph::{Connectivity, CsrGraph}};
+use crate::util::determinism::with_rng;
+
+/// A heuristic between a cell and a goal
+pub type Heuristic<'a> = (&'a str, &'a dyn Fn((usize, usize), (usize, usize)) -> f64);
+
+/// The accuracy of a heuristic
+/// ## Fields
+/// * `name` - The name of the heuristic
+/// * `samples` - The number of (start, goal) pairs scored
+/// * `mean_ratio` - The mean ratio of the estimate to the true distance
+/// * `mean_absolute_error` - The mean absolute difference from the true distance
+/// * `overestimates` - The number of samples where the estimate exceeded the true distance
+/// * `histogram` - Counts of the ratio in equal bins over [0, 2), the last bin also counts larger ratios
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeuristicReport {
+    pub name: String,
+    pub samples: usize,
+    pub mean_ratio: f64,
+    pub mean_absolute_error: f64,
+    pub overestimates: usize,
+    pub histogram: Vec<usize>,
+}
+
+impl HeuristicReport {
+    /// Checks if the heuristic never overestimated a sampled distance
+    pub fn is_admissible(&self) -> bool {
+        self.overestimates == 0
+    }
+}
+
+/// The true distance from every cell to a goal, `None` for unreachable cells
+/// ## Returns
+/// The distances indexed by the ids of the graph
+pub fn backward_dijkstra(graph: &CsrGraph<(usize, usize), f64>, goal: (usize, usize)) -> Vec<Option<f64>> {
+    let mut distances = vec![None; graph.len()];
+    let Some(goal) = graph.id(&goal) else {
+        return distances;
+    };
+    // Non-negative floats order the same as their bit patterns
+    let mut open = BinaryHeap::from([Reverse((0f64.to_bits(), goal))]);
+    while let Some(Reverse((distance, node))) = open.pop() {
+        if distances[node].is_some() {
+            continue;
+        }
+        let distance = f64::from_bits(distance);
+        distances[node] = Some(distance);
+        for (child, cost) in graph.adjacent(node) {
+            if distances[child].is_none() {
+                open.push(Reverse(((distance + cost).to_bits(), child)));
+            }
+        }
+    }
+    distances
+}
+
+/// Scores heuristics against the true distances of a grid
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `connectivity` - The moves allowed, see `BitPackedGrid::to_graph`
+/// * `cost_model` - The cost of each move
+/// * `heuristics` - The named heuristics to score
+/// * `goals` - The goals to sample, see `sample_cells`
+/// * `samples` - The number of reachable start cells sampled per goal
+/// * `bins` - The number of bins of each histogram
+pub fn analyze_heuristics(
+    grid: &BitPackedGrid,
+    connectivity: Connectivity,
+    cost_model: impl Fn((usize, usize), (usize, usize)) -> f64,
+    heuristics: &[Heuristic],
+    goals: &[(usize, usize)],
+    samples: usize,
+    bins: usize,
+) -> Vec<HeuristicReport> {
+    assert!(bins > 0, "Histograms must have a bin");
+    let graph = grid.to_graph(connectivity, cost_model);
+    let mut reports = heuristics.iter()
+        .map(|(name, _)| HeuristicReport {
+            name: name.to_string(),
+            samples: 0,
+            mean_ratio: 0.0,
+            mean_absolute_error: 0.0,
+            overestimates: 0,
+            histogram: vec![0; bins],
+        })
+        .collect::<Vec<_>>();
+    for goal in goals {
+        let distances = backward_dijkstra(&graph, *goal);
+        let reachable = (0..graph.len())
+            .filter_map(|id| distances[id].filter(|d| *d > 0.0).map(|d| (graph.nodes[id], d)))
+            .collect::<Vec<_>>();
+        for (start, distance) in with_rng(|rng| reachable.choose_multiple(rng, samples).copied().collect::<Vec<_>>()) {
+            for ((_, heuristic), report) in heuristics.iter().zip(reports.iter_mut()) {
+                let estimate = heuristic(start, *goal);
+                let ratio = estimate / distance;
+                report.samples += 1;
+                report.mean_ratio += ratio;
+                report.mean_absolute_error += (estimate - distance).abs();
+                report.overestimates += (estimate > distance + 1e-9) as usize;
+                let bin = ((ratio / 2.0 * bins as f64).max(0.0) as usize).min(bins - 1);
+                report.histogram[bin] += 1;
+            }
+        }
+    }
+    for report in reports.iter_mut().filter(|r| r.samples > 0) {
+        report.mean_ratio /= report.samples as f64;
+        report.mean_absolute_error /= report.samples as f64;
+    }
+    reports
+}
+
+/// Samples distinct free cells of a grid, such as goals for `analyze_heuristics`
+pub fn sample_cells(grid: &BitPackedGrid, count: usize) -> Vec<(usize, usize)> {
+    let cells = (0..grid.original_width)
+        .flat_map(|x| (0..grid.original_height).map(move |y| (x, y)))
+        .filter(|n| grid.get_bit_value(*n))
+        .collect::<Vec<_>>();
+    with_rng(|rng| cells.choose_multiple(rng, count).copied().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::csrgraph::unit_cost;
+    use crate::heuristics::distance::manhattan_distance;
+
+    #[test]
+    fn test_analyze_heuristics() {
+        let grid = BitPackedGrid::new_from_string("....\n.@@.\n....\n".to_string());
+        let manhattan = |a, b| manhattan_distance(a, b) as f64;
+        let zero = |_, _| 0.0;
+        let double = |a, b| 2.0 * manhattan_distance(a, b) as f64;
+        let heuristics: [Heuristic; 3] = [("manhattan", &manhattan), ("zero", &zero), ("double", &double)];
+        let goals = sample_cells(&grid, 10);
+        assert_eq!(goals.len(), 10);
+        let reports = analyze_heuristics(&grid, Connectivity::Four, unit_cost, &heuristics, &goals, 100, 4);
+        assert!(reports.iter().all(|r| r.samples == 90));
+        assert!(reports[0].is_admissible() && reports[0].mean_ratio <= 1.0 && reports[0].mean_ratio > 0.5);
+        assert_eq!(reports[1].histogram, vec![90, 0, 0, 0]);
+        assert_eq!(reports[1].mean_ratio, 0.0);
+        assert!(!reports[2].is_admissible());
+        assert_eq!(reports[2].histogram[0] + reports[2].histogram[1], 0);
+    }
+
+    #[test]
+    fn test_connectivity_distances_admissible() {
+        use crate::domains::csrgraph::euclidean_cost;
+        let grid = BitPackedGrid::new_from_string("........\n.@@..@..\n....@...\n.@......\n...@@.@.\n........\n".to_string());
+        let goals = sample_cells(&grid, 8);
+        for connectivity in [Connectivity::Four, Connectivity::EightNoCornerCutting, Connectivity::Sixteen] {
+            let distance = |a, b| connectivity.distance(a, b);
+            let heuristics: [Heuristic; 1] = [("distance", &distance)];
+            let reports = analyze_heuristics(&grid, connectivity, euclidean_cost, &heuristics, &goals, 40, 4);
+            assert!(reports[0].is_admissible(), "{:?}", connectivity);
+            assert!(reports[0].mean_ratio > 0.8);
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! # Map Analysis
+//! Tools that characterise grid maps, such as those in benchmark sets or
+//! produced by generators. These include:
+//! * Statistics, free-cell ratio, connected components, corridor widths and decision density
+//! * Normalize, filling of unreachable pockets and trimming of empty borders
+//! * Chokepoints, articulation cells and bridges of the free space
+//! * Regions, decomposition of free space into rectangular rooms and corridors
+//! * Heuristics, accuracy of heuristic estimates against true distances
+//! * Traffic, per-cell usage and congestion of many paths
+//! * Expansions, diffs of the nodes expanded by two searches on the same instance
+
+#![allow(dead_code)]
+pub mod statistics;
+pub mod normalize;
+pub mod chokepoints;
+pub mod regions;
+pub mod traffic;
+pub mod expansions;
+#[cfg(feature = "sampling")]
+pub mod heuristics;
@@ -0,0 +1,87 @@
+//! # Map Normalisation
+//! A preprocessing pass that removes cells which can never be part of a query
+//! from a reference cell. Free cells unreachable from the reference are filled
+//! in as obstacles and the rows and columns bordering the map that contain no
+//! free cells are trimmed. The transform between the original and the
+//! normalised map is reported so queries can be translated.
+
+use super::statistics::component_labels;
+use crate::domains::bitpackedgrid::BitPackedGrid;
+
+/// The transform applied by normalisation
+/// ## Fields
+/// * `offset` - The position of the normalised map within the original map
+/// * `width` - The width of the normalised map
+/// * `height` - The height of the normalised map
+/// * `filled` - The number of unreachable free cells that were filled
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Normalization {
+    pub offset: (usize, usize),
+    pub width: usize,
+    pub height: usize,
+    pub filled: usize,
+}
+
+impl Normalization {
+    /// Converts a cell of the original map to the normalised map
+    pub fn to_normalized(&self, (x, y): (usize, usize)) -> Option<(usize, usize)> {
+        let (x, y) = (x.checked_sub(self.offset.0)?, y.checked_sub(self.offset.1)?);
+        (x < self.width && y < self.height).then_some((x, y))
+    }
+
+    /// Converts a cell of the normalised map to the original map
+    pub fn to_original(&self, (x, y): (usize, usize)) -> (usize, usize) {
+        (x + self.offset.0, y + self.offset.1)
+    }
+}
+
+/// Normalises a grid map relative to a reference cell
+/// ## Arguments
+/// * `grid` - The grid map to normalise
+/// * `reference` - A free cell, every cell not reachable from it is removed
+/// ## Returns
+/// The normalised grid map and the transform that produced it
+pub fn normalize(grid: &BitPackedGrid, reference: (usize, usize)) -> (BitPackedGrid, Normalization) {
+    assert!(grid.bounds_check(reference) && grid.get_bit_value(reference), "Reference cell must be free");
+    let (labels, _) = component_labels(grid);
+    let component = labels[reference.0][reference.1];
+    let reachable = |x: usize, y: usize| labels[x][y] == component;
+
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (usize::MAX, usize::MAX, 0, 0);
+    let mut filled = 0;
+    for (x, column) in labels.iter().enumerate() {
+        for (y, label) in column.iter().enumerate() {
+            if *label == component {
+                (min_x, min_y) = (min_x.min(x), min_y.min(y));
+                (max_x, max_y) = (max_x.max(x), max_y.max(y));
+            } else if label.is_some() {
+                filled += 1;
+            }
+        }
+    }
+
+    let (width, height) = (max_x - min_x + 1, max_y - min_y + 1);
+    let mut normalized = BitPackedGrid::new(width, height);
+    for x in 0..width {
+        for y in 0..height {
+            normalized.set_bit_value((x, y), reachable(x + min_x, y + min_y));
+        }
+    }
+    (normalized, Normalization { offset: (min_x, min_y), width, height, filled })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let grid = BitPackedGrid::new_from_string("@@@@@\n@..@.\n@.@@.\n@@@@@\n".to_string());
+        let (normalized, transform) = normalize(&grid, (1, 1));
+        assert_eq!(normalized.print_cells(None), "..\n.@\n");
+        assert_eq!(transform, Normalization { offset: (1, 1), width: 2, height: 2, filled: 2 });
+        assert_eq!(transform.to_normalized((2, 1)), Some((1, 0)));
+        assert_eq!(transform.to_normalized((4, 1)), None);
+        assert_eq!(transform.to_original((0, 1)), (1, 2));
+    }
+}
@@ -0,0 +1,174 @@
+//! # Region Decomposition
+//! Decomposition of the free space of a grid map into disjoint rectangles.
+//! Rectangles are grown greedily, first as far along the row as possible and
+//! then downwards while the whole span remains free. Narrow rectangles are
+//! classified as corridors and the remaining ones as rooms. Neighbouring
+//! regions are connected in an adjacency graph weighted by the length of
+//! their shared border, which can seed navmesh construction or summarise a map.
+
+use std::fmt::Write;
+
+use crate::domains::{adjacencylist::AdjacencyList, bitpackedgrid::BitPackedGrid};
+
+/// The classification of a region
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegionKind {
+    Room,
+    Corridor,
+}
+
+/// A rectangle of free cells
+/// ## Fields
+/// * `origin` - The top left cell of the rectangle
+/// * `width` - The width of the rectangle
+/// * `height` - The height of the rectangle
+/// * `kind` - Whether the rectangle is a room or a corridor
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub origin: (usize, usize),
+    pub width: usize,
+    pub height: usize,
+    pub kind: RegionKind,
+}
+
+impl Region {
+    /// The number of cells in the region
+    pub fn area(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// Checks if a cell is within the region
+    pub fn contains(&self, (x, y): (usize, usize)) -> bool {
+        x >= self.origin.0 && x < self.origin.0 + self.width
+            && y >= self.origin.1 && y < self.origin.1 + self.height
+    }
+}
+
+/// A decomposition of a grid map into rectangular regions
+/// ## Fields
+/// * `regions` - The regions in the order they were grown
+/// * `labels` - The region of each cell indexed as `labels[x][y]`, obstacles are `None`
+/// * `graph` - The adjacency graph of regions weighted by shared border length
+pub struct Regions {
+    pub regions: Vec<Region>,
+    pub labels: Vec<Vec<Option<usize>>>,
+    pub graph: AdjacencyList<usize, usize>,
+}
+
+impl Regions {
+    /// Decomposes a grid map into rectangular regions
+    /// ## Arguments
+    /// * `grid` - The grid map to decompose
+    /// * `corridor_width` - Regions no wider than this in either direction are corridors
+    pub fn new(grid: &BitPackedGrid, corridor_width: usize) -> Self {
+        let (width, height) = (grid.original_width, grid.original_height);
+        let mut labels: Vec<Vec<Option<usize>>> = vec![vec![None; height]; width];
+        let mut regions = Vec::new();
+        let open = |labels: &Vec<Vec<Option<usize>>>, x: usize, y: usize| {
+            grid.get_bit_value((x, y)) && labels[x][y].is_none()
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                if !open(&labels, x, y) {
+                    continue;
+                }
+                let w = (x..width).take_while(|&i| open(&labels, i, y)).count();
+                let h = (y..height).take_while(|&j| (x..x + w).all(|i| open(&labels, i, j))).count();
+                for column in labels.iter_mut().skip(x).take(w) {
+                    for label in column.iter_mut().skip(y).take(h) {
+                        *label = Some(regions.len());
+                    }
+                }
+                let kind = if w.min(h) <= corridor_width { RegionKind::Corridor } else { RegionKind::Room };
+                regions.push(Region { origin: (x, y), width: w, height: h, kind });
+            }
+        }
+
+        let mut graph = AdjacencyList::new_nodes((0..regions.len()).collect());
+        for x in 0..width {
+            for y in 0..height {
+                let Some(a) = labels[x][y] else { continue };
+                for (nx, ny) in [(x + 1, y), (x, y + 1)] {
+                    match labels.get(nx).and_then(|column| column.get(ny)).copied().flatten() {
+                        Some(b) if a != b => {
+                            add_border(&mut graph, a, b);
+                            add_border(&mut graph, b, a);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Regions { regions, labels, graph }
+    }
+
+    /// The region containing a cell
+    pub fn region_of(&self, (x, y): (usize, usize)) -> Option<usize> {
+        self.labels.get(x).and_then(|column| column.get(y)).copied().flatten()
+    }
+
+    /// The number of regions
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Checks if there are no regions
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// A human readable summary of the decomposition
+    pub fn summary(&self) -> String {
+        let rooms = self.regions.iter().filter(|r| r.kind == RegionKind::Room).count();
+        let mut summary = format!("{} regions, {} rooms and {} corridors\n", self.len(), rooms, self.len() - rooms);
+        for (id, region) in self.regions.iter().enumerate() {
+            let mut neighbours = self.graph.adjacent(id).map(|(n, _)| *n).collect::<Vec<_>>();
+            neighbours.sort();
+            writeln!(
+                summary,
+                "{} {:?} {}x{} at {:?} -> {:?}",
+                id, region.kind, region.width, region.height, region.origin, neighbours
+            ).unwrap();
+        }
+        summary
+    }
+}
+
+/// Increments the border length between two regions, adding the edge if absent
+fn add_border(graph: &mut AdjacencyList<usize, usize>, from: usize, to: usize) {
+    match graph[from].iter_mut().find(|(n, _)| *n == to) {
+        Some((_, length)) => *length += 1,
+        None => graph.add_edge(from, to, 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regions() {
+        let grid = BitPackedGrid::new_from_string("...@@\n.....\n...@@\n".to_string());
+        let regions = Regions::new(&grid, 1);
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions.regions[0], Region { origin: (0, 0), width: 3, height: 3, kind: RegionKind::Room });
+        assert_eq!(regions.regions[1], Region { origin: (3, 1), width: 2, height: 1, kind: RegionKind::Corridor });
+        assert_eq!(regions.region_of((4, 1)), Some(1));
+        assert_eq!(regions.region_of((3, 0)), None);
+        assert_eq!(regions.graph[0], vec![(1, 1)]);
+        assert_eq!(regions.graph.degree(1), 1);
+        let covered: usize = regions.regions.iter().map(Region::area).sum();
+        assert_eq!(covered, 11);
+    }
+
+    #[test]
+    fn test_regions_summary() {
+        let grid = BitPackedGrid::new_from_string("..@..\n.....\n..@..\n".to_string());
+        let regions = Regions::new(&grid, 1);
+        assert_eq!(regions.len(), 3);
+        assert_eq!(regions.regions[1].area(), 6);
+        assert_eq!(regions.graph[2], vec![(0, 1), (1, 1)]);
+        assert!(regions.summary().starts_with("3 regions, 2 rooms and 1 corridors\n"));
+    }
+}
@@ -0,0 +1,391 @@
+//! # Map Statistics
+//! Metrics describing the structure and complexity of a bitpacked grid map.
+//! Connectivity is 4-connected throughout.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::domains::bitpackedgrid::BitPackedGrid;
+
+/// Statistics of a grid map
+/// ## Fields
+/// * `free_cells` - The number of free cells
+/// * `free_ratio` - The fraction of cells that are free
+/// * `component_sizes` - The size of each connected component, largest first
+/// * `corridor_widths` - A histogram of corridor widths where index `w` counts the
+///   free cells whose narrowest horizontal or vertical run of free cells is `w` long
+/// * `decision_density` - The fraction of free cells with three or more free neighbours
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapStatistics {
+    pub free_cells: usize,
+    pub free_ratio: f32,
+    pub component_sizes: Vec<usize>,
+    pub corridor_widths: Vec<usize>,
+    pub decision_density: f32,
+}
+
+impl MapStatistics {
+    /// Computes the statistics of a grid map
+    /// ## Complexity
+    /// O(n) where n is the number of cells in the grid map
+    pub fn new(grid: &BitPackedGrid) -> Self {
+        let total = grid.original_width * grid.original_height;
+        let free = grid.free_cells().count();
+        let (labels, count) = component_labels(grid);
+        let mut component_sizes = vec![0; count];
+        for label in labels.iter().flatten().flatten() {
+            component_sizes[*label] += 1;
+        }
+        component_sizes.sort_by(|a, b| b.cmp(a));
+        let decisions = grid.free_cells()
+            .filter(|(x, y)| grid.adjacent((*x, *y), false).count() >= 3)
+            .count();
+        MapStatistics {
+            free_cells: free,
+            free_ratio: free as f32 / total.max(1) as f32,
+            component_sizes,
+            corridor_widths: corridor_widths(grid),
+            decision_density: decisions as f32 / free.max(1) as f32,
+        }
+    }
+
+    /// Computes the statistics of the region of a grid map marked by the free
+    /// cells of a mask, treating cells outside the region as obstacles
+    pub fn new_masked(grid: &BitPackedGrid, mask: &BitPackedGrid) -> Self {
+        let mut region = grid.clone();
+        region.intersection(mask);
+        let mut statistics = MapStatistics::new(&region);
+        let area = mask.free_cells().count();
+        statistics.free_ratio = statistics.free_cells as f32 / area.max(1) as f32;
+        statistics
+    }
+
+    /// The number of connected components
+    pub fn components(&self) -> usize {
+        self.component_sizes.len()
+    }
+}
+
+/// Labels every free cell with the id of its connected component
+/// ## Returns
+/// The label of each cell indexed as `labels[x][y]`, where obstacles are `None`,
+/// and the number of components
+pub fn component_labels(grid: &BitPackedGrid) -> (Vec<Vec<Option<usize>>>, usize) {
+    let mut labels = vec![vec![None; grid.original_height]; grid.original_width];
+    let mut count = 0;
+    for (x, y) in grid.free_cells() {
+        if labels[x][y].is_some() {
+            continue;
+        }
+        labels[x][y] = Some(count);
+        let mut queue = VecDeque::from([(x, y)]);
+        while let Some((x, y)) = queue.pop_front() {
+            for (nx, ny) in grid.adjacent((x, y), false) {
+                if grid.bounds_check((nx, ny)) && labels[nx][ny].is_none() {
+                    labels[nx][ny] = Some(count);
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+        count += 1;
+    }
+    (labels, count)
+}
+
+/// Breadth-first distances from a cell to every cell reachable by 4-connected
+/// moves, the source included at distance 0
+pub fn grid_distances(grid: &BitPackedGrid, source: (usize, usize)) -> HashMap<(usize, usize), usize> {
+    let mut distances = HashMap::from([(source, 0)]);
+    let mut queue = VecDeque::from([source]);
+    while let Some(node) = queue.pop_front() {
+        let distance = distances[&node] + 1;
+        for child in grid.adjacent(node, false).filter(|n| grid.bounds_check(*n)) {
+            distances.entry(child).or_insert_with(|| {
+                queue.push_back(child);
+                distance
+            });
+        }
+    }
+    distances
+}
+
+/// Connected component labels of a grid map that can be updated incrementally
+/// after edits, relabelling only the components touching the edited cells
+/// ## Fields
+/// * `labels` - The label of each cell indexed as `labels[x][y]`, obstacles are `None`
+/// * `sizes` - The number of cells with each label
+#[derive(Clone, Debug)]
+pub struct ComponentMap {
+    pub labels: Vec<Vec<Option<usize>>>,
+    pub sizes: HashMap<usize, usize>,
+    next_label: usize,
+}
+
+impl ComponentMap {
+    /// Labels the connected components of a grid map
+    pub fn new(grid: &BitPackedGrid) -> Self {
+        let (labels, count) = component_labels(grid);
+        let mut sizes = HashMap::new();
+        for label in labels.iter().flatten().flatten() {
+            *sizes.entry(*label).or_insert(0) += 1;
+        }
+        ComponentMap { labels, sizes, next_label: count }
+    }
+
+    /// The number of connected components
+    pub fn components(&self) -> usize {
+        self.sizes.len()
+    }
+
+    /// Checks if two cells are free and in the same component
+    pub fn connected(&self, (x1, y1): (usize, usize), (x2, y2): (usize, usize)) -> bool {
+        self.labels[x1][y1].is_some() && self.labels[x1][y1] == self.labels[x2][y2]
+    }
+
+    /// The label of a cell, `None` for obstacles and cells outside the grid
+    pub fn label(&self, (x, y): (usize, usize)) -> Option<usize> {
+        self.labels.get(x).and_then(|column| column.get(y)).copied().flatten()
+    }
+
+    /// Checks if two cells are free and in the same component like
+    /// `connected`, returning false for cells outside the grid
+    pub fn same_component(&self, a: (usize, usize), b: (usize, usize)) -> bool {
+        self.label(a).is_some() && self.label(a) == self.label(b)
+    }
+
+    /// Runs a planner between two cells only when they are in the same
+    /// component, so queries without a path fail without searching
+    pub fn plan_connected<R>(
+        &self,
+        start: (usize, usize),
+        goal: (usize, usize),
+        planner: impl FnOnce((usize, usize), (usize, usize)) -> Option<R>,
+    ) -> Option<R> {
+        self.same_component(start, goal).then(|| planner(start, goal)).flatten()
+    }
+
+    /// Relabels the components affected by edited cells. Components are given
+    /// fresh labels when they are merged or split, unaffected components keep theirs.
+    /// A freed cell whose free neighbours share a label joins their component,
+    /// and a blocked cell with at most one free neighbour cannot split its
+    /// component, so these edits are labelled without a flood fill. These
+    /// shortcuts only hold for a single edit, when several cells change at once
+    /// the components around every edit are flood filled again.
+    /// ## Arguments
+    /// * `grid` - The grid after the edits
+    /// * `changed` - The cells whose value changed
+    /// ## Complexity
+    /// O(a) where a is the number of cells in the affected components, O(1)
+    /// per edit that is labelled without a flood fill
+    pub fn update(&mut self, grid: &BitPackedGrid, changed: &[(usize, usize)]) {
+        let mut seeds = Vec::new();
+        let single = changed.len() == 1;
+        for &(x, y) in changed.iter().filter(|n| grid.bounds_check(**n)) {
+            if let Some(label) = self.labels[x][y].take() {
+                self.shrink(label);
+            }
+            let neighbours = grid.adjacent((x, y), false).filter(|n| grid.bounds_check(*n)).collect::<Vec<_>>();
+            let labels = neighbours.iter().map(|&(nx, ny)| self.labels[nx][ny]).collect::<Vec<_>>();
+            if single && grid.get_bit_value((x, y)) {
+                let joined = match labels.first() {
+                    None => Some(self.next_label),
+                    Some(Some(label)) if labels.iter().all(|l| *l == Some(*label)) => Some(*label),
+                    _ => None,
+                };
+                if let Some(label) = joined {
+                    self.next_label = self.next_label.max(label + 1);
+                    self.labels[x][y] = Some(label);
+                    *self.sizes.entry(label).or_insert(0) += 1;
+                    continue;
+                }
+            } else if single && neighbours.len() <= 1 {
+                continue;
+            }
+            seeds.push((x, y));
+            seeds.extend(neighbours);
+        }
+        let mut visited = vec![vec![false; grid.original_height]; grid.original_width];
+        for seed in seeds {
+            if !grid.get_bit_value(seed) || visited[seed.0][seed.1] {
+                continue;
+            }
+            let label = self.next_label;
+            self.next_label += 1;
+            visited[seed.0][seed.1] = true;
+            let mut queue = VecDeque::from([seed]);
+            let mut size = 0;
+            while let Some((x, y)) = queue.pop_front() {
+                if let Some(old) = self.labels[x][y].replace(label) {
+                    self.shrink(old);
+                }
+                size += 1;
+                for (nx, ny) in grid.adjacent((x, y), false) {
+                    if grid.bounds_check((nx, ny)) && !visited[nx][ny] {
+                        visited[nx][ny] = true;
+                        queue.push_back((nx, ny));
+                    }
+                }
+            }
+            self.sizes.insert(label, size);
+        }
+    }
+
+    /// Removes a cell from the count of a label
+    fn shrink(&mut self, label: usize) {
+        if let Some(size) = self.sizes.get_mut(&label) {
+            *size -= 1;
+            if *size == 0 {
+                self.sizes.remove(&label);
+            }
+        }
+    }
+}
+
+/// Histogram of corridor widths, the width of a free cell is the length of the
+/// shorter of the horizontal and vertical runs of free cells through it
+pub fn corridor_widths(grid: &BitPackedGrid) -> Vec<usize> {
+    let (width, height) = (grid.original_width, grid.original_height);
+    let mut horizontal = vec![vec![0; height]; width];
+    let mut vertical = vec![vec![0; height]; width];
+    for y in 0..height {
+        run_lengths((0..width).map(|x| (x, y)), grid, &mut horizontal);
+    }
+    for x in 0..width {
+        run_lengths((0..height).map(|y| (x, y)), grid, &mut vertical);
+    }
+    let mut histogram = Vec::new();
+    for (x, y) in grid.free_cells() {
+        let w = horizontal[x][y].min(vertical[x][y]);
+        if histogram.len() <= w {
+            histogram.resize(w + 1, 0);
+        }
+        histogram[w] += 1;
+    }
+    histogram
+}
+
+/// Writes the length of the run of free cells each cell of a line belongs to
+fn run_lengths(
+    line: impl Iterator<Item = (usize, usize)>,
+    grid: &BitPackedGrid,
+    lengths: &mut [Vec<usize>],
+) {
+    let mut run = Vec::new();
+    for cell in line.map(Some).chain(std::iter::once(None)) {
+        match cell {
+            Some(cell) if grid.get_bit_value(cell) => run.push(cell),
+            _ => {
+                let length = run.len();
+                for (x, y) in run.drain(..) {
+                    lengths[x][y] = length;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_component_labels() {
+        let grid = BitPackedGrid::new_from_string("..@.\n@@@.\n.@..\n".to_string());
+        let (labels, count) = component_labels(&grid);
+        assert_eq!(count, 3);
+        assert_eq!(labels[0][0], labels[1][0]);
+        assert_eq!(labels[3][0], labels[2][2]);
+        assert_ne!(labels[0][0], labels[0][2]);
+        assert_eq!(labels[2][0], None);
+    }
+
+    #[test]
+    fn test_component_map_update() {
+        let mut grid = BitPackedGrid::new_from_string("..@..\n..@..\n.....\n".to_string());
+        let mut components = ComponentMap::new(&grid);
+        assert_eq!(components.components(), 1);
+        grid.set_bit_value((2, 2), false);
+        components.update(&grid, &[(2, 2)]);
+        assert_eq!(components.components(), 2);
+        assert!(!components.connected((0, 0), (4, 0)));
+        assert_eq!(components.labels[2][2], None);
+        let mut sizes = components.sizes.values().copied().collect::<Vec<_>>();
+        sizes.sort();
+        assert_eq!(sizes, vec![6, 6]);
+        grid.set_bit_value((2, 0), true);
+        components.update(&grid, &[(2, 0)]);
+        assert_eq!(components.components(), 1);
+        assert!(components.connected((0, 2), (4, 2)));
+        assert_eq!(components.sizes.values().sum::<usize>(), 13);
+        assert!(components.same_component((0, 0), (4, 2)) && !components.same_component((0, 0), (9, 9)));
+    }
+
+    #[test]
+    fn test_component_map_local_updates() {
+        let mut grid = BitPackedGrid::new_from_string("...@....\n...@....\n...@@@@.\n".to_string());
+        let mut components = ComponentMap::new(&grid);
+        let edits = [((7, 2), false), ((4, 0), false), ((7, 2), true), ((3, 0), true), ((3, 1), true), ((2, 2), false)];
+        for (cell, value) in edits {
+            grid.set_bit_value(cell, value);
+            components.update(&grid, &[cell]);
+            let fresh = ComponentMap::new(&grid);
+            for a in grid.free_cells() {
+                for b in grid.free_cells() {
+                    assert_eq!(components.same_component(a, b), fresh.same_component(a, b), "{:?} {:?}", a, b);
+                }
+            }
+            let mut sizes = (components.sizes.values().copied().collect::<Vec<_>>(), fresh.sizes.values().copied().collect::<Vec<_>>());
+            sizes.0.sort();
+            sizes.1.sort();
+            assert_eq!(sizes.0, sizes.1);
+        }
+        assert_eq!(components.components(), 1);
+        assert_eq!(components.plan_connected((0, 0), (7, 0), |s, g| Some((s, g))), Some(((0, 0), (7, 0))));
+        grid.fill_area((3, 0), 1, 3, false);
+        components.update(&grid, &[(3, 0), (3, 1), (3, 2)]);
+        assert_eq!(components.plan_connected((0, 0), (7, 0), |_, _| -> Option<()> { panic!("not searched") }), None);
+    }
+
+    #[test]
+    fn test_component_map_batch_update() {
+        let mut grid = BitPackedGrid::new_from_string("....\n".to_string());
+        let mut components = ComponentMap::new(&grid);
+        grid.set_bit_value((1, 0), false);
+        grid.set_bit_value((2, 0), false);
+        components.update(&grid, &[(1, 0), (2, 0)]);
+        assert!(!components.same_component((0, 0), (3, 0)));
+        assert_eq!(components.components(), 2);
+        grid.set_bit_value((1, 0), true);
+        grid.set_bit_value((2, 0), true);
+        components.update(&grid, &[(1, 0), (2, 0)]);
+        assert!(components.same_component((0, 0), (3, 0)));
+        assert_eq!((components.components(), components.sizes.values().sum::<usize>()), (1, 4));
+    }
+
+    #[test]
+    fn test_masked_statistics() {
+        let grid = BitPackedGrid::new_from_string("..@..\n..@..\n.....\n".to_string());
+        let mask = BitPackedGrid::new_from_string("@@@@@\n.....\n@@@@@\n".to_string());
+        let statistics = MapStatistics::new_masked(&grid, &mask);
+        assert_eq!(statistics.free_cells, 4);
+        assert_eq!(statistics.free_ratio, 0.8);
+        assert_eq!(statistics.component_sizes, vec![2, 2]);
+    }
+
+    #[test]
+    fn test_corridor_widths() {
+        let grid = BitPackedGrid::new_from_string("...\n.@.\n...\n".to_string());
+        assert_eq!(corridor_widths(&grid), vec![0, 4, 0, 4]);
+    }
+
+    #[test]
+    fn test_map_statistics() {
+        let grid = BitPackedGrid::new_from_string(".....\n@@.@@\n@@.@@\n".to_string());
+        let statistics = MapStatistics::new(&grid);
+        assert_eq!(statistics.free_cells, 7);
+        assert_eq!(statistics.free_ratio, 7.0 / 15.0);
+        assert_eq!(statistics.component_sizes, vec![7]);
+        assert_eq!(statistics.components(), 1);
+        assert_eq!(statistics.corridor_widths, vec![0, 6, 0, 1]);
+        assert_eq!(statistics.decision_density, 1.0 / 7.0);
+    }
+}
@@ -0,0 +1,117 @@
+//! # Traffic Flow
+//! Aggregation of many paths over a grid map into per-cell usage, such as the
+//! paths of a benchmark scenario or the plans of a warehouse fleet. The
+//! congestion of a cell is its usage divided by the number of free neighbours,
+//! so busy corridors and doorways score higher than busy open areas. Usage is
+//! drawn as a heatmap over the map to find the hotspots of a layout.
+
+use crate::domains::{bitpackedgrid::BitPackedGrid, grid::Grid};
+
+/// The usage of every cell of a grid map by a set of paths
+/// ## Fields
+/// * `usage` - The number of times each cell was visited, including waits
+/// * `openness` - The number of free 4-connected neighbours of each cell
+/// * `paths` - The number of paths added
+#[derive(Clone, Debug, PartialEq)]
+pub struct Traffic {
+    pub usage: Grid<usize>,
+    pub openness: Grid<usize>,
+    pub paths: usize,
+}
+
+impl Traffic {
+    /// Creates an empty record of traffic over a grid map
+    pub fn new(grid: &BitPackedGrid) -> Self {
+        let openness = Grid::from_fn(grid.original_width, grid.original_height, |n| {
+            grid.adjacent(n, false).filter(|m| grid.bounds_check(*m)).count()
+        });
+        Traffic { usage: Grid::new_aligned(grid, 0), openness, paths: 0 }
+    }
+
+    /// Adds the cells of a path to the usage
+    pub fn add_path(&mut self, path: &[(usize, usize)]) {
+        for node in path {
+            self.usage[*node] += 1;
+        }
+        self.paths += 1;
+    }
+
+    /// Adds the cells of many paths to the usage
+    pub fn add_paths(&mut self, paths: &[Vec<(usize, usize)>]) {
+        for path in paths {
+            self.add_path(path);
+        }
+    }
+
+    /// The congestion of a cell, its usage per free neighbour
+    pub fn congestion(&self, node: (usize, usize)) -> f64 {
+        self.usage[node] as f64 / self.openness[node].max(1) as f64
+    }
+
+    /// The congestion score of the whole map, the mean congestion of the cell
+    /// of each visit, so maps whose traffic funnels through a few narrow cells
+    /// score higher than maps spreading the same traffic out
+    pub fn congestion_score(&self) -> f64 {
+        let visits = self.usage.cells.iter().sum::<usize>();
+        if visits == 0 {
+            return 0.0;
+        }
+        let weighted = self.usage.iter().map(|(n, u)| *u as f64 * self.congestion(n)).sum::<f64>();
+        weighted / visits as f64
+    }
+
+    /// The most congested cells, most congested first
+    pub fn hotspots(&self, count: usize) -> Vec<((usize, usize), f64)> {
+        let mut cells = self.usage.iter()
+            .filter(|(_, u)| **u > 0)
+            .map(|(n, _)| (n, self.congestion(n)))
+            .collect::<Vec<_>>();
+        cells.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        cells.truncate(count);
+        cells
+    }
+
+    /// The usage of every cell normalised to [0, 1] for plotting as a heatmap
+    pub fn heatmap(&self) -> Vec<((usize, usize), f64)> {
+        self.usage.heatmap(|u| *u as f64)
+    }
+
+    /// The congestion of every cell normalised to [0, 1] for plotting as a heatmap
+    pub fn congestion_heatmap(&self) -> Vec<((usize, usize), f64)> {
+        Grid::from_fn(self.usage.width, self.usage.height, |n| self.congestion(n)).heatmap(|c| *c)
+    }
+
+    /// Plots the usage of every cell as a heatmap over the grid map
+    #[cfg(feature = "viz")]
+    pub fn plot_cells(&self, grid: &BitPackedGrid, filename: &str) {
+        grid.plot_cells(filename, None, Some(self.heatmap()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traffic() {
+        let grid = BitPackedGrid::new_from_string("...@...\n.......\n...@...\n".to_string());
+        let mut traffic = Traffic::new(&grid);
+        assert_eq!(traffic.congestion_score(), 0.0);
+        let through = (0..7).map(|x| (x, 1)).collect::<Vec<_>>();
+        traffic.add_paths(&[through.clone(), through]);
+        traffic.add_path(&[(0, 0), (1, 0), (1, 0)]);
+        assert_eq!(traffic.paths, 3);
+        assert_eq!(traffic.usage[(1, 0)], 2);
+        assert_eq!(traffic.openness[(3, 1)], 2);
+        assert_eq!(traffic.congestion((3, 1)), 1.0);
+        assert_eq!(traffic.hotspots(1), vec![((3, 1), 1.0)]);
+        assert_eq!(traffic.heatmap().iter().find(|(n, _)| *n == (3, 1)).unwrap().1, 1.0);
+        assert_eq!(traffic.congestion_heatmap().iter().find(|(n, _)| *n == (3, 1)).unwrap().1, 1.0);
+
+        let mut spread = Traffic::new(&grid);
+        spread.add_paths(&[(0..7).map(|x| (x, 0)).collect(), (0..7).map(|x| (x, 2)).collect()]);
+        let mut funnel = Traffic::new(&grid);
+        funnel.add_paths(&[(0..7).map(|x| (x, 1)).collect(), (0..7).map(|x| (x, 1)).collect()]);
+        assert!(funnel.congestion_score() > spread.congestion_score());
+    }
+}
@@ -1,19 +1,26 @@
-use std::collections::HashMap;
 use std::collections::hash_map::ValuesMut;
 use std::hash::Hash;
 use std::ops::{Index, IndexMut};
 
+use crate::util::determinism::Map;
+
 
 #[derive(Debug, Clone)]
-pub struct AdjacencyList<N: Eq + Hash, W>(HashMap<N, Vec<(N, W)>>);
+pub struct AdjacencyList<N: Eq + Hash, W>(Map<N, Vec<(N, W)>>);
+
+impl<N: Eq + Hash, W> Default for AdjacencyList<N, W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl<N: Eq + Hash, W> AdjacencyList<N, W> {
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self(Map::default())
     }
 
     pub fn new_nodes(nodes: Vec<N>) -> Self {
-        let mut map = HashMap::new();
+        let mut map = Map::default();
         for node in nodes {
             map.insert(node, Vec::new());
         }
@@ -24,6 +31,10 @@ impl<N: Eq + Hash, W> AdjacencyList<N, W> {
         self.0.insert(node, Vec::new());
     }
 
+    pub fn contains_node(&self, node: &N) -> bool {
+        self.0.contains_key(node)
+    }
+
     pub fn add_edge(&mut self, from: N, to: N, weight: W) {
         self[from].push((to, weight));
     }
@@ -47,6 +58,10 @@ impl<N: Eq + Hash, W> AdjacencyList<N, W> {
         self.0.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn degree(&self, node: N) -> usize {
         self[node].len()
     }
@@ -55,7 +70,7 @@ impl<N: Eq + Hash, W> AdjacencyList<N, W> {
         self.0.iter()
     }
 
-    pub fn iter_mut(&mut self) -> ValuesMut<N, Vec<(N, W)>> {
+    pub fn iter_mut(&mut self) -> ValuesMut<'_, N, Vec<(N, W)>> {
         self.0.values_mut()
     }
 }
@@ -10,12 +10,16 @@
 //! This implementation is based upon Warthog's implementation of bitpacked grid
 //! maps, which can be found: https://bitbucket.org/dharabor/pathfinding/
 
-use std::vec;
+use std::{io, vec};
 
-use super::{create_map_from_string, print_cells, neighbors, plot_cells};
+use super::{create_map_from_string, print_cells, neighbors};
+#[cfg(feature = "viz")]
+use super::{plot_cells, plot_cells_within, Annotation, PIXEL_BUDGET};
+use super::csrgraph::{Connectivity, CsrGraph};
+use crate::util::base64;
 
 /// A grid of bits packed into usize-bit words
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BitPackedGrid {
     pub original_height: usize,
     pub original_width: usize,
@@ -24,6 +28,7 @@ pub struct BitPackedGrid {
     map_width_in_words: usize,
     map_size: usize,
     map_cells: Box<[usize]>,
+    version: usize,
 }
 
 impl BitPackedGrid {
@@ -31,6 +36,7 @@ impl BitPackedGrid {
     const BITS_PER_WORD: usize = usize::BITS as usize;
     const LOG2_BITS_PER_WORD: usize = usize::BITS.trailing_zeros() as usize;
     const INDEX_MASK: usize = BitPackedGrid::BITS_PER_WORD - 1;
+    const HASH_SEED: u64 = 0xcbf29ce484222325;
 
     /// Create a new BitPackedGrid
     pub fn new(width: usize, height: usize) -> BitPackedGrid {
@@ -47,6 +53,7 @@ impl BitPackedGrid {
             map_width_in_words,
             map_size,
             map_cells,
+            version: 0,
         }
     }
 
@@ -74,10 +81,11 @@ impl BitPackedGrid {
         let map_id = self.get_map_id((x, y));
         let word_index = map_id >> BitPackedGrid::LOG2_BITS_PER_WORD;
         let mask = 1 << (map_id & BitPackedGrid::INDEX_MASK);
-        if value {
-            self.map_cells[word_index as usize] |= mask;
-        } else {
-            self.map_cells[word_index as usize] &= !mask;
+        let word = self.map_cells[word_index];
+        let changed = if value { word | mask } else { word & !mask };
+        if changed != word {
+            self.map_cells[word_index] = changed;
+            self.version += 1;
         }
     }
 
@@ -89,6 +97,78 @@ impl BitPackedGrid {
         (self.map_cells[word_index as usize] & mask) != 0
     }
 
+    /// The number of edits made to the grid, which changes whenever a cell
+    /// changes so preprocessing and query caches can detect stale results
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
+    /// A hash of the size and cells of the grid, rolled word by word so that
+    /// it is as fast as copying the map. Unlike the version, grids with the
+    /// same cells hash the same, so it can key artifacts saved across runs.
+    pub fn content_hash(&self) -> u64 {
+        let size = [self.original_width as u64, self.original_height as u64];
+        size.into_iter()
+            .chain(self.map_cells.iter().map(|w| *w as u64))
+            .fold(BitPackedGrid::HASH_SEED, BitPackedGrid::roll_hash)
+    }
+
+    /// A hash of the cells of a rectangle clipped to the grid, such as the
+    /// window of a map around an agent, packing the cells into words in row
+    /// order before rolling them like `content_hash`
+    pub fn window_hash(&self, (x, y): (usize, usize), width: usize, height: usize) -> u64 {
+        let mut hash = [x, y, width, height].into_iter().fold(BitPackedGrid::HASH_SEED, |h, v| BitPackedGrid::roll_hash(h, v as u64));
+        let (mut word, mut bits) = (0u64, 0);
+        for n in self.cells_in_rect((x, y), width, height) {
+            word = word << 1 | self.get_bit_value(n) as u64;
+            bits += 1;
+            if bits == u64::BITS {
+                hash = BitPackedGrid::roll_hash(hash, word);
+                (word, bits) = (0, 0);
+            }
+        }
+        BitPackedGrid::roll_hash(BitPackedGrid::roll_hash(hash, word), bits as u64)
+    }
+
+    /// Rolls a word into a hash
+    fn roll_hash(hash: u64, word: u64) -> u64 {
+        (hash ^ word).wrapping_mul(0x100000001b3).rotate_left(31)
+    }
+
+    /// The bits of the map from a map id onwards as a single word
+    fn word_at(&self, map_id: usize) -> usize {
+        let word_index = map_id >> BitPackedGrid::LOG2_BITS_PER_WORD;
+        let shift = map_id & BitPackedGrid::INDEX_MASK;
+        let low = self.map_cells.get(word_index).copied().unwrap_or(0) >> shift;
+        if shift == 0 {
+            return low;
+        }
+        low | self.map_cells.get(word_index + 1).copied().unwrap_or(0) << (BitPackedGrid::BITS_PER_WORD - shift)
+    }
+
+    /// The cells of a row eastwards of a cell as a word, where bit `i` is set
+    /// if cell `(x + i, y)` is free. Rows are followed by blocked padding, so
+    /// a scan stops at the end of a row before reading the next one.
+    pub(crate) fn row_word_east(&self, (x, y): (usize, usize)) -> usize {
+        self.word_at(self.get_map_id((x, y)))
+    }
+
+    /// The cells of a row westwards of a cell as a word, where bit `i` is set
+    /// if cell `(x - i, y)` is free
+    pub(crate) fn row_word_west(&self, (x, y): (usize, usize)) -> usize {
+        self.word_at(self.get_map_id((x, y)) - (BitPackedGrid::BITS_PER_WORD - 1)).reverse_bits()
+    }
+
+    /// The grid with its rows and columns swapped, so columns can be scanned
+    /// a word at a time as rows
+    pub fn transposed(&self) -> BitPackedGrid {
+        let mut transposed = BitPackedGrid::new(self.original_height, self.original_width);
+        for (x, y) in self.free_cells() {
+            transposed.set_bit_value((y, x), true);
+        }
+        transposed
+    }
+
     /// Check if a given x, y coordinate is within the bounds of the map
     pub fn bounds_check(&self, (x, y): (usize, usize)) -> bool {
         x < self.original_width && y < self.original_height
@@ -127,11 +207,417 @@ impl BitPackedGrid {
         self.adjacent((x, y), false).map(|n| (n,1))
     }
 
+    /// Iterates over every cell of the grid in row order
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.cells_in_rect((0, 0), self.original_width, self.original_height)
+    }
+
+    /// Iterates over the free cells of the grid in row order, a word at a
+    /// time so that blocked stretches of the map are skipped
+    pub fn free_cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.map_cells.iter().enumerate().filter(|(_, word)| **word != 0).flat_map(move |(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                let map_id = (i << BitPackedGrid::LOG2_BITS_PER_WORD) + bit;
+                let x = (map_id % self.map_width).wrapping_sub(BitPackedGrid::PADDING);
+                let y = (map_id / self.map_width).wrapping_sub(BitPackedGrid::PADDING);
+                Some((x, y))
+            })
+        }).filter(move |n| self.bounds_check(*n))
+    }
+
+    /// The number of free cells in the grid, counted a word at a time
+    pub fn count_free(&self) -> usize {
+        self.map_cells.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Iterates over the cells of a rectangle in row order, clipped to the grid
+    /// ## Arguments
+    /// * `(x, y)` - The top left cell of the rectangle
+    /// * `width` - The width of the rectangle
+    /// * `height` - The height of the rectangle
+    pub fn cells_in_rect(&self, (x, y): (usize, usize), width: usize, height: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let xs = x.min(self.original_width)..x.saturating_add(width).min(self.original_width);
+        let ys = y.min(self.original_height)..y.saturating_add(height).min(self.original_height);
+        ys.flat_map(move |y| xs.clone().map(move |x| (x, y)))
+    }
+
+    /// Iterates over the free neighbours of a cell within the grid
+    /// ## Arguments
+    /// * `(x, y)` - The cell
+    /// * `connectivity` - The moves allowed between neighbouring cells
+    pub fn neighbours(&self, x: usize, y: usize, connectivity: Connectivity) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let free = move |n: (usize, usize)| self.bounds_check(n) && self.get_bit_value(n);
+        connectivity.offsets().iter()
+            .map(move |&(dx, dy)| (x.wrapping_add_signed(dx), y.wrapping_add_signed(dy)))
+            .filter(move |&n| connectivity.allows((x, y), n, free))
+    }
+
+    /// Finds the nearest free cell to a cell by Euclidean distance, such as to
+    /// recover an agent whose cell became blocked
+    pub fn nearest_free_cell(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        self.k_nearest_free(x, y, 1).pop()
+    }
+
+    /// Finds the `k` nearest free cells to a cell by searching square rings
+    /// spiralling outwards, such as to place spawns around a point
+    /// ## Returns
+    /// At most `k` free cells, nearest first with ties broken by the smallest coordinates
+    pub fn k_nearest_free(&self, x: usize, y: usize, k: usize) -> Vec<(usize, usize)> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let (w, h) = (self.original_width, self.original_height);
+        let rings = x.max(x.abs_diff(w.saturating_sub(1))).max(y).max(y.abs_diff(h.saturating_sub(1)));
+        let mut found = Vec::new();
+        for r in 0..=rings as isize {
+            for dx in -r..=r {
+                let dys = if dx.abs() == r { (-r..=r).collect::<Vec<_>>() } else { vec![-r, r] };
+                for dy in dys {
+                    let cell = (x.checked_add_signed(dx), y.checked_add_signed(dy));
+                    if let (Some(nx), Some(ny)) = cell {
+                        if self.bounds_check((nx, ny)) && self.get_bit_value((nx, ny)) {
+                            found.push(((dx * dx + dy * dy) as usize, (nx, ny)));
+                        }
+                    }
+                }
+            }
+            // Unsearched cells are at least r + 1 away, so closer cells are final
+            let bound = ((r + 1) * (r + 1)) as usize;
+            if found.iter().filter(|(d, _)| *d < bound).count() >= k {
+                break;
+            }
+        }
+        found.sort();
+        found.into_iter().take(k).map(|(_, n)| n).collect()
+    }
+
+    /// Combines the cells of another grid of the same size word by word
+    fn combine(&mut self, other: &BitPackedGrid, op: impl Fn(usize, usize) -> usize) {
+        assert!(self.original_width == other.original_width && self.original_height == other.original_height,
+            "Grids must be the same size");
+        let mut changed = false;
+        for (word, other) in self.map_cells.iter_mut().zip(other.map_cells.iter()) {
+            let combined = op(*word, *other);
+            changed |= combined != *word;
+            *word = combined;
+        }
+        if changed {
+            self.version += 1;
+        }
+    }
+
+    /// Overwrites the cells with those of another grid of the same size
+    /// without reallocating
+    pub fn copy_from(&mut self, other: &BitPackedGrid) {
+        self.combine(other, |_, b| b);
+    }
+
+    /// Sets every cell that is free in either grid to free (OR)
+    pub fn union(&mut self, other: &BitPackedGrid) {
+        self.combine(other, |a, b| a | b);
+    }
+
+    /// Sets every cell that is not free in both grids to an obstacle (AND),
+    /// this overlays the obstacles of both grids
+    pub fn intersection(&mut self, other: &BitPackedGrid) {
+        self.combine(other, |a, b| a & b);
+    }
+
+    /// Sets every cell that is free in the other grid to an obstacle (AND NOT)
+    pub fn difference(&mut self, other: &BitPackedGrid) {
+        self.combine(other, |a, b| a & !b);
+    }
+
+    /// Sets every cell that is free in exactly one of the grids to free (XOR),
+    /// such as to find where a sampled realization differs from the ground truth
+    pub fn symmetric_difference(&mut self, other: &BitPackedGrid) {
+        self.combine(other, |a, b| a ^ b);
+    }
+
+    /// Applies an operation to the words of a rectangle clipped to the grid,
+    /// with a mask of the bits of each word within the rectangle
+    fn combine_rect(&mut self, (x, y): (usize, usize), width: usize, height: usize, op: impl Fn(usize, usize) -> usize) {
+        let (x_min, x_max) = (x.min(self.original_width), x.saturating_add(width).min(self.original_width));
+        let (y_min, y_max) = (y.min(self.original_height), y.saturating_add(height).min(self.original_height));
+        let mut changed = false;
+        for y in y_min..y_max {
+            let (mut map_id, end) = (self.get_map_id((x_min, y)), self.get_map_id((x_max, y)));
+            while map_id < end {
+                let shift = map_id & BitPackedGrid::INDEX_MASK;
+                let bits = (BitPackedGrid::BITS_PER_WORD - shift).min(end - map_id);
+                let mask = if bits == BitPackedGrid::BITS_PER_WORD { usize::MAX } else { ((1 << bits) - 1) << shift };
+                let word = &mut self.map_cells[map_id >> BitPackedGrid::LOG2_BITS_PER_WORD];
+                let combined = op(*word, mask);
+                changed |= combined != *word;
+                *word = combined;
+                map_id += bits;
+            }
+        }
+        if changed {
+            self.version += 1;
+        }
+    }
+
+    /// Sets every cell of a rectangle clipped to the grid to free or an
+    /// obstacle, a word at a time
+    pub fn fill_area(&mut self, (x, y): (usize, usize), width: usize, height: usize, value: bool) {
+        self.combine_rect((x, y), width, height, |word, mask| if value { word | mask } else { word & !mask });
+    }
+
+    /// Swaps the free cells and obstacles of the grid
+    pub fn invert(&mut self) {
+        self.combine_rect((0, 0), self.original_width, self.original_height, |word, mask| word ^ mask);
+    }
+
+    /// Reallocates the grid with a new size, moving each old cell to its new
+    /// position or dropping it. Cells with no old cell are obstacles.
+    pub(crate) fn remap(&mut self, width: usize, height: usize, moved: impl Fn((usize, usize)) -> Option<(usize, usize)>) {
+        let mut grid = BitPackedGrid::new(width, height);
+        for x in 0..self.original_width {
+            for y in 0..self.original_height {
+                if let Some(n) = moved((x, y)).filter(|n| grid.bounds_check(*n)) {
+                    grid.set_bit_value(n, self.get_bit_value((x, y)));
+                }
+            }
+        }
+        grid.version = self.version + 1;
+        *self = grid;
+    }
+
+    /// Resizes the grid, keeping the cells within the new size. Added cells are obstacles.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.remap(width, height, Some);
+    }
+
+    /// Inserts rows of obstacles before row `at`, shifting the later rows down
+    pub fn insert_rows(&mut self, at: usize, count: usize) {
+        self.remap(self.original_width, self.original_height + count, |(x, y)| {
+            Some((x, if y >= at { y + count } else { y }))
+        });
+    }
+
+    /// Inserts columns of obstacles before column `at`, shifting the later columns right
+    pub fn insert_cols(&mut self, at: usize, count: usize) {
+        self.remap(self.original_width + count, self.original_height, |(x, y)| {
+            Some((if x >= at { x + count } else { x }, y))
+        });
+    }
+
+    /// Grows the grid so that a cell is within its bounds
+    /// ## Returns
+    /// Whether the grid was resized
+    pub fn extend_to_fit(&mut self, (x, y): (usize, usize)) -> bool {
+        if self.bounds_check((x, y)) {
+            return false;
+        }
+        self.resize(self.original_width.max(x + 1), self.original_height.max(y + 1));
+        true
+    }
+
+    /// Checks if a cell is on the outermost ring of the grid
+    pub fn is_border(&self, (x, y): (usize, usize)) -> bool {
+        self.bounds_check((x, y))
+            && (x == 0 || y == 0 || x + 1 == self.original_width || y + 1 == self.original_height)
+    }
+
+    /// Iterates over the cells of the outermost ring of the grid in row order
+    pub fn border_cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let (width, height) = (self.original_width, self.original_height);
+        (0..height).flat_map(move |y| {
+            let columns: Box<dyn Iterator<Item = usize>> = match y == 0 || y + 1 == height {
+                true => Box::new(0..width),
+                false => Box::new([0, width - 1].into_iter().take(width.min(2))),
+            };
+            columns.map(move |x| (x, y))
+        })
+    }
+
+    /// Iterates over the free cells next to an obstacle or the edge of the
+    /// grid in row order, the boundary of the free space
+    /// ## Arguments
+    /// * `diagonal` - Whether diagonal neighbours are considered
+    pub fn boundary_cells(&self, diagonal: bool) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.free_cells().filter(move |n| {
+            neighbors(n.0, n.1, diagonal).any(|m| !self.bounds_check(m) || !self.get_bit_value(m))
+        })
+    }
+
+    /// Checks if every cell of the outermost ring is an obstacle, in which case
+    /// the neighbours of every free cell are within the grid and expansions
+    /// need no bound checks
+    pub fn has_solid_border(&self) -> bool {
+        self.border_cells().all(|n| !self.get_bit_value(n))
+    }
+
+    /// A copy of the grid surrounded by a solid border of obstacles, a common
+    /// preprocessing step removing bound checks from expansions. Cells of the
+    /// bordered grid are offset by `width` from those of this grid.
+    pub fn with_border(&self, width: usize) -> BitPackedGrid {
+        let mut grid = self.clone();
+        grid.remap(self.original_width + 2 * width, self.original_height + 2 * width, |(x, y)| Some((x + width, y + width)));
+        grid
+    }
+
+    /// A copy of the grid with a border of `width` cells removed from each
+    /// side, undoing `with_border`
+    pub fn without_border(&self, width: usize) -> BitPackedGrid {
+        let mut grid = self.clone();
+        let (new_width, new_height) = (self.original_width.saturating_sub(2 * width), self.original_height.saturating_sub(2 * width));
+        grid.remap(new_width, new_height, |(x, y)| Some((x.checked_sub(width)?, y.checked_sub(width)?)));
+        grid
+    }
+
+    /// Maps a path on a grid with a border of `width` back to the cells of
+    /// the grid without it
+    /// ## Returns
+    /// The path, `None` if it passes through the border
+    pub fn strip_border_from_path(path: &[(usize, usize)], width: usize) -> Option<Vec<(usize, usize)>> {
+        path.iter().map(|(x, y)| Some((x.checked_sub(width)?, y.checked_sub(width)?))).collect()
+    }
+
+    /// Encodes the grid as a compact string `WIDTHxHEIGHT:DATA`, where the data
+    /// is the URL-safe base64 of one bit per cell in row order, most significant
+    /// bit first and 1 for free cells. Unlike the packed words the encoding
+    /// does not depend on the word size or padding.
+    pub fn to_compact_string(&self) -> String {
+        format!("{}x{}:{}", self.original_width, self.original_height, base64::encode(&self.to_bits()))
+    }
+
+    /// One bit per cell in row order, most significant bit first and 1 for free cells
+    fn to_bits(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; (self.original_width * self.original_height).div_ceil(8)];
+        for (i, cell) in self.cells().enumerate() {
+            if self.get_bit_value(cell) {
+                bytes[i / 8] |= 0x80 >> (i % 8);
+            }
+        }
+        bytes
+    }
+
+    /// Decodes a grid from one bit per cell, `None` if the bits do not fit the dimensions
+    fn from_bits(width: usize, height: usize, bytes: &[u8]) -> Option<BitPackedGrid> {
+        let cells = width.checked_mul(height)?;
+        if bytes.len() != cells.div_ceil(8) {
+            return None;
+        }
+        let mut grid = BitPackedGrid::new(width, height);
+        for i in 0..cells {
+            grid.set_bit_value((i % width, i / width), bytes[i / 8] & (0x80 >> (i % 8)) != 0);
+        }
+        Some(grid)
+    }
+
+    /// Encodes the grid in binary, the width and height as little endian `u32`
+    /// followed by the bits of the compact string, for checkpoints of large maps
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + (self.original_width * self.original_height).div_ceil(8));
+        bytes.extend((self.original_width as u32).to_le_bytes());
+        bytes.extend((self.original_height as u32).to_le_bytes());
+        bytes.extend(self.to_bits());
+        bytes
+    }
+
+    /// Decodes a grid from the start of its binary encoding
+    /// ## Returns
+    /// The grid and the number of bytes read, or an error if the bytes are truncated
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<(BitPackedGrid, usize)> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Truncated grid bytes");
+        let header = |i: usize| bytes.get(i..i + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize).ok_or_else(invalid);
+        let (width, height) = (header(0)?, header(4)?);
+        let length = width.checked_mul(height).and_then(|cells| cells.div_ceil(8).checked_add(8)).ok_or_else(invalid)?;
+        let bits = bytes.get(8..length).ok_or_else(invalid)?;
+        Ok((BitPackedGrid::from_bits(width, height, bits).ok_or_else(invalid)?, length))
+    }
+
+    /// Decodes a grid from its compact string
+    /// ## Returns
+    /// The grid, `None` if the string is malformed or its data does not fit
+    /// the dimensions
+    pub fn from_compact_string(compact: &str) -> Option<BitPackedGrid> {
+        let (dimensions, data) = compact.trim().split_once(':')?;
+        let (width, height) = dimensions.split_once('x')?;
+        let (width, height) = (width.parse::<usize>().ok()?, height.parse::<usize>().ok()?);
+        // Overflowing dimensions are rejected before the data is decoded
+        width.checked_mul(height)?;
+        BitPackedGrid::from_bits(width, height, &base64::decode(data)?)
+    }
+
+    /// Exports the free cells of the grid as an explicit weighted graph
+    /// ## Arguments
+    /// * `connectivity` - The moves allowed between neighbouring cells
+    /// * `cost_model` - The cost of moving between two neighbouring cells
+    /// ## Returns
+    /// A graph whose node ids number the free cells in row order
+    pub fn to_graph<W>(
+        &self,
+        connectivity: Connectivity,
+        cost_model: impl Fn((usize, usize), (usize, usize)) -> W,
+    ) -> CsrGraph<(usize, usize), W> {
+        CsrGraph::new(self.free_cells().collect(), |&(x, y)| {
+            self.neighbours(x, y, connectivity)
+                .map(|n| (n, cost_model((x, y), n)))
+                .collect::<Vec<_>>()
+        })
+    }
+
+    #[cfg(feature = "viz")]
     pub fn plot_cells(&self, filename: &str, path: Option<Vec<(usize, usize)>>, heatmap: Option<Vec<((usize, usize), f64)>>) {
         plot_cells(self.original_width, self.original_height, filename, |x, y| {
             self.get_bit_value((x, y))
         }, path, heatmap)
     }
+
+    /// Plots the grid map within a pixel budget, downsampling blocks of cells
+    /// into single pixels on maps too large to plot a pixel per cell
+    #[cfg(feature = "viz")]
+    pub fn plot_cells_within(
+        &self,
+        filename: &str,
+        path: Option<Vec<(usize, usize)>>,
+        heatmap: Option<Vec<((usize, usize), f64)>>,
+        budget: usize,
+    ) {
+        plot_cells_within(self.original_width, self.original_height, filename, |x, y| {
+            self.get_bit_value((x, y))
+        }, path, heatmap, &[], budget)
+    }
+
+    /// Plots the grid map with labels, glyphs and outlines drawn over it
+    #[cfg(feature = "viz")]
+    pub fn plot_annotated(
+        &self,
+        filename: &str,
+        path: Option<Vec<(usize, usize)>>,
+        heatmap: Option<Vec<((usize, usize), f64)>>,
+        annotations: &[Annotation],
+    ) {
+        plot_cells_within(self.original_width, self.original_height, filename, |x, y| {
+            self.get_bit_value((x, y))
+        }, path, heatmap, annotations, PIXEL_BUDGET)
+    }
+}
+
+/// Grids are serialized as their compact string, which unlike the packed
+/// words does not depend on the word size or padding
+#[cfg(feature = "serde")]
+impl serde::Serialize for BitPackedGrid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_compact_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for BitPackedGrid {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let compact = <String as serde::Deserialize>::deserialize(deserializer)?;
+        BitPackedGrid::from_compact_string(&compact).ok_or_else(|| serde::de::Error::custom("Invalid compact grid"))
+    }
 }
 
 #[cfg(test)]
@@ -170,9 +656,227 @@ mod tests {
         assert_eq!(grid.print_cells(None), map_str);
     }
 
+    #[test]
+    fn test_bitpackedgrid_to_graph() {
+        use crate::domains::csrgraph::{octile_cost, Connectivity};
+        let grid = BitPackedGrid::new_from_string("..\n.@\n".to_string());
+        let graph = grid.to_graph(Connectivity::Four, |_, _| 1);
+        assert_eq!(graph.nodes, vec![(0, 0), (1, 0), (0, 1)]);
+        assert_eq!(graph.edge_count(), 4);
+        let graph = grid.to_graph(Connectivity::Eight, octile_cost);
+        assert_eq!(graph.edge_count(), 6);
+        assert!(graph.adjacent(1).any(|(n, w)| n == 2 && w == std::f64::consts::SQRT_2));
+        let graph = grid.to_graph(Connectivity::EightNoCornerCutting, octile_cost);
+        assert_eq!(graph.edge_count(), 4);
+    }
+
+    #[test]
+    fn test_bitpackedgrid_algebra() {
+        let a = BitPackedGrid::new_from_string("..@@\n..@@\n".to_string());
+        let b = BitPackedGrid::new_from_string(".@.@\n.@.@\n".to_string());
+        let mut union = a.clone();
+        union.union(&b);
+        assert_eq!(union.print_cells(None), "...@\n...@\n");
+        let mut intersection = a.clone();
+        intersection.intersection(&b);
+        assert_eq!(intersection.print_cells(None), ".@@@\n.@@@\n");
+        let mut difference = a;
+        difference.difference(&b);
+        assert_eq!(difference.print_cells(None), "@.@@\n@.@@\n");
+    }
+
+    #[test]
+    fn test_bitpackedgrid_bulk() {
+        let mut grid = BitPackedGrid::new(70, 3);
+        grid.fill_area((60, 1), 20, 2, true);
+        assert_eq!(grid.count_free(), 20);
+        assert_eq!(grid.free_cells().next(), Some((60, 1)));
+        grid.fill_area((0, 0), 70, 3, true);
+        grid.fill_area((1, 1), 64, 1, false);
+        assert_eq!(grid.count_free(), 210 - 64);
+        assert_eq!(grid.free_cells().collect::<Vec<_>>(), grid.cells().filter(|n| grid.get_bit_value(*n)).collect::<Vec<_>>());
+        let mut inverted = grid.clone();
+        inverted.invert();
+        assert_eq!(inverted.count_free(), 64);
+        assert!(!inverted.get_bit_value((0, 1)) && inverted.get_bit_value((64, 1)));
+        let version = inverted.version();
+        inverted.fill_area((1, 1), 64, 1, false);
+        inverted.fill_area((1, 1), 64, 1, false);
+        assert_eq!(inverted.version(), version + 1);
+        let mut diff = BitPackedGrid::new_from_string("..@\n.@.\n".to_string());
+        diff.symmetric_difference(&BitPackedGrid::new_from_string(".@@\n...\n".to_string()));
+        assert_eq!(diff.print_cells(None), "@.@\n@.@\n");
+    }
+
+    #[test]
+    fn test_bitpackedgrid_resize() {
+        let mut grid = BitPackedGrid::new_from_string("..\n.@\n".to_string());
+        grid.resize(3, 1);
+        assert_eq!(grid.print_cells(None), "..@\n");
+        assert!(!grid.extend_to_fit((1, 0)));
+        assert!(grid.extend_to_fit((70, 1)));
+        assert_eq!((grid.original_width, grid.original_height), (71, 2));
+        assert!(grid.get_bit_value((1, 0)) && !grid.get_bit_value((70, 1)));
+        let mut grid = BitPackedGrid::new_from_string("..\n.@\n".to_string());
+        grid.insert_rows(1, 1);
+        assert_eq!(grid.print_cells(None), "..\n@@\n.@\n");
+        grid.insert_cols(0, 2);
+        assert_eq!(grid.print_cells(None), "@@..\n@@@@\n@@.@\n");
+    }
+
+    #[test]
+    fn test_bitpackedgrid_versioning() {
+        let mut grid = BitPackedGrid::new_from_string("..\n.@\n".to_string());
+        let (version, hash) = (grid.version(), grid.content_hash());
+        grid.set_bit_value((0, 0), true);
+        assert_eq!(grid.version(), version);
+        grid.set_bit_value((1, 1), true);
+        assert_eq!(grid.version(), version + 1);
+        assert_ne!(grid.content_hash(), hash);
+        grid.set_bit_value((1, 1), false);
+        assert_eq!(grid.version(), version + 2);
+        assert_eq!(grid.content_hash(), hash);
+        let copy = BitPackedGrid::new_from_string("..\n.@\n".to_string());
+        assert_eq!(copy.content_hash(), hash);
+        grid.union(&copy);
+        assert_eq!(grid.version(), version + 2);
+        grid.resize(3, 2);
+        assert_eq!(grid.version(), version + 3);
+        assert_ne!(grid.content_hash(), hash);
+        let window = grid.window_hash((0, 0), 2, 1);
+        grid.set_bit_value((2, 1), true);
+        assert_eq!(grid.window_hash((0, 0), 2, 1), window);
+        grid.set_bit_value((1, 0), false);
+        assert_ne!(grid.window_hash((0, 0), 2, 1), window);
+    }
+
+    #[test]
+    fn test_bitpackedgrid_nearest_free() {
+        let grid = BitPackedGrid::new_from_string("@@@@.\n@@@@@\n.@@@@\n...@@\n".to_string());
+        assert_eq!(grid.nearest_free_cell(0, 3), Some((0, 3)));
+        assert_eq!(grid.nearest_free_cell(2, 1), Some((2, 3)));
+        assert_eq!(grid.k_nearest_free(2, 1, 3), vec![(2, 3), (0, 2), (1, 3)]);
+        assert_eq!(grid.k_nearest_free(4, 3, 10).len(), 5);
+        assert_eq!(grid.k_nearest_free(4, 3, 0), vec![]);
+        assert_eq!(grid.nearest_free_cell(9, 0), Some((4, 0)));
+        assert_eq!(BitPackedGrid::new(3, 3).nearest_free_cell(1, 1), None);
+    }
+
+    #[cfg(feature = "viz")]
+    #[test]
+    fn test_bitpackedgrid_plot_within() {
+        use crate::domains::downsample_scale;
+        assert_eq!(downsample_scale(4096, 4096, 2048 * 2048), 2);
+        assert_eq!(downsample_scale(10, 10, 100), 1);
+        assert_eq!(downsample_scale(10, 10, 24), 3);
+        let mut grid = BitPackedGrid::new(600, 300);
+        (0..600).for_each(|x| grid.set_bit_value((x, x % 300), true));
+        let file = std::env::temp_dir().join("bitpackedgrid_plot_within.png");
+        grid.plot_cells_within(file.to_str().unwrap(), Some(vec![(0, 0), (1, 1)]), Some(vec![((5, 5), 1.0)]), 10_000);
+        assert!(file.exists());
+    }
+
+    #[cfg(feature = "viz")]
+    #[test]
+    fn test_bitpackedgrid_plot_annotated() {
+        use crate::domains::Annotation;
+        let grid = BitPackedGrid::new_from_string("....\n.@@.\n....\n".to_string());
+        let Annotation::Outline(segments) = Annotation::boundaries(4, 3, |(x, _)| x / 2) else { unreachable!() };
+        assert_eq!(segments, vec![((2, 0), (2, 1)), ((2, 1), (2, 2)), ((2, 2), (2, 3))]);
+        let annotations = [
+            Annotation::Start((0, 0)),
+            Annotation::Goal((3, 2)),
+            Annotation::Waypoints(vec![(1, 0), (3, 0)]),
+            Annotation::Label { cell: (1, 1), text: "wall".to_string() },
+            Annotation::Outline(segments),
+        ];
+        let file = std::env::temp_dir().join("bitpackedgrid_plot_annotated.png");
+        grid.plot_annotated(file.to_str().unwrap(), None, None, &annotations);
+        assert!(file.exists());
+    }
+
+    #[test]
+    fn test_bitpackedgrid_iterators() {
+        use crate::domains::csrgraph::Connectivity;
+        let grid = BitPackedGrid::new_from_string("..@\n.@.\n...\n".to_string());
+        assert_eq!(grid.cells().count(), 9);
+        assert_eq!(grid.cells().nth(3), Some((0, 1)));
+        assert_eq!(grid.free_cells().count(), 7);
+        assert_eq!(grid.cells_in_rect((1, 1), 5, 5).collect::<Vec<_>>(), vec![(1, 1), (2, 1), (1, 2), (2, 2)]);
+        assert_eq!(grid.cells_in_rect((4, 0), 2, 2).count(), 0);
+        assert_eq!(grid.neighbours(0, 0, Connectivity::Four).collect::<Vec<_>>(), vec![(1, 0), (0, 1)]);
+        assert_eq!(grid.neighbours(2, 1, Connectivity::Eight).collect::<Vec<_>>(), vec![(2, 2), (1, 2), (1, 0)]);
+        assert_eq!(grid.neighbours(2, 1, Connectivity::EightNoCornerCutting).collect::<Vec<_>>(), vec![(2, 2)]);
+        // Knight moves crossing the obstacle in the centre are not allowed
+        assert_eq!(grid.neighbours(0, 0, Connectivity::Sixteen).collect::<Vec<_>>(), vec![(1, 0), (0, 1)]);
+        let open = BitPackedGrid::new_from_string("...\n...\n...\n".to_string());
+        assert_eq!(open.neighbours(0, 0, Connectivity::Sixteen).collect::<Vec<_>>(), vec![(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)]);
+    }
+
     #[test]
     fn test_bitpackedgrid_get_neighbours() {
         let grid = BitPackedGrid::new_from_string(".....\n.@.@.\n.@.@.\n.@.@.\n.....\n....@\n".to_string());
         assert_eq!(grid.adjacent((0, 0), false).collect::<Vec<_>>(), vec![(1, 0), (0, 1)]);
     }
+
+    #[test]
+    fn test_bitpackedgrid_border() {
+        let grid = BitPackedGrid::new_from_string("....\n.@..\n....\n".to_string());
+        assert_eq!(grid.border_cells().count(), 10);
+        assert!(grid.is_border((3, 1)) && !grid.is_border((1, 1)) && !grid.is_border((4, 1)));
+        let open = BitPackedGrid::new_from_string(".....\n.@...\n.....\n.....\n.....\n".to_string());
+        assert_eq!(open.boundary_cells(false).count(), 18);
+        assert_eq!(open.boundary_cells(true).count(), 19);
+        assert!(!grid.has_solid_border());
+
+        let bordered = grid.with_border(1);
+        assert!(bordered.has_solid_border());
+        assert_eq!(bordered.print_cells(None), "@@@@@@\n@....@\n@.@..@\n@....@\n@@@@@@\n");
+        assert_eq!(bordered.without_border(1).print_cells(None), grid.print_cells(None));
+        let path = [(1, 1), (2, 1), (3, 1)];
+        assert_eq!(BitPackedGrid::strip_border_from_path(&path, 1), Some(vec![(0, 0), (1, 0), (2, 0)]));
+        assert_eq!(BitPackedGrid::strip_border_from_path(&[(0, 1)], 1), None);
+        assert_eq!(BitPackedGrid::new(1, 1).border_cells().count(), 1);
+    }
+
+    #[test]
+    fn test_bitpackedgrid_row_words() {
+        let grid = BitPackedGrid::new_from_string("..@..\n.@...\n".to_string());
+        assert_eq!(grid.row_word_east((0, 0)), 0b11011);
+        assert_eq!(grid.row_word_east((3, 1)), 0b11);
+        assert_eq!(grid.row_word_west((4, 1)), 0b10111);
+        assert_eq!(grid.row_word_east((0, 2)), 0);
+        let wide = BitPackedGrid::new_from_string(format!("{}@.\n", ".".repeat(70)));
+        assert_eq!(wide.row_word_east((10, 0)), ((1 << 60) - 1) | 1 << 61);
+        assert_eq!(wide.row_word_west((71, 0)).trailing_ones(), 1);
+        let transposed = grid.transposed();
+        assert_eq!(transposed.print_cells(None), "..\n.@\n@.\n..\n..\n");
+        assert_eq!(transposed.row_word_east((0, 2)), 0b10);
+    }
+
+    #[test]
+    fn test_bitpackedgrid_compact_string() {
+        let grid = BitPackedGrid::new_from_string("..@..\n.@@@.\n.....\n".to_string());
+        let compact = grid.to_compact_string();
+        assert_eq!(compact, "5x3:3H4");
+        let decoded = BitPackedGrid::from_compact_string(&compact).unwrap();
+        assert_eq!(decoded.print_cells(None), grid.print_cells(None));
+        let wide = BitPackedGrid::new_from_string(".".repeat(70) + "\n" + &"@.".repeat(35) + "\n");
+        assert_eq!(BitPackedGrid::from_compact_string(&wide.to_compact_string()).unwrap().print_cells(None), wide.print_cells(None));
+        assert!(BitPackedGrid::from_compact_string("5x3:3H").is_none());
+        assert!(BitPackedGrid::from_compact_string("5x:3H4").is_none());
+        assert!(BitPackedGrid::from_compact_string("5x4:3H4").is_none());
+        assert!(BitPackedGrid::from_compact_string(&format!("{}x{}:3H4", usize::MAX, 3)).is_none());
+    }
+
+    #[test]
+    fn test_bitpackedgrid_bytes() {
+        let grid = BitPackedGrid::new_from_string("..@..\n.@...\n....@\n".to_string());
+        let mut bytes = grid.to_bytes();
+        assert_eq!(bytes.len(), 8 + 2);
+        bytes.push(7);
+        let (decoded, read) = BitPackedGrid::from_bytes(&bytes).unwrap();
+        assert_eq!((decoded.print_cells(None), read), (grid.print_cells(None), 10));
+        assert!(BitPackedGrid::from_bytes(&bytes[..9]).is_err());
+    }
 }
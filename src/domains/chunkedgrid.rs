@@ -0,0 +1,160 @@
+//! # Chunked Grid Maps
+//! A grid map with effectively unbounded signed coordinates for procedural or
+//! exploration worlds with no fixed extent. The map is divided into fixed-size
+//! square chunks, each stored as a bitpacked grid, which are created lazily the
+//! first time a cell inside them is set to a value other than the default.
+//! Cells in chunks that were never created take the default value.
+
+use std::collections::HashMap;
+
+use super::bitpackedgrid::BitPackedGrid;
+
+/// A grid map of lazily created bitpacked chunks
+/// ## Fields
+/// * `chunk_size` - The width and height of each chunk
+/// * `default` - The value of cells in chunks that have not been created
+/// * `chunks` - The created chunks indexed by their chunk coordinates
+#[derive(Debug, Clone)]
+pub struct ChunkedGrid {
+    pub chunk_size: usize,
+    pub default: bool,
+    pub chunks: HashMap<(i64, i64), BitPackedGrid>,
+}
+
+impl ChunkedGrid {
+    /// Creates an empty chunked grid
+    /// ## Arguments
+    /// * `chunk_size` - The width and height of each chunk
+    /// * `default` - Whether cells that have never been set are free
+    pub fn new(chunk_size: usize, default: bool) -> Self {
+        assert!(chunk_size > 0, "Chunks must have a size");
+        ChunkedGrid { chunk_size, default, chunks: HashMap::new() }
+    }
+
+    /// Creates a chunked grid from a string, with the top left cell at the
+    /// origin and every cell outside the string an obstacle
+    pub fn new_from_string(map: String, chunk_size: usize) -> Self {
+        let mut grid = ChunkedGrid::new(chunk_size, false);
+        grid.insert_grid((0, 0), &BitPackedGrid::new_from_string(map));
+        grid
+    }
+
+    /// Splits a cell into its chunk coordinates and its position within the chunk
+    fn locate(&self, (x, y): (i64, i64)) -> ((i64, i64), (usize, usize)) {
+        let size = self.chunk_size as i64;
+        ((x.div_euclid(size), y.div_euclid(size)), (x.rem_euclid(size) as usize, y.rem_euclid(size) as usize))
+    }
+
+    /// Get the value of a cell
+    pub fn get_bit_value(&self, node: (i64, i64)) -> bool {
+        let (chunk, local) = self.locate(node);
+        match self.chunks.get(&chunk) {
+            Some(grid) => grid.get_bit_value(local),
+            None => self.default,
+        }
+    }
+
+    /// Set the value of a cell, creating its chunk if required
+    pub fn set_bit_value(&mut self, node: (i64, i64), value: bool) {
+        let (chunk, local) = self.locate(node);
+        if !self.chunks.contains_key(&chunk) && value == self.default {
+            return;
+        }
+        let (size, default) = (self.chunk_size, self.default);
+        self.chunks
+            .entry(chunk)
+            .or_insert_with(|| {
+                let mut grid = BitPackedGrid::new(size, size);
+                if default {
+                    for x in 0..size {
+                        for y in 0..size {
+                            grid.set_bit_value((x, y), true);
+                        }
+                    }
+                }
+                grid
+            })
+            .set_bit_value(local, value);
+    }
+
+    /// Copies a grid into the map with its top left cell at `origin`
+    pub fn insert_grid(&mut self, origin: (i64, i64), grid: &BitPackedGrid) {
+        for x in 0..grid.original_width {
+            for y in 0..grid.original_height {
+                self.set_bit_value((origin.0 + x as i64, origin.1 + y as i64), grid.get_bit_value((x, y)));
+            }
+        }
+    }
+
+    /// The number of chunks that have been created
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// The smallest (min, max) corners enclosing every created chunk
+    pub fn extent(&self) -> Option<((i64, i64), (i64, i64))> {
+        let size = self.chunk_size as i64;
+        let (min_x, max_x) = (self.chunks.keys().map(|c| c.0).min()?, self.chunks.keys().map(|c| c.0).max()?);
+        let (min_y, max_y) = (self.chunks.keys().map(|c| c.1).min()?, self.chunks.keys().map(|c| c.1).max()?);
+        Some(((min_x * size, min_y * size), ((max_x + 1) * size - 1, (max_y + 1) * size - 1)))
+    }
+
+    /// Get the free neighbors of a given cell
+    pub fn adjacent(&self, (x, y): (i64, i64), diagonal: bool) -> impl Iterator<Item = (i64, i64)> + '_ {
+        let mut neighbors = vec![(x + 1, y), (x, y + 1), (x - 1, y), (x, y - 1)];
+        if diagonal {
+            neighbors.extend([(x + 1, y + 1), (x - 1, y + 1), (x + 1, y - 1), (x - 1, y - 1)]);
+        }
+        neighbors.into_iter().filter(move |n| self.get_bit_value(*n))
+    }
+
+    pub fn adjacent1(&self, node: (i64, i64)) -> impl Iterator<Item = ((i64, i64), usize)> + '_ {
+        self.adjacent(node, false).map(|n| (n, 1))
+    }
+
+    /// Prints the cells between two corners where . is a free cell and @ is an obstacle
+    pub fn print_cells(&self, (min_x, min_y): (i64, i64), (max_x, max_y): (i64, i64)) -> String {
+        let mut s = String::new();
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                s.push(if self.get_bit_value((x, y)) { '.' } else { '@' });
+            }
+            s.push('\n');
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChunkedGrid;
+    #[cfg(feature = "search-core")]
+    use crate::search::astar::astar;
+
+    #[test]
+    fn test_chunkedgrid_lazy_chunks() {
+        let mut grid = ChunkedGrid::new(4, false);
+        assert!(!grid.get_bit_value((-100, 1_000_000)));
+        grid.set_bit_value((3, 3), false);
+        assert_eq!(grid.chunk_count(), 0);
+        grid.set_bit_value((-1, -1), true);
+        grid.set_bit_value((4, 0), true);
+        assert_eq!(grid.chunk_count(), 2);
+        assert!(grid.get_bit_value((-1, -1)) && !grid.get_bit_value((-2, -1)));
+        assert_eq!(grid.extent(), Some(((-4, -4), (7, 3))));
+
+        let open = ChunkedGrid::new(4, true);
+        assert_eq!(open.adjacent((-5, 7), true).count(), 8);
+    }
+
+    #[test]
+    #[cfg(feature = "search-core")]
+    fn test_chunkedgrid_search() {
+        let mut grid = ChunkedGrid::new_from_string("...\n.@.\n...\n".to_string(), 2);
+        grid.set_bit_value((-1, 1), true);
+        assert_eq!(grid.print_cells((-1, 0), (2, 1)), "@...\n..@.\n");
+        let (path, cost) = astar(|n| grid.adjacent1(*n), (-1, 1), |n| *n == (2, 1), |_| 0).unwrap();
+        assert_eq!(cost, 5);
+        assert_eq!(path.len(), 6);
+    }
+}
@@ -0,0 +1,146 @@
+//! # Terrain Class Beliefs
+//! A belief grid where each cell is one of several terrain classes rather than
+//! simply free or blocked. The belief of a cell is a Dirichlet distribution
+//! over the classes, stored as pseudo-counts that start from a uniform prior
+//! and grow with every weighted observation. A cell whose most likely class is
+//! not sufficiently probable is unknown.
+
+use super::bitpackedgrid::BitPackedGrid;
+use super::grid::Grid;
+
+/// The class of terrain of a cell
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Terrain {
+    Free,
+    Obstacle,
+    Hazard,
+}
+
+impl Terrain {
+    /// Every class in the order of their pseudo-counts
+    pub const ALL: [Terrain; 3] = [Terrain::Free, Terrain::Obstacle, Terrain::Hazard];
+
+    /// The index of the class in the pseudo-counts of a cell
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// A grid of Dirichlet beliefs over terrain classes
+/// ## Fields
+/// * `counts` - The pseudo-counts of each class for every cell
+/// * `prior` - The pseudo-count of each class before any observation
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassGrid {
+    pub counts: Grid<[f32; 3]>,
+    pub prior: f32,
+}
+
+impl ClassGrid {
+    /// Creates a grid where every cell has the uniform prior
+    pub fn new(width: usize, height: usize, prior: f32) -> Self {
+        assert!(prior > 0.0, "The prior pseudo-count must be positive");
+        ClassGrid { counts: Grid::new(width, height, [prior; 3]), prior }
+    }
+
+    /// Creates a grid of the same size as an occupancy grid
+    pub fn new_aligned(grid: &BitPackedGrid, prior: f32) -> Self {
+        ClassGrid::new(grid.original_width, grid.original_height, prior)
+    }
+
+    /// The width of the grid
+    pub fn width(&self) -> usize {
+        self.counts.width
+    }
+
+    /// The height of the grid
+    pub fn height(&self) -> usize {
+        self.counts.height
+    }
+
+    /// Check if a given x, y coordinate is within the bounds of the grid
+    pub fn bounds_check(&self, node: (usize, usize)) -> bool {
+        self.counts.bounds_check(node)
+    }
+
+    /// Updates the belief of a cell with an observation of its class
+    /// ## Arguments
+    /// * `node` - The observed cell
+    /// * `terrain` - The observed class
+    /// * `weight` - The confidence of the observation as a pseudo-count
+    pub fn observe(&mut self, node: (usize, usize), terrain: Terrain, weight: f32) {
+        self.counts[node][terrain.index()] += weight;
+    }
+
+    /// The expected probability of each class of a cell
+    pub fn probabilities(&self, node: (usize, usize)) -> [f32; 3] {
+        let counts = self.counts[node];
+        let total = counts.iter().sum::<f32>();
+        counts.map(|c| c / total)
+    }
+
+    /// The expected probability of a cell being of a class
+    pub fn probability(&self, node: (usize, usize), terrain: Terrain) -> f32 {
+        self.probabilities(node)[terrain.index()]
+    }
+
+    /// The total weight of the observations of a cell
+    pub fn evidence(&self, node: (usize, usize)) -> f32 {
+        self.counts[node].iter().sum::<f32>() - 3.0 * self.prior
+    }
+
+    /// The most likely class of a cell, or `None` if the cell is unknown
+    /// because no class has at least the given probability
+    pub fn classify(&self, node: (usize, usize), confidence: f32) -> Option<Terrain> {
+        let probabilities = self.probabilities(node);
+        Terrain::ALL.into_iter()
+            .max_by(|a, b| probabilities[a.index()].total_cmp(&probabilities[b.index()]))
+            .filter(|t| probabilities[t.index()] >= confidence)
+    }
+
+    /// The most likely class of every cell, where unknown cells are `None`
+    pub fn classes(&self, confidence: f32) -> Grid<Option<Terrain>> {
+        Grid::from_fn(self.width(), self.height(), |n| self.classify(n, confidence))
+    }
+
+    /// An occupancy grid where a cell is free unless it is most likely an obstacle
+    pub fn to_bitpacked(&self, confidence: f32) -> BitPackedGrid {
+        self.classes(confidence).to_bitpacked(|t| *t != Some(Terrain::Obstacle))
+    }
+
+    /// Get the neighbors of a given cell within the grid
+    pub fn adjacent(&self, (x, y): (usize, usize), diagonal: bool) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let mut neighbors = vec![(x + 1, y), (x, y + 1), (x.wrapping_sub(1), y), (x, y.wrapping_sub(1))];
+        if diagonal {
+            neighbors.extend([
+                (x + 1, y + 1),
+                (x.wrapping_sub(1), y + 1),
+                (x + 1, y.wrapping_sub(1)),
+                (x.wrapping_sub(1), y.wrapping_sub(1)),
+            ]);
+        }
+        neighbors.into_iter().filter(move |n| self.bounds_check(*n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classgrid_updates() {
+        let mut grid = ClassGrid::new(3, 2, 1.0);
+        assert_eq!(grid.probabilities((0, 0)), [1.0 / 3.0; 3]);
+        assert_eq!(grid.classify((0, 0), 0.5), None);
+        grid.observe((1, 1), Terrain::Hazard, 3.0);
+        grid.observe((1, 1), Terrain::Free, 1.0);
+        assert_eq!(grid.probabilities((1, 1)), [2.0 / 7.0, 1.0 / 7.0, 4.0 / 7.0]);
+        assert_eq!(grid.evidence((1, 1)), 4.0);
+        assert_eq!(grid.classify((1, 1), 0.5), Some(Terrain::Hazard));
+        assert_eq!(grid.classify((1, 1), 0.6), None);
+        grid.observe((2, 0), Terrain::Obstacle, 8.0);
+        assert_eq!(grid.to_bitpacked(0.5).print_cells(None), "..@\n...\n");
+        assert_eq!(grid.classes(0.5)[(1, 1)], Some(Terrain::Hazard));
+        assert_eq!(grid.adjacent((0, 0), true).count(), 3);
+    }
+}
@@ -0,0 +1,212 @@
+//! # Clearance
+//! The distance from every cell of a grid map to the nearest obstacle, for
+//! planning agents that are larger than a cell or should keep away from walls.
+//! Cells outside the map count as obstacles and distances are measured
+//! between cell centres. Distances are either exact euclidean distances,
+//! computed by the separable transform of Felzenszwalb and Huttenlocher,
+//! "Distance Transforms of Sampled Functions" (2012), or chamfer distances of
+//! 8-connected moves costing 1 and √2, computed in two raster passes.
+//!
+//! A clearance map feeds planning in two ways:
+//! * Inflation, blocking every cell within a robot radius of an obstacle
+//!   so any planner on a `BitPackedGrid`, such as `ThetaStar`, treats the
+//!   agent as a point
+//! * Soft costs, raising the cost of cells near obstacles in a `WeightedGrid`
+//!   or the weights of `anyangle::weighted_theta_star`, so paths keep their
+//!   distance from walls where there is room and squeeze past them otherwise
+
+use super::{Cell, bitpackedgrid::BitPackedGrid, grid::Grid, weightedgrid::WeightedGrid};
+
+/// How distances to obstacles are measured
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClearanceMetric {
+    /// The exact euclidean distance
+    Exact,
+    /// The length of the shortest 8-connected path ignoring obstacles, which
+    /// overestimates the euclidean distance by at most about 8%
+    Chamfer,
+}
+
+/// The distance from every cell to the nearest obstacle
+/// ## Fields
+/// * `distances` - The distance of each cell, 0.0 for obstacles
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClearanceMap {
+    pub distances: Grid<f32>,
+}
+
+/// A distance larger than any on a map, the distance of cells with no obstacle
+const FAR: f64 = 1e20;
+
+/// The squared distance transform of a sampled function along a line, the
+/// lower envelope of the parabolas rooted at each sample
+fn squared_transform(f: &[f64]) -> Vec<f64> {
+    let n = f.len();
+    let mut roots = vec![0; n];
+    let mut bounds = vec![0.0; n + 1];
+    let mut k = 0;
+    bounds[0] = f64::NEG_INFINITY;
+    bounds[1] = f64::INFINITY;
+    let intersection = |q: usize, p: usize| ((f[q] + (q * q) as f64) - (f[p] + (p * p) as f64)) / (2.0 * q as f64 - 2.0 * p as f64);
+    for q in 1..n {
+        let mut s = intersection(q, roots[k]);
+        while s <= bounds[k] {
+            k -= 1;
+            s = intersection(q, roots[k]);
+        }
+        k += 1;
+        roots[k] = q;
+        bounds[k] = s;
+        bounds[k + 1] = f64::INFINITY;
+    }
+    k = 0;
+    (0..n).map(|q| {
+        while bounds[k + 1] < q as f64 {
+            k += 1;
+        }
+        let offset = q as f64 - roots[k] as f64;
+        offset * offset + f[roots[k]]
+    }).collect()
+}
+
+impl ClearanceMap {
+    /// Computes the clearance of every cell of a grid map
+    pub fn new(grid: &BitPackedGrid, metric: ClearanceMetric) -> Self {
+        // The map is padded by a ring of obstacles for the cells outside it
+        let (width, height) = (grid.original_width + 2, grid.original_height + 2);
+        let blocked = |x: usize, y: usize| x == 0 || y == 0 || x + 1 == width || y + 1 == height || !grid.get_bit_value((x - 1, y - 1));
+        let padded = match metric {
+            ClearanceMetric::Exact => {
+                let mut squared = (0..width)
+                    .map(|x| squared_transform(&(0..height).map(|y| if blocked(x, y) { 0.0 } else { FAR }).collect::<Vec<_>>()))
+                    .collect::<Vec<_>>();
+                for y in 0..height {
+                    let row = squared_transform(&squared.iter().map(|column| column[y]).collect::<Vec<_>>());
+                    squared.iter_mut().zip(row).for_each(|(column, d)| column[y] = d);
+                }
+                squared.into_iter().map(|column| column.into_iter().map(f64::sqrt).collect()).collect()
+            }
+            ClearanceMetric::Chamfer => {
+                let mut distances = (0..width).map(|x| (0..height).map(|y| if blocked(x, y) { 0.0 } else { FAR }).collect::<Vec<_>>()).collect::<Vec<_>>();
+                let diagonal = std::f64::consts::SQRT_2;
+                let forward = [(-1, -1, diagonal), (0, -1, 1.0), (1, -1, diagonal), (-1, 0, 1.0)];
+                let relax = |distances: &mut Vec<Vec<f64>>, x: usize, y: usize, dx: i64, dy: i64, cost: f64| {
+                    let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                    if nx >= 0 && ny >= 0 && (nx as usize) < width && (ny as usize) < height {
+                        distances[x][y] = distances[x][y].min(distances[nx as usize][ny as usize] + cost);
+                    }
+                };
+                for y in 0..height {
+                    for x in 0..width {
+                        forward.iter().for_each(|&(dx, dy, cost)| relax(&mut distances, x, y, dx, dy, cost));
+                    }
+                }
+                for y in (0..height).rev() {
+                    for x in (0..width).rev() {
+                        forward.iter().for_each(|&(dx, dy, cost)| relax(&mut distances, x, y, -dx, -dy, cost));
+                    }
+                }
+                distances
+            }
+        };
+        let distances = Grid::from_fn(grid.original_width, grid.original_height, |(x, y)| padded[x + 1][y + 1] as f32);
+        ClearanceMap { distances }
+    }
+
+    /// The distance from a cell to the nearest obstacle, 0.0 outside the map
+    pub fn clearance(&self, cell: Cell) -> f32 {
+        self.distances.get(cell).copied().unwrap_or(0.0)
+    }
+
+    /// The grid of the cells an agent of a radius can occupy, those more than
+    /// the radius from every obstacle
+    pub fn inflate(&self, radius: f32) -> BitPackedGrid {
+        let mut grid = BitPackedGrid::new(self.distances.width, self.distances.height);
+        for (cell, distance) in self.distances.iter() {
+            grid.set_bit_value(cell, *distance > radius);
+        }
+        grid
+    }
+
+    /// The penalty of a cell in [0, 1], falling linearly from 1.0 next to an
+    /// obstacle to 0.0 at a distance
+    fn penalty(&self, cell: Cell, distance: f32) -> f32 {
+        let clearance = self.clearance(cell);
+        if distance <= 1.0 {
+            return 0.0;
+        }
+        ((distance - clearance) / (distance - 1.0)).clamp(0.0, 1.0)
+    }
+
+    /// Raises the cost of the cells of a weighted grid near obstacles
+    /// ## Arguments
+    /// * `grid` - The weighted grid, whose obstacles should match the clearance map
+    /// * `distance` - The clearance beyond which costs are unchanged
+    /// * `penalty` - The cost added to the cells next to an obstacle
+    pub fn soft_costs(&self, grid: &WeightedGrid, distance: f32, penalty: u8) -> WeightedGrid {
+        let mut soft = grid.clone();
+        for (cell, cost) in grid.costs.iter().filter(|(_, c)| **c > 0) {
+            let added = (self.penalty(cell, distance) * penalty as f32).round() as u8;
+            soft.set_cost(cell, cost.saturating_add(added));
+        }
+        soft
+    }
+
+    /// The traversal weights of a grid map for `anyangle::weighted_theta_star`,
+    /// 1.0 far from obstacles, up to `1.0 + penalty` next to them and
+    /// infinite for obstacles
+    /// ## Arguments
+    /// * `distance` - The clearance beyond which weights are 1.0
+    /// * `penalty` - The weight added to the cells next to an obstacle
+    pub fn weights(&self, distance: f32, penalty: f32) -> Grid<f32> {
+        Grid::from_fn(self.distances.width, self.distances.height, |cell| {
+            if self.clearance(cell) == 0.0 {
+                f32::INFINITY
+            } else {
+                1.0 + self.penalty(cell, distance) * penalty
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "search-core")]
+    use crate::search::anyangle::weighted_theta_star;
+
+    #[test]
+    fn test_clearance_transforms() {
+        let grid = BitPackedGrid::new_from_string("..........\n...@......\n..........\n......@@..\n..........\n........@.\n".to_string());
+        let exact = ClearanceMap::new(&grid, ClearanceMetric::Exact);
+        let chamfer = ClearanceMap::new(&grid, ClearanceMetric::Chamfer);
+        let mut obstacles = grid.cells().filter(|n| !grid.get_bit_value(*n)).map(|(x, y)| (x as f32 + 1.0, y as f32 + 1.0)).collect::<Vec<_>>();
+        obstacles.extend((0..12).flat_map(|x| [(x as f32, 0.0), (x as f32, 7.0)]));
+        obstacles.extend((0..8).flat_map(|y| [(0.0, y as f32), (11.0, y as f32)]));
+        for (x, y) in grid.cells() {
+            let (px, py) = (x as f32 + 1.0, y as f32 + 1.0);
+            let expected = obstacles.iter().map(|(ox, oy)| (px - ox).hypot(py - oy)).fold(f32::INFINITY, f32::min);
+            assert!((exact.clearance((x, y)) - expected).abs() < 1e-4, "{:?}", (x, y));
+            let approximate = chamfer.clearance((x, y));
+            assert!(approximate >= expected - 1e-4 && approximate <= expected * 1.09 + 1e-4);
+        }
+        assert_eq!((exact.clearance((3, 1)), exact.clearance((4, 1)), exact.clearance((0, 0))), (0.0, 1.0, 1.0));
+        let inflated = exact.inflate(1.0);
+        assert!(!inflated.get_bit_value((4, 1)) && !inflated.get_bit_value((0, 3)) && inflated.get_bit_value((4, 3)));
+    }
+
+    #[test]
+    #[cfg(feature = "search-core")]
+    fn test_clearance_costs() {
+        let grid = BitPackedGrid::new_from_string("..........\n..........\n..........\n..........\n..........\n".to_string());
+        let clearance = ClearanceMap::new(&grid, ClearanceMetric::Exact);
+        let weighted = clearance.soft_costs(&WeightedGrid::from_bitpacked(&grid), 3.0, 10);
+        assert_eq!((weighted.cost((0, 0)), weighted.cost((5, 2))), (Some(11), Some(1)));
+        // Paths between cells next to a wall bend away from it
+        let weights = clearance.weights(3.0, 10.0);
+        let (path, _) = weighted_theta_star(&weights, (1, 0), (8, 0)).unwrap();
+        assert!(path.iter().any(|n| n.1 >= 2));
+        let (straight, _) = weighted_theta_star(&Grid::new(10, 5, 1.0), (1, 0), (8, 0)).unwrap();
+        assert!(straight.iter().all(|n| n.1 == 0));
+    }
+}
@@ -0,0 +1,215 @@
+//! # Compressed Sparse Row Graphs
+//! An explicit weighted graph stored in compressed sparse row form. Nodes are
+//! numbered contiguously and the outgoing edges of node `i` are stored in
+//! `targets[offsets[i]..offsets[i + 1]]` with matching `weights`. This is a
+//! compact, cache friendly representation for graph-only algorithms that need
+//! random access by node id, which can be produced from grid maps with
+//! `BitPackedGrid::to_graph`.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use super::Cell;
+
+/// The moves allowed between neighbouring grid cells
+/// * `Four` - Only cardinal moves
+/// * `Eight` - Cardinal and diagonal moves
+/// * `EightNoCornerCutting` - Diagonal moves are only allowed when both adjacent
+///   cardinal cells are free
+/// * `Sixteen` - Diagonal moves without corner cutting and knight moves, which
+///   are only allowed when both cells the move crosses are free
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    Four,
+    Eight,
+    EightNoCornerCutting,
+    Sixteen,
+}
+
+impl Connectivity {
+    /// The (dx, dy) offsets of the cardinal and diagonal moves in the order of
+    /// `Direction::ALL`, followed by the knight moves
+    const OFFSETS: [(isize, isize); 16] = [
+        (1, 0), (0, 1), (-1, 0), (0, -1), (1, 1), (-1, 1), (1, -1), (-1, -1),
+        (2, 1), (1, 2), (-1, 2), (-2, 1), (-2, -1), (-1, -2), (1, -2), (2, -1),
+    ];
+
+    /// The (dx, dy) offsets of the moves, cardinal moves first
+    pub fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Connectivity::Four => &Connectivity::OFFSETS[..4],
+            Connectivity::Eight | Connectivity::EightNoCornerCutting => &Connectivity::OFFSETS[..8],
+            Connectivity::Sixteen => &Connectivity::OFFSETS,
+        }
+    }
+
+    /// Checks if a move between two cells is allowed
+    /// ## Arguments
+    /// * `from` - The cell moved from
+    /// * `to` - The cell moved to
+    /// * `free` - Whether a cell is free, false outside the grid
+    pub fn allows(&self, (x1, y1): Cell, (x2, y2): Cell, free: impl Fn(Cell) -> bool) -> bool {
+        let crossed = |a: Cell, b: Cell| free(a) && free(b);
+        free((x2, y2)) && match (self, x1.abs_diff(x2), y1.abs_diff(y2)) {
+            (_, 1, 0) | (_, 0, 1) => true,
+            (Connectivity::Eight, 1, 1) => true,
+            (Connectivity::EightNoCornerCutting | Connectivity::Sixteen, 1, 1) => crossed((x1, y2), (x2, y1)),
+            (Connectivity::Sixteen, 2, 1) => crossed(((x1 + x2) / 2, y1), ((x1 + x2) / 2, y2)),
+            (Connectivity::Sixteen, 1, 2) => crossed((x1, (y1 + y2) / 2), (x2, (y1 + y2) / 2)),
+            _ => false,
+        }
+    }
+
+    /// The length of a shortest path between two cells on an empty grid with
+    /// Euclidean move costs, an admissible and consistent heuristic for the
+    /// moves: the Manhattan, octile and 16-connected distances
+    pub fn distance(&self, (x1, y1): Cell, (x2, y2): Cell) -> f64 {
+        let (dx, dy) = (x1.abs_diff(x2), y1.abs_diff(y2));
+        let (long, short) = (dx.max(dy) as f64, dx.min(dy) as f64);
+        let (sqrt_2, sqrt_5) = (std::f64::consts::SQRT_2, 5f64.sqrt());
+        match self {
+            Connectivity::Four => long + short,
+            Connectivity::Eight | Connectivity::EightNoCornerCutting => short * sqrt_2 + long - short,
+            // Knight moves cover two cells of the long axis and one of the short
+            Connectivity::Sixteen if 2.0 * short <= long => short * sqrt_5 + long - 2.0 * short,
+            Connectivity::Sixteen => (long - short) * sqrt_5 + (2.0 * short - long) * sqrt_2,
+        }
+    }
+}
+
+/// A weighted graph in compressed sparse row form
+/// ## Fields
+/// * `nodes` - The label of each node id
+/// * `offsets` - The start of each node's edges in `targets`, with a final sentinel
+/// * `targets` - The target node id of each edge
+/// * `weights` - The weight of each edge
+#[derive(Clone, Debug)]
+pub struct CsrGraph<N: Eq + Hash + Clone, W> {
+    pub nodes: Vec<N>,
+    pub offsets: Vec<usize>,
+    pub targets: Vec<usize>,
+    pub weights: Vec<W>,
+    ids: HashMap<N, usize>,
+}
+
+impl<N: Eq + Hash + Clone, W> CsrGraph<N, W> {
+    /// Creates a graph from a list of node labels and a function returning
+    /// the outgoing edges of each label
+    /// ## Complexity
+    /// O(n + m) where n is the number of nodes and m is the number of edges
+    pub fn new<I>(nodes: Vec<N>, mut edges: impl FnMut(&N) -> I) -> Self
+    where
+        I: IntoIterator<Item = (N, W)>,
+    {
+        let ids = nodes.iter().cloned().enumerate().map(|(i, n)| (n, i)).collect::<HashMap<_, _>>();
+        let mut offsets = Vec::with_capacity(nodes.len() + 1);
+        let mut targets = Vec::new();
+        let mut weights = Vec::new();
+        offsets.push(0);
+        for node in &nodes {
+            for (target, weight) in edges(node) {
+                if let Some(&id) = ids.get(&target) {
+                    targets.push(id);
+                    weights.push(weight);
+                }
+            }
+            offsets.push(targets.len());
+        }
+        CsrGraph { nodes, offsets, targets, weights, ids }
+    }
+
+    /// The number of nodes in the graph
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Checks if the graph has no nodes
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The number of edges in the graph
+    pub fn edge_count(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// The id of a node label
+    pub fn id(&self, node: &N) -> Option<usize> {
+        self.ids.get(node).copied()
+    }
+
+    /// The outgoing edges of a node id as (target id, weight)
+    pub fn edges(&self, id: usize) -> impl Iterator<Item = (usize, &W)> {
+        let range = self.offsets[id]..self.offsets[id + 1];
+        self.targets[range.clone()].iter().copied().zip(self.weights[range].iter())
+    }
+
+    /// The number of outgoing edges of a node id
+    pub fn degree(&self, id: usize) -> usize {
+        self.offsets[id + 1] - self.offsets[id]
+    }
+}
+
+impl<N: Eq + Hash + Clone, W: Copy> CsrGraph<N, W> {
+    /// The outgoing edges of a node id, in the form expected by the search algorithms
+    pub fn adjacent(&self, id: usize) -> impl Iterator<Item = (usize, W)> + '_ {
+        self.edges(id).map(|(n, w)| (n, *w))
+    }
+}
+
+/// A cost model charging the Euclidean length of each move, such as √5 for
+/// knight moves
+pub fn euclidean_cost((x1, y1): (usize, usize), (x2, y2): (usize, usize)) -> f64 {
+    ((x1.abs_diff(x2).pow(2) + y1.abs_diff(y2).pow(2)) as f64).sqrt()
+}
+
+/// A cost model charging 1 for every move
+pub fn unit_cost(_: (usize, usize), _: (usize, usize)) -> f64 {
+    1.0
+}
+
+/// A cost model charging 1 for cardinal moves and √2 for diagonal moves
+pub fn octile_cost((x1, y1): (usize, usize), (x2, y2): (usize, usize)) -> f64 {
+    if x1 != x2 && y1 != y2 {
+        std::f64::consts::SQRT_2
+    } else {
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csr_graph() {
+        let graph = CsrGraph::new(vec!['a', 'b', 'c'], |n| match n {
+            'a' => vec![('b', 1), ('c', 4)],
+            'b' => vec![('c', 2), ('z', 3)],
+            _ => vec![],
+        });
+        assert_eq!(graph.len(), 3);
+        assert_eq!(graph.edge_count(), 3);
+        assert_eq!(graph.offsets, vec![0, 2, 3, 3]);
+        assert_eq!(graph.adjacent(0).collect::<Vec<_>>(), vec![(1, 1), (2, 4)]);
+        assert_eq!(graph.id(&'c'), Some(2));
+        assert_eq!(graph.degree(2), 0);
+    }
+
+    #[test]
+    fn test_connectivity() {
+        let free = |(x, y): Cell| x < 3 && y < 3 && (x, y) != (1, 0);
+        assert!(Connectivity::Eight.allows((0, 0), (1, 1), free));
+        assert!(!Connectivity::EightNoCornerCutting.allows((0, 0), (1, 1), free));
+        assert!(!Connectivity::Four.allows((0, 0), (1, 1), free));
+        assert!(!Connectivity::Sixteen.allows((0, 0), (2, 1), free));
+        assert!(Connectivity::Sixteen.allows((0, 0), (1, 2), free));
+        assert!(!Connectivity::EightNoCornerCutting.allows((0, 0), (1, 2), free));
+        assert_eq!(Connectivity::Sixteen.offsets().len(), 16);
+        assert_eq!(Connectivity::Four.distance((0, 0), (3, 1)), 4.0);
+        assert_eq!(Connectivity::Eight.distance((0, 0), (3, 1)), 2.0 + std::f64::consts::SQRT_2);
+        assert_eq!(Connectivity::Sixteen.distance((0, 0), (4, 2)), 2.0 * 5f64.sqrt());
+        assert_eq!(Connectivity::Sixteen.distance((0, 0), (3, 3)), 3.0 * std::f64::consts::SQRT_2);
+        assert_eq!(euclidean_cost((3, 1), (2, 3)), 5f64.sqrt());
+    }
+}
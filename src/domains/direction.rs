@@ -0,0 +1,219 @@
+//! # Directions
+//! Typed moves between neighbouring cells, replacing ad-hoc offset tuples.
+//! `Direction` covers the 4 and 8-connected moves of square grids, with y
+//! increasing downwards so north is up, and `HexDirection` the 6 moves of hex
+//! grids in axial coordinates. Both rotate clockwise as seen with y down, and
+//! the cardinal directions come first in `Direction::ALL` so that the order of
+//! neighbours, and any index stored per direction, fits in 2 bits for
+//! 4-connected moves.
+
+/// A move on a square grid
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Direction {
+    East,
+    South,
+    West,
+    North,
+    SouthEast,
+    SouthWest,
+    NorthEast,
+    NorthWest,
+}
+
+impl Direction {
+    /// Every direction, cardinal directions first, in the order of `neighbors`
+    pub const ALL: [Direction; 8] = [
+        Direction::East, Direction::South, Direction::West, Direction::North,
+        Direction::SouthEast, Direction::SouthWest, Direction::NorthEast, Direction::NorthWest,
+    ];
+
+    /// The 4-connected directions
+    pub const CARDINAL: [Direction; 4] = [Direction::East, Direction::South, Direction::West, Direction::North];
+
+    /// Every direction in clockwise order from north
+    const COMPASS: [Direction; 8] = [
+        Direction::North, Direction::NorthEast, Direction::East, Direction::SouthEast,
+        Direction::South, Direction::SouthWest, Direction::West, Direction::NorthWest,
+    ];
+
+    /// The index of the direction in `ALL`
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// The direction at an index of `ALL`
+    pub fn from_index(index: usize) -> Option<Direction> {
+        Direction::ALL.get(index).copied()
+    }
+
+    /// The (dx, dy) offset of a move
+    pub fn offset(&self) -> (isize, isize) {
+        match self {
+            Direction::East => (1, 0),
+            Direction::South => (0, 1),
+            Direction::West => (-1, 0),
+            Direction::North => (0, -1),
+            Direction::SouthEast => (1, 1),
+            Direction::SouthWest => (-1, 1),
+            Direction::NorthEast => (1, -1),
+            Direction::NorthWest => (-1, -1),
+        }
+    }
+
+    /// The direction with an offset, `None` unless both components are in [-1, 1]
+    /// and not both 0
+    pub fn from_offset(offset: (isize, isize)) -> Option<Direction> {
+        Direction::ALL.into_iter().find(|d| d.offset() == offset)
+    }
+
+    /// The direction moving between two neighbouring cells
+    pub fn between((x1, y1): (usize, usize), (x2, y2): (usize, usize)) -> Option<Direction> {
+        Direction::from_offset((x2 as isize - x1 as isize, y2 as isize - y1 as isize))
+    }
+
+    /// The cell reached by the move, `None` if it leaves the first quadrant
+    pub fn apply(&self, (x, y): (usize, usize)) -> Option<(usize, usize)> {
+        let (dx, dy) = self.offset();
+        Some((x.checked_add_signed(dx)?, y.checked_add_signed(dy)?))
+    }
+
+    /// The cell reached by the move, wrapping around below 0 so bitpacked
+    /// grids can bound check without branching
+    pub fn apply_wrapping(&self, (x, y): (usize, usize)) -> (usize, usize) {
+        let (dx, dy) = self.offset();
+        (x.wrapping_add_signed(dx), y.wrapping_add_signed(dy))
+    }
+
+    /// Checks if the move is diagonal
+    pub fn is_diagonal(&self) -> bool {
+        self.index() >= 4
+    }
+
+    /// The direction rotated clockwise by `steps` eighths of a turn, negative
+    /// steps rotating anticlockwise
+    pub fn rotate(&self, steps: isize) -> Direction {
+        let position = Direction::COMPASS.iter().position(|d| d == self).unwrap() as isize;
+        Direction::COMPASS[(position + steps).rem_euclid(8) as usize]
+    }
+
+    /// The direction an eighth of a turn clockwise
+    pub fn clockwise(&self) -> Direction {
+        self.rotate(1)
+    }
+
+    /// The direction an eighth of a turn anticlockwise
+    pub fn anticlockwise(&self) -> Direction {
+        self.rotate(-1)
+    }
+
+    /// The direction half a turn around
+    pub fn opposite(&self) -> Direction {
+        self.rotate(4)
+    }
+
+    /// The two cardinal directions a diagonal move is made of, `None` for
+    /// cardinal moves
+    pub fn components(&self) -> Option<(Direction, Direction)> {
+        self.is_diagonal().then(|| (self.anticlockwise(), self.clockwise()))
+    }
+
+    /// The lowercase compass name of the direction, such as "north-east"
+    pub fn name(&self) -> &'static str {
+        ["east", "south", "west", "north", "south-east", "south-west", "north-east", "north-west"][self.index()]
+    }
+
+    /// The numpad digit of the direction, where 8 is north and 3 is south-east
+    pub fn digit(&self) -> char {
+        ['6', '2', '4', '8', '3', '1', '9', '7'][self.index()]
+    }
+
+    /// The direction of a numpad digit
+    pub fn from_digit(digit: char) -> Option<Direction> {
+        Direction::ALL.into_iter().find(|d| d.digit() == digit)
+    }
+}
+
+/// A move on a hex grid in axial (q, r) coordinates, where the rows are
+/// pointy-topped and r increases downwards
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HexDirection {
+    East,
+    SouthEast,
+    SouthWest,
+    West,
+    NorthWest,
+    NorthEast,
+}
+
+impl HexDirection {
+    /// Every direction in clockwise order from east
+    pub const ALL: [HexDirection; 6] = [
+        HexDirection::East, HexDirection::SouthEast, HexDirection::SouthWest,
+        HexDirection::West, HexDirection::NorthWest, HexDirection::NorthEast,
+    ];
+
+    /// The (dq, dr) offset of a move
+    pub fn offset(&self) -> (isize, isize) {
+        match self {
+            HexDirection::East => (1, 0),
+            HexDirection::SouthEast => (0, 1),
+            HexDirection::SouthWest => (-1, 1),
+            HexDirection::West => (-1, 0),
+            HexDirection::NorthWest => (0, -1),
+            HexDirection::NorthEast => (1, -1),
+        }
+    }
+
+    /// The direction with an axial offset
+    pub fn from_offset(offset: (isize, isize)) -> Option<HexDirection> {
+        HexDirection::ALL.into_iter().find(|d| d.offset() == offset)
+    }
+
+    /// The cell reached by the move, `None` if it leaves the first quadrant
+    pub fn apply(&self, (q, r): (usize, usize)) -> Option<(usize, usize)> {
+        let (dq, dr) = self.offset();
+        Some((q.checked_add_signed(dq)?, r.checked_add_signed(dr)?))
+    }
+
+    /// The direction rotated clockwise by `steps` sixths of a turn, negative
+    /// steps rotating anticlockwise
+    pub fn rotate(&self, steps: isize) -> HexDirection {
+        HexDirection::ALL[(*self as isize + steps).rem_euclid(6) as usize]
+    }
+
+    /// The direction half a turn around
+    pub fn opposite(&self) -> HexDirection {
+        self.rotate(3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_direction() {
+        assert!(Direction::ALL.iter().enumerate().all(|(i, d)| d.index() == i));
+        assert_eq!(Direction::North.clockwise(), Direction::NorthEast);
+        assert_eq!(Direction::North.rotate(-3), Direction::SouthWest);
+        assert_eq!(Direction::NorthWest.rotate(10), Direction::NorthEast);
+        assert!(Direction::ALL.iter().all(|d| d.opposite().offset() == (-d.offset().0, -d.offset().1)));
+        assert_eq!(Direction::SouthEast.components(), Some((Direction::East, Direction::South)));
+        assert_eq!(Direction::West.components(), None);
+        assert_eq!(Direction::between((2, 2), (1, 3)), Some(Direction::SouthWest));
+        assert_eq!(Direction::between((2, 2), (4, 2)), None);
+        assert_eq!(Direction::North.apply((3, 0)), None);
+        assert_eq!(Direction::West.apply_wrapping((0, 1)), (usize::MAX, 1));
+        assert_eq!(Direction::from_digit('9'), Some(Direction::NorthEast));
+    }
+
+    #[test]
+    fn test_hex_direction() {
+        assert_eq!(HexDirection::East.rotate(2), HexDirection::SouthWest);
+        assert_eq!(HexDirection::East.rotate(-1), HexDirection::NorthEast);
+        assert!(HexDirection::ALL.iter().all(|d| d.opposite().offset() == (-d.offset().0, -d.offset().1)));
+        assert_eq!(HexDirection::from_offset((1, -1)), Some(HexDirection::NorthEast));
+        assert_eq!(HexDirection::SouthWest.apply((2, 2)), Some((1, 3)));
+        assert_eq!(HexDirection::NorthWest.apply((0, 0)), None);
+    }
+}
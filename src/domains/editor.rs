@@ -0,0 +1,153 @@
+//! # Grid Editing
+//! A structured editing API on bitpacked grids for interactive tools built on
+//! the crate, such as map editors or viewers. Every operation, whether a single
+//! cell, a wall segment, a filled rectangle or a round brush stroke, is recorded
+//! as one edit holding the previous value of each changed cell, so it can be
+//! undone and redone as a unit.
+
+use crate::fov::linedrawing::bresenham;
+
+use super::bitpackedgrid::BitPackedGrid;
+
+/// The cells changed by an operation as (cell, previous value, new value)
+type Edit = Vec<((usize, usize), bool, bool)>;
+
+/// A grid with an undo and redo stack of edits
+/// ## Fields
+/// * `grid` - The grid being edited
+/// * `undo_stack` - The edits that can be undone, most recent last
+/// * `redo_stack` - The undone edits that can be redone, most recent last
+#[derive(Debug, Clone)]
+pub struct GridEditor {
+    grid: BitPackedGrid,
+    undo_stack: Vec<Edit>,
+    redo_stack: Vec<Edit>,
+}
+
+impl GridEditor {
+    /// Creates an editor of a grid with empty undo and redo stacks
+    pub fn new(grid: BitPackedGrid) -> Self {
+        GridEditor { grid, undo_stack: Vec::new(), redo_stack: Vec::new() }
+    }
+
+    /// The grid being edited
+    pub fn grid(&self) -> &BitPackedGrid {
+        &self.grid
+    }
+
+    /// Consumes the editor returning the edited grid
+    pub fn into_inner(self) -> BitPackedGrid {
+        self.grid
+    }
+
+    /// Applies an operation to the given cells, skipping cells outside the grid
+    /// or already holding the value
+    /// ## Returns
+    /// The number of cells changed
+    fn apply(&mut self, cells: impl IntoIterator<Item = (usize, usize)>, value: bool) -> usize {
+        let mut edit = Edit::new();
+        for cell in cells {
+            if self.grid.bounds_check(cell) && self.grid.get_bit_value(cell) != value {
+                self.grid.set_bit_value(cell, value);
+                edit.push((cell, !value, value));
+            }
+        }
+        let changed = edit.len();
+        if changed > 0 {
+            self.undo_stack.push(edit);
+            self.redo_stack.clear();
+        }
+        changed
+    }
+
+    /// Sets a single cell
+    pub fn set(&mut self, cell: (usize, usize), value: bool) -> usize {
+        self.apply([cell], value)
+    }
+
+    /// Draws a wall segment between two cells, or clears it if `value` is true
+    pub fn line(&mut self, from: (usize, usize), to: (usize, usize), value: bool) -> usize {
+        self.apply(bresenham(from, to, |_, _| true), value)
+    }
+
+    /// Fills the rectangle between two corners inclusive
+    pub fn rectangle(&mut self, (x0, y0): (usize, usize), (x1, y1): (usize, usize), value: bool) -> usize {
+        let (xs, ys) = (x0.min(x1)..=x0.max(x1), y0.min(y1)..=y0.max(y1));
+        self.apply(xs.flat_map(|x| ys.clone().map(move |y| (x, y))), value)
+    }
+
+    /// Paints every cell within a euclidean radius of a center cell
+    pub fn brush(&mut self, (cx, cy): (usize, usize), radius: usize, value: bool) -> usize {
+        let (xs, ys) = (cx.saturating_sub(radius)..=cx + radius, cy.saturating_sub(radius)..=cy + radius);
+        let cells = xs.flat_map(|x| ys.clone().map(move |y| (x, y)))
+            .filter(|&(x, y)| x.abs_diff(cx).pow(2) + y.abs_diff(cy).pow(2) <= radius * radius);
+        self.apply(cells, value)
+    }
+
+    /// Reverts the most recent edit
+    /// ## Returns
+    /// Whether there was an edit to undo
+    pub fn undo(&mut self) -> bool {
+        let Some(edit) = self.undo_stack.pop() else { return false };
+        for (cell, previous, _) in edit.iter().rev() {
+            self.grid.set_bit_value(*cell, *previous);
+        }
+        self.redo_stack.push(edit);
+        true
+    }
+
+    /// Reapplies the most recently undone edit
+    /// ## Returns
+    /// Whether there was an edit to redo
+    pub fn redo(&mut self) -> bool {
+        let Some(edit) = self.redo_stack.pop() else { return false };
+        for (cell, _, value) in &edit {
+            self.grid.set_bit_value(*cell, *value);
+        }
+        self.undo_stack.push(edit);
+        true
+    }
+
+    /// Checks if there is an edit to undo
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// Checks if there is an edit to redo
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor_operations() {
+        let mut editor = GridEditor::new(BitPackedGrid::new_from_string(".....\n.....\n.....\n.....\n.....\n".to_string()));
+        assert_eq!(editor.line((0, 0), (4, 0), false), 5);
+        assert_eq!(editor.rectangle((3, 4), (1, 3), false), 6);
+        assert_eq!(editor.brush((2, 2), 1, true), 1);
+        assert_eq!(editor.set((9, 9), false), 0);
+        assert_eq!(editor.grid().print_cells(None), "@@@@@\n.....\n.....\n.@.@.\n.@@@.\n");
+        assert_eq!(editor.brush((0, 2), 1, false), 4);
+        assert_eq!(editor.grid().print_cells(None), "@@@@@\n@....\n@@...\n@@.@.\n.@@@.\n");
+    }
+
+    #[test]
+    fn test_editor_undo_redo() {
+        let original = BitPackedGrid::new_from_string("...\n...\n".to_string());
+        let mut editor = GridEditor::new(original.clone());
+        editor.rectangle((0, 0), (2, 1), false);
+        editor.set((1, 1), true);
+        assert!(editor.undo());
+        assert_eq!(editor.grid().print_cells(None), "@@@\n@@@\n");
+        assert!(editor.undo() && !editor.undo());
+        assert_eq!(editor.grid().print_cells(None), original.print_cells(None));
+        assert!(editor.redo());
+        editor.set((0, 0), true);
+        assert!(!editor.can_redo());
+        assert_eq!(editor.into_inner().print_cells(None), ".@@\n@@@\n");
+    }
+}
@@ -0,0 +1,127 @@
+//! # Fixed Grid
+//! A grid map whose size is known at compile time, for the many searches of
+//! game AI on small maps. Each row is a single 64 bit word held inline in an
+//! array, so a grid of up to 64 by 64 cells is one contiguous block without
+//! any heap allocation or padding, and cells are numbered in row order for
+//! the flat search arrays of `search::searcher::GridSearcher`.
+
+use super::{Cell, bitpackedgrid::BitPackedGrid};
+
+/// A grid map of `W` by `H` cells, at most 64 cells wide, where a set bit is
+/// a free cell
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FixedGrid<const W: usize, const H: usize> {
+    rows: [u64; H],
+}
+
+impl<const W: usize, const H: usize> FixedGrid<W, H> {
+    /// Fails to compile for grids wider than a row word
+    const FITS: () = assert!(W <= 64, "A fixed grid is at most 64 cells wide");
+
+    /// Creates a grid with every cell blocked
+    pub fn new() -> Self {
+        let () = Self::FITS;
+        FixedGrid { rows: [0; H] }
+    }
+
+    /// Creates a grid with every cell free
+    pub fn new_free() -> Self {
+        let () = Self::FITS;
+        let row = if W == 64 { u64::MAX } else { (1 << W) - 1 };
+        FixedGrid { rows: [row; H] }
+    }
+
+    /// Copies a bitpacked grid of the same size, `None` if the sizes differ
+    pub fn from_bitpacked(grid: &BitPackedGrid) -> Option<Self> {
+        if (grid.original_width, grid.original_height) != (W, H) {
+            return None;
+        }
+        let mut fixed = FixedGrid::new();
+        grid.free_cells().for_each(|cell| fixed.set_bit_value(cell, true));
+        Some(fixed)
+    }
+
+    /// Creates a grid from a string of `.` free cells and `@` obstacles, `None`
+    /// if it is not `W` by `H` cells
+    pub fn new_from_string(map: String) -> Option<Self> {
+        FixedGrid::from_bitpacked(&BitPackedGrid::new_from_string(map))
+    }
+
+    /// Copies the grid into a bitpacked grid
+    pub fn to_bitpacked(&self) -> BitPackedGrid {
+        let mut grid = BitPackedGrid::new(W, H);
+        self.free_cells().for_each(|cell| grid.set_bit_value(cell, true));
+        grid
+    }
+
+    /// Checks if a cell is within the grid
+    pub fn bounds_check(&self, (x, y): Cell) -> bool {
+        x < W && y < H
+    }
+
+    /// Checks if a cell is free, false outside the grid
+    pub fn get_bit_value(&self, (x, y): Cell) -> bool {
+        self.bounds_check((x, y)) && (self.rows[y] >> x) & 1 == 1
+    }
+
+    /// Sets whether a cell within the grid is free
+    pub fn set_bit_value(&mut self, (x, y): Cell, value: bool) {
+        debug_assert!(self.bounds_check((x, y)), "The cell is outside the grid");
+        if value {
+            self.rows[y] |= 1 << x;
+        } else {
+            self.rows[y] &= !(1 << x);
+        }
+    }
+
+    /// The row order index of a cell
+    pub fn index(&self, (x, y): Cell) -> usize {
+        y * W + x
+    }
+
+    /// The cell of a row order index
+    pub fn cell(&self, index: usize) -> Cell {
+        (index % W, index / W)
+    }
+
+    /// The free 4-connected neighbours of a cell
+    pub fn adjacent(&self, (x, y): Cell) -> impl Iterator<Item = Cell> + '_ {
+        [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)]
+            .into_iter()
+            .filter(|n| self.get_bit_value(*n))
+    }
+
+    /// The free cells of the grid in row order
+    pub fn free_cells(&self) -> impl Iterator<Item = Cell> + '_ {
+        (0..H).flat_map(move |y| (0..W).map(move |x| (x, y))).filter(|n| self.get_bit_value(*n))
+    }
+
+    /// The number of free cells
+    pub fn count_free(&self) -> usize {
+        self.rows.iter().map(|row| row.count_ones() as usize).sum()
+    }
+}
+
+impl<const W: usize, const H: usize> Default for FixedGrid<W, H> {
+    fn default() -> Self {
+        FixedGrid::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_grid() {
+        let map = "....\n.@@.\n....\n".to_string();
+        let grid = FixedGrid::<4, 3>::new_from_string(map.clone()).unwrap();
+        assert!(FixedGrid::<3, 3>::new_from_string(map.clone()).is_none());
+        assert_eq!((grid.count_free(), grid.get_bit_value((1, 1)), grid.get_bit_value((4, 0))), (10, false, false));
+        assert_eq!(grid.adjacent((0, 1)).collect::<Vec<_>>(), vec![(0, 0), (0, 2)]);
+        assert_eq!((grid.index((1, 2)), grid.cell(9)), (9, (1, 2)));
+        let bitpacked = BitPackedGrid::new_from_string(map);
+        assert_eq!(grid.to_bitpacked().free_cells().collect::<Vec<_>>(), bitpacked.free_cells().collect::<Vec<_>>());
+        assert_eq!(FixedGrid::<64, 2>::new_free().count_free(), 128);
+    }
+}
@@ -0,0 +1,259 @@
+//! # Map Generators
+//! Procedural grid maps for tests and benchmarks that do not depend on map
+//! files. Every generator takes a seed, so the same arguments give the same
+//! map on every run and platform.
+//! * Random obstacles, each cell blocked independently with a density
+//! * Mazes, carved by a recursive backtracker so every free cell is connected
+//! * Rooms and corridors, rectangular rooms joined in turn by L-shaped corridors
+//! * Caves, random fill smoothed by a cellular automaton
+//! * Noise terrain, Perlin noise thresholded into obstacles or used as the
+//!   belief of a sampling grid
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use super::{Cell, bitpackedgrid::BitPackedGrid};
+use super::samplegrid::SampleGrid;
+
+/// A map with each cell blocked independently
+/// ## Arguments
+/// * `density` - The chance of a cell being an obstacle in [0, 1]
+pub fn random_obstacles(width: usize, height: usize, density: f64, seed: u64) -> BitPackedGrid {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut grid = BitPackedGrid::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            grid.set_bit_value((x, y), !rng.gen_bool(density.clamp(0.0, 1.0)));
+        }
+    }
+    grid
+}
+
+/// A perfect maze carved by a recursive backtracker, with passages on the
+/// cells of even coordinates and the walls between them knocked through, so
+/// there is exactly one path between any two free cells
+pub fn maze(width: usize, height: usize, seed: u64) -> BitPackedGrid {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut grid = BitPackedGrid::new(width, height);
+    if width == 0 || height == 0 {
+        return grid;
+    }
+    grid.set_bit_value((0, 0), true);
+    let mut stack: Vec<Cell> = vec![(0, 0)];
+    while let Some(&(x, y)) = stack.last() {
+        let mut unvisited = [(x.wrapping_sub(2), y), (x + 2, y), (x, y.wrapping_sub(2)), (x, y + 2)]
+            .into_iter()
+            .filter(|n| grid.bounds_check(*n) && !grid.get_bit_value(*n))
+            .collect::<Vec<_>>();
+        unvisited.shuffle(&mut rng);
+        match unvisited.first() {
+            Some(&(nx, ny)) => {
+                grid.set_bit_value(((x + nx) / 2, (y + ny) / 2), true);
+                grid.set_bit_value((nx, ny), true);
+                stack.push((nx, ny));
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+    grid
+}
+
+/// A map of rectangular rooms, each joined to the room placed before it by an
+/// L-shaped corridor, so every room is connected
+/// ## Arguments
+/// * `rooms` - The number of rooms to place, fewer are placed when rooms
+///   do not fit without overlapping
+pub fn rooms_and_corridors(width: usize, height: usize, rooms: usize, seed: u64) -> BitPackedGrid {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut grid = BitPackedGrid::new(width, height);
+    let max_size = (width.min(height) / 4).max(3);
+    let mut placed: Vec<(Cell, usize, usize)> = Vec::new();
+    for _ in 0..rooms * 10 {
+        if placed.len() == rooms || width < 5 || height < 5 {
+            break;
+        }
+        let (w, h) = (rng.gen_range(3..=max_size.min(width - 2)), rng.gen_range(3..=max_size.min(height - 2)));
+        let (x, y) = (rng.gen_range(1..width - w), rng.gen_range(1..height - h));
+        // Rooms keep a wall between them
+        let overlaps = placed.iter().any(|&((px, py), pw, ph)| x <= px + pw && px <= x + w && y <= py + ph && py <= y + h);
+        if overlaps {
+            continue;
+        }
+        grid.fill_area((x, y), w, h, true);
+        if let Some(&((px, py), pw, ph)) = placed.last() {
+            let (from, to) = ((px + pw / 2, py + ph / 2), (x + w / 2, y + h / 2));
+            let corner = if rng.gen_bool(0.5) { (to.0, from.1) } else { (from.0, to.1) };
+            for (a, b) in [(from, corner), (corner, to)] {
+                let (left, top) = (a.0.min(b.0), a.1.min(b.1));
+                grid.fill_area((left, top), a.0.abs_diff(b.0) + 1, a.1.abs_diff(b.1) + 1, true);
+            }
+        }
+        placed.push(((x, y), w, h));
+    }
+    grid
+}
+
+/// A cave map made by randomly filling the map and smoothing it with a
+/// cellular automaton, blocking cells with at least five blocked neighbours
+/// and freeing cells with fewer than four. Cells outside the map count as
+/// blocked, so the cave is walled in.
+/// ## Arguments
+/// * `fill` - The chance of a cell being an obstacle before smoothing, about 0.45
+/// * `iterations` - The number of smoothing steps
+pub fn caves(width: usize, height: usize, fill: f64, iterations: usize, seed: u64) -> BitPackedGrid {
+    let mut grid = random_obstacles(width, height, fill, seed);
+    for _ in 0..iterations {
+        let mut next = grid.clone();
+        for (x, y) in grid.cells() {
+            let blocked = (-1..=1isize)
+                .flat_map(|dx| (-1..=1isize).map(move |dy| (dx, dy)))
+                .filter(|&(dx, dy)| (dx, dy) != (0, 0))
+                .filter(|&(dx, dy)| {
+                    let n = (x.wrapping_add_signed(dx), y.wrapping_add_signed(dy));
+                    !grid.bounds_check(n) || !grid.get_bit_value(n)
+                })
+                .count();
+            if blocked >= 5 {
+                next.set_bit_value((x, y), false);
+            } else if blocked < 4 {
+                next.set_bit_value((x, y), true);
+            }
+        }
+        grid = next;
+    }
+    grid
+}
+
+/// Two dimensional Perlin noise over a lattice shuffled from a seed
+struct Perlin {
+    permutation: Vec<usize>,
+}
+
+impl Perlin {
+    fn new(rng: &mut impl Rng) -> Self {
+        let mut permutation = (0..256).collect::<Vec<_>>();
+        permutation.shuffle(rng);
+        permutation.extend_from_within(..);
+        Perlin { permutation }
+    }
+
+    /// The dot product of the gradient of a lattice point with an offset
+    fn gradient(&self, (i, j): (usize, usize), (dx, dy): (f64, f64)) -> f64 {
+        match self.permutation[self.permutation[i & 255] + (j & 255)] & 3 {
+            0 => dx + dy,
+            1 => -dx + dy,
+            2 => dx - dy,
+            _ => -dx - dy,
+        }
+    }
+
+    /// The noise at a point, in about [-1, 1]
+    fn noise(&self, x: f64, y: f64) -> f64 {
+        let fade = |t: f64| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let lerp = |a: f64, b: f64, t: f64| a + t * (b - a);
+        let (i, j) = (x.floor() as usize, y.floor() as usize);
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+        let (u, v) = (fade(fx), fade(fy));
+        let top = lerp(self.gradient((i, j), (fx, fy)), self.gradient((i + 1, j), (fx - 1.0, fy)), u);
+        let bottom = lerp(self.gradient((i, j + 1), (fx, fy - 1.0)), self.gradient((i + 1, j + 1), (fx - 1.0, fy - 1.0)), u);
+        lerp(top, bottom, v)
+    }
+}
+
+/// Perlin noise over a map, such as the belief of a sampling grid
+/// ## Arguments
+/// * `scale` - The number of cells between lattice points, larger scales
+///   give smoother noise
+/// ## Returns
+/// The noise of each cell in [0, 1], indexed as `noise[x][y]` like
+/// `SampleGrid::new_from_grid`
+pub fn perlin_noise(width: usize, height: usize, scale: f64, seed: u64) -> Vec<Vec<f32>> {
+    let perlin = Perlin::new(&mut StdRng::seed_from_u64(seed));
+    let scale = scale.max(f64::EPSILON);
+    (0..width)
+        .map(|x| {
+            (0..height)
+                .map(|y| ((perlin.noise(x as f64 / scale, y as f64 / scale) + 1.0) / 2.0).clamp(0.0, 1.0) as f32)
+                .collect()
+        })
+        .collect()
+}
+
+/// A terrain map of Perlin noise, free where the noise is at least a threshold
+pub fn noise_terrain(width: usize, height: usize, scale: f64, threshold: f32, seed: u64) -> BitPackedGrid {
+    let noise = perlin_noise(width, height, scale, seed);
+    let mut grid = BitPackedGrid::new(width, height);
+    for (x, column) in noise.iter().enumerate() {
+        for (y, value) in column.iter().enumerate() {
+            grid.set_bit_value((x, y), *value >= threshold);
+        }
+    }
+    grid
+}
+
+/// A sampling grid whose belief is Perlin noise, with a ground truth sampled
+/// from the belief so that it is one of the worlds the belief describes
+pub fn noise_samplegrid(width: usize, height: usize, scale: f64, seed: u64) -> SampleGrid {
+    let noise = perlin_noise(width, height, scale, seed);
+    let mut rng = StdRng::seed_from_u64(seed.wrapping_add(1));
+    let mut ground_truth = BitPackedGrid::new(width, height);
+    for (x, column) in noise.iter().enumerate() {
+        for (y, value) in column.iter().enumerate() {
+            ground_truth.set_bit_value((x, y), rng.gen::<f32>() < *value);
+        }
+    }
+    SampleGrid::new_from_grid(noise, ground_truth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The number of free cells reachable from the first free cell
+    fn reachable(grid: &BitPackedGrid) -> usize {
+        let Some(start) = grid.free_cells().next() else { return 0 };
+        let mut seen = BitPackedGrid::new(grid.original_width, grid.original_height);
+        seen.set_bit_value(start, true);
+        let mut stack = vec![start];
+        while let Some((x, y)) = stack.pop() {
+            for n in grid.adjacent((x, y), false) {
+                if !seen.get_bit_value(n) {
+                    seen.set_bit_value(n, true);
+                    stack.push(n);
+                }
+            }
+        }
+        seen.count_free()
+    }
+
+    #[test]
+    fn test_generators_connected() {
+        let maze = maze(21, 15, 3);
+        assert!(maze.get_bit_value((20, 14)) && !maze.get_bit_value((1, 1)));
+        assert_eq!(reachable(&maze), maze.count_free());
+        let rooms = rooms_and_corridors(60, 40, 8, 5);
+        assert!(rooms.count_free() > 9);
+        assert_eq!(reachable(&rooms), rooms.count_free());
+        let random = random_obstacles(100, 100, 0.3, 1);
+        assert!((6500..7500).contains(&random.count_free()));
+        let cave = caves(60, 40, 0.45, 4, 2);
+        assert!(cave.count_free() > 0 && cave.count_free() < 60 * 40);
+    }
+
+    #[test]
+    fn test_generators_seeded() {
+        assert_eq!(maze(31, 31, 7).to_compact_string(), maze(31, 31, 7).to_compact_string());
+        assert_ne!(maze(31, 31, 7).to_compact_string(), maze(31, 31, 8).to_compact_string());
+        assert_eq!(caves(40, 30, 0.45, 3, 9).to_compact_string(), caves(40, 30, 0.45, 3, 9).to_compact_string());
+        let noise = perlin_noise(32, 16, 8.0, 4);
+        assert_eq!(noise, perlin_noise(32, 16, 8.0, 4));
+        assert!(noise.iter().flatten().all(|p| (0.0..=1.0).contains(p)));
+        // Neighbouring cells of smooth noise are close
+        assert!(noise.windows(2).all(|w| w[0].iter().zip(&w[1]).all(|(a, b)| (a - b).abs() < 0.25)));
+        let terrain = noise_terrain(32, 16, 8.0, 0.5, 4);
+        assert_eq!(terrain.count_free(), noise.iter().flatten().filter(|p| **p >= 0.5).count());
+        let grid = noise_samplegrid(32, 16, 8.0, 4);
+        assert_eq!(grid.beliefs(), noise);
+    }
+}
@@ -0,0 +1,234 @@
+//! # Explicit Graphs
+//! A directed graph of nodes carrying payloads, such as the coordinates of a
+//! road network or the centres of the polygons of a navmesh, joined by
+//! weighted edges. Nodes are numbered in the order they are added, so the
+//! searches run on the ids and the payloads inform heuristics. Road networks
+//! are read from the DIMACS shortest path challenge format, an `.gr` file of
+//! arcs and an `.co` file of coordinates.
+
+use std::io;
+
+/// The coordinates of a DIMACS node
+pub type Coordinates = (i64, i64);
+
+/// A directed graph with node payloads and weighted edges
+/// ## Fields
+/// * `nodes` - The payload of each node, indexed by its id
+/// * `edges` - The outgoing edges of each node with their weights
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AdjacencyList<N, W> {
+    pub nodes: Vec<N>,
+    pub edges: Vec<Vec<(usize, W)>>,
+}
+
+impl<N, W> AdjacencyList<N, W> {
+    pub fn new() -> Self {
+        AdjacencyList { nodes: Vec::new(), edges: Vec::new() }
+    }
+
+    /// Adds a node with a payload
+    /// ## Returns
+    /// The id of the node
+    pub fn add_node(&mut self, payload: N) -> usize {
+        self.nodes.push(payload);
+        self.edges.push(Vec::new());
+        self.nodes.len() - 1
+    }
+
+    /// Adds a directed edge between two nodes, panicking if either is missing
+    pub fn add_edge(&mut self, from: usize, to: usize, weight: W) {
+        assert!(to < self.nodes.len(), "node {} is not in the graph", to);
+        self.edges[from].push((to, weight));
+    }
+
+    /// The payload of a node
+    pub fn payload(&self, id: usize) -> Option<&N> {
+        self.nodes.get(id)
+    }
+
+    /// The outgoing edges of a node, none for missing nodes
+    pub fn edges(&self, id: usize) -> &[(usize, W)] {
+        self.edges.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.iter().map(Vec::len).sum()
+    }
+
+    /// The graph with every edge reversed, whose edges are the incoming
+    /// edges of the graph, such as for searching backwards from a goal
+    pub fn reversed(&self) -> Self
+    where
+        N: Clone,
+        W: Clone,
+    {
+        let mut edges = vec![Vec::new(); self.nodes.len()];
+        for (from, outgoing) in self.edges.iter().enumerate() {
+            for (to, weight) in outgoing {
+                edges[*to].push((from, weight.clone()));
+            }
+        }
+        AdjacencyList { nodes: self.nodes.clone(), edges }
+    }
+
+    /// The graph searched with a heuristic between the payloads of nodes
+    pub fn with_heuristic<H: Fn(&N, &N) -> W>(&self, heuristic: H) -> Informed<'_, N, W, H> {
+        Informed { graph: self, heuristic }
+    }
+}
+
+impl AdjacencyList<Coordinates, u64> {
+    /// The largest scale of the Euclidean distance between coordinates that
+    /// never exceeds the weight of an edge, so `euclidean` stays admissible
+    pub fn euclidean_scale(&self) -> f64 {
+        let mut scale = f64::INFINITY;
+        for (from, edges) in self.edges.iter().enumerate() {
+            for (to, weight) in edges {
+                let distance = euclidean(self.nodes[from], self.nodes[*to]);
+                if distance > 0.0 {
+                    scale = scale.min(*weight as f64 / distance);
+                }
+            }
+        }
+        if scale.is_finite() { scale } else { 0.0 }
+    }
+
+    /// The graph searched with the scaled Euclidean distance as heuristic
+    pub fn with_euclidean(&self) -> Informed<'_, Coordinates, u64, impl Fn(&Coordinates, &Coordinates) -> u64> {
+        let scale = self.euclidean_scale();
+        self.with_heuristic(move |a, b| (euclidean(*a, *b) * scale) as u64)
+    }
+}
+
+/// The Euclidean distance between coordinates
+fn euclidean((x1, y1): Coordinates, (x2, y2): Coordinates) -> f64 {
+    ((x1 - x2) as f64).hypot((y1 - y2) as f64)
+}
+
+/// A graph searched with a heuristic between the payloads of its nodes
+/// ## Fields
+/// * `graph` - The graph searched
+/// * `heuristic` - An admissible estimate of the cost between two payloads
+pub struct Informed<'a, N, W, H> {
+    pub graph: &'a AdjacencyList<N, W>,
+    pub heuristic: H,
+}
+
+/// The fields of a DIMACS line after its tag, failing if any is missing or
+/// not an integer
+fn dimacs_fields<const N: usize>(line: &str, number: usize) -> io::Result<[i64; N]> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("invalid DIMACS line {}: {}", number + 1, line));
+    let mut fields = line.split_whitespace().skip(1).map(|f| f.parse::<i64>());
+    let mut values = [0; N];
+    for value in values.iter_mut() {
+        *value = fields.next().and_then(Result::ok).ok_or_else(invalid)?;
+    }
+    Ok(values)
+}
+
+/// A DIMACS node id converted to an id of the graph
+fn dimacs_id(id: i64, nodes: usize) -> io::Result<usize> {
+    if id < 1 || id as usize > nodes {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("DIMACS node {} is out of range", id)));
+    }
+    Ok(id as usize - 1)
+}
+
+/// Parses a DIMACS road network
+/// ## Arguments
+/// * `gr` - The `.gr` file, a `p sp <nodes> <arcs>` line and `a <from> <to> <weight>` arcs
+/// * `co` - The `.co` file of `v <id> <x> <y>` coordinates, nodes without one lying at the origin
+/// ## Returns
+/// The graph with nodes numbered from 0 rather than 1 as in the files
+pub fn parse_dimacs(gr: &str, co: &str) -> io::Result<AdjacencyList<Coordinates, u64>> {
+    let mut graph = AdjacencyList::new();
+    for (number, line) in gr.lines().enumerate() {
+        match line.split_whitespace().next() {
+            Some("p") => {
+                let count = line.split_whitespace().nth(2).and_then(|n| n.parse::<usize>().ok());
+                let count = count.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid DIMACS problem line"))?;
+                (0..count).for_each(|_| { graph.add_node((0, 0)); });
+            }
+            Some("a") => {
+                let [from, to, weight] = dimacs_fields(line, number)?;
+                let (from, to) = (dimacs_id(from, graph.len())?, dimacs_id(to, graph.len())?);
+                graph.add_edge(from, to, weight.max(0) as u64);
+            }
+            _ => {}
+        }
+    }
+    for (number, line) in co.lines().enumerate() {
+        if line.split_whitespace().next() == Some("v") {
+            let [id, x, y] = dimacs_fields(line, number)?;
+            let id = dimacs_id(id, graph.len())?;
+            graph.nodes[id] = (x, y);
+        }
+    }
+    Ok(graph)
+}
+
+/// Loads a DIMACS road network from its `.gr` and `.co` files
+pub fn load_dimacs(gr: &str, co: &str) -> io::Result<AdjacencyList<Coordinates, u64>> {
+    parse_dimacs(&std::fs::read_to_string(gr)?, &std::fs::read_to_string(co)?)
+}
+
+#[cfg(all(test, feature = "search-core"))]
+mod tests {
+    use super::*;
+    use crate::search::domain::{search, search_with_stats};
+
+    const GR: &str = "c a small road network\np sp 4 5\na 1 2 10\na 2 4 10\na 1 3 12\na 3 4 15\na 4 1 20\n";
+    const CO: &str = "p aux sp co 4\nv 1 0 0\nv 2 10 0\nv 3 0 10\nv 4 10 10\n";
+
+    #[test]
+    fn test_graph_dimacs() {
+        let graph = parse_dimacs(GR, CO).unwrap();
+        assert_eq!((graph.len(), graph.edge_count()), (4, 5));
+        assert_eq!(graph.payload(3), Some(&(10, 10)));
+        assert_eq!(graph.edges(0), &[(1, 10), (2, 12)]);
+        assert_eq!(search(&graph, 0, 3), Some((vec![0, 1, 3], 20)));
+        assert!(parse_dimacs("p sp 2 1\na 1 3 5\n", "").is_err());
+        assert!(parse_dimacs("p sp 2 1\na 1 x 5\n", "").is_err());
+
+        let file = std::env::temp_dir().join("pathfinding-rs-dimacs");
+        let (gr, co) = (file.with_extension("gr"), file.with_extension("co"));
+        std::fs::write(&gr, GR).unwrap();
+        std::fs::write(&co, CO).unwrap();
+        let loaded = load_dimacs(gr.to_str().unwrap(), co.to_str().unwrap()).unwrap();
+        std::fs::remove_file(gr).unwrap();
+        std::fs::remove_file(co).unwrap();
+        assert_eq!(loaded, graph);
+    }
+
+    #[test]
+    fn test_graph_euclidean_search() {
+        let mut graph = AdjacencyList::new();
+        for x in 0..20 {
+            for y in 0..20 {
+                graph.add_node((x, y));
+            }
+        }
+        for id in 0..graph.len() {
+            let (x, y) = graph.nodes[id];
+            for (nx, ny) in [(x + 1, y), (x, y + 1), (x - 1, y), (x, y - 1)] {
+                if (0..20).contains(&nx) && (0..20).contains(&ny) {
+                    graph.add_edge(id, (nx * 20 + ny) as usize, 3);
+                }
+            }
+        }
+        assert_eq!(graph.euclidean_scale(), 3.0);
+        let (plain, plain_stats) = search_with_stats(&graph, 0, 399);
+        let (informed, informed_stats) = search_with_stats(&graph.with_euclidean(), 0, 399);
+        assert_eq!(plain.map(|(_, c)| c), Some(114));
+        assert_eq!(informed.map(|(_, c)| c), Some(114));
+        assert!(informed_stats.expanded < plain_stats.expanded);
+    }
+}
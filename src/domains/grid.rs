@@ -0,0 +1,145 @@
+//! # Grid Layers
+//! A generic grid storing a value of any type per cell, used for metadata
+//! aligned with an occupancy grid such as terrain tags, region ids or danger
+//! values. Layers use the same printing and plotting plumbing as the other grid
+//! domains, where a predicate decides which values are drawn as free cells.
+
+use std::ops::{Index, IndexMut};
+
+use super::bitpackedgrid::BitPackedGrid;
+use super::print_cells;
+#[cfg(feature = "viz")]
+use super::plot_cells;
+
+/// A grid of values stored in row-major order
+/// ## Fields
+/// * `width` - The width of the grid
+/// * `height` - The height of the grid
+/// * `cells` - The value of each cell at index `x + y * width`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Grid<T> {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    /// Creates a grid where every cell has the same value
+    pub fn new(width: usize, height: usize, value: T) -> Self {
+        Grid { width, height, cells: vec![value; width * height] }
+    }
+
+    /// Creates a layer of the same size as an occupancy grid
+    pub fn new_aligned(grid: &BitPackedGrid, value: T) -> Self {
+        Grid::new(grid.original_width, grid.original_height, value)
+    }
+}
+
+impl<T> Grid<T> {
+    /// Creates a grid from the value of each cell
+    pub fn from_fn(width: usize, height: usize, mut value: impl FnMut((usize, usize)) -> T) -> Self {
+        let cells = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(&mut value).collect();
+        Grid { width, height, cells }
+    }
+
+    /// Check if a given x, y coordinate is within the bounds of the grid
+    pub fn bounds_check(&self, (x, y): (usize, usize)) -> bool {
+        x < self.width && y < self.height
+    }
+
+    /// Checks if the grid has the same size as an occupancy grid
+    pub fn is_aligned(&self, grid: &BitPackedGrid) -> bool {
+        self.width == grid.original_width && self.height == grid.original_height
+    }
+
+    /// The value of a cell, or `None` if the cell is out of bounds
+    pub fn get(&self, node: (usize, usize)) -> Option<&T> {
+        self.bounds_check(node).then(|| &self.cells[node.0 + node.1 * self.width])
+    }
+
+    /// Sets the value of a cell
+    pub fn set(&mut self, node: (usize, usize), value: T) {
+        self[node] = value;
+    }
+
+    /// Iterates over every cell and its value in row-major order
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), &T)> + '_ {
+        self.cells.iter().enumerate().map(|(i, v)| ((i % self.width, i / self.width), v))
+    }
+
+    /// Creates a new grid by applying a function to every value
+    pub fn map<U>(&self, f: impl Fn(&T) -> U) -> Grid<U> {
+        Grid { width: self.width, height: self.height, cells: self.cells.iter().map(f).collect() }
+    }
+
+    /// An occupancy grid where a cell is free if its value satisfies a predicate
+    pub fn to_bitpacked(&self, is_free: impl Fn(&T) -> bool) -> BitPackedGrid {
+        let mut grid = BitPackedGrid::new(self.width, self.height);
+        for (node, value) in self.iter() {
+            grid.set_bit_value(node, is_free(value));
+        }
+        grid
+    }
+
+    /// The values of the grid normalised to [0, 1] for plotting as a heatmap
+    pub fn heatmap(&self, value: impl Fn(&T) -> f64) -> Vec<((usize, usize), f64)> {
+        let values = self.iter().map(|(n, v)| (n, value(v))).collect::<Vec<_>>();
+        let max = values.iter().map(|(_, v)| *v).fold(0.0, f64::max);
+        values.into_iter().map(|(n, v)| (n, if max > 0.0 { v / max } else { 0.0 })).collect()
+    }
+
+    /// Prints the grid where . is a value satisfying the predicate and @ is any other value
+    pub fn print_cells(&self, is_free: impl Fn(&T) -> bool, path: Option<Vec<(usize, usize)>>) -> String {
+        print_cells(self.width, self.height, |x, y| is_free(&self[(x, y)]), path)
+    }
+
+    /// Plots the grid where a value not satisfying the predicate is drawn as an obstacle
+    #[cfg(feature = "viz")]
+    pub fn plot_cells(
+        &self,
+        filename: &str,
+        is_free: impl Fn(&T) -> bool,
+        path: Option<Vec<(usize, usize)>>,
+        heatmap: Option<Vec<((usize, usize), f64)>>,
+    ) {
+        plot_cells(self.width, self.height, filename, |x, y| is_free(&self[(x, y)]), path, heatmap)
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        assert!(self.bounds_check((x, y)), "Cell out of bounds");
+        &self.cells[x + y * self.width]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+        assert!(self.bounds_check((x, y)), "Cell out of bounds");
+        &mut self.cells[x + y * self.width]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_layer() {
+        let occupancy = BitPackedGrid::new_from_string("..\n.@\n..\n".to_string());
+        let mut terrain = Grid::new_aligned(&occupancy, 'g');
+        assert!(terrain.is_aligned(&occupancy));
+        terrain.set((1, 1), 'w');
+        terrain[(0, 2)] = 'w';
+        assert_eq!(terrain.get((1, 1)), Some(&'w'));
+        assert_eq!(terrain.get((2, 0)), None);
+        assert_eq!(terrain.print_cells(|t| *t == 'g', Some(vec![(0, 0)])), "*.\n.@\n@.\n");
+        let danger = Grid::from_fn(2, 3, |(x, y)| (x + y) as f64);
+        assert_eq!(danger.heatmap(|d| *d)[5], ((1, 2), 1.0));
+        assert_eq!(danger.map(|d| *d < 2.0).to_bitpacked(|d| *d).print_cells(None), "..\n.@\n@@\n");
+        assert_eq!(terrain.iter().filter(|(_, t)| **t == 'w').map(|(n, _)| n).collect::<Vec<_>>(), vec![(1, 1), (0, 2)]);
+    }
+}
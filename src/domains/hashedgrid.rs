@@ -5,7 +5,9 @@
 
 use std::{collections::HashSet, fs::read_to_string};
 
-use super::{create_map_from_string, print_cells, plot_cells, neighbors};
+use super::{create_map_from_string, print_cells, neighbors};
+#[cfg(feature = "viz")]
+use super::plot_cells;
 
 /// A grid map whoch uses a hashset of obstacles to represent obstacles
 /// This is the simplest grid map implementation
@@ -150,10 +152,12 @@ impl HashedGrid {
         print_cells(self.width, self.height, |x, y| self.get_map_value(x, y), Some(path))
     }
 
+    #[cfg(feature = "viz")]
     pub fn plot_cells(&self, filename: &str, path: Vec<(usize, usize)>) {
         plot_cells(self.width, self.height, filename, |x, y| self.get_map_value(x, y), Some(path), None)
     }
 
+    #[cfg(feature = "viz")]
     pub fn plot_cells_with_heatmap(&self, filename: &str, heatmap: Vec<((usize, usize), f64)>) {
         plot_cells(self.width, self.height, filename, |x, y| self.get_map_value(x, y), None, Some(heatmap))
     }
@@ -4,14 +4,52 @@
 //! * HashedGrid, a grid map that uses a hash map to store the map
 //! * AdjacencyList, a graph representation of a map
 //! * SampleGrid, a grid map that uses a hash map to store the map and has a chance of being occupied
+//! * CsrGraph, an explicit weighted graph in compressed sparse row form
+//! * Graph, a directed graph with node payloads read from DIMACS road networks
+//! * ChunkedGrid, an unbounded grid map of lazily created bitpacked chunks
+//! * DoubleBufferedGrid, a pair of bitpacked grids read and written in alternating ticks
+//! * ObservedGrid, a bitpacked grid that publishes the cells changed by mutations
+//! * SharedGrid, a bitpacked grid read through snapshots by many threads and written in epochs
+//! * Grid, a layer of typed per-cell metadata aligned with an occupancy grid
+//! * FixedGrid, a compile-time sized grid map of at most 64 by 64 cells held inline
+//! * WeightedGrid, a grid map with a traversal cost per cell for terrain
+//! * Clearance, distance transforms of obstacles for inflation and wall-avoiding costs
+//! * GridEditor, structured editing of a bitpacked grid with an undo stack
+//! * ClassGrid, a Dirichlet belief over terrain classes such as hazards
+//! * Morph, sequences of maps changing gradually from one map to another
+//! * Generators, seeded procedural mazes, rooms, caves and noise terrain
+//! * Direction, typed square and hex grid moves with rotation
 
 #![allow(dead_code)]
 pub mod adjacencylist;
 pub mod hashedgrid;
 pub mod bitpackedgrid;
+#[cfg(feature = "sampling")]
 pub mod samplegrid;
+pub mod csrgraph;
+pub mod graph;
+pub mod chunkedgrid;
+pub mod doublebufferedgrid;
+pub mod observedgrid;
+pub mod sharedgrid;
+pub mod grid;
+pub mod fixedgrid;
+pub mod weightedgrid;
+pub mod clearance;
+pub mod editor;
+pub mod classgrid;
+pub mod direction;
+#[cfg(feature = "sampling")]
+pub mod morph;
+#[cfg(feature = "sampling")]
+pub mod generators;
 
+#[cfg(feature = "viz")]
 use plotters::{prelude::*, style::Color};
+
+/// A cell of a grid map, as its column and row
+pub type Cell = (usize, usize);
+
 /// A helper function that creates a map from a string given functions
 /// to initialize the map and add obstacles
 /// ## Arguments
@@ -100,6 +138,75 @@ pub fn print_points(
     )
 }
 
+/// The most pixels a plot of a grid map uses before cells are downsampled
+#[cfg(feature = "viz")]
+pub const PIXEL_BUDGET: usize = 2048 * 2048;
+
+/// The width and height in pixels of each tile of a plot drawn at once
+#[cfg(feature = "viz")]
+const TILE_SIZE: usize = 256;
+
+/// An annotation drawn over a plot of a grid map so that figures explain
+/// themselves without editing
+#[cfg(feature = "viz")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Annotation {
+    /// A text label at a cell
+    Label { cell: (usize, usize), text: String },
+    /// A start glyph, a green circle
+    Start((usize, usize)),
+    /// A goal glyph, a red cross
+    Goal((usize, usize)),
+    /// Waypoints numbered from 1 in order
+    Waypoints(Vec<(usize, usize)>),
+    /// Line segments between cell corners, such as the boundaries of clusters
+    Outline(Vec<((usize, usize), (usize, usize))>),
+    /// Cells filled in an RGB colour, such as the nodes one search expanded
+    Highlight { cells: Vec<(usize, usize)>, colour: (u8, u8, u8) },
+}
+
+#[cfg(feature = "viz")]
+impl Annotation {
+    /// Outlines the boundaries between cells of different regions, such as
+    /// the clusters of an abstraction
+    /// ## Arguments
+    /// * `width` - The width of the map
+    /// * `height` - The height of the map
+    /// * `region` - The region of a cell
+    pub fn boundaries<R: PartialEq>(width: usize, height: usize, region: impl Fn((usize, usize)) -> R) -> Self {
+        let mut segments = Vec::new();
+        for x in 0..width {
+            for y in 0..height {
+                if x + 1 < width && region((x, y)) != region((x + 1, y)) {
+                    segments.push(((x + 1, y), (x + 1, y + 1)));
+                }
+                if y + 1 < height && region((x, y)) != region((x, y + 1)) {
+                    segments.push(((x, y + 1), (x + 1, y + 1)));
+                }
+            }
+        }
+        Annotation::Outline(segments)
+    }
+}
+
+/// Pairs of nodes spread over a list by fixed prime strides, the queries of
+/// tests comparing searches on the same map
+#[cfg(test)]
+pub(crate) fn test_queries<N: Clone>(nodes: &[N], count: usize) -> Vec<(N, N)> {
+    (0..count).map(|i| (nodes[i * 7919 % nodes.len()].clone(), nodes[(i * 104729 + 17) % nodes.len()].clone())).collect()
+}
+
+/// The smallest number of cells along each side of a pixel so that a map fits
+/// within a pixel budget
+#[cfg(feature = "viz")]
+pub(crate) fn downsample_scale(width: usize, height: usize, budget: usize) -> usize {
+    let mut scale = 1;
+    while width.div_ceil(scale) * height.div_ceil(scale) > budget.max(1) {
+        scale += 1;
+    }
+    scale
+}
+
 /// Helper function to plot the cells on a plotter backend
 /// ## Arguments
 /// * `width` - The width of the map
@@ -109,6 +216,7 @@ pub fn print_points(
 /// true if the cell is free and false if it is an obstacle
 /// * `path` - An optional path to plot on the map
 /// * `heatmap` - An optional heatmap to plot on the map
+#[cfg(feature = "viz")]
 pub(crate) fn plot_cells(
     width: usize, 
     height: usize, 
@@ -117,39 +225,163 @@ pub(crate) fn plot_cells(
     path: Option<Vec<(usize, usize)>>,
     heatmap: Option<Vec<((usize, usize), f64)>>,
 ) {
-    let root = BitMapBackend::new(output_file, (width as u32, height as u32)).into_drawing_area();
+    plot_cells_within(width, height, output_file, get_cell_value, path, heatmap, &[], PIXEL_BUDGET)
+}
+
+/// Helper function to plot the cells within a pixel budget. Maps with more
+/// cells than the budget are downsampled so each pixel covers a square block
+/// of cells, shaded by the fraction of obstacles in the block, and obstacles
+/// are drawn tile by tile so large maps never hold every cell in memory.
+/// ## Arguments
+/// * `annotations` - Labels, glyphs and outlines drawn over the plot
+/// * `budget` - The most pixels of the plot, see `plot_cells` for the others
+#[cfg(feature = "viz")]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn plot_cells_within(
+    width: usize,
+    height: usize,
+    output_file: &str,
+    get_cell_value: impl Fn(usize, usize) -> bool,
+    path: Option<Vec<(usize, usize)>>,
+    heatmap: Option<Vec<((usize, usize), f64)>>,
+    annotations: &[Annotation],
+    budget: usize,
+) {
+    let scale = downsample_scale(width, height, budget);
+    let (pixel_width, pixel_height) = (width.div_ceil(scale), height.div_ceil(scale));
+    let root = BitMapBackend::new(output_file, (pixel_width as u32, pixel_height as u32)).into_drawing_area();
+    draw_cells(&root, (width, height), scale, &get_cell_value, path, heatmap, annotations);
+}
+
+/// A frame of an animation, the path and annotations drawn over a grid map
+#[cfg(feature = "viz")]
+pub type Frame = (Option<Vec<(usize, usize)>>, Vec<Annotation>);
+
+/// Plots frames of a grid map as an animated GIF within a pixel budget, see
+/// `plot_cells_within` for the drawing of each frame
+/// ## Arguments
+/// * `frames` - The path and annotations drawn in each frame
+/// * `delay` - The time each frame is shown in milliseconds
+#[cfg(feature = "viz")]
+pub(crate) fn plot_frames_gif(
+    width: usize,
+    height: usize,
+    output_file: &str,
+    get_cell_value: impl Fn(usize, usize) -> bool,
+    frames: Vec<Frame>,
+    delay: u32,
+    budget: usize,
+) {
+    let scale = downsample_scale(width, height, budget);
+    let size = (width.div_ceil(scale) as u32, height.div_ceil(scale) as u32);
+    let root = BitMapBackend::gif(output_file, size, delay).expect("Unable to create animation").into_drawing_area();
+    for (path, annotations) in frames {
+        draw_cells(&root, (width, height), scale, &get_cell_value, path, None, &annotations);
+        root.present().expect("Unable to write frame");
+    }
+}
+
+/// Draws the cells of a grid map onto a drawing area, downsampled by a scale
+#[cfg(feature = "viz")]
+fn draw_cells<DB: DrawingBackend>(
+    root: &DrawingArea<DB, plotters::coord::Shift>,
+    (width, height): (usize, usize),
+    scale: usize,
+    get_cell_value: &impl Fn(usize, usize) -> bool,
+    path: Option<Vec<(usize, usize)>>,
+    heatmap: Option<Vec<((usize, usize), f64)>>,
+    annotations: &[Annotation],
+) {
+    let (pixel_width, pixel_height) = (width.div_ceil(scale), height.div_ceil(scale));
     root.fill(&WHITE).unwrap();
-    let mut chart = ChartBuilder::on(&root)
+    let mut chart = ChartBuilder::on(root)
         .margin(5)
-        .build_cartesian_2d(0..width as i32, 0..height as i32)
+        .build_cartesian_2d(0..pixel_width as i32, 0..pixel_height as i32)
         .unwrap();
     chart.configure_mesh().disable_mesh().draw().unwrap();
+    let to_pixel = |(x, y): (usize, usize)| ((x / scale) as i32, (pixel_height - y / scale) as i32);
 
-    // Draw obstacles, will draw a rectangle for each cell that is an obstacle (is)
-    let series = (0..width)
-        .flat_map(|x| (0..height).map(move |y| (x, y)))
-        .filter(|(x, y)| !get_cell_value(x.clone(), y.clone()))
-        .map(|(x, y)| (x as i32, (height-y) as i32))
-        .map(|(x, y)| Rectangle::new([(x, y), (x, y)], &BLACK));
-    chart.draw_series(series).expect("Unable to draw obstacles");
+    // Draw obstacles a tile at a time, shading each pixel by the fraction of
+    // obstacles in the cells it covers
+    for tile_x in (0..pixel_width).step_by(TILE_SIZE) {
+        for tile_y in (0..pixel_height).step_by(TILE_SIZE) {
+            let mut shades = Vec::new();
+            for px in tile_x..(tile_x + TILE_SIZE).min(pixel_width) {
+                for py in tile_y..(tile_y + TILE_SIZE).min(pixel_height) {
+                    let xs = px * scale..((px + 1) * scale).min(width);
+                    let ys = py * scale..((py + 1) * scale).min(height);
+                    let cells = xs.len() * ys.len();
+                    let obstacles = xs.flat_map(|x| ys.clone().map(move |y| (x, y)))
+                        .filter(|(x, y)| !get_cell_value(*x, *y))
+                        .count();
+                    if obstacles > 0 {
+                        shades.push((px as i32, (pixel_height - py) as i32, obstacles as f64 / cells as f64));
+                    }
+                }
+            }
+            let series = shades.into_iter()
+                .map(|(x, y, shade)| Rectangle::new([(x, y), (x, y)], BLACK.mix(shade)));
+            chart.draw_series(series).expect("Unable to draw obstacles");
+        }
+    }
     
-    // Draw Heatmap of paths of values
+    // Draw Heatmap of paths of values, keeping the hottest cell of each pixel
     if let Some(heatmap) = heatmap {
-        let heatmap = heatmap
-            .iter()
-            .map(|((x, y), color)| (*x as i32, (height - *y) as i32, *color))
-            .map(|(x, y, color)| Rectangle::new([(x, y), (x, y)], &BLUE.mix(color)));
+        let mut pixels = std::collections::HashMap::new();
+        for (cell, color) in heatmap {
+            let hottest = pixels.entry(to_pixel(cell)).or_insert(color);
+            *hottest = color.max(*hottest);
+        }
+        let heatmap = pixels
+            .into_iter()
+            .map(|((x, y), color)| Rectangle::new([(x, y), (x, y)], BLUE.mix(color)));
         chart.draw_series(heatmap).unwrap();
     }
 
     // Draw path
     if let Some(path) = path {
-        let path = path
-            .iter()
-            .map(|(x, y)| (*x as i32, (height - *y) as i32))
+        let mut pixels = path.into_iter().map(to_pixel).collect::<Vec<_>>();
+        pixels.dedup();
+        let path = pixels
+            .into_iter()
             .map(|(x, y)| Rectangle::new([(x, y), (x, y)], &RED));
         chart.draw_series(path).unwrap();
     }
+
+    // Draw annotations over everything else
+    let corner = |(x, y): (usize, usize)| ((x / scale) as i32, (pixel_height - y / scale) as i32);
+    let font = ("sans-serif", 12).into_font();
+    for annotation in annotations {
+        match annotation {
+            Annotation::Label { cell, text } => {
+                chart.draw_series([Text::new(text.clone(), to_pixel(*cell), font.clone())]).unwrap();
+            }
+            Annotation::Start(cell) => {
+                chart.draw_series([Circle::new(to_pixel(*cell), 4, GREEN.filled())]).unwrap();
+            }
+            Annotation::Goal(cell) => {
+                chart.draw_series([Cross::new(to_pixel(*cell), 4, RED.stroke_width(2))]).unwrap();
+            }
+            Annotation::Waypoints(cells) => {
+                chart.draw_series(cells.iter().map(|c| Circle::new(to_pixel(*c), 3, MAGENTA.filled()))).unwrap();
+                let labels = cells.iter().enumerate()
+                    .map(|(i, c)| Text::new((i + 1).to_string(), to_pixel(*c), font.clone()));
+                chart.draw_series(labels).unwrap();
+            }
+            Annotation::Outline(segments) => {
+                let lines = segments.iter()
+                    .map(|(from, to)| PathElement::new(vec![corner(*from), corner(*to)], CYAN));
+                chart.draw_series(lines).unwrap();
+            }
+            Annotation::Highlight { cells, colour } => {
+                let colour = RGBColor(colour.0, colour.1, colour.2);
+                let mut pixels = cells.iter().map(|c| to_pixel(*c)).collect::<Vec<_>>();
+                pixels.sort_unstable();
+                pixels.dedup();
+                chart.draw_series(pixels.into_iter().map(|p| Rectangle::new([p, p], colour.filled()))).unwrap();
+            }
+        }
+    }
 }
 
 /// Helper function to get a iterator of the neighbors of a cell
@@ -159,19 +391,8 @@ pub fn neighbors(
     diagonal: bool
 ) -> impl Iterator<Item = (usize, usize)>
 {
-    let mut neighbors = vec![
-        (x.wrapping_add(1), y), // These are wrapping as avoid branching 
-        (x, y.wrapping_add(1)), // on bitpacked grids. This means bound
-        (x.wrapping_sub(1), y), // checks or padding is required.
-        (x, y.wrapping_sub(1))
-    ];
-    if diagonal {
-        neighbors.extend(vec![
-            (x.wrapping_add(1), y.wrapping_add(1)),
-            (x.wrapping_sub(1), y.wrapping_add(1)),
-            (x.wrapping_add(1), y.wrapping_sub(1)),
-            (x.wrapping_sub(1), y.wrapping_sub(1)),
-        ]);
-    }
-    neighbors.into_iter()
+    // These are wrapping as avoid branching on bitpacked grids. This means
+    // bound checks or padding is required.
+    let directions = if diagonal { &direction::Direction::ALL[..] } else { &direction::Direction::CARDINAL[..] };
+    directions.iter().map(move |d| d.apply_wrapping((x, y)))
 }
\ No newline at end of file
@@ -0,0 +1,139 @@
+//! # Map Morphing
+//! Sequences of intermediate maps between two grid maps of the same size,
+//! used as benchmarks of incremental planners under gradual change. Every cell
+//! that differs between the maps is given a time in [0, 1] at which it flips
+//! from its value in the first map to its value in the second, so each cell
+//! changes at most once across a sequence.
+//! * Random, flip times are uniform, a cell-wise probabilistic interpolation
+//! * Morphological, flip times grow with the distance into the region of
+//!   differing cells, so new obstacles grow inwards from the edges of the
+//!   region like a dilation and removed obstacles erode away
+
+use std::collections::VecDeque;
+
+use rand::Rng;
+
+use crate::util::determinism::with_rng;
+
+use super::bitpackedgrid::BitPackedGrid;
+
+/// How the cells that differ between two maps are ordered to flip
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Morph {
+    Random,
+    Morphological,
+}
+
+/// The flip time of each cell that differs between two maps
+fn flip_times(from: &BitPackedGrid, to: &BitPackedGrid, morph: Morph) -> Vec<((usize, usize), f64)> {
+    assert_eq!(
+        (from.original_width, from.original_height),
+        (to.original_width, to.original_height),
+        "Morphed maps must be the same size"
+    );
+    let differs = |cell: (usize, usize)| from.get_bit_value(cell) != to.get_bit_value(cell);
+    let cells = from.cells().filter(|c| differs(*c)).collect::<Vec<_>>();
+    match morph {
+        Morph::Random => with_rng(|rng| cells.into_iter().map(|c| (c, rng.gen::<f64>())).collect()),
+        Morph::Morphological => {
+            // Breadth-first depth into the differing region from the cells that agree
+            let (width, height) = (from.original_width, from.original_height);
+            let mut depth = vec![vec![usize::MAX; height]; width];
+            let mut queue = VecDeque::new();
+            for cell in &cells {
+                let (x, y) = *cell;
+                let neighbours = [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)];
+                if neighbours.iter().any(|n| n.0 < width && n.1 < height && !differs(*n)) {
+                    depth[x][y] = 1;
+                    queue.push_back(*cell);
+                }
+            }
+            while let Some((x, y)) = queue.pop_front() {
+                for (nx, ny) in [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)] {
+                    if nx < width && ny < height && differs((nx, ny)) && depth[nx][ny] == usize::MAX {
+                        depth[nx][ny] = depth[x][y] + 1;
+                        queue.push_back((nx, ny));
+                    }
+                }
+            }
+            // A region filling the whole map has no edge, so it flips at once
+            let deepest = cells.iter().map(|(x, y)| depth[*x][*y]).filter(|d| *d != usize::MAX).max().unwrap_or(1);
+            cells.into_iter()
+                .map(|(x, y)| ((x, y), depth[x][y].min(deepest) as f64 / deepest as f64))
+                .collect()
+        }
+    }
+}
+
+/// The map a fraction of the way from one map to another
+/// ## Arguments
+/// * `from` - The map at `t` of 0
+/// * `to` - The map at `t` of 1, of the same size
+/// * `t` - The fraction of the way between the maps
+/// * `morph` - How the differing cells are ordered
+pub fn interpolate(from: &BitPackedGrid, to: &BitPackedGrid, t: f64, morph: Morph) -> BitPackedGrid {
+    let mut grid = from.clone();
+    for (cell, time) in flip_times(from, to, morph) {
+        if time <= t {
+            grid.set_bit_value(cell, to.get_bit_value(cell));
+        }
+    }
+    grid
+}
+
+/// A sequence of maps changing gradually from one map to another
+/// ## Arguments
+/// * `from` - The first map of the sequence
+/// * `to` - The last map of the sequence, of the same size
+/// * `steps` - The number of changes, the sequence has `steps + 1` maps
+/// * `morph` - How the differing cells are ordered
+/// ## Returns
+/// The maps of the sequence, where each cell changes at most once
+pub fn morph_sequence(from: &BitPackedGrid, to: &BitPackedGrid, steps: usize, morph: Morph) -> Vec<BitPackedGrid> {
+    let mut times = flip_times(from, to, morph);
+    times.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let mut frames = vec![from.clone()];
+    let mut flipped = times.into_iter().peekable();
+    for step in 1..=steps {
+        let t = step as f64 / steps.max(1) as f64;
+        let mut frame = frames.last().unwrap().clone();
+        while let Some((cell, _)) = flipped.next_if(|(_, time)| *time <= t || step == steps) {
+            frame.set_bit_value(cell, to.get_bit_value(cell));
+        }
+        frames.push(frame);
+    }
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_morph_sequence() {
+        let from = BitPackedGrid::new_from_string(".......\n.......\n.......\n".to_string());
+        let to = BitPackedGrid::new_from_string("@@@@@@@\n@@@@@@@\n@@@@@@@\n".to_string());
+        for morph in [Morph::Random, Morph::Morphological] {
+            let frames = morph_sequence(&from, &to, 4, morph);
+            assert_eq!(frames.len(), 5);
+            assert_eq!(frames[0].print_cells(None), from.print_cells(None));
+            assert_eq!(frames[4].print_cells(None), to.print_cells(None));
+            let free = frames.iter().map(|f| f.free_cells().count()).collect::<Vec<_>>();
+            assert!(free.windows(2).all(|w| w[0] >= w[1]));
+        }
+    }
+
+    #[test]
+    fn test_morphological_growth() {
+        let from = BitPackedGrid::new_from_string("@......\n@......\n@......\n".to_string());
+        let to = BitPackedGrid::new_from_string("@@@@@@.\n@@@@@@.\n@@@@@@.\n".to_string());
+        let frames = morph_sequence(&from, &to, 5, Morph::Morphological);
+        // The wall grows a column at a time away from the existing wall and the free column
+        assert_eq!(frames[1].print_cells(None), from.print_cells(None));
+        assert_eq!(frames[2].free_cells().count(), 12);
+        let halfway = interpolate(&from, &to, 0.5, Morph::Morphological);
+        assert_eq!(halfway.free_cells().count(), 12);
+        assert!(!halfway.get_bit_value((1, 1)) && !halfway.get_bit_value((5, 1)));
+        assert!(halfway.get_bit_value((3, 1)));
+    }
+}
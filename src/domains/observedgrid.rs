@@ -0,0 +1,74 @@
+//! # Observed Grid Maps
+//! A bitpacked grid that records every cell whose value changes so that the
+//! changes can be published to subscribers, such as incremental planners that
+//! repair their search after the map is edited.
+
+use crate::util::notify::{ChangeNotifier, Subscription};
+
+use super::bitpackedgrid::BitPackedGrid;
+
+/// A bitpacked grid whose mutations are published as batches of changed cells
+/// ## Fields
+/// * `notifier` - The changes recorded since the last publish and the subscribers
+#[derive(Debug, Clone)]
+pub struct ObservedGrid {
+    grid: BitPackedGrid,
+    pub notifier: ChangeNotifier<(usize, usize)>,
+}
+
+impl ObservedGrid {
+    /// Observes a grid
+    pub fn new(grid: BitPackedGrid) -> Self {
+        ObservedGrid { grid, notifier: ChangeNotifier::default() }
+    }
+
+    /// The observed grid, which can only be mutated through the observed grid
+    pub fn grid(&self) -> &BitPackedGrid {
+        &self.grid
+    }
+
+    /// Get the value of a cell
+    pub fn get_bit_value(&self, node: (usize, usize)) -> bool {
+        self.grid.get_bit_value(node)
+    }
+
+    /// Set the value of a cell, recording it if the value changed
+    pub fn set_bit_value(&mut self, node: (usize, usize), value: bool) {
+        if self.grid.get_bit_value(node) != value {
+            self.grid.set_bit_value(node, value);
+            self.notifier.record(node);
+        }
+    }
+
+    /// Adds a subscriber to the changes of the grid
+    pub fn subscribe(&mut self) -> Subscription<(usize, usize)> {
+        self.notifier.subscribe()
+    }
+
+    /// Publishes the cells changed since the last publish
+    pub fn publish(&mut self) -> usize {
+        self.notifier.publish()
+    }
+
+    /// Consumes the observed grid returning the grid
+    pub fn into_inner(self) -> BitPackedGrid {
+        self.grid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observedgrid_publish() {
+        let mut grid = ObservedGrid::new(BitPackedGrid::new_from_string("..\n.@\n".to_string()));
+        let subscription = grid.subscribe();
+        grid.set_bit_value((0, 0), true);
+        grid.set_bit_value((1, 1), true);
+        grid.set_bit_value((0, 1), false);
+        assert_eq!(grid.publish(), 2);
+        assert_eq!(subscription.changes(), vec![(1, 1), (0, 1)]);
+        assert_eq!(grid.grid().print_cells(None), "..\n@.\n");
+    }
+}
@@ -1,16 +1,29 @@
+use std::{collections::VecDeque, error::Error, fmt, io};
+
 use super::bitpackedgrid::BitPackedGrid;
-use super::{create_map_from_string, plot_cells, print_cells};
+use super::grid::Grid;
+use super::{create_map_from_string, print_cells};
+#[cfg(feature = "viz")]
+use super::plot_cells;
+use crate::fov::fieldofvision::shadowcasting;
 use crate::util::matrix::{convolve2d, ConvResolve, gaussian_kernal, matrix_overlay};
 use crate::util::filter::KalmanNode;
+use crate::util::history::{ObservationHistory, ObservationUpdate};
+use crate::util::notify::{ChangeNotifier, Subscription};
+use crate::util::determinism::with_rng;
+use rand::Rng;
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SampleGrid {
     /// The sampling grid which determines the probability of a cell being occupied.
     /// It has a value between 0.0 and 1.0
-    pub sample_grid: Vec<Vec<KalmanNode>>,
+    sample_grid: Vec<Vec<KalmanNode>>,
 
-    /// The bitpacked grid which represents sampled cells
+    /// The bitpacked grid which represents sampled cells, a cell whose belief
+    /// is 0.0 is never free
     /// TODO: This cam be simplified to a smaller sub grid
-    pub gridmap: BitPackedGrid,
+    gridmap: BitPackedGrid,
 
     /// The real values of the grid
     pub ground_truth: BitPackedGrid,
@@ -19,16 +32,167 @@ pub struct SampleGrid {
     // can be removed for reduced space
     pub width: usize,
     pub height: usize,
+
+    /// An optional record of every observation, used for retrospective smoothing
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub observation_history: Option<ObservationHistory>,
+
+    /// An optional publisher of the cells whose belief was updated
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub change_notifier: Option<ChangeNotifier<(usize, usize)>>,
+
+    /// An optional record of the cells whose belief changed since they were
+    /// last sampled, so only those are resampled by `sample_dirty`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    dirty_cells: Option<ChangeNotifier<(usize, usize)>>,
+
+    /// An optional terrain class of each cell that scales its belief when sampled
+    terrain: Option<TerrainWeights>,
+
+    /// The covariances of the Kalman filters and how beliefs decay over time
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub config: BeliefConfig,
+}
+
+/// The parameters of the Kalman filter of every cell of a sampling grid
+/// ## Fields
+/// * `covariance` - The covariance of a cell that has never been observed
+/// * `sense_covariance` - The measurement covariance of sensing at the edge of
+///   the sensing radius, see `SampleGrid::sense`
+/// * `process_noise` - The variance added to every belief per unit of time by
+///   `SampleGrid::predict`, 0.0 for a static world
+/// * `decay_rate` - The rate every belief returns to the prior by
+///   `SampleGrid::decay`, 0.0 for a static world
+/// * `prior_belief` - The belief that cells decay towards
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BeliefConfig {
+    pub covariance: f32,
+    pub sense_covariance: f32,
+    pub process_noise: f32,
+    pub decay_rate: f32,
+    pub prior_belief: f32,
+}
+
+impl Default for BeliefConfig {
+    fn default() -> Self {
+        BeliefConfig {
+            covariance: SampleGrid::COVARIANCE,
+            sense_covariance: SampleGrid::SENSE_COVARIANCE,
+            process_noise: 0.0,
+            decay_rate: 0.0,
+            prior_belief: 0.5,
+        }
+    }
+}
+
+/// The terrain class of every cell and how traversable each class is for the
+/// agent sampling the grid, such as water being passable for boats only
+/// ## Fields
+/// * `classes` - The class of each cell, an index into `weights`
+/// * `weights` - The factor in [0, 1] scaling the belief of cells of each
+///   class when sampled, where 0.0 is impassable and 1.0 leaves the belief
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TerrainWeights {
+    pub classes: Grid<usize>,
+    pub weights: Vec<f32>,
+}
+
+/// The reason a sampling grid or a belief is invalid
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SampleGridError {
+    /// The grid has no cells
+    Empty,
+    /// A row of the grid, indexed by `x`, has a different length to the first
+    RaggedRows { row: usize, expected: usize, found: usize },
+    /// The ground truth has a different size to the grid
+    SizeMismatch { expected: (usize, usize), found: (usize, usize) },
+    /// A probability is not a number in [0, 1]
+    InvalidProbability { cell: (usize, usize), value: f32 },
+    /// A cell is outside the grid
+    OutOfBounds { cell: (usize, usize) },
+    /// A cell has a terrain class without a weight
+    UnknownClass { cell: (usize, usize), class: usize },
+    /// The weight of a terrain class is not a number in [0, 1]
+    InvalidWeight { class: usize, value: f32 },
+}
+
+impl fmt::Display for SampleGridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SampleGridError::Empty => write!(f, "Sampling grid has no cells"),
+            SampleGridError::RaggedRows { row, expected, found } => {
+                write!(f, "Row {} has {} cells but the first row has {}", row, found, expected)
+            }
+            SampleGridError::SizeMismatch { expected, found } => {
+                write!(f, "Ground truth is {:?} but the sampling grid is {:?}", found, expected)
+            }
+            SampleGridError::InvalidProbability { cell, value } => {
+                write!(f, "Probability {} of cell {:?} is not in [0, 1]", value, cell)
+            }
+            SampleGridError::OutOfBounds { cell } => write!(f, "Cell {:?} is outside the sampling grid", cell),
+            SampleGridError::UnknownClass { cell, class } => {
+                write!(f, "Cell {:?} has terrain class {} without a weight", cell, class)
+            }
+            SampleGridError::InvalidWeight { class, value } => {
+                write!(f, "Weight {} of terrain class {} is not in [0, 1]", value, class)
+            }
+        }
+    }
+}
+
+impl Error for SampleGridError {}
+
+/// How the belief of unknown cells is interpolated from sparse known cells
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    /// Weights each known cell by the inverse of its distance raised to a power
+    InverseDistance { power: f32 },
+    /// Weights each known cell by a squared exponential kernel with a length scale
+    Kernel { length_scale: f32 },
 }
 
 impl SampleGrid {
-    /// The default covariance of the Kalman filter
+    /// The default covariance of the Kalman filter, see `BeliefConfig`
     const COVARIANCE: f32 = 1.0;
 
-    /// Creates a new sampling grid from a sampling grid and a ground truth grid
+    /// The default measurement covariance of sensing at the edge of the
+    /// sensing radius, see `BeliefConfig`
+    pub const SENSE_COVARIANCE: f32 = 0.2;
+
+    /// Creates a new sampling grid from a sampling grid and a ground truth grid,
+    /// clamping probabilities to [0, 1]
+    /// ## Panics
+    /// If the grid is empty, has rows of different lengths, contains a NaN or
+    /// does not match the size of the ground truth
     pub fn new_from_grid(grid: Vec<Vec<f32>>, ground_truth: BitPackedGrid) -> Self {
+        let grid = grid.into_iter()
+            .map(|row| row.into_iter().map(|p| if p.is_nan() { p } else { p.clamp(0.0, 1.0) }).collect())
+            .collect();
+        SampleGrid::try_new_from_grid(grid, ground_truth).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Creates a new sampling grid from a sampling grid indexed as `grid[x][y]`
+    /// and a ground truth grid, rejecting malformed grids and probabilities
+    pub fn try_new_from_grid(grid: Vec<Vec<f32>>, ground_truth: BitPackedGrid) -> Result<Self, SampleGridError> {
         let width = grid.len();
-        let height = grid[0].len();
+        let height = grid.first().map_or(0, |row| row.len());
+        if height == 0 {
+            return Err(SampleGridError::Empty);
+        }
+        for (x, row) in grid.iter().enumerate() {
+            if row.len() != height {
+                return Err(SampleGridError::RaggedRows { row: x, expected: height, found: row.len() });
+            }
+            if let Some((y, value)) = row.iter().enumerate().find(|(_, p)| !(0.0..=1.0).contains(*p)) {
+                return Err(SampleGridError::InvalidProbability { cell: (x, y), value: *value });
+            }
+        }
+        let found = (ground_truth.original_width, ground_truth.original_height);
+        if found != (width, height) {
+            return Err(SampleGridError::SizeMismatch { expected: (width, height), found });
+        }
         let sample_grid = grid.into_iter()
             .map(|row| row.into_iter().map(|state| KalmanNode {
                 state,
@@ -41,9 +205,14 @@ impl SampleGrid {
             ground_truth,
             width,
             height,
+            observation_history: None,
+            change_notifier: None,
+            dirty_cells: None,
+            terrain: None,
+            config: BeliefConfig::default(),
         };
         grid.init_gridmap();
-        grid
+        Ok(grid)
     }
 
     /// Creates a new sampling grid with a given size
@@ -58,6 +227,11 @@ impl SampleGrid {
             ground_truth: BitPackedGrid::new(width, height),
             width,
             height,
+            observation_history: None,
+            change_notifier: None,
+            dirty_cells: None,
+            terrain: None,
+            config: BeliefConfig::default(),
         }
     }
 
@@ -77,12 +251,112 @@ impl SampleGrid {
         SampleGrid::new_from_string(s)
     }
     
+    /// Encodes the beliefs in binary for checkpoints, the width and height as
+    /// little endian `u32` followed by the state and covariance of every cell
+    /// as little endian `f32` in `[x][y]` order, then the ground truth and the
+    /// gridmap as `BitPackedGrid::to_bytes`. Terrain, observation history and
+    /// subscribers are not encoded.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + 8 * self.width * self.height);
+        bytes.extend((self.width as u32).to_le_bytes());
+        bytes.extend((self.height as u32).to_le_bytes());
+        for node in self.sample_grid.iter().flatten() {
+            bytes.extend(node.state.to_le_bytes());
+            bytes.extend(node.covariance.to_le_bytes());
+        }
+        bytes.extend(self.ground_truth.to_bytes());
+        bytes.extend(self.gridmap.to_bytes());
+        bytes
+    }
+
+    /// Decodes beliefs from their binary encoding
+    /// ## Returns
+    /// The sampling grid, or an error if the bytes are truncated or malformed
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let word = |i: usize| bytes.get(i..i + 4).map(|b| <[u8; 4]>::try_from(b).unwrap()).ok_or_else(|| invalid("Truncated belief bytes"));
+        let (width, height) = (u32::from_le_bytes(word(0)?) as usize, u32::from_le_bytes(word(4)?) as usize);
+        let mut sample_grid = Vec::with_capacity(width);
+        for x in 0..width {
+            let column = (0..height).map(|y| {
+                let offset = 8 + 8 * (x * height + y);
+                Ok(KalmanNode { state: f32::from_le_bytes(word(offset)?), covariance: f32::from_le_bytes(word(offset + 4)?) })
+            });
+            sample_grid.push(column.collect::<io::Result<Vec<_>>>()?);
+        }
+        let offset = 8 + 8 * width * height;
+        let (ground_truth, read) = BitPackedGrid::from_bytes(&bytes[offset..])?;
+        let (gridmap, _) = BitPackedGrid::from_bytes(&bytes[offset + read..])?;
+        if (ground_truth.original_width, ground_truth.original_height) != (width, height)
+            || (gridmap.original_width, gridmap.original_height) != (width, height)
+        {
+            return Err(invalid("Grids do not match the size of the beliefs"));
+        }
+        Ok(SampleGrid {
+            sample_grid,
+            gridmap,
+            ground_truth,
+            width,
+            height,
+            observation_history: None,
+            change_notifier: None,
+            dirty_cells: None,
+            terrain: None,
+            config: BeliefConfig::default(),
+        })
+    }
+
+    /// Creates a sampling grid whose prior belief is interpolated from a sparse
+    /// set of cells with known probabilities of being free. The state of each
+    /// cell is the weighted mean of the known cells and its covariance shrinks
+    /// from the default towards zero as it nears a known cell, as in the
+    /// posterior of a gaussian process. Without known cells every cell is 0.5.
+    /// ## Arguments
+    /// * `ground_truth` - The real values of the grid
+    /// * `known` - The known cells and their probability of being free
+    /// * `interpolation` - The weighting of known cells by distance
+    pub fn new_from_known(ground_truth: BitPackedGrid, known: &[((usize, usize), f32)], interpolation: Interpolation) -> Self {
+        let (width, height) = (ground_truth.original_width, ground_truth.original_height);
+        let mut grid = SampleGrid::new_with_size(width, height);
+        grid.ground_truth = ground_truth;
+        for x in 0..width {
+            for y in 0..height {
+                let distances = known.iter()
+                    .map(|((kx, ky), p)| (((x as f32 - *kx as f32).powi(2) + (y as f32 - *ky as f32).powi(2)).sqrt(), *p))
+                    .collect::<Vec<_>>();
+                let node = &mut grid.sample_grid[x][y];
+                if let Some(&(_, p)) = distances.iter().find(|(d, _)| *d == 0.0) {
+                    *node = KalmanNode { state: p, covariance: 0.0 };
+                    continue;
+                }
+                let weight = |d: f32| match interpolation {
+                    Interpolation::InverseDistance { power } => d.powf(-power),
+                    Interpolation::Kernel { length_scale } => (-d * d / (2.0 * length_scale * length_scale)).exp(),
+                };
+                let total = distances.iter().map(|(d, _)| weight(*d)).sum::<f32>();
+                node.state = if total > 0.0 {
+                    distances.iter().map(|(d, p)| weight(*d) * p).sum::<f32>() / total
+                } else {
+                    0.5
+                };
+                let nearest = distances.iter().map(|(d, _)| *d).fold(f32::INFINITY, f32::min);
+                let closeness = match interpolation {
+                    Interpolation::InverseDistance { .. } => 1.0 / (1.0 + nearest),
+                    Interpolation::Kernel { .. } => weight(nearest),
+                };
+                node.covariance = grid.config.covariance * (1.0 - closeness);
+            }
+        }
+        grid.init_gridmap();
+        grid
+    }
+
     /// Initializes an area of the bitfield from the sampling grid values, where
     /// 0.0 indicates a guaranteed obstacles and (0,1) indicates a probability
     pub fn init_gridmap_area(&mut self, (x, y): (usize, usize), width: usize, height: usize) {
         for x in x..x + width {
             for y in y..y + height {
-                self.gridmap.set_bit_value((x, y), self.sample_grid[x][y].state != 0.0);
+                self.gridmap.set_bit_value((x, y), self.sample_probability((x, y)) != 0.0);
             }
         }
     }
@@ -90,7 +364,7 @@ impl SampleGrid {
     pub fn init_gridmap_nearest(&mut self) {
         for x in 0..self.width {
             for y in 0..self.height {
-                self.gridmap.set_bit_value((x, y), self.sample_grid[x][y].state > 0.5);
+                self.gridmap.set_bit_value((x, y), self.sample_probability((x, y)) > 0.5);
             }
         }
     }
@@ -119,6 +393,114 @@ impl SampleGrid {
         }
     }
 
+    /// Reallocates the grid with a new size, moving each old cell and its
+    /// observations to its new position or dropping them. Cells with no old
+    /// cell are obstacles with the covariance of the configuration.
+    fn remap(&mut self, width: usize, height: usize, moved: impl Fn((usize, usize)) -> Option<(usize, usize)>) {
+        let moved = |n| moved(n).filter(|&(x, y)| x < width && y < height);
+        let node = KalmanNode { state: 0.0, covariance: self.config.covariance };
+        let mut sample_grid = vec![vec![node; height]; width];
+        for (x, column) in std::mem::take(&mut self.sample_grid).into_iter().enumerate() {
+            for (y, node) in column.into_iter().enumerate() {
+                if let Some((nx, ny)) = moved((x, y)) {
+                    sample_grid[nx][ny] = node;
+                }
+            }
+        }
+        self.sample_grid = sample_grid;
+        self.gridmap.remap(width, height, moved);
+        self.ground_truth.remap(width, height, moved);
+        if let Some(history) = self.observation_history.as_mut() {
+            history.priors = std::mem::take(&mut history.priors).into_iter()
+                .filter_map(|(n, prior)| Some((moved(n)?, prior)))
+                .collect();
+            history.observations = std::mem::take(&mut history.observations).into_iter()
+                .filter_map(|(n, observations)| Some((moved(n)?, observations)))
+                .collect();
+            history.updates = std::mem::take(&mut history.updates).into_iter()
+                .filter_map(|update| Some(ObservationUpdate { node: moved(update.node)?, ..update }))
+                .collect();
+        }
+        if let Some(notifier) = self.change_notifier.as_mut() {
+            notifier.remap(moved);
+        }
+        if let Some(dirty) = self.dirty_cells.as_mut() {
+            dirty.remap(moved);
+        }
+        if let Some(terrain) = self.terrain.as_mut() {
+            let mut classes = Grid::new(width, height, 0);
+            for (n, class) in terrain.classes.iter() {
+                if let Some(m) = moved(n) {
+                    classes[m] = *class;
+                }
+            }
+            terrain.classes = classes;
+        }
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Resizes the grid, keeping the cells within the new size. Added cells are obstacles.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.remap(width, height, Some);
+    }
+
+    /// Inserts rows of obstacles before row `at`, shifting the later rows down
+    pub fn insert_rows(&mut self, at: usize, count: usize) {
+        self.remap(self.width, self.height + count, |(x, y)| {
+            Some((x, if y >= at { y + count } else { y }))
+        });
+    }
+
+    /// Inserts columns of obstacles before column `at`, shifting the later columns right
+    pub fn insert_cols(&mut self, at: usize, count: usize) {
+        self.remap(self.width + count, self.height, |(x, y)| {
+            Some((if x >= at { x + count } else { x }, y))
+        });
+    }
+
+    /// Grows the grid so that a cell is within its bounds
+    /// ## Returns
+    /// Whether the grid was resized
+    pub fn extend_to_fit(&mut self, (x, y): (usize, usize)) -> bool {
+        if self.bound_check((x, y)) {
+            return false;
+        }
+        self.resize(self.width.max(x + 1), self.height.max(y + 1));
+        true
+    }
+
+    /// Downsamples the belief into a coarse grid where each cell covers a square
+    /// block of `factor` cells, for hierarchical planning under uncertainty.
+    /// The belief of a coarse cell matches the moments of the beliefs it covers,
+    /// so its state is their mean and its covariance is their mean covariance
+    /// plus the variance of their states, keeping the uncertainty of blocks that
+    /// mix free and blocked cells. A coarse cell of the ground truth is free
+    /// when any cell it covers is free, as in a grid pyramid. Terrain classes
+    /// are not carried over to the coarse grid.
+    pub fn downsample(&self, factor: usize) -> SampleGrid {
+        assert!(factor > 0, "Downsampling requires a factor");
+        let (width, height) = (self.width.div_ceil(factor), self.height.div_ceil(factor));
+        let mut coarse = SampleGrid::new_with_size(width, height).with_config(self.config);
+        for cx in 0..width {
+            for cy in 0..height {
+                let block = (cx * factor..((cx + 1) * factor).min(self.width))
+                    .flat_map(|x| (cy * factor..((cy + 1) * factor).min(self.height)).map(move |y| (x, y)))
+                    .collect::<Vec<_>>();
+                let n = block.len() as f32;
+                let state = block.iter().map(|&(x, y)| self.sample_grid[x][y].state).sum::<f32>() / n;
+                let covariance = block.iter()
+                    .map(|&(x, y)| self.sample_grid[x][y].covariance + (self.sample_grid[x][y].state - state).powi(2))
+                    .sum::<f32>() / n;
+                coarse.sample_grid[cx][cy] = KalmanNode { state, covariance };
+                let free = block.iter().any(|n| self.ground_truth.get_bit_value(*n));
+                coarse.ground_truth.set_bit_value((cx, cy), free);
+            }
+        }
+        coarse.init_gridmap();
+        coarse
+    }
+
     /// Blurs the sampling grid with a gaussian kernal.
     /// Note that this operation sets all covariances to 1.0
     pub fn blur_samplegrid(&mut self, size: usize, sigma: f32) {
@@ -128,19 +510,154 @@ impl SampleGrid {
             &kernal,
             ConvResolve::Nearest,
         );
+        self.mark_all_dirty();
     }
 
-    /// Samples a cell with a given chance
+    /// Blurs the sampling grid with a gaussian kernal only within a region,
+    /// where the free cells of the mask mark the region. Cells outside the
+    /// region are unchanged but still blur into the cells inside it.
+    pub fn blur_samplegrid_masked(&mut self, size: usize, sigma: f32, mask: &BitPackedGrid) {
+        let kernal = gaussian_kernal(size, sigma);
+        let blurred = convolve2d(&self.sample_grid, &kernal, ConvResolve::Nearest);
+        for (x, column) in blurred.into_iter().enumerate() {
+            for (y, node) in column.into_iter().enumerate() {
+                if mask.get_bit_value((x, y)) {
+                    self.sample_grid[x][y] = node;
+                    if let Some(dirty) = &mut self.dirty_cells {
+                        dirty.record((x, y));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Blurs the sampling grid with a gaussian kernal that does not cross
+    /// walls, cells believed to be blocked with certainty. Each cell is a
+    /// weighted average of the cells in the kernal it reaches by 4-connected
+    /// moves without leaving the kernal or entering a wall, with the weights
+    /// renormalised over those cells. Walls are unchanged, other covariances
+    /// are set to 1.0 as with `blur_samplegrid`.
+    pub fn blur_samplegrid_walled(&mut self, size: usize, sigma: f32) {
+        let kernal = gaussian_kernal(size, sigma);
+        let center = size / 2;
+        let wall = |n: (usize, usize)| self.sample_grid[n.0][n.1].state == 0.0;
+        let mut blurred = self.sample_grid.clone();
+        for x in 0..self.width {
+            for y in 0..self.height {
+                if wall((x, y)) {
+                    continue;
+                }
+                let in_kernal = |(i, j): (usize, usize)| {
+                    i + center >= x && j + center >= y && i + center - x < size && j + center - y < size
+                };
+                let mut reached = vec![(x, y)];
+                let mut queue = VecDeque::from([(x, y)]);
+                while let Some((i, j)) = queue.pop_front() {
+                    let neighbours = [(i.wrapping_sub(1), j), (i + 1, j), (i, j.wrapping_sub(1)), (i, j + 1)];
+                    for n in neighbours {
+                        if n.0 < self.width && n.1 < self.height && in_kernal(n) && !wall(n) && !reached.contains(&n) {
+                            reached.push(n);
+                            queue.push_back(n);
+                        }
+                    }
+                }
+                let weight = |(i, j): (usize, usize)| kernal[i + center - x][j + center - y];
+                let total = reached.iter().map(|n| weight(*n)).sum::<f32>();
+                let state = reached.iter().map(|n| weight(*n) * self.sample_grid[n.0][n.1].state).sum::<f32>();
+                blurred[x][y] = KalmanNode { state: state / total, covariance: self.config.covariance };
+            }
+        }
+        self.sample_grid = blurred;
+        self.mark_all_dirty();
+    }
+
+    /// Samples the cells of a region, the free cells of the mask
+    pub fn sample_masked(&mut self, mask: &BitPackedGrid) {
+        with_rng(|rng| self.sample_masked_with(mask, rng))
+    }
+
+    /// Samples the cells of a region like `sample_masked`, drawing from a generator
+    pub fn sample_masked_with(&mut self, mask: &BitPackedGrid, rng: &mut impl Rng) {
+        for x in 0..self.width {
+            for y in 0..self.height {
+                if mask.get_bit_value((x, y)) {
+                    self.sample_with((x, y), rng);
+                }
+            }
+        }
+    }
+
+    /// Gives every cell a terrain class that scales its belief when sampled
+    /// ## Arguments
+    /// * `classes` - The class of each cell, aligned with the grid
+    /// * `weights` - The factor in [0, 1] scaling the belief of each class
+    pub fn set_terrain(&mut self, classes: Grid<usize>, weights: Vec<f32>) -> Result<(), SampleGridError> {
+        let found = (classes.width, classes.height);
+        if found != (self.width, self.height) {
+            return Err(SampleGridError::SizeMismatch { expected: (self.width, self.height), found });
+        }
+        if let Some((cell, class)) = classes.iter().find(|(_, c)| **c >= weights.len()) {
+            return Err(SampleGridError::UnknownClass { cell, class: *class });
+        }
+        self.terrain = Some(TerrainWeights { classes, weights: Vec::new() });
+        self.set_class_weights(weights)
+    }
+
+    /// Changes the weight of each terrain class, such as to sample the grid for
+    /// another kind of agent
+    pub fn set_class_weights(&mut self, weights: Vec<f32>) -> Result<(), SampleGridError> {
+        if let Some((class, value)) = weights.iter().enumerate().find(|(_, w)| !(0.0..=1.0).contains(*w)) {
+            return Err(SampleGridError::InvalidWeight { class, value: *value });
+        }
+        let terrain = self.terrain.as_mut().expect("Class weights require terrain classes");
+        if let Some((cell, class)) = terrain.classes.iter().find(|(_, c)| **c >= weights.len()) {
+            return Err(SampleGridError::UnknownClass { cell, class: *class });
+        }
+        terrain.weights = weights;
+        Ok(())
+    }
+
+    /// The terrain classes of the cells and their weights, if any
+    pub fn terrain(&self) -> Option<&TerrainWeights> {
+        self.terrain.as_ref()
+    }
+
+    /// Removes the terrain classes, so cells are sampled by their belief alone
+    pub fn clear_terrain(&mut self) {
+        self.terrain = None;
+    }
+
+    /// The chance of a cell being sampled as free, its belief scaled by the
+    /// weight of its terrain class
+    pub fn sample_probability(&self, (x, y): (usize, usize)) -> f32 {
+        let weight = self.terrain.as_ref().map_or(1.0, |t| t.weights[t.classes[(x, y)]]);
+        self.sample_grid[x][y].state * weight
+    }
+
+    /// Samples a cell with a given chance, drawing from the generator of the
+    /// thread, see `util::determinism`
     pub fn sample(&mut self, (x, y): (usize, usize)) {
-        let value = self.sample_grid[x][y].state != 0.0 && rand::random::<f32>() < self.sample_grid[x][y].state;
+        with_rng(|rng| self.sample_with((x, y), rng))
+    }
+
+    /// Samples a cell with a given chance, drawing from a generator so that
+    /// the same seed gives the same samples
+    pub fn sample_with(&mut self, (x, y): (usize, usize), rng: &mut impl Rng) {
+        let probability = self.sample_probability((x, y));
+        let value = probability != 0.0 && rng.gen::<f32>() < probability;
         self.gridmap.set_bit_value((x, y), value);
     }
 
     /// Samples an area of the grid
     pub fn sample_area(&mut self, (x, y): (usize, usize), width: usize, height: usize) {
+        with_rng(|rng| self.sample_area_with((x, y), width, height, rng))
+    }
+
+    /// Samples an area of the grid, drawing from a generator
+    pub fn sample_area_with(&mut self, (x, y): (usize, usize), width: usize, height: usize, rng: &mut impl Rng) {
         for x in x..x + width {
             for y in y..y + height {
-                self.sample((x, y));
+                self.sample_with((x, y), rng);
             }
         }
     }
@@ -157,13 +674,58 @@ impl SampleGrid {
 
     /// Samples a cell with a given chance
     pub fn sample_radius(&mut self, (x, y): (usize, usize), radius: usize) {
+        with_rng(|rng| self.sample_radius_with((x, y), radius, rng))
+    }
+
+    /// Samples the cells within a radius of a cell, drawing from a generator
+    pub fn sample_radius_with(&mut self, (x, y): (usize, usize), radius: usize, rng: &mut impl Rng) {
         let (n, width, height) = self.radius_calc((x, y), radius);
-        self.sample_area(n, width, height);
+        self.sample_area_with(n, width, height, rng);
     }
 
     /// Samples all cells in the grid
     pub fn sample_all(&mut self) {
-        self.sample_area((0, 0), self.width, self.height)
+        with_rng(|rng| self.sample_all_with(rng))
+    }
+
+    /// Samples all cells in the grid, drawing from a generator. Every cell is
+    /// then clean, see `sample_dirty`.
+    pub fn sample_all_with(&mut self, rng: &mut impl Rng) {
+        self.sample_area_with((0, 0), self.width, self.height, rng);
+        if let Some(dirty) = &mut self.dirty_cells {
+            dirty.take_pending();
+        }
+    }
+
+    /// Enables or disables tracking the cells whose belief changed since they
+    /// were last sampled. Tracking starts with every cell clean.
+    pub fn track_dirty(&mut self, enabled: bool) {
+        self.dirty_cells = if enabled { Some(self.dirty_cells.take().unwrap_or_default()) } else { None };
+    }
+
+    /// The cells whose belief changed since they were last sampled, in the
+    /// order they changed, empty when tracking is disabled
+    pub fn dirty_cells(&self) -> &[(usize, usize)] {
+        self.dirty_cells.as_ref().map_or(&[], |dirty| &dirty.pending)
+    }
+
+    /// Resamples only the cells whose belief changed since they were last
+    /// sampled, leaving the rest of the gridmap as it was. This is much
+    /// cheaper than `sample_all` on large grids where few beliefs change
+    /// between samples, and requires `track_dirty`.
+    /// ## Returns
+    /// The number of cells resampled
+    pub fn sample_dirty(&mut self) -> usize {
+        with_rng(|rng| self.sample_dirty_with(rng))
+    }
+
+    /// Resamples the changed cells like `sample_dirty`, drawing from a generator
+    pub fn sample_dirty_with(&mut self, rng: &mut impl Rng) -> usize {
+        let dirty = self.dirty_cells.as_mut().map(|d| d.take_pending()).unwrap_or_default();
+        for cell in &dirty {
+            self.sample_with(*cell, rng);
+        }
+        dirty.len()
     }
 
     /// Samples a cell with a given chance
@@ -172,7 +734,187 @@ impl SampleGrid {
     /// * `measurement_covariance` - The variance of the measurement where 0.0 is a perfect measurement
     pub fn update_node(&mut self, (x, y): (usize, usize), measurement_covariance: f32) {
         let measurement = self.ground_truth.get_bit_value((x, y)) as u8 as f32;
+        if let Some(history) = &mut self.observation_history {
+            history.record((x, y), &self.sample_grid[x][y], measurement, measurement_covariance);
+        }
         self.sample_grid[x][y].update(measurement, measurement_covariance);
+        self.refresh((x, y));
+    }
+
+    /// Adds a subscriber to the cells whose belief is updated, enabling change
+    /// notifications if they are not already enabled
+    pub fn subscribe(&mut self) -> Subscription<(usize, usize)> {
+        self.change_notifier.get_or_insert_with(ChangeNotifier::default).subscribe()
+    }
+
+    /// Publishes the cells whose belief was updated since the last publish
+    pub fn publish_changes(&mut self) -> usize {
+        self.change_notifier.as_mut().map_or(0, |n| n.publish())
+    }
+
+    /// Fuses an external probability raster, such as another sensor or a learned
+    /// predictor, into the belief of every cell
+    /// ## Arguments
+    /// * `matrix` - The probability of each cell being free, indexed as `matrix[x][y]`
+    /// * `covariance` - The covariance of the raster where 0.0 is a perfect measurement
+    pub fn fuse_prior(&mut self, matrix: &[Vec<f32>], covariance: f32) {
+        assert!(matrix.len() == self.width && matrix.iter().all(|row| row.len() == self.height),
+            "Prior must match the size of the sampling grid");
+        for (x, prior) in matrix.iter().enumerate() {
+            for (y, measurement) in prior.iter().enumerate() {
+                self.update_belief((x, y), *measurement, covariance);
+            }
+        }
+    }
+
+    /// Sets the parameters of the Kalman filters, giving the cells that have
+    /// never been observed the covariance of the new configuration
+    pub fn with_config(mut self, config: BeliefConfig) -> Self {
+        for node in self.sample_grid.iter_mut().flatten() {
+            if node.covariance == self.config.covariance {
+                node.covariance = config.covariance;
+            }
+        }
+        self.config = config;
+        self
+    }
+
+    /// Grows the covariance of every belief by the process noise over some
+    /// time, so that in a dynamic world old observations are trusted less and
+    /// new observations move beliefs further. Covariances never exceed that of
+    /// a cell that has never been observed, and beliefs are left unchanged.
+    pub fn predict(&mut self, dt: f32) {
+        let BeliefConfig { covariance, process_noise, .. } = self.config;
+        for node in self.sample_grid.iter_mut().flatten().filter(|n| n.covariance < covariance) {
+            node.predict(process_noise, dt);
+            node.covariance = node.covariance.min(covariance);
+        }
+    }
+
+    /// Decays every belief and covariance towards the prior of the
+    /// configuration over some time, so cells that are not observed again
+    /// return to being unknown
+    pub fn decay(&mut self, dt: f32) {
+        let BeliefConfig { covariance, decay_rate, prior_belief, .. } = self.config;
+        if decay_rate == 0.0 {
+            return;
+        }
+        let prior = KalmanNode { state: prior_belief, covariance };
+        for x in 0..self.width {
+            for y in 0..self.height {
+                self.sample_grid[x][y].decay(&prior, decay_rate, dt);
+                self.refresh((x, y));
+            }
+        }
+    }
+
+    /// The belief and covariance of a cell
+    pub fn node(&self, (x, y): (usize, usize)) -> &KalmanNode {
+        &self.sample_grid[x][y]
+    }
+
+    /// The probability of a cell being free
+    pub fn belief(&self, (x, y): (usize, usize)) -> f32 {
+        self.sample_grid[x][y].state
+    }
+
+    /// The covariance of the belief of a cell
+    pub fn covariance(&self, (x, y): (usize, usize)) -> f32 {
+        self.sample_grid[x][y].covariance
+    }
+
+    /// The belief of every cell, indexed as `beliefs[x][y]`
+    pub fn beliefs(&self) -> Vec<Vec<f32>> {
+        self.sample_grid.iter().map(|row| row.iter().map(|n| n.state).collect()).collect()
+    }
+
+    /// The covariance of every cell, indexed as `covariances[x][y]`
+    pub fn covariances(&self) -> Vec<Vec<f32>> {
+        self.sample_grid.iter().map(|row| row.iter().map(|n| n.covariance).collect()).collect()
+    }
+
+    /// A copy of the belief and covariance of every cell, indexed as `nodes[x][y]`
+    pub fn to_nodes(&self) -> Vec<Vec<KalmanNode>> {
+        self.sample_grid.clone()
+    }
+
+    /// The grid of sampled cells
+    pub fn gridmap(&self) -> &BitPackedGrid {
+        &self.gridmap
+    }
+
+    /// Marks every cell of the gridmap as an obstacle, such as before sampling an area
+    pub fn clear_gridmap(&mut self) {
+        self.gridmap = BitPackedGrid::new(self.width, self.height);
+    }
+
+    /// Marks every cell of the gridmap as free, such as before revealing areas
+    /// under the freespace assumption
+    pub fn fill_gridmap(&mut self) {
+        for x in 0..self.width {
+            for y in 0..self.height {
+                self.gridmap.set_bit_value((x, y), true);
+            }
+        }
+    }
+
+    /// Sets the probability of a cell being free, clamped to [0, 1]
+    pub fn set_belief(&mut self, (x, y): (usize, usize), state: f32) {
+        self.sample_grid[x][y].state = state;
+        self.refresh((x, y));
+    }
+
+    /// Sets the probability of a cell being free, rejecting cells outside the
+    /// grid and probabilities outside [0, 1]
+    pub fn try_set_belief(&mut self, cell: (usize, usize), state: f32) -> Result<(), SampleGridError> {
+        if !self.bound_check(cell) {
+            return Err(SampleGridError::OutOfBounds { cell });
+        }
+        if !(0.0..=1.0).contains(&state) {
+            return Err(SampleGridError::InvalidProbability { cell, value: state });
+        }
+        self.set_belief(cell, state);
+        Ok(())
+    }
+
+    /// Sets the belief and covariance of a cell, clamping the belief to [0, 1]
+    pub fn set_node(&mut self, (x, y): (usize, usize), node: KalmanNode) {
+        self.sample_grid[x][y] = node;
+        self.refresh((x, y));
+    }
+
+    /// Fuses a measurement of a cell into its belief, such as the belief of
+    /// another map, without observing the ground truth
+    pub fn update_belief(&mut self, (x, y): (usize, usize), measurement: f32, covariance: f32) {
+        self.sample_grid[x][y].update(measurement, covariance);
+        self.refresh((x, y));
+    }
+
+    /// Restores the invariants of a cell after its belief changed
+    fn refresh(&mut self, (x, y): (usize, usize)) {
+        let node = &mut self.sample_grid[x][y];
+        assert!(!node.state.is_nan(), "Belief of cell {:?} is not a number", (x, y));
+        node.state = node.state.clamp(0.0, 1.0);
+        if node.state == 0.0 {
+            self.gridmap.set_bit_value((x, y), false);
+        }
+        if let Some(notifier) = &mut self.change_notifier {
+            notifier.record((x, y));
+        }
+        if let Some(dirty) = &mut self.dirty_cells {
+            dirty.record((x, y));
+        }
+    }
+
+    /// Marks every cell as changed since it was last sampled, such as after a blur
+    fn mark_all_dirty(&mut self) {
+        if let Some(dirty) = &mut self.dirty_cells {
+            for x in 0..self.width {
+                for y in 0..self.height {
+                    dirty.record((x, y));
+                }
+            }
+        }
     }
 
     /// Updates the kernal based upon a gaussian kernal with a radius.
@@ -189,6 +931,46 @@ impl SampleGrid {
         }
     }
 
+    /// Senses every cell visible from a cell within a radius, found by
+    /// shadowcasting against the ground truth, with a measurement covariance
+    /// growing from 0.0 at the cell to the sense covariance of the
+    /// configuration at the radius
+    /// ## Returns
+    /// The cells whose belief changed, such as for an incremental planner
+    pub fn sense(&mut self, (x, y): (usize, usize), radius: usize) -> Vec<(usize, usize)> {
+        let radius_squared = radius.max(1).pow(2) as f32;
+        let sense_covariance = self.config.sense_covariance;
+        self.sense_with((x, y), radius, |distance_squared| sense_covariance * distance_squared / radius_squared)
+    }
+
+    /// Senses every cell visible from a cell within a radius like `sense`
+    /// ## Arguments
+    /// * `covariance` - The measurement covariance of a cell at a squared distance
+    pub fn sense_with(&mut self, (x, y): (usize, usize), radius: usize, covariance: impl Fn(f32) -> f32) -> Vec<(usize, usize)> {
+        let visible = shadowcasting(
+            (x, y),
+            radius,
+            |vx, vy| self.bound_check((vx, vy)),
+            |vx, vy| self.ground_truth.get_bit_value((vx, vy)),
+        );
+        let mut changed = Vec::new();
+        for ((vx, vy), _) in visible {
+            let before = self.sample_grid[vx][vy].state;
+            let distance_squared = (vx.abs_diff(x).pow(2) + vy.abs_diff(y).pow(2)) as f32;
+            self.update_node((vx, vy), covariance(distance_squared));
+            if self.sample_grid[vx][vy].state != before {
+                changed.push((vx, vy));
+            }
+        }
+        changed.sort_unstable();
+        changed
+    }
+
+    /// Checks if the belief of a cell has been updated by an observation or prior
+    pub fn is_observed(&self, (x, y): (usize, usize)) -> bool {
+        self.sample_grid[x][y].covariance < self.config.covariance
+    }
+
     /// Checks if within bounds
     pub fn bound_check(&self, (x, y): (usize, usize)) -> bool {
         x < self.width && y < self.height
@@ -203,6 +985,7 @@ impl SampleGrid {
         print_cells(self.width, self.height, |x, y| self.sample_grid[x][y].state != 0.0, path)
     }
 
+    #[cfg(feature = "viz")]
     pub fn plot_sampling_cells(&self, output_file: &str, path: Option<Vec<(usize, usize)>>, heatmap: Option<Vec<((usize, usize), f64)>>) {
         plot_cells(self.width, self.height, output_file, |x, y| self.sample_grid[x][y].state != 0.0, path, heatmap)
     }
@@ -212,7 +995,7 @@ impl SampleGrid {
 mod tests {
     use crate::domains::bitpackedgrid::BitPackedGrid;
 
-    use super::SampleGrid;
+    use super::{BeliefConfig, Interpolation, SampleGrid, SampleGridError};
 
     #[test]
     fn test_samplegrid_new() {
@@ -236,6 +1019,28 @@ mod tests {
         assert_eq!(grid.gridmap.get_bit_value((0, 1)), false);
     }
 
+    #[test]
+    fn test_samplegrid_new_from_known() {
+        let ground_truth = BitPackedGrid::new_from_string(".....\n.....\n".to_string());
+        let known = [((0, 0), 1.0), ((4, 0), 0.0)];
+        let grid = SampleGrid::new_from_known(ground_truth.clone(), &known, Interpolation::InverseDistance { power: 2.0 });
+        assert_eq!((grid.width, grid.height), (5, 2));
+        assert_eq!(grid.sample_grid[0][0].state, 1.0);
+        assert_eq!(grid.sample_grid[0][0].covariance, 0.0);
+        assert!(!grid.gridmap.get_bit_value((4, 0)));
+        assert_eq!(grid.sample_grid[2][0].state, 0.5);
+        assert!(grid.sample_grid[1][0].state > 0.5 && grid.sample_grid[3][0].state < 0.5);
+        assert!(grid.sample_grid[1][0].covariance < grid.sample_grid[2][0].covariance);
+        assert_eq!(grid.sample_grid[1][0].covariance, 0.5);
+
+        let kernel = SampleGrid::new_from_known(ground_truth.clone(), &known, Interpolation::Kernel { length_scale: 1.0 });
+        assert!(kernel.sample_grid[1][1].covariance < kernel.sample_grid[2][1].covariance);
+        assert!(kernel.sample_grid[2][1].covariance < 1.0);
+        let empty = SampleGrid::new_from_known(ground_truth, &[], Interpolation::Kernel { length_scale: 1.0 });
+        assert_eq!(empty.sample_grid[3][1].state, 0.5);
+        assert!(!empty.is_observed((3, 1)));
+    }
+
     #[test]
     fn test_gridmap_init() {
         let mut grid = SampleGrid::new_from_string("@....\n.....\n.....\n.....\n".to_string());
@@ -246,6 +1051,63 @@ mod tests {
         assert_eq!(grid.gridmap.print_cells(None), "@..@@\n...@@\n...@@\n@@@@@\n");
     }
 
+    #[test]
+    fn test_fuse_prior() {
+        let mut grid = SampleGrid::new_from_string("..\n@@\n".to_string());
+        grid.fuse_prior(&[vec![0.0, 0.0], vec![1.0, 1.0]], 1.0);
+        assert_eq!(grid.sample_grid[0][0].state, 0.5);
+        assert_eq!(grid.sample_grid[0][1].state, 0.0);
+        assert_eq!(grid.sample_grid[1][1].state, 0.5);
+        assert_eq!(grid.sample_grid[1][0].covariance, 0.5);
+    }
+
+    #[test]
+    fn test_samplegrid_resize() {
+        let mut grid = SampleGrid::new_from_string("..\n.@\n".to_string());
+        grid.observation_history = Some(Default::default());
+        grid.update_node((1, 0), 0.5);
+        grid.insert_cols(0, 1);
+        grid.insert_rows(0, 1);
+        assert_eq!((grid.width, grid.height), (3, 3));
+        assert_eq!(grid.ground_truth.print_cells(None), "@@@\n@..\n@.@\n");
+        assert_eq!(grid.gridmap.print_cells(None), "@@@\n@..\n@.@\n");
+        assert_eq!(grid.sample_grid[2][1].state, 1.0);
+        assert!(grid.observation_history.as_ref().unwrap().observations.contains_key(&(2, 1)));
+        assert!(grid.extend_to_fit((4, 0)));
+        assert_eq!(grid.sample_grid.len(), 5);
+        grid.resize(1, 1);
+        assert_eq!((grid.sample_grid.len(), grid.sample_grid[0].len()), (1, 1));
+        assert!(grid.observation_history.unwrap().observations.is_empty());
+    }
+
+    #[test]
+    fn test_samplegrid_change_notifications() {
+        let mut grid = SampleGrid::new_from_string("..\n.@\n".to_string());
+        grid.update_node((0, 0), 0.5);
+        let subscription = grid.subscribe();
+        grid.update_node((1, 1), 0.5);
+        grid.update_node((0, 1), 0.5);
+        grid.update_node((1, 1), 0.5);
+        assert_eq!(grid.publish_changes(), 2);
+        assert_eq!(subscription.changes(), vec![(1, 1), (0, 1)]);
+    }
+
+    #[test]
+    fn test_samplegrid_downsample() {
+        let mut grid = SampleGrid::new_from_string("..@\n.@@\n...\n".to_string());
+        grid.update_node((0, 0), 0.0);
+        let coarse = grid.downsample(2);
+        assert_eq!((coarse.width, coarse.height), (2, 2));
+        let node = &coarse.sample_grid[0][0];
+        assert!((node.state - 0.75).abs() < 1e-6);
+        // The mean covariance of 0.75 plus the variance of the states of 0.1875
+        assert!((node.covariance - 0.9375).abs() < 1e-6);
+        assert_eq!(coarse.sample_grid[1][0].state, 0.0);
+        assert!((coarse.sample_grid[1][0].covariance - 1.0).abs() < 1e-6);
+        assert_eq!(coarse.ground_truth.print_cells(None), ".@\n..\n");
+        assert_eq!(coarse.gridmap.print_cells(None), ".@\n..\n");
+    }
+
     #[test]
     fn test_blur() {
         let mut grid = SampleGrid::new_from_string("@....\n@@...\n@@@..\n@@@..\n@@...\n".to_string());
@@ -268,4 +1130,200 @@ mod tests {
             .collect();
         println!("{:?}", grid_sample);
     }
+
+    #[test]
+    fn test_walled_blur() {
+        let map = "..@..\n..@..\n..@..\n".to_string();
+        let mut grid = SampleGrid::new_from_string(map.clone());
+        grid.set_belief((3, 1), 0.2);
+        grid.blur_samplegrid_walled(3, 1.0);
+        // The low belief beyond the wall does not leak through it
+        assert_eq!(grid.belief((1, 1)), 1.0);
+        assert_eq!(grid.belief((2, 1)), 0.0);
+        assert!(grid.belief((4, 1)) < 1.0 && grid.belief((4, 1)) > 0.2);
+        assert_eq!(grid.covariance((4, 1)), 1.0);
+
+        let mut plain = SampleGrid::new_from_string(map);
+        plain.blur_samplegrid(3, 1.0);
+        assert!(plain.belief((1, 1)) < 1.0);
+    }
+
+    #[test]
+    fn test_masked_blur_and_sample() {
+        let map = "@....\n@@...\n@@@..\n@@@..\n@@...\n".to_string();
+        let mut blurred = SampleGrid::new_from_string(map.clone());
+        blurred.blur_samplegrid(3, 1.0);
+        let mut grid = SampleGrid::new_from_string(map.clone());
+        let mask = BitPackedGrid::new_from_string("@@@..\n@@@..\n@@@@@\n@@@@@\n@@@@@\n".to_string());
+        grid.blur_samplegrid_masked(3, 1.0, &mask);
+        assert_eq!(grid.sample_grid[3][0].state, blurred.sample_grid[3][0].state);
+        assert_eq!(grid.sample_grid[2][2].state, 0.0);
+        assert_eq!(grid.sample_grid[3][3].state, 1.0);
+
+        let mut grid = SampleGrid::new_from_string(map);
+        grid.clear_gridmap();
+        grid.sample_masked(&mask);
+        assert!(grid.gridmap().get_bit_value((4, 0)));
+        assert!(!grid.gridmap().get_bit_value((4, 4)));
+    }
+
+    #[test]
+    fn test_samplegrid_validation() {
+        let truth = || BitPackedGrid::new(2, 2);
+        let grid = SampleGrid::new_from_grid(vec![vec![1.5, 0.5], vec![-1.0, 0.0]], truth());
+        assert_eq!(grid.beliefs(), vec![vec![1.0, 0.5], vec![0.0, 0.0]]);
+        assert_eq!(
+            SampleGrid::try_new_from_grid(vec![vec![0.5, 2.0], vec![0.0, 0.0]], truth()).err(),
+            Some(SampleGridError::InvalidProbability { cell: (0, 1), value: 2.0 })
+        );
+        assert_eq!(
+            SampleGrid::try_new_from_grid(vec![vec![0.5, 0.5], vec![0.0]], truth()).err(),
+            Some(SampleGridError::RaggedRows { row: 1, expected: 2, found: 1 })
+        );
+        assert_eq!(SampleGrid::try_new_from_grid(vec![], truth()).err(), Some(SampleGridError::Empty));
+        let error = SampleGrid::try_new_from_grid(vec![vec![0.5; 3]; 2], truth()).err().unwrap();
+        assert_eq!(error.to_string(), "Ground truth is (2, 2) but the sampling grid is (2, 3)");
+
+        let mut grid = grid;
+        assert_eq!(grid.try_set_belief((0, 0), 0.25), Ok(()));
+        assert_eq!(grid.try_set_belief((0, 0), f32::NAN).unwrap_err().to_string(), "Probability NaN of cell (0, 0) is not in [0, 1]");
+        assert_eq!(grid.try_set_belief((2, 0), 0.5), Err(SampleGridError::OutOfBounds { cell: (2, 0) }));
+        assert_eq!(grid.belief((0, 0)), 0.25);
+    }
+
+    #[test]
+    fn test_samplegrid_terrain() {
+        use crate::domains::grid::Grid;
+        let mut grid = SampleGrid::new_from_string("...\n...\n".to_string());
+        // Column 1 is water, passable for boats but not for cars
+        let classes = Grid::from_fn(3, 2, |(x, _)| (x == 1) as usize);
+        assert_eq!(
+            grid.set_terrain(classes.clone(), vec![1.0]),
+            Err(SampleGridError::UnknownClass { cell: (1, 0), class: 1 })
+        );
+        grid.set_terrain(classes, vec![1.0, 0.0]).unwrap();
+        grid.sample_all();
+        assert_eq!(grid.gridmap().print_cells(None), ".@.\n.@.\n");
+        grid.set_class_weights(vec![0.0, 1.0]).unwrap();
+        grid.init_gridmap();
+        assert_eq!(grid.gridmap().print_cells(None), "@.@\n@.@\n");
+        assert_eq!(grid.set_class_weights(vec![1.0, 1.5]), Err(SampleGridError::InvalidWeight { class: 1, value: 1.5 }));
+        grid.set_class_weights(vec![1.0, 0.5]).unwrap();
+        assert_eq!(grid.sample_probability((1, 1)), 0.5);
+        grid.resize(2, 2);
+        assert_eq!(grid.terrain().unwrap().classes.width, 2);
+        grid.clear_terrain();
+        assert_eq!(grid.sample_probability((1, 1)), 1.0);
+    }
+
+    #[test]
+    fn test_samplegrid_accessors() {
+        let mut grid = SampleGrid::new_from_string("..\n..\n".to_string());
+        let changes = grid.subscribe();
+        grid.set_belief((0, 0), 1.5);
+        assert_eq!(grid.belief((0, 0)), 1.0);
+        grid.set_belief((1, 0), -0.5);
+        assert_eq!(grid.belief((1, 0)), 0.0);
+        assert!(!grid.gridmap().get_bit_value((1, 0)));
+        grid.update_belief((0, 1), 0.0, 0.0);
+        assert_eq!(grid.beliefs(), vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+        assert!(!grid.gridmap().get_bit_value((0, 1)));
+        assert_eq!(grid.covariance((0, 1)), 0.0);
+        assert_eq!(grid.covariances()[1][1], 1.0);
+        assert_eq!(grid.publish_changes(), 3);
+        assert_eq!(changes.changes().len(), 3);
+    }
+
+    #[test]
+    fn test_samplegrid_sense() {
+        let truth = BitPackedGrid::new_from_string("......\n..@...\n......\n".to_string());
+        let mut grid = SampleGrid::new_from_grid(vec![vec![0.5; 3]; 6], truth);
+        let changed = grid.sense((0, 1), 4);
+        assert_eq!(grid.belief((0, 1)), 1.0);
+        assert!(grid.belief((2, 1)) < 0.5 && changed.contains(&(2, 1)));
+        // The cell behind the wall is not visible
+        assert_eq!(grid.belief((3, 1)), 0.5);
+        assert!(!changed.contains(&(3, 1)) && !grid.is_observed((3, 1)));
+        // Nearer cells are measured with less covariance
+        assert!(grid.covariance((1, 0)) < grid.covariance((3, 0)));
+        assert!(changed.iter().all(|n| grid.is_observed(*n)));
+        assert!(grid.sense_with((0, 1), 4, |_| 0.0).iter().all(|n| grid.belief(*n) == grid.ground_truth.get_bit_value(*n) as u8 as f32));
+    }
+
+    #[test]
+    fn test_samplegrid_belief_decay() {
+        let truth = BitPackedGrid::new_from_string("....\n".to_string());
+        let config = BeliefConfig { covariance: 2.0, process_noise: 0.1, decay_rate: 0.5, ..Default::default() };
+        let mut grid = SampleGrid::new_from_grid(vec![vec![0.5]; 4], truth).with_config(config);
+        assert_eq!(grid.covariance((3, 0)), 2.0);
+        grid.sense_with((0, 0), 1, |_| 0.1);
+        let observed = grid.covariance((0, 0));
+        assert!(grid.is_observed((0, 0)) && grid.belief((0, 0)) > 0.9);
+        grid.predict(1.0);
+        assert!((grid.covariance((0, 0)) - observed - 0.1).abs() < 1e-6);
+        grid.predict(100.0);
+        assert_eq!((grid.covariance((0, 0)), grid.covariance((3, 0))), (2.0, 2.0));
+        assert!(grid.belief((0, 0)) > 0.9);
+        // Decay forgets old observations
+        grid.sense_with((0, 0), 1, |_| 0.1);
+        grid.decay(20.0);
+        assert!((grid.belief((0, 0)) - 0.5).abs() < 1e-3 && (grid.covariance((0, 0)) - 2.0).abs() < 1e-3);
+        assert_eq!(grid.downsample(2).config, config);
+    }
+
+    #[test]
+    fn test_seeded_sampling() {
+        use rand::{rngs::StdRng, SeedableRng};
+        let mut grid = SampleGrid::new_from_string("..@.\n....\n.@..\n".to_string());
+        grid.blur_samplegrid(3, 1.0);
+        let mut realization = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..4).map(|_| { grid.sample_all_with(&mut rng); grid.gridmap().print_cells(None) }).collect::<Vec<_>>()
+        };
+        assert_eq!(realization(7), realization(7));
+        assert_ne!(realization(7), realization(8));
+    }
+
+    #[test]
+    fn test_sample_dirty() {
+        let mut grid = SampleGrid::new_from_string("....\n....\n.@..\n".to_string());
+        assert_eq!(grid.sample_dirty(), 0);
+        grid.track_dirty(true);
+        grid.set_belief((0, 0), 0.0);
+        grid.set_belief((3, 2), 0.5);
+        grid.update_belief((0, 0), 0.0, 0.1);
+        assert_eq!(grid.dirty_cells(), &[(0, 0), (3, 2)]);
+        let before = grid.gridmap().clone();
+        assert_eq!(grid.sample_dirty(), 2);
+        assert!(grid.dirty_cells().is_empty() && !grid.gridmap().get_bit_value((0, 0)));
+        assert_eq!(grid.gridmap().get_bit_value((1, 0)), before.get_bit_value((1, 0)));
+        grid.blur_samplegrid(3, 1.0);
+        assert_eq!(grid.dirty_cells().len(), 12);
+        grid.sample_all();
+        assert_eq!(grid.sample_dirty(), 0);
+    }
+
+    #[test]
+    fn test_samplegrid_bytes() {
+        let mut grid = SampleGrid::new_from_string("..@.\n....\n.@..\n".to_string());
+        grid.sense((0, 0), 3);
+        let bytes = grid.to_bytes();
+        assert_eq!(bytes.len(), 8 + 8 * 12 + 2 * (8 + 2));
+        let decoded = SampleGrid::from_bytes(&bytes).unwrap();
+        assert_eq!((decoded.beliefs(), decoded.covariances()), (grid.beliefs(), grid.covariances()));
+        assert_eq!(decoded.ground_truth.print_cells(None), grid.ground_truth.print_cells(None));
+        assert_eq!(decoded.gridmap().print_cells(None), grid.gridmap().print_cells(None));
+        assert!(SampleGrid::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[cfg(all(feature = "serde", feature = "io"))]
+    #[test]
+    fn test_samplegrid_serde() {
+        let mut grid = SampleGrid::new_from_string("..@.\n....\n".to_string());
+        grid.update_node((1, 1), 0.5);
+        let json = serde_json::to_string(&grid).unwrap();
+        let decoded: SampleGrid = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.covariances(), grid.covariances());
+        assert_eq!(decoded.ground_truth.to_compact_string(), grid.ground_truth.to_compact_string());
+    }
 }
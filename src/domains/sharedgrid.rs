@@ -0,0 +1,122 @@
+//! # Shared Grid Maps
+//! A bitpacked grid that can be read by many planner threads while another
+//! thread, such as a sensor, writes to it. Readers take immutable snapshots of
+//! the grid which are shared through an `Arc` and never change, so a planner
+//! sees a consistent map for the whole of its search. Writers mutate a private
+//! copy through an explicit handle and commit it as a new epoch, after which
+//! new snapshots see the changes while existing snapshots keep the old map.
+
+use std::{ops::Deref, sync::{Arc, RwLock}};
+
+use super::bitpackedgrid::BitPackedGrid;
+
+/// An immutable view of a shared grid at an epoch
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub epoch: usize,
+    grid: Arc<BitPackedGrid>,
+}
+
+impl Deref for Snapshot {
+    type Target = BitPackedGrid;
+
+    fn deref(&self) -> &BitPackedGrid {
+        &self.grid
+    }
+}
+
+/// A grid shared between threads, cloning it shares the same grid
+#[derive(Debug, Clone)]
+pub struct SharedGrid {
+    latest: Arc<RwLock<Snapshot>>,
+}
+
+impl SharedGrid {
+    /// Shares a grid, starting at epoch 0
+    pub fn new(grid: BitPackedGrid) -> Self {
+        SharedGrid { latest: Arc::new(RwLock::new(Snapshot { epoch: 0, grid: Arc::new(grid) })) }
+    }
+
+    /// The latest committed grid
+    /// ## Complexity
+    /// O(1), the grid is not copied
+    pub fn snapshot(&self) -> Snapshot {
+        self.latest.read().unwrap().clone()
+    }
+
+    /// The epoch of the latest committed grid
+    pub fn epoch(&self) -> usize {
+        self.latest.read().unwrap().epoch
+    }
+
+    /// A handle for mutating the grid, starting from the latest committed grid
+    pub fn writer(&self) -> GridWriter {
+        GridWriter { grid: (*self.snapshot()).clone(), shared: self.clone() }
+    }
+}
+
+/// A mutation handle of a shared grid, changes are only seen by readers once committed
+pub struct GridWriter {
+    grid: BitPackedGrid,
+    shared: SharedGrid,
+}
+
+impl GridWriter {
+    /// Get the value of a cell including uncommitted changes
+    pub fn get_bit_value(&self, node: (usize, usize)) -> bool {
+        self.grid.get_bit_value(node)
+    }
+
+    /// Set the value of a cell
+    pub fn set_bit_value(&mut self, node: (usize, usize), value: bool) {
+        self.grid.set_bit_value(node, value);
+    }
+
+    /// Publishes the changes as a new epoch. Changes committed by other writers
+    /// since this writer was created are overwritten.
+    /// ## Returns
+    /// The new epoch
+    pub fn commit(&mut self) -> usize {
+        let mut latest = self.shared.latest.write().unwrap();
+        latest.epoch += 1;
+        latest.grid = Arc::new(self.grid.clone());
+        latest.epoch
+    }
+}
+
+#[cfg(all(test, feature = "search-core"))]
+mod tests {
+    use super::*;
+    #[cfg(feature = "sampling")]
+    use crate::domains::samplegrid::SampleGrid;
+    use crate::search::uninformed::bfs;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_sharedgrid_concurrent_reads() {
+        assert_send_sync::<BitPackedGrid>();
+        #[cfg(feature = "sampling")]
+        assert_send_sync::<SampleGrid>();
+        assert_send_sync::<Snapshot>();
+        let shared = SharedGrid::new(BitPackedGrid::new_from_string("...\n...\n...\n".to_string()));
+        let before = shared.snapshot();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                let snapshot = shared.snapshot();
+                scope.spawn(move || {
+                    let path = bfs(|n| snapshot.adjacent(*n, false), (0, 0), |n| *n == (2, 2));
+                    assert_eq!(path.unwrap().1, 4);
+                });
+            }
+            let mut writer = shared.writer();
+            scope.spawn(move || {
+                writer.set_bit_value((1, 1), false);
+                assert_eq!(writer.commit(), 1);
+            });
+        });
+        assert_eq!(shared.epoch(), 1);
+        assert!(before.get_bit_value((1, 1)));
+        assert!(!shared.snapshot().get_bit_value((1, 1)));
+    }
+}
@@ -0,0 +1,188 @@
+//! # Weighted Grid Maps
+//! A grid map with a traversal cost per cell instead of a free or blocked bit,
+//! for terrain such as roads, swamps and water. Costs are small integer tiers,
+//! where a cost of 0 marks an obstacle and every other cost is that of
+//! entering the cell, so the searches keep working with exact integer costs.
+//! Maps are read from the Moving AI format, whose terrain characters are given
+//! costs by a cost table.
+
+use crate::heuristics::distance::manhattan_distance;
+use crate::heuristics::octile::{exact_octile_distance, OctileCost};
+
+use super::{Cell, bitpackedgrid::BitPackedGrid};
+use super::direction::Direction;
+use super::grid::Grid;
+
+/// The default costs of the Moving AI terrain characters, where passable
+/// ground (`.` and `G`) costs 1, swamp (`S`) 3 and water (`W`) 5, while trees
+/// (`T`) and out of bounds cells (`@` and `O`) are obstacles
+pub fn moving_ai_cost(terrain: char) -> u8 {
+    match terrain {
+        '.' | 'G' => 1,
+        'S' => 3,
+        'W' => 5,
+        _ => 0,
+    }
+}
+
+/// A grid of per-cell traversal costs
+/// ## Fields
+/// * `costs` - The cost of entering each cell, 0 for obstacles
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightedGrid {
+    pub costs: Grid<u8>,
+}
+
+impl WeightedGrid {
+    /// Create a weighted grid where every cell is an obstacle
+    pub fn new(width: usize, height: usize) -> WeightedGrid {
+        WeightedGrid { costs: Grid::new(width, height, 0) }
+    }
+
+    /// Create a weighted grid from a string with the default Moving AI costs
+    /// ## Arguments
+    /// * `map` - A string of terrain characters, optionally with a Moving AI header
+    pub fn new_from_string(map: String) -> WeightedGrid {
+        WeightedGrid::new_from_string_with_costs(map, moving_ai_cost)
+    }
+
+    /// Create a weighted grid from a string with a cost table
+    /// ## Arguments
+    /// * `map` - A string of terrain characters, optionally with a Moving AI header
+    /// * `cost` - The cost of each terrain character, 0 for obstacles
+    pub fn new_from_string_with_costs(map: String, cost: impl Fn(char) -> u8) -> WeightedGrid {
+        let map = map.trim();
+        // The header of a Moving AI map ends with a `map` line
+        let map = map.find("map\n").map_or(map, |i| &map[i + 4..]);
+        let lines = map.lines().map(str::trim_end).filter(|l| !l.is_empty()).collect::<Vec<_>>();
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        WeightedGrid {
+            costs: Grid::from_fn(width, lines.len(), |(x, y)| lines[y].chars().nth(x).map_or(0, &cost)),
+        }
+    }
+
+    /// Create a weighted grid from a file with the default Moving AI costs
+    pub fn new_from_file(filename: &str) -> WeightedGrid {
+        let s = std::fs::read_to_string(filename).expect("Unable to read file");
+        WeightedGrid::new_from_string(s)
+    }
+
+    /// Create a weighted grid from an occupancy grid where free cells cost 1
+    pub fn from_bitpacked(grid: &BitPackedGrid) -> WeightedGrid {
+        let costs = Grid::from_fn(grid.original_width, grid.original_height, |n| grid.get_bit_value(n) as u8);
+        WeightedGrid { costs }
+    }
+
+    pub fn width(&self) -> usize {
+        self.costs.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.costs.height
+    }
+
+    /// Check if a given x, y coordinate is within the bounds of the map
+    pub fn bounds_check(&self, node: Cell) -> bool {
+        self.costs.bounds_check(node)
+    }
+
+    /// The cost of entering a cell, `None` for obstacles and cells outside the map
+    pub fn cost(&self, node: Cell) -> Option<usize> {
+        self.costs.get(node).filter(|c| **c > 0).map(|c| *c as usize)
+    }
+
+    /// Sets the cost of entering a cell, 0 making it an obstacle
+    pub fn set_cost(&mut self, node: Cell, cost: u8) {
+        self.costs.set(node, cost);
+    }
+
+    /// The smallest cost of a passable cell, which scales distance heuristics
+    /// so they stay admissible
+    pub fn min_cost(&self) -> usize {
+        self.costs.cells.iter().filter(|c| **c > 0).min().map_or(1, |c| *c as usize)
+    }
+
+    /// The 4-connected neighbours of a cell with the cost of entering them
+    pub fn adjacent(&self, (x, y): Cell) -> impl Iterator<Item = (Cell, usize)> + '_ {
+        Direction::CARDINAL.iter()
+            .map(move |d| d.apply_wrapping((x, y)))
+            .filter_map(move |n| self.cost(n).map(|c| (n, c)))
+    }
+
+    /// The 8-connected neighbours of a cell with their exact octile costs,
+    /// the cost of entering a cell scaling the length of the move. Diagonal
+    /// moves may not cut the corner of an obstacle.
+    pub fn adjacent_octile(&self, (x, y): Cell) -> impl Iterator<Item = (Cell, OctileCost)> + '_ {
+        Direction::ALL.iter()
+            .map(move |d| d.apply_wrapping((x, y)))
+            .filter(move |&(nx, ny)| self.cost((nx, y)).is_some() && self.cost((x, ny)).is_some())
+            .filter_map(move |n| self.cost(n).map(|c| {
+                let step = OctileCost::step((x, y), n);
+                (n, OctileCost::new(step.straight * c as u64, step.diagonal * c as u64))
+            }))
+    }
+
+    /// The Manhattan distance scaled by the smallest cost, an admissible
+    /// heuristic for `adjacent`
+    pub fn heuristic(&self, node: Cell, goal: Cell) -> usize {
+        manhattan_distance(node, goal) * self.min_cost()
+    }
+
+    /// The octile distance scaled by the smallest cost, an admissible
+    /// heuristic for `adjacent_octile`
+    pub fn octile_heuristic(&self, node: Cell, goal: Cell) -> OctileCost {
+        let (distance, min) = (exact_octile_distance(node, goal), self.min_cost() as u64);
+        OctileCost::new(distance.straight * min, distance.diagonal * min)
+    }
+
+    /// An occupancy grid of the passable cells
+    pub fn to_bitpacked(&self) -> BitPackedGrid {
+        self.costs.to_bitpacked(|c| *c > 0)
+    }
+
+    /// Prints the grid where . is a passable cell and @ is an obstacle
+    pub fn print_cells(&self, path: Option<Vec<Cell>>) -> String {
+        self.costs.print_cells(|c| *c > 0, path)
+    }
+
+    /// Plots the grid with the costs of the passable cells as a heatmap
+    #[cfg(feature = "viz")]
+    pub fn plot_cells(&self, filename: &str, path: Option<Vec<Cell>>) {
+        let heatmap = self.costs.heatmap(|c| *c as f64);
+        self.costs.plot_cells(filename, |c| *c > 0, path, Some(heatmap))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "search-core")]
+    use crate::search::astar::astar;
+
+    #[test]
+    fn test_weighted_grid() {
+        let grid = WeightedGrid::new_from_string("type octile\nheight 3\nwidth 5\nmap\n.SSS.\n.T.W.\n.....\n".to_string());
+        assert_eq!((grid.width(), grid.height()), (5, 3));
+        assert_eq!(grid.cost((1, 0)), Some(3));
+        assert_eq!(grid.cost((3, 1)), Some(5));
+        assert_eq!(grid.cost((1, 1)), None);
+        assert_eq!(grid.cost((5, 0)), None);
+        assert_eq!(grid.min_cost(), 1);
+        assert_eq!(grid.print_cells(None), ".....\n.@...\n.....\n");
+        assert_eq!(grid.adjacent((2, 1)).collect::<Vec<_>>(), vec![((3, 1), 5), ((2, 2), 1), ((2, 0), 3)]);
+        assert_eq!(WeightedGrid::from_bitpacked(&grid.to_bitpacked()).cost((1, 0)), Some(1));
+    }
+
+    #[test]
+    #[cfg(feature = "search-core")]
+    fn test_weighted_search() {
+        let grid = WeightedGrid::new_from_string(".SSS.\n.T.W.\n.....\n".to_string());
+        // The detour through the bottom row is cheaper than the swamp
+        let (path, cost) = astar(|n| grid.adjacent(*n), (0, 0), |n| *n == (4, 0), |n| grid.heuristic(*n, (4, 0))).unwrap();
+        assert_eq!(cost, 8);
+        assert_eq!(path.iter().skip(1).map(|n| grid.cost(*n).unwrap()).sum::<usize>(), cost);
+        assert!(path.iter().all(|n| !matches!(grid.cost(*n), Some(3) | Some(5))));
+        let (_, octile) = astar(|n| grid.adjacent_octile(*n), (0, 0), |n| *n == (4, 0), |n| grid.octile_heuristic(*n, (4, 0))).unwrap();
+        assert_eq!(octile, OctileCost::new(6, 1));
+    }
+}
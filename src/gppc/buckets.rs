@@ -0,0 +1,126 @@
+//! # Difficulty Buckets
+//! Bucketing of queries by the length of their optimal path, as done by the
+//! Moving AI benchmark sets, and sampling of instance sets with the same number
+//! of queries in every bucket. Comparing algorithms on balanced sets stops the
+//! many short queries of a map from dominating aggregate results.
+
+use std::collections::BTreeMap;
+
+use rand::seq::SliceRandom;
+
+use crate::analysis::heuristics::{backward_dijkstra, sample_cells};
+use crate::domains::{bitpackedgrid::BitPackedGrid, csrgraph::{octile_cost, Connectivity}};
+use crate::util::determinism::with_rng;
+
+use super::scenario::Experiment;
+
+/// The range of optimal lengths covered by each bucket of the Moving AI sets
+pub const BUCKET_WIDTH: f64 = 4.0;
+
+/// The bucket of a query with a given optimal length
+pub fn bucket_of(optimal: f64, width: f64) -> usize {
+    (optimal / width).max(0.0) as usize
+}
+
+/// Reassigns the bucket of every experiment from its optimal length
+pub fn rebucket(experiments: &mut [Experiment], width: f64) {
+    for experiment in experiments {
+        experiment.bucket = bucket_of(experiment.optimal, width);
+    }
+}
+
+/// Groups experiments by their bucket
+pub fn group_by_bucket(experiments: &[Experiment]) -> BTreeMap<usize, Vec<&Experiment>> {
+    let mut buckets: BTreeMap<usize, Vec<&Experiment>> = BTreeMap::new();
+    for experiment in experiments {
+        buckets.entry(experiment.bucket).or_default().push(experiment);
+    }
+    buckets
+}
+
+/// Samples `per_bucket` experiments from every bucket, skipping buckets with
+/// fewer experiments so every bucket of the sample has the same size
+/// ## Returns
+/// The sampled experiments ordered by bucket
+pub fn stratified_sample(experiments: &[Experiment], per_bucket: usize) -> Vec<Experiment> {
+    with_rng(|rng| group_by_bucket(experiments)
+        .into_values()
+        .filter(|b| b.len() >= per_bucket)
+        .flat_map(|b| b.choose_multiple(rng, per_bucket).map(|e| (*e).clone()).collect::<Vec<_>>())
+        .collect())
+}
+
+/// Generates a balanced scenario on a grid with octile moves that do not cut
+/// corners. Random goals are drawn and every reachable start is bucketed by
+/// its optimal length until the first `buckets` buckets are filled.
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `map` - The name of the map file recorded in each experiment
+/// * `buckets` - The number of buckets to fill, starting from bucket 0
+/// * `per_bucket` - The number of experiments in each bucket
+/// * `goals` - The maximum number of goals drawn
+/// ## Returns
+/// The experiments ordered by bucket, buckets that could not be filled are omitted
+pub fn generate_scenario(
+    grid: &BitPackedGrid,
+    map: &str,
+    buckets: usize,
+    per_bucket: usize,
+    goals: usize,
+) -> Vec<Experiment> {
+    let graph = grid.to_graph(Connectivity::EightNoCornerCutting, octile_cost);
+    let mut candidates = Vec::new();
+    for goal in sample_cells(grid, goals) {
+        let distances = backward_dijkstra(&graph, goal);
+        candidates.extend((0..graph.len())
+            .filter_map(|id| distances[id].filter(|d| *d > 0.0).map(|d| (graph.nodes[id], d)))
+            .filter(|(_, d)| bucket_of(*d, BUCKET_WIDTH) < buckets)
+            .map(|(start, optimal)| Experiment {
+                bucket: bucket_of(optimal, BUCKET_WIDTH),
+                map: map.to_string(),
+                width: grid.original_width,
+                height: grid.original_height,
+                start,
+                goal,
+                optimal,
+            }));
+        let counts = group_by_bucket(&candidates);
+        if (0..buckets).all(|b| counts.get(&b).is_some_and(|e| e.len() >= per_bucket)) {
+            break;
+        }
+    }
+    stratified_sample(&candidates, per_bucket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stratified_sample() {
+        let mut experiments = [1.0, 2.0, 3.5, 4.0, 5.5, 9.0].iter()
+            .map(|optimal| Experiment {
+                bucket: 0,
+                map: "test.map".to_string(),
+                width: 10,
+                height: 10,
+                start: (0, 0),
+                goal: (1, 1),
+                optimal: *optimal,
+            })
+            .collect::<Vec<_>>();
+        rebucket(&mut experiments, BUCKET_WIDTH);
+        assert_eq!(experiments.iter().map(|e| e.bucket).collect::<Vec<_>>(), vec![0, 0, 0, 1, 1, 2]);
+        let sample = stratified_sample(&experiments, 2);
+        assert_eq!(sample.iter().map(|e| e.bucket).collect::<Vec<_>>(), vec![0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn test_generate_scenario() {
+        let grid = BitPackedGrid::new_from_string("..........\n..........\n..........\n".to_string());
+        let experiments = generate_scenario(&grid, "open.map", 2, 3, 30);
+        assert_eq!(experiments.len(), 6);
+        assert!(experiments.iter().all(|e| e.bucket == bucket_of(e.optimal, BUCKET_WIDTH) && e.bucket < 2));
+        assert!(experiments.iter().all(|e| grid.get_bit_value(e.start) && grid.get_bit_value(e.goal)));
+    }
+}
@@ -0,0 +1,196 @@
+//! # Query Features
+//! Cheap per-instance features of the experiments of a scenario, exported as
+//! CSV for training algorithm selection or runtime prediction models. The
+//! features only look at the map and the endpoints of a query, never at a
+//! search, so they are cheap enough to compute before choosing a planner. Map
+//! wide data, the connected components and the clearance of every cell, is
+//! computed once per map and shared by its experiments.
+
+use std::{collections::VecDeque, io::{self, Write}};
+
+use crate::analysis::statistics::ComponentMap;
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid, direction::Direction, grid::Grid};
+use crate::fov::linedrawing::bresenham;
+use crate::heuristics::distance::octile_distance;
+
+use super::scenario::Experiment;
+
+/// The features of an experiment
+/// ## Fields
+/// * `bucket` - The difficulty bucket of the experiment
+/// * `heuristic` - The octile distance from the start to the goal
+/// * `optimal` - The optimal length of the experiment, a training target
+/// * `start_density` - The fraction of obstacles in the window around the start
+/// * `goal_density` - The fraction of obstacles in the window around the goal
+/// * `line_clearance` - The smallest clearance of the cells on the straight line
+///   from the start to the goal, 0 when the line crosses an obstacle
+/// * `line_blocked` - The fraction of cells on the straight line that are obstacles
+/// * `component` - The number of cells in the connected component of the start
+/// * `connected` - Whether the start and goal are in the same component
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstanceFeatures {
+    pub bucket: usize,
+    pub heuristic: f64,
+    pub optimal: f64,
+    pub start_density: f64,
+    pub goal_density: f64,
+    pub line_clearance: usize,
+    pub line_blocked: f64,
+    pub component: usize,
+    pub connected: bool,
+}
+
+impl InstanceFeatures {
+    /// The column names of the CSV export
+    pub const HEADER: &'static str =
+        "bucket,heuristic,optimal,start_density,goal_density,line_clearance,line_blocked,component,connected";
+
+    /// The features as a line of CSV with the columns of `HEADER`
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{}",
+            self.bucket, self.heuristic, self.optimal, self.start_density, self.goal_density,
+            self.line_clearance, self.line_blocked, self.component, self.connected as u8
+        )
+    }
+}
+
+/// The clearance of every cell, the number of 8-connected moves to the nearest
+/// obstacle or to outside the map, 0 for obstacles
+pub fn clearance(grid: &BitPackedGrid) -> Grid<usize> {
+    let (width, height) = (grid.original_width, grid.original_height);
+    let mut clearance = Grid::from_fn(width, height, |n| if grid.get_bit_value(n) { usize::MAX } else { 0 });
+    let mut queue = VecDeque::new();
+    for (cell, value) in clearance.iter() {
+        let border = cell.0 == 0 || cell.1 == 0 || cell.0 + 1 == width || cell.1 + 1 == height;
+        if *value == 0 || border {
+            queue.push_back(cell);
+        }
+    }
+    for cell in &queue {
+        if clearance[*cell] != 0 {
+            clearance.set(*cell, 1);
+        }
+    }
+    while let Some((x, y)) = queue.pop_front() {
+        let next = clearance[(x, y)] + 1;
+        for direction in Direction::ALL {
+            let neighbour = direction.apply_wrapping((x, y));
+            if clearance.get(neighbour).is_some_and(|c| *c > next) {
+                clearance.set(neighbour, next);
+                queue.push_back(neighbour);
+            }
+        }
+    }
+    clearance
+}
+
+/// Extracts the features of the experiments on a map
+/// ## Fields
+/// * `radius` - The radius of the square windows around the start and goal
+pub struct FeatureExtractor<'a> {
+    grid: &'a BitPackedGrid,
+    components: ComponentMap,
+    clearance: Grid<usize>,
+    pub radius: usize,
+}
+
+impl<'a> FeatureExtractor<'a> {
+    /// Computes the components and clearance of a map
+    /// ## Arguments
+    /// * `grid` - The map of the experiments
+    /// * `radius` - The radius of the square windows around the start and goal
+    pub fn new(grid: &'a BitPackedGrid, radius: usize) -> Self {
+        FeatureExtractor { grid, components: ComponentMap::new(grid), clearance: clearance(grid), radius }
+    }
+
+    /// The fraction of obstacles in the window around a cell, where cells
+    /// outside the map count as obstacles
+    fn density(&self, (x, y): Cell) -> f64 {
+        let side = 2 * self.radius + 1;
+        let window = (x as isize - self.radius as isize..=(x + self.radius) as isize)
+            .flat_map(|x| (y as isize - self.radius as isize..=(y + self.radius) as isize).map(move |y| (x, y)));
+        let obstacles = window
+            .filter(|(x, y)| {
+                let cell = (*x as usize, *y as usize);
+                *x < 0 || *y < 0 || !self.grid.bounds_check(cell) || !self.grid.get_bit_value(cell)
+            })
+            .count();
+        obstacles as f64 / (side * side) as f64
+    }
+
+    /// The features of an experiment
+    pub fn extract(&self, experiment: &Experiment) -> InstanceFeatures {
+        let (start, goal) = (experiment.start, experiment.goal);
+        let line = bresenham(start, goal, |_, _| true);
+        let blocked = line.iter().filter(|n| !self.grid.get_bit_value(**n)).count();
+        let component = self.components.labels[start.0][start.1].map_or(0, |label| self.components.sizes[&label]);
+        InstanceFeatures {
+            bucket: experiment.bucket,
+            heuristic: octile_distance((start.0 as i32, start.1 as i32), (goal.0 as i32, goal.1 as i32)) as f64,
+            optimal: experiment.optimal,
+            start_density: self.density(start),
+            goal_density: self.density(goal),
+            line_clearance: line.iter().map(|n| self.clearance.get(*n).copied().unwrap_or(0)).min().unwrap_or(0),
+            line_blocked: blocked as f64 / line.len().max(1) as f64,
+            component,
+            connected: self.components.connected(start, goal),
+        }
+    }
+
+    /// The features of many experiments
+    pub fn extract_all(&self, experiments: &[Experiment]) -> Vec<InstanceFeatures> {
+        experiments.iter().map(|e| self.extract(e)).collect()
+    }
+}
+
+/// Writes the features of experiments as CSV with a header line
+pub fn write_features(features: &[InstanceFeatures], writer: &mut impl Write) -> io::Result<()> {
+    writeln!(writer, "{}", InstanceFeatures::HEADER)?;
+    for f in features {
+        writeln!(writer, "{}", f.to_csv())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clearance() {
+        let grid = BitPackedGrid::new_from_string(".......\n.......\n...@...\n.......\n.......\n".to_string());
+        let clearance = clearance(&grid);
+        assert_eq!(clearance[(3, 2)], 0);
+        assert_eq!(clearance[(0, 0)], 1);
+        assert_eq!(clearance[(1, 1)], 2);
+        assert_eq!(clearance[(2, 2)], 1);
+        assert_eq!(clearance[(5, 2)], 2);
+    }
+
+    #[test]
+    fn test_extract_features() {
+        let grid = BitPackedGrid::new_from_string(".......\n.......\n...@...\n.......\n.....@@\n".to_string());
+        let experiment = |start, goal| Experiment {
+            bucket: 0, map: "test.map".to_string(), width: 7, height: 5, start, goal, optimal: 6.0,
+        };
+        let extractor = FeatureExtractor::new(&grid, 1);
+        let through = extractor.extract(&experiment((0, 2), (6, 2)));
+        assert_eq!(through.heuristic, 6.0);
+        assert_eq!(through.line_clearance, 0);
+        assert!((through.line_blocked - 1.0 / 7.0).abs() < 1e-9);
+        assert!((through.start_density - 3.0 / 9.0).abs() < 1e-9);
+        assert_eq!(through.component, 32);
+        assert!(through.connected);
+        let clear = extractor.extract(&experiment((1, 1), (5, 1)));
+        assert_eq!(clear.line_clearance, 1);
+        assert_eq!(clear.line_blocked, 0.0);
+        assert!(!extractor.extract(&experiment((1, 1), (6, 4))).connected);
+
+        let mut csv = Vec::new();
+        write_features(&extractor.extract_all(&[experiment((0, 2), (6, 2))]), &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        assert_eq!(csv.lines().nth(1).unwrap().split(',').count(), InstanceFeatures::HEADER.split(',').count());
+    }
+}
@@ -0,0 +1,260 @@
+//! # Competition Harness
+//! A harness following the Grid-based Path Planning Competition (GPPC) rules,
+//! so planners can be evaluated the way competition entries are. An entry
+//! first preprocesses a map, saving whatever data it needs to a file. In the
+//! timed phase the entry loads its data and answers a batch of queries, where
+//! each query may be answered incrementally across several calls that each
+//! return a segment of the path. Every returned path is validated and its
+//! octile length compared against the optimal length of the scenario.
+//! * Scenario, reading and writing of `.scen` benchmark files
+//! * Buckets, difficulty bucketing and balanced sampling of experiments
+//! * Solutions, a flat-file store of outcomes per algorithm version for regression checks
+//! * Features, per-instance query features exported for algorithm selection models
+//! * Reports, median and tail percentiles of benchmark results per algorithm and bucket
+
+#![allow(dead_code)]
+pub mod scenario;
+#[cfg(feature = "sampling")]
+pub mod buckets;
+#[cfg(feature = "io")]
+pub mod solutions;
+pub mod features;
+pub mod reports;
+
+use std::{fs::File, io::{self, BufReader, BufWriter, Read, Write}, time::{Duration, Instant}};
+
+use crate::domains::{bitpackedgrid::BitPackedGrid, csrgraph::{octile_cost, Connectivity}};
+use crate::search::astar::astar;
+
+use scenario::Experiment;
+
+/// An entry of the competition
+pub trait Entry {
+    /// The data loaded by the entry before answering queries
+    type Data;
+
+    /// The name of the entry used in reports
+    fn name(&self) -> String;
+
+    /// Preprocesses a map, writing the data the entry needs to answer queries.
+    /// This phase is not timed.
+    fn preprocess(&self, grid: &BitPackedGrid, writer: &mut dyn Write) -> io::Result<()>;
+
+    /// Loads the preprocessed data of a map
+    fn prepare(&self, grid: &BitPackedGrid, reader: &mut dyn Read) -> io::Result<Self::Data>;
+
+    /// Extends `path` with the next segment of a path between two cells
+    /// ## Returns
+    /// True once the path is complete or no path exists
+    fn get_path(&self, data: &mut Self::Data, start: (usize, usize), goal: (usize, usize), path: &mut Vec<(usize, usize)>) -> bool;
+}
+
+/// The outcome of a query
+/// ## Fields
+/// * `length` - The octile length of the returned path
+/// * `valid` - Whether the path is a sequence of legal moves from the start to the goal
+/// * `suboptimality` - The length of the path over the optimal length of the scenario
+/// * `calls` - The number of calls used to answer the query
+/// * `first_call` - The time taken by the first call, the latency before the agent can move
+/// * `max_call` - The longest time taken by a single call
+/// * `total` - The time taken by all calls
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryResult {
+    pub length: f64,
+    pub valid: bool,
+    pub suboptimality: f64,
+    pub calls: usize,
+    pub first_call: Duration,
+    pub max_call: Duration,
+    pub total: Duration,
+}
+
+/// The results of an entry on a scenario
+/// ## Fields
+/// * `entry` - The name of the entry
+/// * `prepare` - The time taken to load the preprocessed data
+/// * `results` - The outcome of every query in the order of the scenario
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    pub entry: String,
+    pub prepare: Duration,
+    pub results: Vec<QueryResult>,
+}
+
+impl Report {
+    /// The number of queries answered with a valid path
+    pub fn solved(&self) -> usize {
+        self.results.iter().filter(|r| r.valid).count()
+    }
+
+    /// The time taken by every query
+    pub fn total_time(&self) -> Duration {
+        self.results.iter().map(|r| r.total).sum()
+    }
+
+    /// The mean suboptimality of the valid paths
+    pub fn mean_suboptimality(&self) -> f64 {
+        let valid = self.results.iter().filter(|r| r.valid).collect::<Vec<_>>();
+        valid.iter().map(|r| r.suboptimality).sum::<f64>() / valid.len().max(1) as f64
+    }
+
+    /// A one line summary of the report
+    pub fn summary(&self) -> String {
+        format!(
+            "{}: {}/{} solved, suboptimality {:.4}, total {:?}, max call {:?}",
+            self.entry,
+            self.solved(),
+            self.results.len(),
+            self.mean_suboptimality(),
+            self.total_time(),
+            self.results.iter().map(|r| r.max_call).max().unwrap_or_default(),
+        )
+    }
+}
+
+/// Runs entries under the competition rules
+/// ## Fields
+/// * `connectivity` - The moves allowed when validating paths, GPPC uses
+///   `EightNoCornerCutting`
+/// * `max_calls` - The number of calls after which a query is abandoned
+#[derive(Clone, Debug)]
+pub struct Harness {
+    pub connectivity: Connectivity,
+    pub max_calls: usize,
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Harness { connectivity: Connectivity::EightNoCornerCutting, max_calls: 1_000_000 }
+    }
+}
+
+impl Harness {
+    /// Runs the preprocessing phase of an entry, saving its data to a file
+    pub fn preprocess<E: Entry>(&self, entry: &E, grid: &BitPackedGrid, filename: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        entry.preprocess(grid, &mut writer)?;
+        writer.flush()
+    }
+
+    /// Loads the data of an entry from a file and runs the timed queries
+    pub fn run<E: Entry>(&self, entry: &E, grid: &BitPackedGrid, filename: &str, experiments: &[Experiment]) -> io::Result<Report> {
+        let mut reader = BufReader::new(File::open(filename)?);
+        let start = Instant::now();
+        let mut data = entry.prepare(grid, &mut reader)?;
+        let prepare = start.elapsed();
+        let results = experiments.iter().map(|e| self.query(entry, &mut data, grid, e)).collect();
+        Ok(Report { entry: entry.name(), prepare, results })
+    }
+
+    /// Answers a single query, timing every call
+    fn query<E: Entry>(&self, entry: &E, data: &mut E::Data, grid: &BitPackedGrid, experiment: &Experiment) -> QueryResult {
+        let mut path = Vec::new();
+        let mut times = Vec::new();
+        loop {
+            let start = Instant::now();
+            let done = entry.get_path(data, experiment.start, experiment.goal, &mut path);
+            times.push(start.elapsed());
+            if done || times.len() >= self.max_calls {
+                break;
+            }
+        }
+        let valid = self.validate(grid, &path, experiment);
+        let length = path.windows(2).map(|m| octile_cost(m[0], m[1])).sum::<f64>();
+        QueryResult {
+            length,
+            valid,
+            suboptimality: if experiment.optimal > 0.0 { length / experiment.optimal } else { 1.0 },
+            calls: times.len(),
+            first_call: times[0],
+            max_call: times.iter().copied().max().unwrap_or_default(),
+            total: times.iter().sum(),
+        }
+    }
+
+    /// Checks that a path is a sequence of legal moves between free cells
+    /// from the start to the goal of an experiment
+    pub fn validate(&self, grid: &BitPackedGrid, path: &[(usize, usize)], experiment: &Experiment) -> bool {
+        let free = |n: (usize, usize)| grid.bounds_check(n) && grid.get_bit_value(n);
+        path.first() == Some(&experiment.start)
+            && path.last() == Some(&experiment.goal)
+            && path.iter().all(|n| free(*n))
+            && path.windows(2).all(|m| self.connectivity.allows(m[0], m[1], free))
+    }
+}
+
+/// A reference entry running A-Star on the 8-connected grid without cutting
+/// corners and without preprocessing. Octile costs are scaled to integers.
+pub struct AstarEntry;
+
+impl AstarEntry {
+    const STRAIGHT: usize = 10_000;
+    const DIAGONAL: usize = 14_142;
+}
+
+impl Entry for AstarEntry {
+    type Data = BitPackedGrid;
+
+    fn name(&self) -> String {
+        "A*".to_string()
+    }
+
+    fn preprocess(&self, _: &BitPackedGrid, _: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn prepare(&self, grid: &BitPackedGrid, _: &mut dyn Read) -> io::Result<BitPackedGrid> {
+        Ok(grid.clone())
+    }
+
+    fn get_path(&self, grid: &mut BitPackedGrid, start: (usize, usize), goal: (usize, usize), path: &mut Vec<(usize, usize)>) -> bool {
+        let grid = &*grid;
+        let octile = |(x1, y1): (usize, usize), (x2, y2): (usize, usize)| {
+            let (dx, dy) = (x1.abs_diff(x2), y1.abs_diff(y2));
+            AstarEntry::STRAIGHT * dx.max(dy) + (AstarEntry::DIAGONAL - AstarEntry::STRAIGHT) * dx.min(dy)
+        };
+        let result = astar(
+            |&(x, y)| grid.adjacent((x, y), true)
+                .filter(move |&(nx, ny)| grid.bounds_check((nx, ny)) && grid.get_bit_value((nx, y)) && grid.get_bit_value((x, ny)))
+                .map(move |n| (n, octile((x, y), n))),
+            start,
+            |n| *n == goal,
+            |n| octile(*n, goal),
+        );
+        if let Some((found, _)) = result {
+            path.extend(found);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_harness_astar_entry() {
+        let grid = BitPackedGrid::new_from_string("....\n.@..\n....\n".to_string());
+        let experiment = |start, goal, optimal| Experiment {
+            bucket: 0, map: "test.map".to_string(), width: 4, height: 3, start, goal, optimal,
+        };
+        let experiments = [
+            experiment((0, 0), (3, 2), 3.0 + std::f64::consts::SQRT_2),
+            experiment((0, 1), (3, 1), 3.0 + std::f64::consts::SQRT_2),
+        ];
+        let file = std::env::temp_dir().join("pathfinding-rs-gppc-astar.dat");
+        let file = file.to_str().unwrap();
+        let harness = Harness::default();
+        harness.preprocess(&AstarEntry, &grid, file).unwrap();
+        let report = harness.run(&AstarEntry, &grid, file, &experiments).unwrap();
+        std::fs::remove_file(file).unwrap();
+        assert_eq!(report.solved(), 2);
+        assert!(report.results.iter().all(|r| (r.suboptimality - 1.0).abs() < 1e-9 && r.calls == 1));
+        assert!(report.summary().starts_with("A*: 2/2 solved"));
+
+        assert!(!harness.validate(&grid, &[(0, 0), (1, 1)], &experiment((0, 0), (1, 1), 1.0)));
+        assert!(!harness.validate(&grid, &[(0, 0), (1, 0), (2, 1)], &experiment((0, 0), (2, 1), 1.0)));
+        assert!(!harness.validate(&grid, &[(0, 1), (1, 0)], &experiment((0, 1), (1, 0), 1.0)));
+        assert!(harness.validate(&grid, &[(0, 0), (1, 0)], &experiment((0, 0), (1, 0), 1.0)));
+    }
+}
@@ -0,0 +1,228 @@
+//! # Percentile Reports
+//! Summaries of benchmark results by algorithm and difficulty bucket. Results
+//! are read from a CSV with the columns `algorithm,bucket,runtime,expansions`,
+//! in any order, where the runtime is in microseconds and a missing expansion
+//! count is left empty. Means hide the slow tail of a planner, so the median,
+//! 95th and 99th percentile of the runtime and expansions are reported
+//! instead, as a markdown or HTML table to attach to an experiment run.
+
+use std::{collections::BTreeMap, fmt::Write as _, io::{self, Write}};
+
+use super::{scenario::Experiment, Report};
+
+/// The result of a benchmark query
+/// ## Fields
+/// * `algorithm` - The name of the algorithm
+/// * `bucket` - The difficulty bucket of the query
+/// * `runtime` - The time taken in microseconds
+/// * `expansions` - The number of nodes expanded, if recorded
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchmarkRow {
+    pub algorithm: String,
+    pub bucket: usize,
+    pub runtime: f64,
+    pub expansions: Option<usize>,
+}
+
+/// The rows of the valid answers of a harness report
+pub fn rows_from_report(report: &Report, experiments: &[Experiment]) -> Vec<BenchmarkRow> {
+    report.results.iter().zip(experiments)
+        .filter(|(result, _)| result.valid)
+        .map(|(result, experiment)| BenchmarkRow {
+            algorithm: report.entry.clone(),
+            bucket: experiment.bucket,
+            runtime: result.total.as_secs_f64() * 1e6,
+            expansions: None,
+        })
+        .collect()
+}
+
+/// Parses benchmark rows from a CSV with a header line
+pub fn parse_benchmark_csv(csv: &str) -> io::Result<Vec<BenchmarkRow>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let mut lines = csv.lines().filter(|l| !l.trim().is_empty());
+    let header = lines.next().ok_or_else(|| invalid("Missing header".to_string()))?;
+    let columns = header.split(',').map(str::trim).collect::<Vec<_>>();
+    let column = |name: &str| columns.iter().position(|c| *c == name).ok_or_else(|| invalid(format!("Missing column {}", name)));
+    let (algorithm, bucket, runtime) = (column("algorithm")?, column("bucket")?, column("runtime")?);
+    let expansions = column("expansions").ok();
+    lines.map(|line| {
+        let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+        let field = |i: usize| fields.get(i).copied().ok_or_else(|| invalid(format!("Invalid row: {}", line)));
+        let number = |i: usize| field(i).and_then(|f| f.parse::<f64>().map_err(|_| invalid(format!("Invalid row: {}", line))));
+        Ok(BenchmarkRow {
+            algorithm: field(algorithm)?.to_string(),
+            bucket: number(bucket)? as usize,
+            runtime: number(runtime)?,
+            expansions: match expansions {
+                Some(i) if !field(i)?.is_empty() => Some(number(i)? as usize),
+                _ => None,
+            },
+        })
+    })
+    .collect()
+}
+
+/// Writes benchmark rows as CSV with a header line
+pub fn write_benchmark_csv(rows: &[BenchmarkRow], writer: &mut impl Write) -> io::Result<()> {
+    writeln!(writer, "algorithm,bucket,runtime,expansions")?;
+    for row in rows {
+        let expansions = row.expansions.map_or(String::new(), |e| e.to_string());
+        writeln!(writer, "{},{},{},{}", row.algorithm, row.bucket, row.runtime, expansions)?;
+    }
+    Ok(())
+}
+
+/// The nearest rank percentile of values, 0 for no values
+/// ## Arguments
+/// * `sorted` - The values in ascending order
+/// * `p` - The percentile in [0, 100]
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// The median, 95th and 99th percentile of a measurement
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Percentiles {
+    pub median: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+impl Percentiles {
+    /// The percentiles of unsorted values
+    pub fn new(mut values: Vec<f64>) -> Self {
+        values.sort_by(f64::total_cmp);
+        Percentiles { median: percentile(&values, 50.0), p95: percentile(&values, 95.0), p99: percentile(&values, 99.0) }
+    }
+}
+
+/// The percentiles of an algorithm on a difficulty bucket
+/// ## Fields
+/// * `algorithm` - The name of the algorithm
+/// * `bucket` - The difficulty bucket
+/// * `queries` - The number of queries
+/// * `runtime` - The percentiles of the runtime in microseconds
+/// * `expansions` - The percentiles of the expansions, `None` unless every
+///   query recorded them
+#[derive(Clone, Debug, PartialEq)]
+pub struct BucketSummary {
+    pub algorithm: String,
+    pub bucket: usize,
+    pub queries: usize,
+    pub runtime: Percentiles,
+    pub expansions: Option<Percentiles>,
+}
+
+/// Summarises rows by algorithm and bucket, ordered by algorithm then bucket
+pub fn summarise(rows: &[BenchmarkRow]) -> Vec<BucketSummary> {
+    let mut groups: BTreeMap<(&str, usize), Vec<&BenchmarkRow>> = BTreeMap::new();
+    for row in rows {
+        groups.entry((&row.algorithm, row.bucket)).or_default().push(row);
+    }
+    groups.into_iter()
+        .map(|((algorithm, bucket), rows)| BucketSummary {
+            algorithm: algorithm.to_string(),
+            bucket,
+            queries: rows.len(),
+            runtime: Percentiles::new(rows.iter().map(|r| r.runtime).collect()),
+            expansions: rows.iter().map(|r| r.expansions.map(|e| e as f64)).collect::<Option<Vec<_>>>().map(Percentiles::new),
+        })
+        .collect()
+}
+
+/// The cells of a row of the report table
+fn cells(summary: &BucketSummary) -> [String; 9] {
+    let expansions = |f: fn(&Percentiles) -> f64| summary.expansions.as_ref().map_or("-".to_string(), |e| format!("{:.0}", f(e)));
+    [
+        summary.algorithm.clone(),
+        summary.bucket.to_string(),
+        summary.queries.to_string(),
+        format!("{:.1}", summary.runtime.median),
+        format!("{:.1}", summary.runtime.p95),
+        format!("{:.1}", summary.runtime.p99),
+        expansions(|e| e.median),
+        expansions(|e| e.p95),
+        expansions(|e| e.p99),
+    ]
+}
+
+/// The column names of the report table
+const COLUMNS: [&str; 9] = [
+    "Algorithm", "Bucket", "Queries", "Median (us)", "p95 (us)", "p99 (us)",
+    "Median expansions", "p95 expansions", "p99 expansions",
+];
+
+/// The report as a markdown table
+pub fn to_markdown(summaries: &[BucketSummary]) -> String {
+    let mut table = format!("| {} |\n|{}\n", COLUMNS.join(" | "), "---|".repeat(COLUMNS.len()));
+    for summary in summaries {
+        writeln!(table, "| {} |", cells(summary).join(" | ")).unwrap();
+    }
+    table
+}
+
+/// The report as an HTML table
+pub fn to_html(summaries: &[BucketSummary]) -> String {
+    let row = |tag: &str, cells: &[String]| {
+        cells.iter().map(|c| format!("<{0}>{1}</{0}>", tag, c)).collect::<String>()
+    };
+    let mut table = format!("<table>\n<tr>{}</tr>\n", row("th", &COLUMNS.map(String::from)));
+    for summary in summaries {
+        writeln!(table, "<tr>{}</tr>", row("td", &cells(summary))).unwrap();
+    }
+    table.push_str("</table>\n");
+    table
+}
+
+/// Summarises a benchmark CSV file and saves the report, as HTML when the
+/// output file ends in `.html` and as markdown otherwise
+pub fn write_report(csv_file: &str, output_file: &str) -> io::Result<Vec<BucketSummary>> {
+    let summaries = summarise(&parse_benchmark_csv(&std::fs::read_to_string(csv_file)?)?);
+    let report = if output_file.ends_with(".html") { to_html(&summaries) } else { to_markdown(&summaries) };
+    std::fs::write(output_file, report)?;
+    Ok(summaries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let values = (1..=100).map(f64::from).collect::<Vec<_>>();
+        assert_eq!(percentile(&values, 50.0), 50.0);
+        assert_eq!(percentile(&values, 99.0), 99.0);
+        assert_eq!(percentile(&values, 0.0), 1.0);
+        assert_eq!(percentile(&[], 50.0), 0.0);
+        assert_eq!(Percentiles::new(vec![3.0, 1.0, 2.0]), Percentiles { median: 2.0, p95: 3.0, p99: 3.0 });
+    }
+
+    #[test]
+    fn test_benchmark_report() {
+        let csv = "bucket,algorithm,runtime,expansions\n0,astar,10,5\n0,astar,30,7\n1,astar,50,\n0,jps,2.5,1\n";
+        let rows = parse_benchmark_csv(csv).unwrap();
+        assert_eq!(rows[2], BenchmarkRow { algorithm: "astar".to_string(), bucket: 1, runtime: 50.0, expansions: None });
+        let summaries = summarise(&rows);
+        assert_eq!(summaries.len(), 3);
+        assert_eq!(summaries[0].queries, 2);
+        assert_eq!(summaries[0].runtime.median, 10.0);
+        assert_eq!(summaries[0].expansions.unwrap().p99, 7.0);
+        assert_eq!(summaries[1].expansions, None);
+        assert_eq!(summaries[2].algorithm, "jps");
+
+        let markdown = to_markdown(&summaries);
+        assert_eq!(markdown.lines().count(), 5);
+        assert_eq!(markdown.lines().nth(3).unwrap(), "| astar | 1 | 1 | 50.0 | 50.0 | 50.0 | - | - | - |");
+        assert_eq!(to_html(&summaries).matches("<tr>").count(), 4);
+        assert!(parse_benchmark_csv("algorithm,runtime\nastar,1\n").is_err());
+
+        let mut written = Vec::new();
+        write_benchmark_csv(&rows, &mut written).unwrap();
+        assert_eq!(parse_benchmark_csv(&String::from_utf8(written).unwrap()).unwrap(), rows);
+    }
+}
@@ -0,0 +1,85 @@
+//! # Scenario Files
+//! Reading and writing of the `.scen` benchmark files used by the Grid-based Path Planning
+//! Competition and the Moving AI benchmark sets. After a `version 1` header
+//! each line is an experiment with tab separated fields:
+//! `bucket map width height start_x start_y goal_x goal_y optimal_length`.
+
+use std::io;
+
+/// A single query of a scenario
+/// ## Fields
+/// * `bucket` - The difficulty bucket of the query
+/// * `map` - The name of the map file
+/// * `width` - The width of the map
+/// * `height` - The height of the map
+/// * `start` - The start cell
+/// * `goal` - The goal cell
+/// * `optimal` - The octile length of an optimal path
+#[derive(Clone, Debug, PartialEq)]
+pub struct Experiment {
+    pub bucket: usize,
+    pub map: String,
+    pub width: usize,
+    pub height: usize,
+    pub start: (usize, usize),
+    pub goal: (usize, usize),
+    pub optimal: f64,
+}
+
+/// Parses the experiments of a scenario
+pub fn parse_scenario(scenario: &str) -> io::Result<Vec<Experiment>> {
+    let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid experiment: {}", line));
+    let mut experiments = Vec::new();
+    for line in scenario.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with("version")) {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 9 {
+            return Err(invalid(line));
+        }
+        let int = |i: usize| fields[i].parse::<usize>().map_err(|_| invalid(line));
+        experiments.push(Experiment {
+            bucket: int(0)?,
+            map: fields[1].to_string(),
+            width: int(2)?,
+            height: int(3)?,
+            start: (int(4)?, int(5)?),
+            goal: (int(6)?, int(7)?),
+            optimal: fields[8].parse().map_err(|_| invalid(line))?,
+        });
+    }
+    Ok(experiments)
+}
+
+/// Reads the experiments of a scenario file
+pub fn load_scenario(filename: &str) -> io::Result<Vec<Experiment>> {
+    parse_scenario(&std::fs::read_to_string(filename)?)
+}
+
+/// Writes experiments in the format of a scenario file
+pub fn write_scenario(experiments: &[Experiment]) -> String {
+    let mut scenario = "version 1\n".to_string();
+    for e in experiments {
+        scenario.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{:.8}\n",
+            e.bucket, e.map, e.width, e.height, e.start.0, e.start.1, e.goal.0, e.goal.1, e.optimal
+        ));
+    }
+    scenario
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scenario() {
+        let scenario = "version 1\n0\tarena.map\t49\t49\t1\t11\t1\t12\t1\n3\tarena.map\t49\t49\t4\t2\t9\t8\t7.24264069\n";
+        let experiments = parse_scenario(scenario).unwrap();
+        assert_eq!(experiments.len(), 2);
+        assert_eq!(experiments[1].bucket, 3);
+        assert_eq!(experiments[1].start, (4, 2));
+        assert_eq!(experiments[1].goal, (9, 8));
+        assert!((experiments[1].optimal - 7.24264069).abs() < 1e-9);
+        assert!(parse_scenario("version 1\n0\tarena.map\t49\n").is_err());
+        assert_eq!(write_scenario(&experiments), scenario.replace("\t1\n", "\t1.00000000\n"));
+    }
+}
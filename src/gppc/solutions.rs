@@ -0,0 +1,181 @@
+//! # Solution Store
+//! A flat-file store of the cost and expansions of every instance solved by
+//! each version of an algorithm, along with a comparator flagging instances
+//! where a newer version regressed. Each line of the file is a tab separated
+//! record `algorithm version instance cost expansions`, where a missing cost
+//! or expansion count is written as `-`, so stores are easy to diff and merge.
+
+use std::{collections::BTreeMap, fmt::Write as _, io};
+
+use super::{scenario::Experiment, Report};
+
+/// The outcome of an instance for a version of an algorithm
+/// ## Fields
+/// * `cost` - The cost of the solution, `None` if unsolved
+/// * `expansions` - The number of nodes expanded, `None` if not recorded
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Outcome {
+    pub cost: Option<f64>,
+    pub expansions: Option<usize>,
+}
+
+/// A way in which an instance regressed between versions
+#[derive(Clone, Debug, PartialEq)]
+pub enum Regression {
+    /// The instance is no longer solved
+    Unsolved { instance: String },
+    /// The cost of the solution increased
+    Cost { instance: String, baseline: f64, candidate: f64 },
+    /// The number of expansions increased
+    Expansions { instance: String, baseline: usize, candidate: usize },
+}
+
+/// The outcomes of instances indexed by (algorithm, version, instance)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SolutionStore {
+    pub records: BTreeMap<(String, String, String), Outcome>,
+}
+
+/// The identifier of an experiment as `map:start_x,start_y:goal_x,goal_y`
+pub fn instance_id(experiment: &Experiment) -> String {
+    let Experiment { map, start, goal, .. } = experiment;
+    format!("{}:{},{}:{},{}", map, start.0, start.1, goal.0, goal.1)
+}
+
+impl SolutionStore {
+    pub fn new() -> Self {
+        SolutionStore::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Records the outcome of an instance, replacing any previous outcome
+    pub fn insert(&mut self, algorithm: &str, version: &str, instance: &str, outcome: Outcome) {
+        self.records.insert((algorithm.to_string(), version.to_string(), instance.to_string()), outcome);
+    }
+
+    /// The outcome of an instance
+    pub fn get(&self, algorithm: &str, version: &str, instance: &str) -> Option<&Outcome> {
+        self.records.get(&(algorithm.to_string(), version.to_string(), instance.to_string()))
+    }
+
+    /// Records the length of every valid path of a harness report
+    /// ## Arguments
+    /// * `version` - The version of the entry that produced the report
+    /// * `report` - The report, whose entry name is used as the algorithm
+    /// * `experiments` - The experiments the report was run on
+    pub fn record_report(&mut self, version: &str, report: &Report, experiments: &[Experiment]) {
+        for (result, experiment) in report.results.iter().zip(experiments) {
+            let outcome = Outcome { cost: result.valid.then_some(result.length), expansions: None };
+            self.insert(&report.entry, version, &instance_id(experiment), outcome);
+        }
+    }
+
+    /// Compares the instances recorded for two versions of an algorithm
+    /// ## Arguments
+    /// * `tolerance` - The increase in cost ignored as floating point error
+    /// ## Returns
+    /// The regressions of the candidate on instances recorded for both versions
+    pub fn compare(&self, algorithm: &str, baseline: &str, candidate: &str, tolerance: f64) -> Vec<Regression> {
+        let mut regressions = Vec::new();
+        let baselines = self.records.iter().filter(|((a, v, _), _)| a == algorithm && v == baseline);
+        for ((_, _, instance), old) in baselines {
+            let Some(new) = self.get(algorithm, candidate, instance) else { continue };
+            match (old.cost, new.cost) {
+                (Some(_), None) => regressions.push(Regression::Unsolved { instance: instance.clone() }),
+                (Some(b), Some(c)) if c > b + tolerance => {
+                    regressions.push(Regression::Cost { instance: instance.clone(), baseline: b, candidate: c })
+                }
+                _ => {}
+            }
+            if let (Some(b), Some(c)) = (old.expansions, new.expansions) {
+                if c > b {
+                    regressions.push(Regression::Expansions { instance: instance.clone(), baseline: b, candidate: c });
+                }
+            }
+        }
+        regressions
+    }
+
+    /// Parses a store from its flat-file format
+    pub fn parse(store: &str) -> io::Result<Self> {
+        let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid record: {}", line));
+        let mut solutions = SolutionStore::new();
+        for line in store.lines().filter(|l| !l.trim().is_empty()) {
+            let fields = line.split('\t').collect::<Vec<_>>();
+            if fields.len() != 5 {
+                return Err(invalid(line));
+            }
+            let cost = match fields[3] {
+                "-" => None,
+                c => Some(c.parse().map_err(|_| invalid(line))?),
+            };
+            let expansions = match fields[4] {
+                "-" => None,
+                e => Some(e.parse().map_err(|_| invalid(line))?),
+            };
+            solutions.insert(fields[0], fields[1], fields[2], Outcome { cost, expansions });
+        }
+        Ok(solutions)
+    }
+
+    /// Writes the store in its flat-file format, ordered by algorithm, version and instance
+    pub fn write(&self) -> String {
+        let mut store = String::new();
+        for ((algorithm, version, instance), outcome) in &self.records {
+            let cost = outcome.cost.map_or("-".to_string(), |c| c.to_string());
+            let expansions = outcome.expansions.map_or("-".to_string(), |e| e.to_string());
+            writeln!(store, "{}\t{}\t{}\t{}\t{}", algorithm, version, instance, cost, expansions).unwrap();
+        }
+        store
+    }
+
+    /// Reads a store from a file
+    pub fn load(filename: &str) -> io::Result<Self> {
+        SolutionStore::parse(&std::fs::read_to_string(filename)?)
+    }
+
+    /// Writes the store to a file
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        std::fs::write(filename, self.write())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solution_store() {
+        let mut store = SolutionStore::new();
+        let outcome = |cost, expansions| Outcome { cost, expansions };
+        store.insert("astar", "1", "a", outcome(Some(4.0), Some(10)));
+        store.insert("astar", "1", "b", outcome(Some(2.5), Some(8)));
+        store.insert("astar", "1", "c", outcome(Some(1.0), None));
+        store.insert("astar", "2", "a", outcome(Some(4.0), Some(12)));
+        store.insert("astar", "2", "b", outcome(Some(3.0), Some(6)));
+        store.insert("astar", "2", "c", outcome(None, None));
+        store.insert("jps", "1", "a", outcome(Some(9.0), None));
+        assert_eq!(store.compare("astar", "1", "2", 1e-9), vec![
+            Regression::Expansions { instance: "a".to_string(), baseline: 10, candidate: 12 },
+            Regression::Cost { instance: "b".to_string(), baseline: 2.5, candidate: 3.0 },
+            Regression::Unsolved { instance: "c".to_string() },
+        ]);
+        assert_eq!(store.compare("astar", "2", "1", 1e-9), vec![
+            Regression::Expansions { instance: "b".to_string(), baseline: 6, candidate: 8 },
+        ]);
+
+        let filename = std::env::temp_dir().join("pathfinding_rs_solution_store.tsv");
+        let filename = filename.to_str().unwrap();
+        store.save(filename).unwrap();
+        assert_eq!(SolutionStore::load(filename).unwrap(), store);
+        std::fs::remove_file(filename).unwrap();
+        assert!(SolutionStore::parse("astar\t1\ta\t4\n").is_err());
+    }
+}
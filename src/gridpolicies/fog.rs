@@ -0,0 +1,148 @@
+//! # Fog of War
+//! An expansion policy over sampling grids under partial observability. Cells
+//! whose belief has been observed are traversable when they are more likely
+//! free than blocked, while cells that were never observed are treated by an
+//! optimism policy. Planned paths are marked with the first unknown cell they
+//! cross, where the agent has to sense again, which gives the classic
+//! freespace-assumption navigation loop of sense, plan and move.
+
+use crate::domains::samplegrid::SampleGrid;
+use crate::heuristics::distance::manhattan_distance;
+use crate::search::astar::astar;
+
+/// How cells that were never observed are treated
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Optimism {
+    /// Unknown cells are free, the freespace assumption
+    Free,
+    /// Unknown cells are obstacles
+    Blocked,
+    /// Unknown cells are free when their prior belief exceeds a threshold
+    Prior(f32),
+    /// Unknown cells are free but every move into one has an extra cost
+    Penalty(usize),
+}
+
+/// A planned path through partially observed cells
+/// ## Fields
+/// * `path` - The cells of the path
+/// * `cost` - The cost of the path including penalties
+/// * `first_unknown` - The index of the first cell of the path that was never observed
+#[derive(Clone, Debug, PartialEq)]
+pub struct FogPath {
+    pub path: Vec<(usize, usize)>,
+    pub cost: usize,
+    pub first_unknown: Option<usize>,
+}
+
+/// An expander over the observed and unknown cells of a sampling grid
+pub struct FogExpander<'a> {
+    pub grid: &'a SampleGrid,
+    pub optimism: Optimism,
+}
+
+impl<'a> FogExpander<'a> {
+    pub fn new(grid: &'a SampleGrid, optimism: Optimism) -> Self {
+        FogExpander { grid, optimism }
+    }
+
+    /// The cost of moving into a cell, `None` if the cell is not traversable
+    pub fn cost(&self, (x, y): (usize, usize)) -> Option<usize> {
+        let belief = self.grid.belief((x, y));
+        if self.grid.is_observed((x, y)) {
+            return (belief > 0.5).then_some(1);
+        }
+        match self.optimism {
+            Optimism::Free => Some(1),
+            Optimism::Blocked => None,
+            Optimism::Prior(threshold) => (belief > threshold).then_some(1),
+            Optimism::Penalty(penalty) => Some(1 + penalty),
+        }
+    }
+
+    /// Get the traversable 4-connected neighbors of a cell with their costs
+    pub fn adjacent1(&self, node: (usize, usize)) -> impl Iterator<Item = ((usize, usize), usize)> + '_ {
+        self.grid.adjacent(node, false).filter_map(|n| self.cost(n).map(|c| (n, c)))
+    }
+
+    /// The index of the first cell of a path that was never observed
+    pub fn first_unknown(&self, path: &[(usize, usize)]) -> Option<usize> {
+        path.iter().position(|n| !self.grid.is_observed(*n))
+    }
+
+    /// Plans a path under the optimism policy
+    pub fn plan(&self, start: (usize, usize), goal: (usize, usize)) -> Option<FogPath> {
+        let (path, cost) = astar(|n| self.adjacent1(*n), start, |n| *n == goal, |n| manhattan_distance(*n, goal))?;
+        let first_unknown = self.first_unknown(&path);
+        Some(FogPath { path, cost, first_unknown })
+    }
+}
+
+/// Navigates to a goal by repeatedly sensing the cells around the agent,
+/// planning under the optimism policy and moving until the next move would enter
+/// a cell that was unknown when planning
+/// ## Arguments
+/// * `grid` - The sampling grid, whose ground truth is sensed
+/// * `radius` - The radius of the square sensed around the agent each step
+/// * `max_steps` - The number of moves after which navigation is abandoned
+/// ## Returns
+/// The cells visited by the agent, `None` if the goal is unreachable
+pub fn navigate(
+    grid: &mut SampleGrid,
+    start: (usize, usize),
+    goal: (usize, usize),
+    optimism: Optimism,
+    radius: usize,
+    max_steps: usize,
+) -> Option<Vec<(usize, usize)>> {
+    let mut trajectory = vec![start];
+    let mut current = start;
+    while current != goal && trajectory.len() <= max_steps {
+        let (x, y) = current;
+        for x in x.saturating_sub(radius)..(x + radius + 1).min(grid.width) {
+            for y in y.saturating_sub(radius)..(y + radius + 1).min(grid.height) {
+                grid.update_node((x, y), 0.0);
+            }
+        }
+        let plan = FogExpander::new(grid, optimism).plan(current, goal)?;
+        // Moves along the observed prefix of the plan, and always at least one step
+        let end = plan.first_unknown.unwrap_or(plan.path.len()).max(2);
+        for node in &plan.path[1..end] {
+            current = *node;
+            trajectory.push(current);
+        }
+    }
+    (current == goal).then_some(trajectory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fog_optimism() {
+        let mut grid = SampleGrid::new_from_string(".....\n.....\n.@@@.\n.....\n".to_string());
+        grid.update_node((2, 2), 0.0);
+        assert!(grid.is_observed((2, 2)) && !grid.is_observed((1, 2)));
+        let plan = FogExpander::new(&grid, Optimism::Free).plan((2, 0), (2, 3)).unwrap();
+        assert_eq!((plan.cost, plan.first_unknown), (5, Some(0)));
+        assert!(FogExpander::new(&grid, Optimism::Blocked).plan((2, 0), (2, 3)).is_none());
+        let plan = FogExpander::new(&grid, Optimism::Penalty(2)).plan((2, 0), (2, 3)).unwrap();
+        assert_eq!(plan.cost, 15);
+    }
+
+    #[test]
+    fn test_fog_navigate() {
+        let mut grid = SampleGrid::new_from_string("......\n.@@@@.\n......\n".to_string());
+        for x in 0..6 {
+            for y in 0..3 {
+                grid.set_belief((x, y), 1.0);
+            }
+        }
+        let trajectory = navigate(&mut grid, (2, 0), (2, 2), Optimism::Free, 1, 50).unwrap();
+        assert_eq!(trajectory.last(), Some(&(2, 2)));
+        assert!(trajectory.windows(2).all(|m| manhattan_distance(m[0], m[1]) == 1));
+        assert!(trajectory.iter().all(|n| grid.ground_truth.get_bit_value(*n)));
+        assert!(trajectory.len() > 5);
+    }
+}
@@ -1,3 +1,7 @@
 //! # Expansion policies
 
-pub mod fovexpansion;
\ No newline at end of file
+pub mod fovexpansion;
+#[cfg(feature = "search-core")]
+pub mod rsr;
+#[cfg(feature = "sampling")]
+pub mod fog;
\ No newline at end of file
@@ -0,0 +1,156 @@
+//! # Rectangular Symmetry Reduction
+//! Rectangular Symmetry Reduction (RSR) for 4-connected grids, as described by
+//! Harabor and Botea. Preprocessing decomposes the free cells into empty
+//! rectangular rooms. Every path through a room is symmetric to one that only
+//! touches its perimeter, so the interior cells of each room are pruned and
+//! perimeter cells gain macro edges straight across the room. Searches then only
+//! expand perimeter cells, which complements jump point search on maps with
+//! large open halls. The start and goal are connected to the perimeter of their
+//! rooms when they lie in an interior, and paths remain optimal.
+
+use crate::analysis::regions::Regions;
+use crate::domains::{bitpackedgrid::BitPackedGrid, grid::Grid};
+use crate::heuristics::distance::manhattan_distance;
+
+/// An empty rectangle of free cells
+/// ## Fields
+/// * `origin` - The top left cell of the room
+/// * `width` - The width of the room
+/// * `height` - The height of the room
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Room {
+    pub origin: (usize, usize),
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Room {
+    /// The bottom right cell of the room
+    fn end(&self) -> (usize, usize) {
+        (self.origin.0 + self.width - 1, self.origin.1 + self.height - 1)
+    }
+
+    /// Checks if a cell is inside the room and not on its perimeter
+    pub fn is_interior(&self, (x, y): (usize, usize)) -> bool {
+        let (x1, y1) = self.end();
+        x > self.origin.0 && x < x1 && y > self.origin.1 && y < y1
+    }
+}
+
+/// The rooms of a grid and the room of each free cell
+/// ## Fields
+/// * `rooms` - The rooms of the decomposition
+/// * `room_of` - The room id of every cell, `None` for obstacles
+pub struct Rsr {
+    pub rooms: Vec<Room>,
+    pub room_of: Grid<Option<usize>>,
+}
+
+impl Rsr {
+    /// Decomposes the free cells of a grid into rooms, the rectangular regions
+    /// grown right and then down from the first unassigned free cell
+    pub fn new(grid: &BitPackedGrid) -> Self {
+        let regions = Regions::new(grid, 0);
+        let rooms = regions.regions.iter()
+            .map(|r| Room { origin: r.origin, width: r.width, height: r.height })
+            .collect();
+        let room_of = Grid::from_fn(grid.original_width, grid.original_height, |n| regions.region_of(n));
+        Rsr { rooms, room_of }
+    }
+
+    /// The number of interior cells pruned from searches
+    pub fn pruned_cells(&self) -> usize {
+        self.rooms.iter().map(|r| r.width.saturating_sub(2) * r.height.saturating_sub(2)).sum()
+    }
+
+    /// The room containing a cell
+    pub fn room(&self, node: (usize, usize)) -> Option<&Room> {
+        self.room_of.get(node).copied().flatten().map(|id| &self.rooms[id])
+    }
+
+    /// The successors of a cell in a search towards a goal. Perimeter cells
+    /// move along the perimeter, into neighbouring rooms and straight across
+    /// their room. An interior cell can only be the start, which moves
+    /// straight to each side of its room.
+    pub fn successors(&self, grid: &BitPackedGrid, node: (usize, usize), goal: (usize, usize)) -> Vec<((usize, usize), usize)> {
+        let Some(room) = self.room(node) else { return Vec::new() };
+        let same_room = self.room(goal) == Some(room);
+        let (x, y) = node;
+        let (x0, y0, (x1, y1)) = (room.origin.0, room.origin.1, room.end());
+        let mut successors = Vec::new();
+        if room.is_interior(node) {
+            if same_room {
+                successors.push((goal, manhattan_distance(node, goal)));
+            }
+            successors.extend([(x0, y), (x1, y), (x, y0), (x, y1)].map(|n| (n, manhattan_distance(node, n))));
+            return successors;
+        }
+        successors.extend(grid.adjacent1(node).filter(|(n, _)| grid.bounds_check(*n) && !room.is_interior(*n)));
+        if room.width > 2 && (x == x0 || x == x1) {
+            successors.push(((x0 + x1 - x, y), room.width - 1));
+        }
+        if room.height > 2 && (y == y0 || y == y1) {
+            successors.push(((x, y0 + y1 - y), room.height - 1));
+        }
+        if same_room && room.is_interior(goal) && (goal.0 == x || goal.1 == y) {
+            successors.push((goal, manhattan_distance(node, goal)));
+        }
+        successors
+    }
+
+    /// Expands the macro edges of a path into unit moves. Every macro edge lies
+    /// within an empty room, so moving horizontally and then vertically is free.
+    pub fn refine(&self, path: &[(usize, usize)]) -> Vec<(usize, usize)> {
+        let mut refined = path.first().into_iter().copied().collect::<Vec<_>>();
+        for pair in path.windows(2) {
+            let ((mut x, mut y), (gx, gy)) = (pair[0], pair[1]);
+            while x != gx {
+                x = if x < gx { x + 1 } else { x - 1 };
+                refined.push((x, y));
+            }
+            while y != gy {
+                y = if y < gy { y + 1 } else { y - 1 };
+                refined.push((x, y));
+            }
+        }
+        refined
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::search::astar::{astar, astar_with_expanded_set};
+
+    #[test]
+    fn test_rsr_decomposition() {
+        let grid = BitPackedGrid::new_from_string("....@\n....@\n....@\n@@...\n".to_string());
+        let rsr = Rsr::new(&grid);
+        assert_eq!(rsr.rooms[0], Room { origin: (0, 0), width: 4, height: 3 });
+        assert_eq!(rsr.rooms.len(), 2);
+        assert_eq!(rsr.pruned_cells(), 2);
+        assert!(rsr.rooms[0].is_interior((2, 1)) && !rsr.rooms[0].is_interior((3, 1)));
+        assert_eq!(rsr.room((4, 0)), None);
+    }
+
+    #[test]
+    fn test_rsr_search() {
+        let grid = BitPackedGrid::new_from_string(
+            "..........\n..........\n..........\n..........\n@@@@@@@@.@\n..........\n..........\n".to_string()
+        );
+        let rsr = Rsr::new(&grid);
+        for (start, goal) in [((1, 1), (3, 6)), ((0, 0), (9, 6)), ((4, 2), (2, 1)), ((5, 0), (6, 2)), ((8, 4), (8, 1))] {
+            let heuristic = |n: &(usize, usize)| manhattan_distance(*n, goal);
+            let (_, optimal) = astar(|n| grid.adjacent1(*n).filter(|(n, _)| grid.bounds_check(*n)), start, |n| *n == goal, heuristic).unwrap();
+            let mut expanded = HashSet::new();
+            let (path, cost) = astar_with_expanded_set(|n| rsr.successors(&grid, *n, goal), start, |n| *n == goal, Some(&mut expanded), heuristic).unwrap();
+            assert_eq!(cost, optimal);
+            let refined = rsr.refine(&path);
+            assert_eq!(refined.len(), optimal + 1);
+            assert!(refined.windows(2).all(|p| manhattan_distance(p[0], p[1]) == 1 && grid.get_bit_value(p[1])));
+            assert!(expanded.iter().filter(|n| **n != start).all(|n| *n == goal || !rsr.room(*n).unwrap().is_interior(*n)));
+        }
+    }
+}
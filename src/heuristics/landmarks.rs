@@ -0,0 +1,308 @@
+//! # Landmark Heuristics
+//! Differential heuristics for A-Star, known as ALT (A-Star, Landmarks and the
+//! Triangle inequality), following Goldberg and Harrelson, "Computing the
+//! Shortest Path: A* Search Meets Graph Theory" (2005). A few landmarks are
+//! placed on the domain, each as far as possible from those already placed,
+//! and the distance from every landmark to every node is precomputed by
+//! Dijkstra's algorithm. By the triangle inequality the distance from a node
+//! to a goal is at least the difference of their distances from a landmark,
+//! so the largest difference over the landmarks is an admissible heuristic.
+//! Tables store a `u32` per landmark and node, and can be saved to a file so
+//! preprocessing is reused across runs.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::hash::Hash;
+use std::io;
+
+use crate::search::domain::SearchDomain;
+use crate::util::determinism::{Map, Set};
+use crate::util::preprocessing::{par_build, BuildStats};
+
+/// The distance stored for nodes a landmark cannot reach
+const UNREACHABLE: u32 = u32::MAX;
+
+/// A node that can be saved in landmark tables
+pub trait LandmarkNode: Hash + Eq + Clone {
+    /// The number of bytes of an encoded node
+    const BYTES: usize;
+
+    /// Appends the encoding of the node to a buffer
+    fn encode(&self, bytes: &mut Vec<u8>);
+
+    /// Decodes a node from `BYTES` bytes
+    fn decode(bytes: &[u8]) -> Self;
+}
+
+/// Reads a little-endian `u32` at an offset of a buffer
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+impl LandmarkNode for (usize, usize) {
+    const BYTES: usize = 8;
+
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.extend((self.0 as u32).to_le_bytes());
+        bytes.extend((self.1 as u32).to_le_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        (read_u32(bytes, 0) as usize, read_u32(bytes, 4) as usize)
+    }
+}
+
+impl LandmarkNode for usize {
+    const BYTES: usize = 4;
+
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.extend((*self as u32).to_le_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        read_u32(bytes, 0) as usize
+    }
+}
+
+/// Precomputed distances from landmarks to every node reachable from a start
+/// ## Fields
+/// * `nodes` - The nodes of the tables, indexed by their position
+/// * `landmarks` - The index of each landmark in `nodes`
+/// * `tables` - The distance from each landmark to each node
+/// * `symmetric` - Whether every edge has a reverse of the same cost, such as
+///   on grids, which also bounds the distance with the distance to landmarks
+#[derive(Clone, Debug, PartialEq)]
+pub struct Landmarks<N: Hash + Eq> {
+    pub nodes: Vec<N>,
+    pub landmarks: Vec<usize>,
+    pub tables: Vec<Vec<u32>>,
+    pub symmetric: bool,
+    index: Map<N, usize>,
+}
+
+impl<N: Hash + Eq + Clone> Landmarks<N> {
+    /// Places landmarks on a domain with farthest-point placement, the first
+    /// landmark being the node farthest from the start and each next the node
+    /// farthest from the landmarks already placed. Placement runs on the
+    /// calling thread, then the tables are built in parallel.
+    /// ## Arguments
+    /// * `domain` - The domain, whose distances are below `u32::MAX`
+    /// * `start` - A node of the component the landmarks are placed on
+    /// * `count` - The number of landmarks
+    /// * `symmetric` - Whether every edge of the domain has a reverse of the same cost
+    /// ## Returns
+    /// The landmarks and the statistics of building their tables
+    pub fn new<D>(domain: &D, start: N, count: usize, symmetric: bool) -> (Self, BuildStats)
+    where
+        D: SearchDomain<Node = N> + Sync,
+        D::Cost: TryInto<u64>,
+        N: Sync,
+    {
+        let nodes = reachable(domain, &start);
+        let index = nodes.iter().cloned().enumerate().map(|(i, n)| (n, i)).collect::<Map<_, _>>();
+        let mut nearest = dijkstra(domain, &nodes, &index, 0);
+        let mut landmarks = Vec::new();
+        for _ in 0..count.min(nodes.len()) {
+            let farthest = (0..nearest.len()).filter(|i| nearest[*i] != UNREACHABLE).max_by_key(|i| nearest[*i]);
+            let Some(farthest) = farthest.filter(|i| !landmarks.contains(i)) else { break };
+            landmarks.push(farthest);
+            if landmarks.len() < count {
+                let table = dijkstra(domain, &nodes, &index, farthest);
+                for (near, distance) in nearest.iter_mut().zip(&table) {
+                    *near = (*near).min(*distance);
+                }
+            }
+        }
+        let (tables, stats) = par_build(&landmarks, |l| dijkstra(domain, &nodes, &index, *l));
+        (Landmarks { nodes, landmarks, tables, symmetric, index }, stats)
+    }
+
+    /// The distance from a landmark to a node, `None` if it is unreachable
+    pub fn distance(&self, landmark: usize, node: &N) -> Option<u64> {
+        let i = *self.index.get(node)?;
+        Some(self.tables[landmark][i]).filter(|d| *d != UNREACHABLE).map(u64::from)
+    }
+
+    /// The ALT heuristic between a node and a goal, 0 for nodes outside the tables
+    pub fn heuristic(&self, node: &N, goal: &N) -> u64 {
+        let (Some(&n), Some(&g)) = (self.index.get(node), self.index.get(goal)) else { return 0 };
+        self.tables.iter()
+            .filter(|t| t[n] != UNREACHABLE && t[g] != UNREACHABLE)
+            .map(|t| {
+                let (n, g) = (t[n] as u64, t[g] as u64);
+                if self.symmetric { n.abs_diff(g) } else { g.saturating_sub(n) }
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl<N: LandmarkNode> Landmarks<N> {
+    /// Encodes the tables as a count of landmarks and nodes, the symmetric
+    /// flag, the nodes, the landmarks and the distances in little-endian order
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend((self.landmarks.len() as u32).to_le_bytes());
+        bytes.extend((self.nodes.len() as u32).to_le_bytes());
+        bytes.push(self.symmetric as u8);
+        self.nodes.iter().for_each(|n| n.encode(&mut bytes));
+        self.landmarks.iter().for_each(|l| bytes.extend((*l as u32).to_le_bytes()));
+        self.tables.iter().flatten().for_each(|d| bytes.extend(d.to_le_bytes()));
+        bytes
+    }
+
+    /// Decodes tables encoded by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "truncated landmark tables");
+        if bytes.len() < 9 {
+            return Err(invalid());
+        }
+        let (count, len) = (read_u32(bytes, 0) as usize, read_u32(bytes, 4) as usize);
+        let tables_start = len.checked_mul(N::BYTES)
+            .and_then(|nodes| count.checked_mul(4)?.checked_add(nodes)?.checked_add(9))
+            .ok_or_else(invalid)?;
+        let tables_len = count.checked_mul(len).and_then(|cells| cells.checked_mul(4)).ok_or_else(invalid)?;
+        if tables_start.checked_add(tables_len) != Some(bytes.len()) {
+            return Err(invalid());
+        }
+        let nodes = (0..len).map(|i| N::decode(&bytes[9 + i * N::BYTES..])).collect::<Vec<_>>();
+        let landmarks = (0..count).map(|i| read_u32(bytes, 9 + len * N::BYTES + i * 4) as usize).collect();
+        let tables = (0..count)
+            .map(|l| (0..len).map(|i| read_u32(bytes, tables_start + (l * len + i) * 4)).collect())
+            .collect();
+        let index = nodes.iter().cloned().enumerate().map(|(i, n)| (n, i)).collect();
+        Ok(Landmarks { nodes, landmarks, tables, symmetric: bytes[8] != 0, index })
+    }
+
+    /// Saves the tables to a file
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        std::fs::write(filename, self.to_bytes())
+    }
+
+    /// Loads tables saved by `save`
+    pub fn load(filename: &str) -> io::Result<Self> {
+        Landmarks::from_bytes(&std::fs::read(filename)?)
+    }
+}
+
+/// The nodes reachable from a start in the order they are reached
+fn reachable<D: SearchDomain>(domain: &D, start: &D::Node) -> Vec<D::Node> {
+    let mut seen = Set::default();
+    seen.insert(start.clone());
+    let mut nodes = vec![start.clone()];
+    let mut i = 0;
+    while i < nodes.len() {
+        for (child, _) in domain.successors(&nodes[i]) {
+            if seen.insert(child.clone()) {
+                nodes.push(child);
+            }
+        }
+        i += 1;
+    }
+    nodes
+}
+
+/// Dijkstra's algorithm from a source over the indexed nodes of a domain
+/// ## Returns
+/// The distance to each indexed node
+fn dijkstra<D>(domain: &D, nodes: &[D::Node], index: &Map<D::Node, usize>, source: usize) -> Vec<u32>
+where
+    D: SearchDomain,
+    D::Cost: TryInto<u64>,
+{
+    let mut distances = vec![UNREACHABLE; nodes.len()];
+    distances[source] = 0;
+    let mut open = BinaryHeap::from([Reverse((0u64, source))]);
+    while let Some(Reverse((distance, i))) = open.pop() {
+        if distance > distances[i] as u64 {
+            continue;
+        }
+        for (child, cost) in domain.successors(&nodes[i]) {
+            let Some(&j) = index.get(&child) else { continue };
+            let total = distance.saturating_add(cost.try_into().unwrap_or(u64::MAX));
+            if total < distances[j] as u64 {
+                distances[j] = total as u32;
+                open.push(Reverse((total, j)));
+            }
+        }
+    }
+    distances
+}
+
+/// A domain searched with the ALT heuristic of its landmarks
+/// ## Fields
+/// * `domain` - The domain searched
+/// * `landmarks` - The landmarks placed on the domain
+pub struct WithLandmarks<'a, D: SearchDomain> {
+    pub domain: &'a D,
+    pub landmarks: &'a Landmarks<D::Node>,
+}
+
+impl<'g, D> SearchDomain for WithLandmarks<'g, D>
+where
+    D: SearchDomain,
+    D::Cost: TryFrom<u64>,
+{
+    type Node = D::Node;
+    type Cost = D::Cost;
+
+    fn successors<'a>(&'a self, node: &D::Node) -> impl Iterator<Item = (D::Node, D::Cost)> + use<'a, 'g, D> {
+        self.domain.successors(node)
+    }
+
+    fn heuristic(&self, node: &D::Node, goal: &D::Node) -> D::Cost {
+        D::Cost::try_from(self.landmarks.heuristic(node, goal)).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::{bitpackedgrid::BitPackedGrid, test_queries};
+    use crate::domains::graph::parse_dimacs;
+    use crate::search::domain::{search, search_with_stats};
+
+    #[test]
+    fn test_landmarks_grid() {
+        let grid = BitPackedGrid::new_from_file("tests/map.map");
+        let start = grid.free_cells().next().unwrap();
+        let (landmarks, stats) = Landmarks::new(&grid, start, 4, true);
+        assert_eq!((landmarks.landmarks.len(), stats.items), (4, 4));
+        let free = landmarks.nodes.clone();
+        let (mut alt, mut manhattan) = (0, 0);
+        for (from, to) in test_queries(&free, 20) {
+            let (optimal, stats) = search_with_stats(&grid, from, to);
+            let cost = optimal.unwrap().1;
+            assert!(landmarks.heuristic(&from, &to) as usize <= cost);
+            let (found, alt_stats) = search_with_stats(&WithLandmarks { domain: &grid, landmarks: &landmarks }, from, to);
+            assert_eq!(found.unwrap().1, cost);
+            (alt, manhattan) = (alt + alt_stats.expanded, manhattan + stats.expanded);
+        }
+        assert!(alt < manhattan);
+
+        let file = std::env::temp_dir().join("pathfinding-rs-landmarks.dat");
+        let file = file.to_str().unwrap();
+        landmarks.save(file).unwrap();
+        let loaded = Landmarks::<(usize, usize)>::load(file).unwrap();
+        std::fs::remove_file(file).unwrap();
+        assert_eq!(loaded, landmarks);
+        assert!(Landmarks::<(usize, usize)>::from_bytes(&landmarks.to_bytes()[..20]).is_err());
+        // Counts whose table size overflows are rejected rather than indexed
+        let mut overflow = vec![0xff; 8];
+        overflow.push(1);
+        assert!(Landmarks::<(usize, usize)>::from_bytes(&overflow).is_err());
+    }
+
+    #[test]
+    fn test_landmarks_graph() {
+        let graph = parse_dimacs("p sp 4 5\na 1 2 10\na 2 4 10\na 1 3 12\na 3 4 15\na 4 1 20\n", "").unwrap();
+        let (landmarks, _) = Landmarks::new(&graph, 0, 2, false);
+        for from in 0..4 {
+            for to in 0..4 {
+                let optimal = search(&graph, from, to).unwrap().1;
+                assert!(landmarks.heuristic(&from, &to) <= optimal, "{} to {}", from, to);
+            }
+        }
+        assert_eq!(Landmarks::<usize>::from_bytes(&landmarks.to_bytes()).unwrap(), landmarks);
+    }
+}
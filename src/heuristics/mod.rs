@@ -4,6 +4,11 @@
 //! * Euclidean Distance
 //! * Chebyshev Distance
 //! * Octile Distance
+//! * Exact Octile Costs, integer pairs of straight and diagonal moves
+//! * Landmarks, precomputed differential heuristics (ALT) for grids and graphs
 
 #![allow(dead_code)]
-pub mod distance;
\ No newline at end of file
+pub mod distance;
+pub mod octile;
+#[cfg(feature = "search-core")]
+pub mod landmarks;
\ No newline at end of file
@@ -0,0 +1,118 @@
+//! # Exact Octile Costs
+//! Octile path costs held as a pair of integer counts of straight and diagonal
+//! moves instead of an accumulated float. A cost of `a + b * sqrt(2)` is
+//! compared exactly with integer arithmetic, so equal costs always tie and long
+//! paths do not drift, making searches on octile grids deterministic.
+
+use std::{cmp::Ordering, iter::Sum, ops::Add};
+
+use crate::domains::bitpackedgrid::BitPackedGrid;
+
+/// An octile cost of `straight + diagonal * sqrt(2)`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct OctileCost {
+    pub straight: u64,
+    pub diagonal: u64,
+}
+
+impl OctileCost {
+    /// The cost of one straight move
+    pub const STRAIGHT: OctileCost = OctileCost { straight: 1, diagonal: 0 };
+    /// The cost of one diagonal move
+    pub const DIAGONAL: OctileCost = OctileCost { straight: 0, diagonal: 1 };
+
+    pub fn new(straight: u64, diagonal: u64) -> Self {
+        OctileCost { straight, diagonal }
+    }
+
+    /// The cost of a move between two adjacent cells
+    pub fn step((x1, y1): (usize, usize), (x2, y2): (usize, usize)) -> Self {
+        if x1 != x2 && y1 != y2 { OctileCost::DIAGONAL } else { OctileCost::STRAIGHT }
+    }
+
+    /// The approximate value of the cost, for reporting only
+    pub fn to_f64(&self) -> f64 {
+        self.straight as f64 + self.diagonal as f64 * std::f64::consts::SQRT_2
+    }
+}
+
+impl Ord for OctileCost {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Compares a1 + b1 * sqrt(2) with a2 + b2 * sqrt(2) as da with db * sqrt(2)
+        let da = self.straight as i128 - other.straight as i128;
+        let db = other.diagonal as i128 - self.diagonal as i128;
+        match (da.signum(), db.signum()) {
+            (a, b) if a == b => {
+                let squares = (da * da).cmp(&(2 * db * db));
+                if a < 0 { squares.reverse() } else { squares }
+            }
+            (a, b) => a.cmp(&b),
+        }
+    }
+}
+
+impl PartialOrd for OctileCost {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for OctileCost {
+    type Output = OctileCost;
+
+    fn add(self, other: Self) -> Self {
+        OctileCost::new(self.straight + other.straight, self.diagonal + other.diagonal)
+    }
+}
+
+impl Sum for OctileCost {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(OctileCost::default(), Add::add)
+    }
+}
+
+/// The exact octile distance between two cells
+pub fn exact_octile_distance((x1, y1): (usize, usize), (x2, y2): (usize, usize)) -> OctileCost {
+    let (dx, dy) = (x1.abs_diff(x2) as u64, y1.abs_diff(y2) as u64);
+    OctileCost::new(dx.max(dy) - dx.min(dy), dx.min(dy))
+}
+
+impl BitPackedGrid {
+    /// Get the free 8-connected neighbors of a cell with their exact octile
+    /// costs, diagonal moves may not cut the corner of an obstacle
+    pub fn adjacent_octile(&self, (x, y): (usize, usize)) -> impl Iterator<Item = ((usize, usize), OctileCost)> + '_ {
+        self.adjacent((x, y), true)
+            .filter(move |&(nx, ny)| self.bounds_check((nx, ny)) && self.get_bit_value((nx, y)) && self.get_bit_value((x, ny)))
+            .map(move |n| (n, OctileCost::step((x, y), n)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "search-core")]
+    use crate::search::astar::astar;
+
+    #[test]
+    fn test_octile_cost_ordering() {
+        assert!(OctileCost::new(7, 0) < OctileCost::new(0, 5));
+        assert!(OctileCost::new(3, 0) > OctileCost::new(0, 2));
+        assert!(OctileCost::new(1, 2) < OctileCost::new(4, 0));
+        assert_eq!(OctileCost::new(2, 3).cmp(&OctileCost::new(2, 3)), Ordering::Equal);
+        // Costs a float cannot separate after many moves
+        let long = OctileCost::new(0, 1_000_000_000_000);
+        assert!(long < OctileCost::new(1_414_213_562_374, 0) && long > OctileCost::new(1_414_213_562_373, 0));
+        assert_eq!([OctileCost::STRAIGHT, OctileCost::DIAGONAL, OctileCost::DIAGONAL].into_iter().sum::<OctileCost>(), OctileCost::new(1, 2));
+    }
+
+    #[test]
+    #[cfg(feature = "search-core")]
+    fn test_octile_search() {
+        let grid = BitPackedGrid::new_from_string(".....\n.@...\n.....\n".to_string());
+        let goal = (4, 2);
+        let (path, cost) = astar(|n| grid.adjacent_octile(*n), (0, 0), |n| *n == goal, |n| exact_octile_distance(*n, goal)).unwrap();
+        assert_eq!(cost, OctileCost::new(2, 2));
+        assert_eq!(path.windows(2).map(|m| OctileCost::step(m[0], m[1])).sum::<OctileCost>(), cost);
+        assert!((cost.to_f64() - (2.0 + 2.0 * std::f64::consts::SQRT_2)).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,467 @@
+//! # Grid Abstraction
+//! The clustering machinery used by hierarchical planners such as HPA*. A grid
+//! map is split into square clusters, entrances are found along the borders of
+//! neighbouring clusters and an abstract graph is built over the entrance cells.
+//! Edges of the abstract graph either cross a border (inter-edges of cost 1) or
+//! connect two entrance cells of the same cluster (intra-edges weighted by the
+//! shortest distance inside the cluster).
+//!
+//! The abstraction is independent of any search algorithm, so it can be used to
+//! build custom multi-level planners or to visualise the clustering of a map.
+//! This implementation follows Botea et al., "Near Optimal Hierarchical
+//! Path-Finding" (2004).
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use crate::domains::{adjacencylist::AdjacencyList, bitpackedgrid::BitPackedGrid, neighbors};
+use crate::util::preprocessing::{par_build, BuildStats};
+
+/// A pair of neighbouring cells on either side of a cluster border
+type Transition = ((usize, usize), (usize, usize));
+
+/// A rectangular block of cells within a cluster grid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cluster {
+    pub origin: (usize, usize),
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Cluster {
+    /// Checks if a cell lies within the cluster
+    pub fn contains(&self, (x, y): (usize, usize)) -> bool {
+        x >= self.origin.0 && y >= self.origin.1
+            && x < self.origin.0 + self.width && y < self.origin.1 + self.height
+    }
+}
+
+/// A division of a grid map into square clusters, clusters on the right and
+/// bottom edges of the map may be smaller than the cluster size
+#[derive(Debug, Clone)]
+pub struct ClusterGrid {
+    pub cluster_size: usize,
+    pub width_in_clusters: usize,
+    pub height_in_clusters: usize,
+    clusters: Vec<Cluster>,
+}
+
+impl ClusterGrid {
+    /// Creates a cluster grid over a map of the given size
+    /// ## Arguments
+    /// * `width` - The width of the map
+    /// * `height` - The height of the map
+    /// * `cluster_size` - The width and height of each cluster
+    pub fn new(width: usize, height: usize, cluster_size: usize) -> Self {
+        assert!(cluster_size > 0, "Cluster size must be positive");
+        let width_in_clusters = width.div_ceil(cluster_size);
+        let height_in_clusters = height.div_ceil(cluster_size);
+        let mut clusters = Vec::with_capacity(width_in_clusters * height_in_clusters);
+        for cy in 0..height_in_clusters {
+            for cx in 0..width_in_clusters {
+                let origin = (cx * cluster_size, cy * cluster_size);
+                clusters.push(Cluster {
+                    origin,
+                    width: cluster_size.min(width - origin.0),
+                    height: cluster_size.min(height - origin.1),
+                });
+            }
+        }
+        ClusterGrid { cluster_size, width_in_clusters, height_in_clusters, clusters }
+    }
+
+    /// Get the id of the cluster containing a cell
+    pub fn cluster_id(&self, (x, y): (usize, usize)) -> usize {
+        (y / self.cluster_size) * self.width_in_clusters + x / self.cluster_size
+    }
+
+    /// Get a cluster by its id
+    pub fn cluster(&self, id: usize) -> &Cluster {
+        &self.clusters[id]
+    }
+
+    /// Iterate over all clusters
+    pub fn iter(&self) -> impl Iterator<Item = &Cluster> {
+        self.clusters.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.clusters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clusters.is_empty()
+    }
+}
+
+/// A transition between two neighbouring clusters, `from` lies in the first
+/// cluster and `to` in the second. Both cells are free.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entrance {
+    pub clusters: (usize, usize),
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+}
+
+/// A cluster abstraction of a bitpacked grid
+/// ## Fields
+/// * `clusters` - The cluster grid the map is divided into
+/// * `entrances` - The entrances between neighbouring clusters
+/// * `graph` - The abstract graph over entrance cells
+pub struct Abstraction {
+    pub clusters: ClusterGrid,
+    pub entrances: Vec<Entrance>,
+    pub graph: AdjacencyList<(usize, usize), usize>,
+}
+
+impl Abstraction {
+    /// Entrances wider than this place a transition at both ends rather than one in the middle
+    const MAX_ENTRANCE_WIDTH: usize = 6;
+
+    /// Builds an abstraction of a grid
+    /// ## Arguments
+    /// * `grid` - The grid to abstract
+    /// * `cluster_size` - The width and height of each cluster
+    /// ## Returns
+    /// An abstraction with entrances and an abstract graph
+    /// ## Complexity
+    /// O(e * c) where e is the number of entrance cells and c is the cluster area
+    pub fn new(grid: &BitPackedGrid, cluster_size: usize) -> Self {
+        let clusters = ClusterGrid::new(grid.original_width, grid.original_height, cluster_size);
+        let mut abstraction = Abstraction {
+            entrances: Vec::new(),
+            graph: AdjacencyList::new(),
+            clusters,
+        };
+        abstraction.build_entrances(grid);
+        for id in 0..abstraction.clusters.len() {
+            for ((from, to), distance) in abstraction.intra_edges(grid, id) {
+                abstraction.graph.add_edge(from, to, distance);
+            }
+        }
+        abstraction
+    }
+
+    /// Builds an abstraction of a grid, computing the intra-edges of the
+    /// clusters in parallel
+    /// ## Returns
+    /// The abstraction and the statistics of building its intra-edges
+    pub fn new_parallel(grid: &BitPackedGrid, cluster_size: usize) -> (Self, BuildStats) {
+        let clusters = ClusterGrid::new(grid.original_width, grid.original_height, cluster_size);
+        let mut abstraction = Abstraction {
+            entrances: Vec::new(),
+            graph: AdjacencyList::new(),
+            clusters,
+        };
+        abstraction.build_entrances(grid);
+        let ids = (0..abstraction.clusters.len()).collect::<Vec<_>>();
+        let (edges, stats) = par_build(&ids, |id| abstraction.intra_edges(grid, *id));
+        for ((from, to), distance) in edges.into_iter().flatten() {
+            abstraction.graph.add_edge(from, to, distance);
+        }
+        (abstraction, stats)
+    }
+
+    /// Finds the entrances along the right and bottom border of every cluster
+    /// and adds the inter-edges to the abstract graph
+    fn build_entrances(&mut self, grid: &BitPackedGrid) {
+        for id in 0..self.clusters.len() {
+            self.build_right_border(grid, id);
+            self.build_bottom_border(grid, id);
+        }
+    }
+
+    /// Finds the entrances along the right border of a cluster
+    fn build_right_border(&mut self, grid: &BitPackedGrid, id: usize) {
+        let cluster = self.clusters.cluster(id).clone();
+        let right = cluster.origin.0 + cluster.width;
+        if right < grid.original_width {
+            let border = (cluster.origin.1..cluster.origin.1 + cluster.height)
+                .map(|y| ((right - 1, y), (right, y)))
+                .collect::<Vec<_>>();
+            self.add_border_entrances(grid, (id, id + 1), &border);
+        }
+    }
+
+    /// Finds the entrances along the bottom border of a cluster
+    fn build_bottom_border(&mut self, grid: &BitPackedGrid, id: usize) {
+        let cluster = self.clusters.cluster(id).clone();
+        let bottom = cluster.origin.1 + cluster.height;
+        if bottom < grid.original_height {
+            let border = (cluster.origin.0..cluster.origin.0 + cluster.width)
+                .map(|x| ((x, bottom - 1), (x, bottom)))
+                .collect::<Vec<_>>();
+            self.add_border_entrances(grid, (id, id + self.clusters.width_in_clusters), &border);
+        }
+    }
+
+    /// Splits a border into maximal runs of cell pairs that are free on both
+    /// sides and places transitions on each run
+    fn add_border_entrances(
+        &mut self,
+        grid: &BitPackedGrid,
+        clusters: (usize, usize),
+        border: &[Transition],
+    ) {
+        let mut start = None;
+        for i in 0..=border.len() {
+            let open = i < border.len() && grid.get_bit_value(border[i].0) && grid.get_bit_value(border[i].1);
+            match (open, start) {
+                (true, None) => start = Some(i),
+                (false, Some(s)) => {
+                    let run = &border[s..i];
+                    if run.len() < Abstraction::MAX_ENTRANCE_WIDTH {
+                        self.add_entrance(clusters, run[run.len() / 2]);
+                    } else {
+                        self.add_entrance(clusters, run[0]);
+                        self.add_entrance(clusters, run[run.len() - 1]);
+                    }
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Adds an entrance and its inter-edge to the abstract graph
+    fn add_entrance(&mut self, clusters: (usize, usize), (from, to): Transition) {
+        self.add_inter_edge((from, to));
+        self.entrances.push(Entrance { clusters, from, to });
+    }
+
+    /// Adds the nodes of a transition and the inter-edge between them
+    fn add_inter_edge(&mut self, (from, to): Transition) {
+        for node in [from, to] {
+            if !self.graph.contains_node(&node) {
+                self.graph.add_node(node);
+            }
+        }
+        self.graph.add_edge(from, to, 1);
+        self.graph.add_edge(to, from, 1);
+    }
+
+    /// Rebuilds the parts of the abstraction affected by edited cells. The
+    /// entrances of the clusters containing the cells are found again and the
+    /// intra-edges of those clusters and their neighbours are recomputed,
+    /// while the rest of the abstraction is left untouched.
+    /// ## Arguments
+    /// * `grid` - The grid after the edits
+    /// * `changed` - The cells whose value changed
+    /// ## Returns
+    /// The ids of the clusters whose intra-edges were recomputed
+    pub fn update(&mut self, grid: &BitPackedGrid, changed: &[(usize, usize)]) -> Vec<usize> {
+        let width = self.clusters.width_in_clusters;
+        let len = self.clusters.len();
+        let dirty = changed.iter()
+            .filter(|n| grid.bounds_check(**n))
+            .map(|n| self.clusters.cluster_id(*n))
+            .collect::<BTreeSet<_>>();
+        let mut touched = dirty.clone();
+        for &id in &dirty {
+            if id % width > 0 { touched.insert(id - 1); }
+            if id % width + 1 < width { touched.insert(id + 1); }
+            if id >= width { touched.insert(id - width); }
+            if id + width < len { touched.insert(id + width); }
+        }
+
+        self.entrances.retain(|e| !dirty.contains(&e.clusters.0) && !dirty.contains(&e.clusters.1));
+        let stale = self.graph.iter()
+            .map(|(n, _)| *n)
+            .filter(|n| touched.contains(&self.clusters.cluster_id(*n)))
+            .collect::<Vec<_>>();
+        for node in stale {
+            self.graph.remove_node(node);
+        }
+        let relink = self.entrances.iter()
+            .filter(|e| touched.contains(&e.clusters.0) || touched.contains(&e.clusters.1))
+            .map(|e| (e.from, e.to))
+            .collect::<Vec<_>>();
+        for transition in relink {
+            self.add_inter_edge(transition);
+        }
+        for &id in &touched {
+            if id % width + 1 < width && (dirty.contains(&id) || dirty.contains(&(id + 1))) {
+                self.build_right_border(grid, id);
+            }
+            if id + width < len && (dirty.contains(&id) || dirty.contains(&(id + width))) {
+                self.build_bottom_border(grid, id);
+            }
+        }
+        for &id in &touched {
+            for ((from, to), distance) in self.intra_edges(grid, id) {
+                self.graph.add_edge(from, to, distance);
+            }
+        }
+        touched.into_iter().collect()
+    }
+
+    /// The edges connecting every pair of abstract nodes within a cluster by
+    /// their shortest distance inside the cluster
+    fn intra_edges(&self, grid: &BitPackedGrid, id: usize) -> Vec<(Transition, usize)> {
+        let nodes = self.cluster_nodes(id);
+        let mut edges = Vec::new();
+        for node in nodes.iter() {
+            let distances = cluster_distances(grid, self.clusters.cluster(id), *node);
+            for other in nodes.iter().filter(|n| *n != node) {
+                if let Some(distance) = distances.get(other) {
+                    edges.push(((*node, *other), *distance));
+                }
+            }
+        }
+        edges
+    }
+
+    /// Get the abstract nodes lying within a cluster
+    pub fn cluster_nodes(&self, id: usize) -> Vec<(usize, usize)> {
+        let cluster = self.clusters.cluster(id);
+        let mut nodes = self.graph.iter()
+            .map(|(n, _)| *n)
+            .filter(|n| cluster.contains(*n))
+            .collect::<Vec<_>>();
+        nodes.sort();
+        nodes
+    }
+
+    /// Temporarily inserts a cell, such as a start or goal, into the abstract graph
+    /// by connecting it to the abstract nodes of its cluster
+    /// ## Returns
+    /// True if the node was added, false if it was already part of the graph
+    pub fn insert_node(&mut self, grid: &BitPackedGrid, node: (usize, usize)) -> bool {
+        if self.graph.contains_node(&node) {
+            return false;
+        }
+        let id = self.clusters.cluster_id(node);
+        let nodes = self.cluster_nodes(id);
+        let distances = cluster_distances(grid, self.clusters.cluster(id), node);
+        self.graph.add_node(node);
+        for other in nodes {
+            if let Some(distance) = distances.get(&other) {
+                self.graph.add_edge(node, other, *distance);
+                self.graph.add_edge(other, node, *distance);
+            }
+        }
+        true
+    }
+
+    /// Removes a node previously inserted with `insert_node`
+    pub fn remove_node(&mut self, node: (usize, usize)) {
+        self.graph.remove_node(node);
+    }
+
+    /// Prints the grid map with abstract nodes marked as *
+    pub fn print_cells(&self, grid: &BitPackedGrid) -> String {
+        grid.print_cells(Some(self.graph.iter().map(|(n, _)| *n).collect()))
+    }
+
+    /// Plots the grid map with abstract nodes drawn as a heatmap and the
+    /// boundaries of the clusters outlined
+    #[cfg(feature = "viz")]
+    pub fn plot_cells(&self, grid: &BitPackedGrid, filename: &str) {
+        use crate::domains::Annotation;
+        let heatmap = self.graph.iter().map(|(n, _)| (*n, 1.0)).collect();
+        let outline = Annotation::boundaries(grid.original_width, grid.original_height, |n| self.clusters.cluster_id(n));
+        grid.plot_annotated(filename, None, Some(heatmap), &[outline]);
+    }
+}
+
+/// Breadth-first distances from a cell to all cells reachable without leaving the cluster
+pub(crate) fn cluster_distances(
+    grid: &BitPackedGrid,
+    cluster: &Cluster,
+    source: (usize, usize),
+) -> HashMap<(usize, usize), usize> {
+    let mut distances = HashMap::from([(source, 0)]);
+    let mut queue = VecDeque::from([source]);
+    while let Some(node) = queue.pop_front() {
+        let distance = distances[&node];
+        for child in neighbors(node.0, node.1, false) {
+            if cluster.contains(child) && grid.get_bit_value(child) && !distances.contains_key(&child) {
+                distances.insert(child, distance + 1);
+                queue.push_back(child);
+            }
+        }
+    }
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cluster_grid() {
+        let clusters = ClusterGrid::new(10, 7, 4);
+        assert_eq!(clusters.width_in_clusters, 3);
+        assert_eq!(clusters.height_in_clusters, 2);
+        assert_eq!(clusters.cluster_id((9, 6)), 5);
+        assert_eq!(clusters.cluster(5), &Cluster { origin: (8, 4), width: 2, height: 3 });
+    }
+
+    #[test]
+    fn test_abstraction_entrances() {
+        let grid = BitPackedGrid::new_from_string("........\n........\n........\n........\n".to_string());
+        let abstraction = Abstraction::new(&grid, 4);
+        assert_eq!(abstraction.entrances, vec![Entrance { clusters: (0, 1), from: (3, 2), to: (4, 2) }]);
+        assert_eq!(abstraction.graph.len(), 2);
+    }
+
+    #[test]
+    fn test_abstraction_intra_edges() {
+        let grid = BitPackedGrid::new_from_string(
+            "....@...\n....@...\n........\n....@...\n@@.@@@@.\n........\n........\n........\n".to_string()
+        );
+        let abstraction = Abstraction::new(&grid, 4);
+        assert_eq!(abstraction.entrances.len(), 4);
+        // Bottom entrance of the top left cluster is 2 steps from the right entrance
+        assert!(abstraction.graph[(2, 3)].contains(&((3, 2), 2)));
+        assert!(abstraction.graph[(2, 3)].contains(&((2, 4), 1)));
+    }
+
+    #[test]
+    fn test_abstraction_parallel() {
+        let grid = BitPackedGrid::new_from_string(
+            "....@...\n....@...\n........\n....@...\n@@.@@@@.\n........\n........\n........\n".to_string()
+        );
+        let abstraction = Abstraction::new(&grid, 4);
+        let (parallel, stats) = Abstraction::new_parallel(&grid, 4);
+        assert_eq!(stats.items, 4);
+        assert_eq!(parallel.entrances, abstraction.entrances);
+        for (node, edges) in abstraction.graph.iter() {
+            assert_eq!(&parallel.graph[*node], edges);
+        }
+    }
+
+    #[test]
+    fn test_abstraction_update() {
+        let mut grid = BitPackedGrid::new_from_string(
+            "....@.......\n....@.......\n............\n....@.......\n@@.@@@@.@@@@\n............\n............\n............\n".to_string()
+        );
+        let mut abstraction = Abstraction::new(&grid, 4);
+        let changed = [(2, 4), (8, 4), (10, 6)];
+        for node in changed {
+            grid.set_bit_value(node, !grid.get_bit_value(node));
+        }
+        assert_eq!(abstraction.update(&grid, &changed), vec![0, 2, 3, 4, 5]);
+        let rebuilt = Abstraction::new(&grid, 4);
+        fn sorted<T: Ord>(mut v: Vec<T>) -> Vec<T> {
+            v.sort();
+            v
+        }
+        let entrances = |a: &Abstraction| sorted(a.entrances.iter().map(|e| (e.clusters, e.from, e.to)).collect());
+        assert_eq!(entrances(&abstraction), entrances(&rebuilt));
+        assert_eq!(abstraction.graph.len(), rebuilt.graph.len());
+        for (node, edges) in rebuilt.graph.iter() {
+            assert_eq!(sorted(abstraction.graph[*node].clone()), sorted(edges.clone()));
+        }
+    }
+
+    #[test]
+    fn test_abstraction_insert_node() {
+        let grid = BitPackedGrid::new_from_string("........\n........\n........\n........\n".to_string());
+        let mut abstraction = Abstraction::new(&grid, 4);
+        assert!(abstraction.insert_node(&grid, (0, 0)));
+        assert_eq!(abstraction.graph[(0, 0)], vec![((3, 2), 5)]);
+        assert!(!abstraction.insert_node(&grid, (3, 2)));
+        abstraction.remove_node((0, 0));
+        assert_eq!(abstraction.graph[(3, 2)], vec![((4, 2), 1)]);
+    }
+}
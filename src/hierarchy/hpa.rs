@@ -0,0 +1,240 @@
+//! # HPA*
+//! Hierarchical Path-Finding A*, following Botea et al., "Near Optimal
+//! Hierarchical Path-Finding" (2004), on 4-connected bitpacked grids. The
+//! cluster abstraction of the map is built once and reused by every query.
+//! A query connects its start and goal to the abstract nodes of their
+//! clusters while searching the abstract graph, leaving the abstraction as it
+//! was, so one planner answers queries from many threads. The abstract path
+//! is refined into cells by searches confined to the cluster of each
+//! intra-edge, and may be smoothed by replacing detours with straight runs of
+//! free cells. Paths are near optimal, within a few percent
+//! of the optimal length on most maps.
+
+use std::collections::HashMap;
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid, direction::Direction};
+use crate::heuristics::distance::manhattan_distance;
+use crate::search::astar::{astar, astar_with_stats};
+use crate::search::stats::SearchStats;
+
+use super::abstraction::{cluster_distances, Abstraction};
+
+/// An HPA* planner over the abstraction of a map
+/// ## Fields
+/// * `abstraction` - The cluster abstraction shared by every query
+/// * `expanded` - The number of abstract nodes expanded by the last query
+pub struct HpaStar {
+    pub abstraction: Abstraction,
+    pub expanded: usize,
+}
+
+impl HpaStar {
+    /// Preprocesses a grid into clusters of a given size
+    pub fn new(grid: &BitPackedGrid, cluster_size: usize) -> Self {
+        HpaStar { abstraction: Abstraction::new(grid, cluster_size), expanded: 0 }
+    }
+
+    /// Repairs the abstraction after cells of the grid changed, see `Abstraction::update`
+    pub fn update(&mut self, grid: &BitPackedGrid, changed: &[Cell]) -> Vec<usize> {
+        self.abstraction.update(grid, changed)
+    }
+
+    /// Searches the abstract graph between two cells
+    /// ## Returns
+    /// The abstract nodes from the start to the goal with the length of the path
+    pub fn abstract_search(&mut self, grid: &BitPackedGrid, start: Cell, goal: Cell) -> Option<(Vec<Cell>, usize)> {
+        let (result, expanded) = self.query_abstract(grid, start, goal);
+        self.expanded = expanded;
+        result
+    }
+
+    /// Searches the abstract graph like `abstract_search` without mutating
+    /// the planner, connecting the start and goal to the abstract nodes of
+    /// their clusters during the search rather than inserting them
+    /// ## Returns
+    /// The abstract path with its length and the number of abstract nodes expanded
+    pub fn query_abstract(&self, grid: &BitPackedGrid, start: Cell, goal: Cell) -> (Option<(Vec<Cell>, usize)>, usize) {
+        let (result, stats) = self.query_abstract_with_stats(grid, start, goal);
+        (result, stats.expanded)
+    }
+
+    /// Searches the abstract graph like `query_abstract`
+    /// ## Returns
+    /// The abstract path with its length and the statistics of the abstract search
+    pub fn query_abstract_with_stats(&self, grid: &BitPackedGrid, start: Cell, goal: Cell) -> (Option<(Vec<Cell>, usize)>, SearchStats) {
+        let free = |n: Cell| grid.bounds_check(n) && grid.get_bit_value(n);
+        if !free(start) || !free(goal) {
+            return (None, SearchStats::default());
+        }
+        let (graph, clusters) = (&self.abstraction.graph, &self.abstraction.clusters);
+        let mut extra: HashMap<Cell, Vec<(Cell, usize)>> = HashMap::new();
+        let endpoints = [start, goal].into_iter().filter(|n| !graph.contains_node(n)).collect::<Vec<_>>();
+        for (i, &node) in endpoints.iter().enumerate() {
+            let id = clusters.cluster_id(node);
+            let distances = cluster_distances(grid, clusters.cluster(id), node);
+            // A goal in the cluster of the start is connected to it, as when inserted after the start
+            let others = endpoints[..i].iter().filter(|n| clusters.cluster_id(**n) == id).copied();
+            for other in self.abstraction.cluster_nodes(id).into_iter().chain(others) {
+                if let Some(distance) = distances.get(&other) {
+                    extra.entry(node).or_default().push((other, *distance));
+                    extra.entry(other).or_default().push((node, *distance));
+                }
+            }
+        }
+        astar_with_stats(
+            |n| {
+                let edges = if graph.contains_node(n) { graph.adjacent(*n).copied().collect() } else { Vec::new() };
+                edges.into_iter().chain(extra.get(n).into_iter().flatten().copied())
+            },
+            start,
+            |n| *n == goal,
+            |n| manhattan_distance(*n, goal),
+        )
+    }
+
+    /// Refines an abstract path into cells, searching within the cluster of
+    /// each intra-edge
+    pub fn refine(&self, grid: &BitPackedGrid, abstract_path: &[Cell]) -> Option<Vec<Cell>> {
+        let clusters = &self.abstraction.clusters;
+        let mut path = abstract_path.first().map(|n| vec![*n])?;
+        for edge in abstract_path.windows(2) {
+            let (from, to) = (edge[0], edge[1]);
+            let id = clusters.cluster_id(from);
+            if id != clusters.cluster_id(to) {
+                path.push(to);
+                continue;
+            }
+            let cluster = clusters.cluster(id);
+            let (segment, _) = astar(
+                |n| grid.adjacent1(*n).filter(|(m, _)| cluster.contains(*m)).collect::<Vec<_>>(),
+                from,
+                |n| *n == to,
+                |n| manhattan_distance(*n, to),
+            )?;
+            path.extend(segment.into_iter().skip(1));
+        }
+        Some(path)
+    }
+
+    /// Plans a path between two cells
+    /// ## Arguments
+    /// * `grid` - The grid the abstraction was built from
+    /// * `start` - The start cell
+    /// * `goal` - The goal cell
+    /// * `smooth` - Whether to smooth the refined path with `smooth_path`
+    /// ## Returns
+    /// The cells of a path from the start to the goal with its length
+    pub fn hpa_search(&mut self, grid: &BitPackedGrid, start: Cell, goal: Cell, smooth: bool) -> Option<(Vec<Cell>, usize)> {
+        let (result, expanded) = self.query(grid, start, goal, smooth);
+        self.expanded = expanded;
+        result
+    }
+
+    /// Plans a path like `hpa_search` without mutating the planner, so one
+    /// planner can answer queries from many threads
+    /// ## Returns
+    /// The path with its length and the number of abstract nodes expanded
+    pub fn query(&self, grid: &BitPackedGrid, start: Cell, goal: Cell, smooth: bool) -> (Option<(Vec<Cell>, usize)>, usize) {
+        let (path, stats) = self.query_with_stats(grid, start, goal, smooth);
+        (path, stats.expanded)
+    }
+
+    /// Plans a path like `query`
+    /// ## Returns
+    /// The path with its length and the statistics of the abstract search,
+    /// which leave out the searches refining the abstract path
+    pub fn query_with_stats(&self, grid: &BitPackedGrid, start: Cell, goal: Cell, smooth: bool) -> (Option<(Vec<Cell>, usize)>, SearchStats) {
+        let (abstract_path, stats) = self.query_abstract_with_stats(grid, start, goal);
+        let path = abstract_path.and_then(|(abstract_path, _)| self.refine(grid, &abstract_path));
+        let path = path.map(|path| if smooth { smooth_path(grid, &path) } else { path });
+        (path.map(|path| { let length = path.len() - 1; (path, length) }), stats)
+    }
+
+    /// Plans a path like `hpa_search`, also returning the statistics of the
+    /// abstract search
+    pub fn hpa_search_with_stats(&mut self, grid: &BitPackedGrid, start: Cell, goal: Cell, smooth: bool) -> (Option<(Vec<Cell>, usize)>, SearchStats) {
+        let (result, stats) = self.query_with_stats(grid, start, goal, smooth);
+        self.expanded = stats.expanded;
+        (result, stats)
+    }
+}
+
+/// Smooths a 4-connected path, replacing each detour that a straight run of
+/// free cells from an earlier cell of the path can skip with that run
+pub fn smooth_path(grid: &BitPackedGrid, path: &[Cell]) -> Vec<Cell> {
+    let last = path.iter().enumerate().map(|(i, n)| (*n, i)).collect::<HashMap<_, _>>();
+    let mut smoothed = Vec::with_capacity(path.len());
+    let mut i = 0;
+    while i < path.len() {
+        smoothed.push(path[i]);
+        // The furthest later cell of the path reached by a straight run
+        let mut best: Option<(usize, Direction, usize)> = None;
+        for direction in Direction::CARDINAL {
+            let (mut cell, mut steps) = (path[i], 0);
+            loop {
+                cell = direction.apply_wrapping(cell);
+                if !grid.bounds_check(cell) || !grid.get_bit_value(cell) {
+                    break;
+                }
+                steps += 1;
+                if let Some(&j) = last.get(&cell) {
+                    if j > i + steps && best.is_none_or(|(b, _, _)| j > b) {
+                        best = Some((j, direction, steps));
+                    }
+                }
+            }
+        }
+        match best {
+            Some((j, direction, steps)) => {
+                for _ in 1..steps {
+                    smoothed.push(direction.apply_wrapping(*smoothed.last().unwrap()));
+                }
+                i = j;
+            }
+            None => i += 1,
+        }
+    }
+    smoothed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::test_queries;
+
+    fn valid(grid: &BitPackedGrid, path: &[Cell]) -> bool {
+        path.iter().all(|n| grid.get_bit_value(*n)) && path.windows(2).all(|m| manhattan_distance(m[0], m[1]) == 1)
+    }
+
+    #[test]
+    fn test_hpa_search() {
+        let grid = BitPackedGrid::new_from_file("tests/map.map");
+        let mut hpa = HpaStar::new(&grid, 8);
+        let nodes = hpa.abstraction.graph.len();
+        let free = grid.free_cells().collect::<Vec<_>>();
+        for (start, goal) in test_queries(&free, 20) {
+            let optimal = astar(|n| grid.adjacent1(*n), start, |n| *n == goal, |n| manhattan_distance(*n, goal)).map(|(_, c)| c);
+            let found = hpa.hpa_search(&grid, start, goal, false);
+            assert_eq!(found.is_some(), optimal.is_some(), "{:?} to {:?}", start, goal);
+            let (Some((path, length)), Some(optimal)) = (found, optimal) else { continue };
+            assert_eq!((path[0], *path.last().unwrap(), path.len()), (start, goal, length + 1));
+            assert!(valid(&grid, &path) && length >= optimal);
+            let (smoothed, stats) = hpa.hpa_search_with_stats(&grid, start, goal, true);
+            let (smoothed, smoothed_length) = smoothed.unwrap();
+            assert!(valid(&grid, &smoothed) && (optimal..=length).contains(&smoothed_length));
+            assert_eq!(stats.expanded, hpa.expanded);
+        }
+        assert_eq!(hpa.abstraction.graph.len(), nodes);
+    }
+
+    #[test]
+    fn test_smooth_path() {
+        let grid = BitPackedGrid::new_from_string("......\n......\n..@...\n".to_string());
+        let path = [(0, 2), (1, 2), (1, 1), (2, 1), (3, 1), (3, 2), (4, 2), (4, 1), (4, 0), (5, 0)];
+        assert_eq!(smooth_path(&grid, &path), vec![(0, 2), (1, 2), (1, 1), (2, 1), (3, 1), (4, 1), (4, 0), (5, 0)]);
+        let blocked = BitPackedGrid::new_from_string("....\n.@@.\n....\n".to_string());
+        let mut hpa = HpaStar::new(&blocked, 2);
+        assert!(hpa.hpa_search(&blocked, (0, 0), (1, 1), false).is_none());
+        assert_eq!(hpa.hpa_search(&blocked, (0, 1), (3, 1), true).unwrap().1, 5);
+    }
+}
@@ -0,0 +1,12 @@
+//! # Hierarchical Abstractions
+//! Grid abstractions that can be used to build multi-level planners. These include:
+//! * Abstraction, a cluster grid with entrances and an abstract graph
+//! * HpaStar, hierarchical path-finding on an abstraction reused across queries
+//! * GridPyramid, a stack of downscaled grids used for coarse-to-fine planning
+//! * Partition, independent blocks with interfaces for distributed search
+
+#![allow(dead_code)]
+pub mod abstraction;
+pub mod hpa;
+pub mod pyramid;
+pub mod partition;
@@ -0,0 +1,293 @@
+//! # Grid Partitioning
+//! A partition of a grid map into independent blocks for distributed search.
+//! Each block owns its own grid and can be loaded, stored and searched on its
+//! own, such as on a separate machine. Blocks only share their interface, the
+//! free border cells neighbouring a free cell of another block, along with the
+//! distances between the interface cells inside each block.
+//!
+//! A search first asks the blocks of the start and goal for their distances to
+//! their interfaces, then searches the interface graph and finally asks every
+//! block on the route to refine its own segment of the path. As every free
+//! border cell is an interface cell the resulting paths are optimal.
+
+use std::{collections::{HashMap, VecDeque}, time::Instant};
+
+use crate::domains::{adjacencylist::AdjacencyList, bitpackedgrid::BitPackedGrid};
+use crate::heuristics::distance::manhattan_distance;
+use crate::search::astar::astar;
+use crate::util::preprocessing::{par_build, BuildStats};
+
+use super::abstraction::{Cluster, ClusterGrid};
+
+/// An edge of the interface graph as (from, to, distance)
+type Edge = ((usize, usize), (usize, usize), usize);
+
+/// A block of a partitioned grid
+/// ## Fields
+/// * `cluster` - The area of the full grid covered by the block
+/// * `grid` - The cells of the block in local coordinates
+/// * `interface` - The interface cells of the block in global coordinates
+pub struct Block {
+    pub cluster: Cluster,
+    pub grid: BitPackedGrid,
+    pub interface: Vec<(usize, usize)>,
+}
+
+impl Block {
+    /// Converts a global cell to local coordinates within the block
+    fn local(&self, (x, y): (usize, usize)) -> (usize, usize) {
+        (x - self.cluster.origin.0, y - self.cluster.origin.1)
+    }
+
+    /// Converts a local cell within the block to global coordinates
+    fn global(&self, (x, y): (usize, usize)) -> (usize, usize) {
+        (x + self.cluster.origin.0, y + self.cluster.origin.1)
+    }
+
+    /// Checks if a global cell is within the block and free
+    pub fn is_free(&self, node: (usize, usize)) -> bool {
+        self.cluster.contains(node) && self.grid.get_bit_value(self.local(node))
+    }
+
+    /// The distances inside the block from a global cell to every reachable cell
+    pub fn distances(&self, source: (usize, usize)) -> HashMap<(usize, usize), usize> {
+        let source = self.local(source);
+        let mut distances = HashMap::from([(source, 0)]);
+        let mut queue = VecDeque::from([source]);
+        while let Some(node) = queue.pop_front() {
+            let distance = distances[&node];
+            for child in self.grid.adjacent(node, false) {
+                if self.grid.bounds_check(child) && !distances.contains_key(&child) {
+                    distances.insert(child, distance + 1);
+                    queue.push_back(child);
+                }
+            }
+        }
+        distances.into_iter().map(|(n, d)| (self.global(n), d)).collect()
+    }
+
+    /// A shortest path inside the block between two global cells
+    pub fn path(&self, start: (usize, usize), goal: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        let goal = self.local(goal);
+        let (path, _) = astar(
+            |n| self.grid.adjacent1(*n).filter(|(n, _)| self.grid.bounds_check(*n)),
+            self.local(start),
+            |n| *n == goal,
+            |n| manhattan_distance(*n, goal),
+        )?;
+        Some(path.into_iter().map(|n| self.global(n)).collect())
+    }
+}
+
+/// A grid map partitioned into blocks
+/// ## Fields
+/// * `layout` - The division of the map into blocks
+/// * `blocks` - The blocks in the order of the layout
+/// * `graph` - The interface graph, with edges of cost 1 between blocks and
+///   edges weighted by the distance inside a block between its interface cells
+pub struct Partition {
+    pub layout: ClusterGrid,
+    pub blocks: Vec<Block>,
+    pub graph: AdjacencyList<(usize, usize), usize>,
+}
+
+impl Partition {
+    /// Partitions a grid map into square blocks
+    pub fn new(grid: &BitPackedGrid, block_size: usize) -> Self {
+        Partition::from_loader(grid.original_width, grid.original_height, block_size, |cluster| crop(grid, cluster))
+    }
+
+    /// Partitions a grid map into square blocks, computing the distances
+    /// between the interface cells of the blocks in parallel
+    /// ## Returns
+    /// The partition and the statistics of building the interface distances
+    pub fn new_parallel(grid: &BitPackedGrid, block_size: usize) -> (Self, BuildStats) {
+        Partition::build(grid.original_width, grid.original_height, block_size, |cluster| crop(grid, cluster), true)
+    }
+
+    /// Partitions a map whose blocks are loaded one at a time, so the full map
+    /// never has to be held as a single grid
+    /// ## Arguments
+    /// * `width` - The width of the map
+    /// * `height` - The height of the map
+    /// * `block_size` - The width and height of each block
+    /// * `load` - Loads the grid of the area covered by a block
+    pub fn from_loader(
+        width: usize,
+        height: usize,
+        block_size: usize,
+        load: impl FnMut(&Cluster) -> BitPackedGrid,
+    ) -> Self {
+        Partition::build(width, height, block_size, load, false).0
+    }
+
+    fn build(
+        width: usize,
+        height: usize,
+        block_size: usize,
+        mut load: impl FnMut(&Cluster) -> BitPackedGrid,
+        parallel: bool,
+    ) -> (Self, BuildStats) {
+        let layout = ClusterGrid::new(width, height, block_size);
+        let mut blocks = layout.iter()
+            .map(|cluster| Block { cluster: cluster.clone(), grid: load(cluster), interface: Vec::new() })
+            .collect::<Vec<_>>();
+
+        let mut graph = AdjacencyList::new();
+        let is_free = |blocks: &[Block], node: (usize, usize)| {
+            node.0 < width && node.1 < height && blocks[layout.cluster_id(node)].is_free(node)
+        };
+        for id in 0..blocks.len() {
+            let Cluster { origin, width: w, height: h } = blocks[id].cluster.clone();
+            let border = (origin.0..origin.0 + w)
+                .flat_map(|x| (origin.1..origin.1 + h).map(move |y| (x, y)))
+                .filter(|&(x, y)| x == origin.0 || y == origin.1 || x + 1 == origin.0 + w || y + 1 == origin.1 + h);
+            for node in border {
+                if !is_free(&blocks, node) {
+                    continue;
+                }
+                let (x, y) = node;
+                let outside = [(x + 1, y), (x, y + 1), (x.wrapping_sub(1), y), (x, y.wrapping_sub(1))]
+                    .into_iter()
+                    .filter(|n| !blocks[id].cluster.contains(*n) && is_free(&blocks, *n))
+                    .collect::<Vec<_>>();
+                if outside.is_empty() {
+                    continue;
+                }
+                blocks[id].interface.push(node);
+                if !graph.contains_node(&node) {
+                    graph.add_node(node);
+                }
+                for other in outside {
+                    graph.add_edge(node, other, 1);
+                }
+            }
+        }
+        let (edges, stats) = if parallel {
+            par_build(&blocks, interface_edges)
+        } else {
+            let start = Instant::now();
+            let edges = blocks.iter().map(interface_edges).collect::<Vec<_>>();
+            (edges, BuildStats { elapsed: start.elapsed(), items: blocks.len(), threads: 1 })
+        };
+        for (from, to, distance) in edges.into_iter().flatten() {
+            graph.add_edge(from, to, distance);
+        }
+        (Partition { layout, blocks, graph }, stats)
+    }
+
+    /// The block containing a cell
+    pub fn block(&self, node: (usize, usize)) -> &Block {
+        &self.blocks[self.layout.cluster_id(node)]
+    }
+
+    /// Searches for a shortest path across blocks
+    /// ## Returns
+    /// An optional path on the full grid with its cost
+    pub fn search(&self, start: (usize, usize), goal: (usize, usize)) -> Option<(Vec<(usize, usize)>, usize)> {
+        let (start_block, goal_block) = (self.block(start), self.block(goal));
+        if !start_block.is_free(start) || !goal_block.is_free(goal) {
+            return None;
+        }
+        let start_distances = start_block.distances(start);
+        let mut start_edges = start_block.interface.iter()
+            .filter_map(|n| start_distances.get(n).map(|d| (*n, *d)))
+            .collect::<Vec<_>>();
+        if let Some(d) = start_distances.get(&goal) {
+            start_edges.push((goal, *d));
+        }
+        let goal_distances = goal_block.distances(goal);
+
+        let (route, cost) = astar(
+            |n| {
+                let mut edges = if *n == start { start_edges.clone() } else { Vec::new() };
+                if self.graph.contains_node(n) {
+                    edges.extend(self.graph.adjacent(*n).cloned());
+                    if let Some(d) = goal_distances.get(n) {
+                        edges.push((goal, *d));
+                    }
+                }
+                edges
+            },
+            start,
+            |n| *n == goal,
+            |n| manhattan_distance(*n, goal),
+        )?;
+
+        let mut path = vec![start];
+        for pair in route.windows(2) {
+            let block = self.block(pair[0]);
+            if block.cluster.contains(pair[1]) {
+                path.extend(block.path(pair[0], pair[1])?.into_iter().skip(1));
+            } else {
+                path.push(pair[1]);
+            }
+        }
+        Some((path, cost))
+    }
+}
+
+/// Copies the area of a grid covered by a block
+fn crop(grid: &BitPackedGrid, cluster: &Cluster) -> BitPackedGrid {
+    let mut block = BitPackedGrid::new(cluster.width, cluster.height);
+    for x in 0..cluster.width {
+        for y in 0..cluster.height {
+            block.set_bit_value((x, y), grid.get_bit_value((x + cluster.origin.0, y + cluster.origin.1)));
+        }
+    }
+    block
+}
+
+/// The edges between every pair of interface cells of a block weighted by
+/// their distance inside the block
+fn interface_edges(block: &Block) -> Vec<Edge> {
+    let mut edges = Vec::new();
+    for node in &block.interface {
+        let distances = block.distances(*node);
+        for other in block.interface.iter().filter(|n| *n != node) {
+            if let Some(distance) = distances.get(other) {
+                edges.push((*node, *other, *distance));
+            }
+        }
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition_interface() {
+        let grid = BitPackedGrid::new_from_string("..@.\n....\n.@..\n".to_string());
+        let partition = Partition::new(&grid, 2);
+        assert_eq!(partition.blocks.len(), 4);
+        assert_eq!(partition.blocks[0].interface, vec![(0, 1), (1, 1)]);
+        assert_eq!(partition.blocks[1].grid.print_cells(None), "@.\n..\n");
+        assert!(partition.graph.contains_node(&(2, 1)));
+        assert!(!partition.graph.contains_node(&(3, 0)));
+    }
+
+    #[test]
+    fn test_partition_search() {
+        let grid = BitPackedGrid::new_from_string("......\n.@@@@.\n.@....\n.@.@@@\n...@..\n".to_string());
+        let partition = Partition::new(&grid, 2);
+        let optimal = astar(|n| grid.adjacent1(*n), (2, 3), |n| *n == (5, 4), |n| manhattan_distance(*n, (5, 4)));
+        assert_eq!(optimal, None);
+        assert_eq!(partition.search((2, 3), (5, 4)), None);
+
+        let (path, cost) = partition.search((2, 3), (5, 2)).unwrap();
+        assert_eq!(cost, 4);
+        assert_eq!(path, vec![(2, 3), (2, 2), (3, 2), (4, 2), (5, 2)]);
+
+        let (path, cost) = partition.search((0, 4), (5, 2)).unwrap();
+        assert_eq!(cost, 7);
+        assert_eq!(path.len(), 8);
+        assert!(path.windows(2).all(|p| manhattan_distance(p[0], p[1]) == 1));
+        assert!(path.iter().all(|n| grid.get_bit_value(*n)));
+        assert_eq!(partition.search((0, 0), (1, 0)).unwrap().1, 1);
+        let (parallel, stats) = Partition::new_parallel(&grid, 2);
+        assert_eq!(stats.items, 9);
+        assert_eq!(parallel.search((0, 4), (5, 2)).unwrap().1, 7);
+    }
+}
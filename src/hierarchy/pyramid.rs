@@ -0,0 +1,159 @@
+//! # Grid Pyramids
+//! A multi-resolution representation of a grid map, where each level halves
+//! the resolution of the level below it. A coarse cell is free when any of
+//! the cells it covers is free, so a path on the coarse level is an optimistic
+//! outline of a path on the finer level.
+//!
+//! Coarse-to-fine planning solves the query on the coarsest level and then
+//! refines the solution level by level, only searching the cells that lie
+//! within the corridor of the coarser path. The resulting paths are not
+//! guaranteed to be optimal but the search effort on large maps is greatly
+//! reduced.
+
+use std::collections::HashSet;
+
+use crate::domains::{bitpackedgrid::BitPackedGrid, neighbors};
+use crate::heuristics::distance::manhattan_distance;
+use crate::search::astar::astar;
+
+/// A pyramid of downscaled grids where level 0 is the original grid
+pub struct GridPyramid {
+    levels: Vec<BitPackedGrid>,
+}
+
+impl GridPyramid {
+    /// Creates a pyramid of grids from a grid map
+    /// ## Arguments
+    /// * `grid` - The full resolution grid
+    /// * `levels` - The number of levels including the full resolution grid
+    pub fn new(grid: BitPackedGrid, levels: usize) -> Self {
+        assert!(levels > 0, "A pyramid requires at least one level");
+        let mut pyramid = vec![grid];
+        for _ in 1..levels {
+            let coarse = downscale(pyramid.last().unwrap());
+            pyramid.push(coarse);
+        }
+        GridPyramid { levels: pyramid }
+    }
+
+    /// Get the grid at a given level, where 0 is the full resolution grid
+    pub fn level(&self, level: usize) -> &BitPackedGrid {
+        &self.levels[level]
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// Coarse-to-fine search from the start to the goal
+    /// ## Arguments
+    /// * `start` - The start cell on the full resolution grid
+    /// * `goal` - The goal cell on the full resolution grid
+    /// ## Returns
+    /// An optional path on the full resolution grid with its cost, `None` if
+    /// either endpoint is outside the grid or blocked
+    pub fn search(&self, start: (usize, usize), goal: (usize, usize)) -> Option<(Vec<(usize, usize)>, usize)> {
+        let grid = self.levels.first()?;
+        if ![start, goal].iter().all(|n| grid.bounds_check(*n) && grid.get_bit_value(*n)) {
+            return None;
+        }
+        let mut corridor: Option<HashSet<(usize, usize)>> = None;
+        for level in (0..self.levels.len()).rev() {
+            let start = (start.0 >> level, start.1 >> level);
+            let goal = (goal.0 >> level, goal.1 >> level);
+            let path = self.refine(level, start, goal, corridor.as_ref())
+                .or_else(|| corridor.as_ref().and_then(|_| self.refine(level, start, goal, None)))?;
+            if level == 0 {
+                return Some(path);
+            }
+            corridor = Some(path_corridor(&path.0));
+        }
+        None
+    }
+
+    /// Search a single level of the pyramid, only expanding cells whose parent
+    /// lies within the corridor of the coarser level
+    fn refine(
+        &self,
+        level: usize,
+        start: (usize, usize),
+        goal: (usize, usize),
+        corridor: Option<&HashSet<(usize, usize)>>,
+    ) -> Option<(Vec<(usize, usize)>, usize)> {
+        let grid = &self.levels[level];
+        let in_corridor = |(x, y): &(usize, usize)| {
+            corridor.is_none_or(|c| c.contains(&(x >> 1, y >> 1)))
+        };
+        astar(
+            |n| grid.adjacent1(*n).filter(|(n, _)| in_corridor(n)),
+            start,
+            |n| *n == goal,
+            |n| manhattan_distance(*n, goal),
+        )
+    }
+}
+
+/// Halves the resolution of a grid, a coarse cell is free if any cell it covers is free
+fn downscale(grid: &BitPackedGrid) -> BitPackedGrid {
+    let width = grid.original_width.div_ceil(2);
+    let height = grid.original_height.div_ceil(2);
+    let mut coarse = BitPackedGrid::new(width, height);
+    for x in 0..width {
+        for y in 0..height {
+            let free = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                .iter()
+                .map(|(i, j)| (2 * x + i, 2 * y + j))
+                .any(|n| grid.bounds_check(n) && grid.get_bit_value(n));
+            coarse.set_bit_value((x, y), free);
+        }
+    }
+    coarse
+}
+
+/// The cells of a path dilated by their 8-connected neighbours
+fn path_corridor(path: &[(usize, usize)]) -> HashSet<(usize, usize)> {
+    path.iter()
+        .flat_map(|(x, y)| neighbors(*x, *y, true).chain(std::iter::once((*x, *y))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pyramid_downscale() {
+        let grid = BitPackedGrid::new_from_string("@@@.@\n@@@@@\n.@@@@\n".to_string());
+        let pyramid = GridPyramid::new(grid, 3);
+        assert_eq!(pyramid.len(), 3);
+        assert_eq!(pyramid.level(1).print_cells(None), "@.@\n.@@\n");
+        assert_eq!(pyramid.level(2).print_cells(None), ".@\n");
+    }
+
+    #[test]
+    fn test_pyramid_search() {
+        let map = "........\n.@@@@@@.\n.@....@.\n.@.@@.@.\n.@.@@...\n.@......\n.@@@@.@.\n........";
+        let grid = BitPackedGrid::new_from_string(map.to_string());
+        let optimal = astar(|n| grid.adjacent1(*n), (0, 0), |n| *n == (3, 2), |n| manhattan_distance(*n, (3, 2)));
+        let pyramid = GridPyramid::new(BitPackedGrid::new_from_string(map.to_string()), 3);
+        let (path, cost) = pyramid.search((0, 0), (3, 2)).unwrap();
+        assert_eq!(path.first(), Some(&(0, 0)));
+        assert_eq!(path.last(), Some(&(3, 2)));
+        assert_eq!(cost, optimal.unwrap().1);
+    }
+
+    #[test]
+    fn test_pyramid_search_no_path() {
+        let grid = BitPackedGrid::new_from_string("..@..\n..@..\n..@..\n".to_string());
+        let pyramid = GridPyramid::new(grid, 2);
+        assert_eq!(pyramid.search((0, 0), (4, 2)), None);
+        assert_eq!(pyramid.search((2, 0), (1, 0)), None);
+        assert_eq!(pyramid.search((0, 0), (2, 1)), None);
+        assert_eq!(pyramid.search((0, 0), (9, 0)), None);
+        assert!(pyramid.search((0, 0), (1, 2)).is_some());
+    }
+}
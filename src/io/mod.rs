@@ -0,0 +1,11 @@
+//! # IO
+//! Reading and writing of map and benchmark file formats:
+//! * MovingAI, `.map` grid files and `.scen` scenario files of the Moving AI benchmark sets
+//! * Raster, PNG renderings of grids with overlays and occupancy imported from grayscale images
+//! * Polylines, paths in world coordinates as GeoJSON line strings and CSV points
+
+pub mod movingai;
+#[cfg(feature = "images")]
+pub mod raster;
+#[cfg(feature = "io")]
+pub mod polyline;
@@ -0,0 +1,239 @@
+//! # Moving AI Benchmarks
+//! The `.map` and `.scen` files of the Moving AI benchmark sets. A map starts
+//! with a `type`, `height` and `width` header ended by a `map` line, followed
+//! by a row of terrain characters per line, where `.`, `G` and `S` are
+//! passable on octile maps and every other character is an obstacle. A
+//! scenario lists queries with the length of an optimal octile path, read
+//! with `gppc::scenario`, so whole benchmark sets can be run against a solver
+//! and every answer checked for errors and suboptimality.
+
+use std::io;
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+use crate::domains::csrgraph::{octile_cost, Connectivity};
+use crate::gppc::scenario::parse_scenario;
+
+/// A query of a scenario, the start, the goal and the optimal length
+pub type ScenarioQuery = (Cell, Cell, f64);
+
+/// The suboptimality below which a path is considered optimal, absorbing the
+/// rounding of the lengths in scenario files
+const TOLERANCE: f64 = 1e-6;
+
+/// Checks if a terrain character is passable on an octile map
+pub fn is_passable(terrain: char) -> bool {
+    matches!(terrain, '.' | 'G' | 'S')
+}
+
+/// Parses a map with its header
+/// ## Returns
+/// The grid of the map, or an error if the header is missing or the rows do
+/// not match its size
+pub fn parse_map(map: &str) -> io::Result<BitPackedGrid> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let mut lines = map.lines().map(str::trim_end);
+    let (mut width, mut height) = (None, None);
+    for line in lines.by_ref() {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        match fields.as_slice() {
+            ["map"] => break,
+            ["type", _] => {}
+            ["height", h] => height = h.parse::<usize>().ok(),
+            ["width", w] => width = w.parse::<usize>().ok(),
+            _ => return Err(invalid(format!("Invalid map header: {}", line))),
+        }
+    }
+    let (Some(width), Some(height)) = (width, height) else {
+        return Err(invalid("Missing map size".to_string()));
+    };
+    let rows = lines.filter(|l| !l.is_empty()).collect::<Vec<_>>();
+    if rows.len() != height || rows.iter().any(|r| r.chars().count() != width) {
+        return Err(invalid(format!("Map rows do not match a size of {}x{}", width, height)));
+    }
+    let mut grid = BitPackedGrid::new(width, height);
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            if is_passable(c) {
+                grid.set_bit_value((x, y), true);
+            }
+        }
+    }
+    Ok(grid)
+}
+
+/// Reads a map file
+pub fn load_map(filename: &str) -> io::Result<BitPackedGrid> {
+    parse_map(&std::fs::read_to_string(filename)?)
+}
+
+/// Writes a grid as an octile map with its header
+pub fn write_map(grid: &BitPackedGrid) -> String {
+    format!("type octile\nheight {}\nwidth {}\nmap\n{}", grid.original_height, grid.original_width, grid.print_cells(None))
+}
+
+/// Parses the queries of a scenario
+pub fn parse_queries(scenario: &str) -> io::Result<Vec<ScenarioQuery>> {
+    Ok(parse_scenario(scenario)?.into_iter().map(|e| (e.start, e.goal, e.optimal)).collect())
+}
+
+/// Reads the queries of a scenario file
+pub fn load_queries(filename: &str) -> io::Result<Vec<ScenarioQuery>> {
+    parse_queries(&std::fs::read_to_string(filename)?)
+}
+
+/// An error in the answer of a solver to a query
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryError {
+    /// No path was found for a query with a path
+    NotFound,
+    /// The path does not go from the start to the goal by legal moves
+    InvalidPath,
+    /// The path is shorter than the optimal length of the scenario
+    ShorterThanOptimal,
+}
+
+/// The answer of a solver to a query
+/// ## Fields
+/// * `query` - The query answered
+/// * `length` - The octile length of the path found, if any
+/// * `error` - The error in the answer, if any
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryOutcome {
+    pub query: ScenarioQuery,
+    pub length: Option<f64>,
+    pub error: Option<QueryError>,
+}
+
+impl QueryOutcome {
+    /// The length of the path over the optimal length, for answers without errors
+    pub fn suboptimality(&self) -> Option<f64> {
+        let length = self.length.filter(|_| self.error.is_none())?;
+        Some(if self.query.2 > 0.0 { length / self.query.2 } else { 1.0 })
+    }
+}
+
+/// The answers of a solver to the queries of a scenario
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScenarioReport {
+    pub outcomes: Vec<QueryOutcome>,
+}
+
+impl ScenarioReport {
+    /// The answers with errors
+    pub fn errors(&self) -> impl Iterator<Item = &QueryOutcome> + '_ {
+        self.outcomes.iter().filter(|o| o.error.is_some())
+    }
+
+    /// The number of answers without errors whose paths are longer than optimal
+    pub fn suboptimal(&self) -> usize {
+        self.outcomes.iter().filter(|o| o.suboptimality().is_some_and(|s| s > 1.0 + TOLERANCE)).count()
+    }
+
+    /// The mean suboptimality of the answers without errors
+    pub fn mean_suboptimality(&self) -> f64 {
+        let suboptimality = self.outcomes.iter().filter_map(QueryOutcome::suboptimality).collect::<Vec<_>>();
+        suboptimality.iter().sum::<f64>() / suboptimality.len().max(1) as f64
+    }
+
+    /// The largest suboptimality of the answers without errors
+    pub fn max_suboptimality(&self) -> f64 {
+        self.outcomes.iter().filter_map(QueryOutcome::suboptimality).fold(1.0, f64::max)
+    }
+
+    /// A one line summary of the report
+    pub fn summary(&self) -> String {
+        format!(
+            "{} queries, {} errors, {} suboptimal, suboptimality mean {:.4} max {:.4}",
+            self.outcomes.len(),
+            self.errors().count(),
+            self.suboptimal(),
+            self.mean_suboptimality(),
+            self.max_suboptimality(),
+        )
+    }
+}
+
+/// Runs a solver on the queries of a scenario, checking every path is a
+/// sequence of 8-connected moves without corner cutting between free cells
+/// ## Arguments
+/// * `grid` - The map of the scenario
+/// * `queries` - The queries of the scenario
+/// * `solver` - Finds a path on the map between a start and a goal
+pub fn run_queries<S>(grid: &BitPackedGrid, queries: &[ScenarioQuery], mut solver: S) -> ScenarioReport
+where
+    S: FnMut(&BitPackedGrid, Cell, Cell) -> Option<Vec<Cell>>,
+{
+    let free = |n: Cell| grid.bounds_check(n) && grid.get_bit_value(n);
+    let outcomes = queries.iter().map(|&(start, goal, optimal)| {
+        let Some(path) = solver(grid, start, goal) else {
+            return QueryOutcome { query: (start, goal, optimal), length: None, error: Some(QueryError::NotFound) };
+        };
+        let length = path.windows(2).map(|m| octile_cost(m[0], m[1])).sum::<f64>();
+        let valid = path.first() == Some(&start) && path.last() == Some(&goal)
+            && path.iter().all(|n| free(*n))
+            && path.windows(2).all(|m| Connectivity::EightNoCornerCutting.allows(m[0], m[1], free));
+        let error = if !valid {
+            Some(QueryError::InvalidPath)
+        } else if length < optimal - TOLERANCE * optimal.max(1.0) {
+            Some(QueryError::ShorterThanOptimal)
+        } else {
+            None
+        };
+        QueryOutcome { query: (start, goal, optimal), length: Some(length), error }
+    }).collect();
+    ScenarioReport { outcomes }
+}
+
+/// Runs a solver on a scenario file and its map file
+pub fn run_scenario_file<S>(map_file: &str, scenario_file: &str, solver: S) -> io::Result<ScenarioReport>
+where
+    S: FnMut(&BitPackedGrid, Cell, Cell) -> Option<Vec<Cell>>,
+{
+    Ok(run_queries(&load_map(map_file)?, &load_queries(scenario_file)?, solver))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::octile::exact_octile_distance;
+    use crate::search::astar::astar;
+
+    fn octile_astar(grid: &BitPackedGrid, start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+        astar(|n| grid.adjacent_octile(*n), start, |n| *n == goal, |n| exact_octile_distance(*n, goal)).map(|(p, _)| p)
+    }
+
+    #[test]
+    fn test_parse_map() {
+        let map = "type octile\nheight 3\nwidth 4\nmap\n.G@.\nS.T.\n....\n";
+        let grid = parse_map(map).unwrap();
+        assert_eq!(grid.print_cells(None), "..@.\n..@.\n....\n");
+        assert_eq!(parse_map(&write_map(&grid)).unwrap().print_cells(None), grid.print_cells(None));
+        assert!(parse_map("type octile\nheight 2\nwidth 4\nmap\n....\n").is_err());
+        assert!(parse_map("....\n....\n").is_err());
+        assert_eq!(load_map("tests/map.map").unwrap().print_cells(None), BitPackedGrid::new_from_file("tests/map.map").print_cells(None));
+    }
+
+    #[test]
+    fn test_run_scenario_file() {
+        let grid = parse_map("type octile\nheight 3\nwidth 5\nmap\n.....\n.@@@.\n.....\n").unwrap();
+        let scenario = "version 1\n0\tt.map\t5\t3\t0\t0\t4\t2\t6\n0\tt.map\t5\t3\t0\t0\t4\t0\t4\n";
+        let (map_file, scenario_file) = (std::env::temp_dir().join("test_movingai.map"), std::env::temp_dir().join("test_movingai.scen"));
+        std::fs::write(&map_file, write_map(&grid)).unwrap();
+        std::fs::write(&scenario_file, scenario).unwrap();
+        let report = run_scenario_file(map_file.to_str().unwrap(), scenario_file.to_str().unwrap(), octile_astar).unwrap();
+        assert_eq!((report.errors().count(), report.suboptimal()), (0, 0));
+        assert!((report.max_suboptimality() - 1.0).abs() < 1e-6);
+
+        // A detour is suboptimal, a jump is invalid and a missing path is an error
+        let detour = run_queries(&grid, &parse_queries(scenario).unwrap()[1..], |_, _, _| {
+            Some(vec![(0, 0), (0, 1), (0, 2), (1, 2), (2, 2), (3, 2), (4, 2), (4, 1), (4, 0)])
+        });
+        assert_eq!((detour.suboptimal(), detour.max_suboptimality()), (1, 2.0));
+        let jump = run_queries(&grid, &parse_queries(scenario).unwrap(), |_, s, g| Some(vec![s, g]));
+        assert!(jump.errors().all(|o| o.error == Some(QueryError::InvalidPath)));
+        let missing = run_queries(&grid, &parse_queries(scenario).unwrap(), |_, _, _| None);
+        assert_eq!(missing.errors().count(), 2);
+        std::fs::remove_file(map_file).unwrap();
+        std::fs::remove_file(scenario_file).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+//! # Polylines
+//! Export of paths as polylines in world coordinates for GIS and robotics
+//! toolchains, as a GeoJSON feature collection of line strings or as CSV rows
+//! of points. Cells are placed in the world by a `CellTransform`, the size of
+//! a cell and the world position of the corner of the map, with the y axis
+//! either pointing down the rows of the map or up, as in ROS occupancy grids.
+
+use std::io::{self, Write};
+
+use serde_json::{json, Value};
+
+use crate::domains::Cell;
+
+/// The placement of the cells of a grid map in world coordinates
+/// ## Fields
+/// * `cell_size` - The width and height of a cell in world units
+/// * `origin` - The world position of the top left corner of the map, or of
+///   the bottom left corner when the y axis points up
+/// * `height` - The height of the map when the y axis points up, `None` when
+///   it points down the rows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellTransform {
+    pub cell_size: f64,
+    pub origin: (f64, f64),
+    pub height: Option<usize>,
+}
+
+impl Default for CellTransform {
+    fn default() -> Self {
+        CellTransform { cell_size: 1.0, origin: (0.0, 0.0), height: None }
+    }
+}
+
+impl CellTransform {
+    /// A transform with the y axis pointing up from the bottom left corner,
+    /// as the resolution and origin of a ROS map
+    pub fn y_up(cell_size: f64, origin: (f64, f64), height: usize) -> Self {
+        CellTransform { cell_size, origin, height: Some(height) }
+    }
+
+    /// The world position of the centre of a cell
+    pub fn to_world(&self, (x, y): Cell) -> (f64, f64) {
+        let row = match self.height {
+            Some(height) => height as f64 - y as f64 - 0.5,
+            None => y as f64 + 0.5,
+        };
+        (self.origin.0 + (x as f64 + 0.5) * self.cell_size, self.origin.1 + row * self.cell_size)
+    }
+
+    /// The cell containing a world position, `None` left of or below the origin
+    pub fn to_cell(&self, (x, y): (f64, f64)) -> Option<Cell> {
+        let column = ((x - self.origin.0) / self.cell_size).floor();
+        let mut row = ((y - self.origin.1) / self.cell_size).floor();
+        if let Some(height) = self.height {
+            row = height as f64 - 1.0 - row;
+        }
+        (column >= 0.0 && row >= 0.0).then_some((column as usize, row as usize))
+    }
+}
+
+/// The paths as a GeoJSON feature collection of line strings, each with its
+/// index and world length as properties
+pub fn paths_to_geojson(paths: &[Vec<Cell>], transform: &CellTransform) -> Value {
+    let features = paths.iter().enumerate().map(|(index, path)| {
+        let points = path.iter().map(|n| transform.to_world(*n)).collect::<Vec<_>>();
+        let length = points.windows(2).map(|w| (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1)).sum::<f64>();
+        json!({
+            "type": "Feature",
+            "properties": { "index": index, "length": length },
+            "geometry": {
+                "type": "LineString",
+                "coordinates": points.iter().map(|(x, y)| [*x, *y]).collect::<Vec<_>>(),
+            },
+        })
+    });
+    json!({ "type": "FeatureCollection", "features": features.collect::<Vec<_>>() })
+}
+
+/// Writes the paths as a GeoJSON feature collection
+pub fn write_geojson(paths: &[Vec<Cell>], transform: &CellTransform, writer: &mut impl Write) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *writer, &paths_to_geojson(paths, transform))?;
+    writeln!(writer)
+}
+
+/// Writes the points of the paths as CSV rows of the path index, the point
+/// index and the world position
+pub fn write_paths_csv(paths: &[Vec<Cell>], transform: &CellTransform, writer: &mut impl Write) -> io::Result<()> {
+    writeln!(writer, "path,point,x,y")?;
+    for (index, path) in paths.iter().enumerate() {
+        for (point, cell) in path.iter().enumerate() {
+            let (x, y) = transform.to_world(*cell);
+            writeln!(writer, "{},{},{},{}", index, point, x, y)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polylines() {
+        let transform = CellTransform::y_up(0.5, (10.0, 20.0), 4);
+        assert_eq!(transform.to_world((0, 3)), (10.25, 20.25));
+        assert_eq!(transform.to_cell(transform.to_world((2, 1))), Some((2, 1)));
+        assert_eq!(CellTransform::default().to_world((1, 2)), (1.5, 2.5));
+        let paths = vec![vec![(0, 0), (1, 0), (1, 1)], vec![(2, 2)]];
+        let geojson = paths_to_geojson(&paths, &CellTransform::default());
+        assert_eq!(geojson["features"][0]["geometry"]["coordinates"], json!([[0.5, 0.5], [1.5, 0.5], [1.5, 1.5]]));
+        assert_eq!(geojson["features"][0]["properties"]["length"], json!(2.0));
+        assert_eq!(geojson["features"][1]["properties"]["index"], json!(1));
+        let mut csv = Vec::new();
+        write_paths_csv(&paths, &CellTransform::default(), &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().collect::<Vec<_>>(), ["path,point,x,y", "0,0,0.5,0.5", "0,1,1.5,0.5", "0,2,1.5,1.5", "1,0,2.5,2.5"]);
+        let mut file = Vec::new();
+        write_geojson(&paths, &transform, &mut file).unwrap();
+        assert_eq!(serde_json::from_slice::<Value>(&file).unwrap(), paths_to_geojson(&paths, &transform));
+    }
+}
@@ -0,0 +1,188 @@
+//! # Raster Images
+//! Rendering of grid maps to PNG images and import of occupancy from
+//! grayscale images, such as the map images saved by ROS `map_server`. A
+//! rendered cell covers a square of `scale` pixels a side, obstacles black and
+//! free cells white, and belief, heatmap and path overlays are blended over
+//! it. Imported pixels are read as the probability of a cell being occupied,
+//! dark pixels being occupied unless the thresholds are negated, and pixels
+//! between the free and occupied thresholds are unknown.
+
+use std::io;
+
+use image::{GrayImage, Rgb, RgbImage};
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid, grid::Grid, samplegrid::SampleGrid};
+
+/// Converts an image error into an IO error
+fn to_io(error: image::ImageError) -> io::Error {
+    match error {
+        image::ImageError::IoError(error) => error,
+        error => io::Error::new(io::ErrorKind::InvalidData, error),
+    }
+}
+
+/// A rendering of a grid map with overlays
+/// ## Fields
+/// * `image` - The rendered pixels
+/// * `scale` - The number of pixels along each side of a cell
+#[derive(Clone, Debug)]
+pub struct Raster {
+    pub image: RgbImage,
+    pub scale: u32,
+}
+
+impl Raster {
+    /// Renders a cell function, white where it returns 1.0 and black where 0.0
+    fn from_fn(width: usize, height: usize, scale: u32, value: impl Fn(Cell) -> f32) -> Self {
+        let scale = scale.max(1);
+        let image = RgbImage::from_fn(width as u32 * scale, height as u32 * scale, |x, y| {
+            let shade = (value(((x / scale) as usize, (y / scale) as usize)).clamp(0.0, 1.0) * 255.0).round() as u8;
+            Rgb([shade; 3])
+        });
+        Raster { image, scale }
+    }
+
+    /// Renders a grid map
+    pub fn from_grid(grid: &BitPackedGrid, scale: u32) -> Self {
+        Raster::from_fn(grid.original_width, grid.original_height, scale, |n| grid.get_bit_value(n) as u8 as f32)
+    }
+
+    /// Renders the belief of a sampling grid, shading each cell by its chance of being free
+    pub fn from_belief(grid: &SampleGrid, scale: u32) -> Self {
+        Raster::from_fn(grid.width, grid.height, scale, |n| grid.belief(n))
+    }
+
+    /// Fills the pixels of a cell, blending a colour over them by a weight in [0, 1]
+    fn blend(&mut self, (x, y): Cell, (r, g, b): (u8, u8, u8), weight: f64) {
+        let weight = weight.clamp(0.0, 1.0);
+        let (x, y) = (x as u32 * self.scale, y as u32 * self.scale);
+        if x >= self.image.width() || y >= self.image.height() {
+            return;
+        }
+        for px in x..x + self.scale {
+            for py in y..y + self.scale {
+                let Rgb(pixel) = self.image.get_pixel_mut(px, py);
+                for (channel, colour) in pixel.iter_mut().zip([r, g, b]) {
+                    *channel = (*channel as f64 * (1.0 - weight) + colour as f64 * weight).round() as u8;
+                }
+            }
+        }
+    }
+
+    /// Blends a heatmap of values in [0, 1] over the rendering in a colour
+    pub fn heatmap(&mut self, heatmap: &[(Cell, f64)], colour: (u8, u8, u8)) -> &mut Self {
+        for &(cell, value) in heatmap {
+            self.blend(cell, colour, value);
+        }
+        self
+    }
+
+    /// Draws the cells of a path in a colour
+    pub fn path(&mut self, path: &[Cell], colour: (u8, u8, u8)) -> &mut Self {
+        for &cell in path {
+            self.blend(cell, colour, 1.0);
+        }
+        self
+    }
+
+    /// Saves the rendering as a PNG image
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        self.image.save_with_format(filename, image::ImageFormat::Png).map_err(to_io)
+    }
+}
+
+/// The thresholds classifying the pixels of an occupancy image, following
+/// the map images of ROS `map_server`
+/// ## Fields
+/// * `occupied` - Pixels with an occupancy probability above this are occupied
+/// * `free` - Pixels with an occupancy probability below this are free
+/// * `negate` - Whether white pixels are occupied rather than black ones
+/// * `unknown_free` - Whether unknown pixels are imported as free cells
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OccupancyThresholds {
+    pub occupied: f32,
+    pub free: f32,
+    pub negate: bool,
+    pub unknown_free: bool,
+}
+
+impl Default for OccupancyThresholds {
+    fn default() -> Self {
+        OccupancyThresholds { occupied: 0.65, free: 0.196, negate: false, unknown_free: false }
+    }
+}
+
+impl OccupancyThresholds {
+    /// Classifies a pixel as free, occupied or unknown
+    /// ## Returns
+    /// Whether the pixel is free, `None` if it is unknown
+    pub fn classify(&self, luma: u8) -> Option<bool> {
+        let shade = luma as f32 / 255.0;
+        let occupancy = if self.negate { shade } else { 1.0 - shade };
+        if occupancy > self.occupied {
+            Some(false)
+        } else if occupancy < self.free {
+            Some(true)
+        } else {
+            None
+        }
+    }
+}
+
+/// The occupancy of every pixel of a grayscale image, true for free cells
+/// and `None` for unknown ones
+pub fn occupancy(image: &GrayImage, thresholds: &OccupancyThresholds) -> Grid<Option<bool>> {
+    Grid::from_fn(image.width() as usize, image.height() as usize, |(x, y)| {
+        thresholds.classify(image.get_pixel(x as u32, y as u32).0[0])
+    })
+}
+
+/// Imports a grid map from a grayscale image, one cell per pixel
+pub fn grid_from_image(image: &GrayImage, thresholds: &OccupancyThresholds) -> BitPackedGrid {
+    let occupancy = occupancy(image, thresholds);
+    let mut grid = BitPackedGrid::new(occupancy.width, occupancy.height);
+    for (cell, free) in occupancy.iter() {
+        grid.set_bit_value(cell, free.unwrap_or(thresholds.unknown_free));
+    }
+    grid
+}
+
+/// Loads a grid map from an image file, converted to grayscale
+pub fn load_occupancy(filename: &str, thresholds: &OccupancyThresholds) -> io::Result<BitPackedGrid> {
+    let image = image::open(filename).map_err(to_io)?.to_luma8();
+    Ok(grid_from_image(&image, thresholds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raster_round_trip() {
+        let grid = BitPackedGrid::new_from_string("....\n.@@.\n....\n".to_string());
+        let mut raster = Raster::from_grid(&grid, 2);
+        assert_eq!(raster.image.dimensions(), (8, 6));
+        assert_eq!((raster.image.get_pixel(2, 2), raster.image.get_pixel(1, 1)), (&Rgb([0; 3]), &Rgb([255; 3])));
+        raster.heatmap(&[((0, 0), 0.5)], (0, 0, 255)).path(&[(3, 2)], (255, 0, 0));
+        assert_eq!((raster.image.get_pixel(0, 0), raster.image.get_pixel(7, 5)), (&Rgb([128, 128, 255]), &Rgb([255, 0, 0])));
+        let file = std::env::temp_dir().join("pathfinding_raster_test.png");
+        let file = file.to_str().unwrap();
+        Raster::from_grid(&grid, 1).save(file).unwrap();
+        let loaded = load_occupancy(file, &OccupancyThresholds::default()).unwrap();
+        assert_eq!(loaded.free_cells().collect::<Vec<_>>(), grid.free_cells().collect::<Vec<_>>());
+        std::fs::remove_file(file).unwrap();
+        assert!(load_occupancy("missing.png", &OccupancyThresholds::default()).is_err());
+    }
+
+    #[test]
+    fn test_occupancy_thresholds() {
+        let image = GrayImage::from_raw(4, 1, vec![0, 128, 210, 255]).unwrap();
+        let thresholds = OccupancyThresholds::default();
+        assert_eq!(occupancy(&image, &thresholds).iter().map(|(_, o)| *o).collect::<Vec<_>>(), vec![Some(false), None, Some(true), Some(true)]);
+        let negated = OccupancyThresholds { negate: true, unknown_free: true, ..thresholds };
+        let grid = grid_from_image(&image, &negated);
+        assert_eq!((0..4).map(|x| grid.get_bit_value((x, 0))).collect::<Vec<_>>(), vec![true, true, false, false]);
+        let belief = SampleGrid::new_from_grid(vec![vec![0.0], vec![1.0]], BitPackedGrid::new_from_string("..\n".to_string()));
+        assert_eq!(Raster::from_belief(&belief, 1).image.get_pixel(1, 0), &Rgb([255; 3]));
+    }
+}
@@ -0,0 +1,45 @@
+//! # Pathfinding Algorithms
+//! A collection of pathfinding algorithms, domains and tools. The crate is
+//! split into cargo features so only the planners that are needed are
+//! compiled, all of which except `deterministic`, `profiling` and `serde` are enabled by default:
+//! * `grids` - Grid and graph domains, heuristics, field of vision, map analysis, paths
+//! * `search-core` - Search algorithms, the hierarchical planners built on them and the competition harness
+//! * `sampling` - Sampling grids, belief filters, risk-sensitive planning and simulation
+//! * `mapf` - Multi-agent pathfinding utilities
+//! * `continuous` - Planners over continuous space
+//! * `viz` - Plotting of grids, paths and heatmaps to images
+//! * `io` - Export of search iterations for the external visualiser and of paths as GeoJSON
+//! * `images` - Rendering of grids to PNG images and import of occupancy images
+//! * `parallel` - Multi-threaded preprocessing with rayon
+//! * `deterministic` - Fixed seeds and hashers for identical results across runs
+//! * `profiling` - Timing of search phases in the search statistics
+//! * `serde` - Serialization of grids, beliefs and search statistics
+//!
+//! For example, an embedded or wasm build with only grid search can use
+//! `default-features = false, features = ["search-core"]`.
+
+#[cfg(feature = "grids")]
+pub mod domains;
+#[cfg(feature = "grids")]
+pub mod heuristics;
+#[cfg(feature = "search-core")]
+pub mod search;
+pub mod util;
+#[cfg(feature = "grids")]
+pub mod fov;
+#[cfg(feature = "grids")]
+pub mod gridpolicies;
+#[cfg(feature = "search-core")]
+pub mod hierarchy;
+#[cfg(feature = "mapf")]
+pub mod mapf;
+#[cfg(feature = "search-core")]
+pub mod analysis;
+#[cfg(feature = "sampling")]
+pub mod sim;
+#[cfg(feature = "search-core")]
+pub mod path;
+#[cfg(feature = "search-core")]
+pub mod gppc;
+#[cfg(feature = "search-core")]
+pub mod io;
@@ -1,20 +1,13 @@
-mod domains;
-mod heuristics;
-mod search;
-mod util;
-mod fov;
-mod gridpolicies;
-
 fn main() {
 }
 
 
-#[cfg(test)]
+#[cfg(all(test, feature = "viz", feature = "search-core"))]
 mod tests {
 
     use std::collections::HashSet;
 
-    use crate::{domains::bitpackedgrid::BitPackedGrid, search::astar::astar_with_expanded_set, heuristics::distance::manhattan_distance};
+    use pathfinding_rs::{domains::bitpackedgrid::BitPackedGrid, search::astar::astar_with_expanded_set, heuristics::distance::manhattan_distance};
 
     #[test]
     fn run_test() {
@@ -0,0 +1,362 @@
+//! # Conflict-Based Search
+//! An optimal MAPF solver following Sharon et al., "Conflict-Based Search for
+//! Optimal Multi-Agent Pathfinding" (2015). The high level searches a tree of
+//! constraint sets in increasing sum of costs, where each node plans every
+//! agent independently under its constraints. A node whose paths conflict is
+//! split on one conflict into two children, each forbidding one of the agents
+//! from the contested cell or move at that time.
+//!
+//! The improvements of ICBS and later work make the solver usable beyond a
+//! handful of agents, each of which can be turned off to compare against:
+//! * Prioritised conflicts, conflicts are classified with the MDDs of the
+//!   agents, see `mdd`, and cardinal conflicts, which raise the cost of both
+//!   children, are split before semi-cardinal and non-cardinal ones
+//! * Bypassing, a child with the same cost and fewer conflicts than its parent
+//!   replaces the paths of the parent instead of being added to the tree
+//! * The CG heuristic, the size of a minimum vertex cover of the graph of
+//!   agents with cardinal conflicts, which bounds the extra cost at each node
+//!
+//! Agents move 4-connected or wait, and stay at their goal once their path ends.
+
+use std::{cmp::Reverse, collections::{BinaryHeap, HashMap, HashSet}};
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+use crate::search::astar::astar;
+
+use super::mdd::{grid_distances, grid_moves, Mdd};
+use super::metrics::{conflicts, sum_of_costs, Conflict};
+
+/// A constraint on the low level search of an agent
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Constraint {
+    /// The agent may not occupy the cell at the time
+    Vertex { agent: usize, cell: Cell, time: usize },
+    /// The agent may not move between the cells from the time to the next
+    Edge { agent: usize, from: Cell, to: Cell, time: usize },
+}
+
+impl Constraint {
+    /// The agent that is constrained
+    pub fn agent(&self) -> usize {
+        match self {
+            Constraint::Vertex { agent, .. } | Constraint::Edge { agent, .. } => *agent,
+        }
+    }
+}
+
+/// How much a conflict raises the cost of resolving it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Cardinality {
+    /// Both children of the split cost more than the parent
+    Cardinal,
+    /// One child of the split costs more than the parent
+    SemiCardinal,
+    /// Neither child of the split needs to cost more than the parent
+    NonCardinal,
+}
+
+/// The configuration of CBS
+/// ## Fields
+/// * `bypass` - Adopts the paths of children with the same cost and fewer conflicts
+/// * `prioritize_conflicts` - Splits cardinal conflicts first
+/// * `cg_heuristic` - Orders the high level by cost plus the CG heuristic
+/// * `max_nodes` - The number of high level nodes to expand before giving up
+#[derive(Clone, Debug)]
+pub struct CbsConfig {
+    pub bypass: bool,
+    pub prioritize_conflicts: bool,
+    pub cg_heuristic: bool,
+    pub max_nodes: usize,
+}
+
+impl Default for CbsConfig {
+    fn default() -> Self {
+        CbsConfig { bypass: true, prioritize_conflicts: true, cg_heuristic: true, max_nodes: 10_000 }
+    }
+}
+
+/// A solution of CBS
+/// ## Fields
+/// * `paths` - The timed path of each agent, see the module documentation of `mapf`
+/// * `sum_of_costs` - The sum of the costs of the paths
+/// * `expanded` - The number of high level nodes expanded
+/// * `generated` - The number of high level nodes generated
+/// * `bypasses` - The number of splits avoided by bypassing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CbsSolution {
+    pub paths: Vec<Vec<Cell>>,
+    pub sum_of_costs: usize,
+    pub expanded: usize,
+    pub generated: usize,
+    pub bypasses: usize,
+}
+
+/// A node of the constraint tree
+struct CbsNode {
+    constraints: Vec<Constraint>,
+    paths: Vec<Vec<Cell>>,
+    cost: usize,
+    heuristic: usize,
+    conflicts: Vec<Conflict<Cell>>,
+}
+
+/// The instance being solved and its per-agent distances to the goal
+struct Instance<'a> {
+    grid: &'a BitPackedGrid,
+    agents: &'a [(Cell, Cell)],
+    to_goal: Vec<HashMap<Cell, usize>>,
+}
+
+impl Instance<'_> {
+    /// Plans the shortest path of an agent satisfying its constraints
+    fn plan(&self, agent: usize, constraints: &[Constraint]) -> Option<Vec<Cell>> {
+        let (start, goal) = self.agents[agent];
+        let to_goal = &self.to_goal[agent];
+        if !to_goal.contains_key(&start) {
+            return None;
+        }
+        let (vertices, edges) = agent_constraints(agent, constraints);
+        // The agent must stay at its goal from after the last constraint on it
+        let last_goal = vertices.iter().filter(|(c, _)| *c == goal).map(|(_, t)| *t).max();
+        let horizon = constraints.iter().map(|c| match c {
+            Constraint::Vertex { time, .. } | Constraint::Edge { time, .. } => *time,
+        }).max().unwrap_or(0) + to_goal.len() + 1;
+        let (path, _) = astar(
+            |(cell, time): &(Cell, usize)| {
+                let (cell, time) = (*cell, *time);
+                grid_moves(self.grid, cell)
+                    .filter(|n| time < horizon && to_goal.contains_key(n))
+                    .filter(|n| !vertices.contains(&(*n, time + 1)) && !edges.contains(&(cell, *n, time)))
+                    .map(|n| ((n, time + 1), 1))
+                    .collect::<Vec<_>>()
+            },
+            (start, 0),
+            |(cell, time)| *cell == goal && last_goal.is_none_or(|t| *time > t),
+            |(cell, _)| to_goal[cell],
+        )?;
+        Some(path.into_iter().map(|(cell, _)| cell).collect())
+    }
+
+    /// The MDD of an agent for the cost of its path, under its constraints
+    fn mdd(&self, agent: usize, constraints: &[Constraint], cost: usize) -> Mdd<(Cell, usize)> {
+        let (start, goal) = self.agents[agent];
+        let (vertices, edges) = agent_constraints(agent, constraints);
+        Mdd::new(
+            |(cell, time): &(Cell, usize)| {
+                let (cell, time) = (*cell, *time);
+                grid_moves(self.grid, cell)
+                    .filter(|n| !vertices.contains(&(*n, time + 1)) && !edges.contains(&(cell, *n, time)))
+                    .map(|n| (n, time + 1))
+                    .collect::<Vec<_>>()
+            },
+            (start, 0),
+            (goal, cost),
+            cost,
+        )
+    }
+}
+
+/// The vertex and edge constraints of an agent
+type AgentConstraints = (HashSet<(Cell, usize)>, HashSet<(Cell, Cell, usize)>);
+
+/// Collects the constraints of an agent into sets
+fn agent_constraints(agent: usize, constraints: &[Constraint]) -> AgentConstraints {
+    let mut vertices = HashSet::new();
+    let mut edges = HashSet::new();
+    for constraint in constraints.iter().filter(|c| c.agent() == agent) {
+        match *constraint {
+            Constraint::Vertex { cell, time, .. } => {
+                vertices.insert((cell, time));
+            }
+            Constraint::Edge { from, to, time, .. } => {
+                edges.insert((from, to, time));
+            }
+        }
+    }
+    (vertices, edges)
+}
+
+/// The MDDs of the agents of a node, built as conflicts are classified
+struct Mdds<'a> {
+    instance: &'a Instance<'a>,
+    mdds: HashMap<usize, Mdd<(Cell, usize)>>,
+}
+
+impl Mdds<'_> {
+    /// Checks if every path of an agent's cost occupies its cell at a time,
+    /// which holds after the end of its path where the agent waits at its goal
+    fn is_singleton(&mut self, node: &CbsNode, agent: usize, time: usize) -> bool {
+        let cost = node.paths[agent].len() - 1;
+        if time >= cost {
+            return true;
+        }
+        let instance = self.instance;
+        let mdd = self.mdds.entry(agent).or_insert_with(|| instance.mdd(agent, &node.constraints, cost));
+        mdd.width(time) == 1
+    }
+
+    /// Classifies a conflict of a node by the MDDs of its agents
+    fn cardinality(&mut self, node: &CbsNode, conflict: &Conflict<Cell>) -> Cardinality {
+        let (a, b, times) = match conflict {
+            Conflict::Vertex { agents: (a, b), time, .. } => (*a, *b, vec![*time]),
+            Conflict::Edge { agents: (a, b), time, .. } => (*a, *b, vec![*time, time + 1]),
+        };
+        let singleton_a = times.iter().all(|t| self.is_singleton(node, a, *t));
+        let singleton_b = times.iter().all(|t| self.is_singleton(node, b, *t));
+        match (singleton_a, singleton_b) {
+            (true, true) => Cardinality::Cardinal,
+            (false, false) => Cardinality::NonCardinal,
+            _ => Cardinality::SemiCardinal,
+        }
+    }
+}
+
+/// The size of a minimum vertex cover of a graph given by its edges
+pub fn min_vertex_cover(edges: &[(usize, usize)]) -> usize {
+    let Some(&(u, v)) = edges.first() else { return 0 };
+    // One end of every edge is in the cover, so branch on the ends of the first
+    let without = |removed: usize| {
+        edges.iter().filter(|(a, b)| *a != removed && *b != removed).copied().collect::<Vec<_>>()
+    };
+    1 + min_vertex_cover(&without(u)).min(min_vertex_cover(&without(v)))
+}
+
+/// The CG heuristic of a node, the minimum vertex cover of its cardinal conflicts
+fn cg_heuristic(node: &CbsNode, mdds: &mut Mdds) -> usize {
+    let mut edges = Vec::new();
+    for conflict in &node.conflicts {
+        let agents = match conflict {
+            Conflict::Vertex { agents, .. } | Conflict::Edge { agents, .. } => *agents,
+        };
+        let edge = (agents.0.min(agents.1), agents.0.max(agents.1));
+        if !edges.contains(&edge) && mdds.cardinality(node, conflict) == Cardinality::Cardinal {
+            edges.push(edge);
+        }
+    }
+    min_vertex_cover(&edges)
+}
+
+/// The constraints that resolve a conflict, one for each of its agents
+fn split(conflict: &Conflict<Cell>) -> [Constraint; 2] {
+    match *conflict {
+        Conflict::Vertex { agents: (a, b), node, time } => [
+            Constraint::Vertex { agent: a, cell: node, time },
+            Constraint::Vertex { agent: b, cell: node, time },
+        ],
+        // The first agent moves along the edge and the second against it
+        Conflict::Edge { agents: (a, b), edge: (u, v), time } => [
+            Constraint::Edge { agent: a, from: u, to: v, time },
+            Constraint::Edge { agent: b, from: v, to: u, time },
+        ],
+    }
+}
+
+/// Solves a MAPF instance optimally with respect to sum of costs
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `agents` - The start and goal of each agent, starts and goals must be distinct
+/// * `config` - The improvements to use and the node limit
+/// ## Returns
+/// The solution, or `None` if a goal is unreachable or no solution was found
+/// within the node limit
+pub fn cbs(grid: &BitPackedGrid, agents: &[(Cell, Cell)], config: &CbsConfig) -> Option<CbsSolution> {
+    let to_goal = agents.iter().map(|(_, goal)| grid_distances(grid, *goal)).collect();
+    let instance = Instance { grid, agents, to_goal };
+    let paths = (0..agents.len()).map(|a| instance.plan(a, &[])).collect::<Option<Vec<_>>>()?;
+    let root = CbsNode { cost: sum_of_costs(&paths), conflicts: conflicts(&paths), paths, constraints: Vec::new(), heuristic: 0 };
+    let mut nodes = vec![root];
+    let mut open = BinaryHeap::from([Reverse((0, 0, 0))]);
+    let mut solution = CbsSolution { paths: Vec::new(), sum_of_costs: 0, expanded: 0, generated: 1, bypasses: 0 };
+    while let Some(Reverse((_, _, index))) = open.pop() {
+        let node = &nodes[index];
+        if node.conflicts.is_empty() {
+            solution.sum_of_costs = node.cost;
+            solution.paths = node.paths.clone();
+            return Some(solution);
+        }
+        if solution.expanded == config.max_nodes {
+            return None;
+        }
+        solution.expanded += 1;
+        let mut mdds = Mdds { instance: &instance, mdds: HashMap::new() };
+        let conflict = match config.prioritize_conflicts {
+            true => node.conflicts.iter().min_by_key(|c| mdds.cardinality(node, c)).unwrap(),
+            false => &node.conflicts[0],
+        };
+        let mut children = Vec::new();
+        for constraint in split(conflict) {
+            let agent = constraint.agent();
+            let mut constraints = node.constraints.clone();
+            constraints.push(constraint);
+            let Some(path) = instance.plan(agent, &constraints) else { continue };
+            let mut paths = node.paths.clone();
+            paths[agent] = path;
+            let child = CbsNode { cost: sum_of_costs(&paths), conflicts: conflicts(&paths), paths, constraints, heuristic: 0 };
+            children.push(child);
+        }
+        solution.generated += children.len();
+        let bypass = children.iter()
+            .position(|c| c.cost == node.cost && c.conflicts.len() < node.conflicts.len())
+            .filter(|_| config.bypass);
+        if let Some(child) = bypass {
+            // The child's paths also satisfy the parent's constraints
+            let child = children.swap_remove(child);
+            let node = &mut nodes[index];
+            node.conflicts = child.conflicts;
+            node.paths = child.paths;
+            solution.bypasses += 1;
+            open.push(Reverse((node.cost + node.heuristic, node.conflicts.len(), index)));
+            continue;
+        }
+        for mut child in children {
+            if config.cg_heuristic {
+                let mut mdds = Mdds { instance: &instance, mdds: HashMap::new() };
+                child.heuristic = cg_heuristic(&child, &mut mdds);
+            }
+            open.push(Reverse((child.cost + child.heuristic, child.conflicts.len(), nodes.len())));
+            nodes.push(child);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapf::icts::icts;
+
+    #[test]
+    fn test_min_vertex_cover() {
+        assert_eq!(min_vertex_cover(&[]), 0);
+        assert_eq!(min_vertex_cover(&[(0, 1), (0, 2), (0, 3)]), 1);
+        assert_eq!(min_vertex_cover(&[(0, 1), (1, 2), (2, 0)]), 2);
+        assert_eq!(min_vertex_cover(&[(0, 1), (2, 3)]), 2);
+    }
+
+    #[test]
+    fn test_cbs() {
+        let grid = BitPackedGrid::new_from_string(".....\n@@.@@\n".to_string());
+        let agents = [((0, 0), (4, 0)), ((4, 0), (0, 0))];
+        let solution = cbs(&grid, &agents, &CbsConfig::default()).unwrap();
+        assert_eq!(solution.sum_of_costs, 11);
+        assert!(conflicts(&solution.paths).is_empty());
+
+        // Every configuration is optimal, the improvements expand fewer nodes
+        let grid = BitPackedGrid::new_from_string("....\n....\n....\n....\n".to_string());
+        let agents = [((0, 0), (3, 3)), ((3, 0), (0, 3)), ((0, 3), (3, 0)), ((3, 3), (0, 0))];
+        let optimal = icts(&grid, &agents, 1000).unwrap().sum_of_costs;
+        let plain = CbsConfig { bypass: false, prioritize_conflicts: false, cg_heuristic: false, ..Default::default() };
+        let plain = cbs(&grid, &agents, &plain).unwrap();
+        let improved = cbs(&grid, &agents, &CbsConfig::default()).unwrap();
+        assert_eq!(plain.sum_of_costs, optimal);
+        assert_eq!(improved.sum_of_costs, optimal);
+        assert!(conflicts(&improved.paths).is_empty());
+        assert!(improved.expanded <= plain.expanded);
+    }
+
+    #[test]
+    fn test_cbs_unreachable() {
+        let grid = BitPackedGrid::new_from_string(".@.\n".to_string());
+        assert!(cbs(&grid, &[((0, 0), (2, 0))], &CbsConfig::default()).is_none());
+    }
+}
@@ -0,0 +1,128 @@
+//! # Crowd Simulation
+//! A simple crowd layer where many agents follow their own paths or a shared
+//! flow field and resolve local collisions by reserving cells each tick. Every
+//! agent proposes its next node, and an agent waits instead when an agent with
+//! a lower index has reserved the same node, when the node is held by a waiting
+//! agent or when the move would swap nodes with another agent. The recorded
+//! trajectories are timed paths, so they are free of vertex and edge conflicts
+//! and can be compared against solvers with the MAPF metrics.
+
+use std::{collections::{HashMap, HashSet}, hash::Hash};
+
+/// A crowd of agents moving on a shared graph
+/// ## Fields
+/// * `positions` - The current node of each agent
+/// * `trajectories` - The timed path of each agent so far
+pub struct Crowd<N> {
+    pub positions: Vec<N>,
+    pub trajectories: Vec<Vec<N>>,
+}
+
+impl<N: Hash + Eq + Clone> Crowd<N> {
+    /// Creates a crowd with an agent at each start node
+    pub fn new(starts: Vec<N>) -> Self {
+        let trajectories = starts.iter().map(|s| vec![s.clone()]).collect();
+        Crowd { positions: starts, trajectories }
+    }
+
+    /// The number of ticks simulated
+    pub fn time(&self) -> usize {
+        self.trajectories.first().map_or(0, |t| t.len() - 1)
+    }
+
+    /// Advances every agent by one tick
+    /// ## Arguments
+    /// * `desired` - Returns the node an agent wants to move to from its
+    ///   current node, or `None` to wait
+    /// ## Returns
+    /// Whether each agent moved
+    pub fn step(&mut self, mut desired: impl FnMut(usize, &N) -> Option<N>) -> Vec<bool> {
+        let mut proposals = self.positions.iter().enumerate()
+            .map(|(agent, node)| desired(agent, node).filter(|next| next != node))
+            .collect::<Vec<_>>();
+        let occupant = self.positions.iter().enumerate().map(|(a, n)| (n, a)).collect::<HashMap<_, _>>();
+        loop {
+            let waiting = (0..proposals.len())
+                .filter(|a| proposals[*a].is_none())
+                .map(|a| &self.positions[a])
+                .collect::<HashSet<_>>();
+            let mut reserved = HashSet::new();
+            let mut changed = false;
+            for agent in 0..proposals.len() {
+                let Some(next) = &proposals[agent] else { continue };
+                let swap = occupant.get(next).is_some_and(|other| proposals[*other].as_ref() == Some(&self.positions[agent]));
+                if waiting.contains(next) || reserved.contains(next) || swap {
+                    proposals[agent] = None;
+                    changed = true;
+                } else {
+                    reserved.insert(next.clone());
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        let mut moved = Vec::with_capacity(proposals.len());
+        for (agent, proposal) in proposals.into_iter().enumerate() {
+            moved.push(proposal.is_some());
+            if let Some(next) = proposal {
+                self.positions[agent] = next;
+            }
+            self.trajectories[agent].push(self.positions[agent].clone());
+        }
+        moved
+    }
+
+    /// Simulates agents following their own paths until every agent has
+    /// finished, no agent can move or `max_ticks` have passed
+    pub fn follow_paths(paths: &[Vec<N>], max_ticks: usize) -> Self {
+        let mut crowd = Crowd::new(paths.iter().map(|p| p[0].clone()).collect());
+        let mut progress = vec![0; paths.len()];
+        while crowd.time() < max_ticks && progress.iter().zip(paths).any(|(i, p)| i + 1 < p.len()) {
+            let moved = crowd.step(|agent, _| paths[agent].get(progress[agent] + 1).cloned());
+            if !moved.contains(&true) {
+                break;
+            }
+            progress.iter_mut().zip(moved).filter(|(_, m)| *m).for_each(|(i, _)| *i += 1);
+        }
+        crowd
+    }
+
+    /// Simulates agents following a flow field, which gives the next node
+    /// towards the goal from each node, until no agent can move or
+    /// `max_ticks` have passed
+    pub fn follow_field(starts: Vec<N>, field: impl Fn(&N) -> Option<N>, max_ticks: usize) -> Self {
+        let mut crowd = Crowd::new(starts);
+        while crowd.time() < max_ticks && crowd.step(|_, node| field(node)).contains(&true) {}
+        crowd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapf::metrics::conflicts;
+
+    #[test]
+    fn test_crowd_follow_paths() {
+        // Two agents cross the same cell, with a third following the second
+        let paths = vec![vec![0, 1, 2], vec![3, 1, 4], vec![4, 3]];
+        let crowd = Crowd::follow_paths(&paths, 10);
+        assert_eq!(crowd.trajectories[0], vec![0, 1, 2, 2]);
+        assert_eq!(crowd.trajectories[1], vec![3, 3, 1, 4]);
+        assert_eq!(crowd.trajectories[2], vec![4, 4, 3, 3]);
+        assert!(conflicts(&crowd.trajectories).is_empty());
+
+        let crowd = Crowd::follow_paths(&[vec![0, 1], vec![1, 0]], 10);
+        assert_eq!(crowd.time(), 1);
+        assert_eq!(crowd.positions, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_crowd_follow_field() {
+        // Agents queue along a corridor towards 0, where each agent stops
+        let crowd = Crowd::follow_field(vec![2, 3, 5], |n: &i32| (*n > 0).then(|| n - 1), 20);
+        assert_eq!(crowd.positions, vec![0, 1, 2]);
+        assert!(conflicts(&crowd.trajectories).is_empty());
+    }
+}
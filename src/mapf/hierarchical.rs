@@ -0,0 +1,205 @@
+//! # Hierarchical MAPF
+//! Multi-agent planning on a cluster abstraction of the map, in the spirit of
+//! HPA*. Each agent first plans a route over the abstract graph, timed by the
+//! cost of its edges. Agents whose routes pass the same abstract node at about
+//! the same time conflict, and conflicts are resolved at the abstract level by
+//! replanning the lower-priority agent with the contested nodes penalised.
+//! The routes are then refined in priority order by a space-time search that
+//! is restricted to the clusters along each route and avoids the cells and
+//! moves reserved by higher-priority agents. Restricting refinement to a
+//! corridor of clusters keeps each search small on large maps.
+//!
+//! Plans can be made k-robust for agents that do not keep to their schedule,
+//! where no agent enters a cell within k ticks of another agent occupying it,
+//! so the plan stays conflict-free when agents are delayed by up to k ticks.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+use crate::heuristics::distance::manhattan_distance;
+use crate::hierarchy::abstraction::Abstraction;
+use crate::search::astar::astar;
+
+use super::prioritized::{space_time_plan, Reservations};
+
+/// Routes passing an abstract node within this many steps of each other conflict
+const ABSTRACT_WINDOW: usize = 2;
+/// The extra cost of an abstract node contested by a higher-priority agent
+const ABSTRACT_PENALTY: usize = 8;
+
+/// An abstract route with the time each of its nodes is reached
+type TimedRoute = Vec<(Cell, usize)>;
+
+/// A solution of the hierarchical planner
+/// ## Fields
+/// * `paths` - The timed path of each agent, see the module documentation of `mapf`
+/// * `routes` - The abstract route of each agent after conflict resolution
+/// * `abstract_conflicts` - The number of conflicts resolved at the abstract level
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HierarchicalPlan {
+    pub paths: Vec<Vec<Cell>>,
+    pub routes: Vec<Vec<Cell>>,
+    pub abstract_conflicts: usize,
+}
+
+/// Plans the abstract route of an agent, inserting its start and goal into the graph
+fn abstract_route(
+    grid: &BitPackedGrid,
+    abstraction: &mut Abstraction,
+    (start, goal): (Cell, Cell),
+    penalties: &HashMap<Cell, usize>,
+) -> Option<TimedRoute> {
+    let inserted = [abstraction.insert_node(grid, start), abstraction.insert_node(grid, goal)];
+    let route = astar(
+        |n| abstraction.graph.adjacent(*n)
+            .map(|(m, c)| (*m, c + penalties.get(m).copied().unwrap_or(0)))
+            .collect::<Vec<_>>(),
+        start,
+        |n| *n == goal,
+        |n| manhattan_distance(*n, goal),
+    );
+    for (node, inserted) in [start, goal].into_iter().zip(inserted) {
+        if inserted {
+            abstraction.remove_node(node);
+        }
+    }
+    let (route, _) = route?;
+    // Time the route by the unpenalised distance between its nodes
+    let mut time = 0;
+    let mut timed = vec![(start, 0)];
+    for pair in route.windows(2) {
+        time += manhattan_distance(pair[0], pair[1]).max(1);
+        timed.push((pair[1], time));
+    }
+    Some(timed)
+}
+
+/// Finds a conflict-free timed path within a set of clusters, or anywhere if
+/// `corridor` is `None`
+fn refine(
+    grid: &BitPackedGrid,
+    abstraction: &Abstraction,
+    agent: (Cell, Cell),
+    corridor: Option<&HashSet<usize>>,
+    reservations: &Reservations,
+    max_time: usize,
+) -> Option<Vec<Cell>> {
+    let allowed = |n: Cell| corridor.is_none_or(|c| c.contains(&abstraction.clusters.cluster_id(n)));
+    space_time_plan(grid, agent, allowed, reservations, max_time)
+}
+
+/// Plans conflict-free paths for many agents on a cluster abstraction
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `abstraction` - A cluster abstraction of the grid, restored after planning
+/// * `agents` - The (start, goal) of each agent in priority order
+/// * `max_time` - The latest time an agent may reach its goal
+/// ## Returns
+/// The plan, or `None` if an agent has no abstract route or no refined path
+pub fn plan_hierarchical(
+    grid: &BitPackedGrid,
+    abstraction: &mut Abstraction,
+    agents: &[(Cell, Cell)],
+    max_time: usize,
+) -> Option<HierarchicalPlan> {
+    plan_hierarchical_robust(grid, abstraction, agents, max_time, 0)
+}
+
+/// Plans k-robust paths for many agents on a cluster abstraction, where no agent
+/// enters a cell within `robustness` ticks of another agent occupying it
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `abstraction` - A cluster abstraction of the grid, restored after planning
+/// * `agents` - The (start, goal) of each agent in priority order
+/// * `max_time` - The latest time an agent may reach its goal
+/// * `robustness` - The number of ticks of delay the plan tolerates
+/// ## Returns
+/// The plan, or `None` if an agent has no abstract route or no refined path
+pub fn plan_hierarchical_robust(
+    grid: &BitPackedGrid,
+    abstraction: &mut Abstraction,
+    agents: &[(Cell, Cell)],
+    max_time: usize,
+    robustness: usize,
+) -> Option<HierarchicalPlan> {
+    let mut routes: Vec<TimedRoute> = Vec::new();
+    let mut abstract_conflicts = 0;
+    for agent in agents {
+        let mut penalties = HashMap::new();
+        let mut route = abstract_route(grid, abstraction, *agent, &penalties)?;
+        for _ in 0..agents.len() {
+            let contested = route.iter()
+                .filter(|(node, time)| *node != agent.0 && *node != agent.1 && routes.iter().flatten()
+                    .any(|(other, t)| other == node && t.abs_diff(*time) <= ABSTRACT_WINDOW + robustness))
+                .map(|(node, _)| *node)
+                .filter(|n| !penalties.contains_key(n))
+                .collect::<Vec<_>>();
+            if contested.is_empty() {
+                break;
+            }
+            abstract_conflicts += contested.len();
+            penalties.extend(contested.into_iter().map(|n| (n, ABSTRACT_PENALTY)));
+            route = abstract_route(grid, abstraction, *agent, &penalties)?;
+        }
+        routes.push(route);
+    }
+
+    // Agents that are yet to be planned hold their start cells at the start
+    let starts = agents.iter().map(|(start, _)| *start).collect();
+    let mut reservations = Reservations::new(robustness, starts);
+    let mut paths = Vec::new();
+    for (agent, route) in agents.iter().zip(&routes) {
+        reservations.starts.remove(&agent.0);
+        let corridor = route.iter().map(|(n, _)| abstraction.clusters.cluster_id(*n)).collect::<HashSet<_>>();
+        let path = refine(grid, abstraction, *agent, Some(&corridor), &reservations, max_time)
+            .or_else(|| refine(grid, abstraction, *agent, None, &reservations, max_time))?;
+        reservations.reserve(&path);
+        paths.push(path);
+    }
+    let routes = routes.into_iter().map(|r| r.into_iter().map(|(n, _)| n).collect()).collect();
+    Some(HierarchicalPlan { paths, routes, abstract_conflicts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapf::metrics::{conflicts, position, robust_conflicts};
+
+    #[test]
+    fn test_plan_hierarchical() {
+        let grid = BitPackedGrid::new_from_string(
+            "........\n........\n........\n........\n@@.@@.@@\n........\n........\n........\n".to_string()
+        );
+        let mut abstraction = Abstraction::new(&grid, 4);
+        let nodes = abstraction.graph.len();
+        let agents = [((0, 0), (7, 7)), ((7, 7), (0, 0)), ((1, 6), (6, 1)), ((6, 1), (1, 6))];
+        let plan = plan_hierarchical(&grid, &mut abstraction, &agents, 64).unwrap();
+        assert_eq!(abstraction.graph.len(), nodes);
+        assert_eq!(plan.paths.len(), 4);
+        assert!(conflicts(&plan.paths).is_empty());
+        for ((start, goal), path) in agents.iter().zip(&plan.paths) {
+            assert_eq!(position(path, 0), Some(start));
+            assert_eq!(path.last(), Some(goal));
+            assert!(path.windows(2).all(|p| manhattan_distance(p[0], p[1]) <= 1));
+            assert!(path.iter().all(|n| grid.get_bit_value(*n)));
+        }
+        assert!(plan.abstract_conflicts > 0);
+        assert!(plan.routes.iter().all(|r| r.len() >= 2));
+        assert!(plan_hierarchical(&grid, &mut abstraction, &agents, 3).is_none());
+    }
+
+    #[test]
+    fn test_plan_hierarchical_robust() {
+        let grid = BitPackedGrid::new_from_string("........\n........\n@@.@@.@@\n........\n".to_string());
+        let mut abstraction = Abstraction::new(&grid, 4);
+        let agents = [((0, 0), (7, 3)), ((1, 0), (6, 3)), ((7, 1), (0, 3))];
+        let plan = plan_hierarchical(&grid, &mut abstraction, &agents, 64).unwrap();
+        assert!(conflicts(&plan.paths).is_empty());
+        assert!(!robust_conflicts(&plan.paths, 2).is_empty());
+        let robust = plan_hierarchical_robust(&grid, &mut abstraction, &agents, 64, 2).unwrap();
+        assert!(robust_conflicts(&robust.paths, 2).is_empty());
+        for ((_, goal), path) in agents.iter().zip(&robust.paths) {
+            assert_eq!(path.last(), Some(goal));
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! # Increasing Cost Tree Search
+//! An optimal MAPF solver following Sharon et al., "The Increasing Cost Tree
+//! Search for Optimal Multi-Agent Pathfinding" (2013). The high level searches
+//! a tree of cost vectors, one cost per agent, breadth-first in increasing sum
+//! of costs, starting from every agent's shortest path cost. Each cost vector
+//! is checked by the low level, which builds a multi-valued decision diagram
+//! (MDD) of every path of the agent's cost and searches the joint MDD for a
+//! conflict-free combination. Pairs of agents are checked first, which prunes
+//! most infeasible cost vectors cheaply.
+//!
+//! Unlike conflict-based search, ICTS does not branch on conflicts, so it
+//! copes with dense instances with many conflicts as long as few extra moves
+//! are needed over the shortest paths. Agents move 4-connected or wait, and
+//! stay at their goal once their path ends.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+
+use super::mdd::{grid_distances, grid_mdd, Mdd};
+
+/// A solution of ICTS
+/// ## Fields
+/// * `paths` - The timed path of each agent, see the module documentation of `mapf`
+/// * `sum_of_costs` - The sum of the costs of the paths
+/// * `nodes` - The number of cost vectors checked by the high level
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IctsSolution {
+    pub paths: Vec<Vec<Cell>>,
+    pub sum_of_costs: usize,
+    pub nodes: usize,
+}
+
+/// The cells an agent can move to from a cell at a time, waiting at the goal
+/// once the paths of the MDD end
+fn children(mdd: &Mdd<Cell>, cell: Cell, time: usize) -> Vec<Cell> {
+    match time < mdd.cost() {
+        true => mdd.children(&cell, time).to_vec(),
+        false => vec![cell],
+    }
+}
+
+/// Searches the joint MDD of some agents for conflict-free paths
+struct JointSearch<'a> {
+    mdds: Vec<&'a Mdd<Cell>>,
+    horizon: usize,
+    dead: HashSet<(usize, Vec<Cell>)>,
+}
+
+impl JointSearch<'_> {
+    /// The conflict-free positions of every agent from a time to the horizon
+    fn search(&mut self, time: usize, positions: Vec<Cell>) -> Option<Vec<Vec<Cell>>> {
+        if time == self.horizon {
+            return Some(vec![positions]);
+        }
+        if self.dead.contains(&(time, positions.clone())) {
+            return None;
+        }
+        let options = self.mdds.iter().zip(&positions)
+            .map(|(mdd, cell)| children(mdd, *cell, time))
+            .collect::<Vec<_>>();
+        let mut next = Vec::with_capacity(positions.len());
+        if let Some(mut rest) = self.assign(time, &positions, &options, &mut next) {
+            rest.insert(0, positions);
+            return Some(rest);
+        }
+        self.dead.insert((time, positions));
+        None
+    }
+
+    /// Chooses the next cell of each agent in turn, avoiding vertex and swap
+    /// conflicts with the agents already assigned
+    fn assign(
+        &mut self,
+        time: usize,
+        positions: &[Cell],
+        options: &[Vec<Cell>],
+        next: &mut Vec<Cell>,
+    ) -> Option<Vec<Vec<Cell>>> {
+        let agent = next.len();
+        if agent == positions.len() {
+            return self.search(time + 1, next.clone());
+        }
+        for cell in &options[agent] {
+            let conflict = next.iter().enumerate()
+                .any(|(other, n)| n == cell || (*n == positions[agent] && positions[other] == *cell));
+            if conflict {
+                continue;
+            }
+            next.push(*cell);
+            let found = self.assign(time, positions, options, next);
+            next.pop();
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+}
+
+/// Finds conflict-free paths of the agents with the given MDDs
+fn joint_paths(mdds: Vec<&Mdd<Cell>>, starts: Vec<Cell>) -> Option<Vec<Vec<Cell>>> {
+    let horizon = mdds.iter().map(|m| m.cost()).max().unwrap_or(0);
+    let mut search = JointSearch { mdds, horizon, dead: HashSet::new() };
+    search.search(0, starts)
+}
+
+/// Solves a MAPF instance optimally with respect to sum of costs
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `agents` - The start and goal of each agent, starts and goals must be distinct
+/// * `max_nodes` - The number of cost vectors to check before giving up
+/// ## Returns
+/// The solution, or `None` if a goal is unreachable or no solution was found
+/// within the node limit
+pub fn icts(grid: &BitPackedGrid, agents: &[(Cell, Cell)], max_nodes: usize) -> Option<IctsSolution> {
+    let from_start = agents.iter().map(|(s, _)| grid_distances(grid, *s)).collect::<Vec<_>>();
+    let root = agents.iter().zip(&from_start)
+        .map(|((_, goal), d)| d.get(goal).copied())
+        .collect::<Option<Vec<_>>>()?;
+    let starts = agents.iter().map(|(s, _)| *s).collect::<Vec<_>>();
+    let mut mdds: HashMap<(usize, usize), Mdd<Cell>> = HashMap::new();
+    let mut queue = VecDeque::from([root.clone()]);
+    let mut visited = HashSet::from([root]);
+    let mut nodes = 0;
+    while let Some(costs) = queue.pop_front() {
+        if nodes == max_nodes {
+            return None;
+        }
+        nodes += 1;
+        for (agent, cost) in costs.iter().enumerate() {
+            mdds.entry((agent, *cost)).or_insert_with(|| grid_mdd(grid, agents[agent].0, agents[agent].1, *cost));
+        }
+        let mdd = |agent: usize| &mdds[&(agent, costs[agent])];
+        let pairs_feasible = (0..agents.len()).all(|a| {
+            (a + 1..agents.len()).all(|b| joint_paths(vec![mdd(a), mdd(b)], vec![starts[a], starts[b]]).is_some())
+        });
+        if pairs_feasible {
+            let all = (0..agents.len()).map(mdd).collect();
+            if let Some(joint) = joint_paths(all, starts.clone()) {
+                let paths = (0..agents.len())
+                    .map(|agent| joint.iter().take(costs[agent] + 1).map(|p| p[agent]).collect())
+                    .collect();
+                return Some(IctsSolution { paths, sum_of_costs: costs.iter().sum(), nodes });
+            }
+        }
+        for agent in 0..agents.len() {
+            let mut child = costs.clone();
+            child[agent] += 1;
+            if visited.insert(child.clone()) {
+                queue.push_back(child);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapf::metrics::{conflicts, sum_of_costs};
+
+    #[test]
+    fn test_icts() {
+        // Two agents swap ends of a corridor using the bay in its middle
+        let grid = BitPackedGrid::new_from_string(".....\n@@.@@\n".to_string());
+        let agents = [((0, 0), (4, 0)), ((4, 0), (0, 0))];
+        let solution = icts(&grid, &agents, 100).unwrap();
+        assert_eq!(solution.sum_of_costs, 11);
+        assert_eq!(sum_of_costs(&solution.paths), 11);
+        assert!(conflicts(&solution.paths).is_empty());
+        for (path, (start, goal)) in solution.paths.iter().zip(agents) {
+            assert_eq!((path[0], *path.last().unwrap()), (start, goal));
+        }
+
+        // Without the bay the agents cannot pass each other
+        let corridor = BitPackedGrid::new_from_string(".....\n@@@@@\n".to_string());
+        assert_eq!(icts(&corridor, &agents, 20), None);
+        let solo = icts(&corridor, &agents[..1], 1).unwrap();
+        assert_eq!((solo.sum_of_costs, solo.nodes), (4, 1));
+    }
+}
@@ -0,0 +1,162 @@
+//! # Kinematic Post-Processing
+//! Conversion of discrete multi-agent plans into timed trajectories that
+//! respect the velocity and acceleration limits of each agent, in the style of
+//! MAPF-POST (Hönig et al., 2016). Waits are removed from each path and the
+//! order in which agents visit every node is kept as precedence constraints,
+//! so an agent only enters a node once the agent before it has reached its
+//! next node. The earliest times satisfying both the kinematic limits and the
+//! constraints are then found by relaxation, which stretches the schedule of
+//! the plan without introducing collisions.
+
+use std::{collections::HashMap, hash::Hash};
+
+/// The motion limits of an agent moving from rest to rest between waypoints
+/// ## Fields
+/// * `max_velocity` - The highest speed of the agent
+/// * `max_acceleration` - The highest acceleration and deceleration of the agent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Kinematics {
+    pub max_velocity: f64,
+    pub max_acceleration: f64,
+}
+
+impl Kinematics {
+    /// The shortest time to move a distance starting and ending at rest, with
+    /// a trapezoidal velocity profile or a triangular one on short moves
+    pub fn move_time(&self, distance: f64) -> f64 {
+        let (v, a) = (self.max_velocity, self.max_acceleration);
+        if distance >= v * v / a {
+            distance / v + v / a
+        } else {
+            2.0 * (distance / a).sqrt()
+        }
+    }
+}
+
+/// A node of a trajectory with the time it is reached
+#[derive(Clone, Debug, PartialEq)]
+pub struct Waypoint<N> {
+    pub node: N,
+    pub time: f64,
+}
+
+/// The waypoints of a path without waits, with the discrete time each is reached
+fn compress<N: Eq + Clone>(path: &[N]) -> Vec<(N, usize)> {
+    let mut waypoints: Vec<(N, usize)> = Vec::new();
+    for (time, node) in path.iter().enumerate() {
+        if waypoints.last().is_none_or(|(last, _)| last != node) {
+            waypoints.push((node.clone(), time));
+        }
+    }
+    waypoints
+}
+
+/// Converts a conflict-free multi-agent plan into timed trajectories
+/// ## Arguments
+/// * `paths` - The timed path of each agent
+/// * `kinematics` - The motion limits of each agent
+/// * `distance` - The distance between two adjacent nodes
+/// * `margin` - The least time between an agent reaching its next waypoint and
+///   the following agent reaching the node it left
+/// ## Returns
+/// The waypoints of each agent, or `None` if the agents of the plan wait on
+/// each other in a cycle with a positive margin, such as agents rotating
+/// around a loop in one tick
+pub fn post_process<N, D>(
+    paths: &[Vec<N>],
+    kinematics: &[Kinematics],
+    distance: D,
+    margin: f64,
+) -> Option<Vec<Vec<Waypoint<N>>>>
+where
+    N: Hash + Eq + Clone,
+    D: Fn(&N, &N) -> f64,
+{
+    assert_eq!(paths.len(), kinematics.len(), "Every agent requires its kinematics");
+    let waypoints = paths.iter().map(|p| compress(p)).collect::<Vec<_>>();
+
+    // An agent may only reach a node once the previous visitor of the node
+    // has reached its next waypoint
+    let mut visits: HashMap<&N, Vec<(usize, usize, usize)>> = HashMap::new();
+    for (agent, path) in waypoints.iter().enumerate() {
+        for (index, (node, time)) in path.iter().enumerate() {
+            visits.entry(node).or_default().push((*time, agent, index));
+        }
+    }
+    let mut precedences = Vec::new();
+    for node_visits in visits.values_mut() {
+        node_visits.sort();
+        for pair in node_visits.windows(2) {
+            let ((_, before, index), (_, after, other)) = (pair[0], pair[1]);
+            if before != after && index + 1 < waypoints[before].len() {
+                precedences.push(((before, index + 1), (after, other)));
+            }
+        }
+    }
+
+    let durations = waypoints.iter().zip(kinematics)
+        .map(|(path, k)| path.windows(2).map(|w| k.move_time(distance(&w[0].0, &w[1].0))).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let mut times = waypoints.iter().map(|p| vec![0.0; p.len()]).collect::<Vec<_>>();
+    let events = times.iter().map(|t| t.len()).sum::<usize>();
+    for _ in 0..=events {
+        let mut changed = false;
+        for agent in 0..times.len() {
+            for index in 1..times[agent].len() {
+                let earliest = times[agent][index - 1] + durations[agent][index - 1];
+                if earliest > times[agent][index] {
+                    times[agent][index] = earliest;
+                    changed = true;
+                }
+            }
+        }
+        for ((before, index), (after, other)) in &precedences {
+            if times[*before][*index] + margin > times[*after][*other] {
+                times[*after][*other] = times[*before][*index] + margin;
+                changed = true;
+            }
+        }
+        if !changed {
+            let trajectories = waypoints.into_iter().zip(times)
+                .map(|(path, times)| path.into_iter().zip(times).map(|((node, _), time)| Waypoint { node, time }).collect())
+                .collect();
+            return Some(trajectories);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_time() {
+        let kinematics = Kinematics { max_velocity: 2.0, max_acceleration: 1.0 };
+        assert_eq!(kinematics.move_time(8.0), 6.0);
+        assert_eq!(kinematics.move_time(1.0), 2.0);
+    }
+
+    #[test]
+    fn test_post_process() {
+        let distance = |a: &i32, b: &i32| (a - b).abs() as f64;
+        let fast = Kinematics { max_velocity: 1.0, max_acceleration: 1.0 };
+        let slow = Kinematics { max_velocity: 0.5, max_acceleration: 1.0 };
+
+        // The first agent follows the second, which is slower
+        let paths = vec![vec![0, 0, 1, 2], vec![1, 2, 2, 3]];
+        let trajectories = post_process(&paths, &[fast, slow], distance, 0.0).unwrap();
+        assert_eq!(trajectories[0].iter().map(|w| w.node).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(trajectories[1].iter().map(|w| w.time).collect::<Vec<_>>(), vec![0.0, 2.5, 5.0]);
+        assert_eq!(trajectories[0][1].time, 2.5);
+        assert_eq!(trajectories[0][2].time, 5.0);
+
+        let alone = post_process(&paths[..1], &[fast], distance, 0.5).unwrap();
+        assert_eq!(alone[0][2].time, 4.0);
+        let delayed = post_process(&paths, &[fast, slow], distance, 0.5).unwrap();
+        assert_eq!(delayed[0][2].time, 5.5);
+        let rotation = [vec![0, 1], vec![1, 2], vec![2, 0]];
+        assert!(post_process(&rotation, &[fast; 3], distance, 0.0).is_some());
+        assert_eq!(post_process(&rotation, &[fast; 3], distance, 0.5), None);
+    }
+}
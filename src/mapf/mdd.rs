@@ -0,0 +1,170 @@
+//! # Multi-Valued Decision Diagrams
+//! The multi-valued decision diagram (MDD) of an agent for a cost holds every
+//! node the agent can occupy at each time on a path of exactly that cost, and
+//! the moves between them. MDDs are the low level of increasing cost tree
+//! search, and levels of width one mark nodes every such path passes, which
+//! classify conflicts as cardinal for conflict-based search heuristics and
+//! symmetry reasoning.
+//!
+//! An MDD is built forwards level by level from the start and then pruned
+//! backwards from the goal, so the expander is only needed in the forward
+//! direction. Waiting is a move like any other, so expanders should yield the
+//! node itself when agents may wait.
+
+use std::{collections::{HashMap, HashSet}, hash::Hash};
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+
+/// The nodes of every path of a given cost between two nodes, by time
+/// ## Fields
+/// * `levels` - The nodes on the paths at each time, with the moves from each
+///   node to the nodes at the next time
+#[derive(Clone, Debug)]
+pub struct Mdd<N> {
+    levels: Vec<HashMap<N, Vec<N>>>,
+}
+
+impl<N: Hash + Eq + Clone> Mdd<N> {
+    /// Builds the MDD of every path of a cost from a start to a goal
+    /// ## Arguments
+    /// * `expander` - A function that returns the nodes reachable in one move,
+    ///   including the node itself if agents may wait
+    /// * `start` - The start node
+    /// * `goal` - The goal node
+    /// * `cost` - The number of moves of every path
+    /// ## Returns
+    /// The MDD, which is empty if there is no path of the cost
+    pub fn new<E, I>(mut expander: E, start: N, goal: N, cost: usize) -> Self
+    where
+        E: FnMut(&N) -> I,
+        I: IntoIterator<Item = N>,
+    {
+        let mut levels = vec![HashMap::from([(start, Vec::new())])];
+        for _ in 0..cost {
+            let mut next = HashMap::new();
+            for (node, children) in levels.last_mut().unwrap() {
+                *children = expander(node).into_iter().collect();
+                for child in children.iter() {
+                    next.insert(child.clone(), Vec::new());
+                }
+            }
+            levels.push(next);
+        }
+        // Prune nodes from which the goal cannot be reached at the last level
+        levels.last_mut().unwrap().retain(|node, _| *node == goal);
+        for time in (0..cost).rev() {
+            let reachable: HashSet<N> = levels[time + 1].keys().cloned().collect();
+            levels[time].retain(|_, children| {
+                children.retain(|c| reachable.contains(c));
+                !children.is_empty()
+            });
+        }
+        if levels[0].is_empty() {
+            levels.iter_mut().for_each(HashMap::clear);
+        }
+        Mdd { levels }
+    }
+
+    /// The number of moves of the paths of the MDD
+    pub fn cost(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    /// Checks if there is no path of the cost
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    /// The nodes on the paths at a time, empty after the last time
+    pub fn level(&self, time: usize) -> impl Iterator<Item = &N> {
+        self.levels.get(time).into_iter().flat_map(|l| l.keys())
+    }
+
+    /// The number of nodes on the paths at a time
+    pub fn width(&self, time: usize) -> usize {
+        self.levels.get(time).map_or(0, HashMap::len)
+    }
+
+    /// Checks if a node is on a path at a time
+    pub fn contains(&self, node: &N, time: usize) -> bool {
+        self.levels.get(time).is_some_and(|l| l.contains_key(node))
+    }
+
+    /// The nodes at the next time reachable from a node on the paths
+    pub fn children(&self, node: &N, time: usize) -> &[N] {
+        self.levels.get(time).and_then(|l| l.get(node)).map_or(&[], Vec::as_slice)
+    }
+
+    /// The nodes every path passes, with the time they are passed
+    pub fn singletons(&self) -> Vec<(usize, N)> {
+        self.levels.iter().enumerate()
+            .filter(|(_, level)| level.len() == 1)
+            .filter_map(|(time, level)| level.keys().next().map(|n| (time, n.clone())))
+            .collect()
+    }
+
+    /// One of the paths of the MDD
+    pub fn path(&self) -> Option<Vec<N>> {
+        let mut node = self.level(0).next()?.clone();
+        let mut path = vec![node.clone()];
+        for time in 0..self.cost() {
+            node = self.children(&node, time)[0].clone();
+            path.push(node.clone());
+        }
+        Some(path)
+    }
+
+    /// The number of distinct paths of the MDD
+    pub fn count_paths(&self) -> usize {
+        let mut counts: HashMap<&N, usize> = self.level(self.cost()).map(|n| (n, 1)).collect();
+        for time in (0..self.cost()).rev() {
+            counts = self.levels[time].iter()
+                .map(|(node, children)| (node, children.iter().map(|c| counts[c]).sum()))
+                .collect();
+        }
+        counts.values().sum()
+    }
+}
+
+/// Builds the MDD of an agent moving 4-connected or waiting on a grid map
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `start` - The start cell
+/// * `goal` - The goal cell
+/// * `cost` - The number of moves, including waits, of every path
+pub fn grid_mdd(grid: &BitPackedGrid, start: Cell, goal: Cell, cost: usize) -> Mdd<Cell> {
+    Mdd::new(|n: &Cell| grid_moves(grid, *n).collect::<Vec<_>>(), start, goal, cost)
+}
+
+/// The cells reachable from a cell in one 4-connected move or wait
+pub fn grid_moves(grid: &BitPackedGrid, cell: Cell) -> impl Iterator<Item = Cell> + '_ {
+    std::iter::once(cell).chain(grid.adjacent(cell, false).filter(|n| grid.bounds_check(*n)))
+}
+
+/// Breadth-first distances from a cell to every cell reachable by 4-connected
+/// moves, the shortest path costs that bound the cost of an agent's MDD
+pub use crate::analysis::statistics::grid_distances;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_mdd() {
+        let grid = BitPackedGrid::new_from_string("...\n.@.\n...\n".to_string());
+        let mdd = grid_mdd(&grid, (0, 0), (2, 2), 4);
+        assert_eq!(mdd.cost(), 4);
+        assert_eq!((0..=4).map(|t| mdd.width(t)).collect::<Vec<_>>(), vec![1, 2, 2, 2, 1]);
+        assert_eq!(mdd.count_paths(), 2);
+        assert_eq!(mdd.singletons(), vec![(0, (0, 0)), (4, (2, 2))]);
+        assert!(mdd.contains(&(1, 0), 1) && !mdd.contains(&(1, 1), 2));
+        let path = mdd.path().unwrap();
+        assert_eq!((path.len(), path[0], path[4]), (5, (0, 0), (2, 2)));
+
+        // A longer cost allows waiting or stepping back
+        let slack = grid_mdd(&grid, (0, 0), (2, 2), 5);
+        assert!(slack.count_paths() > 2 && slack.contains(&(0, 0), 1));
+        assert!(grid_mdd(&grid, (0, 0), (2, 2), 3).is_empty());
+        assert_eq!(grid_mdd(&grid, (0, 0), (0, 0), 0).count_paths(), 1);
+    }
+}
@@ -0,0 +1,267 @@
+//! # MAPF Metrics
+//! Metrics used to evaluate multi-agent solutions. Each step of a path,
+//! including waits, has a cost of 1.
+//! * Makespan, the time at which the last agent finishes its path
+//! * Sum-of-costs, the total length of all timed paths
+//! * Flowtime, the total time until each agent arrives at its goal for the last time
+//! * Conflicts, vertex and edge (swap) conflicts remaining between agents
+//! * Robust conflicts, agents entering a node within k ticks of another agent
+
+use std::{collections::HashMap, hash::Hash};
+
+/// A conflict between two agents of a multi-agent solution
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Conflict<N> {
+    /// Two agents occupy the same node at the same time
+    Vertex { agents: (usize, usize), node: N, time: usize },
+    /// Two agents swap nodes between time `time` and `time + 1`
+    Edge { agents: (usize, usize), edge: (N, N), time: usize },
+}
+
+/// The metrics of a multi-agent solution
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Metrics {
+    pub makespan: usize,
+    pub sum_of_costs: usize,
+    pub flowtime: usize,
+    pub conflicts: usize,
+}
+
+impl Metrics {
+    /// Computes every metric of a multi-agent solution
+    pub fn new<N: Hash + Eq + Clone>(paths: &[Vec<N>]) -> Self {
+        Metrics {
+            makespan: makespan(paths),
+            sum_of_costs: sum_of_costs(paths),
+            flowtime: flowtime(paths),
+            conflicts: conflicts(paths).len(),
+        }
+    }
+}
+
+/// An objective minimised by a multi-agent solution
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Objective {
+    /// The time at which the last agent finishes its path
+    Makespan,
+    /// The total length of all timed paths
+    SumOfCosts,
+}
+
+impl Objective {
+    /// The value of the objective for the timed paths of a solution
+    pub fn evaluate<N>(&self, paths: &[Vec<N>]) -> usize {
+        match self {
+            Objective::Makespan => makespan(paths),
+            Objective::SumOfCosts => sum_of_costs(paths),
+        }
+    }
+}
+
+/// A multi-agent solution, the timed path of each agent
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Solution<N> {
+    pub paths: Vec<Vec<N>>,
+}
+
+impl<N: Hash + Eq + Clone> Solution<N> {
+    /// The position of an agent at a given time
+    pub fn position(&self, agent: usize, time: usize) -> Option<&N> {
+        self.paths.get(agent).and_then(|p| position(p, time))
+    }
+
+    /// The time at which the last agent finishes its path
+    pub fn makespan(&self) -> usize {
+        makespan(&self.paths)
+    }
+
+    /// The total length of all timed paths
+    pub fn sum_of_costs(&self) -> usize {
+        sum_of_costs(&self.paths)
+    }
+
+    /// The value of an objective for the solution
+    pub fn cost(&self, objective: Objective) -> usize {
+        objective.evaluate(&self.paths)
+    }
+
+    /// The vertex and edge conflicts remaining between agents
+    pub fn conflicts(&self) -> Vec<Conflict<N>> {
+        conflicts(&self.paths)
+    }
+
+    /// Every metric of the solution
+    pub fn metrics(&self) -> Metrics {
+        Metrics::new(&self.paths)
+    }
+}
+
+/// The position of an agent at a given time, agents wait at the end of their path
+pub fn position<N>(path: &[N], time: usize) -> Option<&N> {
+    path.get(time).or(path.last())
+}
+
+/// The time at which the last agent finishes its path
+pub fn makespan<N>(paths: &[Vec<N>]) -> usize {
+    paths.iter().map(|p| p.len().saturating_sub(1)).max().unwrap_or(0)
+}
+
+/// The sum of the lengths of all timed paths, including waits
+pub fn sum_of_costs<N>(paths: &[Vec<N>]) -> usize {
+    paths.iter().map(|p| p.len().saturating_sub(1)).sum()
+}
+
+/// The sum over agents of the time they arrive at their goal and stay there,
+/// this ignores any waits at the end of a path
+pub fn flowtime<N: Eq>(paths: &[Vec<N>]) -> usize {
+    paths.iter().map(|p| arrival_time(p)).sum()
+}
+
+/// The time at which an agent arrives at its final position for the last time
+pub fn arrival_time<N: Eq>(path: &[N]) -> usize {
+    match path.last() {
+        Some(goal) => path.iter().rposition(|n| n != goal).map_or(0, |t| t + 1),
+        None => 0,
+    }
+}
+
+/// Finds all vertex and edge conflicts of a multi-agent solution
+/// ## Arguments
+/// * `paths` - The timed path of each agent
+/// ## Returns
+/// A vector of conflicts ordered by time
+/// ## Complexity
+/// O(t * a) where t is the makespan and a is the number of agents
+pub fn conflicts<N: Hash + Eq + Clone>(paths: &[Vec<N>]) -> Vec<Conflict<N>> {
+    let mut conflicts = Vec::new();
+    for time in 0..=makespan(paths) {
+        let mut occupied: HashMap<&N, usize> = HashMap::new();
+        let mut moves: HashMap<(&N, &N), usize> = HashMap::new();
+        for (agent, path) in paths.iter().enumerate() {
+            let Some(node) = position(path, time) else { continue };
+            if let Some(other) = occupied.insert(node, agent) {
+                conflicts.push(Conflict::Vertex { agents: (other, agent), node: node.clone(), time });
+            }
+            let next = position(path, time + 1).unwrap();
+            if next != node {
+                if let Some(other) = moves.get(&(next, node)) {
+                    conflicts.push(Conflict::Edge {
+                        agents: (*other, agent),
+                        edge: (next.clone(), node.clone()),
+                        time,
+                    });
+                }
+                moves.insert((node, next), agent);
+            }
+        }
+    }
+    conflicts
+}
+
+/// Finds every k-robust conflict of a multi-agent solution, where an agent
+/// occupies a node within `k` ticks after another agent occupied it. A plan
+/// without k-robust conflicts stays free of conflicts when agents are delayed
+/// by up to `k` ticks. With `k` of 0 these are the vertex conflicts.
+/// ## Returns
+/// A vertex conflict for each agent and time, against the agent that occupied
+/// the node most recently, ordered by time
+pub fn robust_conflicts<N: Hash + Eq + Clone>(paths: &[Vec<N>], k: usize) -> Vec<Conflict<N>> {
+    let mut conflicts = Vec::new();
+    let mut visits: HashMap<&N, Vec<(usize, usize)>> = HashMap::new();
+    for time in 0..=makespan(paths) {
+        for (agent, path) in paths.iter().enumerate() {
+            let Some(node) = position(path, time) else { continue };
+            let node_visits = visits.entry(node).or_default();
+            let other = node_visits.iter().rev()
+                .take_while(|(t, _)| t + k >= time)
+                .find(|(_, a)| *a != agent);
+            if let Some((_, other)) = other {
+                conflicts.push(Conflict::Vertex { agents: (*other, agent), node: node.clone(), time });
+            }
+            node_visits.push((time, agent));
+        }
+    }
+    conflicts
+}
+
+/// The robustness needed for a plan to stay conflict-free with a given risk
+/// when each agent is delayed at every tick with a given probability. This is
+/// the smallest `k` for which an agent is delayed more than `k` times over
+/// the horizon with at most the given risk.
+pub fn robustness_for_delays(delay_probability: f64, horizon: usize, risk: f64) -> usize {
+    let p = delay_probability.clamp(0.0, 1.0);
+    if p == 0.0 {
+        return 0;
+    }
+    if p == 1.0 {
+        return horizon;
+    }
+    // Accumulates the binomial distribution of the number of delays
+    let mut probability = (1.0 - p).powi(horizon as i32);
+    let mut cumulative = probability;
+    for k in 0..horizon {
+        if 1.0 - cumulative <= risk {
+            return k;
+        }
+        probability *= (horizon - k) as f64 / (k + 1) as f64 * p / (1.0 - p);
+        cumulative += probability;
+    }
+    horizon
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics() {
+        let paths = vec![
+            vec![(0, 0), (1, 0), (2, 0), (2, 0)],
+            vec![(0, 1), (0, 1), (1, 1)],
+        ];
+        assert_eq!(Metrics::new(&paths), Metrics {
+            makespan: 3,
+            sum_of_costs: 5,
+            flowtime: 4,
+            conflicts: 0,
+        });
+    }
+
+    #[test]
+    fn test_vertex_conflict() {
+        let paths = vec![vec![0, 1, 2], vec![2, 1, 0], vec![5]];
+        assert_eq!(conflicts(&paths), vec![Conflict::Vertex { agents: (0, 1), node: 1, time: 1 }]);
+    }
+
+    #[test]
+    fn test_edge_conflict() {
+        let paths = vec![vec![0, 1, 2], vec![3, 2, 1]];
+        assert_eq!(conflicts(&paths), vec![Conflict::Edge { agents: (0, 1), edge: (1, 2), time: 1 }]);
+    }
+
+    #[test]
+    fn test_conflict_after_arrival() {
+        let paths = vec![vec![0], vec![2, 1, 0]];
+        assert_eq!(conflicts(&paths), vec![Conflict::Vertex { agents: (0, 1), node: 0, time: 2 }]);
+    }
+
+    #[test]
+    fn test_robust_conflicts() {
+        let paths = vec![vec![0, 1, 2, 3], vec![5, 5, 1, 4]];
+        assert!(conflicts(&paths).is_empty());
+        assert_eq!(robust_conflicts(&paths, 0), vec![]);
+        assert_eq!(robust_conflicts(&paths, 1), vec![Conflict::Vertex { agents: (0, 1), node: 1, time: 2 }]);
+        assert_eq!(robust_conflicts(&[vec![0, 1], vec![1, 0]], 0).len(), 0);
+        assert_eq!(robust_conflicts(&[vec![0, 1], vec![1, 0]], 1).len(), 2);
+    }
+
+    #[test]
+    fn test_robustness_for_delays() {
+        assert_eq!(robustness_for_delays(0.0, 10, 0.01), 0);
+        assert_eq!(robustness_for_delays(1.0, 10, 0.01), 10);
+        // Over 2 ticks at most one delay happens with probability 0.75
+        assert_eq!(robustness_for_delays(0.5, 2, 0.25), 1);
+        assert_eq!(robustness_for_delays(0.5, 2, 0.2), 2);
+        assert!(robustness_for_delays(0.05, 100, 0.05) > robustness_for_delays(0.05, 100, 0.5));
+    }
+}
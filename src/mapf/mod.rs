@@ -0,0 +1,31 @@
+//! # Multi-Agent Pathfinding
+//! Utilities for multi-agent pathfinding (MAPF) solutions. A solution is a
+//! timed path per agent, where the position of an agent at time `t` is
+//! `path[t]` and agents wait at their final position once their path ends.
+//! These utilities include:
+//! * Metrics, makespan, sum-of-costs, flowtime and conflict detection
+//! * Crowd, agents following paths or flow fields with cell reservation
+//! * Monitor, detection of deadlocked and livelocked agents during execution
+//! * Kinematics, conversion of plans into timed trajectories under motion limits
+//! * Hierarchical, prioritised planning routed over a cluster abstraction
+//! * MDD, multi-valued decision diagrams of the paths of an agent of a given cost
+//! * ICTS, optimal planning by increasing cost tree search
+//! * Prioritized, planning agents one at a time around the reservations of earlier agents
+//! * CBS, optimal planning by conflict-based search with bypassing, prioritised
+//!   conflicts and the CG heuristic
+
+#![allow(dead_code)]
+pub mod metrics;
+pub mod crowd;
+pub mod monitor;
+pub mod kinematics;
+#[cfg(feature = "search-core")]
+pub mod hierarchical;
+#[cfg(feature = "search-core")]
+pub mod mdd;
+#[cfg(feature = "search-core")]
+pub mod icts;
+#[cfg(feature = "search-core")]
+pub mod prioritized;
+#[cfg(feature = "search-core")]
+pub mod cbs;
@@ -0,0 +1,192 @@
+//! # Execution Monitoring
+//! Detection of agents that stop making progress while a crowd executes its
+//! plans. Progress is measured by the remaining distance of each agent to its
+//! goal, and an agent that has not reached its goal stalls when that distance
+//! has not improved over a window of ticks. Stalled agents that stayed in place
+//! for the whole window are deadlocked, while those that kept moving without
+//! getting closer are livelocked. Stalls can trigger replanning of only the
+//! agents involved.
+
+use std::hash::Hash;
+
+use super::crowd::Crowd;
+
+/// How a group of agents stopped making progress
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StallKind {
+    /// The agents have not moved over the window
+    Deadlock,
+    /// The agents moved without getting closer to their goals
+    Livelock,
+}
+
+/// A group of agents that stopped making progress
+/// ## Fields
+/// * `kind` - Whether the agents are deadlocked or livelocked
+/// * `time` - The tick the stall was detected at
+/// * `agents` - The stalled agents
+/// * `cells` - The current node of each stalled agent
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stall<N> {
+    pub kind: StallKind,
+    pub time: usize,
+    pub agents: Vec<usize>,
+    pub cells: Vec<N>,
+}
+
+/// A monitor of the progress of every agent of a crowd
+/// ## Fields
+/// * `window` - The number of ticks without progress after which an agent stalls
+/// * `best` - The smallest remaining distance of each agent so far
+/// * `improved` - The tick each agent last got closer to its goal
+pub struct ExecutionMonitor {
+    pub window: usize,
+    pub best: Vec<usize>,
+    pub improved: Vec<usize>,
+}
+
+impl ExecutionMonitor {
+    /// Creates a monitor for a number of agents
+    pub fn new(agents: usize, window: usize) -> Self {
+        assert!(window > 0, "The window must span at least one tick");
+        ExecutionMonitor { window, best: vec![usize::MAX; agents], improved: vec![0; agents] }
+    }
+
+    /// Forgets the progress of an agent, such as after it was given a new plan
+    pub fn reset(&mut self, agent: usize, time: usize) {
+        self.best[agent] = usize::MAX;
+        self.improved[agent] = time;
+    }
+
+    /// Records the progress of the agents at the current tick of the crowd
+    /// ## Arguments
+    /// * `crowd` - The crowd being executed
+    /// * `remaining` - The remaining distance of an agent at a node to its goal
+    /// ## Returns
+    /// The deadlocked and livelocked groups of agents, if any
+    pub fn observe<N: Hash + Eq + Clone>(
+        &mut self,
+        crowd: &Crowd<N>,
+        remaining: impl Fn(usize, &N) -> usize,
+    ) -> Vec<Stall<N>> {
+        let time = crowd.time();
+        let mut deadlocked = Vec::new();
+        let mut livelocked = Vec::new();
+        for (agent, node) in crowd.positions.iter().enumerate() {
+            let distance = remaining(agent, node);
+            if distance < self.best[agent] {
+                self.best[agent] = distance;
+                self.improved[agent] = time;
+            }
+            if distance == 0 || time - self.improved[agent] < self.window {
+                continue;
+            }
+            let trajectory = &crowd.trajectories[agent];
+            let recent = &trajectory[trajectory.len().saturating_sub(self.window + 1)..];
+            if recent.iter().all(|n| n == node) {
+                deadlocked.push(agent);
+            } else {
+                livelocked.push(agent);
+            }
+        }
+        [(StallKind::Deadlock, deadlocked), (StallKind::Livelock, livelocked)]
+            .into_iter()
+            .filter(|(_, agents)| !agents.is_empty())
+            .map(|(kind, agents)| Stall {
+                kind,
+                time,
+                cells: agents.iter().map(|a| crowd.positions[*a].clone()).collect(),
+                agents,
+            })
+            .collect()
+    }
+}
+
+/// Simulates agents following their own paths while monitoring their
+/// progress, until every agent has finished or `max_ticks` have passed
+/// ## Arguments
+/// * `paths` - The path of each agent
+/// * `max_ticks` - The number of ticks after which the simulation stops
+/// * `window` - The number of ticks without progress after which an agent stalls
+/// * `remaining` - The remaining distance of an agent at a node to its goal
+/// * `replan` - Gives a stalled agent a new path from its current node, or
+///   `None` to keep its path
+/// ## Returns
+/// The simulated crowd and every stall detected
+pub fn follow_paths_monitored<N, R, P>(
+    paths: &[Vec<N>],
+    max_ticks: usize,
+    window: usize,
+    remaining: R,
+    mut replan: P,
+) -> (Crowd<N>, Vec<Stall<N>>)
+where
+    N: Hash + Eq + Clone,
+    R: Fn(usize, &N) -> usize,
+    P: FnMut(usize, &Stall<N>, &Crowd<N>) -> Option<Vec<N>>,
+{
+    let mut paths = paths.to_vec();
+    let mut crowd = Crowd::new(paths.iter().map(|p| p[0].clone()).collect());
+    let mut monitor = ExecutionMonitor::new(paths.len(), window);
+    let mut progress = vec![0; paths.len()];
+    let mut stalls = Vec::new();
+    monitor.observe(&crowd, &remaining);
+    while crowd.time() < max_ticks && progress.iter().zip(&paths).any(|(i, p)| i + 1 < p.len()) {
+        let moved = crowd.step(|agent, _| paths[agent].get(progress[agent] + 1).cloned());
+        progress.iter_mut().zip(moved).filter(|(_, m)| *m).for_each(|(i, _)| *i += 1);
+        for stall in monitor.observe(&crowd, &remaining) {
+            for agent in stall.agents.iter().copied() {
+                if let Some(path) = replan(agent, &stall, &crowd) {
+                    paths[agent] = path;
+                    progress[agent] = 0;
+                    monitor.reset(agent, crowd.time());
+                }
+            }
+            stalls.push(stall);
+        }
+    }
+    (crowd, stalls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapf::metrics::conflicts;
+
+    #[test]
+    fn test_monitor_deadlock_replanning() {
+        // A corridor 0-1-2-3 with a siding 4 off node 2
+        let distance = |from: usize, to: usize| match (from, to) {
+            (4, t) => 1 + 2usize.abs_diff(t),
+            (f, t) => f.abs_diff(t),
+        };
+        let goals = [3, 0];
+        let remaining = |agent: usize, node: &usize| distance(*node, goals[agent]);
+        let paths = vec![vec![0, 1, 2, 3], vec![3, 2, 1, 0]];
+
+        let (crowd, stalls) = follow_paths_monitored(&paths, 8, 2, remaining, |_, _, _| None);
+        assert_eq!(crowd.positions, vec![1, 2]);
+        assert_eq!(stalls[0], Stall { kind: StallKind::Deadlock, time: 3, agents: vec![0, 1], cells: vec![1, 2] });
+
+        let (crowd, stalls) = follow_paths_monitored(&paths, 20, 2, remaining, |agent, _, crowd| {
+            (agent == 1).then(|| vec![crowd.positions[1], 4, 2, 1, 0])
+        });
+        assert_eq!(stalls.len(), 1);
+        assert_eq!(crowd.positions, vec![3, 0]);
+        assert!(conflicts(&crowd.trajectories).is_empty());
+    }
+
+    #[test]
+    fn test_monitor_livelock() {
+        let mut crowd = Crowd::new(vec![0]);
+        let mut monitor = ExecutionMonitor::new(1, 3);
+        for tick in 0..5 {
+            crowd.step(|_, node| Some(1 - node));
+            let stalls = monitor.observe(&crowd, |_, _| 5);
+            assert_eq!(stalls.is_empty(), tick < 3, "tick {}", tick);
+            if !stalls.is_empty() {
+                assert_eq!(stalls[0].kind, StallKind::Livelock);
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! # Prioritised Planning
+//! A baseline MAPF solver following Silver, "Cooperative Pathfinding" (2005).
+//! Agents are planned one at a time in priority order by a space-time A-Star
+//! search that avoids the cells and moves reserved by the agents planned
+//! before them, and each agent then stays parked at its goal. Planning is
+//! fast but neither complete nor optimal, as an agent planned early may block
+//! the only route of a later one, so solutions are compared against CBS.
+//! Agents move 4-connected or wait.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+use crate::heuristics::distance::manhattan_distance;
+use crate::search::astar::astar;
+use crate::search::temporal::{space_time_astar, ReservationTable};
+
+use super::metrics::Solution;
+
+/// The cells and moves reserved by the agents planned so far
+#[derive(Default)]
+pub(crate) struct Reservations {
+    robustness: usize,
+    pub(crate) starts: HashSet<Cell>,
+    vertices: HashSet<(Cell, usize)>,
+    edges: HashSet<(Cell, Cell, usize)>,
+    last: HashMap<Cell, usize>,
+    parked: HashMap<Cell, usize>,
+}
+
+impl Reservations {
+    /// Reservations for k-robust plans, where agents yet to be planned hold their start cells
+    pub(crate) fn new(robustness: usize, starts: HashSet<Cell>) -> Self {
+        Reservations { robustness, starts, ..Default::default() }
+    }
+
+    /// Reserves the cells of a timed path, parking the agent at its last cell
+    pub(crate) fn reserve(&mut self, path: &[Cell]) {
+        for (time, node) in path.iter().enumerate() {
+            self.vertices.insert((*node, time));
+            let last = self.last.entry(*node).or_default();
+            *last = (*last).max(time);
+        }
+        for (time, pair) in path.windows(2).enumerate() {
+            self.edges.insert((pair[0], pair[1], time));
+        }
+        if let Some(goal) = path.last() {
+            self.parked.insert(*goal, path.len() - 1);
+        }
+    }
+
+    /// Checks if a move from a cell at a time to another cell is free of conflicts
+    pub(crate) fn is_free(&self, from: Cell, to: Cell, time: usize) -> bool {
+        let k = self.robustness;
+        (time >= k || !self.starts.contains(&to))
+            && !((time + 1).saturating_sub(k)..=time + 1 + k).any(|t| self.vertices.contains(&(to, t)))
+            && !self.edges.contains(&(to, from, time))
+            && self.parked.get(&to).is_none_or(|t| *t > time + 1 + k)
+    }
+
+    /// Checks if an agent can stay at its goal from a time onwards
+    pub(crate) fn can_park(&self, goal: Cell, time: usize) -> bool {
+        self.last.get(&goal).is_none_or(|t| *t + self.robustness < time)
+    }
+}
+/// Plans the shortest timed path of an agent avoiding the reservations
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `(start, goal)` - The start and goal of the agent
+/// * `allowed` - Whether the agent may enter a cell
+/// * `reservations` - The cells and moves of the agents planned before
+/// * `max_time` - The latest time the agent may reach its goal
+pub(crate) fn space_time_plan(
+    grid: &BitPackedGrid,
+    (start, goal): (Cell, Cell),
+    allowed: impl Fn(Cell) -> bool,
+    reservations: &Reservations,
+    max_time: usize,
+) -> Option<Vec<Cell>> {
+    let (path, _) = astar(
+        |&(node, time): &(Cell, usize)| {
+            if time >= max_time {
+                return Vec::new();
+            }
+            std::iter::once(node)
+                .chain(grid.adjacent(node, false))
+                .filter(|n| grid.bounds_check(*n) && allowed(*n) && reservations.is_free(node, *n, time))
+                .map(|n| ((n, time + 1), 1))
+                .collect::<Vec<_>>()
+        },
+        (start, 0),
+        |&(node, time)| node == goal && reservations.can_park(goal, time),
+        |&(node, _)| manhattan_distance(node, goal),
+    )?;
+    Some(path.into_iter().map(|(n, _)| n).collect())
+}
+
+/// Plans conflict-free paths for many agents in priority order
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `agents` - The (start, goal) of each agent in priority order, starts and goals must be distinct
+/// * `max_time` - The latest time an agent may reach its goal
+/// ## Returns
+/// The solution, or `None` if an agent has no path avoiding the agents before it
+pub fn prioritized_plan(grid: &BitPackedGrid, agents: &[(Cell, Cell)], max_time: usize) -> Option<Solution<Cell>> {
+    let mut reservations = Reservations::default();
+    let mut paths = Vec::with_capacity(agents.len());
+    for agent in agents {
+        let path = space_time_plan(grid, *agent, |_| true, &reservations, max_time)?;
+        reservations.reserve(&path);
+        paths.push(path);
+    }
+    Some(Solution { paths })
+}
+
+/// Plans conflict-free paths for many agents in priority order around the
+/// reservations of a table, such as dynamic obstacles of a simulation or
+/// agents planned elsewhere, reserving the path of each agent in the table
+/// ## Arguments
+/// * `reservations` - The table of occupied cells and moves, extended with the paths planned
+/// ## Returns
+/// The solution, or `None` if an agent has no path avoiding the reservations
+pub fn prioritized_plan_with(
+    grid: &BitPackedGrid,
+    agents: &[(Cell, Cell)],
+    reservations: &mut ReservationTable<Cell>,
+    max_time: usize,
+) -> Option<Solution<Cell>> {
+    let mut paths = Vec::with_capacity(agents.len());
+    for &(start, goal) in agents {
+        let (path, _) = space_time_astar(
+            |n| grid.adjacent(*n, false).filter(|n| grid.bounds_check(*n)).collect::<Vec<_>>(),
+            start,
+            0,
+            goal,
+            |n| manhattan_distance(*n, goal),
+            reservations,
+            max_time,
+        )?;
+        let path = path.into_iter().map(|(n, _)| n).collect::<Vec<_>>();
+        reservations.reserve_path(&path, 0);
+        paths.push(path);
+    }
+    Some(Solution { paths })
+}
+
+/// Plans conflict-free paths trying several priority orders, starting with
+/// the given order followed by the agents with the longest distances first,
+/// keeping the solution with the lowest sum of costs
+/// ## Arguments
+/// * `orders` - The number of rotations of the longest first order tried after it
+pub fn prioritized_plan_orders(grid: &BitPackedGrid, agents: &[(Cell, Cell)], max_time: usize, orders: usize) -> Option<Solution<Cell>> {
+    let mut order = (0..agents.len()).collect::<Vec<_>>();
+    let mut candidates = vec![order.clone()];
+    order.sort_by_key(|a| std::cmp::Reverse(manhattan_distance(agents[*a].0, agents[*a].1)));
+    candidates.push(order.clone());
+    // Rotations of the longest first order give further orders without randomness
+    candidates.extend((1..=orders.min(agents.len().saturating_sub(1))).map(|i| {
+        let mut rotated = order.clone();
+        rotated.rotate_left(i);
+        rotated
+    }));
+    candidates.into_iter()
+        .filter_map(|order| {
+            let ordered = order.iter().map(|a| agents[*a]).collect::<Vec<_>>();
+            let solution = prioritized_plan(grid, &ordered, max_time)?;
+            let mut paths = vec![Vec::new(); agents.len()];
+            for (agent, path) in order.into_iter().zip(solution.paths) {
+                paths[agent] = path;
+            }
+            Some(Solution { paths })
+        })
+        .min_by_key(Solution::sum_of_costs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapf::cbs::{cbs, CbsConfig};
+    use crate::mapf::metrics::Objective;
+
+    #[test]
+    fn test_prioritized_plan() {
+        let grid = BitPackedGrid::new_from_string(".....\n.@.@.\n.....\n".to_string());
+        let agents = [((0, 0), (4, 2)), ((4, 0), (0, 2)), ((2, 0), (2, 2))];
+        let solution = prioritized_plan(&grid, &agents, 32).unwrap();
+        assert!(solution.conflicts().is_empty());
+        for (agent, (start, goal)) in agents.iter().enumerate() {
+            assert_eq!(solution.position(agent, 0), Some(start));
+            assert_eq!(solution.position(agent, 100), Some(goal));
+        }
+        let optimal = cbs(&grid, &agents, &CbsConfig::default()).unwrap();
+        assert!(solution.sum_of_costs() >= optimal.sum_of_costs);
+        assert_eq!(solution.cost(Objective::Makespan), solution.makespan());
+        assert_eq!(solution.metrics().sum_of_costs, solution.cost(Objective::SumOfCosts));
+        let best = prioritized_plan_orders(&grid, &agents, 32, 2).unwrap();
+        assert!(best.conflicts().is_empty() && best.sum_of_costs() <= solution.sum_of_costs());
+        let mut table = ReservationTable::new();
+        let planned = prioritized_plan_with(&grid, &agents, &mut table, 32).unwrap();
+        assert!(planned.conflicts().is_empty());
+        assert_eq!(planned.sum_of_costs(), solution.sum_of_costs());
+        assert!(agents.iter().all(|(_, goal)| table.parked.contains_key(goal)));
+    }
+
+    #[test]
+    fn test_prioritized_plan_incomplete() {
+        // The first agent parks in the corridor the second must pass
+        let grid = BitPackedGrid::new_from_string("@.@\n...\n".to_string());
+        let agents = [((0, 1), (1, 1)), ((2, 1), (1, 0))];
+        assert!(prioritized_plan(&grid, &agents, 16).is_none());
+        let solution = prioritized_plan_orders(&grid, &agents, 16, 0).unwrap();
+        assert!(solution.conflicts().is_empty());
+        assert_eq!(solution.paths[1].last(), Some(&(1, 0)));
+    }
+}
@@ -0,0 +1,70 @@
+//! # Path Corridors
+//! The corridor around a path is every free cell within a number of moves of
+//! the path through free space. Distances are measured inside the free space
+//! rather than across obstacles, so the corridor never leaks through a thin
+//! wall onto the other side. The corridor is a grid mask of the same size as
+//! the map, which can be searched directly to replan within the corridor or be
+//! plotted to visualise it.
+
+use std::collections::VecDeque;
+
+use crate::domains::bitpackedgrid::BitPackedGrid;
+
+/// The free cells within `distance` 4-connected moves of a path
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `path` - The path, cells that are obstacles are ignored
+/// * `distance` - The number of moves the corridor extends from the path
+/// ## Returns
+/// A grid of the same size as the map where the cells of the corridor are free
+pub fn corridor(grid: &BitPackedGrid, path: &[(usize, usize)], distance: usize) -> BitPackedGrid {
+    let mut mask = BitPackedGrid::new(grid.original_width, grid.original_height);
+    let mut queue = VecDeque::new();
+    for node in path.iter().filter(|n| grid.bounds_check(**n) && grid.get_bit_value(**n)) {
+        if !mask.get_bit_value(*node) {
+            mask.set_bit_value(*node, true);
+            queue.push_back((*node, 0));
+        }
+    }
+    while let Some((node, steps)) = queue.pop_front() {
+        if steps == distance {
+            continue;
+        }
+        for child in grid.adjacent(node, false) {
+            if grid.bounds_check(child) && !mask.get_bit_value(child) {
+                mask.set_bit_value(child, true);
+                queue.push_back((child, steps + 1));
+            }
+        }
+    }
+    mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::distance::manhattan_distance;
+    use crate::search::astar::astar;
+
+    #[test]
+    fn test_corridor() {
+        let grid = BitPackedGrid::new_from_string(".....\n@@@@.\n.....\n.....\n".to_string());
+        let path = vec![(0, 0), (1, 0), (2, 0)];
+        assert_eq!(corridor(&grid, &path, 0).print_cells(None), "...@@\n@@@@@\n@@@@@\n@@@@@\n");
+        assert_eq!(corridor(&grid, &path, 2).print_cells(None), ".....\n@@@@@\n@@@@@\n@@@@@\n");
+        let mask = corridor(&grid, &path, 3);
+        assert_eq!(mask.print_cells(None), ".....\n@@@@.\n@@@@@\n@@@@@\n");
+
+        let path = [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (4, 1), (4, 2)];
+        let mask = corridor(&grid, &path, 1);
+        assert_eq!(mask.print_cells(None), ".....\n@@@@.\n@@@..\n@@@@.\n");
+        let (replanned, cost) = astar(
+            |n| mask.adjacent1(*n).filter(|(n, _)| mask.bounds_check(*n)),
+            (0, 0),
+            |n| *n == (3, 2),
+            |n| manhattan_distance(*n, (3, 2)),
+        ).unwrap();
+        assert_eq!(cost, 7);
+        assert!(replanned.iter().all(|n| mask.get_bit_value(*n)));
+    }
+}
@@ -0,0 +1,241 @@
+//! # Coverage Path Planning
+//! Paths that visit every free cell of a region, such as for cleaning robots
+//! or surveys. Regions are masks, grids of the same size as the map whose free
+//! cells are inside the region, so the map itself covers all of its free space.
+//! * Boustrophedon, the region is split into cells by sweeping a line across
+//!   its columns, where a cell ends when an obstacle splits or merges the free
+//!   space. Each cell is covered back and forth column by column, and cells are
+//!   visited depth first with shortest paths between them
+//! * Spanning tree coverage, following Gabriely and Rimon (2001), free 2x2
+//!   blocks are joined by a spanning tree which the path circles around. Every
+//!   cell is visited exactly once and the path returns next to its start, but
+//!   cells outside fully free blocks are not covered
+
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+
+/// A free vertical run of a column as (x, first y, last y)
+type Segment = (usize, usize, usize);
+
+/// Checks if a cell is free and within the region
+fn inside(grid: &BitPackedGrid, region: &BitPackedGrid, cell: Cell) -> bool {
+    grid.bounds_check(cell) && grid.get_bit_value(cell) && region.get_bit_value(cell)
+}
+
+/// Splits the free cells of a region into boustrophedon cells
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `region` - A grid of the same size whose free cells are the region
+/// ## Returns
+/// The column segments of each cell, ordered by column
+pub fn boustrophedon_decomposition(grid: &BitPackedGrid, region: &BitPackedGrid) -> Vec<Vec<Segment>> {
+    let mut cells: Vec<Vec<Segment>> = Vec::new();
+    let mut previous: Vec<(Segment, usize)> = Vec::new();
+    for x in 0..grid.original_width {
+        let mut segments = Vec::new();
+        let mut y = 0;
+        while y < grid.original_height {
+            if inside(grid, region, (x, y)) {
+                let top = y;
+                while y + 1 < grid.original_height && inside(grid, region, (x, y + 1)) {
+                    y += 1;
+                }
+                segments.push((x, top, y));
+            }
+            y += 1;
+        }
+        let overlaps = |a: &Segment, b: &Segment| a.1 <= b.2 && b.1 <= a.2;
+        let mut current = Vec::new();
+        for segment in &segments {
+            let before = previous.iter().filter(|(p, _)| overlaps(p, segment)).collect::<Vec<_>>();
+            // A cell continues only when neither a split nor a merge happens
+            let id = match before.as_slice() {
+                [(p, id)] if segments.iter().filter(|s| overlaps(p, s)).count() == 1 => *id,
+                _ => {
+                    cells.push(Vec::new());
+                    cells.len() - 1
+                }
+            };
+            cells[id].push(*segment);
+            current.push((*segment, id));
+        }
+        previous = current;
+    }
+    cells
+}
+
+/// Appends the shortest path within the region from the end of a path to a cell
+fn route_to(grid: &BitPackedGrid, region: &BitPackedGrid, path: &mut Vec<Cell>, goal: Cell) -> Option<()> {
+    let start = *path.last()?;
+    let mut parents = HashMap::from([(start, start)]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        if node == goal {
+            break;
+        }
+        for child in grid.adjacent(node, false).filter(|n| inside(grid, region, *n)) {
+            if let Entry::Vacant(entry) = parents.entry(child) {
+                entry.insert(node);
+                queue.push_back(child);
+            }
+        }
+    }
+    parents.get(&goal)?;
+    let mut route = vec![goal];
+    while *route.last().unwrap() != start {
+        route.push(parents[route.last().unwrap()]);
+    }
+    path.extend(route.into_iter().rev().skip(1));
+    Some(())
+}
+
+/// Plans a boustrophedon coverage path of the region reachable from a start
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `region` - A grid of the same size whose free cells are the region
+/// * `start` - The start cell, which must be in the region
+/// ## Returns
+/// A 4-connected path from the start visiting every cell of the region
+/// reachable from it, or `None` if the start is not in the region
+pub fn boustrophedon_coverage(grid: &BitPackedGrid, region: &BitPackedGrid, start: Cell) -> Option<Vec<Cell>> {
+    if !inside(grid, region, start) {
+        return None;
+    }
+    let cells = boustrophedon_decomposition(grid, region);
+    let owner = |(x, y): Cell| cells.iter().position(|c| c.iter().any(|s| s.0 == x && (s.1..=s.2).contains(&y)));
+    let adjacent = |a: usize, b: usize| {
+        cells[a].iter().any(|s| cells[b].iter().any(|t| s.0.abs_diff(t.0) == 1 && s.1 <= t.2 && t.1 <= s.2))
+    };
+    let mut order = Vec::new();
+    let mut stack = vec![owner(start)?];
+    let mut visited = vec![false; cells.len()];
+    while let Some(cell) = stack.pop() {
+        if std::mem::replace(&mut visited[cell], true) {
+            continue;
+        }
+        order.push(cell);
+        stack.extend((0..cells.len()).rev().filter(|c| !visited[*c] && adjacent(cell, *c)));
+    }
+    let mut path = vec![start];
+    for cell in order {
+        for (i, &(x, top, bottom)) in cells[cell].iter().enumerate() {
+            let column: Vec<Cell> = match i % 2 {
+                0 => (top..=bottom).map(|y| (x, y)).collect(),
+                _ => (top..=bottom).rev().map(|y| (x, y)).collect(),
+            };
+            route_to(grid, region, &mut path, column[0])?;
+            path.extend(&column[1..]);
+        }
+    }
+    Some(path)
+}
+
+/// Plans a spanning tree coverage path around the free 2x2 blocks of the
+/// region connected to the block of a start
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `region` - A grid of the same size whose free cells are the region
+/// * `start` - The start cell, which must be in a fully free block
+/// ## Returns
+/// A 4-connected path from the start visiting every cell of the connected
+/// blocks once, ending next to the start, or `None` if the block of the start
+/// is not free
+pub fn spanning_tree_coverage(grid: &BitPackedGrid, region: &BitPackedGrid, start: Cell) -> Option<Vec<Cell>> {
+    let block_free = |(bx, by): Cell| {
+        [(0, 0), (1, 0), (0, 1), (1, 1)].iter().all(|(dx, dy)| inside(grid, region, (2 * bx + dx, 2 * by + dy)))
+    };
+    let root = (start.0 / 2, start.1 / 2);
+    if !block_free(root) {
+        return None;
+    }
+    // Depth-first spanning tree over the free blocks, edges are stored both ways
+    let mut edges = HashMap::<Cell, Vec<Cell>>::new();
+    let mut stack = vec![(root, root)];
+    while let Some((block, parent)) = stack.pop() {
+        if edges.contains_key(&block) {
+            continue;
+        }
+        edges.insert(block, Vec::new());
+        if block != parent {
+            edges.get_mut(&block).unwrap().push(parent);
+            edges.get_mut(&parent).unwrap().push(block);
+        }
+        let (bx, by) = block;
+        let neighbours = [(bx.wrapping_sub(1), by), (bx + 1, by), (bx, by.wrapping_sub(1)), (bx, by + 1)];
+        for next in neighbours.into_iter().rev() {
+            if next.0 != usize::MAX && next.1 != usize::MAX && !edges.contains_key(&next) && block_free(next) {
+                stack.push((next, block));
+            }
+        }
+    }
+    let connected = |a: Cell, b: Cell| edges.get(&a).is_some_and(|e| e.contains(&b));
+    // Neighbouring cells in the same block are joined unless a tree edge leaves
+    // the block between them, and cells in different blocks only along a tree edge
+    let joined = |a: Cell, b: Cell| {
+        let (ba, bb) = ((a.0 / 2, a.1 / 2), (b.0 / 2, b.1 / 2));
+        if ba != bb {
+            return connected(ba, bb);
+        }
+        let outward = match a.0 == b.0 {
+            true => (if a.0.is_multiple_of(2) { ba.0.wrapping_sub(1) } else { ba.0 + 1 }, ba.1),
+            false => (ba.0, if a.1.is_multiple_of(2) { ba.1.wrapping_sub(1) } else { ba.1 + 1 }),
+        };
+        !connected(ba, outward)
+    };
+    let mut path = vec![start];
+    let mut previous = start;
+    loop {
+        let current = *path.last().unwrap();
+        let (x, y) = current;
+        let next = [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)]
+            .into_iter()
+            .filter(|n| n.0 != usize::MAX && n.1 != usize::MAX && edges.contains_key(&(n.0 / 2, n.1 / 2)))
+            .find(|n| *n != previous && joined(current, *n))?;
+        if next == start {
+            return Some(path);
+        }
+        previous = current;
+        path.push(next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks a path is 4-connected and visits every expected cell
+    fn check(path: &[Cell], cells: impl Iterator<Item = Cell>) {
+        assert!(path.windows(2).all(|w| w[0].0.abs_diff(w[1].0) + w[0].1.abs_diff(w[1].1) == 1));
+        for cell in cells {
+            assert!(path.contains(&cell), "{:?} is not covered", cell);
+        }
+    }
+
+    #[test]
+    fn test_boustrophedon_coverage() {
+        let grid = BitPackedGrid::new_from_string("......\n..@@..\n..@@..\n......\n".to_string());
+        let cells = boustrophedon_decomposition(&grid, &grid);
+        assert_eq!(cells.len(), 4);
+        let path = boustrophedon_coverage(&grid, &grid, (0, 0)).unwrap();
+        check(&path, grid.free_cells());
+        assert!(path.len() < 2 * grid.free_cells().count());
+
+        let mut region = BitPackedGrid::new(6, 4);
+        (0..3).for_each(|x| region.set_bit_value((x, 0), true));
+        assert_eq!(boustrophedon_coverage(&grid, &region, (1, 0)).unwrap().len(), 4);
+        assert_eq!(boustrophedon_coverage(&grid, &region, (2, 1)), None);
+    }
+
+    #[test]
+    fn test_spanning_tree_coverage() {
+        let grid = BitPackedGrid::new_from_string("......\n......\n..@...\n......\n".to_string());
+        let path = spanning_tree_coverage(&grid, &grid, (0, 0)).unwrap();
+        // The block with the obstacle is skipped and every other cell visited once
+        assert_eq!(path.len(), 20);
+        check(&path, grid.free_cells().filter(|(x, y)| *x >= 4 || *y <= 1 || *x <= 1));
+        let last = path.last().unwrap();
+        assert_eq!(last.0.abs_diff(0) + last.1.abs_diff(0), 1);
+        assert_eq!(spanning_tree_coverage(&grid, &grid, (2, 3)), None);
+    }
+}
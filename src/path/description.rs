@@ -0,0 +1,132 @@
+//! # Route Descriptions
+//! Turn-by-turn summaries of grid paths, such as "go north 12 cells, then turn
+//! east at the corridor junction", for debugging paths and for the dialogue of
+//! game characters giving directions. A path is split into legs of steps in
+//! the same direction, and the place of every turn is named from the region
+//! decomposition of the map: a room, a corridor, or a corridor junction when
+//! the turn is at a corridor cell with more than two free neighbours. Paths from any-angle or
+//! 8-connected planners that zig-zag give many short legs, so legs shorter
+//! than a minimum length can be merged into their neighbours first.
+
+use crate::analysis::regions::{RegionKind, Regions};
+use crate::domains::{Cell, direction::Direction};
+
+/// A run of steps in the same direction
+/// ## Fields
+/// * `direction` - The direction of every step
+/// * `cells` - The number of steps
+/// * `start` - The cell the leg starts from, where the turn onto it happens
+/// * `end` - The cell the leg ends at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Leg {
+    pub direction: Direction,
+    pub cells: usize,
+    pub start: Cell,
+    pub end: Cell,
+}
+
+/// Splits a path of adjacent cells into legs
+/// ## Returns
+/// The legs of the path, `None` if a step is not to an adjacent cell
+pub fn legs(path: &[Cell]) -> Option<Vec<Leg>> {
+    let mut legs: Vec<Leg> = Vec::new();
+    for step in path.windows(2) {
+        let direction = Direction::between(step[0], step[1])?;
+        match legs.last_mut() {
+            Some(leg) if leg.direction == direction => {
+                leg.cells += 1;
+                leg.end = step[1];
+            }
+            _ => legs.push(Leg { direction, cells: 1, start: step[0], end: step[1] }),
+        }
+    }
+    Some(legs)
+}
+
+/// Merges legs shorter than a minimum length into the previous leg, keeping
+/// its direction, so the description follows the overall heading of a path
+pub fn merge_short_legs(legs: &[Leg], min_cells: usize) -> Vec<Leg> {
+    let mut merged: Vec<Leg> = Vec::new();
+    for leg in legs {
+        match merged.last_mut() {
+            Some(last) if leg.cells < min_cells || last.direction == leg.direction => {
+                last.cells += leg.cells;
+                last.end = leg.end;
+            }
+            _ => merged.push(*leg),
+        }
+    }
+    merged
+}
+
+/// The name of the place containing a cell
+fn place(regions: &Regions, cell: Cell) -> String {
+    let free = Direction::CARDINAL.iter()
+        .filter(|d| d.apply(cell).and_then(|n| regions.region_of(n)).is_some())
+        .count();
+    match regions.region_of(cell) {
+        Some(id) => match regions.regions[id].kind {
+            RegionKind::Room => format!("in room {}", id),
+            RegionKind::Corridor if free > 2 => "at the corridor junction".to_string(),
+            RegionKind::Corridor => "in the corridor".to_string(),
+        },
+        None => format!("at {:?}", cell),
+    }
+}
+
+/// Describes a path turn by turn, one sentence per leg and one for the arrival
+/// ## Arguments
+/// * `path` - The path of adjacent cells to describe
+/// * `regions` - The region decomposition of the map naming the places of turns
+/// * `min_cells` - Legs shorter than this are merged into the previous leg
+/// ## Returns
+/// The sentences of the description, `None` if a step is not to an adjacent cell
+pub fn describe(path: &[Cell], regions: &Regions, min_cells: usize) -> Option<Vec<String>> {
+    let legs = merge_short_legs(&legs(path)?, min_cells);
+    let plural = |cells: usize| if cells == 1 { "cell" } else { "cells" };
+    let mut sentences = legs.iter().enumerate()
+        .map(|(i, leg)| match i {
+            0 => format!("Go {} {} {}", leg.direction.name(), leg.cells, plural(leg.cells)),
+            _ => format!(
+                "Turn {} {} and go {} {}",
+                leg.direction.name(), place(regions, leg.start), leg.cells, plural(leg.cells)
+            ),
+        })
+        .collect::<Vec<_>>();
+    if let Some(goal) = path.last() {
+        sentences.push(format!("Arrive {}", place(regions, *goal)));
+    }
+    Some(sentences)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::bitpackedgrid::BitPackedGrid;
+
+    #[test]
+    fn test_legs() {
+        let path = [(0, 0), (1, 0), (2, 0), (2, 1), (3, 2), (4, 2)];
+        let legs = legs(&path).unwrap();
+        assert_eq!(legs.len(), 4);
+        assert_eq!(legs[0], Leg { direction: Direction::East, cells: 2, start: (0, 0), end: (2, 0) });
+        assert_eq!(legs[2].direction, Direction::SouthEast);
+        let merged = merge_short_legs(&legs, 2);
+        assert_eq!(merged, vec![Leg { direction: Direction::East, cells: 5, start: (0, 0), end: (4, 2) }]);
+        assert!(super::legs(&[(0, 0), (2, 0)]).is_none());
+    }
+
+    #[test]
+    fn test_describe() {
+        // A room on the left joined by a corridor to a junction
+        let grid = BitPackedGrid::new_from_string("...@@@@.\n........\n...@@@@.\n".to_string());
+        let regions = Regions::new(&grid, 1);
+        let path = [(0, 0), (0, 1), (1, 1), (2, 1), (3, 1), (4, 1), (5, 1), (6, 1), (7, 1), (7, 2)];
+        let description = describe(&path, &regions, 1).unwrap();
+        assert_eq!(description[0], "Go south 1 cell");
+        assert_eq!(description[1], "Turn east in room 0 and go 7 cells");
+        assert_eq!(description[2], "Turn south at the corridor junction and go 1 cell");
+        assert_eq!(description[3], "Arrive in the corridor");
+        assert_eq!(describe(&[(0, 0)], &regions, 1).unwrap(), vec!["Arrive in room 0".to_string()]);
+    }
+}
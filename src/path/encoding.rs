@@ -0,0 +1,150 @@
+//! # Path Encoding
+//! Compact encodings of grid paths as the direction of each step, for storing
+//! large numbers of benchmark solutions. A path is stored as its start cell and
+//! a bit-packed sequence of directions, using 2 bits per step when every step
+//! is a cardinal move and 3 bits per step otherwise. Paths can also be written
+//! as a string of numpad directions, where 8 is up and 3 is down-right, which is
+//! easy to read and diff, or as a compact base64 string to paste into issue
+//! reports and viewer URLs.
+
+use crate::domains::direction::Direction;
+use crate::util::base64;
+
+/// A path encoded as its start cell and a bit-packed sequence of directions
+/// ## Fields
+/// * `start` - The first cell of the path
+/// * `steps` - The number of steps of the path
+/// * `bits_per_step` - The bits used by each step, 2 or 3
+/// * `data` - The packed directions, least significant bits first
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EncodedPath {
+    pub start: (usize, usize),
+    pub steps: usize,
+    pub bits_per_step: usize,
+    pub data: Vec<u8>,
+}
+
+impl EncodedPath {
+    /// The number of bytes used by the packed directions
+    pub fn byte_len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// The path as a compact string `X,Y,STEPS,BITS:DATA`, where the data is
+    /// the URL-safe base64 of the packed directions
+    pub fn to_compact_string(&self) -> String {
+        format!("{},{},{},{}:{}", self.start.0, self.start.1, self.steps, self.bits_per_step, base64::encode(&self.data))
+    }
+
+    /// Reads a path from its compact string
+    /// ## Returns
+    /// The encoded path, `None` if the string is malformed or its data does not
+    /// fit the number of steps
+    pub fn from_compact_string(compact: &str) -> Option<EncodedPath> {
+        let (header, data) = compact.trim().split_once(':')?;
+        let fields = header.split(',').map(|f| f.parse::<usize>().ok()).collect::<Option<Vec<_>>>()?;
+        let [x, y, steps, bits_per_step] = fields[..] else { return None };
+        let data = base64::decode(data)?;
+        let valid = (bits_per_step == 2 || bits_per_step == 3) && data.len() == (steps * bits_per_step).div_ceil(8);
+        valid.then_some(EncodedPath { start: (x, y), steps, bits_per_step, data })
+    }
+
+    /// The direction index of a step
+    fn direction(&self, step: usize) -> usize {
+        let bit = step * self.bits_per_step;
+        let word = self.data[bit / 8] as usize | (*self.data.get(bit / 8 + 1).unwrap_or(&0) as usize) << 8;
+        (word >> (bit % 8)) & ((1 << self.bits_per_step) - 1)
+    }
+}
+
+/// The direction index of a step between two adjacent cells, the cardinal
+/// directions come first so they fit in 2 bits
+fn direction_of(from: (usize, usize), to: (usize, usize)) -> Option<usize> {
+    Direction::between(from, to).map(|d| d.index())
+}
+
+/// The cell reached by moving in a direction, `None` if it leaves the grid
+fn step(cell: (usize, usize), direction: usize) -> Option<(usize, usize)> {
+    Direction::from_index(direction)?.apply(cell)
+}
+
+/// Encodes a path of adjacent cells
+/// ## Returns
+/// The encoded path, `None` if the path is empty or a step is not to an adjacent cell
+pub fn encode(path: &[(usize, usize)]) -> Option<EncodedPath> {
+    let directions = path.windows(2).map(|m| direction_of(m[0], m[1])).collect::<Option<Vec<_>>>()?;
+    let bits_per_step = if directions.iter().all(|d| *d < 4) { 2 } else { 3 };
+    let mut data = vec![0u8; (directions.len() * bits_per_step).div_ceil(8)];
+    for (i, direction) in directions.iter().enumerate() {
+        let bit = i * bits_per_step;
+        data[bit / 8] |= (direction << (bit % 8)) as u8;
+        if bit % 8 + bits_per_step > 8 {
+            data[bit / 8 + 1] |= (direction >> (8 - bit % 8)) as u8;
+        }
+    }
+    Some(EncodedPath { start: *path.first()?, steps: directions.len(), bits_per_step, data })
+}
+
+/// Decodes an encoded path back into its cells
+pub fn decode(encoded: &EncodedPath) -> Vec<(usize, usize)> {
+    let mut path = vec![encoded.start];
+    for i in 0..encoded.steps {
+        let next = step(*path.last().unwrap(), encoded.direction(i)).expect("Encoded path leaves the grid");
+        path.push(next);
+    }
+    path
+}
+
+/// Writes a path as a string of numpad directions
+/// ## Returns
+/// The directions, `None` if a step is not to an adjacent cell
+pub fn to_directions(path: &[(usize, usize)]) -> Option<String> {
+    path.windows(2).map(|m| Direction::between(m[0], m[1]).map(|d| d.digit())).collect()
+}
+
+/// Reads a path from its start cell and a string of numpad directions
+/// ## Returns
+/// The path, `None` if a character is not a direction or the path leaves the grid
+pub fn from_directions(start: (usize, usize), directions: &str) -> Option<Vec<(usize, usize)>> {
+    let mut path = vec![start];
+    for c in directions.chars() {
+        path.push(Direction::from_digit(c)?.apply(*path.last()?)?);
+    }
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let cardinal = vec![(2, 2), (3, 2), (3, 3), (2, 3), (2, 2), (2, 1)];
+        let encoded = encode(&cardinal).unwrap();
+        assert_eq!((encoded.bits_per_step, encoded.byte_len()), (2, 2));
+        assert_eq!(decode(&encoded), cardinal);
+
+        let octile = vec![(0, 0), (1, 1), (2, 1), (3, 0), (2, 1), (1, 2), (0, 1), (1, 0)];
+        let encoded = encode(&octile).unwrap();
+        assert_eq!((encoded.bits_per_step, encoded.byte_len()), (3, 3));
+        assert_eq!(decode(&encoded), octile);
+        assert_eq!(encode(&[(0, 0), (2, 0)]), None);
+        assert_eq!(decode(&encode(&[(4, 4)]).unwrap()), vec![(4, 4)]);
+
+        let compact = encoded.to_compact_string();
+        assert!(compact.starts_with("0,0,7,3:"));
+        assert_eq!(EncodedPath::from_compact_string(&compact), Some(encoded));
+        assert_eq!(EncodedPath::from_compact_string("0,0,7,3:AA"), None);
+        assert_eq!(EncodedPath::from_compact_string("0,0,7:AAAA"), None);
+    }
+
+    #[test]
+    fn test_directions() {
+        let path = vec![(1, 1), (2, 1), (2, 2), (1, 3), (0, 2), (1, 1)];
+        let directions = to_directions(&path).unwrap();
+        assert_eq!(directions, "62179");
+        assert_eq!(from_directions((1, 1), &directions), Some(path));
+        assert_eq!(from_directions((0, 0), "4"), None);
+        assert_eq!(from_directions((0, 0), "5"), None);
+    }
+}
@@ -0,0 +1,16 @@
+//! # Path Utilities
+//! Operations on paths returned by the search algorithms. These include:
+//! * Corridor, the free cells around a path as a mask for restricted replanning
+//! * Similarity, Fréchet and Hausdorff distances and overlap ratios between paths
+//! * Encoding, compact direction sequences for storing and diffing paths
+//! * Coverage, boustrophedon and spanning tree paths visiting every cell of a region
+//! * Description, turn-by-turn summaries of paths naming the regions of turns
+//! * Simplify, string-pulling, simplification, decimation and length and turn metrics
+
+#![allow(dead_code)]
+pub mod corridor;
+pub mod similarity;
+pub mod encoding;
+pub mod coverage;
+pub mod description;
+pub mod simplify;
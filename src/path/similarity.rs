@@ -0,0 +1,89 @@
+//! # Path Similarity
+//! Measures of how alike two paths are, used to evaluate diverse path
+//! generation and to compare the outputs of planners. Distances between cells
+//! are Euclidean.
+//! * Fréchet distance, the shortest leash that lets two walkers traverse both
+//!   paths in order, sensitive to the order in which cells are visited
+//! * Hausdorff distance, the furthest any cell of one path is from the other path
+//! * Overlap ratio, the fraction of the cells of the shorter path shared with the other
+
+use std::collections::HashSet;
+
+use crate::heuristics::distance::euclidean_distance;
+
+fn distance((x1, y1): (usize, usize), (x2, y2): (usize, usize)) -> f32 {
+    euclidean_distance((x1 as i32, y1 as i32), (x2 as i32, y2 as i32))
+}
+
+/// The discrete Fréchet distance between two paths
+/// ## Complexity
+/// O(nm) where n and m are the lengths of the paths
+pub fn frechet_distance(a: &[(usize, usize)], b: &[(usize, usize)]) -> f32 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    // Row by row dynamic programme over the coupling of the two paths
+    let mut previous: Vec<f32> = Vec::with_capacity(b.len());
+    for (i, p) in a.iter().enumerate() {
+        let mut current: Vec<f32> = Vec::with_capacity(b.len());
+        for (j, q) in b.iter().enumerate() {
+            let d = distance(*p, *q);
+            let reach = match (i, j) {
+                (0, 0) => 0.0,
+                (0, _) => current[j - 1],
+                (_, 0) => previous[0],
+                _ => previous[j].min(previous[j - 1]).min(current[j - 1]),
+            };
+            current.push(d.max(reach));
+        }
+        previous = current;
+    }
+    previous[b.len() - 1]
+}
+
+/// The Hausdorff distance between two paths
+/// ## Complexity
+/// O(nm) where n and m are the lengths of the paths
+pub fn hausdorff_distance(a: &[(usize, usize)], b: &[(usize, usize)]) -> f32 {
+    let directed = |a: &[(usize, usize)], b: &[(usize, usize)]| {
+        a.iter()
+            .map(|p| b.iter().map(|q| distance(*p, *q)).fold(f32::INFINITY, f32::min))
+            .fold(0.0, f32::max)
+    };
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    directed(a, b).max(directed(b, a))
+}
+
+/// The fraction of the distinct cells of the shorter path that are also on
+/// the other path, 1.0 when one path lies entirely on the other
+pub fn overlap_ratio(a: &[(usize, usize)], b: &[(usize, usize)]) -> f32 {
+    let a = a.iter().collect::<HashSet<_>>();
+    let b = b.iter().collect::<HashSet<_>>();
+    let shorter = a.len().min(b.len());
+    if shorter == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f32 / shorter as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_similarity() {
+        let straight = [(0, 0), (1, 0), (2, 0), (3, 0)];
+        let detour = [(0, 0), (0, 1), (1, 1), (2, 1), (3, 1), (3, 0)];
+        assert_eq!(frechet_distance(&straight, &straight), 0.0);
+        assert_eq!(frechet_distance(&straight, &detour), 1.0);
+        assert_eq!(hausdorff_distance(&straight, &detour), 1.0);
+        let reversed = straight.iter().rev().copied().collect::<Vec<_>>();
+        assert_eq!(hausdorff_distance(&straight, &reversed), 0.0);
+        assert_eq!(frechet_distance(&straight, &reversed), 3.0);
+        assert_eq!(overlap_ratio(&straight, &detour), 0.5);
+        assert_eq!(overlap_ratio(&straight[..2], &straight), 1.0);
+        assert_eq!(overlap_ratio(&[], &straight), 0.0);
+    }
+}
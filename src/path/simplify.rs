@@ -0,0 +1,176 @@
+//! # Path Simplification
+//! Post-processing of the paths returned by searches into fewer waypoints,
+//! along with their length and turn metrics. String-pulling keeps only the
+//! waypoints needed for every segment to have line of sight on the grid,
+//! Ramer–Douglas–Peucker drops waypoints within a tolerance of the simplified
+//! line and decimation spaces waypoints out along the path. Every operation
+//! works on cell paths and on any-angle paths of float waypoints through
+//! `PathPoint`, and keeps the first and last waypoints.
+
+use crate::domains::bitpackedgrid::BitPackedGrid;
+use crate::search::thetastar::ThetaStar;
+
+/// A waypoint of a path
+pub trait PathPoint: Copy + PartialEq {
+    /// The position of the waypoint in the plane, cells lying at their centres
+    fn position(&self) -> (f64, f64);
+
+    /// The cell containing the waypoint
+    fn cell(&self) -> (usize, usize);
+}
+
+impl PathPoint for (usize, usize) {
+    fn position(&self) -> (f64, f64) {
+        (self.0 as f64 + 0.5, self.1 as f64 + 0.5)
+    }
+
+    fn cell(&self) -> (usize, usize) {
+        *self
+    }
+}
+
+impl PathPoint for (f32, f32) {
+    fn position(&self) -> (f64, f64) {
+        (self.0 as f64, self.1 as f64)
+    }
+
+    fn cell(&self) -> (usize, usize) {
+        (self.0.max(0.0) as usize, self.1.max(0.0) as usize)
+    }
+}
+
+/// The distance between two waypoints
+fn distance<P: PathPoint>(a: &P, b: &P) -> f64 {
+    let ((x1, y1), (x2, y2)) = (a.position(), b.position());
+    (x1 - x2).hypot(y1 - y2)
+}
+
+/// Pulls a path taut, keeping a waypoint only where the segment from the
+/// previous waypoint to the next loses line of sight. Sight is checked
+/// between the centres of the cells containing the waypoints.
+/// ## Returns
+/// The waypoints of the pulled path, a subsequence of the path
+pub fn string_pull<P: PathPoint>(grid: &BitPackedGrid, path: &[P]) -> Vec<P> {
+    let sight = ThetaStar::new(grid);
+    let Some(first) = path.first() else { return Vec::new() };
+    let mut pulled = vec![*first];
+    let mut anchor = 0;
+    for i in 1..path.len() - 1 {
+        if !sight.line_of_sight(path[anchor].cell(), path[i + 1].cell()) {
+            pulled.push(path[i]);
+            anchor = i;
+        }
+    }
+    if path.len() > 1 {
+        pulled.push(path[path.len() - 1]);
+    }
+    pulled
+}
+
+/// Simplifies a path with Ramer–Douglas–Peucker, dropping the waypoints that
+/// lie within a tolerance of the segment between the waypoints kept around
+/// them. The simplified path is not checked against obstacles.
+pub fn simplify<P: PathPoint>(path: &[P], tolerance: f64) -> Vec<P> {
+    if path.len() < 3 {
+        return path.to_vec();
+    }
+    let mut keep = vec![false; path.len()];
+    (keep[0], keep[path.len() - 1]) = (true, true);
+    let mut stack = vec![(0, path.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let farthest = (first + 1..last)
+            .map(|i| (i, segment_distance(&path[i], &path[first], &path[last])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, _)) = farthest.filter(|(_, d)| *d > tolerance) {
+            keep[i] = true;
+            stack.extend([(first, i), (i, last)]);
+        }
+    }
+    path.iter().zip(keep).filter(|(_, k)| *k).map(|(p, _)| *p).collect()
+}
+
+/// The distance from a waypoint to the segment between two others
+fn segment_distance<P: PathPoint>(point: &P, a: &P, b: &P) -> f64 {
+    let ((px, py), (ax, ay), (bx, by)) = (point.position(), a.position(), b.position());
+    let (dx, dy) = (bx - ax, by - ay);
+    let length = dx * dx + dy * dy;
+    let t = if length > 0.0 { (((px - ax) * dx + (py - ay) * dy) / length).clamp(0.0, 1.0) } else { 0.0 };
+    (px - ax - t * dx).hypot(py - ay - t * dy)
+}
+
+/// Decimates a path, keeping a waypoint once the length travelled along the
+/// path since the last kept waypoint reaches `spacing`
+pub fn decimate<P: PathPoint>(path: &[P], spacing: f64) -> Vec<P> {
+    let mut decimated = path.first().map(|p| vec![*p]).unwrap_or_default();
+    let mut travelled = 0.0;
+    for (i, pair) in path.windows(2).enumerate() {
+        travelled += distance(&pair[0], &pair[1]);
+        if travelled >= spacing || i + 2 == path.len() {
+            decimated.push(pair[1]);
+            travelled = 0.0;
+        }
+    }
+    decimated
+}
+
+/// The Euclidean length of a path
+pub fn path_length<P: PathPoint>(path: &[P]) -> f64 {
+    path.windows(2).map(|pair| distance(&pair[0], &pair[1])).sum()
+}
+
+/// The heading changes along a path in radians, skipping repeated waypoints
+fn headings<P: PathPoint>(path: &[P]) -> Vec<f64> {
+    let mut headings = Vec::new();
+    for pair in path.windows(2).filter(|pair| distance(&pair[0], &pair[1]) > 0.0) {
+        let ((x1, y1), (x2, y2)) = (pair[0].position(), pair[1].position());
+        headings.push((y2 - y1).atan2(x2 - x1));
+    }
+    headings.windows(2).map(|h| {
+        let turn = (h[1] - h[0]).rem_euclid(std::f64::consts::TAU);
+        if turn > std::f64::consts::PI { std::f64::consts::TAU - turn } else { turn }
+    }).collect()
+}
+
+/// The number of waypoints where a path changes heading
+pub fn turn_count<P: PathPoint>(path: &[P]) -> usize {
+    headings(path).into_iter().filter(|turn| *turn > 1e-9).count()
+}
+
+/// The sum of the heading changes along a path in radians
+pub fn total_turning<P: PathPoint>(path: &[P]) -> f64 {
+    headings(path).into_iter().sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_pull() {
+        let grid = BitPackedGrid::new_from_string("......\n.@@@..\n......\n".to_string());
+        let path = [(0, 0), (0, 1), (0, 2), (1, 2), (2, 2), (3, 2), (4, 2), (5, 2)];
+        assert_eq!(string_pull(&grid, &path), vec![(0, 0), (0, 2), (5, 2)]);
+        let around = [(0, 2), (0, 1), (0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (4, 1), (4, 2)];
+        let pulled = string_pull(&grid, &around);
+        assert_eq!(pulled, vec![(0, 2), (0, 0), (4, 0), (4, 2)]);
+        let stairs = [(4, 0), (5, 0), (5, 1), (5, 2)];
+        assert_eq!(string_pull(&grid, &stairs), vec![(4, 0), (5, 2)]);
+        assert!(path_length(&string_pull(&grid, &stairs)) < path_length(&stairs));
+        let floats = around.iter().map(|(x, y)| (*x as f32 + 0.5, *y as f32 + 0.5)).collect::<Vec<_>>();
+        assert_eq!(string_pull(&grid, &floats).len(), pulled.len());
+        assert_eq!(string_pull::<(usize, usize)>(&grid, &[]), vec![]);
+    }
+
+    #[test]
+    fn test_simplify_and_metrics() {
+        let path = [(0.0, 0.0), (1.0, 0.1), (2.0, -0.1), (3.0, 0.0), (3.0, 3.0)];
+        assert_eq!(simplify(&path, 0.5), vec![(0.0, 0.0), (3.0, 0.0), (3.0, 3.0)]);
+        assert_eq!(simplify(&path, 0.01), path.to_vec());
+        let cells = [(0, 0), (1, 0), (2, 0), (2, 1), (2, 2), (3, 2)];
+        assert_eq!(turn_count(&cells), 2);
+        assert!((total_turning(&cells) - std::f64::consts::PI).abs() < 1e-9);
+        assert_eq!(path_length(&cells), 5.0);
+        assert_eq!(decimate(&cells, 2.0), vec![(0, 0), (2, 0), (2, 2), (3, 2)]);
+        assert_eq!(simplify(&cells, 0.1), vec![(0, 0), (2, 0), (2, 2), (3, 2)]);
+    }
+}
@@ -0,0 +1,246 @@
+//! # Adaptive A-Star
+//! A-Star for repeated queries to the same goal that learns a more informed
+//! heuristic from every search, following Koenig and Likhachev, "Adaptive
+//! A*" (2005). Once a search finds a path of cost `c*` every expanded node `s`
+//! is at most `c* - g(s)` from the goal, which is stored as its heuristic for
+//! later searches. The learned heuristic stays admissible and consistent while
+//! the goal and edge costs do not decrease, so later searches remain optimal
+//! while expanding fewer nodes.
+//!
+//! Moving goals and decreasing costs are handled as in Generalized Adaptive
+//! A*, following Sun, Koenig and Yeoh, "Generalized Adaptive A*" (2008), so
+//! a pursuer chasing a moving target on a partially known map keeps reusing
+//! its effort. When the goal moves every learned value is lowered by the
+//! heuristic value of the new goal, and when edge costs decrease, such as
+//! after sensing a `SampleGrid` reveals free cells, learned values are
+//! lowered until they are consistent again.
+
+use std::{collections::{BinaryHeap, HashMap, HashSet}, hash::Hash, ops::{Add, Sub}};
+
+use super::stats::{timed, SearchStats};
+use super::{reconstruct_path_with_cost, SearchNodeState};
+
+/// An A-Star search to a fixed goal that learns heuristic values across searches
+/// ## Fields
+/// * `goal` - The goal of every search
+/// * `learned` - The learned heuristic value of each node expanded by a past search
+/// * `expanded` - The number of nodes expanded by the last search
+pub struct AdaptiveAStar<N, C> {
+    pub goal: N,
+    pub learned: HashMap<N, C>,
+    pub expanded: usize,
+}
+
+impl<N, C> AdaptiveAStar<N, C>
+where
+    N: Hash + Clone + Eq,
+    C: Ord + Default + Clone + Add<Output = C> + Sub<Output = C>,
+{
+    /// Creates a search to a goal with nothing learned
+    pub fn new(goal: N) -> Self {
+        AdaptiveAStar { goal, learned: HashMap::new(), expanded: 0 }
+    }
+
+    /// The heuristic value of a node, the larger of its learned value and the
+    /// base heuristic
+    pub fn heuristic(&self, node: &N, base: impl Fn(&N) -> C) -> C {
+        let h = base(node);
+        match self.learned.get(node) {
+            Some(learned) if *learned > h => learned.clone(),
+            _ => h,
+        }
+    }
+
+    /// Forgets every learned value, such as after edge costs decrease
+    pub fn reset(&mut self) {
+        self.learned.clear();
+    }
+
+    /// Moves the goal, correcting the learned values so they stay admissible
+    /// and consistent for the new goal. The base heuristic of the new goal
+    /// must not be less than that of the old goal minus their distance, which
+    /// holds for distance heuristics satisfying the triangle inequality.
+    /// ## Arguments
+    /// * `goal` - The new goal
+    /// * `base` - The base heuristic to the old goal
+    pub fn move_goal(&mut self, goal: N, base: impl Fn(&N) -> C) {
+        let correction = self.heuristic(&goal, base);
+        for value in self.learned.values_mut() {
+            *value = if *value > correction { value.clone() - correction.clone() } else { C::default() };
+        }
+        self.goal = goal;
+    }
+
+    /// Restores the consistency of the learned values after the costs of the
+    /// edges into and out of some nodes decreased, lowering each learned
+    /// value to the cost through a neighbour and propagating the change
+    /// outwards. Only learned values are lowered, so the repair stays within
+    /// the region explored by past searches.
+    /// ## Arguments
+    /// * `changed` - The nodes whose edge costs decreased, such as the cells
+    ///   returned by `SampleGrid::sense`
+    /// * `neighbours` - The neighbours of a node with the costs of the edges
+    ///   between them, edges being symmetric as on grids
+    /// * `base` - The base heuristic to the goal
+    pub fn repair<E, I>(&mut self, changed: &[N], mut neighbours: E, base: impl Fn(&N) -> C)
+    where
+        E: FnMut(&N) -> I,
+        I: IntoIterator<Item = (N, C)>,
+    {
+        let mut open = BinaryHeap::new();
+        for node in changed {
+            let lowest = neighbours(node).into_iter().map(|(m, c)| c + self.heuristic(&m, &base)).min();
+            if let Some(lowest) = lowest.filter(|l| self.learned.get(node).is_some_and(|h| l < h)) {
+                self.learned.insert(node.clone(), lowest);
+            }
+            open.push(SearchNodeState { cost: self.heuristic(node, &base), node: node.clone() });
+        }
+        while let Some(SearchNodeState { node, cost }) = open.pop() {
+            if cost != self.heuristic(&node, &base) {
+                continue;
+            }
+            for (neighbour, c) in neighbours(&node) {
+                let through = c + cost.clone();
+                if self.learned.get(&neighbour).is_some_and(|h| through < *h) {
+                    self.learned.insert(neighbour.clone(), through.clone());
+                    open.push(SearchNodeState { cost: self.heuristic(&neighbour, &base), node: neighbour });
+                }
+            }
+        }
+    }
+
+    /// Searches from a start to the goal, learning the heuristic value of
+    /// every expanded node once a path is found
+    /// ## Arguments
+    /// * `expander` - A function that returns an iterator over the nodes adjacent to a given node
+    /// * `start` - The start node
+    /// * `base` - An admissible and consistent heuristic to the goal
+    /// ## Returns
+    /// An optional path from the start to the goal with its cost
+    pub fn search<E, I, H>(&mut self, expander: E, start: N, base: H) -> Option<(Vec<N>, C)>
+    where
+        E: FnMut(&N) -> I,
+        I: IntoIterator<Item = (N, C)>,
+        H: Fn(&N) -> C,
+    {
+        self.search_with_stats(expander, start, base).0
+    }
+
+    /// Searches like `search`, also returning the statistics of the search
+    pub fn search_with_stats<E, I, H>(&mut self, mut expander: E, start: N, base: H) -> (Option<(Vec<N>, C)>, SearchStats)
+    where
+        E: FnMut(&N) -> I,
+        I: IntoIterator<Item = (N, C)>,
+        H: Fn(&N) -> C,
+    {
+        timed(|| {
+            let mut stats = SearchStats { heuristic_calls: 1, pushed: 1, peak_open: 1, ..Default::default() };
+            let mut open = BinaryHeap::new();
+            let mut previous = HashMap::new();
+            let mut closed = HashSet::new();
+            previous.insert(start.clone(), (None, C::default()));
+            open.push(SearchNodeState { cost: self.heuristic(&start, &base), node: start });
+            self.expanded = 0;
+            while let Some(SearchNodeState { node, .. }) = open.pop() {
+                stats.popped += 1;
+                if !closed.insert(node.clone()) {
+                    stats.duplicates += 1;
+                    continue;
+                }
+                self.expanded += 1;
+                stats.expanded += 1;
+                if node == self.goal {
+                    let optimal = previous[&node].1.clone();
+                    for expanded in closed {
+                        let learned = optimal.clone() - previous[&expanded].1.clone();
+                        self.learned.insert(expanded, learned);
+                    }
+                    return (Some(reconstruct_path_with_cost(previous, node)), stats);
+                }
+                for (child, cost) in expander(&node) {
+                    stats.generated += 1;
+                    let new_cost = previous[&node].1.clone() + cost;
+                    if !previous.contains_key(&child) || new_cost < previous[&child].1 {
+                        previous.insert(child.clone(), (Some(node.clone()), new_cost.clone()));
+                        open.push(SearchNodeState { cost: new_cost + self.heuristic(&child, &base), node: child });
+                        stats.heuristic_calls += 1;
+                        stats.pushed += 1;
+                        stats.open_size(open.len());
+                    }
+                }
+            }
+            (None, stats)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::bitpackedgrid::BitPackedGrid;
+    use crate::heuristics::distance::manhattan_distance;
+    use crate::search::astar::astar;
+
+    #[test]
+    fn test_adaptive_astar() {
+        let grid = BitPackedGrid::new_from_string(
+            ".......\n.@@@@@.\n.....@.\n.@@@.@.\n.@...@.\n.@@@@@.\n.......\n".to_string()
+        );
+        let goal = (3, 4);
+        let expander = |n: &(usize, usize)| grid.adjacent1(*n).filter(|(n, _)| grid.bounds_check(*n)).collect::<Vec<_>>();
+        let base = |n: &(usize, usize)| manhattan_distance(*n, goal);
+        let mut adaptive = AdaptiveAStar::new(goal);
+
+        let (path, cost) = adaptive.search(expander, (0, 6), base).unwrap();
+        let optimal = astar(expander, (0, 6), |n| *n == goal, base).unwrap();
+        assert_eq!(cost, optimal.1);
+        assert_eq!(path.len(), cost + 1);
+        let first = adaptive.expanded;
+        assert!(adaptive.learned.values().all(|h| *h <= cost));
+        assert!(adaptive.heuristic(&(0, 6), base) == cost);
+
+        let (_, cost) = adaptive.search(expander, (6, 6), base).unwrap();
+        assert_eq!(cost, astar(expander, (6, 6), |n| *n == goal, base).unwrap().1);
+        let (again, stats) = adaptive.search_with_stats(expander, (0, 6), base);
+        assert_eq!(again.map(|(_, c)| c), Some(optimal.1));
+        assert!(adaptive.expanded < first);
+        assert_eq!(stats.expanded, adaptive.expanded);
+        adaptive.reset();
+        assert!(adaptive.learned.is_empty());
+    }
+
+    #[cfg(feature = "sampling")]
+    #[test]
+    fn test_moving_target() {
+        use crate::domains::samplegrid::SampleGrid;
+        let map = "........\n.@@@@@@.\n.@....@.\n.@.@@.@.\n.@....@.\n.@@@.@@.\n........\n";
+        let mut grid = SampleGrid::new_from_string(map.to_string());
+        // The pursuer assumes every cell is free until sensed
+        for cell in grid.ground_truth.cells().collect::<Vec<_>>() {
+            grid.set_belief(cell, 1.0);
+        }
+        let free = |grid: &SampleGrid, n: (usize, usize)| grid.bound_check(n) && grid.belief(n) > 0.5;
+        let neighbours = |grid: &SampleGrid, n: (usize, usize)| {
+            grid.adjacent(n, false).filter(|m| free(grid, *m)).map(|m| (m, 1)).collect::<Vec<_>>()
+        };
+        let (mut agent, mut goal) = ((0, 0), (2, 2));
+        let mut adaptive = AdaptiveAStar::new(goal);
+        for step in 0..12 {
+            let base = |n: &(usize, usize)| manhattan_distance(*n, goal);
+            let changed = grid.sense(agent, 2);
+            adaptive.repair(&changed, |n| neighbours(&grid, *n), base);
+            let expander = |n: &(usize, usize)| neighbours(&grid, *n);
+            let (path, cost) = adaptive.search(expander, agent, base).unwrap();
+            assert_eq!(cost, astar(expander, agent, |n| *n == goal, base).unwrap().1, "step {}", step);
+            agent = path[1.min(path.len() - 1)];
+            // The target walks around the inner room
+            let next = [(2, 2), (3, 2), (4, 2), (5, 2), (5, 3), (5, 4), (4, 4), (3, 4), (2, 4), (2, 3)];
+            let at = next.iter().position(|n| *n == goal).unwrap();
+            adaptive.move_goal(next[(at + 1) % next.len()], base);
+            goal = adaptive.goal;
+            assert!(adaptive.learned.iter().all(|(n, h)| {
+                astar(expander, *n, |m| *m == goal, |m| manhattan_distance(*m, goal)).is_none_or(|(_, c)| *h <= c)
+            }));
+        }
+    }
+}
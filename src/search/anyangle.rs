@@ -0,0 +1,209 @@
+//! # Weighted Any-Angle Search
+//! Theta* on grids where every cell has its own traversal weight, the cost
+//! per unit of distance travelled through it. Theta* shortcuts a successor to
+//! the parent of the node being expanded whenever the straight segment between
+//! them is clear, which assumes every segment costs its length. Here the cost
+//! of a segment between cell centres integrates the weight of every cell it
+//! crosses by the length of the segment within the cell, so shortcuts across
+//! expensive terrain are only taken when they are actually cheaper. Segments
+//! may not cross impassable cells, whose weight is not finite, nor squeeze
+//! between two cells meeting at a corner when either is impassable.
+
+use std::{cmp::{Ordering, Reverse}, collections::{BinaryHeap, HashMap, HashSet}};
+
+use crate::domains::{Cell, grid::Grid};
+
+use super::stats::{timed, SearchStats};
+
+/// A path cost ordered by `f64::total_cmp`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Cost(pub(crate) f64);
+
+impl Eq for Cost {}
+
+impl Ord for Cost {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl PartialOrd for Cost {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The weight of a cell if it is passable
+fn weight(weights: &Grid<f32>, cell: Cell) -> Option<f64> {
+    weights.get(cell).filter(|w| w.is_finite() && **w >= 0.0).map(|w| *w as f64)
+}
+
+/// The cost of the straight segment between the centres of two cells, the
+/// length within each crossed cell weighted by the cell's weight
+/// ## Returns
+/// The cost, or `None` if the segment crosses an impassable cell
+pub fn segment_cost(weights: &Grid<f32>, from: Cell, to: Cell) -> Option<f64> {
+    let (dx, dy) = (to.0 as i64 - from.0 as i64, to.1 as i64 - from.1 as i64);
+    let (ax, ay) = (dx.unsigned_abs(), dy.unsigned_abs());
+    let length = (dx as f64).hypot(dy as f64);
+    let (mut x, mut y) = from;
+    // Crossing i of x is at t = (2i + 1) / 2ax, compared across axes exactly as integers
+    let (mut i, mut j) = (0, 0);
+    let crossing = |k: u64, a: u64| (2 * k + 1) as f64 / (2 * a) as f64;
+    let mut t = 0.0;
+    let mut cost = 0.0;
+    loop {
+        let w = weight(weights, (x, y))?;
+        let next_x = (i < ax).then(|| (2 * i + 1) * ay);
+        let next_y = (j < ay).then(|| (2 * j + 1) * ax);
+        let step_x = |x: usize| if dx > 0 { x + 1 } else { x - 1 };
+        let step_y = |y: usize| if dy > 0 { y + 1 } else { y - 1 };
+        let next = match (next_x, next_y) {
+            (None, None) => {
+                return Some(cost + w * (1.0 - t) * length);
+            }
+            (Some(a), Some(b)) if a == b => {
+                // The segment passes through a corner, both side cells must be passable
+                weight(weights, (step_x(x), y))?;
+                weight(weights, (x, step_y(y)))?;
+                let next = crossing(i, ax);
+                (x, y, i, j) = (step_x(x), step_y(y), i + 1, j + 1);
+                next
+            }
+            (Some(a), b) if b.is_none_or(|b| a < b) => {
+                let next = crossing(i, ax);
+                (x, i) = (step_x(x), i + 1);
+                next
+            }
+            _ => {
+                let next = crossing(j, ay);
+                (y, j) = (step_y(y), j + 1);
+                next
+            }
+        };
+        cost += w * (next - t) * length;
+        t = next;
+    }
+}
+
+/// Theta* with per-cell traversal weights
+/// ## Arguments
+/// * `weights` - The traversal weight of each cell, not finite for obstacles
+/// * `start` - The start cell
+/// * `goal` - The goal cell
+/// ## Returns
+/// An optional path of cell centres joined by straight segments from the
+/// start to the goal, with its weighted cost
+pub fn weighted_theta_star(weights: &Grid<f32>, start: Cell, goal: Cell) -> Option<(Vec<Cell>, f64)> {
+    weighted_theta_star_with_stats(weights, start, goal).0
+}
+
+/// Theta* with per-cell traversal weights like `weighted_theta_star`, also
+/// returning the statistics of the search
+pub fn weighted_theta_star_with_stats(weights: &Grid<f32>, start: Cell, goal: Cell) -> (Option<(Vec<Cell>, f64)>, SearchStats) {
+    timed(|| {
+        let mut stats = SearchStats::default();
+        let path = theta_star_counted(weights, start, goal, &mut stats);
+        (path, stats)
+    })
+}
+
+fn theta_star_counted(weights: &Grid<f32>, start: Cell, goal: Cell, stats: &mut SearchStats) -> Option<(Vec<Cell>, f64)> {
+    weight(weights, start)?;
+    // The smallest weight keeps the straight line distance admissible
+    let min_weight = weights.iter().filter_map(|(n, _)| weight(weights, n)).fold(f64::INFINITY, f64::min);
+    let heuristic = |(x, y): Cell| min_weight * (x as f64 - goal.0 as f64).hypot(y as f64 - goal.1 as f64);
+    let mut g = HashMap::from([(start, 0.0)]);
+    let mut parent = HashMap::from([(start, start)]);
+    let mut closed = HashSet::new();
+    let mut open = BinaryHeap::from([Reverse((Cost(heuristic(start)), start))]);
+    (stats.pushed, stats.heuristic_calls, stats.peak_open) = (1, 1, 1);
+    while let Some(Reverse((_, node))) = open.pop() {
+        stats.popped += 1;
+        if !closed.insert(node) {
+            stats.duplicates += 1;
+            continue;
+        }
+        if node == goal {
+            let mut path = vec![goal];
+            while *path.last().unwrap() != start {
+                path.push(parent[path.last().unwrap()]);
+            }
+            path.reverse();
+            return Some((path, g[&goal]));
+        }
+        stats.expanded += 1;
+        let (x, y) = node;
+        for (nx, ny) in (-1i64..=1).flat_map(|dx| (-1i64..=1).map(move |dy| (x as i64 + dx, y as i64 + dy))) {
+            let child = (nx as usize, ny as usize);
+            if nx < 0 || ny < 0 || child == node || closed.contains(&child) {
+                continue;
+            }
+            stats.generated += 1;
+            // Prefer the straight segment from the grandparent when it is cheaper
+            let grandparent = parent[&node];
+            let through_parent = segment_cost(weights, grandparent, child).map(|c| (grandparent, g[&grandparent] + c));
+            let through_node = segment_cost(weights, node, child).map(|c| (node, g[&node] + c));
+            let best = match (through_parent, through_node) {
+                (Some(a), Some(b)) => if a.1 <= b.1 { a } else { b },
+                (a, b) => match a.or(b) {
+                    Some(best) => best,
+                    None => continue,
+                },
+            };
+            if g.get(&child).is_none_or(|c| best.1 < *c) {
+                g.insert(child, best.1);
+                parent.insert(child, best.0);
+                open.push(Reverse((Cost(best.1 + heuristic(child)), child)));
+                stats.heuristic_calls += 1;
+                stats.pushed += 1;
+                stats.open_size(open.len());
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment_cost() {
+        let mut weights = Grid::new(4, 3, 1.0);
+        assert_eq!(segment_cost(&weights, (0, 0), (2, 0)), Some(2.0));
+        weights.set((1, 0), 3.0);
+        assert_eq!(segment_cost(&weights, (0, 0), (2, 0)), Some(4.0));
+        let diagonal = segment_cost(&weights, (0, 1), (2, 2)).unwrap();
+        assert!((diagonal - 5f64.sqrt()).abs() < 1e-9);
+
+        // Squeezing between two cells meeting at a corner is blocked by either
+        weights.set((1, 1), f32::INFINITY);
+        assert_eq!(segment_cost(&weights, (0, 1), (1, 0)), None);
+        assert_eq!(segment_cost(&weights, (2, 1), (1, 0)), None);
+        assert!(segment_cost(&weights, (0, 1), (1, 2)).is_none());
+        assert_eq!(segment_cost(&weights, (0, 2), (2, 0)), None);
+    }
+
+    #[test]
+    fn test_weighted_theta_star() {
+        let uniform = Grid::new(7, 4, 1.0);
+        let (path, cost) = weighted_theta_star(&uniform, (0, 0), (6, 3)).unwrap();
+        assert_eq!(path, vec![(0, 0), (6, 3)]);
+        assert!((cost - 45f64.sqrt()).abs() < 1e-9);
+
+        // A swamp over the straight line is cheaper to go around
+        let mut swamp = uniform.clone();
+        for (x, y) in [(2, 1), (3, 1), (4, 1), (2, 2), (3, 2), (4, 2), (2, 3), (3, 3), (4, 3)] {
+            swamp.set((x, y), 10.0);
+        }
+        let (found, stats) = weighted_theta_star_with_stats(&swamp, (0, 0), (6, 3));
+        let (path, cost) = found.unwrap();
+        assert!(stats.expanded > 0 && stats.popped <= stats.pushed);
+        let direct = segment_cost(&swamp, (0, 0), (6, 3)).unwrap();
+        assert!(cost < direct);
+        assert!(path.len() > 2);
+        let total: f64 = path.windows(2).map(|w| segment_cost(&swamp, w[0], w[1]).unwrap()).sum();
+        assert!((total - cost).abs() < 1e-9);
+    }
+}
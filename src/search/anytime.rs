@@ -0,0 +1,336 @@
+//! # Anytime Search
+//! A wrapper that turns any bounded-suboptimal planner into an anytime planner.
+//! The planner is restarted with a decreasing inflation weight until either an
+//! optimal (weight 1.0) solution has been found or the wall-clock deadline
+//! passes. The best solution found so far is always returned together with the
+//! suboptimality bound that it is guaranteed to satisfy.
+//!
+//! ARA*, following Likhachev, Gordon and Thrun, "ARA*: Anytime A* with
+//! Provable Bounds on Sub-Optimality" (2003), decreases the weight the same
+//! way but reuses the search between iterations, only re-expanding the nodes
+//! whose costs improved, and can be stopped by a wall-clock or expansion budget
+//! in the middle of an iteration. Every improved solution is reported as it is
+//! found with its bound, which ARA* tightens beyond the weight whenever the
+//! open list proves the solution closer to optimal.
+
+use std::{collections::BinaryHeap, hash::Hash, time::{Duration, Instant}};
+
+use crate::util::determinism::{Map, Set};
+
+use super::stats::{timed, SearchStats};
+use super::{astar::astar, proven_bound, reconstruct_path, BoundedSolution, Planner, SearchNodeState};
+
+/// The best solution found by an anytime search
+/// ## Fields
+/// * `solution` - The best solution with the bound it is proven to satisfy
+/// * `iterations` - The number of restarts that were run
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnytimeSolution<N, C> {
+    pub solution: BoundedSolution<N, C>,
+    pub iterations: usize,
+}
+
+/// Runs weighted restarts of a planner under a deadline
+/// ## Fields
+/// * `planner` - The bounded-suboptimal planner to restart
+/// * `initial_weight` - The inflation weight of the first restart
+/// * `decrement` - The amount the weight is decreased by after each restart
+/// * `deadline` - The wall-clock time after which no further restarts are started
+pub struct Anytime<T: Planner> {
+    pub planner: T,
+    pub initial_weight: f64,
+    pub decrement: f64,
+    pub deadline: Duration,
+}
+
+impl<T: Planner> Anytime<T>
+where
+    T::Cost: Ord + Clone,
+{
+    pub fn new(planner: T, initial_weight: f64, decrement: f64, deadline: Duration) -> Self {
+        assert!(initial_weight >= 1.0, "The initial weight must be at least 1.0");
+        assert!(decrement > 0.0, "The weight decrement must be positive");
+        Anytime { planner, initial_weight, decrement, deadline }
+    }
+
+    /// Runs the anytime search. The first restart is always run, while later
+    /// restarts are only started before the deadline. Restarts are not
+    /// interrupted, so the deadline can be exceeded by the length of one restart.
+    /// ## Returns
+    /// The best solution found, or None if the planner found no solution
+    pub fn run(&mut self) -> Option<AnytimeSolution<T::Node, T::Cost>> {
+        let start = Instant::now();
+        let mut best: Option<AnytimeSolution<T::Node, T::Cost>> = None;
+        let mut weight = self.initial_weight;
+        let mut iterations = 0;
+        loop {
+            iterations += 1;
+            if let Some((path, cost)) = self.planner.plan(weight) {
+                match best.as_mut() {
+                    Some(best) if best.solution.cost <= cost => best.solution.bound = best.solution.bound.min(weight),
+                    _ => best = Some(AnytimeSolution { solution: BoundedSolution { path, cost, bound: weight }, iterations }),
+                }
+            }
+            if weight <= 1.0 || start.elapsed() >= self.deadline {
+                break;
+            }
+            weight = (weight - self.decrement).max(1.0);
+        }
+        best.map(|best| AnytimeSolution { iterations, ..best })
+    }
+}
+
+/// Weighted A-Star over integer costs. The heuristic is inflated by the weight
+/// and rounded down, so for an admissible heuristic solutions are within the
+/// weight of optimal.
+pub struct WeightedAstar<N, E, G, H> {
+    pub start: N,
+    pub expander: E,
+    pub goal: G,
+    pub heuristic: H,
+}
+
+impl<N, E, I, G, H> Planner for WeightedAstar<N, E, G, H>
+where
+    N: Hash + Clone + Eq,
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, usize)>,
+    G: Fn(&N) -> bool,
+    H: Fn(&N) -> usize,
+{
+    type Node = N;
+    type Cost = usize;
+
+    fn plan(&mut self, weight: f64) -> Option<(Vec<N>, usize)> {
+        let heuristic = &self.heuristic;
+        astar(
+            &mut self.expander,
+            self.start.clone(),
+            &self.goal,
+            |n| (heuristic(n) as f64 * weight) as usize,
+        )
+    }
+}
+
+/// The budget after which an anytime search stops
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchBudget {
+    /// The wall-clock time of the search
+    Time(Duration),
+    /// The number of nodes expanded across every iteration
+    Expansions(usize),
+}
+
+/// ARA* over integer costs. The heuristic is inflated by the weight and
+/// rounded down, so for a consistent heuristic every solution is within its
+/// bound of optimal.
+/// ## Fields
+/// * `initial_weight` - The inflation weight of the first iteration
+/// * `decrement` - The amount the weight is decreased by after each iteration
+/// * `expanded` - The number of nodes expanded by the last run
+pub struct AraStar<N, E, G, H> {
+    pub start: N,
+    pub expander: E,
+    pub goal: G,
+    pub heuristic: H,
+    pub initial_weight: f64,
+    pub decrement: f64,
+    pub expanded: usize,
+}
+
+impl<N, E, I, G, H> AraStar<N, E, G, H>
+where
+    N: Hash + Clone + Eq,
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, usize)>,
+    G: Fn(&N) -> bool,
+    H: Fn(&N) -> usize,
+{
+    pub fn new(start: N, expander: E, goal: G, heuristic: H, initial_weight: f64, decrement: f64) -> Self {
+        assert!(initial_weight >= 1.0, "The initial weight must be at least 1.0");
+        assert!(decrement > 0.0, "The weight decrement must be positive");
+        AraStar { start, expander, goal, heuristic, initial_weight, decrement, expanded: 0 }
+    }
+
+    /// Runs ARA* until an optimal solution is found or the budget runs out
+    /// ## Arguments
+    /// * `budget` - The budget after which the search stops, possibly mid-iteration
+    /// * `on_solution` - Called with every improved solution or tightened bound
+    /// ## Returns
+    /// The best solution found, or None if no solution was found within the budget
+    pub fn run(&mut self, budget: SearchBudget, on_solution: impl FnMut(&AnytimeSolution<N, usize>)) -> Option<AnytimeSolution<N, usize>> {
+        self.run_with_stats(budget, on_solution).0
+    }
+
+    /// Runs ARA* like `run`, also returning the statistics of every iteration
+    /// of the run, where nodes re-expanded by later iterations count again
+    pub fn run_with_stats(
+        &mut self,
+        budget: SearchBudget,
+        on_solution: impl FnMut(&AnytimeSolution<N, usize>),
+    ) -> (Option<AnytimeSolution<N, usize>>, SearchStats) {
+        timed(|| {
+            let mut stats = SearchStats::default();
+            let best = self.search(budget, on_solution, &mut stats);
+            (best, stats)
+        })
+    }
+
+    fn search(
+        &mut self,
+        budget: SearchBudget,
+        mut on_solution: impl FnMut(&AnytimeSolution<N, usize>),
+        stats: &mut SearchStats,
+    ) -> Option<AnytimeSolution<N, usize>> {
+        let timer = Instant::now();
+        let exhausted = |expanded: usize| match budget {
+            SearchBudget::Time(limit) => timer.elapsed() >= limit,
+            SearchBudget::Expansions(limit) => expanded >= limit,
+        };
+        self.expanded = 0;
+        let mut weight = self.initial_weight;
+        let f = |g: usize, n: &N, weight: f64| g + ((self.heuristic)(n) as f64 * weight) as usize;
+        // Deterministic containers, as the heap is rebuilt in the iteration order of the open set
+        let mut g = Map::from_iter([(self.start.clone(), 0)]);
+        let mut parent: Map<N, Option<N>> = Map::from_iter([(self.start.clone(), None)]);
+        let mut open = Set::from_iter([self.start.clone()]);
+        let mut heap = BinaryHeap::from([SearchNodeState { node: self.start.clone(), cost: f(0, &self.start, weight) }]);
+        let mut inconsistent = Set::default();
+        let mut closed = Set::default();
+        (stats.pushed, stats.heuristic_calls, stats.peak_open) = (1, 1, 1);
+        let mut incumbent: Option<(N, usize)> = (self.goal)(&self.start).then(|| (self.start.clone(), 0));
+        let mut best: Option<AnytimeSolution<N, usize>> = None;
+        let mut iterations = 0;
+        loop {
+            iterations += 1;
+            // Expand until no open node can lead to a cheaper solution at this weight
+            loop {
+                while heap.peek().is_some_and(|s| !open.contains(&s.node) || s.cost != f(g[&s.node], &s.node, weight)) {
+                    heap.pop();
+                    stats.popped += 1;
+                    stats.duplicates += 1;
+                }
+                let Some(SearchNodeState { node, cost }) = heap.peek() else { break };
+                if incumbent.as_ref().is_some_and(|(_, c)| c <= cost) {
+                    break;
+                }
+                if exhausted(self.expanded) {
+                    return best;
+                }
+                let node = node.clone();
+                heap.pop();
+                stats.popped += 1;
+                open.remove(&node);
+                closed.insert(node.clone());
+                self.expanded += 1;
+                stats.expanded += 1;
+                let cost = g[&node];
+                for (child, edge) in (self.expander)(&node) {
+                    stats.generated += 1;
+                    let child_cost = cost + edge;
+                    if g.get(&child).is_some_and(|c| *c <= child_cost) {
+                        continue;
+                    }
+                    g.insert(child.clone(), child_cost);
+                    parent.insert(child.clone(), Some(node.clone()));
+                    if (self.goal)(&child) && incumbent.as_ref().is_none_or(|(_, c)| child_cost < *c) {
+                        incumbent = Some((child.clone(), child_cost));
+                    }
+                    if closed.contains(&child) {
+                        inconsistent.insert(child);
+                    } else {
+                        heap.push(SearchNodeState { node: child.clone(), cost: f(child_cost, &child, weight) });
+                        open.insert(child);
+                        stats.heuristic_calls += 1;
+                        stats.pushed += 1;
+                        stats.open_size(heap.len());
+                    }
+                }
+            }
+            let (goal, cost) = incumbent.clone()?;
+            // The cheapest uninflated f of an unexpanded node lower bounds the optimal cost
+            let lower = open.iter().chain(&inconsistent).map(|n| f(g[n], n, 1.0)).min();
+            let bound = weight.min(proven_bound(cost as f64, lower.map(|l| l as f64)));
+            if best.as_ref().is_none_or(|b| cost < b.solution.cost || bound < b.solution.bound) {
+                let path = reconstruct_path(parent.clone(), goal);
+                let solution = AnytimeSolution { solution: BoundedSolution { path, cost, bound }, iterations };
+                on_solution(&solution);
+                best = Some(solution);
+            }
+            if bound <= 1.0 || weight <= 1.0 || exhausted(self.expanded) {
+                return best;
+            }
+            weight = (weight - self.decrement).max(1.0);
+            open.extend(inconsistent.drain());
+            closed.clear();
+            heap = open.iter().map(|n| SearchNodeState { node: n.clone(), cost: f(g[n], n, weight) }).collect();
+            stats.heuristic_calls += heap.len();
+            stats.pushed += heap.len();
+            stats.open_size(heap.len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::bitpackedgrid::BitPackedGrid;
+    use crate::heuristics::distance::manhattan_distance;
+
+    #[test]
+    fn test_anytime_weighted_astar() {
+        let grid = BitPackedGrid::new_from_string("........\n...@@@..\n.....@..\n.....@..\n........\n".to_string());
+        let planner = WeightedAstar {
+            start: (0, 4),
+            expander: |n: &(usize, usize)| grid.adjacent1(*n).collect::<Vec<_>>(),
+            goal: |n: &(usize, usize)| *n == (7, 0),
+            heuristic: |n: &(usize, usize)| manhattan_distance(*n, (7, 0)),
+        };
+        let mut anytime = Anytime::new(planner, 3.0, 1.0, Duration::from_secs(10));
+        let solution = anytime.run().unwrap();
+        assert_eq!(solution.iterations, 3);
+        let solution = solution.solution;
+        assert_eq!((solution.cost, solution.bound), (11, 1.0));
+        assert_eq!(solution.path.first(), Some(&(0, 4)));
+    }
+
+    #[test]
+    fn test_anytime_deadline() {
+        let planner = WeightedAstar {
+            start: 0,
+            expander: |n: &usize| vec![(n + 1, 1)],
+            goal: |n: &usize| *n == 5,
+            heuristic: |_: &usize| 0,
+        };
+        let mut anytime = Anytime::new(planner, 5.0, 1.0, Duration::ZERO);
+        let solution = anytime.run().unwrap();
+        assert_eq!(solution.iterations, 1);
+        assert_eq!((solution.solution.cost, solution.solution.bound), (5, 5.0));
+        assert!(solution.solution.within(5.0) && !solution.solution.within(4.0));
+    }
+
+    #[test]
+    fn test_ara_star() {
+        let grid = BitPackedGrid::new_from_file("tests/map.map");
+        let free = grid.free_cells().collect::<Vec<_>>();
+        let (start, goal) = (free[100], free[free.len() - 100]);
+        let optimal = astar(|n| grid.adjacent1(*n), start, |n| *n == goal, |n| manhattan_distance(*n, goal)).unwrap().1;
+        let mut ara = AraStar::new(start, |n: &(usize, usize)| grid.adjacent1(*n), |n: &(usize, usize)| *n == goal, |n: &(usize, usize)| manhattan_distance(*n, goal), 3.0, 0.5);
+        let mut reported = Vec::new();
+        let solution = ara.run(SearchBudget::Time(Duration::from_secs(60)), |s| reported.push((s.solution.cost, s.solution.bound))).unwrap().solution;
+        assert_eq!((solution.cost, solution.bound), (optimal, 1.0));
+        assert_eq!(solution.path.first(), Some(&start));
+        assert_eq!(solution.path.len(), optimal + 1);
+        assert!(reported.windows(2).all(|w| w[1].0 <= w[0].0 && w[1].1 <= w[0].1));
+        assert!(reported.iter().all(|(cost, bound)| *cost as f64 <= bound * optimal as f64 + 1e-9));
+
+        // A small expansion budget stops with the first solution or none at all
+        let total = ara.expanded;
+        let (again, stats) = ara.run_with_stats(SearchBudget::Time(Duration::from_secs(60)), |_| {});
+        assert_eq!((again.map(|s| s.solution.cost), stats.expanded), (Some(optimal), ara.expanded));
+        assert!(stats.popped <= stats.pushed && stats.duplicates <= stats.popped);
+        let first = ara.run(SearchBudget::Expansions(total / 2), |_| {});
+        assert!(first.is_none_or(|s| s.solution.cost >= optimal));
+        assert!(ara.run(SearchBudget::Expansions(0), |_| {}).is_none());
+    }
+}
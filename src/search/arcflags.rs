@@ -0,0 +1,180 @@
+//! # Arc Flags
+//! Preprocessing of explicit graphs that speeds up repeated queries, such as
+//! on road networks, by pruning edges that cannot lie on a shortest path to
+//! the region of the goal. The nodes are split into regions and every edge
+//! stores a flag per region, set when the edge starts a shortest path into the
+//! region. Flags are found with a backward Dijkstra search from every boundary
+//! node of each region, the nodes of the region with an incoming edge from
+//! outside it, and edges within a region are always flagged for it. Queries
+//! then only relax the edges flagged for the goal's region and remain optimal.
+//! Unlike hierarchies that add shortcut edges, arc flags keep the graph as is
+//! at the cost of one bit per edge and region.
+
+use std::{cmp::Reverse, collections::BinaryHeap, hash::Hash, ops::Add};
+
+use crate::domains::csrgraph::CsrGraph;
+use crate::util::preprocessing::{par_build, BuildStats};
+
+use super::astar::astar;
+
+/// The region of every node and the flags of every edge of a graph
+/// ## Fields
+/// * `regions` - The region of each node id
+/// * `region_count` - The number of regions
+/// * `stats` - The statistics of the preprocessing build
+#[derive(Clone, Debug)]
+pub struct ArcFlags {
+    pub regions: Vec<usize>,
+    pub region_count: usize,
+    pub stats: BuildStats,
+    words: usize,
+    flags: Vec<u64>,
+}
+
+impl ArcFlags {
+    /// Computes the arc flags of a graph
+    /// ## Arguments
+    /// * `graph` - The graph, whose edge weights must be non-negative
+    /// * `region` - The region of a node label, in `0..region_count`
+    /// * `region_count` - The number of regions
+    pub fn new<N, W>(graph: &CsrGraph<N, W>, region: impl Fn(&N) -> usize, region_count: usize) -> Self
+    where
+        N: Eq + Hash + Clone + Sync,
+        W: Copy + Ord + Default + Add<Output = W> + Sync + Send,
+    {
+        let regions = graph.nodes.iter().map(&region).collect::<Vec<_>>();
+        assert!(regions.iter().all(|r| *r < region_count), "Regions must be less than the region count");
+        // The incoming edges of each node as (source, edge index)
+        let mut incoming = vec![Vec::new(); graph.len()];
+        for source in 0..graph.len() {
+            for edge in graph.offsets[source]..graph.offsets[source + 1] {
+                incoming[graph.targets[edge]].push((source, edge));
+            }
+        }
+        let ids = (0..region_count).collect::<Vec<_>>();
+        let (flagged, stats) = par_build(&ids, |r| {
+            let mut flagged = vec![false; graph.edge_count()];
+            for (node, edges) in incoming.iter().enumerate().filter(|(n, _)| regions[*n] == *r) {
+                let mut boundary = false;
+                for &(source, edge) in edges {
+                    flagged[edge] |= regions[source] == *r;
+                    boundary |= regions[source] != *r;
+                }
+                if boundary {
+                    flag_shortest_paths(graph, &incoming, node, &mut flagged);
+                }
+            }
+            flagged
+        });
+        let words = region_count.div_ceil(64).max(1);
+        let mut flags = vec![0u64; graph.edge_count() * words];
+        for (r, flagged) in flagged.into_iter().enumerate() {
+            for edge in (0..graph.edge_count()).filter(|e| flagged[*e]) {
+                flags[edge * words + r / 64] |= 1 << (r % 64);
+            }
+        }
+        ArcFlags { regions, region_count, stats, words, flags }
+    }
+
+    /// Checks if an edge index is flagged for a region
+    pub fn is_flagged(&self, edge: usize, region: usize) -> bool {
+        self.flags[edge * self.words + region / 64] & (1 << (region % 64)) != 0
+    }
+
+    /// The fraction of edges flagged for a region, the fraction a query into
+    /// the region may relax
+    pub fn flag_density(&self, region: usize) -> f64 {
+        let edges = self.flags.len() / self.words;
+        (0..edges).filter(|e| self.is_flagged(*e, region)).count() as f64 / edges.max(1) as f64
+    }
+
+    /// Finds a shortest path between node ids, relaxing only the edges flagged
+    /// for the region of the goal
+    /// ## Arguments
+    /// * `graph` - The graph the flags were computed on
+    /// * `start` - The start node id
+    /// * `goal` - The goal node id
+    /// * `heuristic` - An admissible heuristic of a node id
+    /// ## Returns
+    /// An optional path of node ids from the start to the goal and its cost
+    pub fn search<N, W>(
+        &self,
+        graph: &CsrGraph<N, W>,
+        start: usize,
+        goal: usize,
+        heuristic: impl Fn(&usize) -> W,
+    ) -> Option<(Vec<usize>, W)>
+    where
+        N: Eq + Hash + Clone,
+        W: Copy + Ord + Default + Add<Output = W>,
+    {
+        let region = self.regions[goal];
+        astar(
+            |n: &usize| {
+                (graph.offsets[*n]..graph.offsets[*n + 1])
+                    .filter(|e| self.is_flagged(*e, region))
+                    .map(|e| (graph.targets[e], graph.weights[e]))
+                    .collect::<Vec<_>>()
+            },
+            start,
+            |n| *n == goal,
+            heuristic,
+        )
+    }
+}
+
+/// Flags the edges on a shortest path to a node with a backward Dijkstra search
+fn flag_shortest_paths<N, W>(graph: &CsrGraph<N, W>, incoming: &[Vec<(usize, usize)>], target: usize, flagged: &mut [bool])
+where
+    N: Eq + Hash + Clone,
+    W: Copy + Ord + Default + Add<Output = W>,
+{
+    let mut distances: Vec<Option<W>> = vec![None; graph.len()];
+    let mut open = BinaryHeap::from([Reverse((W::default(), target))]);
+    while let Some(Reverse((distance, node))) = open.pop() {
+        if distances[node].is_some() {
+            continue;
+        }
+        distances[node] = Some(distance);
+        for &(source, edge) in &incoming[node] {
+            if distances[source].is_none() {
+                open.push(Reverse((distance + graph.weights[edge], source)));
+            }
+        }
+    }
+    // An edge is on a shortest path when it is tight, including ties
+    for (source, distance) in distances.iter().enumerate() {
+        let Some(distance) = distance else { continue };
+        for (i, (target, weight)) in graph.edges(source).enumerate() {
+            flagged[graph.offsets[source] + i] |= distances[target].is_some_and(|d| d + *weight == *distance);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::{bitpackedgrid::BitPackedGrid, csrgraph::Connectivity};
+    use crate::search::uninformed::bfs;
+
+    #[test]
+    fn test_arc_flags() {
+        let grid = BitPackedGrid::new_from_string("........\n.@@@@@@.\n........\n.@@..@@.\n........\n".to_string());
+        let graph = grid.to_graph(Connectivity::Four, |_, _| 1usize);
+        // Four regions split by the middle column and row
+        let flags = ArcFlags::new(&graph, |(x, y)| (x / 4) + 2 * (y / 3), 4);
+        assert_eq!(flags.region_count, 4);
+        assert_eq!(flags.stats.items, 4);
+        for region in 0..4 {
+            assert!(flags.flag_density(region) < 1.0);
+        }
+        for start in 0..graph.len() {
+            for goal in 0..graph.len() {
+                let (path, cost) = flags.search(&graph, start, goal, |_| 0).unwrap();
+                let (_, optimal) = bfs(|n| graph.adjacent(*n).map(|(m, _)| m).collect::<Vec<_>>(), start, |n| *n == goal).unwrap();
+                assert_eq!(cost, optimal);
+                assert_eq!((path[0], *path.last().unwrap()), (start, goal));
+            }
+        }
+    }
+}
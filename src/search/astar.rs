@@ -6,6 +6,8 @@
 
 use std::{hash::Hash, collections::{HashMap, BinaryHeap, HashSet}, ops::Add};
 use super::{reconstruct_path_with_cost, SearchNodeState};
+use super::openlist::{OpenList, TieBreak};
+use super::stats::{timed, Phase, SearchStats};
 
 
 
@@ -77,9 +79,186 @@ where
     None
 }
 
+/// How A-Star handles nodes reached by a cheaper path after being expanded,
+/// which only happens with inconsistent heuristics such as weighted, learned
+/// or combined heuristics
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reexpansion {
+    /// Reopens expanded nodes, keeping paths optimal for admissible heuristics
+    Allow,
+    /// Never reopens expanded nodes, expanding each node once at the cost of optimality
+    Forbid,
+    /// Reopens expanded nodes and raises the f-value of every child to at
+    /// least that of its parent, so the f-values along a path never decrease
+    Pathmax,
+}
+
+/// A-Star Search with an explicit policy for inconsistent heuristics
+/// ## Arguments
+/// * `expander` - A function that returns an iterator over the nodes adjacent to a given node
+/// * `start` - The start node
+/// * `goal` - A function that returns whether or not a given node is the goal
+/// * `heuristic` - A function that returns the heuristic value of a given node
+/// * `policy` - How nodes reached by a cheaper path after expansion are handled
+/// ## Returns
+/// An optional vector of nodes from the start to the goal and its cost
+pub fn astar_with_reexpansion<E, I, C, N, G, H>(
+    mut expander: E,
+    start: N,
+    goal: G,
+    heuristic: H,
+    policy: Reexpansion,
+) -> Option<(Vec<N>, C)>
+where
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+    C: Ord + Default + Clone + Add<Output = C>,
+    N: Hash + Clone + Eq,
+    G: Fn(&N) -> bool,
+    H: Fn(&N) -> C,
+{
+    let mut open = BinaryHeap::from([SearchNodeState { node: start.clone(), cost: heuristic(&start) }]);
+    let mut previous = HashMap::from([(start, (None, C::default()))]);
+    let mut closed: HashMap<N, C> = HashMap::new();
+    while let Some(SearchNodeState { node, cost: f }) = open.pop() {
+        let g = previous[&node].1.clone();
+        // Stale entries and, without reopening, every later entry of an expanded node are skipped
+        if closed.get(&node).is_some_and(|closed_g| policy == Reexpansion::Forbid || *closed_g <= g) {
+            continue;
+        }
+        closed.insert(node.clone(), g.clone());
+        if goal(&node) {
+            return Some(reconstruct_path_with_cost(previous, node));
+        }
+        for (child, cost) in expander(&node) {
+            if policy == Reexpansion::Forbid && closed.contains_key(&child) {
+                continue;
+            }
+            let new_cost = g.clone() + cost;
+            if previous.get(&child).is_none_or(|(_, c)| new_cost < *c) {
+                let mut child_f = new_cost.clone() + heuristic(&child);
+                if policy == Reexpansion::Pathmax {
+                    child_f = child_f.max(f.clone());
+                }
+                previous.insert(child.clone(), (Some(node.clone()), new_cost));
+                open.push(SearchNodeState { node: child, cost: child_f });
+            }
+        }
+    }
+    None
+}
+
+/// A-Star Search whose ties between equal f-values are broken by a policy
+/// ## Arguments
+/// * `expander` - A function that returns an iterator over the nodes adjacent to a given node
+/// * `start` - The start node
+/// * `goal` - A function that returns whether or not a given node is the goal
+/// * `heuristic` - A function that returns the heuristic value of a given node
+/// * `policy` - The tie-breaking policy of the open list
+/// ## Returns
+/// An optional vector of nodes from the start to the goal and its cost
+pub fn astar_with_tie_breaking<E, I, C, N, G, H, P>(
+    mut expander: E,
+    start: N,
+    goal: G,
+    heuristic: H,
+    policy: P,
+) -> Option<(Vec<N>, C)>
+where
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+    C: Ord + Default + Clone + Add<Output = C>,
+    N: Hash + Clone + Eq,
+    G: Fn(&N) -> bool,
+    H: Fn(&N) -> C,
+    P: TieBreak<C>,
+{
+    let mut open = OpenList::new(policy);
+    open.push(start.clone(), C::default(), heuristic(&start));
+    let mut previous = HashMap::from([(start, (None, C::default()))]);
+    let mut closed = HashSet::new();
+    while let Some((node, _)) = open.pop() {
+        if !closed.insert(node.clone()) {
+            continue;
+        }
+        if goal(&node) {
+            return Some(reconstruct_path_with_cost(previous, node));
+        }
+        let g = previous[&node].1.clone();
+        for (child, cost) in expander(&node) {
+            let new_cost = g.clone() + cost;
+            if previous.get(&child).is_none_or(|(_, c)| new_cost < *c) {
+                previous.insert(child.clone(), (Some(node.clone()), new_cost.clone()));
+                let h = heuristic(&child);
+                open.push(child, new_cost, h);
+            }
+        }
+    }
+    None
+}
+
+/// A-Star Search that records statistics of the search, see `SearchStats`
+/// ## Returns
+/// An optional vector of nodes from the start to the goal with its cost, and
+/// the statistics of the search
+pub fn astar_with_stats<E, I, C, N, G, H>(
+    mut expander: E,
+    start: N,
+    goal: G,
+    heuristic: H,
+) -> (Option<(Vec<N>, C)>, SearchStats)
+where
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+    C: Ord + Default + Clone + Add<Output = C>,
+    N: Hash + Clone + Eq,
+    G: Fn(&N) -> bool,
+    H: Fn(&N) -> C,
+{
+    timed(move || {
+        let mut stats = SearchStats { heuristic_calls: 1, pushed: 1, peak_open: 1, ..Default::default() };
+        let mut open = BinaryHeap::from([SearchNodeState { cost: heuristic(&start), node: start.clone() }]);
+        let mut previous = HashMap::from([(start, (None, C::default()))]);
+        let mut closed = HashSet::new();
+        while let Some(SearchNodeState { node, .. }) = stats.time(Phase::OpenList, || open.pop()) {
+            stats.popped += 1;
+            if !closed.insert(node.clone()) {
+                stats.duplicates += 1;
+                continue;
+            }
+            if goal(&node) {
+                return (Some(reconstruct_path_with_cost(previous, node)), stats);
+            }
+            stats.expanded += 1;
+            // The expansion is timed into its own statistics as its phases record into `stats`
+            let mut expansion = SearchStats::default();
+            expansion.time(Phase::Expand, || {
+                let children = stats.time(Phase::Successors, || expander(&node).into_iter().collect::<Vec<_>>());
+                stats.generated += children.len();
+                let g = previous[&node].1.clone();
+                for (child, cost) in children {
+                    let new_cost = g.clone() + cost;
+                    if previous.get(&child).is_none_or(|(_, c)| new_cost < *c) {
+                        previous.insert(child.clone(), (Some(node.clone()), new_cost.clone()));
+                        let h = stats.time(Phase::Heuristic, || heuristic(&child));
+                        stats.heuristic_calls += 1;
+                        stats.pushed += 1;
+                        stats.time(Phase::OpenList, || open.push(SearchNodeState { node: child, cost: new_cost + h }));
+                        stats.open_size(open.len());
+                    }
+                }
+            });
+            stats.phases.expand += expansion.phases.expand;
+        }
+        (None, stats)
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{SearchNodeState, astar};
+    use super::{SearchNodeState, astar, astar_with_reexpansion, astar_with_stats, astar_with_tie_breaking, Reexpansion};
+    use crate::search::openlist::{Fifo, HighG};
+    use crate::heuristics::distance::manhattan_distance;
     use crate::domains::bitpackedgrid::BitPackedGrid;
     use std::collections::BinaryHeap;
 
@@ -118,6 +297,78 @@ mod tests {
         assert_eq!(path.unwrap().0, vec![(0, 5), (1, 5), (2, 5), (3, 5), (4, 5), (5, 5), (6, 5), (6, 4), (7, 4), (7, 3), (7, 2), (7, 1), (7, 0)]);
     }
 
+    #[test]
+    fn test_astar_reexpansion() {
+        // The heuristic of 'a' is admissible but inconsistent, so 'c' is first
+        // expanded through the longer route via 'b'
+        let edges = |n: &char| match n {
+            's' => vec![('a', 1), ('b', 1)],
+            'a' => vec![('c', 1)],
+            'b' => vec![('c', 2)],
+            'c' => vec![('g', 4)],
+            _ => vec![],
+        };
+        let heuristic = |n: &char| if *n == 'a' { 4 } else { 0 };
+        let cost = |policy| astar_with_reexpansion(edges, 's', |n| *n == 'g', heuristic, policy).unwrap();
+        assert_eq!(cost(Reexpansion::Allow), (vec!['s', 'a', 'c', 'g'], 6));
+        assert_eq!(cost(Reexpansion::Pathmax), (vec!['s', 'a', 'c', 'g'], 6));
+        assert_eq!(cost(Reexpansion::Forbid), (vec!['s', 'b', 'c', 'g'], 7));
+    }
+
+    #[test]
+    fn test_astar_tie_breaking() {
+        let grid = BitPackedGrid::new_from_string("..........\n..........\n..........\n..........\n..........\n".to_string());
+        let goal = (9, 4);
+        let expansions = |high_g: bool| {
+            let mut expanded = 0;
+            let expander = |n: &(usize, usize)| {
+                expanded += 1;
+                grid.adjacent1(*n).filter(|(n, _)| grid.bounds_check(*n)).collect::<Vec<_>>()
+            };
+            let heuristic = |n: &(usize, usize)| manhattan_distance(*n, goal);
+            let (_, cost) = if high_g {
+                astar_with_tie_breaking(expander, (0, 0), |n| *n == goal, heuristic, HighG)
+            } else {
+                astar_with_tie_breaking(expander, (0, 0), |n| *n == goal, heuristic, Fifo::default())
+            }.unwrap();
+            assert_eq!(cost, 13);
+            expanded
+        };
+        assert_eq!(expansions(true), 13);
+        assert!(expansions(false) > 30);
+    }
+
+    #[test]
+    fn test_astar_stats() {
+        let grid = BitPackedGrid::new_from_string("....\n.@@.\n....\n".to_string());
+        let expander = |n: &(usize, usize)| grid.adjacent1(*n).filter(|(n, _)| grid.bounds_check(*n)).collect::<Vec<_>>();
+        let (result, stats) = astar_with_stats(expander, (0, 0), |n| *n == (3, 2), |n| manhattan_distance(*n, (3, 2)));
+        assert_eq!(result.unwrap().1, 5);
+        assert_eq!(stats.pushed, stats.heuristic_calls);
+        assert!(stats.popped <= stats.pushed && stats.expanded < stats.popped);
+        assert!(stats.generated >= stats.pushed - 1);
+        assert_eq!(stats.popped, stats.expanded + stats.duplicates + 1);
+        assert!(stats.peak_open > 1 && stats.peak_open <= stats.pushed);
+        assert!(stats.summary().starts_with(&format!("expanded {}", stats.expanded)));
+        #[cfg(feature = "profiling")]
+        assert!(stats.phases.expand >= stats.phases.heuristic);
+    }
+
+    #[test]
+    fn test_astar_custom_graph() {
+        // Any hashable node type is searched through its own successor function
+        let roads = std::collections::HashMap::from([
+            ("home", vec![("shop", 4), ("park", 1)]),
+            ("park", vec![("shop", 2), ("lake", 1)]),
+            ("lake", vec![("shop", 3)]),
+        ]);
+        let successors = |n: &&str| roads.get(n).cloned().unwrap_or_default();
+        let (result, stats) = astar_with_stats(successors, "home", |n| *n == "shop", |_| 0);
+        assert_eq!(result, Some((vec!["home", "park", "shop"], 3)));
+        assert_eq!(stats.expanded, 3);
+        assert_eq!(astar(successors, "lake", |n| *n == "home", |_| 0), None);
+    }
+
     #[test]
     fn test_search_node() {
         let mut open = BinaryHeap::new();
@@ -0,0 +1,103 @@
+//! # Batch Queries
+//! Answers many start and goal queries on one map, such as the tens of
+//! thousands of queries of an experiment. The domain, or a planner holding
+//! precomputed data such as `HpaStar` or `JumpPointSearch`, is shared
+//! immutably by every query, so it is built once and the queries run across
+//! threads with the `parallel` feature, or in turn on the calling thread
+//! without it. Results keep the order of the queries.
+
+use crate::util::preprocessing::{par_build, BuildStats};
+
+use super::domain::{search_with_stats, SearchDomain};
+
+/// The answer to a query of a batch
+/// ## Fields
+/// * `path` - The path from the start to the goal, `None` if there is none
+/// * `cost` - The cost of the path
+/// * `expanded` - The number of nodes expanded to answer the query
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchResult<N, C> {
+    pub path: Option<Vec<N>>,
+    pub cost: Option<C>,
+    pub expanded: usize,
+}
+
+impl<N, C> BatchResult<N, C> {
+    /// The result of a planner returning an optional path with its cost and
+    /// the number of nodes it expanded
+    pub fn new(result: Option<(Vec<N>, C)>, expanded: usize) -> Self {
+        let (path, cost) = result.map_or((None, None), |(path, cost)| (Some(path), Some(cost)));
+        BatchResult { path, cost, expanded }
+    }
+}
+
+/// The results of a batch in the order of its queries with the statistics of the batch
+pub type BatchResults<N, C> = (Vec<BatchResult<N, C>>, BuildStats);
+
+/// Answers a batch of queries on a domain with A-Star
+/// ## Arguments
+/// * `domain` - The domain shared by every query
+/// * `queries` - The (start, goal) of each query
+/// ## Returns
+/// The result of each query in order with the statistics of the batch
+pub fn solve_batch<D>(domain: &D, queries: &[(D::Node, D::Node)]) -> BatchResults<D::Node, D::Cost>
+where
+    D: SearchDomain + Sync,
+    D::Node: Sync + Send,
+    D::Cost: Send,
+{
+    solve_batch_with(queries, |start, goal| {
+        let (result, stats) = search_with_stats(domain, start.clone(), goal.clone());
+        (result, stats.expanded)
+    })
+}
+
+/// Answers a batch of queries with a planner
+/// ## Arguments
+/// * `queries` - The (start, goal) of each query
+/// * `planner` - Plans a query, returning an optional path with its cost and
+///   the number of nodes expanded
+/// ## Returns
+/// The result of each query in order with the statistics of the batch
+pub fn solve_batch_with<N, C, F>(queries: &[(N, N)], planner: F) -> BatchResults<N, C>
+where
+    N: Sync + Send,
+    C: Send,
+    F: Fn(&N, &N) -> (Option<(Vec<N>, C)>, usize) + Sync + Send,
+{
+    par_build(queries, |(start, goal)| {
+        let (result, expanded) = planner(start, goal);
+        BatchResult::new(result, expanded)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::{bitpackedgrid::BitPackedGrid, test_queries};
+    use crate::hierarchy::hpa::HpaStar;
+    use crate::search::jps::JumpPointSearch;
+
+    #[test]
+    fn test_solve_batch() {
+        let grid = BitPackedGrid::new_from_file("tests/map.map");
+        let free = grid.free_cells().collect::<Vec<_>>();
+        let queries = test_queries(&free, 24);
+        let (results, stats) = solve_batch(&grid, &queries);
+        assert_eq!(stats.items, queries.len());
+        for ((start, goal), result) in queries.iter().zip(&results) {
+            assert_eq!(*result, BatchResult::new(crate::search::domain::search(&grid, *start, *goal), result.expanded));
+            assert!(result.expanded > 0 || start == goal || result.path.is_none());
+        }
+
+        let hpa = HpaStar::new(&grid, 8);
+        let (answers, _) = solve_batch_with(&queries, |s, g| hpa.query(&grid, *s, *g, true));
+        for (answer, result) in answers.iter().zip(&results) {
+            assert_eq!(answer.path.is_some(), result.path.is_some());
+            assert!(answer.cost >= result.cost);
+        }
+        let jps = JumpPointSearch::new(&grid);
+        let (jumps, _) = solve_batch_with(&queries, |s, g| jps.query(*s, *g));
+        assert_eq!(jumps.iter().filter(|r| r.path.is_some()).count(), results.iter().filter(|r| r.path.is_some()).count());
+    }
+}
@@ -0,0 +1,232 @@
+//! # Bidirectional Search
+//! Searches growing one frontier forwards from the start and another
+//! backwards from the goal, expanding whichever frontier has the lowest
+//! priority so that they meet in the middle. On open maps each frontier only
+//! reaches about half the distance between the endpoints, which roughly halves
+//! the expansions of a unidirectional search. The best path through a node
+//! reached by both frontiers is kept, and the search stops once no cheaper
+//! path can exist:
+//! * Bidirectional Dijkstra stops once the smallest costs of the two open
+//!   lists sum to at least the cost of the best path
+//! * Bidirectional A-Star stops once the smallest priority of either open list
+//!   is at least the cost of the best path, as each priority is a lower bound
+//!   on the cost of every path through its frontier
+//!
+//! The backward search needs the predecessors of a node, which on undirected
+//! grids are its successors and on directed graphs are the edges of
+//! `AdjacencyList::reversed`.
+
+use std::{collections::{BinaryHeap, HashMap}, hash::Hash, ops::Add};
+
+use super::{reconstruct_path_with_cost, stats::{timed, SearchStats}, SearchNodeState};
+
+/// Bidirectional Dijkstra Search
+/// ## Arguments
+/// * `forward` - A function that returns the successors of a node with their costs
+/// * `backward` - A function that returns the predecessors of a node with the
+///   costs of the edges from them
+/// * `start` - The start node
+/// * `goal` - The goal node
+/// ## Returns
+/// An optional path from the start to the goal with its cost, and the
+/// statistics of both searches
+pub fn bidirectional_dijkstra<F, B, I, J, C, N>(
+    forward: F,
+    backward: B,
+    start: N,
+    goal: N,
+) -> (Option<(Vec<N>, C)>, SearchStats)
+where
+    F: FnMut(&N) -> I,
+    B: FnMut(&N) -> J,
+    I: IntoIterator<Item = (N, C)>,
+    J: IntoIterator<Item = (N, C)>,
+    C: Ord + Default + Clone + Add<Output = C>,
+    N: Hash + Clone + Eq,
+{
+    timed(|| bidirectional(forward, backward, start, goal, |_| C::default(), |_| C::default(), |f, b, best| f + b >= best))
+}
+
+/// Bidirectional A-Star Search
+/// ## Arguments
+/// * `forward` - A function that returns the successors of a node with their costs
+/// * `backward` - A function that returns the predecessors of a node with the
+///   costs of the edges from them
+/// * `start` - The start node
+/// * `goal` - The goal node
+/// * `to_goal` - An admissible estimate of the cost from a node to the goal
+/// * `to_start` - An admissible estimate of the cost from the start to a node
+/// ## Returns
+/// An optional path from the start to the goal with its cost, and the
+/// statistics of both searches
+pub fn bidirectional_astar<F, B, I, J, C, N, HF, HB>(
+    forward: F,
+    backward: B,
+    start: N,
+    goal: N,
+    to_goal: HF,
+    to_start: HB,
+) -> (Option<(Vec<N>, C)>, SearchStats)
+where
+    F: FnMut(&N) -> I,
+    B: FnMut(&N) -> J,
+    I: IntoIterator<Item = (N, C)>,
+    J: IntoIterator<Item = (N, C)>,
+    C: Ord + Default + Clone + Add<Output = C>,
+    N: Hash + Clone + Eq,
+    HF: Fn(&N) -> C,
+    HB: Fn(&N) -> C,
+{
+    timed(|| bidirectional(forward, backward, start, goal, to_goal, to_start, |f, b, best| f >= best || b >= best))
+}
+
+/// One frontier of a bidirectional search
+struct Frontier<N: Eq, C: Ord> {
+    open: BinaryHeap<SearchNodeState<(N, C), C>>,
+    previous: HashMap<N, (Option<N>, C)>,
+}
+
+impl<N: Hash + Clone + Eq, C: Ord + Default + Clone + Add<Output = C>> Frontier<N, C> {
+    fn new(root: N, priority: C) -> Self {
+        let open = BinaryHeap::from([SearchNodeState { node: (root.clone(), C::default()), cost: priority }]);
+        Frontier { open, previous: HashMap::from([(root, (None, C::default()))]) }
+    }
+
+    /// The smallest priority on the open list
+    fn top(&self) -> Option<C> {
+        self.open.peek().map(|state| state.cost.clone())
+    }
+
+    /// The cost of the best path found to a node
+    fn cost(&self, node: &N) -> Option<C> {
+        self.previous.get(node).map(|(_, c)| c.clone())
+    }
+}
+
+/// Grows the cheaper frontier a node at a time until the stopping criterion
+/// holds for the smallest priorities of both open lists and the best path
+fn bidirectional<F, B, I, J, C, N, HF, HB>(
+    mut forward: F,
+    mut backward: B,
+    start: N,
+    goal: N,
+    to_goal: HF,
+    to_start: HB,
+    stop: impl Fn(C, C, C) -> bool,
+) -> (Option<(Vec<N>, C)>, SearchStats)
+where
+    F: FnMut(&N) -> I,
+    B: FnMut(&N) -> J,
+    I: IntoIterator<Item = (N, C)>,
+    J: IntoIterator<Item = (N, C)>,
+    C: Ord + Default + Clone + Add<Output = C>,
+    N: Hash + Clone + Eq,
+    HF: Fn(&N) -> C,
+    HB: Fn(&N) -> C,
+{
+    let mut stats = SearchStats { heuristic_calls: 2, pushed: 2, peak_open: 2, ..Default::default() };
+    let mut frontiers = [Frontier::new(start.clone(), to_goal(&start)), Frontier::new(goal.clone(), to_start(&goal))];
+    // The best path found, as the node where the frontiers meet and its cost
+    let mut best: Option<(N, C)> = (start == goal).then(|| (start.clone(), C::default()));
+    while let (Some(f), Some(b)) = (frontiers[0].top(), frontiers[1].top()) {
+        if best.as_ref().is_some_and(|(_, cost)| stop(f.clone(), b.clone(), cost.clone())) {
+            break;
+        }
+        let side = if f <= b { 0 } else { 1 };
+        let Some(SearchNodeState { node: (node, g), .. }) = frontiers[side].open.pop() else { break };
+        stats.popped += 1;
+        if frontiers[side].cost(&node).is_some_and(|c| c < g) {
+            stats.duplicates += 1;
+            continue;
+        }
+        stats.expanded += 1;
+        let children = if side == 0 {
+            forward(&node).into_iter().collect::<Vec<_>>()
+        } else {
+            backward(&node).into_iter().collect::<Vec<_>>()
+        };
+        stats.generated += children.len();
+        for (child, cost) in children {
+            let new_cost = g.clone() + cost;
+            if frontiers[side].cost(&child).is_some_and(|c| c <= new_cost) {
+                continue;
+            }
+            frontiers[side].previous.insert(child.clone(), (Some(node.clone()), new_cost.clone()));
+            if let Some(other) = frontiers[1 - side].cost(&child) {
+                let through = new_cost.clone() + other;
+                if best.as_ref().is_none_or(|(_, c)| through < *c) {
+                    best = Some((child.clone(), through));
+                }
+            }
+            let h = if side == 0 { to_goal(&child) } else { to_start(&child) };
+            stats.heuristic_calls += 1;
+            stats.pushed += 1;
+            frontiers[side].open.push(SearchNodeState { node: (child, new_cost.clone()), cost: new_cost + h });
+            stats.open_size(frontiers[0].open.len() + frontiers[1].open.len());
+        }
+    }
+    let Some((meet, cost)) = best else { return (None, stats) };
+    let [forwards, backwards] = frontiers;
+    let (mut path, _) = reconstruct_path_with_cost(forwards.previous, meet.clone());
+    let (mut rest, _) = reconstruct_path_with_cost(backwards.previous, meet);
+    rest.pop();
+    rest.reverse();
+    path.extend(rest);
+    (Some((path, cost)), stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::{bitpackedgrid::BitPackedGrid, graph::AdjacencyList, test_queries};
+    use crate::heuristics::distance::manhattan_distance;
+    use crate::search::astar::{astar, astar_with_stats};
+
+    #[test]
+    fn test_bidirectional_grid() {
+        let grid = BitPackedGrid::new_from_file("tests/map.map");
+        let free = grid.free_cells().collect::<Vec<_>>();
+        let expander = |n: &(usize, usize)| grid.adjacent1(*n);
+        for (start, goal) in test_queries(&free, 12) {
+            let expected = astar(expander, start, |n| *n == goal, |n| manhattan_distance(*n, goal)).map(|(_, c)| c);
+            let (dijkstra, _) = bidirectional_dijkstra(expander, expander, start, goal);
+            let dijkstra_found = dijkstra.is_some();
+            let (astar, _) = bidirectional_astar(expander, expander, start, goal, |n| manhattan_distance(*n, goal), |n| manhattan_distance(*n, start));
+            for (path, cost) in [dijkstra, astar].into_iter().flatten() {
+                assert_eq!((path[0], *path.last().unwrap(), path.len()), (start, goal, cost + 1));
+                assert_eq!(Some(cost), expected);
+                assert!(path.windows(2).all(|w| manhattan_distance(w[0], w[1]) == 1 && grid.get_bit_value(w[1])));
+            }
+            assert_eq!(expected.is_some(), dijkstra_found);
+        }
+        // On an open map the frontiers meet halfway
+        let open = BitPackedGrid::new_from_string(format!("{}\n", ".".repeat(40)).repeat(40));
+        let expander = |n: &(usize, usize)| open.adjacent1(*n);
+        let (_, unidirectional) = astar_with_stats(expander, (0, 20), |n| *n == (39, 20), |_| 0);
+        let (result, stats) = bidirectional_dijkstra(expander, expander, (0, 20), (39, 20));
+        assert_eq!(result.unwrap().1, 39);
+        assert!(stats.expanded * 3 < unidirectional.expanded * 2);
+    }
+
+    #[test]
+    fn test_bidirectional_graph() {
+        let mut graph = AdjacencyList::new();
+        let nodes = (0..5).map(|i| graph.add_node((i as i64, 0))).collect::<Vec<_>>();
+        for (from, to, w) in [(0, 1, 4), (1, 4, 4), (0, 2, 1), (2, 3, 1), (3, 4, 1), (4, 0, 1)] {
+            graph.add_edge(nodes[from], nodes[to], w);
+        }
+        let reversed = graph.reversed();
+        let forward = |n: &usize| graph.edges(*n).to_vec();
+        let backward = |n: &usize| reversed.edges(*n).to_vec();
+        assert_eq!(bidirectional_dijkstra(forward, backward, 0, 4).0, Some((vec![0, 2, 3, 4], 3)));
+        assert_eq!(bidirectional_dijkstra(forward, backward, 4, 3).0, Some((vec![4, 0, 2, 3], 3)));
+        assert_eq!(bidirectional_astar(forward, backward, 1, 1, |_| 0, |_| 0).0, Some((vec![1], 0)));
+        let (result, _) = bidirectional_astar(forward, backward, 1, 2, |_| 0, |_| 0);
+        assert_eq!(result, Some((vec![1, 4, 0, 2], 6)));
+        let mut isolated = graph.clone();
+        isolated.add_node((9, 9));
+        let reversed = isolated.reversed();
+        let result = bidirectional_dijkstra(|n: &usize| isolated.edges(*n).to_vec(), |n: &usize| reversed.edges(*n).to_vec(), 0, 5).0;
+        assert_eq!(result, None);
+    }
+}
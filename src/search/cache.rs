@@ -0,0 +1,280 @@
+//! # Path Caching
+//! A cache of query results keyed by the version of the map and the start and
+//! goal, for servers where many agents request the same routes. Results are
+//! evicted least recently used first once the cache is full, and bumping the
+//! map version, such as the epoch of a shared grid or the version of a
+//! bitpacked grid, makes stale results unreachable without clearing the cache.
+//!
+//! On maps where every edge can be traversed both ways the cache can answer a
+//! query by reversing the cached path of the reverse query. Queries sharing a
+//! goal can also be answered from a backward Dijkstra tree grown once from the
+//! goal, after which each start only has to follow the tree.
+
+use std::{collections::{BTreeMap, BinaryHeap, HashMap, VecDeque}, hash::Hash, ops::Add};
+
+use super::SearchNodeState;
+
+/// The result of a query, `None` when there is no path
+pub type QueryResult<N, C> = Option<(Vec<N>, C)>;
+
+/// A query as (map version, start, goal)
+type Key<N> = (usize, N, N);
+
+/// A backward tree mapping each node to its next node towards the goal and its distance
+type Tree<N, C> = HashMap<N, (Option<N>, C)>;
+
+/// A least recently used cache of query results
+/// ## Fields
+/// * `capacity` - The maximum number of cached results
+/// * `hits` - The number of queries answered from the cache
+/// * `misses` - The number of queries that had to be planned
+/// * `reversible` - Whether paths are valid in reverse, allowing a query to be
+///   answered from the cached result of its reverse
+pub struct PathCache<N, C> {
+    pub capacity: usize,
+    pub hits: usize,
+    pub misses: usize,
+    pub reversible: bool,
+    entries: HashMap<Key<N>, (QueryResult<N, C>, u64)>,
+    recency: BTreeMap<u64, Key<N>>,
+    clock: u64,
+    trees: HashMap<(usize, N), Tree<N, C>>,
+    tree_order: VecDeque<(usize, N)>,
+}
+
+impl<N: Hash + Eq + Clone, C: Clone> PathCache<N, C> {
+    /// Creates an empty cache holding at most `capacity` results
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "Cache must hold a result");
+        PathCache {
+            capacity,
+            hits: 0,
+            misses: 0,
+            reversible: false,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+            trees: HashMap::new(),
+            tree_order: VecDeque::new(),
+        }
+    }
+
+    /// Sets whether queries can be answered by reversing the path of the reverse query
+    pub fn reversible(mut self, reversible: bool) -> Self {
+        self.reversible = reversible;
+        self
+    }
+
+    /// The number of cached results
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks if no results are cached
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Marks a cached result as the most recently used
+    fn touch(&mut self, key: &Key<N>) {
+        if let Some((_, used)) = self.entries.get_mut(key) {
+            self.recency.remove(used);
+            self.clock += 1;
+            *used = self.clock;
+            self.recency.insert(self.clock, key.clone());
+        }
+    }
+
+    /// The cached result of a query, reversing the result of the reverse query
+    /// if the cache is reversible, without counting a hit or miss
+    pub fn get(&mut self, version: usize, start: &N, goal: &N) -> Option<QueryResult<N, C>> {
+        let key = (version, start.clone(), goal.clone());
+        self.touch(&key);
+        if let Some((result, _)) = self.entries.get(&key) {
+            return Some(result.clone());
+        }
+        if !self.reversible {
+            return None;
+        }
+        let key = (version, goal.clone(), start.clone());
+        self.touch(&key);
+        let (result, _) = self.entries.get(&key)?;
+        Some(result.clone().map(|(mut path, cost)| {
+            path.reverse();
+            (path, cost)
+        }))
+    }
+
+    /// Caches the result of a query, evicting the least recently used result if full
+    pub fn insert(&mut self, version: usize, start: N, goal: N, result: QueryResult<N, C>) {
+        let key = (version, start, goal);
+        if let Some((_, used)) = self.entries.remove(&key) {
+            self.recency.remove(&used);
+        } else if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.recency.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        self.recency.insert(self.clock, key.clone());
+        self.entries.insert(key, (result, self.clock));
+    }
+
+    /// Answers a query from the cache, planning and caching it on a miss
+    /// ## Arguments
+    /// * `version` - The version of the map the query is planned on
+    /// * `plan` - Plans the query from the start to the goal
+    pub fn get_or_plan(
+        &mut self,
+        version: usize,
+        start: N,
+        goal: N,
+        plan: impl FnOnce(&N, &N) -> QueryResult<N, C>,
+    ) -> QueryResult<N, C> {
+        if let Some(result) = self.get(version, &start, &goal) {
+            self.hits += 1;
+            return result;
+        }
+        self.misses += 1;
+        let result = plan(&start, &goal);
+        self.insert(version, start, goal, result.clone());
+        result
+    }
+
+    /// Answers a batch of queries, planning each distinct query once
+    pub fn batch(
+        &mut self,
+        version: usize,
+        queries: &[(N, N)],
+        mut plan: impl FnMut(&N, &N) -> QueryResult<N, C>,
+    ) -> Vec<QueryResult<N, C>> {
+        queries.iter()
+            .map(|(start, goal)| self.get_or_plan(version, start.clone(), goal.clone(), &mut plan))
+            .collect()
+    }
+
+    /// Removes every result and backward tree not planned on a version of the map
+    pub fn retain_version(&mut self, version: usize) {
+        self.entries.retain(|(v, _, _), _| *v == version);
+        self.recency.retain(|_, (v, _, _)| *v == version);
+        self.trees.retain(|(v, _), _| *v == version);
+        self.tree_order.retain(|(v, _)| *v == version);
+    }
+
+    /// The number of cached backward trees
+    pub fn tree_count(&self) -> usize {
+        self.trees.len()
+    }
+}
+
+impl<N, C> PathCache<N, C>
+where
+    N: Hash + Eq + Clone,
+    C: Ord + Default + Clone + Add<Output = C>,
+{
+    /// Answers a query from a backward Dijkstra tree of the goal, growing the
+    /// tree on the first query to the goal. At most `capacity` trees are kept,
+    /// discarding the oldest first.
+    /// ## Arguments
+    /// * `version` - The version of the map the query is planned on
+    /// * `predecessors` - Returns the nodes with an edge into a given node and
+    ///   the cost of the edge
+    pub fn get_or_plan_to_goal<E, I>(&mut self, version: usize, start: N, goal: N, predecessors: E) -> QueryResult<N, C>
+    where
+        E: FnMut(&N) -> I,
+        I: IntoIterator<Item = (N, C)>,
+    {
+        let key = (version, goal);
+        if self.trees.contains_key(&key) {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+            if self.trees.len() >= self.capacity {
+                if let Some(oldest) = self.tree_order.pop_front() {
+                    self.trees.remove(&oldest);
+                }
+            }
+            self.trees.insert(key.clone(), backward_tree(key.1.clone(), predecessors));
+            self.tree_order.push_back(key.clone());
+        }
+        let tree = &self.trees[&key];
+        let cost = tree.get(&start)?.1.clone();
+        let mut path = vec![start];
+        while let Some((Some(next), _)) = path.last().and_then(|n| tree.get(n)) {
+            path.push(next.clone());
+        }
+        Some((path, cost))
+    }
+}
+
+/// Grows a Dijkstra tree backwards from a goal over every node that can reach it
+fn backward_tree<N, C, E, I>(goal: N, mut predecessors: E) -> Tree<N, C>
+where
+    N: Hash + Eq + Clone,
+    C: Ord + Default + Clone + Add<Output = C>,
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+{
+    let mut tree = HashMap::from([(goal.clone(), (None, C::default()))]);
+    let mut queue = BinaryHeap::from([SearchNodeState { node: goal, cost: C::default() }]);
+    while let Some(SearchNodeState { node, cost }) = queue.pop() {
+        if tree.get(&node).is_some_and(|(_, c)| *c < cost) {
+            continue;
+        }
+        for (prev, edge) in predecessors(&node) {
+            let distance = cost.clone() + edge;
+            if tree.get(&prev).is_none_or(|(_, c)| distance < *c) {
+                tree.insert(prev.clone(), (Some(node.clone()), distance.clone()));
+                queue.push(SearchNodeState { node: prev, cost: distance });
+            }
+        }
+    }
+    tree
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_cache() {
+        let mut cache = PathCache::new(2);
+        let mut planned = 0;
+        let mut plan = |s: &usize, g: &usize| {
+            planned += 1;
+            Some(((*s..=*g).collect::<Vec<_>>(), g - s))
+        };
+        let results = cache.batch(0, &[(0, 2), (1, 3), (0, 2), (0, 2)], &mut plan);
+        assert_eq!(results[3], Some((vec![0, 1, 2], 2)));
+        assert_eq!((cache.hits, cache.misses), (2, 2));
+
+        cache.get_or_plan(0, 2, 4, &mut plan);
+        assert_eq!(cache.get(0, &1, &3), None);
+        assert!(cache.get(0, &0, &2).is_some());
+        cache.get_or_plan(1, 0, 2, &mut plan);
+        assert_eq!(cache.get(0, &2, &4), None);
+        cache.retain_version(1);
+        assert_eq!(cache.len(), 1);
+        drop(cache);
+        assert_eq!(planned, 4);
+    }
+
+    #[test]
+    fn test_path_cache_reuse() {
+        let mut cache = PathCache::new(1).reversible(true);
+        cache.get_or_plan(0, 0, 3, |_, _| Some((vec![0, 1, 2, 3], 3)));
+        assert_eq!(cache.get_or_plan(0, 3, 0, |_, _| None), Some((vec![3, 2, 1, 0], 3)));
+        assert_eq!(cache.hits, 1);
+
+        // A line 0 - 1 - 2 - 3 where moving right costs 2 and moving left costs 1
+        let predecessors = |n: &i32| {
+            [(n - 1, 2), (n + 1, 1)].into_iter().filter(|(p, _)| (0..4).contains(p)).collect::<Vec<_>>()
+        };
+        assert_eq!(cache.get_or_plan_to_goal(0, 0, 3, predecessors), Some((vec![0, 1, 2, 3], 6)));
+        assert_eq!(cache.get_or_plan_to_goal(0, 2, 3, predecessors), Some((vec![2, 3], 2)));
+        assert_eq!(cache.get_or_plan_to_goal(0, 3, 0, predecessors), Some((vec![3, 2, 1, 0], 3)));
+        assert_eq!(cache.get_or_plan_to_goal(0, 7, 0, predecessors), None);
+        assert_eq!(cache.tree_count(), 1);
+        assert_eq!((cache.hits, cache.misses), (3, 3));
+    }
+}
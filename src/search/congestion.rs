@@ -0,0 +1,123 @@
+//! # Congestion-Aware Planning
+//! Planning many queries on a shared grid map where entering a cell costs more
+//! the more traffic already uses it. The load of a cell is its usage by the
+//! other queries plus any background load, such as a traffic record of earlier
+//! plans or live reservations. Queries are replanned in turn against the load
+//! of the others until no path changes, an equilibrium where no query can
+//! lower its own congested cost alone.
+
+use crate::analysis::traffic::Traffic;
+use crate::domains::{bitpackedgrid::BitPackedGrid, grid::Grid};
+use crate::heuristics::distance::manhattan_distance;
+
+use super::astar::astar;
+
+/// The cost of a move into an unloaded cell
+const MOVE_COST: usize = 10;
+
+/// A query as its (start, goal)
+type Query = ((usize, usize), (usize, usize));
+
+/// An assignment of paths to queries under congestion
+/// ## Fields
+/// * `paths` - The path of each query
+/// * `traffic` - The usage of every cell by the paths
+/// * `rounds` - The number of rounds of replanning
+/// * `converged` - Whether the last round changed no path
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assignment {
+    pub paths: Vec<Vec<(usize, usize)>>,
+    pub traffic: Traffic,
+    pub rounds: usize,
+    pub converged: bool,
+}
+
+/// Plans a path where entering a cell costs `MOVE_COST + penalty * load`
+pub fn congested_plan(
+    grid: &BitPackedGrid,
+    load: &Grid<usize>,
+    start: (usize, usize),
+    goal: (usize, usize),
+    penalty: usize,
+) -> Option<(Vec<(usize, usize)>, usize)> {
+    astar(
+        |n| grid.adjacent(*n, false)
+            .filter(|n| grid.bounds_check(*n))
+            .map(|n| (n, MOVE_COST + penalty * load[n]))
+            .collect::<Vec<_>>(),
+        start,
+        |n| *n == goal,
+        |n| MOVE_COST * manhattan_distance(*n, goal),
+    )
+}
+
+/// Iteratively plans many queries against the congestion of each other
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `queries` - The (start, goal) of each query
+/// * `background` - Load on the cells from outside the queries, aligned with the grid
+/// * `penalty` - The extra cost of each unit of load on an entered cell
+/// * `max_rounds` - The most rounds of replanning every query
+/// ## Returns
+/// The assignment, or `None` if a query has no path
+pub fn congestion_assignment(
+    grid: &BitPackedGrid,
+    queries: &[Query],
+    background: Option<&Grid<usize>>,
+    penalty: usize,
+    max_rounds: usize,
+) -> Option<Assignment> {
+    let mut load = background.cloned().unwrap_or_else(|| Grid::new_aligned(grid, 0));
+    assert!(load.is_aligned(grid), "Background load must match the size of the grid");
+    let mut paths = Vec::with_capacity(queries.len());
+    for (start, goal) in queries {
+        let (path, _) = congested_plan(grid, &load, *start, *goal, penalty)?;
+        path.iter().for_each(|n| load[*n] += 1);
+        paths.push(path);
+    }
+    let (mut rounds, mut converged) = (0, false);
+    while rounds < max_rounds && !converged {
+        rounds += 1;
+        converged = true;
+        for (path, (start, goal)) in paths.iter_mut().zip(queries) {
+            path.iter().for_each(|n| load[*n] -= 1);
+            let (replanned, _) = congested_plan(grid, &load, *start, *goal, penalty)?;
+            let cost = |p: &[(usize, usize)]| p.iter().skip(1).map(|n| MOVE_COST + penalty * load[*n]).sum::<usize>();
+            // Only switch to a strictly cheaper path so that ties do not oscillate
+            if replanned != *path && cost(&replanned) < cost(path) {
+                *path = replanned;
+                converged = false;
+            }
+            path.iter().for_each(|n| load[*n] += 1);
+        }
+    }
+    let mut traffic = Traffic::new(grid);
+    traffic.add_paths(&paths);
+    Some(Assignment { paths, traffic, rounds, converged })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_congestion_assignment() {
+        let grid = BitPackedGrid::new_from_string(".....\n.@@@.\n.....\n".to_string());
+        let queries = vec![((0, 1), (4, 1)); 4];
+        let free = congestion_assignment(&grid, &queries, None, 0, 10).unwrap();
+        assert!(free.converged);
+        assert!(free.paths.windows(2).all(|p| p[0] == p[1]));
+
+        let assignment = congestion_assignment(&grid, &queries, None, 10, 10).unwrap();
+        assert!(assignment.converged);
+        assert!(assignment.rounds <= 10);
+        let top = assignment.paths.iter().filter(|p| p.contains(&(2, 0))).count();
+        assert_eq!(top, 2);
+        assert!(assignment.traffic.congestion_score() < free.traffic.congestion_score());
+
+        let mut background = Grid::new_aligned(&grid, 0);
+        background[(2, 0)] = 100;
+        let avoided = congestion_assignment(&grid, &queries[..1], Some(&background), 10, 10).unwrap();
+        assert!(avoided.paths[0].contains(&(2, 2)));
+    }
+}
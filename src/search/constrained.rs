@@ -0,0 +1,159 @@
+//! # Constrained Search
+//! A-Star with per-query constraints enforced during the search rather than by
+//! filtering finished paths. Queries can forbid nodes and edges, require an
+//! ordered list of waypoints to be visited, and bound the number of moves of
+//! the path. The search runs over states of a node, the number of waypoints
+//! visited so far and, when the length is bounded, the number of moves taken.
+
+use std::{collections::HashSet, hash::Hash, ops::Add};
+
+use super::astar::astar;
+
+/// The constraints of a query
+/// ## Fields
+/// * `forbidden_nodes` - Nodes the path may not visit
+/// * `forbidden_edges` - Moves the path may not make, in either direction
+/// * `waypoints` - Nodes the path must visit in order before reaching the goal
+/// * `max_length` - The maximum number of moves of the path
+#[derive(Clone, Debug)]
+pub struct Constraints<N> {
+    pub forbidden_nodes: HashSet<N>,
+    pub forbidden_edges: HashSet<(N, N)>,
+    pub waypoints: Vec<N>,
+    pub max_length: Option<usize>,
+}
+
+impl<N> Default for Constraints<N> {
+    fn default() -> Self {
+        Constraints { forbidden_nodes: HashSet::new(), forbidden_edges: HashSet::new(), waypoints: Vec::new(), max_length: None }
+    }
+}
+
+impl<N: Hash + Eq + Clone> Constraints<N> {
+    /// Constraints that allow every path
+    pub fn new() -> Self {
+        Constraints::default()
+    }
+
+    /// Forbids visiting a node
+    pub fn forbid(mut self, node: N) -> Self {
+        self.forbidden_nodes.insert(node);
+        self
+    }
+
+    /// Forbids moving between two nodes in either direction
+    pub fn forbid_edge(mut self, a: N, b: N) -> Self {
+        self.forbidden_edges.insert((a.clone(), b.clone()));
+        self.forbidden_edges.insert((b, a));
+        self
+    }
+
+    /// Requires visiting a node after the previously required waypoints
+    pub fn require(mut self, waypoint: N) -> Self {
+        self.waypoints.push(waypoint);
+        self
+    }
+
+    /// Bounds the number of moves of the path
+    pub fn max_length(mut self, length: usize) -> Self {
+        self.max_length = Some(length);
+        self
+    }
+
+    /// Checks if a move is allowed
+    fn allows(&self, from: &N, to: &N) -> bool {
+        !self.forbidden_nodes.contains(to) && !self.forbidden_edges.contains(&(from.clone(), to.clone()))
+    }
+
+    /// The number of waypoints visited after entering a node
+    fn visited(&self, visited: usize, node: &N) -> usize {
+        visited + (self.waypoints.get(visited) == Some(node)) as usize
+    }
+}
+
+/// A-Star search subject to constraints
+/// ## Arguments
+/// * `expander` - A function that returns the adjacent nodes of a node with the cost of each move
+/// * `start` - The start node
+/// * `goal` - The goal node
+/// * `heuristic` - An admissible estimate of the cost between two nodes
+/// * `constraints` - The constraints of the query
+/// ## Returns
+/// An optional path satisfying the constraints with its cost
+pub fn constrained_astar<E, I, C, N, H>(
+    mut expander: E,
+    start: N,
+    goal: N,
+    heuristic: H,
+    constraints: &Constraints<N>,
+) -> Option<(Vec<N>, C)>
+where
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+    C: Ord + Default + Clone + Add<Output = C>,
+    N: Hash + Clone + Eq,
+    H: Fn(&N, &N) -> C,
+{
+    if constraints.forbidden_nodes.contains(&start) {
+        return None;
+    }
+    let waypoints = &constraints.waypoints;
+    // The estimate from a node through the remaining waypoints to the goal
+    let estimate = |node: &N, visited: usize| {
+        let mut targets = waypoints[visited..].iter().chain(std::iter::once(&goal));
+        let first = targets.next().unwrap();
+        let mut cost = heuristic(node, first);
+        let mut previous = first;
+        for target in targets {
+            cost = cost + heuristic(previous, target);
+            previous = target;
+        }
+        cost
+    };
+    let bounded = constraints.max_length.is_some();
+    let limit = constraints.max_length.unwrap_or(usize::MAX);
+    let (states, cost) = astar(
+        |(node, visited, length): &(N, usize, usize)| {
+            let (visited, length) = (*visited, *length);
+            expander(node)
+                .into_iter()
+                .filter(|(child, _)| constraints.allows(node, child) && length < limit)
+                .map(|(child, cost)| {
+                    let visited = constraints.visited(visited, &child);
+                    ((child, visited, if bounded { length + 1 } else { 0 }), cost)
+                })
+                .collect::<Vec<_>>()
+        },
+        (start.clone(), constraints.visited(0, &start), 0),
+        |(node, visited, _)| *node == goal && *visited == waypoints.len(),
+        |(node, visited, _)| estimate(node, *visited),
+    )?;
+    Some((states.into_iter().map(|(node, _, _)| node).collect(), cost))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::bitpackedgrid::BitPackedGrid;
+    use crate::heuristics::distance::manhattan_distance;
+
+    #[test]
+    fn test_constrained_astar() {
+        let grid = BitPackedGrid::new_from_string("....\n.@@.\n....\n".to_string());
+        let expander = |n: &(usize, usize)| grid.adjacent1(*n).filter(|(n, _)| grid.bounds_check(*n));
+        let heuristic = |a: &(usize, usize), b: &(usize, usize)| manhattan_distance(*a, *b);
+        let search = |constraints| constrained_astar(expander, (0, 1), (3, 1), heuristic, &constraints);
+
+        assert_eq!(search(Constraints::new()).unwrap().1, 5);
+        let (path, cost) = search(Constraints::new().forbid((1, 0))).unwrap();
+        assert_eq!(cost, 5);
+        assert!(path.contains(&(1, 2)));
+        assert_eq!(search(Constraints::new().forbid_edge((0, 0), (1, 0)).forbid_edge((0, 2), (0, 1))), None);
+
+        let (path, cost) = search(Constraints::new().require((3, 2)).require((0, 0))).unwrap();
+        assert_eq!(cost, 4 + 5 + 4);
+        assert_eq!(path.iter().position(|n| *n == (3, 2)), Some(4));
+        assert_eq!(search(Constraints::new().require((3, 0)).max_length(5)).unwrap().1, 5);
+        assert_eq!(search(Constraints::new().max_length(4)), None);
+    }
+}
@@ -0,0 +1,117 @@
+//! # Danger-Aware Planning
+//! Planning on grids with a per-cell danger layer, such as threat exposure or
+//! proximity to hazards. A path minimises a weighted sum of its length and the
+//! danger accumulated over the cells it enters. Sweeping the weights traces
+//! the Pareto frontier between the two objectives, giving the paths where
+//! length cannot be reduced without accepting more danger.
+
+use crate::domains::{bitpackedgrid::BitPackedGrid, grid::Grid};
+use crate::heuristics::distance::manhattan_distance;
+
+use super::astar::astar;
+
+/// A path scored by both objectives
+/// ## Fields
+/// * `path` - The cells of the path from the start to the goal
+/// * `length` - The number of moves of the path
+/// * `danger` - The total danger of every cell entered by the path
+#[derive(Clone, Debug, PartialEq)]
+pub struct DangerPath {
+    pub path: Vec<(usize, usize)>,
+    pub length: usize,
+    pub danger: usize,
+}
+
+impl DangerPath {
+    /// Checks if this path is no worse in both objectives and better in one
+    pub fn dominates(&self, other: &DangerPath) -> bool {
+        self.length <= other.length && self.danger <= other.danger
+            && (self.length < other.length || self.danger < other.danger)
+    }
+}
+
+/// Plans a path minimising `length_weight * length + danger_weight * danger`
+/// ## Arguments
+/// * `grid` - The occupancy grid
+/// * `danger` - The danger of entering each cell, aligned with the grid
+/// * `start` - The start cell
+/// * `goal` - The goal cell
+/// * `length_weight` - The weight of each move
+/// * `danger_weight` - The weight of each unit of danger
+pub fn danger_plan(
+    grid: &BitPackedGrid,
+    danger: &Grid<usize>,
+    start: (usize, usize),
+    goal: (usize, usize),
+    length_weight: usize,
+    danger_weight: usize,
+) -> Option<DangerPath> {
+    assert!(danger.is_aligned(grid), "Danger layer must match the size of the grid");
+    let (path, _) = astar(
+        |n| grid.adjacent(*n, false)
+            .filter(|n| grid.bounds_check(*n))
+            .map(|n| (n, length_weight + danger_weight * danger[n]))
+            .collect::<Vec<_>>(),
+        start,
+        |n| *n == goal,
+        |n| length_weight * manhattan_distance(*n, goal),
+    )?;
+    let danger = path.iter().skip(1).map(|n| danger[*n]).sum();
+    Some(DangerPath { length: path.len() - 1, path, danger })
+}
+
+/// The Pareto frontier between length and danger found by sweeping the weights
+/// from length only to danger only
+/// ## Arguments
+/// * `steps` - The number of weightings tried is `steps + 1`
+/// ## Returns
+/// The non-dominated paths sorted by increasing length
+pub fn danger_frontier(
+    grid: &BitPackedGrid,
+    danger: &Grid<usize>,
+    start: (usize, usize),
+    goal: (usize, usize),
+    steps: usize,
+) -> Vec<DangerPath> {
+    // Each extreme is refined by a small weight on the other objective so that
+    // ties are broken towards a non-dominated path
+    let mut weights = vec![(steps * (steps + 1) + 1, 1), (1, steps * (steps + 1) + 1)];
+    weights.extend((1..steps).map(|i| (steps - i, i)));
+    let mut frontier: Vec<DangerPath> = Vec::new();
+    for (length_weight, danger_weight) in weights {
+        let Some(candidate) = danger_plan(grid, danger, start, goal, length_weight, danger_weight) else {
+            return Vec::new();
+        };
+        if frontier.iter().any(|p| p.dominates(&candidate) || (p.length, p.danger) == (candidate.length, candidate.danger)) {
+            continue;
+        }
+        frontier.retain(|p| !candidate.dominates(p));
+        frontier.push(candidate);
+    }
+    frontier.sort_by_key(|p| (p.length, p.danger));
+    frontier
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_danger_frontier() {
+        let grid = BitPackedGrid::new_from_string(".....\n.....\n".to_string());
+        let danger = Grid::from_fn(5, 2, |(x, y)| match (x, y) {
+            (1..=3, 1) => 5,
+            (2, 0) => 1,
+            _ => 0,
+        });
+        let shortest = danger_plan(&grid, &danger, (0, 1), (4, 1), 1, 0).unwrap();
+        assert_eq!((shortest.length, shortest.danger), (4, 15));
+        let safest = danger_plan(&grid, &danger, (0, 1), (4, 1), 0, 1).unwrap();
+        assert_eq!((safest.length, safest.danger), (6, 1));
+
+        let frontier = danger_frontier(&grid, &danger, (0, 1), (4, 1), 4);
+        let scores = frontier.iter().map(|p| (p.length, p.danger)).collect::<Vec<_>>();
+        assert_eq!(scores, vec![(4, 15), (6, 1)]);
+        assert!(frontier.windows(2).all(|p| !p[0].dominates(&p[1]) && !p[1].dominates(&p[0])));
+    }
+}
@@ -0,0 +1,105 @@
+//! # Diverse Paths
+//! Generation of k mutually diverse paths using the iterative penalty method.
+//! After each path is found, entering any of its nodes becomes more expensive
+//! in proportion to how many previous paths used it, pushing later searches
+//! away from the routes already returned. Useful for presenting route options
+//! or spreading risk across an uncertain map.
+
+use std::{collections::{HashMap, HashSet}, hash::Hash};
+
+use super::astar::astar;
+
+/// Finds up to `k` diverse paths between a start and goal
+/// ## Arguments
+/// * `expander` - A function that returns the nodes adjacent to a given node with their costs
+/// * `start` - The start node
+/// * `goal` - A function that returns whether or not a given node is the goal
+/// * `heuristic` - An admissible heuristic, which remains admissible under penalties
+/// * `k` - The number of searches to run
+/// * `penalty` - The added cost of entering a node for each previous path that used it
+/// ## Returns
+/// The distinct paths found in order with their unpenalized costs, this can
+/// be fewer than `k` if a search repeats an earlier path
+pub fn diverse_paths<E, I, N, G, H>(
+    mut expander: E,
+    start: N,
+    goal: G,
+    heuristic: H,
+    k: usize,
+    penalty: usize,
+) -> Vec<(Vec<N>, usize)>
+where
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, usize)>,
+    N: Hash + Clone + Eq,
+    G: Fn(&N) -> bool,
+    H: Fn(&N) -> usize,
+{
+    let mut uses: HashMap<N, usize> = HashMap::new();
+    let mut paths: Vec<(Vec<N>, usize)> = Vec::new();
+    for _ in 0..k {
+        let Some((path, cost)) = astar(
+            |n| expander(n).into_iter()
+                .map(|(c, w)| {
+                    let used = uses.get(&c).copied().unwrap_or(0);
+                    (c, w + penalty * used)
+                })
+                .collect::<Vec<_>>(),
+            start.clone(),
+            &goal,
+            &heuristic,
+        ) else {
+            break;
+        };
+        let penalties: usize = path.iter().skip(1).map(|n| uses.get(n).copied().unwrap_or(0)).sum();
+        for node in path.iter().skip(1) {
+            *uses.entry(node.clone()).or_insert(0) += 1;
+        }
+        if !paths.iter().any(|(p, _)| *p == path) {
+            paths.push((path, cost - penalty * penalties));
+        }
+    }
+    paths
+}
+
+/// The fraction of nodes two paths share, as the Jaccard similarity of their node sets
+pub fn overlap<N: Hash + Eq>(a: &[N], b: &[N]) -> f32 {
+    let a = a.iter().collect::<HashSet<_>>();
+    let b = b.iter().collect::<HashSet<_>>();
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f32 / union as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::bitpackedgrid::BitPackedGrid;
+    use crate::heuristics::distance::manhattan_distance;
+
+    #[test]
+    fn test_diverse_paths() {
+        let grid = BitPackedGrid::new_from_string(".....\n.@@@.\n.....\n".to_string());
+        let paths = diverse_paths(
+            |n| grid.adjacent1(*n),
+            (0, 1),
+            |n| *n == (4, 1),
+            |n| manhattan_distance(*n, (4, 1)),
+            2,
+            10,
+        );
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[0].1, 6);
+        assert_eq!(paths[1].1, 6);
+        assert_eq!(overlap(&paths[0].0, &paths[1].0), 2.0 / 12.0);
+    }
+
+    #[test]
+    fn test_overlap() {
+        assert_eq!(overlap(&[1, 2, 3], &[1, 2, 3]), 1.0);
+        assert_eq!(overlap(&[1, 2], &[3, 4]), 0.0);
+        assert_eq!(overlap::<usize>(&[], &[]), 0.0);
+    }
+}
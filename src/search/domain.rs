@@ -0,0 +1,208 @@
+//! # Search Domains
+//! A trait for the domains searched, so a user-defined domain is searched by
+//! implementing its successors and heuristic once instead of writing the
+//! closures of every search. The search algorithms stay generic over
+//! closures, and the functions here run them on any `SearchDomain`, which is
+//! implemented for the bitpacked, fixed, weighted and sampling grids, adjacency lists,
+//! explicit graphs and CSR graphs.
+
+use std::{hash::Hash, ops::Add};
+
+use crate::domains::{Cell, adjacencylist::AdjacencyList, graph, bitpackedgrid::BitPackedGrid, csrgraph::CsrGraph, fixedgrid::FixedGrid, weightedgrid::WeightedGrid};
+#[cfg(feature = "sampling")]
+use crate::domains::samplegrid::SampleGrid;
+use crate::heuristics::distance::manhattan_distance;
+
+use super::{astar::{astar, astar_with_stats}, stats::SearchStats};
+
+/// A domain that can be searched
+pub trait SearchDomain {
+    type Node: Hash + Eq + Clone;
+    type Cost: Ord + Default + Clone + Add<Output = Self::Cost>;
+
+    /// The successors of a node with the costs of the edges to them, which
+    /// may borrow the domain but not the node
+    fn successors<'a>(&'a self, node: &Self::Node) -> impl Iterator<Item = (Self::Node, Self::Cost)> + use<'a, Self>;
+
+    /// An admissible estimate of the cost from a node to a goal, by default
+    /// 0 so the search is uninformed
+    fn heuristic(&self, _node: &Self::Node, _goal: &Self::Node) -> Self::Cost {
+        Self::Cost::default()
+    }
+}
+
+/// Searches a domain for a shortest path with A-Star and its heuristic
+/// ## Returns
+/// An optional path from the start to the goal with its cost
+pub fn search<D: SearchDomain>(domain: &D, start: D::Node, goal: D::Node) -> Option<DomainPath<D>> {
+    astar(|n| domain.successors(n), start, |n| *n == goal, |n| domain.heuristic(n, &goal))
+}
+
+/// A path through a domain with its cost
+pub type DomainPath<D> = (Vec<<D as SearchDomain>::Node>, <D as SearchDomain>::Cost);
+
+/// Searches a domain like `search`, also returning the expansion statistics
+pub fn search_with_stats<D: SearchDomain>(domain: &D, start: D::Node, goal: D::Node) -> (Option<DomainPath<D>>, SearchStats) {
+    astar_with_stats(|n| domain.successors(n), start, |n| *n == goal, |n| domain.heuristic(n, &goal))
+}
+
+/// Searches a domain for a shortest path ignoring its heuristic, as Dijkstra's algorithm
+pub fn search_uninformed<D: SearchDomain>(domain: &D, start: D::Node, goal: D::Node) -> Option<DomainPath<D>> {
+    astar(|n| domain.successors(n), start, |n| *n == goal, |_| D::Cost::default())
+}
+
+/// 4-connected moves of unit cost between free cells
+impl SearchDomain for BitPackedGrid {
+    type Node = Cell;
+    type Cost = usize;
+
+    fn successors<'a>(&'a self, node: &Cell) -> impl Iterator<Item = (Cell, usize)> + use<'a> {
+        self.adjacent1(*node)
+    }
+
+    fn heuristic(&self, node: &Cell, goal: &Cell) -> usize {
+        manhattan_distance(*node, *goal)
+    }
+}
+
+/// 4-connected moves of unit cost between free cells
+impl<const W: usize, const H: usize> SearchDomain for FixedGrid<W, H> {
+    type Node = Cell;
+    type Cost = usize;
+
+    fn successors<'a>(&'a self, node: &Cell) -> impl Iterator<Item = (Cell, usize)> + use<'a, W, H> {
+        self.adjacent(*node).map(|n| (n, 1))
+    }
+
+    fn heuristic(&self, node: &Cell, goal: &Cell) -> usize {
+        manhattan_distance(*node, *goal)
+    }
+}
+
+/// 4-connected moves costing the terrain entered
+impl SearchDomain for WeightedGrid {
+    type Node = Cell;
+    type Cost = usize;
+
+    fn successors<'a>(&'a self, node: &Cell) -> impl Iterator<Item = (Cell, usize)> + use<'a> {
+        self.adjacent(*node)
+    }
+
+    fn heuristic(&self, node: &Cell, goal: &Cell) -> usize {
+        WeightedGrid::heuristic(self, *node, *goal)
+    }
+}
+
+/// 4-connected moves of unit cost between cells that may be free
+#[cfg(feature = "sampling")]
+impl SearchDomain for SampleGrid {
+    type Node = Cell;
+    type Cost = usize;
+
+    fn successors<'a>(&'a self, node: &Cell) -> impl Iterator<Item = (Cell, usize)> + use<'a> {
+        self.adjacent(*node, false).filter(|n| self.belief(*n) != 0.0).map(|n| (n, 1))
+    }
+
+    fn heuristic(&self, node: &Cell, goal: &Cell) -> usize {
+        manhattan_distance(*node, *goal)
+    }
+}
+
+/// The edges of the list, nodes without an entry having no successors
+impl<N, W> SearchDomain for AdjacencyList<N, W>
+where
+    N: Hash + Eq + Clone,
+    W: Ord + Default + Clone + Add<Output = W>,
+{
+    type Node = N;
+    type Cost = W;
+
+    fn successors<'a>(&'a self, node: &N) -> impl Iterator<Item = (N, W)> + use<'a, N, W> {
+        let edges = if self.contains_node(node) { self[node.clone()].as_slice() } else { &[] };
+        edges.iter().cloned()
+    }
+}
+
+/// The edges between node ids, see `CsrGraph::id` for the id of a label
+impl<N, W> SearchDomain for CsrGraph<N, W>
+where
+    N: Hash + Eq + Clone,
+    W: Ord + Default + Copy + Add<Output = W>,
+{
+    type Node = usize;
+    type Cost = W;
+
+    fn successors<'a>(&'a self, node: &usize) -> impl Iterator<Item = (usize, W)> + use<'a, N, W> {
+        self.adjacent(*node)
+    }
+}
+
+/// The edges of the graph, searched on the ids of its nodes without an
+/// heuristic
+impl<N, W> SearchDomain for graph::AdjacencyList<N, W>
+where
+    W: Ord + Default + Copy + Add<Output = W>,
+{
+    type Node = usize;
+    type Cost = W;
+
+    fn successors<'a>(&'a self, node: &usize) -> impl Iterator<Item = (usize, W)> + use<'a, N, W> {
+        self.edges(*node).iter().copied()
+    }
+}
+
+impl<'g, N, W, H> SearchDomain for graph::Informed<'g, N, W, H>
+where
+    W: Ord + Default + Copy + Add<Output = W>,
+    H: Fn(&N, &N) -> W,
+{
+    type Node = usize;
+    type Cost = W;
+
+    fn successors<'a>(&'a self, node: &usize) -> impl Iterator<Item = (usize, W)> + use<'a, 'g, N, W, H> {
+        self.graph.edges(*node).iter().copied()
+    }
+
+    fn heuristic(&self, node: &usize, goal: &usize) -> W {
+        (self.heuristic)(&self.graph.nodes[*node], &self.graph.nodes[*goal])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A user-defined domain, the integers where a step adds 1 or doubles
+    struct Doubling;
+
+    impl SearchDomain for Doubling {
+        type Node = u32;
+        type Cost = usize;
+
+        fn successors<'a>(&'a self, node: &u32) -> impl Iterator<Item = (u32, usize)> + use<'a> {
+            [(node + 1, 1), (node * 2, 1)].into_iter().filter(|(n, _)| *n <= 1000)
+        }
+    }
+
+    #[test]
+    fn test_search_domains() {
+        assert_eq!(search(&Doubling, 1, 20).unwrap().0, vec![1, 2, 4, 5, 10, 20]);
+        let grid = BitPackedGrid::new_from_string("....\n.@@.\n....\n".to_string());
+        let (path, cost) = search(&grid, (0, 1), (3, 1)).unwrap();
+        assert_eq!((cost, path.len()), (5, 6));
+        assert_eq!(search_uninformed(&grid, (0, 1), (3, 1)).unwrap().1, cost);
+        let weighted = WeightedGrid::new_from_string("..S.\n.@@.\n....\n".to_string());
+        assert_eq!(search(&weighted, (0, 1), (3, 1)).unwrap().1, 5);
+        let mut graph = AdjacencyList::new_nodes(vec!["a", "c"]);
+        graph.add_edge("a", "b", 4);
+        graph.add_edge("a", "c", 1);
+        graph.add_edge("c", "b", 2);
+        assert_eq!(search(&graph, "a", "b"), Some((vec!["a", "c", "b"], 3)));
+        assert!(search(&graph, "b", "a").is_none());
+        let csr = grid.to_graph(crate::domains::csrgraph::Connectivity::Four, |_, _| 1);
+        let (from, to) = (csr.id(&(0, 1)).unwrap(), csr.id(&(3, 1)).unwrap());
+        assert_eq!(search(&csr, from, to).unwrap().1, 5);
+        let (_, stats) = search_with_stats(&grid, (0, 0), (3, 2));
+        assert!(stats.expanded > 0);
+    }
+}
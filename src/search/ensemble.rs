@@ -0,0 +1,108 @@
+//! # Ensemble Planning
+//! Monte Carlo evaluation of planning on a sampling grid. The grid is sampled
+//! into many possible worlds, a planner is run on each of them in parallel and
+//! the outcomes are aggregated into the rate of worlds where a path was found,
+//! the distribution of its cost and how often each cell was traversed. Each
+//! world is sampled with a seed derived from a master seed and its index, so
+//! an ensemble is reproducible whatever the number of threads.
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid, grid::Grid, samplegrid::SampleGrid};
+use crate::util::preprocessing::{par_build_seeded, BuildStats};
+
+/// The aggregated outcomes of planning on sampled worlds
+/// ## Fields
+/// * `samples` - The number of worlds sampled
+/// * `costs` - The cost of the path of every world where one was found, in increasing order
+/// * `visits` - The number of paths traversing each cell
+/// * `build` - The statistics of the parallel sampling and planning
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnsembleStats {
+    pub samples: usize,
+    pub costs: Vec<usize>,
+    pub visits: Grid<usize>,
+    pub build: BuildStats,
+}
+
+impl EnsembleStats {
+    /// The fraction of worlds where a path was found
+    pub fn success_rate(&self) -> f64 {
+        self.costs.len() as f64 / self.samples.max(1) as f64
+    }
+
+    /// The mean cost of the paths found
+    pub fn mean_cost(&self) -> Option<f64> {
+        (!self.costs.is_empty()).then(|| self.costs.iter().sum::<usize>() as f64 / self.costs.len() as f64)
+    }
+
+    /// The cost below which a fraction of the paths found lie, by the nearest rank
+    /// ## Arguments
+    /// * `percentile` - The fraction of paths, in [0, 1]
+    pub fn percentile_cost(&self, percentile: f64) -> Option<usize> {
+        let rank = (percentile.clamp(0.0, 1.0) * self.costs.len() as f64).ceil() as usize;
+        self.costs.get(rank.max(1) - 1).copied()
+    }
+
+    /// The fraction of worlds whose path traversed each cell, as the heatmap
+    /// of `SampleGrid::plot_sampling_cells`
+    pub fn heatmap(&self) -> Vec<(Cell, f64)> {
+        self.visits.iter()
+            .filter(|(_, v)| **v > 0)
+            .map(|(n, v)| (n, *v as f64 / self.samples.max(1) as f64))
+            .collect()
+    }
+}
+
+/// Samples worlds from a sampling grid and plans on each of them in parallel
+/// ## Arguments
+/// * `grid` - The sampling grid worlds are sampled from
+/// * `samples` - The number of worlds to sample
+/// * `seed` - The master seed of the samples
+/// * `planner` - Plans on a sampled world, returning a path and its cost
+pub fn ensemble_plan<P>(grid: &SampleGrid, samples: usize, seed: u64, planner: P) -> EnsembleStats
+where
+    P: Fn(&BitPackedGrid) -> Option<(Vec<Cell>, usize)> + Sync + Send,
+{
+    let indices = (0..samples).collect::<Vec<_>>();
+    let (results, build) = par_build_seeded(&indices, seed, |_| {
+        let mut world = grid.clone();
+        world.sample_all();
+        planner(world.gridmap())
+    });
+    let mut visits = Grid::new(grid.width, grid.height, 0);
+    let mut costs = Vec::new();
+    for (path, cost) in results.into_iter().flatten() {
+        for cell in path {
+            visits.set(cell, visits[cell] + 1);
+        }
+        costs.push(cost);
+    }
+    costs.sort_unstable();
+    EnsembleStats { samples, costs, visits, build }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::distance::manhattan_distance;
+    use crate::search::astar::astar;
+
+    #[test]
+    fn test_ensemble_plan() {
+        // The middle cell of the short row is free half of the time
+        let ground_truth = BitPackedGrid::new_from_string("...\n...\n".to_string());
+        let beliefs = vec![vec![1.0, 1.0], vec![0.5, 1.0], vec![1.0, 1.0]];
+        let grid = SampleGrid::new_from_grid(beliefs, ground_truth);
+        let planner = |world: &BitPackedGrid| {
+            astar(|n| world.adjacent1(*n), (0, 0), |n| *n == (2, 0), |n| manhattan_distance(*n, (2, 0)))
+        };
+        let stats = ensemble_plan(&grid, 200, 3, planner);
+        assert_eq!((stats.samples, stats.success_rate()), (200, 1.0));
+        assert_eq!((stats.percentile_cost(0.0), stats.percentile_cost(1.0)), (Some(2), Some(4)));
+        let shortcuts = stats.costs.iter().filter(|c| **c == 2).count();
+        assert!(shortcuts > 60 && shortcuts < 140);
+        let heatmap = stats.heatmap();
+        assert!(heatmap.contains(&((0, 0), 1.0)));
+        assert!(heatmap.contains(&((1, 0), shortcuts as f64 / 200.0)));
+        assert_eq!(ensemble_plan(&grid, 200, 3, planner).costs, stats.costs);
+    }
+}
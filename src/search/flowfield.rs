@@ -0,0 +1,202 @@
+//! # Flow Fields
+//! Distance maps and flow fields from a set of goals, computed by a single
+//! backwards search so that any number of agents heading to the same goals
+//! share one computation instead of planning a path each. Uniform cost grids
+//! are searched breadth-first and weighted grids with Dijkstra's algorithm.
+//! The field gives each cell the 4-connected direction of a neighbour on a
+//! shortest path to the nearest goal, which agents follow one step at a time,
+//! such as agents of a `mapf::crowd::Crowd`.
+
+use std::{cmp::Reverse, collections::{BinaryHeap, VecDeque}};
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid, direction::Direction, grid::Grid, weightedgrid::WeightedGrid};
+
+/// The distance from every cell to the nearest goal and the direction to move
+/// towards it
+/// ## Fields
+/// * `distances` - The cost from each cell to the nearest goal, `None` when
+///   no goal can be reached
+/// * `directions` - The direction of the next cell towards the nearest goal,
+///   `None` at goals and cells that cannot reach a goal
+#[derive(Clone, Debug)]
+pub struct FlowField {
+    pub distances: Grid<Option<usize>>,
+    pub directions: Grid<Option<Direction>>,
+}
+
+impl FlowField {
+    /// Computes the flow field of a grid with unit costs breadth-first
+    pub fn new(grid: &BitPackedGrid, goals: &[Cell]) -> Self {
+        let (width, height) = (grid.original_width, grid.original_height);
+        let free = |n: Cell| grid.bounds_check(n) && grid.get_bit_value(n);
+        let mut distances = Grid::new(width, height, None);
+        let mut queue = VecDeque::new();
+        for &goal in goals.iter().filter(|g| free(**g)) {
+            distances[goal] = Some(0);
+            queue.push_back(goal);
+        }
+        while let Some(node) = queue.pop_front() {
+            let distance = distances[node].unwrap_or_default() + 1;
+            for neighbour in Direction::CARDINAL.iter().map(|d| d.apply_wrapping(node)).filter(|n| free(*n)) {
+                if distances[neighbour].is_none() {
+                    distances[neighbour] = Some(distance);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+        FlowField::from_distances(distances, |n| free(n).then_some(1))
+    }
+
+    /// Computes the flow field of a weighted grid with Dijkstra's algorithm,
+    /// where moving into a cell costs the cost of the cell
+    pub fn new_weighted(grid: &WeightedGrid, goals: &[Cell]) -> Self {
+        let mut distances = Grid::new(grid.width(), grid.height(), None);
+        let mut open = BinaryHeap::new();
+        for &goal in goals.iter().filter(|g| grid.cost(**g).is_some()) {
+            distances[goal] = Some(0);
+            open.push(Reverse((0, goal)));
+        }
+        while let Some(Reverse((distance, node))) = open.pop() {
+            if distances[node] != Some(distance) {
+                continue;
+            }
+            // Moving from a neighbour into the node costs the cost of the node
+            let Some(cost) = grid.cost(node) else { continue };
+            for (neighbour, _) in grid.adjacent(node) {
+                let through = distance + cost;
+                if distances[neighbour].is_none_or(|d| through < d) {
+                    distances[neighbour] = Some(through);
+                    open.push(Reverse((through, neighbour)));
+                }
+            }
+        }
+        FlowField::from_distances(distances, |n| grid.cost(n))
+    }
+
+    /// Points every cell that can reach a goal at the neighbour continuing a
+    /// shortest path, the first of `Direction::CARDINAL` on ties
+    fn from_distances(distances: Grid<Option<usize>>, cost: impl Fn(Cell) -> Option<usize>) -> Self {
+        let directions = Grid::from_fn(distances.width, distances.height, |node| {
+            let distance = distances[node].filter(|d| *d > 0)?;
+            Direction::CARDINAL.into_iter().find(|d| {
+                let next = d.apply_wrapping(node);
+                distances.get(next).copied().flatten().zip(cost(next)).is_some_and(|(n, c)| n + c == distance)
+            })
+        });
+        FlowField { distances, directions }
+    }
+
+    /// The cost from a cell to the nearest goal
+    pub fn distance(&self, node: Cell) -> Option<usize> {
+        self.distances.get(node).copied().flatten()
+    }
+
+    /// The direction of the next cell towards the nearest goal
+    pub fn direction(&self, node: Cell) -> Option<Direction> {
+        self.directions.get(node).copied().flatten()
+    }
+
+    /// The next cell towards the nearest goal, `None` at goals and cells that
+    /// cannot reach a goal
+    pub fn next(&self, node: Cell) -> Option<Cell> {
+        self.direction(node).map(|d| d.apply_wrapping(node))
+    }
+
+    /// The path from a cell to the nearest goal following the field
+    pub fn path(&self, start: Cell) -> Option<Vec<Cell>> {
+        self.distance(start)?;
+        let mut path = vec![start];
+        while let Some(next) = self.next(*path.last().unwrap()) {
+            path.push(next);
+        }
+        Some(path)
+    }
+}
+
+/// An agent following a flow field
+/// ## Fields
+/// * `position` - The current cell of the agent
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Agent {
+    pub position: Cell,
+}
+
+impl Agent {
+    /// Creates an agent at a cell
+    pub fn new(position: Cell) -> Self {
+        Agent { position }
+    }
+
+    /// Moves the agent one cell along a flow field
+    /// ## Returns
+    /// Whether the agent moved, false once it is at a goal or cannot reach one
+    pub fn step(&mut self, field: &FlowField) -> bool {
+        match field.next(self.position) {
+            Some(next) => {
+                self.position = next;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Checks if the agent is at a goal of a flow field
+    pub fn at_goal(&self, field: &FlowField) -> bool {
+        field.distance(self.position) == Some(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::distance::manhattan_distance;
+    use crate::search::astar::astar;
+
+    #[test]
+    fn test_flowfield() {
+        let grid = BitPackedGrid::new_from_string("......\n.@@@@.\n......\n@@@@.@\n......\n".to_string());
+        let goals = [(0, 4), (5, 0)];
+        let field = FlowField::new(&grid, &goals);
+        for start in grid.free_cells() {
+            let nearest = goals.iter()
+                .filter_map(|g| astar(|n| grid.adjacent1(*n), start, |n| n == g, |n| manhattan_distance(*n, *g)))
+                .map(|(_, c)| c)
+                .min();
+            assert_eq!(field.distance(start), nearest, "{:?}", start);
+            let path = field.path(start).unwrap();
+            assert_eq!(path.len(), nearest.unwrap() + 1);
+            assert!(goals.contains(path.last().unwrap()));
+        }
+        let mut agent = Agent::new((0, 0));
+        let mut steps = 0;
+        while agent.step(&field) {
+            steps += 1;
+        }
+        assert!(agent.at_goal(&field) && steps == field.distance((0, 0)).unwrap());
+        let walled = BitPackedGrid::new_from_string("..@.\n".to_string());
+        assert_eq!(FlowField::new(&walled, &[(0, 0)]).distance((3, 0)), None);
+        assert!(!Agent::new((3, 0)).step(&FlowField::new(&walled, &[(0, 0)])));
+    }
+
+    #[test]
+    fn test_weighted_flowfield() {
+        let grid = WeightedGrid::new_from_string_with_costs("....\n.WW.\n....\n".to_string(), |c| match c {
+            '.' => 1,
+            'W' => 5,
+            _ => 0,
+        });
+        let field = FlowField::new_weighted(&grid, &[(3, 1)]);
+        for (start, distance) in [((0, 1), Some(5)), ((1, 1), Some(4)), ((3, 1), Some(0))] {
+            let expected = astar(|n| grid.adjacent(*n), start, |n| *n == (3, 1), |_| 0).map(|(_, c)| c);
+            assert_eq!((field.distance(start), expected), (distance, distance));
+        }
+        // Agents avoid the expensive cells
+        let path = field.path((0, 1)).unwrap();
+        assert!(path.iter().all(|n| grid.cost(*n) == Some(1)));
+        #[cfg(feature = "mapf")]
+        {
+            let crowd = crate::mapf::crowd::Crowd::follow_field(vec![(0, 0), (0, 2)], |n| field.next(*n), 20);
+            assert!(crowd.positions.contains(&(3, 1)));
+        }
+    }
+}
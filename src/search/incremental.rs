@@ -0,0 +1,307 @@
+//! # Incremental D-Star Lite
+//! D-Star Lite, following Koenig and Likhachev, "D* Lite" (2002), for agents
+//! replanning on maps that change as they are sensed, such as the gridmap of a
+//! `SampleGrid` after its beliefs are updated. The search runs backwards from
+//! the goal and keeps its priority queue and g and rhs values between plans,
+//! so when the costs around a few cells change only the affected part of the
+//! search is repaired instead of planning from scratch. The agent moves by
+//! changing the start, which is accounted for by the key modifier rather than
+//! by reordering the queue.
+//!
+//! The expander is passed to every call instead of being stored, so it can
+//! borrow a map that is changed between calls. Edges are assumed to be
+//! undirected, the successors of a node also being its predecessors.
+
+use std::{collections::{BinaryHeap, HashMap}, hash::Hash, ops::Add};
+
+use super::stats::{timed, SearchStats};
+use super::SearchNodeState;
+
+/// An incremental search from a moving start to a fixed goal
+/// ## Fields
+/// * `start` - The current start, the cell of the agent
+/// * `goal` - The goal of every plan
+/// * `expanded` - The number of nodes expanded by the last plan
+pub struct DStarLite<N: Eq, C: Ord, H> {
+    pub start: N,
+    pub goal: N,
+    pub expanded: usize,
+    heuristic: H,
+    /// The key modifier, the heuristic distance the start has moved, keeping
+    /// the keys queued before a move lower bounds of their current keys
+    km: C,
+    g: HashMap<N, C>,
+    rhs: HashMap<N, C>,
+    open: BinaryHeap<SearchNodeState<N, (C, C)>>,
+    /// The current key of each queued node, older entries of the heap being stale
+    queued: HashMap<N, (C, C)>,
+    /// The statistics of the work since the last plan, including the updates before it
+    stats: SearchStats,
+}
+
+impl<N, C, H> DStarLite<N, C, H>
+where
+    N: Hash + Eq + Clone,
+    C: Ord + Default + Clone + Add<Output = C>,
+    H: Fn(&N, &N) -> C,
+{
+    /// Creates a search between a start and a goal, planned on the first call
+    /// to `plan`
+    /// ## Arguments
+    /// * `start` - The start node
+    /// * `goal` - The goal node
+    /// * `heuristic` - A consistent heuristic between two nodes
+    pub fn new(start: N, goal: N, heuristic: H) -> Self {
+        let mut search = DStarLite {
+            start,
+            goal: goal.clone(),
+            expanded: 0,
+            heuristic,
+            km: C::default(),
+            g: HashMap::new(),
+            rhs: HashMap::from([(goal.clone(), C::default())]),
+            open: BinaryHeap::new(),
+            queued: HashMap::new(),
+            stats: SearchStats::default(),
+        };
+        search.enqueue(goal);
+        search
+    }
+
+    /// The cost from a node to the goal found by the search, if it is known
+    pub fn cost_to_goal(&self, node: &N) -> Option<&C> {
+        self.g.get(node)
+    }
+
+    /// The key of a node with a finite g or rhs value
+    fn key(&self, node: &N) -> (C, C) {
+        let m = match (self.g.get(node), self.rhs.get(node)) {
+            (Some(g), Some(rhs)) => g.min(rhs),
+            (Some(m), None) | (None, Some(m)) => m,
+            (None, None) => unreachable!("Only nodes with a finite value have keys"),
+        }
+        .clone();
+        (m.clone() + (self.heuristic)(&self.start, node) + self.km.clone(), m)
+    }
+
+    /// Pushes a node with its current key
+    fn enqueue(&mut self, node: N) {
+        let key = self.key(&node);
+        self.queued.insert(node.clone(), key.clone());
+        self.open.push(SearchNodeState { node, cost: key });
+        self.stats.heuristic_calls += 1;
+        self.stats.pushed += 1;
+        self.stats.open_size(self.open.len());
+    }
+
+    /// Recomputes the rhs value of a node from its successors, queueing it if
+    /// it is locally inconsistent
+    fn update_vertex<E, I>(&mut self, node: N, expander: &mut E)
+    where
+        E: FnMut(&N) -> I,
+        I: IntoIterator<Item = (N, C)>,
+    {
+        if node != self.goal {
+            let rhs = expander(&node).into_iter()
+                .filter_map(|(child, cost)| self.g.get(&child).map(|g| g.clone() + cost))
+                .min();
+            match rhs {
+                Some(rhs) => self.rhs.insert(node.clone(), rhs),
+                None => self.rhs.remove(&node),
+            };
+        }
+        if self.g.get(&node) != self.rhs.get(&node) {
+            self.enqueue(node);
+        } else {
+            self.queued.remove(&node);
+        }
+    }
+
+    /// Updates the rhs values of a node and its predecessors
+    fn update_around<E, I>(&mut self, node: N, expander: &mut E)
+    where
+        E: FnMut(&N) -> I,
+        I: IntoIterator<Item = (N, C)>,
+    {
+        let predecessors = expander(&node).into_iter().map(|(n, _)| n).collect::<Vec<_>>();
+        self.stats.generated += predecessors.len();
+        self.update_vertex(node, expander);
+        for predecessor in predecessors {
+            self.update_vertex(predecessor, expander);
+        }
+    }
+
+    /// Expands nodes until the start is locally consistent and no queued key
+    /// is below its key
+    fn compute_shortest_path<E, I>(&mut self, expander: &mut E)
+    where
+        E: FnMut(&N) -> I,
+        I: IntoIterator<Item = (N, C)>,
+    {
+        self.expanded = 0;
+        while let Some(SearchNodeState { node, cost: old_key }) = self.open.pop() {
+            if self.queued.get(&node) != Some(&old_key) {
+                self.stats.popped += 1;
+                self.stats.duplicates += 1;
+                continue;
+            }
+            let start_consistent = self.g.get(&self.start) == self.rhs.get(&self.start);
+            let start_key = self.rhs.get(&self.start).map(|_| self.key(&self.start));
+            if start_consistent && start_key.is_some_and(|k| old_key >= k) {
+                self.open.push(SearchNodeState { node, cost: old_key });
+                break;
+            }
+            self.stats.popped += 1;
+            self.queued.remove(&node);
+            self.expanded += 1;
+            self.stats.expanded += 1;
+            let new_key = self.key(&node);
+            if old_key < new_key {
+                self.enqueue(node);
+                continue;
+            }
+            let rhs = self.rhs.get(&node).cloned();
+            let overconsistent = rhs.as_ref().is_some_and(|r| self.g.get(&node).is_none_or(|g| g > r));
+            if overconsistent {
+                self.g.insert(node.clone(), rhs.unwrap());
+                let predecessors = expander(&node).into_iter().map(|(n, _)| n).collect::<Vec<_>>();
+                self.stats.generated += predecessors.len();
+                for predecessor in predecessors {
+                    self.update_vertex(predecessor, expander);
+                }
+            } else {
+                self.g.remove(&node);
+                self.update_around(node, expander);
+            }
+        }
+    }
+
+    /// Moves the start, such as after the agent makes a move
+    pub fn move_to(&mut self, start: N) {
+        self.km = self.km.clone() + (self.heuristic)(&self.start, &start);
+        self.start = start;
+    }
+
+    /// Informs the search of the nodes whose edges changed, such as the cells
+    /// of a gridmap that became free or blocked, which are repaired on the
+    /// next call to `plan`
+    /// ## Arguments
+    /// * `changed` - The nodes whose edge costs changed
+    /// * `expander` - The successors of a node on the changed map
+    pub fn update<E, I>(&mut self, changed: &[N], mut expander: E)
+    where
+        E: FnMut(&N) -> I,
+        I: IntoIterator<Item = (N, C)>,
+    {
+        for node in changed {
+            self.update_around(node.clone(), &mut expander);
+        }
+    }
+
+    /// Plans from the start to the goal, repairing the search of the last plan
+    /// ## Arguments
+    /// * `expander` - The successors of a node on the current map
+    /// ## Returns
+    /// An optional path from the start to the goal with its cost
+    pub fn plan<E, I>(&mut self, expander: E) -> Option<(Vec<N>, C)>
+    where
+        E: FnMut(&N) -> I,
+        I: IntoIterator<Item = (N, C)>,
+    {
+        self.plan_with_stats(expander).0
+    }
+
+    /// Plans like `plan`, also returning the statistics of the repair, which
+    /// count the work of the updates since the last plan
+    pub fn plan_with_stats<E, I>(&mut self, expander: E) -> (Option<(Vec<N>, C)>, SearchStats)
+    where
+        E: FnMut(&N) -> I,
+        I: IntoIterator<Item = (N, C)>,
+    {
+        timed(|| {
+            let path = self.repair(expander);
+            (path, std::mem::take(&mut self.stats))
+        })
+    }
+
+    /// Repairs the search and follows the costs from the start to the goal
+    fn repair<E, I>(&mut self, mut expander: E) -> Option<(Vec<N>, C)>
+    where
+        E: FnMut(&N) -> I,
+        I: IntoIterator<Item = (N, C)>,
+    {
+        self.compute_shortest_path(&mut expander);
+        let cost = self.g.get(&self.start)?.clone();
+        let mut path = vec![self.start.clone()];
+        let mut current = self.start.clone();
+        while current != self.goal {
+            if path.len() > self.g.len() {
+                return None;
+            }
+            current = expander(&current).into_iter()
+                .filter_map(|(child, cost)| self.g.get(&child).map(|g| (g.clone() + cost, child)))
+                .min_by(|a, b| a.0.cmp(&b.0))?
+                .1;
+            path.push(current.clone());
+        }
+        Some((path, cost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::bitpackedgrid::BitPackedGrid;
+    use crate::heuristics::distance::manhattan_distance;
+    use crate::domains::Cell;
+    use crate::search::astar::astar;
+
+    fn optimal(grid: &BitPackedGrid, start: Cell, goal: Cell) -> Option<usize> {
+        astar(|n| grid.adjacent1(*n), start, |n| *n == goal, |n| manhattan_distance(*n, goal)).map(|(_, c)| c)
+    }
+
+    #[test]
+    fn test_dstarlite_repairs() {
+        let mut grid = BitPackedGrid::new_from_string("........\n...###..\n.....#..\n.....#..\n........\n........\n".to_string());
+        let (start, goal) = ((0, 5), (7, 0));
+        let mut search = DStarLite::new(start, goal, |a: &Cell, b: &Cell| manhattan_distance(*a, *b));
+        let (path, cost) = search.plan(|n| grid.adjacent1(*n)).unwrap();
+        assert_eq!((path[0], *path.last().unwrap(), path.len()), (start, goal, cost + 1));
+        assert_eq!(Some(cost), optimal(&grid, start, goal));
+        let initial = search.expanded;
+
+        // Walls appear next to the agent after it moves, and the plan is repaired
+        search.move_to(path[2]);
+        grid.set_bit_value((4, 4), false);
+        grid.set_bit_value((4, 5), false);
+        search.update(&[(4, 4), (4, 5)], |n| grid.adjacent1(*n));
+        let (found, stats) = search.plan_with_stats(|n| grid.adjacent1(*n));
+        let (path, cost) = found.unwrap();
+        assert_eq!(Some(cost), optimal(&grid, search.start, goal));
+        assert!(path.iter().all(|n| grid.get_bit_value(*n)));
+        assert!(search.expanded < initial);
+        assert_eq!(stats.expanded, search.expanded);
+        assert!(stats.pushed > 0 && stats.duplicates <= stats.popped);
+
+        // Opening a gap gives a shorter path
+        grid.set_bit_value((5, 2), true);
+        search.update(&[(5, 2)], |n| grid.adjacent1(*n));
+        let (_, shorter) = search.plan(|n| grid.adjacent1(*n)).unwrap();
+        assert_eq!(Some(shorter), optimal(&grid, search.start, goal));
+        assert!(shorter <= cost);
+    }
+
+    #[test]
+    fn test_dstarlite_unreachable() {
+        let mut grid = BitPackedGrid::new_from_string("...\n...\n...\n".to_string());
+        let mut search = DStarLite::new((0, 0), (2, 2), |a: &Cell, b: &Cell| manhattan_distance(*a, *b));
+        assert_eq!(search.plan(|n| grid.adjacent1(*n)).unwrap().1, 4);
+        grid.set_bit_value((1, 2), false);
+        grid.set_bit_value((2, 1), false);
+        search.update(&[(1, 2), (2, 1)], |n| grid.adjacent1(*n));
+        assert!(search.plan(|n| grid.adjacent1(*n)).is_none());
+        grid.set_bit_value((2, 1), true);
+        search.update(&[(2, 1)], |n| grid.adjacent1(*n));
+        assert_eq!(search.plan(|n| grid.adjacent1(*n)).unwrap().1, 4);
+    }
+}
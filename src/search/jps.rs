@@ -0,0 +1,279 @@
+//! # Jump Point Search
+//! Jump Point Search, following Harabor and Grastien, "Online Graph Pruning
+//! for Pathfinding on Grid Maps" (2011), for 8-connected bitpacked grids
+//! where diagonal moves may not cut the corners of obstacles. Symmetric paths
+//! are pruned by jumping in straight lines and diagonals until a node with a
+//! forced neighbour is found, so only these jump points are pushed to the
+//! open list. Like Warthog, straight jumps scan the grid a word at a time,
+//! rows through the grid and columns through a transposed copy, finding the
+//! first obstacle and forced neighbour of 64 cells with a few bit operations.
+//! Paths and costs are the same as those of A-Star with `adjacent_octile`.
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid, direction::Direction};
+use crate::heuristics::octile::{exact_octile_distance, OctileCost};
+
+use super::stats::{timed, SearchStats};
+use super::{reconstruct_path_with_cost, SearchNodeState};
+
+/// The direction of the move between two distinct cells on a straight or diagonal line
+fn direction((x1, y1): Cell, (x2, y2): Cell) -> Direction {
+    let offset = ((x2 as isize - x1 as isize).signum(), (y2 as isize - y1 as isize).signum());
+    Direction::from_offset(offset).expect("The cells are distinct")
+}
+
+/// Jumps along a row from a cell, a word of cells at a time
+/// ## Arguments
+/// * `grid` - The grid scanned, transposed to jump along a column
+/// * `(x, y)` - The cell jumped from
+/// * `east` - Whether to jump east rather than west
+/// * `goal` - The goal, which stops a jump that reaches it
+/// ## Returns
+/// The first jump point or the goal, `None` if an obstacle is reached first
+fn jump_row(grid: &BitPackedGrid, (mut x, y): Cell, east: bool, goal: Cell) -> Option<Cell> {
+    let read = |cell| if east { grid.row_word_east(cell) } else { grid.row_word_west(cell) };
+    let offset = |x: usize, i: usize| if east { x + i } else { x - i };
+    loop {
+        let (above, row, below) = (read((x, y.wrapping_sub(1))), read((x, y)), read((x, y + 1)));
+        // A neighbour is forced where the cell behind it along the row is blocked
+        let forced = (above & !(above << 1) | below & !(below << 1)) & !1;
+        let blocked = (!row).trailing_zeros() as usize;
+        let stop = (forced.trailing_zeros() as usize).min(blocked);
+        if goal.1 == y {
+            let distance = if east { goal.0.wrapping_sub(x) } else { x.wrapping_sub(goal.0) };
+            if distance > 0 && distance < stop {
+                return Some(goal);
+            }
+        }
+        if stop < blocked {
+            return Some((offset(x, stop), y));
+        }
+        if blocked < usize::BITS as usize {
+            return None;
+        }
+        // The last cell is kept to check for a forced neighbour after it
+        x = offset(x, usize::BITS as usize - 1);
+    }
+}
+
+/// A Jump Point Search on an 8-connected grid
+/// ## Fields
+/// * `expanded` - The number of jump points expanded by the last search
+pub struct JumpPointSearch<'a> {
+    grid: &'a BitPackedGrid,
+    transposed: BitPackedGrid,
+    pub expanded: usize,
+}
+
+impl<'a> JumpPointSearch<'a> {
+    /// Prepares a search on a grid, transposing it to scan its columns
+    pub fn new(grid: &'a BitPackedGrid) -> Self {
+        JumpPointSearch { grid, transposed: grid.transposed(), expanded: 0 }
+    }
+
+    fn free(&self, cell: Cell) -> bool {
+        self.grid.get_bit_value(cell)
+    }
+
+    /// Jumps along a row or column from a cell
+    fn jump_straight(&self, cell: Cell, direction: Direction, goal: Cell) -> Option<Cell> {
+        let (dx, dy) = direction.offset();
+        if dy == 0 {
+            jump_row(self.grid, cell, dx > 0, goal)
+        } else {
+            jump_row(&self.transposed, (cell.1, cell.0), dy > 0, (goal.1, goal.0)).map(|(y, x)| (x, y))
+        }
+    }
+
+    /// Jumps diagonally from a cell, stopping at cells from which a straight
+    /// jump along either axis of the move finds a jump point
+    fn jump_diagonal(&self, mut cell: Cell, direction: Direction, goal: Cell) -> Option<Cell> {
+        let (a, b) = direction.components()?;
+        loop {
+            let next = direction.apply_wrapping(cell);
+            if !self.free(next) || !self.free(a.apply_wrapping(cell)) || !self.free(b.apply_wrapping(cell)) {
+                return None;
+            }
+            cell = next;
+            if cell == goal || self.jump_straight(cell, a, goal).is_some() || self.jump_straight(cell, b, goal).is_some() {
+                return Some(cell);
+            }
+        }
+    }
+
+    /// The directions to jump in from a node reached from a parent, the
+    /// natural and forced neighbours of the move into the node
+    fn directions(&self, node: Cell, parent: Option<Cell>) -> Vec<Direction> {
+        let Some(parent) = parent else { return Direction::ALL.to_vec() };
+        let travel = direction(parent, node);
+        if let Some((a, b)) = travel.components() {
+            return vec![a, b, travel];
+        }
+        let mut directions = vec![travel];
+        for turn in [-1, 1] {
+            // The side cell is forced when the cell behind it is blocked
+            let (across, behind) = (travel.rotate(2 * turn), travel.rotate(3 * turn));
+            if self.free(across.apply_wrapping(node)) && !self.free(behind.apply_wrapping(node)) {
+                directions.push(across);
+                directions.push(travel.rotate(turn));
+            }
+        }
+        directions
+    }
+
+    /// The jump points reached from a node
+    fn successors(&self, node: Cell, parent: Option<Cell>, goal: Cell) -> Vec<Cell> {
+        self.directions(node, parent).into_iter()
+            .filter_map(|d| if d.is_diagonal() { self.jump_diagonal(node, d, goal) } else { self.jump_straight(node, d, goal) })
+            .collect()
+    }
+
+    /// Searches for a shortest path between two cells
+    /// ## Returns
+    /// The jump points of a shortest path from the start to the goal with its
+    /// cost, consecutive jump points lying on a straight or diagonal line
+    pub fn search_jump_points(&mut self, start: Cell, goal: Cell) -> Option<(Vec<Cell>, OctileCost)> {
+        let (result, expanded) = self.query_jump_points(start, goal);
+        self.expanded = expanded;
+        result
+    }
+
+    /// Searches for the jump points of a shortest path like
+    /// `search_jump_points` without mutating the search, so one search can
+    /// answer queries from many threads
+    /// ## Returns
+    /// The jump points with the cost of the path and the number of jump points expanded
+    pub fn query_jump_points(&self, start: Cell, goal: Cell) -> (Option<(Vec<Cell>, OctileCost)>, usize) {
+        let (result, stats) = self.query_jump_points_with_stats(start, goal);
+        (result, stats.expanded)
+    }
+
+    /// Searches for the jump points of a shortest path like `query_jump_points`,
+    /// counting jump points as the nodes of `SearchStats`
+    /// ## Returns
+    /// The jump points with the cost of the path and the statistics of the search
+    pub fn query_jump_points_with_stats(&self, start: Cell, goal: Cell) -> (Option<(Vec<Cell>, OctileCost)>, SearchStats) {
+        timed(|| {
+            let mut stats = SearchStats::default();
+            if !self.grid.bounds_check(start) || !self.grid.bounds_check(goal) || !self.free(start) || !self.free(goal) {
+                return (None, stats);
+            }
+            let mut open = BinaryHeap::from([SearchNodeState { node: start, cost: exact_octile_distance(start, goal) }]);
+            let mut previous: HashMap<Cell, (Option<Cell>, OctileCost)> = HashMap::from([(start, (None, OctileCost::default()))]);
+            let mut closed = HashSet::new();
+            (stats.pushed, stats.heuristic_calls, stats.peak_open) = (1, 1, 1);
+            while let Some(SearchNodeState { node, .. }) = open.pop() {
+                stats.popped += 1;
+                if !closed.insert(node) {
+                    stats.duplicates += 1;
+                    continue;
+                }
+                if node == goal {
+                    return (Some(reconstruct_path_with_cost(previous, node)), stats);
+                }
+                stats.expanded += 1;
+                let (parent, g) = previous[&node];
+                for child in self.successors(node, parent, goal) {
+                    stats.generated += 1;
+                    let cost = g + exact_octile_distance(node, child);
+                    if previous.get(&child).is_none_or(|(_, c)| cost < *c) {
+                        previous.insert(child, (Some(node), cost));
+                        open.push(SearchNodeState { node: child, cost: cost + exact_octile_distance(child, goal) });
+                        stats.heuristic_calls += 1;
+                        stats.pushed += 1;
+                        stats.open_size(open.len());
+                    }
+                }
+            }
+            (None, stats)
+        })
+    }
+
+    /// Searches for a shortest path between two cells
+    /// ## Returns
+    /// Every cell of a shortest path from the start to the goal with its cost
+    pub fn search(&mut self, start: Cell, goal: Cell) -> Option<(Vec<Cell>, OctileCost)> {
+        let (jump_points, cost) = self.search_jump_points(start, goal)?;
+        Some((fill_jumps(&jump_points), cost))
+    }
+
+    /// Searches for a shortest path like `search`, also returning the
+    /// statistics of the search over jump points
+    pub fn search_with_stats(&mut self, start: Cell, goal: Cell) -> (Option<(Vec<Cell>, OctileCost)>, SearchStats) {
+        let (result, stats) = self.query_jump_points_with_stats(start, goal);
+        self.expanded = stats.expanded;
+        (result.map(|(jump_points, cost)| (fill_jumps(&jump_points), cost)), stats)
+    }
+
+    /// Searches for a shortest path like `search` without mutating the search
+    /// ## Returns
+    /// Every cell of a shortest path with its cost and the number of jump points expanded
+    pub fn query(&self, start: Cell, goal: Cell) -> (Option<(Vec<Cell>, OctileCost)>, usize) {
+        let (result, expanded) = self.query_jump_points(start, goal);
+        (result.map(|(jump_points, cost)| (fill_jumps(&jump_points), cost)), expanded)
+    }
+}
+
+/// The cells between consecutive jump points of a path
+fn fill_jumps(jump_points: &[Cell]) -> Vec<Cell> {
+    let mut path = vec![jump_points[0]];
+    for next in jump_points.iter().skip(1) {
+        let travel = direction(*path.last().unwrap(), *next);
+        while path.last().unwrap() != next {
+            path.push(travel.apply_wrapping(*path.last().unwrap()));
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::test_queries;
+    use crate::search::astar::astar_with_stats;
+
+    fn octile_astar(grid: &BitPackedGrid, start: Cell, goal: Cell) -> (Option<OctileCost>, usize) {
+        let (result, stats) = astar_with_stats(|n| grid.adjacent_octile(*n), start, |n| *n == goal, |n| exact_octile_distance(*n, goal));
+        (result.map(|(_, c)| c), stats.expanded)
+    }
+
+    #[test]
+    fn test_jps() {
+        let grid = BitPackedGrid::new_from_string("........\n...@@@..\n.....@..\n.@...@..\n.@......\n".to_string());
+        let mut jps = JumpPointSearch::new(&grid);
+        let (path, cost) = jps.search((0, 4), (7, 0)).unwrap();
+        assert_eq!(Some(cost), octile_astar(&grid, (0, 4), (7, 0)).0);
+        assert_eq!((path[0], *path.last().unwrap()), ((0, 4), (7, 0)));
+        assert!(path.windows(2).all(|m| grid.adjacent_octile(m[0]).any(|(n, _)| n == m[1])));
+        assert_eq!(path.windows(2).map(|m| OctileCost::step(m[0], m[1])).sum::<OctileCost>(), cost);
+        assert_eq!(jps.search((2, 2), (2, 2)).unwrap().0, vec![(2, 2)]);
+        assert!(jps.search((0, 0), (3, 1)).is_none());
+        let (found, stats) = jps.search_with_stats((0, 4), (7, 0));
+        assert_eq!((found.map(|(p, _)| p), stats.expanded), (Some(path), jps.expanded));
+        assert!(stats.pushed <= stats.generated + 1 && stats.popped <= stats.pushed);
+
+        let walled = BitPackedGrid::new_from_string(".@.\n.@.\n.@.\n".to_string());
+        assert!(JumpPointSearch::new(&walled).search((0, 0), (2, 2)).is_none());
+    }
+
+    #[test]
+    fn test_jps_matches_astar() {
+        let grid = BitPackedGrid::new_from_file("tests/map.map");
+        let mut jps = JumpPointSearch::new(&grid);
+        let free = grid.free_cells().collect::<Vec<_>>();
+        let (mut jps_expanded, mut astar_expanded) = (0, 0);
+        for (start, goal) in test_queries(&free, 40) {
+            let (optimal, expanded) = octile_astar(&grid, start, goal);
+            let found = jps.search(start, goal);
+            assert_eq!(found.as_ref().map(|(_, c)| *c), optimal, "{:?} to {:?}", start, goal);
+            if let Some((path, cost)) = found {
+                assert_eq!(path.windows(2).map(|m| OctileCost::step(m[0], m[1])).sum::<OctileCost>(), cost);
+                assert!(path.iter().all(|n| grid.get_bit_value(*n)));
+            }
+            jps_expanded += jps.expanded;
+            astar_expanded += expanded;
+        }
+        assert!(jps_expanded < astar_expanded);
+    }
+}
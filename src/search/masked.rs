@@ -0,0 +1,69 @@
+//! # Masked Search
+//! Search restricted to a region of a grid map, where the region is given as a
+//! mask, a bitpacked grid of the same size whose free cells are inside the
+//! region. Masks come from corridors around a path, clusters, rooms or hand
+//! drawn areas, so replanning can stay close to a previous plan and
+//! experiments can run on part of a map.
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+use crate::heuristics::distance::manhattan_distance;
+
+use super::astar::astar;
+
+/// Restricts an expander to the cells of a mask
+/// ## Arguments
+/// * `mask` - A grid whose free cells are the cells the search may enter
+/// * `expander` - The expander of the unrestricted search
+/// ## Returns
+/// An expander that only yields the neighbours within the mask
+pub fn within_mask<'a, E, I, C>(mask: &'a BitPackedGrid, mut expander: E) -> impl FnMut(&Cell) -> Vec<(Cell, C)> + 'a
+where
+    E: FnMut(&Cell) -> I + 'a,
+    I: IntoIterator<Item = (Cell, C)>,
+{
+    move |node| {
+        expander(node)
+            .into_iter()
+            .filter(|(n, _)| mask.bounds_check(*n) && mask.get_bit_value(*n))
+            .collect()
+    }
+}
+
+/// Plans a 4-connected path with unit costs that stays within a mask
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `mask` - A grid of the same size whose free cells are the region
+/// * `start` - The start cell
+/// * `goal` - The goal cell
+/// ## Returns
+/// The path and its length, or `None` if the goal cannot be reached within the region
+pub fn masked_astar(grid: &BitPackedGrid, mask: &BitPackedGrid, start: Cell, goal: Cell) -> Option<(Vec<Cell>, usize)> {
+    astar(
+        within_mask(mask, |n: &Cell| grid.adjacent1(*n).filter(|(n, _)| grid.bounds_check(*n))),
+        start,
+        |n| *n == goal,
+        |n| manhattan_distance(*n, goal),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::corridor::corridor;
+
+    #[test]
+    fn test_masked_astar() {
+        let grid = BitPackedGrid::new_from_string(".....\n.@@@.\n.....\n.@@@.\n.....\n".to_string());
+        let (path, length) = masked_astar(&grid, &grid, (0, 2), (4, 2)).unwrap();
+        assert_eq!(length, 4);
+
+        // The shortcut through the middle row closes, replanning stays in the corridor
+        let mut blocked = grid.clone();
+        blocked.set_bit_value((2, 2), false);
+        let mask = corridor(&blocked, &path, 4);
+        let (replanned, length) = masked_astar(&blocked, &mask, (0, 2), (4, 2)).unwrap();
+        assert_eq!(length, 8);
+        assert!(replanned.iter().all(|n| mask.get_bit_value(*n)));
+        assert_eq!(masked_astar(&blocked, &corridor(&blocked, &path, 3), (0, 2), (4, 2)), None);
+    }
+}
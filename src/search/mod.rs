@@ -1,11 +1,79 @@
+//! # Search
+//! Search algorithms shared by every domain. The algorithms are generic over
+//! the node type, which only needs to be `Hash + Eq + Clone`, and take the
+//! domain as closures: an expander returning the successors of a node with
+//! their edge costs, a goal test and a heuristic. Grid domains expose their
+//! neighbours as such expanders, and a graph of any other type is searched by
+//! writing its own, such as `astar::astar` returning the path and its cost and
+//! `astar::astar_with_stats` also returning the expansion statistics. Domains
+//! implementing `domain::SearchDomain` are searched without writing closures.
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use pathfinding_rs::search::astar::astar_with_stats;
+//!
+//! let roads = HashMap::from([("home", vec![("shop", 4), ("park", 1)]), ("park", vec![("shop", 2)])]);
+//! let (result, stats) = astar_with_stats(|n: &&str| roads.get(n).cloned().unwrap_or_default(), "home", |n| *n == "shop", |_| 0);
+//! assert_eq!(result, Some((vec!["home", "park", "shop"], 3)));
+//! assert!(stats.expanded > 0);
+//! ```
+
 #![allow(dead_code)]
 
-use std::{collections::HashMap, hash::Hash, ops::Add, cmp::Ordering};
+use std::{collections::HashMap, hash::{BuildHasher, Hash}, ops::Add, cmp::Ordering};
 
 pub mod uninformed;
 pub mod astar;
-pub mod dstarlite;
+pub mod bidirectional;
+#[cfg(feature = "sampling")]
 pub mod samplestar;
+pub mod anytime;
+pub mod suboptimal;
+pub mod diverse;
+#[cfg(feature = "sampling")]
+pub mod risk;
+#[cfg(feature = "sampling")]
+pub mod probabilistic;
+#[cfg(feature = "sampling")]
+pub mod ensemble;
+pub mod danger;
+pub mod pareto;
+pub mod constrained;
+pub mod cache;
+pub mod openlist;
+pub mod stats;
+pub mod trace;
+pub mod terrain;
+pub mod temporal;
+pub mod congestion;
+pub mod adaptive;
+pub mod snap;
+pub mod masked;
+pub mod pruning;
+pub mod partial;
+pub mod anyangle;
+pub mod portfolio;
+pub mod arcflags;
+pub mod jps;
+pub mod thetastar;
+pub mod domain;
+pub mod batch;
+pub mod flowfield;
+pub mod stateid;
+pub mod searcher;
+pub mod query;
+pub mod incremental;
+#[cfg(feature = "sampling")]
+pub mod soak;
+
+/// A planner whose solutions are bounded-suboptimal, such as weighted A-Star
+pub trait Planner {
+    type Node;
+    type Cost;
+
+    /// Plans a path whose cost is at most `weight` times the optimal cost
+    fn plan(&mut self, weight: f64) -> Option<(Vec<Self::Node>, Self::Cost)>;
+}
 
 /// Reconstructs a path from a given node to the start node
 /// ## Arguments
@@ -13,9 +81,10 @@ pub mod samplestar;
 /// * `node` - The node to reconstruct the path from
 /// ## Returns
 /// A vector of nodes from the start to the given node
-pub(crate) fn reconstruct_path<N>(parent: HashMap<N, Option<N>>, mut node: N) -> Vec<N>
+pub(crate) fn reconstruct_path<N, S>(parent: HashMap<N, Option<N>, S>, mut node: N) -> Vec<N>
 where
     N: Hash + Eq + Clone,
+    S: BuildHasher,
 {
     let mut path = vec![node.clone()];
     while let Some(Some(prev)) = parent.get(&node) {
@@ -41,10 +110,10 @@ where
     C: Ord + Default + Clone + Add<Output = C>,
 {
     let mut path = vec![node.clone()];
-    let mut cost = C::default();
-    while let Some((Some(prev), c)) = parent.get(&node) {
+    // Costs are stored from the start, so the cost of the path is that of its last node
+    let cost = parent.get(&node).map(|(_, c)| c.clone()).unwrap_or_default();
+    while let Some((Some(prev), _)) = parent.get(&node) {
         path.push(prev.clone());
-        cost = cost + c.clone();
         node = prev.clone();
     }
     path.reverse();
@@ -68,4 +137,51 @@ impl<N: Eq, C: Ord> PartialOrd for SearchNodeState<N, C> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
+}
+
+/// A solution of a bounded-suboptimal or anytime search with the bound it proves
+/// ## Fields
+/// * `path` - The path from the start to the goal
+/// * `cost` - The cost of the path
+/// * `bound` - The cost is at most `bound` times the optimal cost
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoundedSolution<N, C> {
+    pub path: Vec<N>,
+    pub cost: C,
+    pub bound: f64,
+}
+
+impl<N, C> BoundedSolution<N, C> {
+    /// Checks if the solution is proven to be within a bound of the optimal cost
+    pub fn within(&self, bound: f64) -> bool {
+        self.bound <= bound
+    }
+}
+
+/// The suboptimality bound proven for a solution by the open list of a search
+/// ## Arguments
+/// * `cost` - The cost of the solution
+/// * `f_min` - The smallest f-value of the open list, which lower bounds the
+///   optimal cost for an admissible heuristic, `None` if the list is empty
+/// ## Returns
+/// The ratio of the cost to `f_min`, 1.0 once the open list is exhausted
+pub fn proven_bound(cost: f64, f_min: Option<f64>) -> f64 {
+    match f_min {
+        Some(f_min) if f_min < cost => if f_min > 0.0 { cost / f_min } else { f64::INFINITY },
+        _ => 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proven_bound() {
+        assert_eq!(proven_bound(12.0, Some(8.0)), 1.5);
+        assert_eq!((proven_bound(12.0, Some(14.0)), proven_bound(12.0, None)), (1.0, 1.0));
+        assert_eq!((proven_bound(0.0, Some(0.0)), proven_bound(3.0, Some(0.0))), (1.0, f64::INFINITY));
+        let solution = BoundedSolution { path: vec![(0, 0), (1, 0)], cost: 12, bound: proven_bound(12.0, Some(10.0)) };
+        assert!(solution.within(1.2) && !solution.within(1.1));
+    }
 }
\ No newline at end of file
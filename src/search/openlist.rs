@@ -0,0 +1,176 @@
+//! # Open Lists
+//! An open list ordered by f-value whose ties are broken by a pluggable policy.
+//! On grids many nodes share the same f-value, so the tie-breaking policy alone
+//! can change the number of expansions by large factors. Policies include:
+//! * HighG, prefers the deepest node, usually the fewest expansions on grids
+//! * LowH, prefers the node estimated closest to the goal
+//! * Fifo, prefers the node pushed first
+//! * Lifo, prefers the node pushed last
+//! * Random, breaks ties uniformly with a seeded generator
+
+use std::{cmp::{Ordering, Reverse}, collections::BinaryHeap, ops::Add};
+
+/// A policy breaking ties between open nodes of equal f-value, where nodes
+/// with a smaller key are popped first
+pub trait TieBreak<C> {
+    type Key: Ord;
+
+    /// The key of a node pushed with the given g and h values
+    fn key(&mut self, g: &C, h: &C) -> Self::Key;
+}
+
+/// Prefers nodes with a higher g-value
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HighG;
+
+impl<C: Ord + Clone> TieBreak<C> for HighG {
+    type Key = Reverse<C>;
+
+    fn key(&mut self, g: &C, _: &C) -> Self::Key {
+        Reverse(g.clone())
+    }
+}
+
+/// Prefers nodes with a lower h-value
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LowH;
+
+impl<C: Ord + Clone> TieBreak<C> for LowH {
+    type Key = C;
+
+    fn key(&mut self, _: &C, h: &C) -> Self::Key {
+        h.clone()
+    }
+}
+
+/// Prefers nodes pushed earlier
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Fifo(usize);
+
+impl<C> TieBreak<C> for Fifo {
+    type Key = usize;
+
+    fn key(&mut self, _: &C, _: &C) -> Self::Key {
+        self.0 += 1;
+        self.0
+    }
+}
+
+/// Prefers nodes pushed later
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lifo(usize);
+
+impl<C> TieBreak<C> for Lifo {
+    type Key = Reverse<usize>;
+
+    fn key(&mut self, _: &C, _: &C) -> Self::Key {
+        self.0 += 1;
+        Reverse(self.0)
+    }
+}
+
+/// Breaks ties uniformly at random with a seeded generator
+#[cfg(feature = "sampling")]
+pub struct Random(rand::rngs::StdRng);
+
+#[cfg(feature = "sampling")]
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random(rand::SeedableRng::seed_from_u64(seed))
+    }
+}
+
+#[cfg(feature = "sampling")]
+impl<C> TieBreak<C> for Random {
+    type Key = u64;
+
+    fn key(&mut self, _: &C, _: &C) -> Self::Key {
+        rand::Rng::gen(&mut self.0)
+    }
+}
+
+/// An entry of the open list, ordered so the smallest (f, key) is at the top of the heap
+struct Entry<N, C, K> {
+    f: C,
+    key: K,
+    node: N,
+}
+
+impl<N, C: Ord, K: Ord> Ord for Entry<N, C, K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.f, &self.key).cmp(&(&other.f, &other.key)).reverse()
+    }
+}
+
+impl<N, C: Ord, K: Ord> PartialOrd for Entry<N, C, K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N, C: Ord, K: Ord> PartialEq for Entry<N, C, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<N, C: Ord, K: Ord> Eq for Entry<N, C, K> {}
+
+/// A min-heap of nodes by f-value with ties broken by a policy
+pub struct OpenList<N, C, P: TieBreak<C>> {
+    heap: BinaryHeap<Entry<N, C, P::Key>>,
+    policy: P,
+}
+
+impl<N, C, P> OpenList<N, C, P>
+where
+    C: Ord + Clone + Add<Output = C>,
+    P: TieBreak<C>,
+{
+    pub fn new(policy: P) -> Self {
+        OpenList { heap: BinaryHeap::new(), policy }
+    }
+
+    /// Pushes a node with its g and h values
+    pub fn push(&mut self, node: N, g: C, h: C) {
+        let key = self.policy.key(&g, &h);
+        self.heap.push(Entry { f: g + h, key, node });
+    }
+
+    /// Pops the node with the lowest f-value, breaking ties by the policy
+    /// ## Returns
+    /// The node and its f-value
+    pub fn pop(&mut self) -> Option<(N, C)> {
+        self.heap.pop().map(|e| (e.node, e.f))
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_list_tie_breaking() {
+        fn order<P: TieBreak<usize>>(policy: P) -> String {
+            let mut open = OpenList::new(policy);
+            for (n, g, h) in [('a', 1, 3), ('b', 3, 1), ('c', 2, 2), ('d', 0, 1)] {
+                open.push(n, g, h);
+            }
+            std::iter::from_fn(|| open.pop().map(|(n, _)| n)).collect()
+        }
+        assert_eq!(order(HighG), "dbca");
+        assert_eq!(order(LowH), "dbca");
+        assert_eq!(order(Fifo::default()), "dabc");
+        assert_eq!(order(Lifo::default()), "dcba");
+        #[cfg(feature = "sampling")]
+        assert_eq!(order(Random::new(1)), order(Random::new(1)));
+    }
+}
@@ -0,0 +1,130 @@
+//! # Multi-Objective Search
+//! A bi-objective label-setting search in the style of NAMOA*. Instead of a
+//! single cost per node, every node keeps the set of non-dominated cost pairs
+//! (labels) of the paths reaching it, and labels are expanded in lexicographic
+//! order of their estimated total cost. The search returns the Pareto set, one
+//! path for every cost pair where neither objective can be improved without
+//! worsening the other, such as distance against risk or energy.
+
+use std::{collections::{BinaryHeap, HashMap}, hash::Hash, ops::Add};
+
+use super::SearchNodeState;
+
+/// A path reaching a node with a cost pair
+struct Label<N, C> {
+    node: N,
+    cost: (C, C),
+    parent: Option<usize>,
+    removed: bool,
+}
+
+/// Checks if a cost pair is no worse than another in both objectives
+fn covers<C: Ord>(a: &(C, C), b: &(C, C)) -> bool {
+    a.0 <= b.0 && a.1 <= b.1
+}
+
+/// Bi-objective search for the Pareto set of paths
+/// ## Arguments
+/// * `expander` - A function that returns the adjacent nodes of a node with the pair of costs of each move
+/// * `start` - The start node
+/// * `goal` - A function that returns whether or not a given node is the goal
+/// * `heuristic` - A function that returns an admissible estimate of both costs to the goal
+/// ## Returns
+/// The Pareto set of paths with their cost pairs, sorted by the first objective
+pub fn pareto_search<E, I, C, N, G, H>(
+    mut expander: E,
+    start: N,
+    goal: G,
+    heuristic: H,
+) -> Vec<(Vec<N>, (C, C))>
+where
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, (C, C))>,
+    C: Ord + Default + Clone + Add<Output = C>,
+    N: Hash + Clone + Eq,
+    G: Fn(&N) -> bool,
+    H: Fn(&N) -> (C, C),
+{
+    let estimate = |cost: &(C, C), node: &N| {
+        let h = heuristic(node);
+        (cost.0.clone() + h.0, cost.1.clone() + h.1)
+    };
+    let mut labels = vec![Label { node: start.clone(), cost: (C::default(), C::default()), parent: None, removed: false }];
+    let mut node_labels: HashMap<N, Vec<usize>> = HashMap::from([(start.clone(), vec![0])]);
+    let mut open = BinaryHeap::from([SearchNodeState { node: 0, cost: estimate(&labels[0].cost, &start) }]);
+    let mut solutions: Vec<usize> = Vec::new();
+
+    while let Some(SearchNodeState { node: id, cost: f }) = open.pop() {
+        if labels[id].removed || solutions.iter().any(|s| covers(&labels[*s].cost, &f)) {
+            continue;
+        }
+        let node = labels[id].node.clone();
+        if goal(&node) {
+            solutions.push(id);
+            continue;
+        }
+        for (child, (c1, c2)) in expander(&node) {
+            let cost = (labels[id].cost.0.clone() + c1, labels[id].cost.1.clone() + c2);
+            let f = estimate(&cost, &child);
+            if solutions.iter().any(|s| covers(&labels[*s].cost, &f)) {
+                continue;
+            }
+            let existing = node_labels.entry(child.clone()).or_default();
+            if existing.iter().any(|l| covers(&labels[*l].cost, &cost)) {
+                continue;
+            }
+            existing.retain(|l| {
+                let dominated = covers(&cost, &labels[*l].cost);
+                labels[*l].removed |= dominated;
+                !dominated
+            });
+            existing.push(labels.len());
+            open.push(SearchNodeState { node: labels.len(), cost: f });
+            labels.push(Label { node: child, cost, parent: Some(id), removed: false });
+        }
+    }
+
+    let mut paths = solutions.into_iter().map(|id| {
+        let cost = labels[id].cost.clone();
+        let mut path = vec![labels[id].node.clone()];
+        let mut current = id;
+        while let Some(parent) = labels[current].parent {
+            path.push(labels[parent].node.clone());
+            current = parent;
+        }
+        path.reverse();
+        (path, cost)
+    }).collect::<Vec<_>>();
+    paths.sort_by(|a, b| a.1.cmp(&b.1));
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::{bitpackedgrid::BitPackedGrid, grid::Grid};
+    use crate::heuristics::distance::manhattan_distance;
+
+    #[test]
+    fn test_pareto_search() {
+        let grid = BitPackedGrid::new_from_string(".....\n.....\n.....\n".to_string());
+        let risk = Grid::from_fn(5, 3, |(x, y)| match (x, y) {
+            (1..=3, 1) => 5,
+            (2, 0) => 1,
+            _ => 0,
+        });
+        let front = pareto_search(
+            |n| grid.adjacent(*n, false)
+                .filter(|n| grid.bounds_check(*n))
+                .map(|n| (n, (1, risk[n])))
+                .collect::<Vec<_>>(),
+            (0, 1),
+            |n| *n == (4, 1),
+            |n| (manhattan_distance(*n, (4, 1)), 0),
+        );
+        let costs = front.iter().map(|(_, c)| *c).collect::<Vec<_>>();
+        assert_eq!(costs, vec![(4, 15), (6, 0)]);
+        assert_eq!(front[0].0, vec![(0, 1), (1, 1), (2, 1), (3, 1), (4, 1)]);
+        assert!(front[1].0.iter().all(|n| risk[*n] == 0));
+    }
+}
@@ -0,0 +1,127 @@
+//! # Partial Expansion A-Star
+//! A-Star that only pushes the successors of a node whose f-value is within a
+//! cutoff of the node's stored f-value, following Yoshizumi, Miura and Ishida,
+//! "A* with Partial Expansion for Large Branching Factor Problems" (2000). The
+//! node is pushed back with the smallest f-value of its remaining successors
+//! instead, so the open list holds far fewer nodes on domains with many
+//! successors, such as voxel grids and time-expanded graphs, at the cost of
+//! regenerating the successors of nodes that are expanded more than once. A
+//! cutoff of zero gives PEA*, and larger cutoffs trade memory for fewer
+//! re-expansions.
+
+use std::{collections::{BinaryHeap, HashMap, HashSet}, hash::Hash, ops::Add};
+
+use super::{reconstruct_path_with_cost, SearchNodeState};
+use super::stats::{timed, SearchStats};
+
+/// Partial Expansion A-Star Search
+/// ## Arguments
+/// * `expander` - A function that returns an iterator over the nodes adjacent to a given node
+/// * `start` - The start node
+/// * `goal` - A function that returns whether or not a given node is the goal
+/// * `heuristic` - A consistent heuristic
+/// * `cutoff` - How far above a node's stored f-value its successors are still pushed
+/// ## Returns
+/// An optional vector of nodes from the start to the goal with its cost, and
+/// the statistics of the search where `expanded` counts partial expansions
+pub fn partial_expansion_astar<E, I, C, N, G, H>(
+    mut expander: E,
+    start: N,
+    goal: G,
+    heuristic: H,
+    cutoff: C,
+) -> (Option<(Vec<N>, C)>, SearchStats)
+where
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+    C: Ord + Default + Clone + Add<Output = C>,
+    N: Hash + Clone + Eq,
+    G: Fn(&N) -> bool,
+    H: Fn(&N) -> C,
+{
+    timed(move || {
+        let mut stats = SearchStats { heuristic_calls: 1, pushed: 1, peak_open: 1, ..Default::default() };
+        let f = heuristic(&start);
+        let mut stored = HashMap::from([(start.clone(), f.clone())]);
+        let mut open = BinaryHeap::from([SearchNodeState { cost: f, node: start.clone() }]);
+        let mut previous = HashMap::from([(start, (None, C::default()))]);
+        let mut closed = HashSet::new();
+        while let Some(SearchNodeState { node, cost }) = open.pop() {
+            stats.popped += 1;
+            // Entries are stale once the node is closed or its stored f-value changes
+            if closed.contains(&node) || stored[&node] != cost {
+                stats.duplicates += 1;
+                continue;
+            }
+            if goal(&node) {
+                return (Some(reconstruct_path_with_cost(previous, node)), stats);
+            }
+            stats.expanded += 1;
+            let threshold = cost.clone() + cutoff.clone();
+            let g = previous[&node].1.clone();
+            let mut deferred: Option<C> = None;
+            for (child, edge) in expander(&node) {
+                stats.generated += 1;
+                let new_cost = g.clone() + edge;
+                if previous.get(&child).is_some_and(|(_, c)| new_cost >= *c) {
+                    continue;
+                }
+                stats.heuristic_calls += 1;
+                let child_f = new_cost.clone() + heuristic(&child);
+                if child_f > threshold {
+                    if deferred.as_ref().is_none_or(|d| child_f < *d) {
+                        deferred = Some(child_f);
+                    }
+                    continue;
+                }
+                previous.insert(child.clone(), (Some(node.clone()), new_cost));
+                stored.insert(child.clone(), child_f.clone());
+                closed.remove(&child);
+                stats.pushed += 1;
+                open.push(SearchNodeState { node: child, cost: child_f });
+                stats.open_size(open.len());
+            }
+            match deferred {
+                Some(next) => {
+                    stored.insert(node.clone(), next.clone());
+                    stats.pushed += 1;
+                    open.push(SearchNodeState { node, cost: next });
+                    stats.open_size(open.len());
+                }
+                None => {
+                    closed.insert(node);
+                }
+            }
+        }
+        (None, stats)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::bitpackedgrid::BitPackedGrid;
+    use crate::heuristics::distance::manhattan_distance;
+    use crate::search::astar::astar_with_stats;
+
+    #[test]
+    fn test_partial_expansion_astar() {
+        let grid = BitPackedGrid::new_from_string(
+            "........\n.@@@@@@.\n......@.\n.@@@@.@.\n........\n".to_string()
+        );
+        let goal = (7, 4);
+        let expander = |n: &(usize, usize)| grid.adjacent1(*n).filter(|(n, _)| grid.bounds_check(*n)).collect::<Vec<_>>();
+        let h = |n: &(usize, usize)| manhattan_distance(*n, goal);
+        let (optimal, astar_stats) = astar_with_stats(expander, (0, 0), |n| *n == goal, h);
+        let optimal = optimal.unwrap();
+        for cutoff in [0, 2] {
+            let (result, stats) = partial_expansion_astar(expander, (0, 0), |n| *n == goal, h, cutoff);
+            let (path, cost) = result.unwrap();
+            assert_eq!(cost, optimal.1);
+            assert_eq!(path.len(), cost + 1);
+            assert!(stats.pushed - stats.popped <= astar_stats.pushed - astar_stats.popped);
+        }
+        let (result, _) = partial_expansion_astar(expander, (0, 0), |n| *n == (1, 1), |_| 0, 0);
+        assert_eq!(result, None);
+    }
+}
@@ -0,0 +1,266 @@
+//! # Algorithm Portfolios
+//! A planner that picks one of several grid planners per query from cheap
+//! features of the map and query, following a rule table where the first
+//! matching rule wins. The features are the size of the start's connected
+//! component, a distance bucket and the obstacle density of the box spanned by
+//! the start and goal. To tune the rule table, queries can instead run every
+//! planner and record which one would have won, the fastest planner finding a
+//! shortest path among those found.
+
+use std::{collections::HashMap, ops::Range, time::{Duration, Instant}};
+
+use crate::analysis::statistics::ComponentMap;
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+use crate::heuristics::distance::manhattan_distance;
+
+/// A planner on a grid map from a start to a goal
+pub type GridPlanner = Box<dyn FnMut(&BitPackedGrid, Cell, Cell) -> Option<Vec<Cell>>>;
+
+/// The features of a query used to pick a planner
+/// ## Fields
+/// * `component` - The number of cells in the connected component of the start
+/// * `distance_bucket` - The bucket of the manhattan distance from the start to
+///   the goal, 0 for a distance of 0 and `b` for distances in `[2^(b-1), 2^b)`
+/// * `density` - The fraction of obstacles in the box spanned by the start and goal
+/// * `connected` - Whether the start and goal are in the same component
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueryFeatures {
+    pub component: usize,
+    pub distance_bucket: u32,
+    pub density: f64,
+    pub connected: bool,
+}
+
+/// A rule of a portfolio, choosing a planner when every feature of a query is
+/// within its range. Ranges are unbounded until narrowed.
+/// ## Fields
+/// * `planner` - The name of the planner chosen
+/// * `component` - The range of component sizes
+/// * `distance_bucket` - The range of distance buckets
+/// * `density` - The range of obstacle densities
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub planner: String,
+    pub component: Range<usize>,
+    pub distance_bucket: Range<u32>,
+    pub density: Range<f64>,
+}
+
+impl Rule {
+    /// Creates a rule choosing a planner for every query
+    pub fn new(planner: &str) -> Self {
+        Rule {
+            planner: planner.to_string(),
+            component: 0..usize::MAX,
+            distance_bucket: 0..u32::MAX,
+            density: 0.0..f64::INFINITY,
+        }
+    }
+
+    /// Restricts the rule to a range of component sizes
+    pub fn component(mut self, range: Range<usize>) -> Self {
+        self.component = range;
+        self
+    }
+
+    /// Restricts the rule to a range of distance buckets
+    pub fn distance_bucket(mut self, range: Range<u32>) -> Self {
+        self.distance_bucket = range;
+        self
+    }
+
+    /// Restricts the rule to a range of obstacle densities
+    pub fn density(mut self, range: Range<f64>) -> Self {
+        self.density = range;
+        self
+    }
+
+    /// Checks if the features of a query match the rule
+    pub fn matches(&self, features: &QueryFeatures) -> bool {
+        self.component.contains(&features.component)
+            && self.distance_bucket.contains(&features.distance_bucket)
+            && self.density.contains(&features.density)
+    }
+}
+
+/// The outcome of a query run on every planner of a portfolio
+/// ## Fields
+/// * `features` - The features of the query
+/// * `chosen` - The planner the rules chose
+/// * `winner` - The fastest planner finding a shortest path, if any found one
+/// * `times` - The time each planner took
+#[derive(Clone, Debug, PartialEq)]
+pub struct PortfolioRecord {
+    pub features: QueryFeatures,
+    pub chosen: String,
+    pub winner: Option<String>,
+    pub times: HashMap<String, Duration>,
+}
+
+/// A portfolio of named planners on a grid map with a rule table
+/// ## Fields
+/// * `rules` - The rules checked in order, the first matching rule chooses
+/// * `fallback` - The planner chosen when no rule matches
+/// * `records` - The records of every query run with `plan_recorded`
+pub struct Portfolio {
+    grid: BitPackedGrid,
+    components: ComponentMap,
+    planners: Vec<(String, GridPlanner)>,
+    pub rules: Vec<Rule>,
+    pub fallback: String,
+    pub records: Vec<PortfolioRecord>,
+}
+
+impl Portfolio {
+    /// Creates a portfolio over a grid map without planners, labelling its
+    /// connected components once
+    /// ## Arguments
+    /// * `grid` - The grid map queries are planned on
+    /// * `fallback` - The planner chosen when no rule matches
+    pub fn new(grid: BitPackedGrid, fallback: &str) -> Self {
+        let components = ComponentMap::new(&grid);
+        Portfolio { grid, components, planners: Vec::new(), rules: Vec::new(), fallback: fallback.to_string(), records: Vec::new() }
+    }
+
+    /// Adds a named planner
+    pub fn with_planner(mut self, name: &str, planner: GridPlanner) -> Self {
+        self.planners.push((name.to_string(), planner));
+        self
+    }
+
+    /// Adds a rule after the existing rules
+    pub fn with_rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// The features of a query
+    pub fn features(&self, start: Cell, goal: Cell) -> QueryFeatures {
+        let component = self.components.labels[start.0][start.1]
+            .map_or(0, |label| self.components.sizes[&label]);
+        let distance = manhattan_distance(start, goal);
+        let (xs, ys) = (start.0.min(goal.0)..=start.0.max(goal.0), start.1.min(goal.1)..=start.1.max(goal.1));
+        let area = xs.clone().count() * ys.clone().count();
+        let obstacles = xs.flat_map(|x| ys.clone().map(move |y| (x, y)))
+            .filter(|n| !self.grid.get_bit_value(*n))
+            .count();
+        QueryFeatures {
+            component,
+            distance_bucket: usize::BITS - distance.leading_zeros(),
+            density: obstacles as f64 / area as f64,
+            connected: self.components.connected(start, goal),
+        }
+    }
+
+    /// The name of the planner the rules choose for a query
+    pub fn choose(&self, features: &QueryFeatures) -> &str {
+        self.rules.iter()
+            .find(|rule| rule.matches(features))
+            .map_or(&self.fallback, |rule| &rule.planner)
+    }
+
+    /// The index of a planner by name
+    fn planner(&self, name: &str) -> usize {
+        self.planners.iter()
+            .position(|(n, _)| n == name)
+            .unwrap_or_else(|| panic!("No planner named {} in the portfolio", name))
+    }
+
+    /// Plans a query with the planner the rules choose
+    /// ## Returns
+    /// The name of the chosen planner and its path, which is `None` without
+    /// running any planner when the start and goal are disconnected
+    pub fn plan(&mut self, start: Cell, goal: Cell) -> (String, Option<Vec<Cell>>) {
+        let features = self.features(start, goal);
+        let chosen = self.choose(&features).to_string();
+        if !features.connected {
+            return (chosen, None);
+        }
+        let index = self.planner(&chosen);
+        let path = (self.planners[index].1)(&self.grid, start, goal);
+        (chosen, path)
+    }
+
+    /// Plans a query with every planner, recording which would have won
+    /// ## Returns
+    /// The path of the planner the rules choose
+    pub fn plan_recorded(&mut self, start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+        let features = self.features(start, goal);
+        let chosen = self.choose(&features).to_string();
+        let index = self.planner(&chosen);
+        let mut times = HashMap::new();
+        let mut results = Vec::new();
+        for (name, planner) in self.planners.iter_mut() {
+            let timer = Instant::now();
+            let path = planner(&self.grid, start, goal);
+            let time = timer.elapsed();
+            times.insert(name.clone(), time);
+            results.push((name.clone(), path, time));
+        }
+        let shortest = results.iter().filter_map(|(_, p, _)| p.as_ref().map(Vec::len)).min();
+        let winner = results.iter()
+            .filter(|(_, p, _)| p.as_ref().map(Vec::len) == shortest && shortest.is_some())
+            .min_by_key(|(_, _, time)| *time)
+            .map(|(name, _, _)| name.clone());
+        self.records.push(PortfolioRecord { features, chosen, winner, times });
+        results.swap_remove(index).1
+    }
+
+    /// The fraction of recorded queries with a winner where the rules chose it
+    pub fn accuracy(&self) -> f64 {
+        let decided = self.records.iter().filter(|r| r.winner.is_some());
+        let (hits, total) = decided.fold((0, 0), |(h, t), r| (h + (r.winner.as_ref() == Some(&r.chosen)) as usize, t + 1));
+        if total == 0 { 0.0 } else { hits as f64 / total as f64 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{astar::astar, uninformed::bfs};
+
+    fn portfolio() -> Portfolio {
+        let grid = BitPackedGrid::new_from_string("......@...\n.@@...@...\n......@...\n".to_string());
+        Portfolio::new(grid, "astar")
+            .with_planner("astar", Box::new(|grid: &BitPackedGrid, start, goal| {
+                astar(|n| grid.adjacent1(*n), start, |n| *n == goal, |n| manhattan_distance(*n, goal)).map(|(path, _)| path)
+            }))
+            .with_planner("bfs", Box::new(|grid: &BitPackedGrid, start, goal| {
+                bfs(|n| grid.adjacent(*n, false).filter(|m| grid.bounds_check(*m)).collect::<Vec<_>>(), start, |n| *n == goal)
+                    .map(|(path, _)| path)
+            }))
+            .with_rule(Rule::new("bfs").distance_bucket(0..3))
+    }
+
+    #[test]
+    fn test_portfolio_features() {
+        let portfolio = portfolio();
+        let features = portfolio.features((0, 0), (2, 2));
+        assert_eq!(features.component, 16);
+        assert_eq!(features.distance_bucket, 3);
+        assert!((features.density - 2.0 / 9.0).abs() < 1e-9);
+        assert!(features.connected);
+        assert!(!portfolio.features((0, 0), (8, 0)).connected);
+        assert_eq!(portfolio.choose(&features), "astar");
+        assert_eq!(portfolio.choose(&portfolio.features((0, 0), (1, 0))), "bfs");
+    }
+
+    #[test]
+    fn test_portfolio_plan() {
+        let mut portfolio = portfolio();
+        let (chosen, path) = portfolio.plan((0, 0), (5, 2));
+        assert_eq!(chosen, "astar");
+        assert_eq!(path.unwrap().len(), 8);
+        assert_eq!(portfolio.plan((0, 0), (8, 0)), ("astar".to_string(), None));
+
+        let path = portfolio.plan_recorded((0, 0), (0, 2)).unwrap();
+        assert_eq!(path.len(), 3);
+        let record = &portfolio.records[0];
+        assert_eq!(record.chosen, "bfs");
+        assert_eq!(record.times.len(), 2);
+        assert!(record.winner.is_some());
+        portfolio.plan_recorded((0, 0), (8, 0));
+        assert_eq!(portfolio.records[1].winner, None);
+        assert!((0.0..=1.0).contains(&portfolio.accuracy()));
+    }
+}
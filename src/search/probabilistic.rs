@@ -0,0 +1,111 @@
+//! # Probabilistic Planning
+//! Planning directly on the occupancy probabilities of a sampling grid, rather
+//! than on worlds sampled from it, so path length can be traded against the
+//! risk of collision without Monte Carlo sampling. Each cell entered is scored
+//! by its chance `p` of being free, either as a log risk `1 + λ·(-ln p)`, where
+//! the penalties of a path sum to `-λ` times the log of its chance of being
+//! traversable, or as the expected cost `1 + (1 - p)·penalty` of a path where
+//! a blocked cell costs a penalty. Cells that are never free are impassable.
+//! Costs are scaled to integers so the search stays exact.
+
+use crate::domains::{Cell, samplegrid::SampleGrid};
+use crate::heuristics::distance::manhattan_distance;
+
+use super::astar::astar;
+
+/// The scale of the integer costs searched, each move costing at least this
+const SCALE: f64 = 1000.0;
+
+/// The score of entering a cell with a chance of being free
+/// * `LogRisk(λ)` - A move plus λ times the negative log of the chance
+/// * `ExpectedPenalty(penalty)` - A move plus the penalty times the chance of
+///   the cell being blocked
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProbabilityCost {
+    LogRisk(f32),
+    ExpectedPenalty(f32),
+}
+
+impl ProbabilityCost {
+    /// The cost of entering a cell with a chance of being free
+    pub fn step(&self, probability: f32) -> f64 {
+        let probability = probability.clamp(0.0, 1.0) as f64;
+        match *self {
+            ProbabilityCost::LogRisk(lambda) => 1.0 - lambda.max(0.0) as f64 * probability.ln(),
+            ProbabilityCost::ExpectedPenalty(penalty) => 1.0 + (1.0 - probability) * penalty.max(0.0) as f64,
+        }
+    }
+}
+
+/// A path planned on occupancy probabilities
+/// ## Fields
+/// * `path` - The cells of the path from the start to the goal
+/// * `length` - The number of moves of the path
+/// * `probability` - The chance of every cell entered being free, assuming
+///   cells are independent
+/// * `cost` - The total cost of the path under the objective planned with
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProbabilityPath {
+    pub path: Vec<Cell>,
+    pub length: usize,
+    pub probability: f32,
+    pub cost: f64,
+}
+
+/// The chance of every cell entered by a path being free
+pub fn path_probability(grid: &SampleGrid, path: &[Cell]) -> f32 {
+    path.iter().skip(1).map(|n| grid.sample_probability(*n)).product()
+}
+
+/// Plans a 4-connected path minimising a probability cost
+/// ## Arguments
+/// * `grid` - The sampling grid whose probabilities are planned on
+/// * `start` - The start cell
+/// * `goal` - The goal cell
+/// * `objective` - The cost of entering each cell
+pub fn probability_plan(grid: &SampleGrid, start: Cell, goal: Cell, objective: ProbabilityCost) -> Option<ProbabilityPath> {
+    let in_bounds = |(x, y): Cell| x < grid.width && y < grid.height;
+    if !in_bounds(start) || !in_bounds(goal) {
+        return None;
+    }
+    // Rounding up keeps every move at least the scale, so the heuristic stays admissible
+    let step = |n: Cell| (objective.step(grid.sample_probability(n)) * SCALE).ceil().min(u64::MAX as f64) as u64;
+    let (path, _) = astar(
+        |&(x, y)| [(x + 1, y), (x, y + 1), (x.wrapping_sub(1), y), (x, y.wrapping_sub(1))].into_iter()
+            .filter(|n| in_bounds(*n) && grid.sample_probability(*n) > 0.0)
+            .map(|n| (n, step(n)))
+            .collect::<Vec<_>>(),
+        start,
+        |n| *n == goal,
+        |n| manhattan_distance(*n, goal) as u64 * SCALE as u64,
+    )?;
+    let cost = path.iter().skip(1).map(|n| objective.step(grid.sample_probability(*n))).sum();
+    Some(ProbabilityPath { length: path.len() - 1, probability: path_probability(grid, &path), path, cost })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::bitpackedgrid::BitPackedGrid;
+
+    #[test]
+    fn test_probability_plan() {
+        // A short row through uncertain cells and a detour through known free cells
+        let ground_truth = BitPackedGrid::new_from_string(".....\n.....\n".to_string());
+        let grid = SampleGrid::new_from_grid(
+            (0..5).map(|x| vec![if (1..=3).contains(&x) { 0.7 } else { 1.0 }, 1.0]).collect(),
+            ground_truth,
+        );
+        let (start, goal) = ((0, 0), (4, 0));
+        let short = probability_plan(&grid, start, goal, ProbabilityCost::LogRisk(0.0)).unwrap();
+        assert_eq!((short.length, short.path[1]), (4, (1, 0)));
+        assert!((short.probability - 0.7f32.powi(3)).abs() < 1e-6);
+        let safe = probability_plan(&grid, start, goal, ProbabilityCost::LogRisk(5.0)).unwrap();
+        assert_eq!((safe.length, safe.probability), (6, 1.0));
+        assert_eq!(safe.cost, 6.0);
+        let expected = probability_plan(&grid, start, goal, ProbabilityCost::ExpectedPenalty(1.0)).unwrap();
+        assert_eq!(expected.length, 4);
+        assert!((expected.cost - 4.9).abs() < 1e-6);
+        assert_eq!(probability_plan(&grid, start, goal, ProbabilityCost::ExpectedPenalty(10.0)).unwrap().length, 6);
+    }
+}
@@ -0,0 +1,271 @@
+//! # Pruning Preprocessing
+//! Preprocessing passes that block the cells of a grid map no shortest path
+//! needs, so 4-connected searches with unit costs expand fewer nodes:
+//! * Dead ends, the corridors and trees of cells peeled off the map one cell
+//!   with at most one free neighbour at a time, which a shortest path only
+//!   enters when it starts or ends inside them
+//! * Swamps, following Pochter, Zohar, Rosenschein and Felner, "Search Space
+//!   Reduction Using Swamp Hierarchies" (2010), regions whose removal keeps
+//!   the distance between every pair of cells around them
+//!
+//! A pass is a `Preprocessor` and passes are chained with `then`, each
+//! running on the map left by the one before. The pruned map can be given to
+//! other precomputations, such as `JumpPointSearch::new` or `HpaStar::new`,
+//! and `PruningMask::grid_for` restores the regions a query starts or ends in.
+
+use std::collections::VecDeque;
+
+use crate::analysis::{regions::Regions, statistics::{component_labels, grid_distances}};
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid, grid::Grid};
+use crate::heuristics::distance::manhattan_distance;
+
+use super::astar::astar;
+
+/// A region of pruned cells
+/// ## Fields
+/// * `cells` - The cells of the region
+/// * `stage` - The index of the pass that pruned the region in its chain
+/// * `independent` - Whether the regions of the pass can be restored one at
+///   a time, as for dead ends, rather than with every region pruned after it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrunedRegion {
+    pub cells: Vec<Cell>,
+    pub stage: usize,
+    pub independent: bool,
+}
+
+/// The cells pruned from a grid map by a chain of preprocessing passes
+/// ## Fields
+/// * `pruned` - The grid map with every pruned cell blocked
+/// * `regions` - The pruned regions in the order they were pruned
+/// * `labels` - The region of each pruned cell
+#[derive(Clone, Debug)]
+pub struct PruningMask {
+    pub pruned: BitPackedGrid,
+    pub regions: Vec<PrunedRegion>,
+    pub labels: Grid<Option<usize>>,
+}
+
+impl PruningMask {
+    /// A mask of a grid map pruning nothing
+    pub fn new(grid: &BitPackedGrid) -> Self {
+        let labels = Grid::new(grid.original_width, grid.original_height, None);
+        PruningMask { pruned: grid.clone(), regions: Vec::new(), labels }
+    }
+
+    /// Blocks the cells of a region
+    fn prune(&mut self, cells: Vec<Cell>, stage: usize, independent: bool) {
+        for &cell in &cells {
+            self.pruned.set_bit_value(cell, false);
+            self.labels[cell] = Some(self.regions.len());
+        }
+        self.regions.push(PrunedRegion { cells, stage, independent });
+    }
+
+    /// The number of pruned cells
+    pub fn pruned_cells(&self) -> usize {
+        self.regions.iter().map(|r| r.cells.len()).sum()
+    }
+
+    /// The pruned region of a cell, `None` for cells that are not pruned
+    pub fn region(&self, cell: Cell) -> Option<usize> {
+        self.labels.get(cell).copied().flatten()
+    }
+
+    /// The grid to search between two cells, the pruned grid with the regions
+    /// of the endpoints restored. Restoring a region restores every region
+    /// pruned after it by the same pass, as its removal was only checked with
+    /// them in place, and every region pruned by later passes.
+    pub fn grid_for(&self, start: Cell, goal: Cell) -> BitPackedGrid {
+        let endpoints = [self.region(start), self.region(goal)];
+        let Some(first) = endpoints.iter().flatten().min().copied() else { return self.pruned.clone() };
+        let stage = self.regions[first].stage;
+        let mut grid = self.pruned.clone();
+        for (i, region) in self.regions.iter().enumerate() {
+            let restored = region.stage > stage
+                || region.stage == stage && if region.independent { endpoints.contains(&Some(i)) } else { i >= first };
+            if restored {
+                region.cells.iter().for_each(|cell| grid.set_bit_value(*cell, true));
+            }
+        }
+        grid
+    }
+
+    /// Plans a 4-connected path with unit costs on the grid for its endpoints
+    pub fn search(&self, start: Cell, goal: Cell) -> Option<(Vec<Cell>, usize)> {
+        let grid = self.grid_for(start, goal);
+        astar(
+            |n: &Cell| grid.adjacent1(*n).filter(|(n, _)| grid.bounds_check(*n)).collect::<Vec<_>>(),
+            start,
+            |n| *n == goal,
+            |n| manhattan_distance(*n, goal),
+        )
+    }
+}
+
+/// A preprocessing pass over a grid map
+pub trait Preprocessor {
+    /// Prunes cells of the grid left by earlier passes into a mask
+    /// ## Arguments
+    /// * `mask` - The mask of the earlier passes
+    /// * `stage` - The index of the pass in its chain
+    fn prune(&self, mask: &mut PruningMask, stage: usize);
+
+    /// The number of passes the preprocessor runs
+    fn stages(&self) -> usize {
+        1
+    }
+
+    /// Runs the preprocessor on a grid map
+    fn preprocess(&self, grid: &BitPackedGrid) -> PruningMask {
+        let mut mask = PruningMask::new(grid);
+        self.prune(&mut mask, 0);
+        mask
+    }
+
+    /// Runs another preprocessor on the grid left by this one
+    fn then<P: Preprocessor>(self, next: P) -> Chain<Self, P>
+    where
+        Self: Sized,
+    {
+        Chain(self, next)
+    }
+}
+
+/// Two preprocessors run one after the other
+pub struct Chain<A, B>(pub A, pub B);
+
+impl<A: Preprocessor, B: Preprocessor> Preprocessor for Chain<A, B> {
+    fn prune(&self, mask: &mut PruningMask, stage: usize) {
+        self.0.prune(mask, stage);
+        self.1.prune(mask, stage + self.0.stages());
+    }
+
+    fn stages(&self) -> usize {
+        self.0.stages() + self.1.stages()
+    }
+}
+
+/// Prunes dead ends, each connected set of peeled cells being a region
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DeadEnds;
+
+impl Preprocessor for DeadEnds {
+    fn prune(&self, mask: &mut PruningMask, stage: usize) {
+        let grid = &mask.pruned;
+        let degree = |grid: &BitPackedGrid, n: Cell| grid.adjacent(n, false).filter(|m| grid.bounds_check(*m)).count();
+        let mut remaining = grid.clone();
+        let mut peeled = BitPackedGrid::new(grid.original_width, grid.original_height);
+        let mut queue = grid.free_cells().filter(|n| degree(grid, *n) <= 1).collect::<VecDeque<_>>();
+        while let Some(node) = queue.pop_front() {
+            if !remaining.get_bit_value(node) || degree(&remaining, node) > 1 {
+                continue;
+            }
+            remaining.set_bit_value(node, false);
+            peeled.set_bit_value(node, true);
+            queue.extend(grid.adjacent(node, false).filter(|n| remaining.bounds_check(*n) && remaining.get_bit_value(*n)));
+        }
+        for component in components(&peeled) {
+            mask.prune(component, stage, true);
+        }
+    }
+}
+
+/// The connected sets of free cells of a grid
+fn components(grid: &BitPackedGrid) -> Vec<Vec<Cell>> {
+    let (labels, count) = component_labels(grid);
+    let mut components = vec![Vec::new(); count];
+    for (x, y) in grid.free_cells() {
+        if let Some(label) = labels[x][y] {
+            components[label].push((x, y));
+        }
+    }
+    components
+}
+
+/// Prunes swamps among the rectangles of `analysis::regions::Regions`. Each
+/// candidate is checked with a breadth-first search from every cell bordering
+/// it, so the pass suits maps that are preprocessed once and searched often.
+/// ## Fields
+/// * `corridor_width` - The corridor width of the decomposition into rectangles
+/// * `max_area` - The largest rectangle tried as a swamp
+#[derive(Clone, Copy, Debug)]
+pub struct Swamps {
+    pub corridor_width: usize,
+    pub max_area: usize,
+}
+
+impl Default for Swamps {
+    fn default() -> Self {
+        Swamps { corridor_width: 2, max_area: 64 }
+    }
+}
+
+impl Preprocessor for Swamps {
+    fn prune(&self, mask: &mut PruningMask, stage: usize) {
+        let regions = Regions::new(&mask.pruned, self.corridor_width);
+        for region in regions.regions.iter().filter(|r| r.area() <= self.max_area) {
+            let grid = &mask.pruned;
+            let cells = grid.cells_in_rect(region.origin, region.width, region.height).collect::<Vec<_>>();
+            let mut without = grid.clone();
+            cells.iter().for_each(|cell| without.set_bit_value(*cell, false));
+            let mut border = cells.iter()
+                .flat_map(|cell| without.adjacent(*cell, false).filter(|n| without.bounds_check(*n)))
+                .collect::<Vec<_>>();
+            border.sort_unstable();
+            border.dedup();
+            // Every pair of border cells must stay as close without the region
+            let swamp = border.iter().all(|b| {
+                let (through, around) = (grid_distances(grid, *b), grid_distances(&without, *b));
+                border.iter().all(|c| through.get(c) == around.get(c))
+            });
+            if swamp {
+                mask.prune(cells, stage, false);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The cost of a shortest path on a grid
+    fn distance(grid: &BitPackedGrid, start: Cell, goal: Cell) -> Option<usize> {
+        grid_distances(grid, start).get(&goal).copied()
+    }
+
+    #[test]
+    fn test_dead_ends() {
+        let grid = BitPackedGrid::new_from_string("......\n.@@@@.\n......\n@@.@@@\n@@.@@@\n".to_string());
+        let mask = DeadEnds.preprocess(&grid);
+        assert_eq!(mask.regions.len(), 1);
+        assert_eq!(mask.regions[0].cells, vec![(2, 3), (2, 4)]);
+        assert!(!mask.pruned.get_bit_value((2, 3)) && mask.pruned.get_bit_value((2, 2)));
+        assert_eq!(mask.search((0, 0), (5, 2)).map(|(_, c)| c), Some(7));
+        assert_eq!(mask.search((2, 4), (5, 0)).map(|(_, c)| c), Some(7));
+        // A tree is peeled entirely
+        let tree = BitPackedGrid::new_from_string("...\n@.@\n@.@\n".to_string());
+        assert_eq!(DeadEnds.preprocess(&tree).pruned_cells(), 5);
+    }
+
+    #[test]
+    fn test_pruning_preserves_distances() {
+        let grid = BitPackedGrid::new_from_string(
+            "..........\n.@@..@@@@.\n.@@..@@.@.\n.@@@@@@.@.\n.......@..\n.@@@@@@...\n".to_string(),
+        );
+        let mask = DeadEnds.then(Swamps { corridor_width: 1, max_area: 8 }).preprocess(&grid);
+        // The room below the top corridor is a swamp and the spur in the bottom row a dead end
+        let swamps = mask.regions.iter().filter(|r| r.stage == 1).map(|r| r.cells.clone()).collect::<Vec<_>>();
+        assert!(swamps.contains(&vec![(3, 1), (4, 1), (3, 2), (4, 2)]));
+        assert_eq!(mask.region((0, 5)), Some(0));
+        let free = grid.free_cells().collect::<Vec<_>>();
+        for &start in &free {
+            for &goal in &free {
+                assert_eq!(mask.search(start, goal).map(|(_, c)| c), distance(&grid, start, goal), "{:?} {:?}", start, goal);
+            }
+        }
+        let kept = free.iter().filter(|n| mask.region(**n).is_none()).count();
+        assert_eq!(kept + mask.pruned_cells(), free.len());
+    }
+}
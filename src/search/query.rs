@@ -0,0 +1,189 @@
+//! # Queries
+//! A query layer in front of grid planners that handles degenerate queries
+//! the same way for every planner, instead of leaving them to the ad-hoc
+//! behaviour of each algorithm. Empty maps and endpoints outside the map are
+//! errors, blocked endpoints are errors unless snapping to a nearby free cell
+//! is enabled, a start equal to its goal is answered without planning, and
+//! with component labels disconnected queries are answered as unreachable
+//! without running the planner.
+
+use std::{error::Error, fmt};
+
+use crate::analysis::statistics::ComponentMap;
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+
+use super::snap::{snap_point, SnapError};
+
+/// The reason a query could not be answered
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QueryError {
+    /// The map has no cells
+    EmptyMap,
+    /// An endpoint lies outside the map
+    OutOfBounds { cell: Cell },
+    /// An endpoint is an obstacle and snapping is disabled
+    Blocked { cell: Cell },
+    /// A blocked endpoint could not be snapped to a free cell
+    Snap(SnapError),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::EmptyMap => write!(f, "The map is empty"),
+            QueryError::OutOfBounds { cell } => write!(f, "Cell {:?} is outside the map", cell),
+            QueryError::Blocked { cell } => write!(f, "Cell {:?} is blocked", cell),
+            QueryError::Snap(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for QueryError {}
+
+/// How a query was answered
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueryOutcome {
+    /// The start is the goal, answered without planning
+    Trivial,
+    /// The planner found a path
+    Found(Vec<Cell>),
+    /// The goal cannot be reached from the start
+    Unreachable,
+}
+
+/// The answer to a query
+/// ## Fields
+/// * `start` - The start cell planned from, after any snapping
+/// * `goal` - The goal cell planned to, after any snapping
+/// * `snapped` - Whether either endpoint was snapped to a free cell
+/// * `outcome` - How the query was answered
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryAnswer {
+    pub start: Cell,
+    pub goal: Cell,
+    pub snapped: bool,
+    pub outcome: QueryOutcome,
+}
+
+impl QueryAnswer {
+    /// The path from the start to the goal, a single cell for trivial queries
+    pub fn path(&self) -> Option<Vec<Cell>> {
+        match &self.outcome {
+            QueryOutcome::Trivial => Some(vec![self.start]),
+            QueryOutcome::Found(path) => Some(path.clone()),
+            QueryOutcome::Unreachable => None,
+        }
+    }
+}
+
+/// Checks and answers queries on a grid map
+/// ## Fields
+/// * `snap_radius` - The radius within which blocked endpoints snap to the
+///   nearest free cell, `None` to reject blocked endpoints
+pub struct QueryLayer<'a> {
+    grid: &'a BitPackedGrid,
+    components: Option<ComponentMap>,
+    pub snap_radius: Option<f32>,
+}
+
+impl<'a> QueryLayer<'a> {
+    /// Creates a query layer rejecting blocked endpoints
+    pub fn new(grid: &'a BitPackedGrid) -> Self {
+        QueryLayer { grid, components: None, snap_radius: None }
+    }
+
+    /// Snaps blocked endpoints to the nearest free cell within a radius
+    pub fn with_snapping(mut self, radius: f32) -> Self {
+        self.snap_radius = Some(radius);
+        self
+    }
+
+    /// Labels the connected components of the map once, so disconnected
+    /// queries are answered without planning
+    pub fn with_components(mut self) -> Self {
+        self.components = Some(ComponentMap::new(self.grid));
+        self
+    }
+
+    /// Checks an endpoint, snapping it if blocked
+    /// ## Returns
+    /// The free cell to plan with and whether it was snapped
+    fn endpoint(&self, cell: Cell) -> Result<(Cell, bool), QueryError> {
+        if !self.grid.bounds_check(cell) {
+            return Err(QueryError::OutOfBounds { cell });
+        }
+        if self.grid.get_bit_value(cell) {
+            return Ok((cell, false));
+        }
+        let radius = self.snap_radius.ok_or(QueryError::Blocked { cell })?;
+        let centre = (cell.0 as f32 + 0.5, cell.1 as f32 + 0.5);
+        snap_point(self.grid, centre, radius).map(|c| (c, true)).map_err(QueryError::Snap)
+    }
+
+    /// Answers a query with a planner, which is only run on non-degenerate
+    /// queries between free cells
+    /// ## Arguments
+    /// * `start` - The start cell
+    /// * `goal` - The goal cell
+    /// * `planner` - Plans a path on the map from a start to a goal
+    pub fn answer<P>(&self, start: Cell, goal: Cell, mut planner: P) -> Result<QueryAnswer, QueryError>
+    where
+        P: FnMut(&BitPackedGrid, Cell, Cell) -> Option<Vec<Cell>>,
+    {
+        if self.grid.original_width == 0 || self.grid.original_height == 0 {
+            return Err(QueryError::EmptyMap);
+        }
+        let (start, snapped_start) = self.endpoint(start)?;
+        let (goal, snapped_goal) = self.endpoint(goal)?;
+        let outcome = if start == goal {
+            QueryOutcome::Trivial
+        } else if self.components.as_ref().is_some_and(|c| !c.same_component(start, goal)) {
+            QueryOutcome::Unreachable
+        } else {
+            planner(self.grid, start, goal).map_or(QueryOutcome::Unreachable, QueryOutcome::Found)
+        };
+        Ok(QueryAnswer { start, goal, snapped: snapped_start || snapped_goal, outcome })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::distance::manhattan_distance;
+    use crate::search::astar::astar;
+
+    fn planner(grid: &BitPackedGrid, start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+        astar(|n| grid.adjacent1(*n), start, |n| *n == goal, |n| manhattan_distance(*n, goal)).map(|(path, _)| path)
+    }
+
+    #[test]
+    fn test_query_layer() {
+        let grid = BitPackedGrid::new_from_string("...@.\n.@.@.\n...@.\n".to_string());
+        let layer = QueryLayer::new(&grid);
+        let answer = layer.answer((0, 0), (2, 2), planner).unwrap();
+        assert_eq!(answer.path().unwrap().len(), 5);
+        assert!(!answer.snapped);
+        let trivial = layer.answer((2, 1), (2, 1), |_, _, _| panic!("Trivial queries are not planned")).unwrap();
+        assert_eq!((trivial.outcome.clone(), trivial.path()), (QueryOutcome::Trivial, Some(vec![(2, 1)])));
+        assert_eq!(layer.answer((0, 0), (4, 0), planner).unwrap().outcome, QueryOutcome::Unreachable);
+        assert_eq!(layer.answer((1, 1), (0, 0), planner), Err(QueryError::Blocked { cell: (1, 1) }));
+        assert_eq!(layer.answer((0, 0), (5, 0), planner), Err(QueryError::OutOfBounds { cell: (5, 0) }));
+        assert_eq!(QueryLayer::new(&BitPackedGrid::new(0, 0)).answer((0, 0), (0, 0), planner), Err(QueryError::EmptyMap));
+    }
+
+    #[test]
+    fn test_query_layer_snapping() {
+        let grid = BitPackedGrid::new_from_string("...@.\n.@.@.\n...@.\n".to_string());
+        let layer = QueryLayer::new(&grid).with_snapping(1.0).with_components();
+        let answer = layer.answer((1, 1), (2, 2), planner).unwrap();
+        assert!(answer.snapped);
+        assert!(grid.get_bit_value(answer.start));
+        assert_eq!(answer.path().unwrap().first(), Some(&answer.start));
+        // Disconnected queries are not planned with component labels
+        let answer = layer.answer((0, 0), (4, 2), |_, _, _| panic!("Disconnected queries are not planned")).unwrap();
+        assert_eq!(answer.outcome, QueryOutcome::Unreachable);
+        let walled = BitPackedGrid::new_from_string("...\n.@.\n...\n".to_string());
+        let error = QueryLayer::new(&walled).with_snapping(0.4).answer((1, 1), (0, 0), planner).unwrap_err();
+        assert!(matches!(error, QueryError::Snap(SnapError::NoFreeCell { .. })));
+    }
+}
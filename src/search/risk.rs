@@ -0,0 +1,227 @@
+//! # Risk-Sensitive Planning
+//! Planning on sampling grids against an ensemble of sampled worlds. Each
+//! candidate path is followed in every world, replanning from the last free
+//! cell when the path is blocked, and the resulting travel costs are scored by
+//! a risk objective. Besides the expected cost, paths can be chosen by
+//! mean + λ·variance, which penalises unpredictable routes, or by the
+//! conditional value at risk (CVaR), the mean cost of the worst outcomes.
+//! Worlds can also be sampled only near a candidate corridor, fixing every
+//! other cell to its most likely state, which is cheaper when only the cells
+//! near the path affect its cost.
+
+use crate::domains::{bitpackedgrid::BitPackedGrid, samplegrid::SampleGrid};
+use crate::heuristics::distance::manhattan_distance;
+use crate::path::corridor::corridor;
+use crate::util::determinism::with_rng;
+use rand::Rng;
+
+use super::{diverse::diverse_paths, uninformed::bfs};
+
+/// An objective over the travel costs of a path across sampled worlds
+/// * `Expected` - The mean cost
+/// * `MeanVariance(λ)` - The mean cost plus λ times the variance of the cost
+/// * `CVaR(α)` - The mean of the worst α fraction of costs, where α is in (0, 1]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RiskObjective {
+    Expected,
+    MeanVariance(f32),
+    CVaR(f32),
+}
+
+impl RiskObjective {
+    /// Scores a set of travel costs, lower is better
+    pub fn evaluate(&self, costs: &[f32]) -> f32 {
+        if costs.is_empty() {
+            return 0.0;
+        }
+        let n = costs.len() as f32;
+        let mean = costs.iter().sum::<f32>() / n;
+        match self {
+            RiskObjective::Expected => mean,
+            RiskObjective::MeanVariance(lambda) => {
+                let variance = costs.iter().map(|c| (c - mean).powi(2)).sum::<f32>() / n;
+                mean + lambda * variance
+            }
+            RiskObjective::CVaR(alpha) => {
+                assert!(*alpha > 0.0 && *alpha <= 1.0, "CVaR requires α in (0, 1]");
+                let mut sorted = costs.to_vec();
+                sorted.sort_by(|a, b| b.total_cmp(a));
+                let tail = ((alpha * n).ceil() as usize).max(1);
+                sorted[..tail].iter().sum::<f32>() / tail as f32
+            }
+        }
+    }
+}
+
+/// Samples worlds from a sampling grid, each is a possible ground truth
+pub fn sample_worlds(grid: &mut SampleGrid, count: usize) -> Vec<BitPackedGrid> {
+    with_rng(|rng| sample_worlds_with(grid, count, rng))
+}
+
+/// Samples worlds from a sampling grid like `sample_worlds`, drawing from a
+/// generator so that the same seed gives the same worlds
+pub fn sample_worlds_with(grid: &mut SampleGrid, count: usize, rng: &mut impl Rng) -> Vec<BitPackedGrid> {
+    (0..count)
+        .map(|_| {
+            grid.sample_all_with(rng);
+            grid.gridmap().clone()
+        })
+        .collect()
+}
+
+/// Samples worlds that only vary near a path. Cells within `distance` moves
+/// of the path through possibly free cells are sampled, every other cell is
+/// fixed to free when its belief is above a half.
+/// ## Arguments
+/// * `grid` - The sampling grid to sample from
+/// * `path` - The candidate path, such as the optimistic path to the goal
+/// * `distance` - The number of moves the sampled corridor extends from the path
+/// * `count` - The number of worlds to sample
+/// ## Returns
+/// The sampled worlds
+pub fn sample_worlds_near(
+    grid: &mut SampleGrid,
+    path: &[(usize, usize)],
+    distance: usize,
+    count: usize,
+) -> Vec<BitPackedGrid> {
+    let mut possible = BitPackedGrid::new(grid.width, grid.height);
+    let mut likely = BitPackedGrid::new(grid.width, grid.height);
+    for x in 0..grid.width {
+        for y in 0..grid.height {
+            possible.set_bit_value((x, y), grid.belief((x, y)) != 0.0);
+            likely.set_bit_value((x, y), grid.belief((x, y)) > 0.5);
+        }
+    }
+    let mask = corridor(&possible, path, distance);
+    let cells: Vec<_> = mask.free_cells().collect();
+    (0..count)
+        .map(|_| {
+            grid.sample_masked(&mask);
+            let mut world = likely.clone();
+            for cell in &cells {
+                world.set_bit_value(*cell, grid.gridmap().get_bit_value(*cell));
+            }
+            world
+        })
+        .collect()
+}
+
+/// The travel cost of following a path in a world. When the next cell of the
+/// path is blocked the agent replans from its current cell, and if the goal is
+/// unreachable the cost travelled so far plus `blocked_cost` is charged.
+pub fn path_cost(path: &[(usize, usize)], world: &BitPackedGrid, blocked_cost: f32) -> f32 {
+    let Some(&goal) = path.last() else { return 0.0 };
+    for (i, window) in path.windows(2).enumerate() {
+        if !world.get_bit_value(window[1]) {
+            return i as f32 + match bfs(|n| world.adjacent(*n, false), window[0], |n| *n == goal) {
+                Some((_, length)) => length as f32,
+                None => blocked_cost,
+            };
+        }
+    }
+    (path.len() - 1) as f32
+}
+
+/// The travel cost of a path in every world
+pub fn path_costs(path: &[(usize, usize)], worlds: &[BitPackedGrid], blocked_cost: f32) -> Vec<f32> {
+    worlds.iter().map(|world| path_cost(path, world, blocked_cost)).collect()
+}
+
+/// Plans the path that minimises a risk objective over sampled worlds. Candidate
+/// paths are diverse paths over the cells that may be free.
+/// ## Arguments
+/// * `grid` - The sampling grid to plan on
+/// * `start` - The start cell
+/// * `goal` - The goal cell
+/// * `worlds` - The sampled worlds to evaluate candidates in
+/// * `candidates` - The number of candidate paths to generate
+/// * `blocked_cost` - The added cost when the goal is unreachable in a world
+/// * `objective` - The risk objective to minimise
+/// ## Returns
+/// The best candidate path and its score
+pub fn risk_sensitive_plan(
+    grid: &SampleGrid,
+    start: (usize, usize),
+    goal: (usize, usize),
+    worlds: &[BitPackedGrid],
+    candidates: usize,
+    blocked_cost: f32,
+    objective: RiskObjective,
+) -> Option<(Vec<(usize, usize)>, f32)> {
+    let paths = diverse_paths(
+        |n| grid.adjacent(*n, false)
+            .filter(|(x, y)| grid.belief((*x, *y)) != 0.0)
+            .map(|n| (n, 1))
+            .collect::<Vec<_>>(),
+        start,
+        |n| *n == goal,
+        |n| manhattan_distance(*n, goal),
+        candidates,
+        2,
+    );
+    paths.into_iter()
+        .map(|(path, _)| {
+            let score = objective.evaluate(&path_costs(&path, worlds, blocked_cost));
+            (path, score)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_risk_objective() {
+        let costs = [2.0, 2.0, 4.0, 8.0];
+        assert_eq!(RiskObjective::Expected.evaluate(&costs), 4.0);
+        assert_eq!(RiskObjective::MeanVariance(0.5).evaluate(&costs), 7.0);
+        assert_eq!(RiskObjective::CVaR(0.5).evaluate(&costs), 6.0);
+        assert_eq!(RiskObjective::CVaR(1.0).evaluate(&costs), 4.0);
+    }
+
+    #[test]
+    fn test_sample_worlds_near() {
+        let truth = BitPackedGrid::new_from_string(".....\n.....\n.....\n.....\n".to_string());
+        let mut grid = SampleGrid::new_from_grid(vec![vec![0.5; 4]; 5], truth);
+        grid.set_belief((4, 3), 0.6);
+        let path = [(0, 0), (1, 0), (2, 0)];
+        let worlds = sample_worlds_near(&mut grid, &path, 1, 20);
+        assert_eq!(worlds.len(), 20);
+        for world in &worlds {
+            // Cells away from the corridor take their most likely state
+            assert!(!world.get_bit_value((0, 3)) && !world.get_bit_value((4, 2)));
+            assert!(world.get_bit_value((4, 3)));
+        }
+        assert!(worlds.iter().any(|w| w.get_bit_value((1, 0))));
+        assert!(worlds.iter().any(|w| !w.get_bit_value((1, 0))));
+    }
+
+    #[test]
+    fn test_path_cost() {
+        let world = BitPackedGrid::new_from_string("...\n.@.\n...\n".to_string());
+        let path = [(0, 1), (1, 1), (2, 1)];
+        assert_eq!(path_cost(&path, &world, 100.0), 4.0);
+        let world = BitPackedGrid::new_from_string(".@.\n.@.\n.@.\n".to_string());
+        assert_eq!(path_cost(&path, &world, 100.0), 100.0);
+    }
+
+    #[test]
+    fn test_risk_sensitive_plan() {
+        // The short route through the middle is blocked in half the worlds,
+        // costing a long detour, while the routes around the sides are always free
+        let map = ".....\n.@.@.\n.@.@.\n.@.@.\n.....\n";
+        let mut grid = SampleGrid::new_from_string(map.to_string());
+        grid.set_belief((2, 1), 0.5);
+        let free = BitPackedGrid::new_from_string(map.to_string());
+        let blocked = BitPackedGrid::new_from_string(".....\n.@@@.\n.@.@.\n.@.@.\n.....\n".to_string());
+        let worlds = [free.clone(), blocked.clone(), free, blocked];
+        let (path, score) = risk_sensitive_plan(&grid, (2, 3), (2, 0), &worlds, 4, 100.0, RiskObjective::CVaR(0.5)).unwrap();
+        assert_eq!(score, 9.0);
+        assert!(!path.contains(&(2, 1)));
+        let (path, score) = risk_sensitive_plan(&grid, (2, 3), (2, 0), &worlds, 4, 100.0, RiskObjective::Expected).unwrap();
+        assert_eq!(score, 7.0);
+        assert!(path.contains(&(2, 1)));
+    }
+}
@@ -3,7 +3,9 @@
 
 use std::collections::HashMap;
 
-use crate::{domains::samplegrid::SampleGrid, heuristics::distance::manhattan_distance, util::visualiser::{Visualiser, self}};
+use crate::{domains::samplegrid::SampleGrid, heuristics::distance::manhattan_distance, util::history::CovarianceHistory};
+#[cfg(feature = "io")]
+use crate::util::visualiser::{Visualiser, self};
 
 use super::astar::astar;
 
@@ -21,7 +23,9 @@ pub struct SampleStar {
     epoch: usize,
     radius: usize,
     final_path: Vec<(usize, usize)>,
+    #[cfg(feature = "io")]
     visualiser: Option<Visualiser>,
+    covariance_history: Option<CovarianceHistory>,
 }
 
 impl SampleStar {
@@ -36,7 +40,9 @@ impl SampleStar {
             epoch,
             radius,
             final_path: vec![start],
+            #[cfg(feature = "io")]
             visualiser: None,
+            covariance_history: None,
         }
     }
 
@@ -48,13 +54,16 @@ impl SampleStar {
         let mut heatmap = HashMap::new();
         // let mut cached_paths = HashMap::new();
         self.grid.update_node_kern(self.current, self.radius);
+        if let Some(history) = &mut self.covariance_history {
+            history.record(&self.grid);
+        }
         // self.grid.init_gridmap_radius(self.current, self.radius + 1); // +1 to account for previous update
         self.grid.init_gridmap_nearest();
         for _ in 0..self.epoch {
             // self.grid.sample_all();
             self.grid.sample_radius(self.current, self.radius);
             let path = astar(
-                |n| self.grid.gridmap.adjacent1(*n),
+                |n| self.grid.gridmap().adjacent1(*n),
                 self.current,
                 |n| *n == self.goal,
                 |n| manhattan_distance(*n, self.goal),
@@ -65,6 +74,7 @@ impl SampleStar {
                 }
             }
         }
+        #[cfg(feature = "io")]
         let old = self.current;
         self.current = self.grid.adjacent(self.current, false)
             .filter(|n| heatmap.contains_key(n))
@@ -73,15 +83,31 @@ impl SampleStar {
             .clone();
         self.final_path.push(self.current);
 
+        #[cfg(feature = "io")]
         if let Some(visualiser) = &self.visualiser {
             visualiser.visualise_iteration(&self.grid, self.final_path.len()-1, Some(old), Some(self.current), &heatmap);
         }
         false
     }
 
+    #[cfg(feature = "io")]
     pub fn add_visualiser(&mut self, file_path: &str) {
         self.visualiser = Some(Visualiser::new(file_path, &self.grid, Some(self.current), Some(self.goal)));
     }
+
+    /// Records the covariance of the sampling grid after every observation
+    pub fn record_covariance(&mut self) {
+        self.covariance_history = Some(CovarianceHistory::new(&self.grid));
+    }
+
+    /// Plots how the uncertainty of the sampling grid shrank along the
+    /// traversed path, one frame per recorded step
+    #[cfg(feature = "viz")]
+    pub fn plot_covariance_evolution(&self, file_path: &str) {
+        if let Some(history) = &self.covariance_history {
+            history.plot_evolution(&self.grid, file_path, &self.final_path);
+        }
+    }
 }
 
 /*
@@ -89,7 +115,7 @@ Heuristics could include ones that take into account probability of being an obs
 `self.grid.sample_grid[x][y].state * manhattan_distance(n*, self.goal)`
 */
 
-#[cfg(test)]
+#[cfg(all(test, feature = "io"))]
 mod tests {
     use super::*;
 
@@ -0,0 +1,208 @@
+//! # Reusable Grid Searcher
+//! A-Star on 4-connected grids with unit costs for running many queries on
+//! the same or similarly sized maps. The searcher owns its open list and
+//! per-cell arrays, indexed by the row order index of each cell, and keeps
+//! them between queries. Instead of clearing the arrays, every query bumps a
+//! generation counter and a cell's entries only count when they were written
+//! in the current generation, so a query allocates nothing once the arrays
+//! fit the map and costs only the cells it reaches.
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid, fixedgrid::FixedGrid};
+use crate::heuristics::distance::manhattan_distance;
+
+use super::stats::SearchStats;
+
+/// A grid whose cells are numbered in row order for flat search arrays
+pub trait FlatGrid {
+    /// The width and height of the grid
+    fn dimensions(&self) -> (usize, usize);
+
+    /// Checks if a cell is within the grid and free
+    fn is_free(&self, cell: Cell) -> bool;
+}
+
+impl<const W: usize, const H: usize> FlatGrid for FixedGrid<W, H> {
+    fn dimensions(&self) -> (usize, usize) {
+        (W, H)
+    }
+
+    fn is_free(&self, cell: Cell) -> bool {
+        self.get_bit_value(cell)
+    }
+}
+
+impl FlatGrid for BitPackedGrid {
+    fn dimensions(&self) -> (usize, usize) {
+        (self.original_width, self.original_height)
+    }
+
+    fn is_free(&self, cell: Cell) -> bool {
+        self.bounds_check(cell) && self.get_bit_value(cell)
+    }
+}
+
+/// An A-Star search reusing its memory across queries
+/// ## Fields
+/// * `stats` - The statistics of the last query
+#[derive(Clone, Debug, Default)]
+pub struct GridSearcher {
+    g: Vec<u32>,
+    parent: Vec<u32>,
+    /// The generation in which the cost and parent of each cell were written
+    reached: Vec<u32>,
+    /// The generation in which each cell was expanded
+    closed: Vec<u32>,
+    generation: u32,
+    open: BinaryHeap<Reverse<(u32, u32, u32)>>,
+    pub stats: SearchStats,
+}
+
+impl GridSearcher {
+    pub fn new() -> Self {
+        GridSearcher::default()
+    }
+
+    /// Creates a searcher with arrays sized for grids of a number of cells
+    pub fn with_capacity(cells: usize) -> Self {
+        let mut searcher = GridSearcher::new();
+        searcher.reserve(cells);
+        searcher
+    }
+
+    /// Grows the arrays to hold a number of cells
+    fn reserve(&mut self, cells: usize) {
+        assert!(cells <= u32::MAX as usize, "The grid has too many cells for 32 bit indices");
+        if self.g.len() < cells {
+            self.g.resize(cells, 0);
+            self.parent.resize(cells, 0);
+            self.reached.resize(cells, 0);
+            self.closed.resize(cells, 0);
+        }
+    }
+
+    /// Starts a new generation, clearing the arrays only when the counter wraps
+    fn next_generation(&mut self) {
+        if self.generation == u32::MAX {
+            self.reached.iter_mut().for_each(|g| *g = 0);
+            self.closed.iter_mut().for_each(|g| *g = 0);
+            self.generation = 0;
+        }
+        self.generation += 1;
+        self.open.clear();
+        self.stats = SearchStats::default();
+    }
+
+    /// Finds a shortest path between two cells
+    /// ## Returns
+    /// An optional path from the start to the goal with its cost
+    pub fn search(&mut self, grid: &impl FlatGrid, start: Cell, goal: Cell) -> Option<(Vec<Cell>, usize)> {
+        let mut path = Vec::new();
+        self.search_into(grid, start, goal, &mut path).map(|cost| (path, cost))
+    }
+
+    /// Finds a shortest path between two cells, writing it into a reused buffer
+    /// ## Returns
+    /// The cost of the path, `None` leaving the buffer empty if there is none
+    pub fn search_into(&mut self, grid: &impl FlatGrid, start: Cell, goal: Cell, path: &mut Vec<Cell>) -> Option<usize> {
+        path.clear();
+        if !grid.is_free(start) || !grid.is_free(goal) {
+            return None;
+        }
+        let (width, height) = grid.dimensions();
+        self.reserve(width * height);
+        self.next_generation();
+        let index = |(x, y): Cell| (y * width + x) as u32;
+        let cell = |i: u32| (i as usize % width, i as usize / width);
+        let generation = self.generation;
+        let (root, target) = (index(start), index(goal));
+        self.g[root as usize] = 0;
+        self.parent[root as usize] = root;
+        self.reached[root as usize] = generation;
+        let h = manhattan_distance(start, goal) as u32;
+        self.open.push(Reverse((h, h, root)));
+        self.stats.pushed += 1;
+        while let Some(Reverse((_, _, node))) = self.open.pop() {
+            self.stats.popped += 1;
+            if self.closed[node as usize] == generation {
+                self.stats.duplicates += 1;
+                continue;
+            }
+            if node == target {
+                let mut current = node;
+                path.push(cell(current));
+                while current != root {
+                    current = self.parent[current as usize];
+                    path.push(cell(current));
+                }
+                path.reverse();
+                return Some(self.g[node as usize] as usize);
+            }
+            self.closed[node as usize] = generation;
+            self.stats.expanded += 1;
+            let (x, y) = cell(node);
+            let g = self.g[node as usize] + 1;
+            for neighbour in [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)] {
+                if !grid.is_free(neighbour) {
+                    continue;
+                }
+                self.stats.generated += 1;
+                let child = index(neighbour) as usize;
+                if self.reached[child] == generation && self.g[child] <= g {
+                    continue;
+                }
+                self.g[child] = g;
+                self.parent[child] = node;
+                self.reached[child] = generation;
+                let h = manhattan_distance(neighbour, goal) as u32;
+                self.open.push(Reverse((g + h, h, child as u32)));
+                self.stats.pushed += 1;
+                self.stats.open_size(self.open.len());
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::test_queries;
+    use crate::search::{astar::astar, domain::search};
+
+    #[test]
+    fn test_grid_searcher() {
+        let grid = BitPackedGrid::new_from_file("tests/map.map");
+        let free = grid.free_cells().collect::<Vec<_>>();
+        let mut searcher = GridSearcher::new();
+        let mut path = Vec::new();
+        for (start, goal) in test_queries(&free, 20) {
+            let expected = astar(|n| grid.adjacent1(*n), start, |n| *n == goal, |n| manhattan_distance(*n, goal)).map(|(_, c)| c);
+            assert_eq!(searcher.search_into(&grid, start, goal, &mut path), expected);
+            if let Some(cost) = expected {
+                assert_eq!((path[0], *path.last().unwrap(), path.len()), (start, goal, cost + 1));
+                assert!(path.windows(2).all(|w| manhattan_distance(w[0], w[1]) == 1 && grid.get_bit_value(w[1])));
+            }
+        }
+        // The arrays keep their size across queries
+        let cells = searcher.g.len();
+        searcher.search(&grid, free[0], free[1]);
+        assert_eq!(searcher.g.len(), cells);
+    }
+
+    #[test]
+    fn test_fixed_grid_search() {
+        let grid = FixedGrid::<6, 5>::new_from_string("......\n.@@@@.\n.@....\n.@.@@@\n......\n".to_string()).unwrap();
+        let mut searcher = GridSearcher::with_capacity(30);
+        let expected = search(&grid, (2, 2), (5, 0)).map(|(_, c)| c);
+        assert_eq!(searcher.search(&grid, (2, 2), (5, 0)).map(|(_, c)| c), expected);
+        assert!(expected.is_some() && searcher.stats.expanded > 0);
+        assert_eq!(searcher.search(&grid, (0, 0), (0, 0)), Some((vec![(0, 0)], 0)));
+        assert_eq!(searcher.search(&grid, (0, 0), (1, 1)), None);
+        // A wrapped generation counter clears the arrays
+        searcher.generation = u32::MAX;
+        assert_eq!(searcher.search(&grid, (2, 2), (5, 0)).map(|(_, c)| c), expected);
+        assert_eq!(searcher.generation, 1);
+    }
+}
@@ -0,0 +1,144 @@
+//! # Start and Goal Snapping
+//! Conversion of continuous start and goal points, such as the positions of
+//! robots or clicks on a map, to the free cells a grid search can start from.
+//! Cell `(x, y)` covers the square from `(x, y)` to `(x + 1, y + 1)`, and a
+//! point snaps to the free cell whose centre is nearest within a radius. Query
+//! snapping is component-aware, so a point next to a wall snaps to the side of
+//! the wall from which the other point is reachable.
+
+use std::{error::Error, fmt};
+
+use crate::analysis::statistics::component_labels;
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+
+/// The reason a point could not be snapped to a cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SnapError {
+    /// The point is not finite or lies outside the map
+    OutOfBounds { point: (f32, f32) },
+    /// No free cell has its centre within the radius of the point
+    NoFreeCell { point: (f32, f32), radius: f32 },
+    /// The free cells near the start and the goal are all in different components
+    Disconnected { start: (f32, f32), goal: (f32, f32) },
+}
+
+impl fmt::Display for SnapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapError::OutOfBounds { point } => write!(f, "Point {:?} is outside the map", point),
+            SnapError::NoFreeCell { point, radius } => {
+                write!(f, "No free cell within {} of point {:?}", radius, point)
+            }
+            SnapError::Disconnected { start, goal } => {
+                write!(f, "No free cells near start {:?} and goal {:?} are connected", start, goal)
+            }
+        }
+    }
+}
+
+impl Error for SnapError {}
+
+/// The free cells whose centres are within a radius of a point, nearest first
+fn candidates(grid: &BitPackedGrid, point: (f32, f32), radius: f32) -> Result<Vec<(Cell, f32)>, SnapError> {
+    let (x, y) = point;
+    let (width, height) = (grid.original_width as f32, grid.original_height as f32);
+    if !x.is_finite() || !y.is_finite() || !(0.0..=width).contains(&x) || !(0.0..=height).contains(&y) {
+        return Err(SnapError::OutOfBounds { point });
+    }
+    let range = |c: f32, size: usize| {
+        let low = (c - radius - 0.5).floor().max(0.0) as usize;
+        let high = ((c + radius - 0.5).ceil().max(0.0) as usize).min(size.saturating_sub(1));
+        low..=high
+    };
+    let mut cells = Vec::new();
+    for cx in range(x, grid.original_width) {
+        for cy in range(y, grid.original_height) {
+            let distance = (cx as f32 + 0.5 - x).hypot(cy as f32 + 0.5 - y);
+            if distance <= radius && grid.get_bit_value((cx, cy)) {
+                cells.push(((cx, cy), distance));
+            }
+        }
+    }
+    cells.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+    Ok(cells)
+}
+
+/// Snaps a point to the nearest free cell within a radius
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `point` - The continuous point in cell units
+/// * `radius` - The furthest distance from the point to the centre of the cell
+/// ## Returns
+/// The nearest free cell, ties broken by the smallest coordinates
+pub fn snap_point(grid: &BitPackedGrid, point: (f32, f32), radius: f32) -> Result<Cell, SnapError> {
+    candidates(grid, point, radius)?
+        .first()
+        .map(|(cell, _)| *cell)
+        .ok_or(SnapError::NoFreeCell { point, radius })
+}
+
+/// Snaps a start and a goal to free cells in the same connected component,
+/// choosing the pair with the least total snapping distance
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `start` - The continuous start point in cell units
+/// * `goal` - The continuous goal point in cell units
+/// * `radius` - The furthest distance from each point to the centre of its cell
+/// ## Returns
+/// The snapped start and goal cells
+pub fn snap_query(
+    grid: &BitPackedGrid,
+    start: (f32, f32),
+    goal: (f32, f32),
+    radius: f32,
+) -> Result<(Cell, Cell), SnapError> {
+    let starts = candidates(grid, start, radius)?;
+    let goals = candidates(grid, goal, radius)?;
+    if starts.is_empty() {
+        return Err(SnapError::NoFreeCell { point: start, radius });
+    }
+    if goals.is_empty() {
+        return Err(SnapError::NoFreeCell { point: goal, radius });
+    }
+    let (labels, _) = component_labels(grid);
+    let label = |(x, y): Cell| labels[x][y];
+    let mut best: Option<(Cell, Cell, f32)> = None;
+    // Candidates are sorted, so the first goal in a component is its nearest
+    for (s, ds) in &starts {
+        if let Some((g, dg)) = goals.iter().find(|(g, _)| label(*g) == label(*s)) {
+            if best.is_none_or(|(_, _, d)| ds + dg < d) {
+                best = Some((*s, *g, ds + dg));
+            }
+        }
+    }
+    best.map(|(s, g, _)| (s, g)).ok_or(SnapError::Disconnected { start, goal })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snap_point() {
+        let grid = BitPackedGrid::new_from_string("..@\n.@@\n...\n".to_string());
+        assert_eq!(snap_point(&grid, (0.4, 0.6), 0.5), Ok((0, 0)));
+        assert_eq!(snap_point(&grid, (1.5, 1.5), 1.0), Ok((0, 1)));
+        assert_eq!(snap_point(&grid, (2.5, 0.5), 0.5), Err(SnapError::NoFreeCell { point: (2.5, 0.5), radius: 0.5 }));
+        assert_eq!(snap_point(&grid, (-1.0, 0.5), 2.0), Err(SnapError::OutOfBounds { point: (-1.0, 0.5) }));
+        assert!(snap_point(&grid, (f32::NAN, 0.5), 2.0).is_err());
+    }
+
+    #[test]
+    fn test_snap_query() {
+        let grid = BitPackedGrid::new_from_string("..@..\n..@..\n@@@..\n".to_string());
+        // The nearest cell to the start is walled off from the goal
+        assert_eq!(snap_point(&grid, (2.4, 0.5), 1.0), Ok((1, 0)));
+        assert_eq!(snap_query(&grid, (2.4, 0.5), (4.5, 2.5), 1.5), Ok(((3, 0), (4, 2))));
+        assert_eq!(
+            snap_query(&grid, (0.5, 0.5), (4.5, 2.5), 1.0),
+            Err(SnapError::Disconnected { start: (0.5, 0.5), goal: (4.5, 2.5) })
+        );
+        let error = snap_query(&grid, (0.5, 2.5), (4.5, 2.5), 0.5).unwrap_err();
+        assert_eq!(error.to_string(), "No free cell within 0.5 of point (0.5, 2.5)");
+    }
+}
@@ -0,0 +1,286 @@
+//! # Soak Testing
+//! A long-running randomised harness for planners that keep state between
+//! queries, where bugs tend to appear only after a particular interleaving of
+//! changes. The harness keeps a ground truth and the belief planned on, and
+//! interleaves edits of the ground truth, observations copying a window of
+//! the ground truth into the belief, and queries on the belief. Every answer
+//! is checked against a fresh A-Star search on the belief, and the operations
+//! are recorded so a failing run can be replayed exactly. Planners are
+//! soaked through the `IncrementalPlanner` trait, with adapters for adaptive
+//! A-Star, D-Star Lite and path caches.
+
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+use crate::heuristics::distance::manhattan_distance;
+use crate::util::determinism::{with_rng, with_seed};
+
+use super::{adaptive::AdaptiveAStar, astar::astar, cache::PathCache, incremental::DStarLite};
+
+/// A planner answering 4-connected queries on a changing belief
+pub trait IncrementalPlanner {
+    /// Informs the planner of the cells of the belief that changed
+    fn update(&mut self, belief: &BitPackedGrid, changed: &[Cell]);
+
+    /// Plans a path with its cost on the belief
+    fn plan(&mut self, belief: &BitPackedGrid, start: Cell, goal: Cell) -> Option<(Vec<Cell>, usize)>;
+}
+
+/// Adaptive A-Star searches per goal, forgetting what was learned when a cell
+/// becomes free as the learned heuristic is no longer admissible
+#[derive(Default)]
+pub struct AdaptivePlanner {
+    pub searches: HashMap<Cell, AdaptiveAStar<Cell, usize>>,
+}
+
+impl IncrementalPlanner for AdaptivePlanner {
+    fn update(&mut self, belief: &BitPackedGrid, changed: &[Cell]) {
+        if changed.iter().any(|n| belief.get_bit_value(*n)) {
+            self.searches.values_mut().for_each(AdaptiveAStar::reset);
+        }
+    }
+
+    fn plan(&mut self, belief: &BitPackedGrid, start: Cell, goal: Cell) -> Option<(Vec<Cell>, usize)> {
+        let search = self.searches.entry(goal).or_insert_with(|| AdaptiveAStar::new(goal));
+        search.search(|n| belief.adjacent1(*n), start, |n| manhattan_distance(*n, goal))
+    }
+}
+
+/// The heuristic of the D-Star Lite searches of a soak run
+type CellHeuristic = fn(&Cell, &Cell) -> usize;
+
+/// D-Star Lite searches per goal, each repaired with the changed cells and
+/// moved to the start of every query
+#[derive(Default)]
+pub struct DStarLitePlanner {
+    pub searches: HashMap<Cell, DStarLite<Cell, usize, CellHeuristic>>,
+}
+
+impl IncrementalPlanner for DStarLitePlanner {
+    fn update(&mut self, belief: &BitPackedGrid, changed: &[Cell]) {
+        for search in self.searches.values_mut() {
+            search.update(changed, |n| belief.adjacent1(*n));
+        }
+    }
+
+    fn plan(&mut self, belief: &BitPackedGrid, start: Cell, goal: Cell) -> Option<(Vec<Cell>, usize)> {
+        let heuristic: CellHeuristic = |a, b| manhattan_distance(*a, *b);
+        let search = self.searches.entry(goal).or_insert_with(|| DStarLite::new(start, goal, heuristic));
+        search.move_to(start);
+        search.plan(|n| belief.adjacent1(*n))
+    }
+}
+
+/// A path cache keyed by the version of the belief
+pub struct CachedPlanner {
+    pub cache: PathCache<Cell, usize>,
+}
+
+impl IncrementalPlanner for CachedPlanner {
+    fn update(&mut self, belief: &BitPackedGrid, _: &[Cell]) {
+        self.cache.retain_version(belief.version());
+    }
+
+    fn plan(&mut self, belief: &BitPackedGrid, start: Cell, goal: Cell) -> Option<(Vec<Cell>, usize)> {
+        self.cache.get_or_plan(belief.version(), start, goal, |s, g| {
+            astar(|n| belief.adjacent1(*n), *s, |n| n == g, |n| manhattan_distance(*n, *g))
+        })
+    }
+}
+
+/// An operation of a soak run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoakOp {
+    /// Sets a cell of the ground truth free or blocked
+    Edit { cell: Cell, free: bool },
+    /// Copies the ground truth within a radius of a cell into the belief
+    Observe { cell: Cell, radius: usize },
+    /// Plans between two cells of the belief
+    Query { start: Cell, goal: Cell },
+}
+
+/// A query where a planner disagreed with a fresh A-Star search
+/// ## Fields
+/// * `step` - The index of the query in the operations
+/// * `start` - The start of the query
+/// * `goal` - The goal of the query
+/// * `expected` - The optimal cost found by A-Star
+/// * `found` - The cost returned by the planner
+/// * `valid` - Whether the returned path, if any, is a legal path of its cost
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SoakFailure {
+    pub step: usize,
+    pub start: Cell,
+    pub goal: Cell,
+    pub expected: Option<usize>,
+    pub found: Option<usize>,
+    pub valid: bool,
+}
+
+/// The outcome of a soak run
+/// ## Fields
+/// * `ops` - Every operation of the run, for replaying it
+/// * `queries` - The number of queries checked
+/// * `changed` - The number of belief cells changed by observations
+/// * `failures` - The queries where the planner was wrong
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SoakReport {
+    pub ops: Vec<SoakOp>,
+    pub queries: usize,
+    pub changed: usize,
+    pub failures: Vec<SoakFailure>,
+}
+
+impl SoakReport {
+    /// Checks if every query was answered correctly
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// The mix of operations of a soak run
+/// ## Fields
+/// * `steps` - The number of operations
+/// * `edit_probability` - The probability of an operation being an edit
+/// * `observe_probability` - The probability of an operation being an
+///   observation, the remaining operations being queries
+/// * `radius` - The radius of observations
+/// * `seed` - The seed of the run
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SoakConfig {
+    pub steps: usize,
+    pub edit_probability: f64,
+    pub observe_probability: f64,
+    pub radius: usize,
+    pub seed: u64,
+}
+
+impl Default for SoakConfig {
+    fn default() -> Self {
+        SoakConfig { steps: 1000, edit_probability: 0.3, observe_probability: 0.3, radius: 2, seed: 0 }
+    }
+}
+
+/// Generates the random operations of a soak run on a map, edits setting
+/// cells free or blocked with equal probability
+pub fn generate_ops(config: &SoakConfig, width: usize, height: usize) -> Vec<SoakOp> {
+    with_seed(config.seed, || with_rng(|rng| {
+        let mut ops = Vec::with_capacity(config.steps);
+        for _ in 0..config.steps {
+            let roll = rng.gen::<f64>();
+            let cell = (rng.gen_range(0..width), rng.gen_range(0..height));
+            ops.push(if roll < config.edit_probability {
+                SoakOp::Edit { cell, free: rng.gen_bool(0.5) }
+            } else if roll < config.edit_probability + config.observe_probability {
+                SoakOp::Observe { cell, radius: config.radius }
+            } else {
+                SoakOp::Query { start: cell, goal: (rng.gen_range(0..width), rng.gen_range(0..height)) }
+            });
+        }
+        ops
+    }))
+}
+
+/// Runs operations against a planner, checking every query
+/// ## Arguments
+/// * `ground_truth` - The true map, changed by edits
+/// * `belief` - The map planned on, changed by observations
+/// * `ops` - The operations to run in order
+/// * `planner` - The planner under test
+pub fn replay(
+    ground_truth: &mut BitPackedGrid,
+    belief: &mut BitPackedGrid,
+    ops: &[SoakOp],
+    planner: &mut dyn IncrementalPlanner,
+) -> SoakReport {
+    let mut report = SoakReport { ops: ops.to_vec(), ..Default::default() };
+    for (step, op) in ops.iter().enumerate() {
+        match *op {
+            SoakOp::Edit { cell, free } => ground_truth.set_bit_value(cell, free),
+            SoakOp::Observe { cell: (x, y), radius } => {
+                let window = (x.saturating_sub(radius), y.saturating_sub(radius));
+                let (width, height) = (x + radius + 1 - window.0, y + radius + 1 - window.1);
+                let changed = ground_truth.cells_in_rect(window, width, height)
+                    .filter(|n| belief.get_bit_value(*n) != ground_truth.get_bit_value(*n))
+                    .collect::<Vec<_>>();
+                if changed.is_empty() {
+                    continue;
+                }
+                for n in &changed {
+                    belief.set_bit_value(*n, ground_truth.get_bit_value(*n));
+                }
+                report.changed += changed.len();
+                planner.update(belief, &changed);
+            }
+            SoakOp::Query { start, goal } => {
+                if !belief.get_bit_value(start) || !belief.get_bit_value(goal) {
+                    continue;
+                }
+                report.queries += 1;
+                let expected = astar(|n| belief.adjacent1(*n), start, |n| *n == goal, |n| manhattan_distance(*n, goal)).map(|(_, c)| c);
+                let answer = planner.plan(belief, start, goal);
+                let valid = answer.as_ref().is_none_or(|(path, cost)| {
+                    path.first() == Some(&start) && path.last() == Some(&goal) && path.len() == cost + 1
+                        && path.iter().all(|n| belief.get_bit_value(*n))
+                        && path.windows(2).all(|m| manhattan_distance(m[0], m[1]) == 1)
+                });
+                let found = answer.map(|(_, c)| c);
+                if found != expected || !valid {
+                    report.failures.push(SoakFailure { step, start, goal, expected, found, valid });
+                }
+            }
+        }
+    }
+    report
+}
+
+/// Soaks a planner with random operations, starting from a belief equal to
+/// the ground truth
+pub fn soak(ground_truth: &BitPackedGrid, config: &SoakConfig, planner: &mut dyn IncrementalPlanner) -> SoakReport {
+    let ops = generate_ops(config, ground_truth.original_width, ground_truth.original_height);
+    replay(&mut ground_truth.clone(), &mut ground_truth.clone(), &ops, planner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A planner that keeps answering from a cache without invalidating it
+    struct StalePlanner(PathCache<Cell, usize>);
+
+    impl IncrementalPlanner for StalePlanner {
+        fn update(&mut self, _: &BitPackedGrid, _: &[Cell]) {}
+
+        fn plan(&mut self, belief: &BitPackedGrid, start: Cell, goal: Cell) -> Option<(Vec<Cell>, usize)> {
+            self.0.get_or_plan(0, start, goal, |s, g| astar(|n| belief.adjacent1(*n), *s, |n| n == g, |_| 0))
+        }
+    }
+
+    #[test]
+    fn test_soak_planners() {
+        let grid = BitPackedGrid::new_from_string("........\n.@@.@@@.\n........\n.@@@.@@.\n........\n".to_string());
+        let config = SoakConfig { steps: 400, seed: 7, ..Default::default() };
+        let report = soak(&grid, &config, &mut AdaptivePlanner::default());
+        assert!(report.passed(), "{:?}", report.failures);
+        assert!(report.queries > 0 && report.changed > 0);
+        let report = soak(&grid, &config, &mut DStarLitePlanner::default());
+        assert!(report.passed(), "{:?}", report.failures);
+        let report = soak(&grid, &config, &mut CachedPlanner { cache: PathCache::new(16) });
+        assert!(report.passed(), "{:?}", report.failures);
+        assert_eq!(generate_ops(&config, 8, 5), report.ops);
+    }
+
+    #[test]
+    fn test_soak_catches_stale_planner() {
+        let grid = BitPackedGrid::new_from_string("........\n.@@.@@@.\n........\n.@@@.@@.\n........\n".to_string());
+        let config = SoakConfig { steps: 400, seed: 7, ..Default::default() };
+        let report = soak(&grid, &config, &mut StalePlanner(PathCache::new(64)));
+        assert!(!report.passed());
+        // Replaying the operations reproduces the first failure
+        let mut planner = StalePlanner(PathCache::new(64));
+        let replayed = replay(&mut grid.clone(), &mut grid.clone(), &report.ops, &mut planner);
+        assert_eq!(replayed.failures[0], report.failures[0]);
+    }
+}
@@ -0,0 +1,244 @@
+//! # State Ids
+//! Dense integer ids of search states, so algorithms can keep their per-state
+//! data in flat arrays instead of hash maps keyed by states. An indexer maps
+//! states to ids and back: grids number their cells in row order without any
+//! hashing, while an interner assigns ids to arbitrary states in the order they
+//! are first seen. The public API of the searches built on them still takes
+//! and returns states, ids only being used internally.
+
+use std::{collections::{BinaryHeap, HashMap}, hash::Hash, ops::{Add, Index}};
+
+use super::SearchNodeState;
+
+/// The id of a state, a small integer indexing flat arrays
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StateId(pub u32);
+
+impl StateId {
+    /// The id as an array index
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A mapping between states and dense ids
+pub trait StateIndexer {
+    type State;
+
+    /// The id of a state, assigning a new id to an unseen state
+    fn id(&mut self, state: &Self::State) -> StateId;
+
+    /// The state of an id
+    fn state(&self, id: StateId) -> Self::State;
+}
+
+/// Numbers the cells of a grid in row order
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridIndexer {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl GridIndexer {
+    pub fn new(width: usize, height: usize) -> Self {
+        assert!(width * height <= u32::MAX as usize, "The grid has too many cells for 32 bit ids");
+        GridIndexer { width, height }
+    }
+
+    /// The number of ids, one per cell
+    pub fn len(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// Checks if the grid has no cells
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl StateIndexer for GridIndexer {
+    type State = (usize, usize);
+
+    fn id(&mut self, &(x, y): &(usize, usize)) -> StateId {
+        debug_assert!(x < self.width && y < self.height, "The cell is outside the grid");
+        StateId((y * self.width + x) as u32)
+    }
+
+    fn state(&self, id: StateId) -> (usize, usize) {
+        (id.index() % self.width, id.index() / self.width)
+    }
+}
+
+/// Assigns ids to states in the order they are first seen
+#[derive(Clone, Debug)]
+pub struct Interner<N> {
+    ids: HashMap<N, StateId>,
+    states: Vec<N>,
+}
+
+impl<N: Hash + Eq + Clone> Interner<N> {
+    pub fn new() -> Self {
+        Interner { ids: HashMap::new(), states: Vec::new() }
+    }
+
+    /// The id of a state if it has been seen
+    pub fn get(&self, state: &N) -> Option<StateId> {
+        self.ids.get(state).copied()
+    }
+
+    /// The number of states seen
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Checks if no states have been seen
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
+
+impl<N: Hash + Eq + Clone> Default for Interner<N> {
+    fn default() -> Self {
+        Interner::new()
+    }
+}
+
+impl<N: Hash + Eq + Clone> StateIndexer for Interner<N> {
+    type State = N;
+
+    fn id(&mut self, state: &N) -> StateId {
+        if let Some(id) = self.ids.get(state) {
+            return *id;
+        }
+        let id = StateId(self.states.len() as u32);
+        self.ids.insert(state.clone(), id);
+        self.states.push(state.clone());
+        id
+    }
+
+    fn state(&self, id: StateId) -> N {
+        self.states[id.index()].clone()
+    }
+}
+
+/// A value per state id stored in a flat array, growing as ids are written
+/// and reading the default value for unwritten ids
+#[derive(Clone, Debug)]
+pub struct IdMap<T> {
+    values: Vec<T>,
+    default: T,
+}
+
+impl<T: Clone> IdMap<T> {
+    /// Creates a map of default values
+    pub fn new(default: T) -> Self {
+        IdMap { values: Vec::new(), default }
+    }
+
+    /// Creates a map with room for a number of ids, such as the cells of a grid
+    pub fn with_capacity(default: T, capacity: usize) -> Self {
+        IdMap { values: vec![default.clone(); capacity], default }
+    }
+
+    /// Sets the value of an id
+    pub fn insert(&mut self, id: StateId, value: T) {
+        if id.index() >= self.values.len() {
+            self.values.resize(id.index() + 1, self.default.clone());
+        }
+        self.values[id.index()] = value;
+    }
+}
+
+impl<T> Index<StateId> for IdMap<T> {
+    type Output = T;
+
+    fn index(&self, id: StateId) -> &T {
+        self.values.get(id.index()).unwrap_or(&self.default)
+    }
+}
+
+/// A-Star search keeping its per-state data in flat arrays indexed by the ids
+/// of an indexer, see `astar` for the arguments
+pub fn astar_indexed<X, E, I, C, G, H>(
+    indexer: &mut X,
+    mut expander: E,
+    start: X::State,
+    goal: G,
+    heuristic: H,
+) -> Option<(Vec<X::State>, C)>
+where
+    X: StateIndexer,
+    E: FnMut(&X::State) -> I,
+    I: IntoIterator<Item = (X::State, C)>,
+    C: Ord + Default + Clone + Add<Output = C>,
+    G: Fn(&X::State) -> bool,
+    H: Fn(&X::State) -> C,
+{
+    // The parent and cost of every reached state
+    let mut previous: IdMap<Option<(Option<StateId>, C)>> = IdMap::new(None);
+    let start_id = indexer.id(&start);
+    previous.insert(start_id, Some((None, C::default())));
+    let mut open = BinaryHeap::from([SearchNodeState { node: start_id, cost: heuristic(&start) }]);
+    while let Some(SearchNodeState { node, .. }) = open.pop() {
+        let state = indexer.state(node);
+        let (_, cost) = previous[node].clone().unwrap();
+        if goal(&state) {
+            let mut path = vec![state];
+            let mut current = node;
+            while let Some((Some(parent), _)) = previous[current] {
+                path.push(indexer.state(parent));
+                current = parent;
+            }
+            path.reverse();
+            return Some((path, cost));
+        }
+        for (child, step) in expander(&state) {
+            let new_cost = cost.clone() + step;
+            let child_id = indexer.id(&child);
+            if previous[child_id].as_ref().is_none_or(|(_, c)| new_cost < *c) {
+                previous.insert(child_id, Some((Some(node), new_cost.clone())));
+                open.push(SearchNodeState { node: child_id, cost: new_cost + heuristic(&child) });
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::bitpackedgrid::BitPackedGrid;
+    use crate::heuristics::distance::manhattan_distance;
+    use crate::search::astar::astar;
+
+    #[test]
+    fn test_indexers() {
+        let mut grid = GridIndexer::new(4, 3);
+        assert_eq!(grid.id(&(1, 2)), StateId(9));
+        assert_eq!(grid.state(StateId(9)), (1, 2));
+        assert_eq!(grid.len(), 12);
+        let mut interner = Interner::new();
+        assert_eq!(interner.id(&"a"), StateId(0));
+        assert_eq!(interner.id(&"b"), StateId(1));
+        assert_eq!(interner.id(&"a"), StateId(0));
+        assert_eq!((interner.len(), interner.get(&"c")), (2, None));
+        let mut map = IdMap::new(0);
+        map.insert(StateId(3), 7);
+        assert_eq!((map[StateId(3)], map[StateId(1)], map[StateId(10)]), (7, 0, 0));
+    }
+
+    #[test]
+    fn test_astar_indexed() {
+        let grid = BitPackedGrid::new_from_string("......\n.@@@@.\n.@....\n.@.@@@\n......\n".to_string());
+        let (start, goal) = ((2, 2), (5, 4));
+        let expected = astar(|n| grid.adjacent1(*n), start, |n| *n == goal, |n| manhattan_distance(*n, goal)).unwrap();
+        let mut indexer = GridIndexer::new(grid.original_width, grid.original_height);
+        let (path, cost) = astar_indexed(&mut indexer, |n| grid.adjacent1(*n), start, |n| *n == goal, |n| manhattan_distance(*n, goal)).unwrap();
+        assert_eq!(cost, expected.1);
+        assert_eq!((path[0], *path.last().unwrap(), path.len()), (start, goal, expected.0.len()));
+        let mut interner = Interner::new();
+        let (_, cost) = astar_indexed(&mut interner, |n| grid.adjacent1(*n), start, |n| *n == goal, |_| 0).unwrap();
+        assert_eq!(cost, expected.1);
+        assert!(astar_indexed(&mut indexer, |n| grid.adjacent1(*n), start, |n| *n == (0, 5), |_| 0).is_none());
+    }
+}
@@ -0,0 +1,224 @@
+//! # Search Statistics
+//! Counters of the work done by a search along with coarse timings of each of
+//! its phases. Counters are always collected as they are cheap, while phase
+//! timings are only measured with the `profiling` feature. With the feature each
+//! phase also runs inside its own non-inlined function, so phases show up as
+//! separate frames in flamegraphs without configuring an external profiler.
+//! The wall time of a whole search is likewise only measured with the feature.
+//!
+//! Searches report their statistics through `_with_stats` variants, such as
+//! `astar::astar_with_stats`, `domain::search_with_stats`, the searches of
+//! `jps`, `thetastar`, `anyangle`, `adaptive` and `anytime::AraStar`, and
+//! `hierarchy::hpa::HpaStar`, whose statistics cover its abstract search.
+//! `incremental::DStarLite` counts the work of its updates since the last
+//! plan along with the repair.
+//!
+//! An experiment harness records a `QueryStats` row per query, pairing the
+//! statistics with the cost of the path found and the heuristic estimate at
+//! the start, and dumps the rows with `write_stats_csv`.
+
+use std::io::{self, Write};
+use std::time::Duration;
+#[cfg(feature = "profiling")]
+use std::time::Instant;
+
+/// A phase of a search
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Expanding a node, which includes generating and pushing its successors
+    Expand,
+    /// Evaluating the heuristic
+    Heuristic,
+    /// Pushing to and popping from the open list
+    OpenList,
+    /// Generating the successors of a node
+    Successors,
+}
+
+/// The time spent in each phase of a search
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhaseTimes {
+    pub expand: Duration,
+    pub heuristic: Duration,
+    pub open_list: Duration,
+    pub successors: Duration,
+}
+
+impl PhaseTimes {
+    /// The time spent in a phase
+    pub fn get(&self, phase: Phase) -> Duration {
+        match phase {
+            Phase::Expand => self.expand,
+            Phase::Heuristic => self.heuristic,
+            Phase::OpenList => self.open_list,
+            Phase::Successors => self.successors,
+        }
+    }
+
+    fn get_mut(&mut self, phase: Phase) -> &mut Duration {
+        match phase {
+            Phase::Expand => &mut self.expand,
+            Phase::Heuristic => &mut self.heuristic,
+            Phase::OpenList => &mut self.open_list,
+            Phase::Successors => &mut self.successors,
+        }
+    }
+}
+
+/// The statistics of a search
+/// ## Fields
+/// * `expanded` - The number of nodes expanded
+/// * `generated` - The number of successors generated
+/// * `pushed` - The number of nodes pushed to the open list
+/// * `popped` - The number of nodes popped from the open list
+/// * `heuristic_calls` - The number of heuristic evaluations
+/// * `duplicates` - The number of popped nodes skipped as they were already
+///   expanded or had been reached more cheaply since being pushed
+/// * `peak_open` - The largest size of the open list
+/// * `wall_time` - The time taken by the whole search, only measured with the `profiling` feature
+/// * `phases` - The time spent in each phase, only measured with the `profiling` feature
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchStats {
+    pub expanded: usize,
+    pub generated: usize,
+    pub pushed: usize,
+    pub popped: usize,
+    pub heuristic_calls: usize,
+    pub duplicates: usize,
+    pub peak_open: usize,
+    pub wall_time: Duration,
+    pub phases: PhaseTimes,
+}
+
+impl SearchStats {
+    /// Runs a phase of the search, timing it with the `profiling` feature
+    #[cfg(feature = "profiling")]
+    #[inline(never)]
+    pub fn time<R>(&mut self, phase: Phase, f: impl FnOnce() -> R) -> R {
+        let start = Instant::now();
+        let result = f();
+        *self.phases.get_mut(phase) += start.elapsed();
+        result
+    }
+
+    /// Runs a phase of the search, timing it with the `profiling` feature
+    #[cfg(not(feature = "profiling"))]
+    #[inline(always)]
+    pub fn time<R>(&mut self, _: Phase, f: impl FnOnce() -> R) -> R {
+        f()
+    }
+
+    /// Records the size of the open list after a push
+    #[inline(always)]
+    pub fn open_size(&mut self, size: usize) {
+        self.peak_open = self.peak_open.max(size);
+    }
+
+    /// A one line summary of the statistics
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "expanded {}, generated {}, pushed {}, popped {}, heuristic calls {}, duplicates {}, peak open {}",
+            self.expanded, self.generated, self.pushed, self.popped, self.heuristic_calls, self.duplicates, self.peak_open
+        );
+        if cfg!(feature = "profiling") {
+            summary.push_str(&format!(
+                ", wall time {:?}, expand {:?}, heuristic {:?}, open list {:?}, successors {:?}",
+                self.wall_time, self.phases.expand, self.phases.heuristic, self.phases.open_list, self.phases.successors
+            ));
+        }
+        summary
+    }
+}
+
+/// Runs a whole search, measuring its wall time with the `profiling` feature
+#[cfg(feature = "profiling")]
+pub(crate) fn timed<R>(search: impl FnOnce() -> (R, SearchStats)) -> (R, SearchStats) {
+    let start = Instant::now();
+    let (result, mut stats) = search();
+    stats.wall_time = start.elapsed();
+    (result, stats)
+}
+
+/// Runs a whole search, measuring its wall time with the `profiling` feature
+#[cfg(not(feature = "profiling"))]
+#[inline(always)]
+pub(crate) fn timed<R>(search: impl FnOnce() -> (R, SearchStats)) -> (R, SearchStats) {
+    search()
+}
+
+/// The statistics of one query of an experiment
+/// ## Fields
+/// * `query` - A label of the query, such as its scenario and index
+/// * `path_cost` - The cost of the path found, `None` when there is no path
+/// * `start_heuristic` - The heuristic estimate of the cost at the start
+/// * `stats` - The statistics of the search
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QueryStats {
+    pub query: String,
+    pub path_cost: Option<f64>,
+    pub start_heuristic: f64,
+    pub stats: SearchStats,
+}
+
+impl QueryStats {
+    /// The fraction of the path cost estimated by the heuristic at the start,
+    /// 1 for a perfect heuristic and `None` when there is no path or it is free
+    pub fn heuristic_ratio(&self) -> Option<f64> {
+        self.path_cost.filter(|c| *c > 0.0).map(|c| self.start_heuristic / c)
+    }
+}
+
+/// Writes the statistics of queries as CSV with a header line, one row per query
+pub fn write_stats_csv(rows: &[QueryStats], writer: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        writer,
+        "query,path_cost,start_heuristic,heuristic_ratio,expanded,generated,pushed,popped,heuristic_calls,duplicates,peak_open,wall_time"
+    )?;
+    for row in rows {
+        let optional = |v: Option<f64>| v.map_or(String::new(), |v| v.to_string());
+        let stats = &row.stats;
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{},{},{},{}",
+            row.query, optional(row.path_cost), row.start_heuristic, optional(row.heuristic_ratio()),
+            stats.expanded, stats.generated, stats.pushed, stats.popped, stats.heuristic_calls,
+            stats.duplicates, stats.peak_open, stats.wall_time.as_secs_f64()
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::bitpackedgrid::BitPackedGrid;
+    use crate::heuristics::distance::manhattan_distance;
+    use crate::search::astar::astar_with_stats;
+
+    #[test]
+    fn test_write_stats_csv() {
+        let grid = BitPackedGrid::new_from_string("....\n.@@.\n....\n".to_string());
+        let rows = [((0, 0), (3, 2)), ((0, 0), (0, 0))].into_iter().enumerate().map(|(i, (start, goal))| {
+            let (result, stats) = astar_with_stats(|n| grid.adjacent1(*n), start, |n| *n == goal, |n| manhattan_distance(*n, goal));
+            QueryStats {
+                query: format!("q{}", i),
+                path_cost: result.map(|(_, c)| c as f64),
+                start_heuristic: manhattan_distance(start, goal) as f64,
+                stats,
+            }
+        }).collect::<Vec<_>>();
+        assert_eq!(rows[0].heuristic_ratio(), Some(1.0));
+        assert_eq!(rows[1].heuristic_ratio(), None);
+        let mut csv = Vec::new();
+        write_stats_csv(&rows, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("query,path_cost,start_heuristic"));
+        assert!(lines[1].starts_with(&format!("q0,5,5,1,{},", rows[0].stats.expanded)));
+        assert!(lines.iter().all(|l| l.split(',').count() == 12));
+    }
+}
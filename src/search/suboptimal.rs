@@ -0,0 +1,281 @@
+//! # Suboptimal Search
+//! Best-first searches that trade solution quality for fewer expansions, run
+//! on any `SearchDomain` with integer costs:
+//! * Greedy best-first search, expanding the node nearest the goal by the
+//!   heuristic without any guarantee on the cost of the solution
+//! * Focal search (A*ε), expanding the node nearest the goal among the open
+//!   nodes whose f-value is within a weight of the smallest
+//! * Explicit Estimation Search, following Thayer and Ruml, "Bounded
+//!   Suboptimal Search: A Direct Approach Using Inadmissible Estimates"
+//!   (2011), which keeps the focal list on an inadmissible estimate of the
+//!   cost and only falls back to the admissible f-value to keep the bound
+//!
+//! Nodes are reopened whenever a cheaper path to them is found, so the
+//! smallest f-value on the open list remains a lower bound on the optimal
+//! cost. Every solution is returned with the bound it achieved, the ratio of
+//! its cost to that lower bound, which for focal search and EES is at most
+//! the weight and is often much tighter.
+
+use std::collections::{BTreeSet, HashMap};
+
+use super::{domain::SearchDomain, proven_bound, BoundedSolution};
+use super::stateid::{IdMap, Interner, StateId, StateIndexer};
+use super::stats::{timed, SearchStats};
+
+/// A solution of a suboptimal search with the bound proven by its open list
+pub type SuboptimalSolution<N> = BoundedSolution<N, usize>;
+
+/// Greedy best-first search, expanding the open node with the smallest
+/// heuristic and breaking ties by the smallest f-value
+/// ## Returns
+/// An optional solution with the bound it achieved, and the statistics of the search
+pub fn greedy_best_first<D>(domain: &D, start: D::Node, goal: D::Node) -> (Option<SuboptimalSolution<D::Node>>, SearchStats)
+where
+    D: SearchDomain<Cost = usize>,
+{
+    let heuristic = |n: &D::Node| domain.heuristic(n, &goal);
+    explicit_estimation(domain, start, &goal, f64::INFINITY, heuristic, heuristic)
+}
+
+/// Focal search, expanding the open node with the smallest heuristic among
+/// those whose f-value is at most `weight` times the smallest f-value
+/// ## Arguments
+/// * `weight` - The suboptimality bound, at least 1.0
+/// ## Returns
+/// An optional solution with the bound it achieved, and the statistics of the search
+pub fn focal_search<D>(domain: &D, start: D::Node, goal: D::Node, weight: f64) -> (Option<SuboptimalSolution<D::Node>>, SearchStats)
+where
+    D: SearchDomain<Cost = usize>,
+{
+    assert!(weight >= 1.0, "The weight must be at least 1.0");
+    let heuristic = |n: &D::Node| domain.heuristic(n, &goal);
+    explicit_estimation(domain, start, &goal, weight, heuristic, heuristic)
+}
+
+/// Explicit Estimation Search
+/// ## Arguments
+/// * `weight` - The suboptimality bound, at least 1.0
+/// * `estimate` - An inadmissible but more accurate estimate of the cost from
+///   a node to the goal than the heuristic of the domain
+/// * `distance` - An estimate of the number of edges from a node to the goal
+/// ## Returns
+/// An optional solution with the bound it achieved, and the statistics of the search
+pub fn explicit_estimation_search<D, HE, HD>(
+    domain: &D,
+    start: D::Node,
+    goal: D::Node,
+    weight: f64,
+    estimate: HE,
+    distance: HD,
+) -> (Option<SuboptimalSolution<D::Node>>, SearchStats)
+where
+    D: SearchDomain<Cost = usize>,
+    HE: Fn(&D::Node) -> usize,
+    HD: Fn(&D::Node) -> usize,
+{
+    assert!(weight >= 1.0, "The weight must be at least 1.0");
+    explicit_estimation(domain, start, &goal, weight, estimate, distance)
+}
+
+/// The keys of an open node, its admissible f-value, its estimated f-value
+/// and its estimated distance to the goal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Keys {
+    f: usize,
+    estimate: usize,
+    distance: usize,
+}
+
+/// The open list of EES, ordered by the admissible f-value, by the estimated
+/// f-value and, for the focal nodes whose estimated f-value is within the
+/// weight of the smallest, by the estimated distance
+struct OpenLists {
+    keys: HashMap<StateId, Keys>,
+    by_f: BTreeSet<(usize, StateId)>,
+    by_estimate: BTreeSet<(usize, StateId)>,
+    focal: BTreeSet<(usize, usize, StateId)>,
+    weight: f64,
+    threshold: usize,
+}
+
+impl OpenLists {
+    fn new(weight: f64) -> Self {
+        OpenLists {
+            keys: HashMap::new(),
+            by_f: BTreeSet::new(),
+            by_estimate: BTreeSet::new(),
+            focal: BTreeSet::new(),
+            weight,
+            threshold: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    fn insert(&mut self, id: StateId, keys: Keys) {
+        self.remove(id);
+        self.keys.insert(id, keys);
+        self.by_f.insert((keys.f, id));
+        self.by_estimate.insert((keys.estimate, id));
+        if keys.estimate <= self.threshold {
+            self.focal.insert((keys.distance, keys.estimate, id));
+        }
+        self.refocus();
+    }
+
+    fn remove(&mut self, id: StateId) {
+        if let Some(keys) = self.keys.remove(&id) {
+            self.by_f.remove(&(keys.f, id));
+            self.by_estimate.remove(&(keys.estimate, id));
+            self.focal.remove(&(keys.distance, keys.estimate, id));
+            self.refocus();
+        }
+    }
+
+    /// Moves nodes into or out of the focal list after the smallest estimated
+    /// f-value changed
+    fn refocus(&mut self) {
+        let Some(&(smallest, _)) = self.by_estimate.first() else { return };
+        let threshold = if self.weight.is_infinite() { usize::MAX } else { (smallest as f64 * self.weight) as usize };
+        let (low, high) = (self.threshold.min(threshold), self.threshold.max(threshold));
+        if low == high {
+            return;
+        }
+        let moved = self.by_estimate.range((low + 1, StateId(0))..).take_while(|(e, _)| *e <= high).map(|(_, id)| *id).collect::<Vec<_>>();
+        for id in moved {
+            let keys = self.keys[&id];
+            if threshold > self.threshold {
+                self.focal.insert((keys.distance, keys.estimate, id));
+            } else {
+                self.focal.remove(&(keys.distance, keys.estimate, id));
+            }
+        }
+        self.threshold = threshold;
+    }
+
+    /// The smallest admissible f-value, a lower bound on the optimal cost
+    fn lower_bound(&self) -> Option<usize> {
+        self.by_f.first().map(|(f, _)| *f)
+    }
+
+    /// The node to expand, the nearest focal node if its f-value is within the
+    /// bound, else the node with the smallest estimated f-value if it is, else
+    /// the node with the smallest f-value
+    fn select(&self) -> Option<StateId> {
+        let &(f_min, best_f) = self.by_f.first()?;
+        let within = |id: &StateId| self.keys[id].f == f_min || self.keys[id].f as f64 <= self.weight * f_min as f64;
+        let nearest = self.focal.first().map(|(_, _, id)| *id).filter(within);
+        let estimated = self.by_estimate.first().map(|(_, id)| *id).filter(within);
+        Some(nearest.or(estimated).unwrap_or(best_f))
+    }
+}
+
+/// Explicit Estimation Search with unchecked weights, shared by every search
+/// of the module
+fn explicit_estimation<D, HE, HD>(
+    domain: &D,
+    start: D::Node,
+    goal: &D::Node,
+    weight: f64,
+    estimate: HE,
+    distance: HD,
+) -> (Option<SuboptimalSolution<D::Node>>, SearchStats)
+where
+    D: SearchDomain<Cost = usize>,
+    HE: Fn(&D::Node) -> usize,
+    HD: Fn(&D::Node) -> usize,
+{
+    timed(|| {
+        let mut stats = SearchStats { heuristic_calls: 1, pushed: 1, peak_open: 1, ..Default::default() };
+        let mut states = Interner::new();
+        let mut g = IdMap::new(usize::MAX);
+        let mut parent: IdMap<Option<StateId>> = IdMap::new(None);
+        let mut open = OpenLists::new(weight);
+        let keys = |node: &D::Node, cost: usize| Keys {
+            f: cost + domain.heuristic(node, goal),
+            estimate: cost + estimate(node),
+            distance: distance(node),
+        };
+        let root = states.id(&start);
+        g.insert(root, 0);
+        open.insert(root, keys(&start, 0));
+        while let Some(id) = open.select() {
+            let lower_bound = open.lower_bound();
+            open.remove(id);
+            stats.popped += 1;
+            let node = states.state(id);
+            if node == *goal {
+                let mut path = vec![node];
+                let mut current = parent[id];
+                while let Some(previous) = current {
+                    path.push(states.state(previous));
+                    current = parent[previous];
+                }
+                path.reverse();
+                let cost = g[id];
+                let bound = proven_bound(cost as f64, lower_bound.map(|f| f as f64));
+                return (Some(SuboptimalSolution { path, cost, bound }), stats);
+            }
+            stats.expanded += 1;
+            for (child, edge) in domain.successors(&node) {
+                stats.generated += 1;
+                let cost = g[id] + edge;
+                let child_id = states.id(&child);
+                if cost >= g[child_id] {
+                    continue;
+                }
+                g.insert(child_id, cost);
+                parent.insert(child_id, Some(id));
+                stats.heuristic_calls += 1;
+                stats.pushed += 1;
+                open.insert(child_id, keys(&child, cost));
+                stats.open_size(open.len());
+            }
+        }
+        (None, stats)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::{bitpackedgrid::BitPackedGrid, test_queries};
+    use crate::search::domain::search_with_stats;
+
+    #[test]
+    fn test_suboptimal_bounds() {
+        let grid = BitPackedGrid::new_from_file("tests/map.map");
+        let free = grid.free_cells().collect::<Vec<_>>();
+        let mut expanded = [0; 4];
+        for (start, goal) in test_queries(&free, 10) {
+            let (optimal, astar) = search_with_stats(&grid, start, goal);
+            let Some((_, optimal)) = optimal else {
+                assert!(greedy_best_first(&grid, start, goal).0.is_none());
+                continue;
+            };
+            let inflated = |n: &(usize, usize)| grid.heuristic(n, &goal) * 3 / 2;
+            let (greedy, greedy_stats) = greedy_best_first(&grid, start, goal);
+            let (focal, focal_stats) = focal_search(&grid, start, goal, 1.5);
+            let (ees, ees_stats) = explicit_estimation_search(&grid, start, goal, 1.5, inflated, |n| grid.heuristic(n, &goal));
+            for (solution, weight) in [(greedy.unwrap(), f64::INFINITY), (focal.unwrap(), 1.5), (ees.unwrap(), 1.5)] {
+                assert_eq!((solution.path[0], *solution.path.last().unwrap()), (start, goal));
+                assert_eq!(solution.path.len(), solution.cost + 1);
+                assert!(solution.cost >= optimal && solution.bound <= weight);
+                assert!(solution.cost as f64 <= solution.bound * optimal as f64 + 1e-9);
+            }
+            for (total, stats) in expanded.iter_mut().zip([astar, greedy_stats, focal_stats, ees_stats]) {
+                *total += stats.expanded;
+            }
+        }
+        // Focal search can reopen many nodes, while greedy search and EES expand fewer than A-Star
+        assert!(expanded[1] < expanded[0] && expanded[3] < expanded[0]);
+        // A weight of 1.0 is optimal
+        let (solution, _) = focal_search(&grid, free[0], free[free.len() - 1], 1.0);
+        let expected = search_with_stats(&grid, free[0], free[free.len() - 1]).0.map(|(_, c)| c);
+        assert_eq!(solution.map(|s| (s.cost, s.bound)), expected.map(|c| (c, 1.0)));
+        let (start, _) = greedy_best_first(&grid, free[0], free[0]);
+        assert_eq!(start, Some(SuboptimalSolution { path: vec![free[0]], cost: 0, bound: 1.0 }));
+    }
+}
@@ -0,0 +1,329 @@
+//! # Temporal A-Star
+//! A-Star over space-time states, where a state is a node paired with the time
+//! it is reached. Agents may wait in place, moves take the time of their cost
+//! and cells may only be available within windows of time, such as doors that
+//! open and close on a schedule. As the cost of a state is its arrival time the
+//! search finds the earliest arrival at the goal, and a deadline bounds the
+//! times searched. The availability of a cell is also given as its safe
+//! intervals, the maximal spans of time during which it can be occupied.
+//!
+//! Space-time A-Star plans around a `ReservationTable` of the cells and moves
+//! occupied by other agents or dynamic obstacles at each time, as in
+//! cooperative pathfinding, avoiding both arriving at a reserved cell and
+//! swapping cells with another agent along an edge.
+
+use std::{collections::{HashMap, HashSet}, hash::Hash};
+
+use super::astar::astar;
+
+/// An inclusive span of time `(start, end)`
+pub type Interval = (usize, usize);
+
+/// The times at which cells can be occupied. Cells without windows are always
+/// available, while cells with windows are only available within them.
+#[derive(Clone, Debug)]
+pub struct Availability<N> {
+    pub windows: HashMap<N, Vec<Interval>>,
+}
+
+impl<N: Hash + Eq + Clone> Default for Availability<N> {
+    fn default() -> Self {
+        Availability::new()
+    }
+}
+
+impl<N: Hash + Eq + Clone> Availability<N> {
+    /// Creates an availability where every cell is always available
+    pub fn new() -> Self {
+        Availability { windows: HashMap::new() }
+    }
+
+    /// Makes a cell available within a window, in addition to its other windows
+    pub fn open(&mut self, node: N, start: usize, end: usize) {
+        let windows = self.windows.entry(node).or_default();
+        windows.push((start, end));
+        windows.sort();
+    }
+
+    /// Checks if a cell can be occupied at a time
+    pub fn is_available(&self, node: &N, time: usize) -> bool {
+        self.windows.get(node).is_none_or(|w| w.iter().any(|(s, e)| *s <= time && time <= *e))
+    }
+
+    /// The safe intervals of a cell in order, merging overlapping or adjacent windows
+    pub fn safe_intervals(&self, node: &N) -> Vec<Interval> {
+        let Some(windows) = self.windows.get(node) else {
+            return vec![(0, usize::MAX)];
+        };
+        let mut intervals: Vec<Interval> = Vec::new();
+        for &(start, end) in windows {
+            match intervals.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => intervals.push((start, end)),
+            }
+        }
+        intervals
+    }
+}
+
+/// Temporal A-Star Search for the earliest arrival at a goal
+/// ## Arguments
+/// * `expander` - A function that returns the nodes adjacent to a given node
+///   with the time taken to move to them
+/// * `start` - The start node
+/// * `start_time` - The time at the start node
+/// * `goal` - A function that returns whether or not a given node is the goal
+/// * `heuristic` - An admissible estimate of the time to reach the goal
+/// * `availability` - The times at which cells can be occupied
+/// * `deadline` - The latest time the goal may be reached
+/// ## Returns
+/// An optional timed path of (node, time) states, where consecutive states
+/// with the same node are waits, and the time of arrival at the goal
+pub fn temporal_astar<E, I, N, G, H>(
+    mut expander: E,
+    start: N,
+    start_time: usize,
+    goal: G,
+    heuristic: H,
+    availability: &Availability<N>,
+    deadline: usize,
+) -> Option<(Vec<(N, usize)>, usize)>
+where
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, usize)>,
+    N: Hash + Clone + Eq,
+    G: Fn(&N) -> bool,
+    H: Fn(&N) -> usize,
+{
+    if !availability.is_available(&start, start_time) {
+        return None;
+    }
+    let feasible = |node: &N, time: usize| {
+        time.saturating_add(heuristic(node)) <= deadline && availability.is_available(node, time)
+    };
+    let (path, _) = astar(
+        |(node, time): &(N, usize)| {
+            let mut children = Vec::new();
+            if feasible(node, time + 1) {
+                children.push(((node.clone(), time + 1), 1));
+            }
+            for (child, duration) in expander(node) {
+                if feasible(&child, time + duration) {
+                    children.push(((child, time + duration), duration));
+                }
+            }
+            children
+        },
+        (start, start_time),
+        |(node, _)| goal(node),
+        |(node, _)| heuristic(node),
+    )?;
+    let arrival = path.last().map(|(_, t)| *t)?;
+    Some((path, arrival))
+}
+
+/// The cells and moves occupied at each time, such as by agents already
+/// planned or by dynamic obstacles of a simulation
+/// ## Fields
+/// * `vertices` - The cells occupied at each time
+/// * `edges` - The moves `(from, to, time)` made from `from` at `time`
+/// * `parked` - The cells occupied from a time onwards, such as by agents at their goals
+#[derive(Clone, Debug)]
+pub struct ReservationTable<N> {
+    pub vertices: HashSet<(N, usize)>,
+    pub edges: HashSet<(N, N, usize)>,
+    pub parked: HashMap<N, usize>,
+    last: HashMap<N, usize>,
+}
+
+impl<N: Hash + Eq + Clone> Default for ReservationTable<N> {
+    fn default() -> Self {
+        ReservationTable::new()
+    }
+}
+
+impl<N: Hash + Eq + Clone> ReservationTable<N> {
+    /// Creates a table with nothing reserved
+    pub fn new() -> Self {
+        ReservationTable { vertices: HashSet::new(), edges: HashSet::new(), parked: HashMap::new(), last: HashMap::new() }
+    }
+
+    /// Reserves a cell at a time
+    pub fn reserve_cell(&mut self, node: N, time: usize) {
+        let last = self.last.entry(node.clone()).or_default();
+        *last = (*last).max(time);
+        self.vertices.insert((node, time));
+    }
+
+    /// Reserves a move from a cell at a time to another cell at the next time
+    pub fn reserve_move(&mut self, from: N, to: N, time: usize) {
+        self.edges.insert((from, to, time));
+    }
+
+    /// Reserves a cell from a time onwards
+    pub fn park(&mut self, node: N, time: usize) {
+        let parked = self.parked.entry(node).or_insert(time);
+        *parked = (*parked).min(time);
+    }
+
+    /// Reserves the cells and moves of a timed path starting at a time, one
+    /// step per unit of time, parking at its last cell
+    pub fn reserve_path(&mut self, path: &[N], start_time: usize) {
+        for (i, node) in path.iter().enumerate() {
+            self.reserve_cell(node.clone(), start_time + i);
+        }
+        for (i, pair) in path.windows(2).enumerate() {
+            self.reserve_move(pair[0].clone(), pair[1].clone(), start_time + i);
+        }
+        if let Some(last) = path.last() {
+            self.park(last.clone(), start_time + path.len() - 1);
+        }
+    }
+
+    /// Checks if a cell is occupied at a time
+    pub fn is_reserved(&self, node: &N, time: usize) -> bool {
+        self.vertices.contains(&(node.clone(), time)) || self.parked.get(node).is_some_and(|t| *t <= time)
+    }
+
+    /// Checks if a move from a cell at a time to another cell, or a wait when
+    /// they are the same, is free of vertex and edge-swap conflicts
+    pub fn is_free(&self, from: &N, to: &N, time: usize) -> bool {
+        !self.is_reserved(to, time + 1)
+            && !self.edges.contains(&(to.clone(), from.clone(), time))
+            && !self.edges.contains(&(from.clone(), to.clone(), time))
+    }
+
+    /// Checks if a cell can be occupied forever from a time onwards
+    pub fn can_park(&self, node: &N, time: usize) -> bool {
+        self.last.get(node).is_none_or(|t| *t < time) && !self.parked.contains_key(node)
+    }
+}
+
+/// Space-Time A-Star Search for the earliest arrival at a goal avoiding the
+/// reservations of a table, where every move and wait takes one unit of time.
+/// The goal is only reached once it can be occupied for the rest of time, so
+/// the path can be reserved with `ReservationTable::reserve_path` for the
+/// agents planned after it.
+/// ## Arguments
+/// * `expander` - A function that returns the nodes adjacent to a given node
+/// * `start` - The start node
+/// * `start_time` - The time at the start node
+/// * `goal` - The goal node
+/// * `heuristic` - An admissible estimate of the time to reach the goal
+/// * `reservations` - The cells and moves occupied at each time
+/// * `max_time` - The latest time the goal may be reached
+/// ## Returns
+/// An optional timed path of (node, time) states, where consecutive states
+/// with the same node are waits, and the time of arrival at the goal
+pub fn space_time_astar<E, I, N, H>(
+    mut expander: E,
+    start: N,
+    start_time: usize,
+    goal: N,
+    heuristic: H,
+    reservations: &ReservationTable<N>,
+    max_time: usize,
+) -> Option<(Vec<(N, usize)>, usize)>
+where
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = N>,
+    N: Hash + Clone + Eq,
+    H: Fn(&N) -> usize,
+{
+    if reservations.is_reserved(&start, start_time) {
+        return None;
+    }
+    let (path, _) = astar(
+        |(node, time): &(N, usize)| {
+            if *time >= max_time || time.saturating_add(heuristic(node)) > max_time {
+                return Vec::new();
+            }
+            std::iter::once(node.clone())
+                .chain(expander(node))
+                .filter(|child| reservations.is_free(node, child, *time))
+                .map(|child| ((child, time + 1), 1))
+                .collect::<Vec<_>>()
+        },
+        (start, start_time),
+        |(node, time)| *node == goal && reservations.can_park(node, *time),
+        |(node, _)| heuristic(node),
+    )?;
+    let arrival = path.last().map(|(_, t)| *t)?;
+    Some((path, arrival))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::bitpackedgrid::BitPackedGrid;
+    use crate::heuristics::distance::manhattan_distance;
+
+    #[test]
+    fn test_safe_intervals() {
+        let mut availability = Availability::new();
+        availability.open((1, 0), 8, 9);
+        availability.open((1, 0), 2, 4);
+        availability.open((1, 0), 5, 6);
+        assert_eq!(availability.safe_intervals(&(1, 0)), vec![(2, 6), (8, 9)]);
+        assert_eq!(availability.safe_intervals(&(0, 0)), vec![(0, usize::MAX)]);
+        assert!(availability.is_available(&(1, 0), 9) && !availability.is_available(&(1, 0), 7));
+    }
+
+    #[test]
+    fn test_temporal_astar() {
+        let grid = BitPackedGrid::new_from_string("....\n@@.@\n....\n".to_string());
+        let search = |availability: &Availability<(usize, usize)>, deadline| temporal_astar(
+            |n| grid.adjacent1(*n).filter(|(n, _)| grid.bounds_check(*n)).collect::<Vec<_>>(),
+            (0, 0),
+            0,
+            |n| *n == (0, 2),
+            |n| manhattan_distance(*n, (0, 2)),
+            availability,
+            deadline,
+        );
+        let (path, arrival) = search(&Availability::new(), 100).unwrap();
+        assert_eq!(arrival, 6);
+        assert_eq!(path.len(), 7);
+
+        let mut door = Availability::new();
+        door.open((2, 1), 5, 5);
+        let (path, arrival) = search(&door, 100).unwrap();
+        assert_eq!(arrival, 8);
+        assert!(path.contains(&((2, 1), 5)));
+        assert!(path.windows(2).any(|p| p[0].0 == p[1].0));
+        assert_eq!(search(&door, 7), None);
+
+        door.windows.insert((2, 1), vec![(0, 1)]);
+        assert_eq!(search(&door, 30), None);
+    }
+
+    #[test]
+    fn test_space_time_astar() {
+        let grid = BitPackedGrid::new_from_string(".....\n@@.@@\n.....\n".to_string());
+        let expander = |n: &(usize, usize)| grid.adjacent(*n, false).filter(|n| grid.bounds_check(*n)).collect::<Vec<_>>();
+        let plan = |start, goal, table: &ReservationTable<_>| {
+            space_time_astar(expander, start, 0, goal, |n| manhattan_distance(*n, goal), table, 20)
+        };
+        let mut table = ReservationTable::new();
+        let (first, arrival) = plan((0, 0), (0, 2), &table).unwrap();
+        assert_eq!(arrival, 6);
+        table.reserve_path(&first.iter().map(|(n, _)| *n).collect::<Vec<_>>(), 0);
+        // The second agent crosses the corridor the other way without meeting the first
+        let (second, arrival) = plan((4, 2), (4, 0), &table).unwrap();
+        assert!(arrival > 6);
+        for (t, (node, time)) in second.iter().enumerate() {
+            assert_eq!(*time, t);
+            assert!(first.get(t).is_none_or(|(n, _)| n != node));
+        }
+        for pair in second.windows(2) {
+            assert!(!table.edges.contains(&(pair[1].0, pair[0].0, pair[0].1)));
+        }
+        // A dynamic obstacle holding the corridor delays the crossing
+        let mut obstacle = ReservationTable::new();
+        (0..6).for_each(|t| obstacle.reserve_cell((2, 1), t));
+        let (path, arrival) = plan((2, 0), (2, 2), &obstacle).unwrap();
+        assert_eq!((arrival, path[6].0), (7, (2, 1)));
+        assert!(path.windows(2).any(|w| w[0].0 == w[1].0));
+        assert_eq!(plan((2, 0), (2, 2), &{ obstacle.park((2, 1), 6); obstacle }), None);
+    }
+}
@@ -0,0 +1,93 @@
+//! # Terrain-Aware Planning
+//! Planning on terrain class beliefs where each class has its own traversal
+//! cost. The cost of entering a cell is the expected cost over its class
+//! probabilities, and cells that are too likely to be obstacles are avoided.
+
+use crate::domains::classgrid::{ClassGrid, Terrain};
+use crate::heuristics::distance::manhattan_distance;
+
+use super::astar::astar;
+
+/// The traversal costs of each terrain class
+/// ## Fields
+/// * `free` - The cost of entering a free cell
+/// * `hazard` - The cost of entering a hazardous cell
+/// * `obstacle` - The cost of entering a cell that turns out to be an obstacle
+/// * `max_obstacle` - The probability of being an obstacle above which a cell is never entered
+#[derive(Clone, Debug, PartialEq)]
+pub struct TerrainCosts {
+    pub free: usize,
+    pub hazard: usize,
+    pub obstacle: usize,
+    pub max_obstacle: f32,
+}
+
+impl TerrainCosts {
+    /// The cost of entering a cell of a class
+    pub fn cost(&self, terrain: Terrain) -> usize {
+        match terrain {
+            Terrain::Free => self.free,
+            Terrain::Obstacle => self.obstacle,
+            Terrain::Hazard => self.hazard,
+        }
+    }
+
+    /// The expected cost of entering a cell, or `None` if it is too likely to be an obstacle
+    pub fn expected_cost(&self, grid: &ClassGrid, node: (usize, usize)) -> Option<usize> {
+        let probabilities = grid.probabilities(node);
+        if probabilities[Terrain::Obstacle.index()] > self.max_obstacle {
+            return None;
+        }
+        let cost = Terrain::ALL.iter().map(|t| probabilities[t.index()] * self.cost(*t) as f32).sum::<f32>();
+        Some(cost.round() as usize)
+    }
+}
+
+/// Plans the path of least expected traversal cost on a terrain class belief
+/// ## Returns
+/// An optional path from the start to the goal with its expected cost
+pub fn terrain_plan(
+    grid: &ClassGrid,
+    start: (usize, usize),
+    goal: (usize, usize),
+    costs: &TerrainCosts,
+) -> Option<(Vec<(usize, usize)>, usize)> {
+    let min_cost = Terrain::ALL.iter().map(|t| costs.cost(*t)).min().unwrap_or(0);
+    astar(
+        |n| grid.adjacent(*n, false)
+            .filter_map(|n| costs.expected_cost(grid, n).map(|c| (n, c)))
+            .collect::<Vec<_>>(),
+        start,
+        |n| *n == goal,
+        |n| min_cost * manhattan_distance(*n, goal),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terrain_plan() {
+        let mut grid = ClassGrid::new(4, 3, 1.0);
+        for x in 0..4 {
+            for y in 0..3 {
+                grid.observe((x, y), Terrain::Free, 98.0);
+            }
+        }
+        for node in [(1, 0), (2, 0)] {
+            grid.observe(node, Terrain::Hazard, 100.0);
+        }
+        grid.observe((1, 1), Terrain::Obstacle, 100.0);
+        let costs = TerrainCosts { free: 10, hazard: 1000, obstacle: 100, max_obstacle: 0.5 };
+        assert_eq!(costs.expected_cost(&grid, (1, 1)), None);
+        assert_eq!(costs.expected_cost(&grid, (0, 0)), Some(21));
+
+        let (path, _) = terrain_plan(&grid, (0, 0), (3, 0), &costs).unwrap();
+        assert_eq!(path.len(), 8);
+        assert!(path.iter().all(|n| n.1 > 0 || n.0 == 0 || n.0 == 3));
+        let cheap = TerrainCosts { hazard: 10, ..costs };
+        let (path, _) = terrain_plan(&grid, (0, 0), (3, 0), &cheap).unwrap();
+        assert_eq!(path, vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+    }
+}
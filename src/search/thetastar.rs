@@ -0,0 +1,287 @@
+//! # Theta* and Lazy Theta*
+//! Any-angle search on bitpacked grids, following Nash, Daniel, Koenig and
+//! Felner, "Theta*: Any-Angle Path Planning on Grids" (2007) and Nash, Koenig
+//! and Tovey, "Lazy Theta*" (2010). Cell `(x, y)` covers the square from
+//! `(x, y)` to `(x + 1, y + 1)`, and paths are straight segments between cell
+//! centres that may not cross a blocked cell nor touch the corner of one.
+//! Theta* checks the line of sight from the parent of a node to each of its
+//! successors, while Lazy Theta* assumes it and only checks once a node is
+//! expanded, which needs far fewer checks for the same kind of paths.
+//!
+//! Line of sight is checked a word at a time: the cells a segment crosses in a
+//! row are a contiguous span, read from the row with a single mask, and steep
+//! segments are checked the same way on a transposed copy of the grid.
+
+use std::{cmp::Reverse, collections::{BinaryHeap, HashMap, HashSet}};
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+
+use super::anyangle::Cost;
+use super::stats::{timed, SearchStats};
+
+/// The straight line distance between the centres of two cells
+fn distance((x1, y1): Cell, (x2, y2): Cell) -> f64 {
+    (x1 as f64 - x2 as f64).hypot(y1 as f64 - y2 as f64)
+}
+
+/// The span of cells crossed in each row by the segment between the centres
+/// of two cells of a shallow segment, whose rows are crossed in order
+fn row_spans((x0, y0): Cell, (x1, y1): Cell) -> impl Iterator<Item = (usize, usize, usize)> {
+    let (dx, dy) = (x1 as i64 - x0 as i64, y1 as i64 - y0 as i64);
+    let rows = dy.unsigned_abs() as i64;
+    (0..=rows).map(move |k| {
+        if rows == 0 {
+            return (x0.min(x1), x0.max(x1), y0);
+        }
+        // The x of the segment where it enters and leaves the row, over 2 * rows
+        let at = |k: i64| 2 * rows * x0 as i64 + dx * k.clamp(0, 2 * rows);
+        let (a, b) = (at(2 * k - 1), at(2 * k + 1));
+        let (low, high) = (a.min(b), a.max(b));
+        // A cell is crossed if the segment touches it, including at a corner
+        let first = -(-(low - rows)).div_euclid(2 * rows);
+        let last = (high + rows).div_euclid(2 * rows);
+        (first as usize, last as usize, y0.wrapping_add_signed((k * dy.signum()) as isize))
+    })
+}
+
+/// Checks if every cell in a span of a row is free, a word at a time
+fn span_free(grid: &BitPackedGrid, first: usize, last: usize, y: usize) -> bool {
+    let mut x = first;
+    while x <= last {
+        let length = (last - x + 1).min(usize::BITS as usize);
+        let mask = if length == usize::BITS as usize { usize::MAX } else { (1 << length) - 1 };
+        if grid.row_word_east((x, y)) & mask != mask {
+            return false;
+        }
+        x += length;
+    }
+    true
+}
+
+/// An any-angle path
+/// ## Fields
+/// * `waypoints` - The points where the path turns, from the centre of the start to the centre of the goal
+/// * `vertices` - The cells of the waypoints
+/// * `cells` - Every cell touched by the path in order
+/// * `cost` - The length of the path
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnyAnglePath {
+    pub waypoints: Vec<(f32, f32)>,
+    pub vertices: Vec<Cell>,
+    pub cells: Vec<Cell>,
+    pub cost: f64,
+}
+
+/// Theta* and Lazy Theta* on an 8-connected grid
+/// ## Fields
+/// * `expanded` - The number of nodes expanded by the last search
+/// * `sight_checks` - The number of line of sight checks of the last search
+pub struct ThetaStar<'a> {
+    grid: &'a BitPackedGrid,
+    transposed: BitPackedGrid,
+    pub expanded: usize,
+    pub sight_checks: usize,
+}
+
+impl<'a> ThetaStar<'a> {
+    /// Prepares searches on a grid, transposing it to check steep segments
+    pub fn new(grid: &'a BitPackedGrid) -> Self {
+        ThetaStar { grid, transposed: grid.transposed(), expanded: 0, sight_checks: 0 }
+    }
+
+    /// Checks if the segment between the centres of two cells crosses only free cells
+    pub fn line_of_sight(&self, from: Cell, to: Cell) -> bool {
+        if !self.grid.bounds_check(from) || !self.grid.bounds_check(to) {
+            return false;
+        }
+        if from.0.abs_diff(to.0) >= from.1.abs_diff(to.1) {
+            row_spans(from, to).all(|(first, last, y)| span_free(self.grid, first, last, y))
+        } else {
+            row_spans((from.1, from.0), (to.1, to.0)).all(|(first, last, x)| span_free(&self.transposed, first, last, x))
+        }
+    }
+
+    /// Every cell touched by the segment between the centres of two cells, in order
+    pub fn line_cells(from: Cell, to: Cell) -> Vec<Cell> {
+        let forward = |first: usize, last: usize, increasing: bool| -> Vec<usize> {
+            if increasing { (first..=last).collect() } else { (first..=last).rev().collect() }
+        };
+        if from.0.abs_diff(to.0) >= from.1.abs_diff(to.1) {
+            row_spans(from, to).flat_map(|(first, last, y)| forward(first, last, to.0 >= from.0).into_iter().map(move |x| (x, y))).collect()
+        } else {
+            row_spans((from.1, from.0), (to.1, to.0)).flat_map(|(first, last, x)| forward(first, last, to.1 >= from.1).into_iter().map(move |y| (x, y))).collect()
+        }
+    }
+
+    fn sight(&mut self, from: Cell, to: Cell) -> bool {
+        self.sight_checks += 1;
+        self.line_of_sight(from, to)
+    }
+
+    /// The 8-connected neighbours of a cell, without cutting corners
+    fn neighbours(&self, node: Cell) -> Vec<Cell> {
+        self.grid.adjacent_octile(node).map(|(n, _)| n).collect()
+    }
+
+    /// Searches for an any-angle path with Theta*
+    pub fn theta_star(&mut self, start: Cell, goal: Cell) -> Option<AnyAnglePath> {
+        self.search(start, goal, false).0
+    }
+
+    /// Searches for an any-angle path with Lazy Theta*
+    pub fn lazy_theta_star(&mut self, start: Cell, goal: Cell) -> Option<AnyAnglePath> {
+        self.search(start, goal, true).0
+    }
+
+    /// Searches for an any-angle path with Theta*, also returning the
+    /// statistics of the search
+    pub fn theta_star_with_stats(&mut self, start: Cell, goal: Cell) -> (Option<AnyAnglePath>, SearchStats) {
+        self.search(start, goal, false)
+    }
+
+    /// Searches for an any-angle path with Lazy Theta*, also returning the
+    /// statistics of the search
+    pub fn lazy_theta_star_with_stats(&mut self, start: Cell, goal: Cell) -> (Option<AnyAnglePath>, SearchStats) {
+        self.search(start, goal, true)
+    }
+
+    fn search(&mut self, start: Cell, goal: Cell, lazy: bool) -> (Option<AnyAnglePath>, SearchStats) {
+        (self.expanded, self.sight_checks) = (0, 0);
+        timed(|| {
+            let mut stats = SearchStats::default();
+            let path = self.search_counted(start, goal, lazy, &mut stats);
+            (path, stats)
+        })
+    }
+
+    fn search_counted(&mut self, start: Cell, goal: Cell, lazy: bool, stats: &mut SearchStats) -> Option<AnyAnglePath> {
+        let free = |n: Cell| self.grid.bounds_check(n) && self.grid.get_bit_value(n);
+        if !free(start) || !free(goal) {
+            return None;
+        }
+        let mut g = HashMap::from([(start, 0.0)]);
+        let mut parent = HashMap::from([(start, start)]);
+        let mut closed = HashSet::new();
+        let mut open = BinaryHeap::from([Reverse((Cost(distance(start, goal)), start))]);
+        (stats.pushed, stats.heuristic_calls, stats.peak_open) = (1, 1, 1);
+        while let Some(Reverse((_, node))) = open.pop() {
+            stats.popped += 1;
+            if !closed.insert(node) {
+                stats.duplicates += 1;
+                continue;
+            }
+            self.expanded += 1;
+            stats.expanded += 1;
+            if lazy && !self.sight(parent[&node], node) {
+                // The assumed line of sight is blocked, so the best expanded neighbour is the parent
+                let (best, cost) = self.neighbours(node).into_iter()
+                    .filter(|n| closed.contains(n))
+                    .map(|n| (n, g[&n] + distance(n, node)))
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .expect("A node is reached from an expanded neighbour");
+                parent.insert(node, best);
+                g.insert(node, cost);
+            }
+            if node == goal {
+                return Some(self.path(&parent, start, goal, g[&goal]));
+            }
+            for child in self.neighbours(node) {
+                if closed.contains(&child) {
+                    continue;
+                }
+                stats.generated += 1;
+                let grandparent = parent[&node];
+                let (from, cost) = if lazy || self.sight(grandparent, child) {
+                    (grandparent, g[&grandparent] + distance(grandparent, child))
+                } else {
+                    (node, g[&node] + distance(node, child))
+                };
+                if g.get(&child).is_none_or(|c| cost < *c) {
+                    g.insert(child, cost);
+                    parent.insert(child, from);
+                    open.push(Reverse((Cost(cost + distance(child, goal)), child)));
+                    stats.heuristic_calls += 1;
+                    stats.pushed += 1;
+                    stats.open_size(open.len());
+                }
+            }
+        }
+        None
+    }
+
+    fn path(&self, parent: &HashMap<Cell, Cell>, start: Cell, goal: Cell, cost: f64) -> AnyAnglePath {
+        let mut vertices = vec![goal];
+        while *vertices.last().unwrap() != start {
+            vertices.push(parent[vertices.last().unwrap()]);
+        }
+        vertices.reverse();
+        let mut cells = vec![start];
+        for segment in vertices.windows(2) {
+            cells.extend(ThetaStar::line_cells(segment[0], segment[1]).into_iter().skip(1));
+        }
+        let waypoints = vertices.iter().map(|&(x, y)| (x as f32 + 0.5, y as f32 + 0.5)).collect();
+        AnyAnglePath { waypoints, vertices, cells, cost }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::test_queries;
+    use crate::heuristics::octile::exact_octile_distance;
+    use crate::search::astar::astar;
+
+    #[test]
+    fn test_line_of_sight() {
+        let grid = BitPackedGrid::new_from_string("......\n..@...\n......\n......\n".to_string());
+        let theta = ThetaStar::new(&grid);
+        assert!(theta.line_of_sight((0, 0), (5, 0)));
+        assert!(!theta.line_of_sight((0, 1), (5, 1)));
+        assert!(!theta.line_of_sight((2, 0), (2, 3)));
+        assert!(theta.line_of_sight((0, 3), (5, 1)));
+        assert!(!theta.line_of_sight((0, 2), (5, 0)));
+        // Touching the corner of a blocked cell blocks the line
+        assert!(!theta.line_of_sight((1, 0), (3, 2)));
+        assert!(!theta.line_of_sight((1, 3), (2, 0)));
+        assert_eq!(ThetaStar::line_cells((0, 0), (2, 1)), vec![(0, 0), (1, 0), (1, 1), (2, 1)]);
+        assert_eq!(ThetaStar::line_cells((1, 3), (1, 1)), vec![(1, 3), (1, 2), (1, 1)]);
+        let wide = BitPackedGrid::new_from_string(format!("{}\n{}@\n", ".".repeat(150), ".".repeat(149)));
+        let theta = ThetaStar::new(&wide);
+        assert!(theta.line_of_sight((0, 0), (149, 0)));
+        assert!(!theta.line_of_sight((0, 1), (149, 1)));
+        // The line of sight agrees with the cells it touches
+        let grid = BitPackedGrid::new_from_file("tests/map.map");
+        let theta = ThetaStar::new(&grid);
+        let free = grid.free_cells().collect::<Vec<_>>();
+        for (a, b) in test_queries(&free, 500) {
+            let (a, b) = (a, ((a.0 + b.0) / 2, (a.1 + b.1) / 2));
+            assert_eq!(theta.line_of_sight(a, b), ThetaStar::line_cells(a, b).iter().all(|n| grid.get_bit_value(*n)), "{:?} {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_theta_star() {
+        let grid = BitPackedGrid::new_from_string("........\n...@@...\n...@@...\n........\n".to_string());
+        let mut theta = ThetaStar::new(&grid);
+        let path = theta.theta_star((0, 0), (7, 0)).unwrap();
+        assert_eq!((path.vertices.clone(), path.cost), (vec![(0, 0), (7, 0)], 7.0));
+        assert_eq!(path.waypoints, vec![(0.5, 0.5), (7.5, 0.5)]);
+        assert_eq!(path.cells.len(), 8);
+
+        let octile = |start: Cell, goal: Cell| astar(|n| grid.adjacent_octile(*n), start, |n| *n == goal, |n| exact_octile_distance(*n, goal)).unwrap().1.to_f64();
+        for (start, goal) in [((0, 3), (7, 1)), ((1, 2), (6, 1)), ((0, 1), (7, 2))] {
+            for path in [theta.theta_star(start, goal).unwrap(), theta.lazy_theta_star(start, goal).unwrap()] {
+                assert!(path.cost <= octile(start, goal) + 1e-9);
+                assert!(path.vertices.windows(2).all(|s| theta.line_of_sight(s[0], s[1])));
+                let length = path.vertices.windows(2).map(|s| distance(s[0], s[1])).sum::<f64>();
+                assert!((length - path.cost).abs() < 1e-9);
+                assert!(path.cells.iter().all(|n| grid.get_bit_value(*n)));
+                assert_eq!((path.cells[0], *path.cells.last().unwrap()), (start, goal));
+            }
+        }
+        assert!(theta.theta_star((0, 0), (3, 1)).is_none());
+        let (path, stats) = theta.lazy_theta_star_with_stats((0, 3), (7, 1));
+        assert_eq!((path, stats.expanded), (theta.lazy_theta_star((0, 3), (7, 1)), theta.expanded));
+        assert!(stats.popped <= stats.pushed && stats.peak_open > 0);
+    }
+}
@@ -0,0 +1,220 @@
+//! # Search Traces
+//! An instrumented A-Star that records the order nodes are opened and
+//! expanded, so the progress of a search can be replayed. The state of the
+//! search after any number of expansions is recovered from the trace, which
+//! on grids is plotted as frames or an animated GIF of the closed set and
+//! frontier growing until the final path is found. Plots are useful for
+//! figures and for seeing how a heuristic steers a search.
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::Add;
+
+#[cfg(feature = "viz")]
+use crate::domains::{bitpackedgrid::BitPackedGrid, plot_frames_gif, Annotation, Frame, PIXEL_BUDGET};
+
+use super::{reconstruct_path_with_cost, SearchNodeState};
+
+/// An event of a search
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraceEvent<N> {
+    /// A node was pushed to the open list
+    Opened(N),
+    /// A node was popped from the open list and expanded
+    Expanded(N),
+}
+
+/// The events of a search in order
+/// ## Fields
+/// * `events` - Every opening and expansion of the search
+/// * `path` - The path found by the search
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchTrace<N> {
+    pub events: Vec<TraceEvent<N>>,
+    pub path: Option<Vec<N>>,
+}
+
+impl<N: Hash + Eq + Clone> SearchTrace<N> {
+    /// The number of nodes expanded by the search
+    pub fn expansions(&self) -> usize {
+        self.events.iter().filter(|e| matches!(e, TraceEvent::Expanded(_))).count()
+    }
+
+    /// The nodes in the order they were expanded
+    pub fn expansion_order(&self) -> Vec<N> {
+        self.events.iter().filter_map(|e| match e {
+            TraceEvent::Expanded(n) => Some(n.clone()),
+            _ => None,
+        }).collect()
+    }
+
+    /// The state of the search after a number of expansions
+    /// ## Returns
+    /// The expanded nodes and the nodes on the open list, both in the order
+    /// they were reached
+    pub fn state(&self, expansions: usize) -> (Vec<N>, Vec<N>) {
+        let (mut closed, mut open) = (Vec::new(), Vec::new());
+        let mut expanded = HashSet::new();
+        for event in &self.events {
+            match event {
+                TraceEvent::Expanded(_) if closed.len() == expansions => break,
+                TraceEvent::Expanded(n) => {
+                    expanded.insert(n.clone());
+                    closed.push(n.clone());
+                }
+                TraceEvent::Opened(n) => open.push(n.clone()),
+            }
+        }
+        let mut seen = HashSet::new();
+        open.retain(|n| !expanded.contains(n) && seen.insert(n.clone()));
+        (closed, open)
+    }
+}
+
+/// A-Star Search that records a trace of its progress, see `SearchTrace`
+/// ## Returns
+/// An optional vector of nodes from the start to the goal with its cost, and
+/// the trace of the search
+pub fn astar_with_trace<E, I, C, N, G, H>(
+    mut expander: E,
+    start: N,
+    goal: G,
+    heuristic: H,
+) -> (Option<(Vec<N>, C)>, SearchTrace<N>)
+where
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+    C: Ord + Default + Clone + Add<Output = C>,
+    N: Hash + Clone + Eq,
+    G: Fn(&N) -> bool,
+    H: Fn(&N) -> C,
+{
+    let mut trace = SearchTrace { events: vec![TraceEvent::Opened(start.clone())], path: None };
+    let mut open = BinaryHeap::from([SearchNodeState { cost: heuristic(&start), node: start.clone() }]);
+    let mut previous = HashMap::from([(start, (None, C::default()))]);
+    let mut closed = HashSet::new();
+    while let Some(SearchNodeState { node, .. }) = open.pop() {
+        if !closed.insert(node.clone()) {
+            continue;
+        }
+        if goal(&node) {
+            let (path, cost) = reconstruct_path_with_cost(previous, node);
+            trace.path = Some(path.clone());
+            return (Some((path, cost)), trace);
+        }
+        trace.events.push(TraceEvent::Expanded(node.clone()));
+        let g = previous[&node].1.clone();
+        for (child, cost) in expander(&node) {
+            let new_cost = g.clone() + cost;
+            if previous.get(&child).is_none_or(|(_, c)| new_cost < *c) {
+                previous.insert(child.clone(), (Some(node.clone()), new_cost.clone()));
+                trace.events.push(TraceEvent::Opened(child.clone()));
+                open.push(SearchNodeState { cost: new_cost + heuristic(&child), node: child });
+            }
+        }
+    }
+    (None, trace)
+}
+
+/// The colour of expanded cells in plots of traces
+#[cfg(feature = "viz")]
+const CLOSED_COLOUR: (u8, u8, u8) = (150, 190, 240);
+
+/// The colour of cells on the open list in plots of traces
+#[cfg(feature = "viz")]
+const OPEN_COLOUR: (u8, u8, u8) = (120, 210, 120);
+
+/// The number of expansions shown by each of a number of frames, evenly
+/// spaced so the last frame shows the whole search
+#[cfg(feature = "viz")]
+fn frame_steps(expansions: usize, frames: usize) -> Vec<usize> {
+    let frames = frames.max(1);
+    (1..=frames).map(|i| expansions * i / frames).collect()
+}
+
+/// The path and annotations of each frame of a trace on a grid, the final
+/// path drawn in the last frame
+#[cfg(feature = "viz")]
+fn trace_frames(trace: &SearchTrace<(usize, usize)>, frames: usize) -> Vec<Frame> {
+    let steps = frame_steps(trace.expansions(), frames);
+    let endpoints = trace.path.as_ref().map(|p| vec![Annotation::Start(p[0]), Annotation::Goal(p[p.len() - 1])]);
+    steps.iter().enumerate().map(|(i, step)| {
+        let (closed, open) = trace.state(*step);
+        let mut annotations = vec![
+            Annotation::Highlight { cells: closed, colour: CLOSED_COLOUR },
+            Annotation::Highlight { cells: open, colour: OPEN_COLOUR },
+        ];
+        annotations.extend(endpoints.clone().unwrap_or_default());
+        let path = if i + 1 == steps.len() { trace.path.clone() } else { None };
+        (path, annotations)
+    }).collect()
+}
+
+/// Plots the progress of a search on a grid as numbered PNG frames, the
+/// expanded cells in blue, the open list in green and the final path in red
+/// ## Arguments
+/// * `grid` - The grid searched
+/// * `trace` - The trace of the search
+/// * `prefix` - The prefix of the files, each named `<prefix>_<frame>.png`
+/// * `frames` - The number of frames
+/// ## Returns
+/// The names of the files written
+#[cfg(feature = "viz")]
+pub fn plot_trace_frames(grid: &BitPackedGrid, trace: &SearchTrace<(usize, usize)>, prefix: &str, frames: usize) -> Vec<String> {
+    trace_frames(trace, frames).into_iter().enumerate().map(|(i, (path, annotations))| {
+        let filename = format!("{}_{:04}.png", prefix, i);
+        grid.plot_annotated(&filename, path, None, &annotations);
+        filename
+    }).collect()
+}
+
+/// Plots the progress of a search on a grid as an animated GIF, see
+/// `plot_trace_frames` for the colours
+/// ## Arguments
+/// * `delay` - The time each frame is shown in milliseconds
+#[cfg(feature = "viz")]
+pub fn plot_trace_gif(grid: &BitPackedGrid, trace: &SearchTrace<(usize, usize)>, filename: &str, frames: usize, delay: u32) {
+    let (width, height) = (grid.original_width, grid.original_height);
+    plot_frames_gif(width, height, filename, |x, y| grid.get_bit_value((x, y)), trace_frames(trace, frames), delay, PIXEL_BUDGET)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::bitpackedgrid::BitPackedGrid;
+    use crate::heuristics::distance::manhattan_distance;
+    use crate::search::astar::astar_with_stats;
+
+    #[test]
+    fn test_astar_with_trace() {
+        let grid = BitPackedGrid::new_from_string("......\n.@@@@.\n......\n".to_string());
+        let search = |n: &(usize, usize)| grid.adjacent1(*n);
+        let (result, trace) = astar_with_trace(search, (0, 0), |n| *n == (5, 2), |n| manhattan_distance(*n, (5, 2)));
+        let (expected, stats) = astar_with_stats(search, (0, 0), |n| *n == (5, 2), |n| manhattan_distance(*n, (5, 2)));
+        assert_eq!(result, expected);
+        assert_eq!(trace.path, result.map(|(p, _)| p));
+        assert_eq!(trace.expansions(), stats.expanded);
+        assert_eq!(trace.expansion_order()[0], (0, 0));
+        assert_eq!(trace.state(0), (vec![], vec![(0, 0)]));
+        let (closed, open) = trace.state(1);
+        assert_eq!((closed, open.len()), (vec![(0, 0)], 2));
+        let (closed, open) = trace.state(trace.expansions());
+        assert_eq!(closed.len(), stats.expanded);
+        assert!(open.iter().all(|n| !closed.contains(n)));
+    }
+
+    #[cfg(feature = "viz")]
+    #[test]
+    fn test_plot_trace() {
+        let grid = BitPackedGrid::new_from_string("......\n.@@@@.\n......\n".to_string());
+        let (_, trace) = astar_with_trace(|n| grid.adjacent1(*n), (0, 0), |n| *n == (5, 2), |n| manhattan_distance(*n, (5, 2)));
+        let prefix = std::env::temp_dir().join("pathfinding-rs-trace");
+        let files = plot_trace_frames(&grid, &trace, prefix.to_str().unwrap(), 3);
+        assert_eq!(files.len(), 3);
+        files.iter().for_each(|f| std::fs::remove_file(f).unwrap());
+        let gif = std::env::temp_dir().join("pathfinding-rs-trace.gif");
+        plot_trace_gif(&grid, &trace, gif.to_str().unwrap(), 4, 100);
+        assert!(std::fs::metadata(&gif).unwrap().len() > 0);
+        std::fs::remove_file(gif).unwrap();
+    }
+}
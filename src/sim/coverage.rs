@@ -0,0 +1,153 @@
+//! # Exploration Coverage
+//! Tracking of how much of the free space of the ground truth an exploring
+//! agent has observed over time. A free cell is covered once the covariance of
+//! its belief falls below a threshold. Coverage curves of different strategies
+//! can be compared by the area under them, which rewards covering space early,
+//! exported as CSV or plotted together.
+
+use std::io::{self, Write};
+
+#[cfg(feature = "viz")]
+use plotters::prelude::*;
+
+use crate::domains::samplegrid::SampleGrid;
+
+/// The fraction of the free cells of the ground truth whose covariance is below a threshold
+pub fn coverage(grid: &SampleGrid, threshold: f32) -> f64 {
+    let (mut free, mut covered) = (0, 0);
+    for x in 0..grid.width {
+        for y in 0..grid.height {
+            if grid.ground_truth.get_bit_value((x, y)) {
+                free += 1;
+                covered += (grid.covariance((x, y)) < threshold) as usize;
+            }
+        }
+    }
+    if free == 0 { 1.0 } else { covered as f64 / free as f64 }
+}
+
+/// The coverage of an exploration episode recorded over time
+/// ## Fields
+/// * `threshold` - The covariance below which a cell is covered
+/// * `samples` - The (step, coverage) pairs in the order recorded
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoverageCurve {
+    pub threshold: f32,
+    pub samples: Vec<(usize, f64)>,
+}
+
+impl CoverageCurve {
+    /// Starts an empty curve
+    pub fn new(threshold: f32) -> Self {
+        CoverageCurve { threshold, samples: Vec::new() }
+    }
+
+    /// Records the coverage of a grid at a step
+    pub fn record(&mut self, step: usize, grid: &SampleGrid) -> f64 {
+        let fraction = coverage(grid, self.threshold);
+        self.samples.push((step, fraction));
+        fraction
+    }
+
+    /// The number of recorded samples
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Checks if no samples have been recorded
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// The coverage at the last recorded step
+    pub fn last(&self) -> Option<f64> {
+        self.samples.last().map(|(_, c)| *c)
+    }
+
+    /// The first step at which the coverage reached a fraction
+    pub fn steps_to(&self, fraction: f64) -> Option<usize> {
+        self.samples.iter().find(|(_, c)| *c >= fraction).map(|(s, _)| *s)
+    }
+
+    /// The area under the curve up to a horizon, holding each sample until the
+    /// next, normalised by the horizon so that immediate full coverage is 1.0
+    pub fn area(&self, horizon: usize) -> f64 {
+        if horizon == 0 {
+            return 0.0;
+        }
+        let mut area = 0.0;
+        for (i, (step, fraction)) in self.samples.iter().enumerate() {
+            let next = self.samples.get(i + 1).map_or(horizon, |(s, _)| *s).min(horizon);
+            area += next.saturating_sub(*step) as f64 * fraction;
+        }
+        area / horizon as f64
+    }
+
+    /// Writes the curve as CSV with a `step,coverage` header
+    pub fn write_csv(&self, writer: &mut impl Write) -> io::Result<()> {
+        writeln!(writer, "step,coverage")?;
+        for (step, fraction) in &self.samples {
+            writeln!(writer, "{},{}", step, fraction)?;
+        }
+        Ok(())
+    }
+
+    /// Saves the curve as CSV
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        let mut file = io::BufWriter::new(std::fs::File::create(filename)?);
+        self.write_csv(&mut file)
+    }
+}
+
+/// Plots labelled coverage curves, such as those of different exploration
+/// strategies, as lines over the steps
+#[cfg(feature = "viz")]
+pub fn plot_coverage(output_file: &str, curves: &[(&str, &CoverageCurve)]) {
+    let max_step = curves.iter().flat_map(|(_, c)| c.samples.iter().map(|(s, _)| *s)).max().unwrap_or(0).max(1);
+    let root = BitMapBackend::new(output_file, (640, 480)).into_drawing_area();
+    root.fill(&WHITE).unwrap();
+    let mut chart = ChartBuilder::on(&root)
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(40)
+        .build_cartesian_2d(0..max_step, 0.0..1.0)
+        .unwrap();
+    chart.configure_mesh().x_desc("Step").y_desc("Coverage").draw().unwrap();
+    for (i, (label, curve)) in curves.iter().enumerate() {
+        let colour = Palette99::pick(i).to_rgba();
+        chart.draw_series(LineSeries::new(curve.samples.iter().cloned(), colour))
+            .unwrap()
+            .label(*label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], colour));
+    }
+    chart.configure_series_labels().background_style(WHITE).border_style(BLACK).draw().unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage_curve() {
+        let mut grid = SampleGrid::new_from_string("..@\n...\n".to_string());
+        let mut curve = CoverageCurve::new(0.5);
+        assert_eq!(curve.record(0, &grid), 0.0);
+        grid.update_node((0, 0), 0.0);
+        grid.update_node((2, 0), 0.0);
+        assert_eq!(curve.record(2, &grid), 0.2);
+        for x in 0..3 {
+            grid.update_node((x, 1), 0.0);
+        }
+        grid.update_node((1, 0), 0.0);
+        assert_eq!(curve.record(3, &grid), 1.0);
+        assert_eq!(curve.len(), 3);
+        assert_eq!(curve.last(), Some(1.0));
+        assert_eq!(curve.steps_to(0.2), Some(2));
+        assert_eq!(curve.steps_to(0.5), Some(3));
+        assert!((curve.area(5) - (0.2 + 2.0) / 5.0).abs() < 1e-9);
+
+        let mut csv = Vec::new();
+        curve.write_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "step,coverage\n0,0\n2,0.2\n3,1\n");
+    }
+}
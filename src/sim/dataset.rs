@@ -0,0 +1,162 @@
+//! # Imitation Datasets
+//! Generation of (local belief window, optimal next move) training pairs for
+//! learned policies and heuristics. Worlds are sampled from a sampling grid and
+//! solved with A-Star, and every step of each optimal path becomes a sample of
+//! the belief around the agent labelled with the move taken. Datasets can be
+//! exported as CSV or as a float32 NumPy `.npy` array.
+
+use std::io::{self, Write};
+
+use crate::domains::samplegrid::SampleGrid;
+use crate::heuristics::distance::manhattan_distance;
+use crate::search::astar::astar;
+
+use super::env::{belief_window, Action};
+use super::replanning::Query;
+
+/// A training pair
+/// ## Fields
+/// * `window` - The belief around the agent, see `belief_window`
+/// * `goal_offset` - The offset from the agent to the goal
+/// * `action` - The optimal next move
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub window: Vec<f32>,
+    pub goal_offset: (isize, isize),
+    pub action: Action,
+}
+
+/// A set of training pairs
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dataset {
+    pub samples: Vec<Sample>,
+}
+
+impl Dataset {
+    /// Generates samples by solving sampled worlds
+    /// ## Arguments
+    /// * `grid` - The sampling grid, its belief forms the windows and worlds are sampled from it
+    /// * `queries` - The (start, goal) pairs to solve in every world
+    /// * `worlds` - The number of worlds to sample
+    /// * `window_radius` - The window has a width of `2 * window_radius + 1`
+    pub fn generate(grid: &mut SampleGrid, queries: &[Query], worlds: usize, window_radius: usize) -> Self {
+        let mut samples = Vec::new();
+        for _ in 0..worlds {
+            grid.sample_all();
+            for &(start, goal) in queries {
+                let Some((path, _)) = astar(
+                    |n| grid.gridmap().adjacent1(*n),
+                    start,
+                    |n| *n == goal,
+                    |n| manhattan_distance(*n, goal),
+                ) else {
+                    continue;
+                };
+                for step in path.windows(2) {
+                    samples.push(Sample {
+                        window: belief_window(grid, step[0], window_radius),
+                        goal_offset: (goal.0 as isize - step[0].0 as isize, goal.1 as isize - step[0].1 as isize),
+                        action: Action::between(step[0], step[1]).unwrap(),
+                    });
+                }
+            }
+        }
+        Dataset { samples }
+    }
+
+    /// The number of samples
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Checks if there are no samples
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// The samples as rows of the window, the goal offset and the action id
+    fn rows(&self) -> impl Iterator<Item = Vec<f32>> + '_ {
+        self.samples.iter().map(|s| {
+            let mut row = s.window.clone();
+            row.extend([s.goal_offset.0 as f32, s.goal_offset.1 as f32, s.action.id() as f32]);
+            row
+        })
+    }
+
+    /// Writes the dataset as CSV with one sample per line, the last three
+    /// columns are the goal offset and the action id
+    pub fn write_csv(&self, writer: &mut impl Write) -> io::Result<()> {
+        for row in self.rows() {
+            let line = row.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",");
+            writeln!(writer, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the dataset as a 2D float32 `.npy` array with the same columns as the CSV
+    pub fn write_npy(&self, writer: &mut impl Write) -> io::Result<()> {
+        let columns = self.samples.first().map(|s| s.window.len() + 3).unwrap_or(0);
+        let mut header = format!(
+            "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+            self.len(), columns
+        );
+        // The magic string, version and header length take 10 bytes and the
+        // header is padded so the data is 64 byte aligned
+        let padding = 64 - (10 + header.len() + 1) % 64;
+        header.push_str(&" ".repeat(padding % 64));
+        header.push('\n');
+        writer.write_all(b"\x93NUMPY\x01\x00")?;
+        writer.write_all(&(header.len() as u16).to_le_bytes())?;
+        writer.write_all(header.as_bytes())?;
+        for row in self.rows() {
+            for value in row {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves the dataset, as `.npy` if the file name ends with `.npy` and as CSV otherwise
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        let mut file = io::BufWriter::new(std::fs::File::create(filename)?);
+        if filename.ends_with(".npy") {
+            self.write_npy(&mut file)
+        } else {
+            self.write_csv(&mut file)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dataset_generate() {
+        let mut grid = SampleGrid::new_from_string("...\n@@.\n...\n".to_string());
+        let dataset = Dataset::generate(&mut grid, &[((0, 0), (0, 2)), ((0, 0), (0, 1))], 2, 1);
+        assert_eq!(dataset.len(), 12);
+        assert_eq!(dataset.samples[0].action, Action::Right);
+        assert_eq!(dataset.samples[0].goal_offset, (0, 2));
+        assert_eq!(dataset.samples[0].window, vec![0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(dataset.samples[5].action, Action::Left);
+    }
+
+    #[test]
+    fn test_dataset_export() {
+        let dataset = Dataset {
+            samples: vec![Sample { window: vec![1.0, 0.5], goal_offset: (-1, 2), action: Action::Down }],
+        };
+        let mut csv = Vec::new();
+        dataset.write_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "1,0.5,-1,2,1\n");
+        let mut npy = Vec::new();
+        dataset.write_npy(&mut npy).unwrap();
+        assert_eq!(&npy[..6], b"\x93NUMPY");
+        let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        assert!(String::from_utf8_lossy(&npy[10..10 + header_len]).contains("'shape': (1, 5)"));
+        assert_eq!(npy.len(), 10 + header_len + 5 * 4);
+        assert_eq!(&npy[npy.len() - 4..], &1.0f32.to_le_bytes());
+    }
+}
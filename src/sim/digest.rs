@@ -0,0 +1,84 @@
+//! # Belief Digest Caching
+//! Caching of plans keyed by a digest of the belief around a query, so an
+//! agent loop can skip planning when the observations since the last plan did
+//! not change the relevant part of the map. The relevant part is the bounding
+//! box of the start and goal grown by a margin, and a cached plan is only
+//! reused while every cell along it is still believed to be free.
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+use crate::search::cache::PathCache;
+
+/// A planner that reuses plans made on the same belief window
+/// ## Fields
+/// * `margin` - The number of cells the window extends beyond the start and goal
+/// * `cache` - The cached plans keyed by the digest of their window
+pub struct DigestPlanner<P> {
+    pub margin: usize,
+    pub cache: PathCache<Cell, usize>,
+    planner: P,
+}
+
+impl<P> DigestPlanner<P>
+where
+    P: FnMut(&BitPackedGrid, Cell, Cell) -> Option<Vec<Cell>>,
+{
+    /// Wraps a planner with a cache of at most `capacity` plans
+    pub fn new(planner: P, margin: usize, capacity: usize) -> Self {
+        DigestPlanner { margin, cache: PathCache::new(capacity), planner }
+    }
+
+    /// The digest of the belief window of a query
+    pub fn digest(&self, belief: &BitPackedGrid, start: Cell, goal: Cell) -> usize {
+        let (x, y) = (start.0.min(goal.0).saturating_sub(self.margin), start.1.min(goal.1).saturating_sub(self.margin));
+        let width = start.0.max(goal.0) + self.margin + 1 - x;
+        let height = start.1.max(goal.1) + self.margin + 1 - y;
+        belief.window_hash((x, y), width, height) as usize
+    }
+
+    /// Plans on the belief, reusing the cached plan of the query if its
+    /// window is unchanged and the plan is still free
+    pub fn plan(&mut self, belief: &BitPackedGrid, start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+        let digest = self.digest(belief, start, goal);
+        if let Some(Some((path, _))) = self.cache.get(digest, &start, &goal) {
+            if path.iter().all(|n| belief.get_bit_value(*n)) {
+                self.cache.hits += 1;
+                return Some(path);
+            }
+        }
+        self.cache.misses += 1;
+        let path = (self.planner)(belief, start, goal);
+        if let Some(path) = &path {
+            self.cache.insert(digest, start, goal, Some((path.clone(), path.len() - 1)));
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{astar_planner, run_episode, RadiusSensor};
+
+    #[test]
+    fn test_digest_planner() {
+        let mut belief = BitPackedGrid::new_from_string("........\n.@@.....\n........\n........\n".to_string());
+        let mut planner = DigestPlanner::new(astar_planner, 1, 8);
+        let path = planner.plan(&belief, (0, 0), (3, 2)).unwrap();
+        assert_eq!(planner.plan(&belief, (0, 0), (3, 2)), Some(path.clone()));
+        belief.set_bit_value((7, 3), false);
+        assert_eq!(planner.plan(&belief, (0, 0), (3, 2)), Some(path));
+        assert_eq!((planner.cache.hits, planner.cache.misses), (2, 1));
+        belief.set_bit_value((4, 0), false);
+        planner.plan(&belief, (0, 0), (3, 2)).unwrap();
+        assert_eq!(planner.cache.misses, 2);
+
+        let ground_truth = BitPackedGrid::new_from_string("....\n.@@.\n....\n".to_string());
+        let open = BitPackedGrid::new_from_string("....\n....\n....\n".to_string());
+        for _ in 0..2 {
+            let mut belief = open.clone();
+            let episode = run_episode(&ground_truth, &mut belief, (0, 1), (3, 1), |b, s, g| planner.plan(b, s, g), &RadiusSensor { radius: 1 }, 20);
+            assert!(episode.success);
+        }
+        assert!(planner.cache.hits > 2);
+    }
+}
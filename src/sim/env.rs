@@ -0,0 +1,233 @@
+//! # Reinforcement Learning Environment
+//! A gym-style environment over sampling grid worlds. The agent observes a
+//! window of its belief centred on itself together with the offset to the goal,
+//! chooses a move direction and receives a reward of -1 for every step and a
+//! bonus for reaching the goal. After every move the cells around the agent are
+//! measured against the ground truth, so the belief sharpens as the agent explores.
+
+use crate::domains::{direction::Direction, samplegrid::SampleGrid};
+
+/// A move of the agent
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Action {
+    /// All actions, indexed by their action id
+    pub const ALL: [Action; 4] = [Action::Up, Action::Down, Action::Left, Action::Right];
+
+    /// The action moving between two neighbouring cells
+    pub fn between((x1, y1): (usize, usize), (x2, y2): (usize, usize)) -> Option<Action> {
+        Action::ALL.into_iter().find(|a| a.apply((x1, y1)) == Some((x2, y2)))
+    }
+
+    /// The id of the action
+    pub fn id(&self) -> usize {
+        Action::ALL.iter().position(|a| a == self).unwrap()
+    }
+
+    /// The direction of the move
+    pub fn direction(&self) -> Direction {
+        match self {
+            Action::Up => Direction::North,
+            Action::Down => Direction::South,
+            Action::Left => Direction::West,
+            Action::Right => Direction::East,
+        }
+    }
+
+    /// The cell reached by taking the action, or None if it leaves the grid
+    fn apply(&self, cell: (usize, usize)) -> Option<(usize, usize)> {
+        self.direction().apply(cell)
+    }
+}
+
+/// What the agent observes
+/// ## Fields
+/// * `window` - The belief of the cells around the agent in row-major order,
+///   where cells outside the grid are 0.0
+/// * `goal_offset` - The offset from the agent to the goal
+#[derive(Clone, Debug, PartialEq)]
+pub struct Observation {
+    pub window: Vec<f32>,
+    pub goal_offset: (isize, isize),
+}
+
+/// The result of taking a step in the environment
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    pub observation: Observation,
+    pub reward: f32,
+    pub done: bool,
+}
+
+/// The configuration of the environment
+/// ## Fields
+/// * `window_radius` - The observation window has a width of `2 * window_radius + 1`
+/// * `sensor_radius` - The radius of cells measured after every move
+/// * `measurement_covariance` - The covariance of sensor measurements
+/// * `goal_bonus` - The reward for reaching the goal
+/// * `collision_penalty` - The added penalty for moving into an obstacle
+/// * `max_steps` - The number of steps after which the episode ends
+#[derive(Clone, Debug)]
+pub struct EnvConfig {
+    pub window_radius: usize,
+    pub sensor_radius: usize,
+    pub measurement_covariance: f32,
+    pub goal_bonus: f32,
+    pub collision_penalty: f32,
+    pub max_steps: usize,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig {
+            window_radius: 3,
+            sensor_radius: 1,
+            measurement_covariance: 0.0,
+            goal_bonus: 100.0,
+            collision_penalty: 1.0,
+            max_steps: 1000,
+        }
+    }
+}
+
+/// A gym-style environment over a sampling grid
+pub struct GridEnv {
+    initial: SampleGrid,
+    pub grid: SampleGrid,
+    pub config: EnvConfig,
+    pub start: (usize, usize),
+    pub goal: (usize, usize),
+    pub position: (usize, usize),
+    pub steps: usize,
+}
+
+impl GridEnv {
+    pub fn new(grid: SampleGrid, start: (usize, usize), goal: (usize, usize), config: EnvConfig) -> Self {
+        assert!(grid.bound_check(start) && grid.bound_check(goal));
+        let mut env = GridEnv {
+            initial: grid.clone(),
+            grid,
+            config,
+            start,
+            goal,
+            position: start,
+            steps: 0,
+        };
+        env.sense();
+        env
+    }
+
+    /// The number of actions
+    pub fn action_space(&self) -> usize {
+        Action::ALL.len()
+    }
+
+    /// The length of the observation window
+    pub fn observation_size(&self) -> usize {
+        (2 * self.config.window_radius + 1).pow(2)
+    }
+
+    /// Restores the initial belief and returns the agent to the start
+    pub fn reset(&mut self) -> Observation {
+        self.grid = self.initial.clone();
+        self.position = self.start;
+        self.steps = 0;
+        self.sense();
+        self.observe()
+    }
+
+    /// Takes an action, moving into an obstacle leaves the agent in place
+    pub fn step(&mut self, action: Action) -> Step {
+        self.steps += 1;
+        let mut reward = -1.0;
+        match action.apply(self.position) {
+            Some(next) if self.grid.bound_check(next) && self.grid.ground_truth.get_bit_value(next) => {
+                self.position = next;
+            }
+            _ => reward -= self.config.collision_penalty,
+        }
+        self.sense();
+        let reached = self.position == self.goal;
+        if reached {
+            reward += self.config.goal_bonus;
+        }
+        Step {
+            observation: self.observe(),
+            reward,
+            done: reached || self.steps >= self.config.max_steps,
+        }
+    }
+
+    /// The current observation of the agent
+    pub fn observe(&self) -> Observation {
+        let (x, y) = (self.position.0 as isize, self.position.1 as isize);
+        Observation {
+            window: belief_window(&self.grid, self.position, self.config.window_radius),
+            goal_offset: (self.goal.0 as isize - x, self.goal.1 as isize - y),
+        }
+    }
+
+    /// Measures the cells around the agent
+    fn sense(&mut self) {
+        let (x, y) = self.position;
+        let r = self.config.sensor_radius;
+        for i in x.saturating_sub(r)..=(x + r).min(self.grid.width - 1) {
+            for j in y.saturating_sub(r)..=(y + r).min(self.grid.height - 1) {
+                self.grid.update_node((i, j), self.config.measurement_covariance);
+            }
+        }
+    }
+}
+
+/// The belief of the cells within a square window around a cell in row-major
+/// order, where cells outside the grid are 0.0
+pub fn belief_window(grid: &SampleGrid, (x, y): (usize, usize), radius: usize) -> Vec<f32> {
+    let r = radius as isize;
+    let (x, y) = (x as isize, y as isize);
+    (-r..=r)
+        .flat_map(|dy| (-r..=r).map(move |dx| (x + dx, y + dy)))
+        .map(|(i, j)| match (usize::try_from(i), usize::try_from(j)) {
+            (Ok(i), Ok(j)) if grid.bound_check((i, j)) => grid.belief((i, j)),
+            _ => 0.0,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_env() {
+        let grid = SampleGrid::new_from_string("...\n.@.\n...\n".to_string());
+        let config = EnvConfig { window_radius: 1, goal_bonus: 10.0, ..EnvConfig::default() };
+        let mut env = GridEnv::new(grid, (0, 0), (2, 0), config);
+        let observation = env.reset();
+        assert_eq!(env.observation_size(), 9);
+        assert_eq!(observation.window, vec![0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.0]);
+        assert_eq!(observation.goal_offset, (2, 0));
+
+        let step = env.step(Action::Up);
+        assert_eq!(step.reward, -2.0);
+        assert_eq!(env.position, (0, 0));
+        let step = env.step(Action::Right);
+        assert_eq!(step.reward, -1.0);
+        assert!(!step.done);
+        let step = env.step(Action::Right);
+        assert_eq!(step.reward, 9.0);
+        assert!(step.done);
+
+        env.reset();
+        assert_eq!(env.position, (0, 0));
+        assert_eq!(Action::between((1, 1), (1, 0)), Some(Action::Up));
+        assert_eq!(Action::between((1, 1), (2, 2)), None);
+        assert_eq!(Action::Right.id(), 3);
+        assert_eq!(env.steps, 0);
+    }
+}
@@ -0,0 +1,264 @@
+//! # Experiment Scenarios
+//! A whole experiment as a single serializable object: the map, the queries,
+//! the scripted dynamics, the sensor of the agent and the seed of the run. The
+//! same scenario file is consumed by the runner, the simulator and the
+//! visualiser, so an experiment is shared and rerun by passing one file.
+//! Scenarios are saved as text with one keyword per line:
+//!
+//! ```text
+//! map 5x3:3H4
+//! sensor radius 1
+//! seed 7
+//! max_steps 100
+//! known false
+//! query 0 0 4 0
+//! event 1 set 0 2 0 2 1
+//! event 4 toggle 3 1
+//! event 6 flood 0 0 2 1
+//! ```
+//!
+//! where the map is a compact grid string, queries are `start_x start_y goal_x
+//! goal_y` and events are a tick followed by `set free cells..`, `toggle
+//! cells..` or `flood id seed depth`, cells being written as `x y`.
+
+use std::{fmt::Write as _, io};
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+use crate::util::determinism::{derive_seed, with_seed};
+
+use super::{
+    replanning::Query,
+    scenario::{run_scenario_episode, Event, Scenario as Dynamics},
+    Episode, RadiusSensor, RaycastSensor, SensorModel,
+};
+
+/// A serializable description of the sensor of the agent
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SensorSpec {
+    /// A `RadiusSensor` with a radius
+    Radius(usize),
+    /// A `RaycastSensor` with a radius
+    Raycast(usize),
+}
+
+impl SensorSpec {
+    /// The sensor model described
+    pub fn sensor(&self) -> Box<dyn SensorModel> {
+        match *self {
+            SensorSpec::Radius(radius) => Box::new(RadiusSensor { radius }),
+            SensorSpec::Raycast(radius) => Box::new(RaycastSensor { radius }),
+        }
+    }
+}
+
+/// An experiment on a dynamic map
+/// ## Fields
+/// * `map` - The ground truth at the start of the experiment
+/// * `queries` - The start and goal of each episode
+/// * `dynamics` - The events changing the ground truth during each episode
+/// * `sensor` - The sensor of the agent
+/// * `seed` - The master seed, each query being run with a seed derived from it
+/// * `max_steps` - The number of moves after which an episode fails
+/// * `known` - Whether the agent starts knowing the map, otherwise it assumes
+///   every cell is free
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub map: BitPackedGrid,
+    pub queries: Vec<Query>,
+    pub dynamics: Dynamics,
+    pub sensor: SensorSpec,
+    pub seed: u64,
+    pub max_steps: usize,
+    pub known: bool,
+}
+
+impl Scenario {
+    /// Creates a scenario on a map without queries or events, where a known
+    /// map is sensed with a radius of 1
+    pub fn new(map: BitPackedGrid) -> Self {
+        let max_steps = 4 * map.original_width * map.original_height;
+        Scenario { map, queries: Vec::new(), dynamics: Dynamics::new(), sensor: SensorSpec::Radius(1), seed: 0, max_steps, known: true }
+    }
+
+    /// Adds a query
+    pub fn query(mut self, start: Cell, goal: Cell) -> Self {
+        self.queries.push((start, goal));
+        self
+    }
+
+    /// Sets the events changing the map
+    pub fn with_dynamics(mut self, dynamics: Dynamics) -> Self {
+        self.dynamics = dynamics;
+        self
+    }
+
+    /// Sets the sensor of the agent
+    pub fn with_sensor(mut self, sensor: SensorSpec) -> Self {
+        self.sensor = sensor;
+        self
+    }
+
+    /// Sets the master seed
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The belief of the agent at the start of an episode
+    pub fn belief(&self) -> BitPackedGrid {
+        if self.known {
+            return self.map.clone();
+        }
+        let mut belief = self.map.clone();
+        for cell in self.map.cells() {
+            belief.set_bit_value(cell, true);
+        }
+        belief
+    }
+
+    /// The ground truth after every event up to and including a tick
+    pub fn map_at(&self, time: usize) -> BitPackedGrid {
+        self.dynamics.map_at(&self.map, time)
+    }
+
+    /// Runs an episode per query, each from a fresh belief and a seed derived
+    /// from the master seed and the index of the query
+    /// ## Arguments
+    /// * `planner` - Plans a path on the belief from the agent's cell to the goal
+    pub fn run<P>(&self, mut planner: P) -> Vec<Episode>
+    where
+        P: FnMut(&BitPackedGrid, Cell, Cell) -> Option<Vec<Cell>>,
+    {
+        let sensor = self.sensor.sensor();
+        self.queries.iter().enumerate()
+            .map(|(i, &(start, goal))| with_seed(derive_seed(self.seed, i as u64), || {
+                let mut belief = self.belief();
+                run_scenario_episode(&self.dynamics, &self.map, &mut belief, start, goal, &mut planner, sensor.as_ref(), self.max_steps)
+            }))
+            .collect()
+    }
+
+    /// Parses a scenario, see the module documentation for the format
+    pub fn parse(scenario: &str) -> io::Result<Self> {
+        let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid scenario line: {}", line));
+        let mut lines = scenario.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#'));
+        let first = lines.next().ok_or_else(|| invalid(""))?;
+        let map = first.strip_prefix("map ").and_then(BitPackedGrid::from_compact_string).ok_or_else(|| invalid(first))?;
+        let mut parsed = Scenario::new(map);
+        for line in lines {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let int = |i: usize| fields.get(i).and_then(|f| f.parse::<usize>().ok()).ok_or_else(|| invalid(line));
+            let cells = |from: usize| {
+                if fields.len() < from || !(fields.len() - from).is_multiple_of(2) {
+                    return Err(invalid(line));
+                }
+                (from..fields.len()).step_by(2).map(|i| Ok((int(i)?, int(i + 1)?))).collect::<io::Result<Vec<_>>>()
+            };
+            match (fields[0], fields.len()) {
+                ("sensor", 3) if fields[1] == "radius" => parsed.sensor = SensorSpec::Radius(int(2)?),
+                ("sensor", 3) if fields[1] == "raycast" => parsed.sensor = SensorSpec::Raycast(int(2)?),
+                ("seed", 2) => parsed.seed = fields[1].parse().map_err(|_| invalid(line))?,
+                ("max_steps", 2) => parsed.max_steps = int(1)?,
+                ("known", 2) => parsed.known = fields[1].parse().map_err(|_| invalid(line))?,
+                ("query", 5) => parsed.queries.push(((int(1)?, int(2)?), (int(3)?, int(4)?))),
+                ("event", 3..) => {
+                    let event = match fields[2] {
+                        "set" => Event::Set { cells: cells(4)?, free: int(3)? == 1 },
+                        "toggle" => Event::Toggle { cells: cells(3)? },
+                        "flood" if fields.len() == 7 => Event::Flood { flood: int(3)?, seed: (int(4)?, int(5)?), depth: int(6)? },
+                        _ => return Err(invalid(line)),
+                    };
+                    parsed.dynamics = parsed.dynamics.at(int(1)?, event);
+                }
+                _ => return Err(invalid(line)),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Writes the scenario, see the module documentation for the format
+    pub fn write(&self) -> String {
+        let cells = |cells: &[Cell]| cells.iter().map(|(x, y)| format!(" {} {}", x, y)).collect::<String>();
+        let mut scenario = format!("map {}\n", self.map.to_compact_string());
+        match self.sensor {
+            SensorSpec::Radius(radius) => writeln!(scenario, "sensor radius {}", radius).unwrap(),
+            SensorSpec::Raycast(radius) => writeln!(scenario, "sensor raycast {}", radius).unwrap(),
+        }
+        writeln!(scenario, "seed {}\nmax_steps {}\nknown {}", self.seed, self.max_steps, self.known).unwrap();
+        for ((sx, sy), (gx, gy)) in &self.queries {
+            writeln!(scenario, "query {} {} {} {}", sx, sy, gx, gy).unwrap();
+        }
+        for (time, event) in &self.dynamics.events {
+            match event {
+                Event::Set { cells: set, free } => writeln!(scenario, "event {} set {}{}", time, *free as u8, cells(set)),
+                Event::Toggle { cells: toggled } => writeln!(scenario, "event {} toggle{}", time, cells(toggled)),
+                Event::Flood { flood, seed, depth } => writeln!(scenario, "event {} flood {} {} {} {}", time, flood, seed.0, seed.1, depth),
+            }
+            .unwrap();
+        }
+        scenario
+    }
+
+    /// Reads a scenario from a file
+    pub fn load(filename: &str) -> io::Result<Self> {
+        Scenario::parse(&std::fs::read_to_string(filename)?)
+    }
+
+    /// Writes the scenario to a file
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        std::fs::write(filename, self.write())
+    }
+
+    /// Plots the ground truth at a tick with the endpoints of the queries
+    /// highlighted and an optional path
+    #[cfg(feature = "viz")]
+    pub fn plot(&self, output_file: &str, time: usize, path: Option<Vec<Cell>>) {
+        let endpoints = self.queries.iter().flat_map(|&(start, goal)| [(start, 1.0), (goal, 1.0)]).collect();
+        self.map_at(time).plot_cells(output_file, path, Some(endpoints));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::astar_planner;
+
+    #[test]
+    fn test_scenario_round_trip() {
+        let map = BitPackedGrid::new_from_string(".....\n.....\n.....\n".to_string());
+        let dynamics = Dynamics::new().wall(1, (2, 0), (2, 1)).door((3, 2), 2, 4).flood(3, (0, 2), 1, 1);
+        let scenario = Scenario::new(map).query((0, 0), (4, 0)).query((0, 1), (4, 2))
+            .with_dynamics(dynamics)
+            .with_sensor(SensorSpec::Raycast(3))
+            .with_seed(42);
+        let written = scenario.write();
+        let parsed = Scenario::parse(&written).unwrap();
+        assert_eq!(parsed.write(), written);
+        assert_eq!(parsed.dynamics, scenario.dynamics);
+        assert_eq!((parsed.sensor, parsed.seed, parsed.queries.len()), (SensorSpec::Raycast(3), 42, 2));
+        assert_eq!(parsed.map_at(4).print_cells(None), scenario.map_at(4).print_cells(None));
+        assert!(Scenario::parse("map 5x3:3H4\nevent 1 set 0 2\n").is_err());
+        assert!(Scenario::parse("sensor radius 1\n").is_err());
+
+        let filename = std::env::temp_dir().join("test_experiment_scenario.txt");
+        let filename = filename.to_str().unwrap();
+        scenario.save(filename).unwrap();
+        assert_eq!(Scenario::load(filename).unwrap().write(), written);
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_scenario_run() {
+        let map = BitPackedGrid::new_from_string(".....\n.....\n.....\n".to_string());
+        let scenario = Scenario::new(map).query((0, 0), (4, 0))
+            .with_dynamics(Dynamics::new().wall(1, (2, 0), (2, 1)));
+        let episodes = scenario.run(astar_planner);
+        assert_eq!(episodes.len(), 1);
+        assert!(episodes[0].success);
+        assert_eq!((episodes[0].cost, episodes[0].replans), (8, 1));
+        // An agent assuming free space learns of the wall the same way
+        let unknown = Scenario { known: false, ..scenario.clone() };
+        assert_eq!(unknown.belief().print_cells(None), ".....\n.....\n.....\n");
+        assert_eq!(Scenario::parse(&unknown.write()).unwrap().run(astar_planner), unknown.run(astar_planner));
+    }
+}
@@ -0,0 +1,268 @@
+//! # Simulator Harness
+//! The step loop shared by experiments on partially observed maps. An agent
+//! senses the ground truth of a sampling grid with a sensor model, plans on
+//! the belief with a navigation policy and replans when the plan is blocked or
+//! one of the replan triggers fires, until it reaches its goal, finds no plan
+//! or runs out of steps. Hooks observe every sensing, plan and move, so
+//! experiments can record metrics, draw frames or change the ground truth
+//! without copying the loop.
+
+use crate::domains::{Cell, samplegrid::SampleGrid};
+use crate::heuristics::distance::manhattan_distance;
+
+use super::policies::NavigationPolicy;
+use super::{Episode, ReplanStats, ReplanTrigger, SensorModel};
+
+/// An agent navigating a sampling grid
+/// ## Fields
+/// * `position` - The current cell of the agent
+/// * `goal` - The cell the agent is heading to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Agent {
+    pub position: Cell,
+    pub goal: Cell,
+}
+
+impl Agent {
+    pub fn new(position: Cell, goal: Cell) -> Self {
+        Agent { position, goal }
+    }
+}
+
+/// How a simulation ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The agent reached its goal
+    Reached,
+    /// The policy found no plan from the agent to its goal
+    NoPlan,
+    /// The step budget ran out before the agent reached its goal
+    StepBudget,
+}
+
+/// The report of a simulation
+/// ## Fields
+/// * `episode` - The cells the agent travelled through, its moves and replans
+/// * `distance` - The euclidean length of the travelled path
+/// * `steps` - The number of steps taken, the moves and the bumps into unsensed obstacles
+/// * `replans` - The replans by their trigger
+/// * `outcome` - How the simulation ended
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimReport {
+    pub episode: Episode,
+    pub distance: f64,
+    pub steps: usize,
+    pub replans: ReplanStats,
+    pub outcome: Outcome,
+}
+
+/// Callbacks at each stage of a simulation step, all doing nothing by default
+pub trait SimHooks {
+    /// Called after the cells observed at a step were measured
+    fn on_sense(&mut self, _step: usize, _belief: &mut SampleGrid, _observed: &[Cell]) {}
+
+    /// Called after a plan was made, with the trigger that caused it
+    fn on_plan(&mut self, _step: usize, _plan: &[Cell], _trigger: ReplanTrigger) {}
+
+    /// Called after the agent moved between two cells
+    fn on_move(&mut self, _step: usize, _from: Cell, _to: Cell) {}
+}
+
+impl SimHooks for () {}
+
+/// Runs agents on sampling grids with a navigation policy and sensor model
+/// ## Fields
+/// * `policy` - Plans paths on the belief
+/// * `sensor` - Observes the ground truth around the agent
+/// * `triggers` - The conditions under which the agent replans before its
+///   plan is blocked
+/// * `max_steps` - The number of steps after which a simulation fails
+/// * `measurement_covariance` - The covariance of the measurements of sensed cells
+pub struct Simulator<P: NavigationPolicy, S: SensorModel> {
+    pub policy: P,
+    pub sensor: S,
+    pub triggers: Vec<ReplanTrigger>,
+    pub max_steps: usize,
+    pub measurement_covariance: f32,
+}
+
+impl<P: NavigationPolicy, S: SensorModel> Simulator<P, S> {
+    /// Creates a simulator with perfect measurements replanning only when blocked
+    pub fn new(policy: P, sensor: S, max_steps: usize) -> Self {
+        Simulator { policy, sensor, triggers: vec![ReplanTrigger::Blocked], max_steps, measurement_covariance: 0.0 }
+    }
+
+    /// Replans whenever any of the triggers fire
+    pub fn with_triggers(mut self, triggers: Vec<ReplanTrigger>) -> Self {
+        self.triggers = triggers;
+        self
+    }
+
+    /// Measures sensed cells with a covariance
+    pub fn with_measurement_covariance(mut self, measurement_covariance: f32) -> Self {
+        self.measurement_covariance = measurement_covariance;
+        self
+    }
+
+    /// Runs an agent on a sampling grid without hooks
+    pub fn run(&mut self, belief: &mut SampleGrid, agent: &mut Agent) -> SimReport {
+        self.run_with_hooks(belief, agent, &mut ())
+    }
+
+    /// Runs an agent on a sampling grid, against its ground truth, until it
+    /// reaches its goal, finds no plan or runs out of steps. A plan is blocked
+    /// once it enters an observed cell more likely blocked than free, and
+    /// bumping into an unsensed obstacle measures it without moving.
+    /// ## Arguments
+    /// * `belief` - The sampling grid, updated as cells are sensed
+    /// * `agent` - The agent, left at the cell it stopped in
+    /// * `hooks` - The callbacks of every step
+    pub fn run_with_hooks(&mut self, belief: &mut SampleGrid, agent: &mut Agent, hooks: &mut impl SimHooks) -> SimReport {
+        let mut report = SimReport {
+            episode: Episode { path: vec![agent.position], cost: 0, replans: 0, success: false },
+            distance: 0.0,
+            steps: 0,
+            replans: ReplanStats::default(),
+            outcome: Outcome::StepBudget,
+        };
+        let blocked = |belief: &SampleGrid, n: Cell| belief.is_observed(n) && belief.belief(n) <= 0.5;
+        let mut plan: Vec<Cell> = Vec::new();
+        let mut planned = false;
+        let mut changed = Vec::new();
+        let mut since_plan = 0;
+        loop {
+            let observed = self.sensor.observe(agent.position, &belief.ground_truth);
+            let mut sensed_change = false;
+            for &cell in &observed {
+                let before = blocked(belief, cell);
+                belief.update_node(cell, self.measurement_covariance);
+                if blocked(belief, cell) != before {
+                    changed.push(cell);
+                    sensed_change = true;
+                }
+            }
+            hooks.on_sense(report.steps, belief, &observed);
+            if agent.position == agent.goal {
+                report.outcome = Outcome::Reached;
+                break;
+            }
+            if report.steps >= self.max_steps {
+                break;
+            }
+            let near_plan = |c: &Cell, distance: usize| plan.iter().any(|n| manhattan_distance(*n, *c) <= distance);
+            let fired = if plan.is_empty() || plan.iter().any(|n| blocked(belief, *n)) {
+                Some(ReplanTrigger::Blocked)
+            } else {
+                self.triggers.iter().copied().find(|trigger| match trigger {
+                    ReplanTrigger::Blocked => false,
+                    ReplanTrigger::BeliefChange { threshold, distance } => {
+                        changed.iter().filter(|c| near_plan(c, *distance)).count() >= *threshold
+                    }
+                    ReplanTrigger::Periodic(k) => since_plan >= *k,
+                })
+            };
+            match fired {
+                Some(trigger) => {
+                    if planned {
+                        report.episode.replans += 1;
+                        match trigger {
+                            ReplanTrigger::Blocked => report.replans.blocked += 1,
+                            ReplanTrigger::BeliefChange { .. } => report.replans.belief_changes += 1,
+                            ReplanTrigger::Periodic(_) => report.replans.periodic += 1,
+                        }
+                    }
+                    planned = true;
+                    changed.clear();
+                    since_plan = 0;
+                    let Some(path) = self.policy.plan(belief, agent.position, agent.goal) else {
+                        report.outcome = Outcome::NoPlan;
+                        break;
+                    };
+                    hooks.on_plan(report.steps, &path, trigger);
+                    plan = path.into_iter().skip(1).rev().collect();
+                }
+                None if sensed_change => report.replans.avoided += 1,
+                None => {}
+            }
+            let Some(next) = plan.pop() else {
+                report.outcome = Outcome::NoPlan;
+                break;
+            };
+            report.steps += 1;
+            since_plan += 1;
+            if !belief.ground_truth.get_bit_value(next) {
+                belief.update_node(next, self.measurement_covariance);
+                plan.clear();
+                continue;
+            }
+            let from = agent.position;
+            agent.position = next;
+            report.distance += (from.0 as f64 - next.0 as f64).hypot(from.1 as f64 - next.1 as f64);
+            report.episode.path.push(next);
+            report.episode.cost += 1;
+            hooks.on_move(report.steps, from, next);
+        }
+        report.episode.success = report.outcome == Outcome::Reached;
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::bitpackedgrid::BitPackedGrid;
+    use crate::sim::policies::FreespacePolicy;
+    use crate::sim::RaycastSensor;
+
+    /// Counts the calls of every hook
+    #[derive(Default)]
+    struct Counter {
+        senses: usize,
+        plans: usize,
+        moves: usize,
+    }
+
+    impl SimHooks for Counter {
+        fn on_sense(&mut self, _: usize, _: &mut SampleGrid, _: &[Cell]) {
+            self.senses += 1;
+        }
+
+        fn on_plan(&mut self, _: usize, _: &[Cell], _: ReplanTrigger) {
+            self.plans += 1;
+        }
+
+        fn on_move(&mut self, _: usize, _: Cell, _: Cell) {
+            self.moves += 1;
+        }
+    }
+
+    #[test]
+    fn test_simulator() {
+        let truth = BitPackedGrid::new_from_string(".......\n.@@@@@.\n.@...@.\n.@.@.@.\n...@...\n".to_string());
+        let mut belief = SampleGrid::new_from_grid(vec![vec![0.5; 5]; 7], truth.clone());
+        let mut simulator = Simulator::new(FreespacePolicy, RaycastSensor { radius: 2 }, 50);
+        let mut agent = Agent::new((2, 4), (4, 2));
+        let mut counter = Counter::default();
+        let report = simulator.run_with_hooks(&mut belief, &mut agent, &mut counter);
+        assert_eq!(report.outcome, Outcome::Reached);
+        assert!(report.episode.success && agent.position == (4, 2));
+        assert!(report.episode.replans > 0);
+        assert!(report.episode.path.iter().all(|n| truth.get_bit_value(*n)));
+        assert_eq!(report.distance, report.episode.cost as f64);
+        assert_eq!((counter.moves, counter.plans), (report.episode.cost, report.episode.replans + 1));
+        assert_eq!(counter.senses, report.steps + 1);
+    }
+
+    #[test]
+    fn test_simulator_failures() {
+        let truth = BitPackedGrid::new_from_string("...\n@@@\n...\n".to_string());
+        let mut belief = SampleGrid::new_from_grid(vec![vec![0.5; 3]; 3], truth);
+        let mut simulator = Simulator::new(FreespacePolicy, RaycastSensor { radius: 1 }, 1);
+        let report = simulator.run(&mut belief, &mut Agent::new((0, 0), (2, 0)));
+        assert_eq!((report.outcome, report.steps), (Outcome::StepBudget, 1));
+        let mut simulator = Simulator::new(FreespacePolicy, RaycastSensor { radius: 3 }, 20);
+        let report = simulator.run(&mut belief, &mut Agent::new((0, 0), (0, 2)));
+        assert_eq!(report.outcome, Outcome::NoPlan);
+        assert!(!report.episode.success);
+    }
+}
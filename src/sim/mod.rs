@@ -0,0 +1,323 @@
+//! # Simulation
+//! Simulation of agents navigating partially known maps. An agent plans on
+//! its belief of the map, follows the plan while sensing the ground truth and
+//! replans whenever the plan runs into a cell discovered to be blocked. These
+//! include:
+//! * Sensor models, which reveal the ground truth around the agent
+//! * Episodes, a single navigation task from a start to a goal
+//! * Replan triggers, policies deciding when an episode plans again
+//! * Replanning, an evaluator of the expected executed cost of a planner
+//! * GridEnv, a gym-style reinforcement learning environment over sampling grids
+//! * Dataset, imitation learning pairs of belief windows and optimal moves
+//! * Sensing, scheduling of limited sensing actions by path-relevant entropy
+//! * SharedBelief, a sampling grid fused from the delayed observations of a team,
+//!   and merging of the local maps of agents
+//! * Coverage, the fraction of free space observed over an exploration episode
+//! * DigestPlanner, plans cached by a digest of the belief around each query
+//! * Policies, freespace, risk-averse and information-seeking navigation on beliefs
+//! * Watchman routes, short routes from which every free cell is observed
+//! * Scenarios, scripted walls, doors and floods changing the map during an episode
+//! * Reveal, a benchmark mode where the map is revealed around the agent as it moves
+//! * Monitor, false-free and false-blocked cells of the belief over a long run
+//! * Stopping criteria ending episodes on reaching the goal, coverage, entropy or a step budget
+//! * Experiment scenarios, a map, queries, dynamics, sensor and seed saved as one file
+//! * Simulator, the canonical sense, plan and move loop with hooks and a step budget
+
+#![allow(dead_code)]
+pub mod replanning;
+pub mod env;
+pub mod dataset;
+pub mod sensing;
+pub mod shared;
+pub mod coverage;
+pub mod digest;
+pub mod policies;
+pub mod watchman;
+pub mod scenario;
+pub mod reveal;
+pub mod monitor;
+pub mod stopping;
+pub mod experiment;
+pub mod harness;
+
+use crate::domains::bitpackedgrid::BitPackedGrid;
+use crate::fov::fieldofvision::raycasting;
+use crate::heuristics::distance::manhattan_distance;
+use crate::search::astar::astar;
+
+/// A model of which cells an agent observes from its position
+pub trait SensorModel {
+    /// The cells observed from a position in the ground truth
+    fn observe(&self, position: (usize, usize), ground_truth: &BitPackedGrid) -> Vec<(usize, usize)>;
+}
+
+/// Observes every cell in a square window around the agent
+pub struct RadiusSensor {
+    pub radius: usize,
+}
+
+impl SensorModel for RadiusSensor {
+    fn observe(&self, (x, y): (usize, usize), ground_truth: &BitPackedGrid) -> Vec<(usize, usize)> {
+        let (x_max, y_max) = (x + self.radius, y + self.radius);
+        (x.saturating_sub(self.radius)..=x_max)
+            .flat_map(|i| (y.saturating_sub(self.radius)..=y_max).map(move |j| (i, j)))
+            .filter(|n| ground_truth.bounds_check(*n))
+            .collect()
+    }
+}
+
+/// Observes the cells in line of sight of the agent using raycasting
+pub struct RaycastSensor {
+    pub radius: usize,
+}
+
+impl SensorModel for RaycastSensor {
+    fn observe(&self, position: (usize, usize), ground_truth: &BitPackedGrid) -> Vec<(usize, usize)> {
+        let mut observed = raycasting(position, self.radius, |x, y| {
+            ground_truth.bounds_check((x, y)) && ground_truth.get_bit_value((x, y))
+        });
+        observed.push(position);
+        observed.retain(|n| ground_truth.bounds_check(*n));
+        observed
+    }
+}
+
+/// The outcome of an episode
+/// ## Fields
+/// * `path` - The cells the agent travelled through
+/// * `cost` - The number of moves the agent made
+/// * `replans` - The number of times the agent planned after its first plan
+/// * `success` - Whether the agent reached the goal
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Episode {
+    pub path: Vec<(usize, usize)>,
+    pub cost: usize,
+    pub replans: usize,
+    pub success: bool,
+}
+
+/// A condition under which an agent plans again before its plan is used up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplanTrigger {
+    /// Replans when the plan runs into a cell believed to be blocked, which
+    /// always applies as a blocked plan cannot be followed
+    Blocked,
+    /// Replans when at least `threshold` cells within `distance` moves of the
+    /// remaining plan changed since the last plan, such as a shortcut opening
+    BeliefChange { threshold: usize, distance: usize },
+    /// Replans every `k` steps
+    Periodic(usize),
+}
+
+/// The replans of an episode by their trigger
+/// ## Fields
+/// * `blocked` - Replans because the plan was blocked
+/// * `belief_changes` - Replans because the belief along the plan changed
+/// * `periodic` - Replans because of the period
+/// * `avoided` - Steps where the belief changed without a replan, each a replan
+///   saved over replanning on every change
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplanStats {
+    pub blocked: usize,
+    pub belief_changes: usize,
+    pub periodic: usize,
+    pub avoided: usize,
+}
+
+/// Runs a navigation episode on a partially known map
+/// ## Arguments
+/// * `ground_truth` - The true map
+/// * `belief` - The map believed by the agent, updated as cells are observed
+/// * `start` - The start cell
+/// * `goal` - The goal cell
+/// * `planner` - Plans a path on the belief from the agent's cell to the goal
+/// * `sensor` - The sensor model of the agent
+/// * `max_steps` - The number of moves after which the episode fails
+pub fn run_episode<P, S>(
+    ground_truth: &BitPackedGrid,
+    belief: &mut BitPackedGrid,
+    start: (usize, usize),
+    goal: (usize, usize),
+    planner: P,
+    sensor: &S,
+    max_steps: usize,
+) -> Episode
+where
+    P: FnMut(&BitPackedGrid, (usize, usize), (usize, usize)) -> Option<Vec<(usize, usize)>>,
+    S: SensorModel + ?Sized,
+{
+    run_episode_with_triggers(ground_truth, belief, start, goal, planner, sensor, max_steps, &[ReplanTrigger::Blocked]).0
+}
+
+/// Runs a navigation episode that replans whenever any of the triggers fire,
+/// see `run_episode` for the other arguments
+/// ## Arguments
+/// * `triggers` - The conditions under which the agent replans
+/// ## Returns
+/// The episode and the replans by their trigger
+#[allow(clippy::too_many_arguments)]
+pub fn run_episode_with_triggers<P, S>(
+    ground_truth: &BitPackedGrid,
+    belief: &mut BitPackedGrid,
+    start: (usize, usize),
+    goal: (usize, usize),
+    planner: P,
+    sensor: &S,
+    max_steps: usize,
+    triggers: &[ReplanTrigger],
+) -> (Episode, ReplanStats)
+where
+    P: FnMut(&BitPackedGrid, (usize, usize), (usize, usize)) -> Option<Vec<(usize, usize)>>,
+    S: SensorModel + ?Sized,
+{
+    let mut ground_truth = ground_truth.clone();
+    simulate(&mut ground_truth, belief, start, goal, planner, sensor, max_steps, triggers, |_, _| {})
+}
+
+/// Runs an episode on a ground truth that changes as it runs, calling
+/// `on_tick` with the number of moves made before sensing at every step
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn simulate<P, S, T>(
+    ground_truth: &mut BitPackedGrid,
+    belief: &mut BitPackedGrid,
+    start: (usize, usize),
+    goal: (usize, usize),
+    mut planner: P,
+    sensor: &S,
+    max_steps: usize,
+    triggers: &[ReplanTrigger],
+    mut on_tick: T,
+) -> (Episode, ReplanStats)
+where
+    P: FnMut(&BitPackedGrid, (usize, usize), (usize, usize)) -> Option<Vec<(usize, usize)>>,
+    S: SensorModel + ?Sized,
+    T: FnMut(usize, &mut BitPackedGrid),
+{
+    let mut episode = Episode { path: vec![start], cost: 0, replans: 0, success: false };
+    let mut stats = ReplanStats::default();
+    let mut plan: Vec<(usize, usize)> = Vec::new();
+    let mut current = start;
+    let mut planned = false;
+    let mut changed = Vec::new();
+    let mut since_plan = 0;
+    while episode.cost < max_steps {
+        on_tick(episode.cost, ground_truth);
+        let mut sensed_change = false;
+        for cell in sensor.observe(current, ground_truth) {
+            let value = ground_truth.get_bit_value(cell);
+            if belief.get_bit_value(cell) != value {
+                belief.set_bit_value(cell, value);
+                changed.push(cell);
+                sensed_change = true;
+            }
+        }
+        if current == goal {
+            episode.success = true;
+            break;
+        }
+        let near_plan = |c: &(usize, usize), distance: usize| {
+            plan.iter().any(|n| manhattan_distance(*n, *c) <= distance)
+        };
+        let fired = if plan.is_empty() || plan.iter().any(|n| !belief.get_bit_value(*n)) {
+            Some(ReplanTrigger::Blocked)
+        } else {
+            triggers.iter().copied().find(|trigger| match trigger {
+                ReplanTrigger::Blocked => false,
+                ReplanTrigger::BeliefChange { threshold, distance } => {
+                    changed.iter().filter(|c| near_plan(c, *distance)).count() >= *threshold
+                }
+                ReplanTrigger::Periodic(k) => since_plan >= *k,
+            })
+        };
+        match fired {
+            Some(trigger) => {
+                if planned {
+                    episode.replans += 1;
+                    match trigger {
+                        ReplanTrigger::Blocked => stats.blocked += 1,
+                        ReplanTrigger::BeliefChange { .. } => stats.belief_changes += 1,
+                        ReplanTrigger::Periodic(_) => stats.periodic += 1,
+                    }
+                }
+                planned = true;
+                changed.clear();
+                since_plan = 0;
+                match planner(belief, current, goal) {
+                    Some(path) => plan = path.into_iter().skip(1).rev().collect(),
+                    None => break,
+                }
+            }
+            None if sensed_change => stats.avoided += 1,
+            None => {}
+        }
+        let Some(next) = plan.pop() else { break };
+        current = next;
+        episode.path.push(current);
+        episode.cost += 1;
+        since_plan += 1;
+    }
+    episode.success |= current == goal;
+    (episode, stats)
+}
+
+/// A planner using A-Star with the manhattan distance on 4-connected grids
+pub fn astar_planner(grid: &BitPackedGrid, start: (usize, usize), goal: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+    astar(|n| grid.adjacent1(*n), start, |n| *n == goal, |n| manhattan_distance(*n, goal))
+        .map(|(path, _)| path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_episode() {
+        let ground_truth = BitPackedGrid::new_from_string(".....\n.@@@.\n.@...\n.@.@.\n...@.\n".to_string());
+        let mut belief = BitPackedGrid::new_from_string(".....\n.....\n.....\n.....\n.....\n".to_string());
+        let episode = run_episode(&ground_truth, &mut belief, (2, 4), (2, 0), astar_planner, &RadiusSensor { radius: 1 }, 100);
+        assert!(episode.success);
+        assert!(episode.replans > 0);
+        assert_eq!(episode.path.last(), Some(&(2, 0)));
+        assert_eq!(episode.cost, episode.path.len() - 1);
+        assert!(episode.path.iter().all(|n| ground_truth.get_bit_value(*n)));
+        let mut belief = BitPackedGrid::new_from_string(".....\n.....\n.....\n.....\n.....\n".to_string());
+        let episode = run_episode(&ground_truth, &mut belief, (2, 4), (2, 0), astar_planner, &RaycastSensor { radius: 1 }, 3);
+        assert!(!episode.success);
+        assert_eq!(episode.cost, 3);
+    }
+
+    #[test]
+    fn test_replan_triggers() {
+        // The belief misses the gap in the wall, which is seen on the way round
+        let ground_truth = BitPackedGrid::new_from_string(".......\n.@.@@@@\n.......\n".to_string());
+        let belief = BitPackedGrid::new_from_string(".......\n.@@@@@@\n.......\n".to_string());
+        let sensor = RadiusSensor { radius: 1 };
+        let run = |triggers: &[ReplanTrigger]| {
+            let mut belief = belief.clone();
+            run_episode_with_triggers(&ground_truth, &mut belief, (5, 0), (5, 2), astar_planner, &sensor, 50, triggers)
+        };
+        let (blocked, stats) = run(&[ReplanTrigger::Blocked]);
+        assert!(blocked.success);
+        assert_eq!((blocked.cost, blocked.replans), (12, 0));
+        assert_eq!(stats.avoided, 1);
+
+        let (changed, stats) = run(&[ReplanTrigger::BeliefChange { threshold: 1, distance: 1 }]);
+        assert_eq!((changed.cost, changed.replans), (8, 1));
+        assert_eq!(stats, ReplanStats { belief_changes: 1, ..Default::default() });
+
+        let (periodic, stats) = run(&[ReplanTrigger::Periodic(3)]);
+        assert!(periodic.success);
+        assert_eq!(stats.periodic, periodic.replans);
+        assert!(periodic.replans > 0);
+    }
+
+    #[test]
+    fn test_run_episode_unreachable() {
+        let ground_truth = BitPackedGrid::new_from_string("...\n@@@\n...\n".to_string());
+        let mut belief = BitPackedGrid::new_from_string("...\n...\n...\n".to_string());
+        let episode = run_episode(&ground_truth, &mut belief, (0, 0), (0, 2), astar_planner, &RadiusSensor { radius: 2 }, 10);
+        assert!(!episode.success);
+        assert_eq!(episode.path, vec![(0, 0)]);
+        assert_eq!(episode.replans, 0);
+    }
+}
@@ -0,0 +1,164 @@
+//! # Map Quality Monitoring
+//! Tracking of how far the believed map of an agent diverges from the ground
+//! truth over a long run. A cell is false-free when it is believed free but is
+//! blocked, which can send a plan into a wall, and false-blocked when it is
+//! believed blocked but is free, which hides shortcuts. The counts are recorded
+//! as time series that can be exported as CSV or plotted, for example to check
+//! that a sensor model keeps the belief close to the ground truth.
+
+use std::io::{self, Write};
+
+#[cfg(feature = "viz")]
+use plotters::prelude::*;
+
+use crate::domains::{bitpackedgrid::BitPackedGrid, samplegrid::SampleGrid};
+
+/// The divergence of a believed map from the ground truth at a step
+/// ## Fields
+/// * `step` - The step the divergence was measured at
+/// * `false_free` - The number of cells believed free that are blocked
+/// * `false_blocked` - The number of cells believed blocked that are free
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MapQuality {
+    pub step: usize,
+    pub false_free: usize,
+    pub false_blocked: usize,
+}
+
+impl MapQuality {
+    /// Measures the divergence of a belief from a ground truth of the same size
+    pub fn measure(step: usize, belief: &BitPackedGrid, ground_truth: &BitPackedGrid) -> Self {
+        let mut quality = MapQuality { step, ..Default::default() };
+        for x in 0..ground_truth.original_width {
+            for y in 0..ground_truth.original_height {
+                match (belief.get_bit_value((x, y)), ground_truth.get_bit_value((x, y))) {
+                    (true, false) => quality.false_free += 1,
+                    (false, true) => quality.false_blocked += 1,
+                    _ => {}
+                }
+            }
+        }
+        quality
+    }
+
+    /// The number of cells whose belief is wrong
+    pub fn errors(&self) -> usize {
+        self.false_free + self.false_blocked
+    }
+}
+
+/// The divergence of a believed map recorded over a run
+/// ## Fields
+/// * `samples` - The measurements in the order recorded
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MapQualityMonitor {
+    pub samples: Vec<MapQuality>,
+}
+
+impl MapQualityMonitor {
+    /// Starts an empty monitor
+    pub fn new() -> Self {
+        MapQualityMonitor { samples: Vec::new() }
+    }
+
+    /// Records the divergence of a belief from a ground truth at a step
+    pub fn record(&mut self, step: usize, belief: &BitPackedGrid, ground_truth: &BitPackedGrid) -> MapQuality {
+        let quality = MapQuality::measure(step, belief, ground_truth);
+        self.samples.push(quality);
+        quality
+    }
+
+    /// Records the divergence of the gridmap of a sampling grid from its ground truth
+    pub fn record_grid(&mut self, step: usize, grid: &SampleGrid) -> MapQuality {
+        self.record(step, grid.gridmap(), &grid.ground_truth)
+    }
+
+    /// The number of recorded samples
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Checks if no samples have been recorded
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// The (step, count) series of false-free cells
+    pub fn false_free(&self) -> Vec<(usize, usize)> {
+        self.samples.iter().map(|q| (q.step, q.false_free)).collect()
+    }
+
+    /// The (step, count) series of false-blocked cells
+    pub fn false_blocked(&self) -> Vec<(usize, usize)> {
+        self.samples.iter().map(|q| (q.step, q.false_blocked)).collect()
+    }
+
+    /// The sample with the most wrong cells
+    pub fn worst(&self) -> Option<MapQuality> {
+        self.samples.iter().max_by_key(|q| q.errors()).copied()
+    }
+
+    /// Writes the series as CSV with a `step,false_free,false_blocked` header
+    pub fn write_csv(&self, writer: &mut impl Write) -> io::Result<()> {
+        writeln!(writer, "step,false_free,false_blocked")?;
+        for q in &self.samples {
+            writeln!(writer, "{},{},{}", q.step, q.false_free, q.false_blocked)?;
+        }
+        Ok(())
+    }
+
+    /// Saves the series as CSV
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        let mut file = io::BufWriter::new(std::fs::File::create(filename)?);
+        self.write_csv(&mut file)
+    }
+
+    /// Plots the false-free and false-blocked counts as lines over the steps
+    #[cfg(feature = "viz")]
+    pub fn plot(&self, output_file: &str) {
+        let max_step = self.samples.iter().map(|q| q.step).max().unwrap_or(0).max(1);
+        let max_count = self.samples.iter().map(|q| q.false_free.max(q.false_blocked)).max().unwrap_or(0).max(1);
+        let root = BitMapBackend::new(output_file, (640, 480)).into_drawing_area();
+        root.fill(&WHITE).unwrap();
+        let mut chart = ChartBuilder::on(&root)
+            .margin(10)
+            .x_label_area_size(30)
+            .y_label_area_size(40)
+            .build_cartesian_2d(0..max_step, 0..max_count)
+            .unwrap();
+        chart.configure_mesh().x_desc("Step").y_desc("Cells").draw().unwrap();
+        for (i, (label, series)) in [("false free", self.false_free()), ("false blocked", self.false_blocked())].into_iter().enumerate() {
+            let colour = Palette99::pick(i).to_rgba();
+            chart.draw_series(LineSeries::new(series, colour))
+                .unwrap()
+                .label(label)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], colour));
+        }
+        chart.configure_series_labels().background_style(WHITE).border_style(BLACK).draw().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_quality_monitor() {
+        let mut grid = SampleGrid::new_from_string("..@\n.@.\n".to_string());
+        let mut monitor = MapQualityMonitor::new();
+        grid.fill_gridmap();
+        assert_eq!(monitor.record_grid(0, &grid), MapQuality { step: 0, false_free: 2, false_blocked: 0 });
+        grid.init_gridmap();
+        let mut belief = grid.gridmap().clone();
+        belief.set_bit_value((0, 0), false);
+        assert_eq!(monitor.record(4, &belief, &grid.ground_truth).errors(), 1);
+        assert_eq!(monitor.len(), 2);
+        assert_eq!(monitor.false_free(), vec![(0, 2), (4, 0)]);
+        assert_eq!(monitor.false_blocked(), vec![(0, 0), (4, 1)]);
+        assert_eq!(monitor.worst().unwrap().step, 0);
+
+        let mut csv = Vec::new();
+        monitor.write_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "step,false_free,false_blocked\n0,2,0\n4,0,1\n");
+    }
+}
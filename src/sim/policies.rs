@@ -0,0 +1,220 @@
+//! # Navigation Policies
+//! Policies trading off exploration and exploitation when navigating on a
+//! sampling grid whose belief is refined by sensing. The freespace policy
+//! optimistically assumes unobserved cells are free, the risk-averse policy
+//! only enters cells whose belief clears a threshold and the information
+//! seeking policy prefers uncertain cells, taking detours that observe more of
+//! the map. All policies run on the same episodes, so they can be compared on
+//! executed cost and success.
+
+use crate::domains::{Cell, samplegrid::SampleGrid};
+use crate::heuristics::distance::manhattan_distance;
+use crate::search::astar::astar;
+
+use super::sensing::entropy;
+use super::stopping::{first_met, StoppingCriterion};
+use super::{Episode, SensorModel};
+
+/// The cost of a move in the policies with weighted moves
+const STEP: usize = 100;
+
+/// A strategy for planning on an uncertain belief
+pub trait NavigationPolicy {
+    /// Plans a path on the belief from the agent's cell to the goal
+    fn plan(&mut self, belief: &SampleGrid, start: Cell, goal: Cell) -> Option<Vec<Cell>>;
+}
+
+/// Plans a 4-connected path through the passable cells of a belief
+fn plan_passable(
+    belief: &SampleGrid,
+    start: Cell,
+    goal: Cell,
+    passable: impl Fn(Cell) -> bool,
+    cost: impl Fn(Cell) -> usize,
+    step: usize,
+) -> Option<Vec<Cell>> {
+    astar(
+        |n| belief.adjacent(*n, false).filter(|n| passable(*n)).map(|n| (n, cost(n))).collect::<Vec<_>>(),
+        start,
+        |n| *n == goal,
+        |n| manhattan_distance(*n, goal) * step,
+    )
+    .map(|(path, _)| path)
+}
+
+/// Assumes every unobserved cell is free and observed cells are free when
+/// they are more likely free than not
+pub struct FreespacePolicy;
+
+impl NavigationPolicy for FreespacePolicy {
+    fn plan(&mut self, belief: &SampleGrid, start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+        plan_passable(belief, start, goal, |n| !belief.is_observed(n) || belief.belief(n) > 0.5, |_| 1, 1)
+    }
+}
+
+/// Only enters cells whose belief of being free is at least a threshold,
+/// whether or not they have been observed
+pub struct RiskAversePolicy {
+    pub threshold: f32,
+}
+
+impl NavigationPolicy for RiskAversePolicy {
+    fn plan(&mut self, belief: &SampleGrid, start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+        plan_passable(belief, start, goal, |n| belief.belief(n) >= self.threshold, |_| 1, 1)
+    }
+}
+
+/// Discounts moves into uncertain cells by their entropy, so paths detour
+/// through unobserved space when the detour is short enough
+/// ## Fields
+/// * `bonus` - The discount of a move into a cell of one bit of entropy, out of
+///   a move cost of 100
+pub struct InformationSeekingPolicy {
+    pub bonus: usize,
+}
+
+impl NavigationPolicy for InformationSeekingPolicy {
+    fn plan(&mut self, belief: &SampleGrid, start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+        assert!(self.bonus < STEP, "The bonus must be less than the cost of a move");
+        let cost = |n| STEP - (self.bonus as f32 * entropy(belief.belief(n))).round() as usize;
+        plan_passable(belief, start, goal, |n| belief.belief(n) > 0.0, cost, STEP - self.bonus)
+    }
+}
+
+/// Runs a navigation episode on a sampling grid, observing the ground truth
+/// of the cells sensed at every step perfectly
+/// ## Arguments
+/// * `belief` - The sampling grid, whose ground truth is the true map
+/// * `start` - The start cell
+/// * `goal` - The goal cell
+/// * `policy` - The navigation policy
+/// * `sensor` - The sensor model of the agent
+/// * `max_steps` - The number of moves after which the episode fails
+pub fn run_policy_episode<S: SensorModel + ?Sized>(
+    belief: &mut SampleGrid,
+    start: Cell,
+    goal: Cell,
+    policy: &mut dyn NavigationPolicy,
+    sensor: &S,
+    max_steps: usize,
+) -> Episode {
+    let criteria = [StoppingCriterion::GoalReached, StoppingCriterion::StepBudget(max_steps)];
+    run_policy_episode_until(belief, start, goal, policy, sensor, &criteria).0
+}
+
+/// Runs a navigation episode that stops when the first of the criteria is met,
+/// see `run_policy_episode` for the other arguments. An episode also stops
+/// when the policy finds no plan or the agent has nowhere left to move, such
+/// as at the goal without a goal criterion.
+/// ## Arguments
+/// * `criteria` - The stopping criteria checked in order after every sensing
+/// ## Returns
+/// The episode and the criterion that stopped it, if any
+pub fn run_policy_episode_until<S: SensorModel + ?Sized>(
+    belief: &mut SampleGrid,
+    start: Cell,
+    goal: Cell,
+    policy: &mut dyn NavigationPolicy,
+    sensor: &S,
+    criteria: &[StoppingCriterion],
+) -> (Episode, Option<StoppingCriterion>) {
+    let mut episode = Episode { path: vec![start], cost: 0, replans: 0, success: false };
+    let mut plan: Vec<Cell> = Vec::new();
+    let mut current = start;
+    let mut planned = false;
+    let stopped = loop {
+        for cell in sensor.observe(current, &belief.ground_truth) {
+            belief.update_node(cell, 0.0);
+        }
+        if let Some(criterion) = first_met(criteria, belief, current, goal, episode.cost) {
+            break Some(criterion);
+        }
+        if plan.is_empty() || plan.iter().any(|n| belief.belief(*n) == 0.0) {
+            if planned {
+                episode.replans += 1;
+            }
+            planned = true;
+            match policy.plan(belief, current, goal) {
+                Some(path) => plan = path.into_iter().skip(1).rev().collect(),
+                None => break None,
+            }
+        }
+        let Some(next) = plan.pop() else { break None };
+        // Bumping into an unsensed obstacle observes it without moving
+        if !belief.ground_truth.get_bit_value(next) {
+            belief.update_node(next, 0.0);
+            plan.clear();
+            continue;
+        }
+        current = next;
+        episode.path.push(current);
+        episode.cost += 1;
+    };
+    episode.success = current == goal;
+    (episode, stopped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::bitpackedgrid::BitPackedGrid;
+    use crate::sim::RadiusSensor;
+
+    /// A 5 by 3 open map with a prior belief of each cell
+    fn grid(prior: impl Fn(Cell) -> f32) -> SampleGrid {
+        let truth = BitPackedGrid::new_from_string(".....\n.....\n.....\n".to_string());
+        SampleGrid::new_from_grid((0..5).map(|x| (0..3).map(|y| prior((x, y))).collect()).collect(), truth)
+    }
+
+    #[test]
+    fn test_navigation_policies() {
+        let sensor = RadiusSensor { radius: 1 };
+        let run = |prior: fn(Cell) -> f32, policy: &mut dyn NavigationPolicy| {
+            run_policy_episode(&mut grid(prior), (0, 1), (4, 1), policy, &sensor, 20)
+        };
+        // The direct route is believed unlikely to be free, though it is
+        let doubtful = |(x, y): Cell| if y == 1 && (1..4).contains(&x) { 0.3 } else { 1.0 };
+        assert_eq!(run(doubtful, &mut FreespacePolicy).cost, 4);
+        let averse = run(doubtful, &mut RiskAversePolicy { threshold: 0.5 });
+        assert!(averse.success);
+        assert_eq!(averse.cost, 6);
+
+        // The row above is unobserved, worth a detour to an information seeker
+        let unknown = |(_, y): Cell| if y == 0 { 0.5 } else { 1.0 };
+        assert_eq!(run(unknown, &mut FreespacePolicy).cost, 4);
+        let seeking = run(unknown, &mut InformationSeekingPolicy { bonus: 90 });
+        assert!(seeking.success);
+        assert_eq!(seeking.cost, 6);
+        assert!(seeking.path.contains(&(2, 0)));
+    }
+
+    #[test]
+    fn test_policy_episode_bumps() {
+        let truth = BitPackedGrid::new_from_string("...\n.@.\n...\n".to_string());
+        let mut belief = SampleGrid::new_from_grid(vec![vec![1.0; 3]; 3], truth);
+        let episode = run_policy_episode(&mut belief, (1, 0), (1, 2), &mut FreespacePolicy, &RadiusSensor { radius: 0 }, 10);
+        assert!(episode.success);
+        assert_eq!(episode.cost, 4);
+        assert_eq!(belief.belief((1, 1)), 0.0);
+    }
+
+    #[test]
+    fn test_policy_episode_until() {
+        let sensor = RadiusSensor { radius: 1 };
+        let unknown = |(_, y): Cell| if y == 1 { 1.0 } else { 0.5 };
+        let coverage = StoppingCriterion::Coverage { threshold: 0.5, fraction: 0.6 };
+        let (episode, stopped) = run_policy_episode_until(
+            &mut grid(unknown), (0, 1), (4, 1), &mut FreespacePolicy, &sensor, &[coverage, StoppingCriterion::GoalReached],
+        );
+        assert_eq!(stopped, Some(coverage));
+        assert!(!episode.success && episode.cost > 0);
+        let (episode, stopped) = run_policy_episode_until(
+            &mut grid(unknown), (0, 1), (4, 1), &mut FreespacePolicy, &sensor, &[StoppingCriterion::StepBudget(2)],
+        );
+        assert_eq!((episode.cost, stopped), (2, Some(StoppingCriterion::StepBudget(2))));
+        // Without a goal criterion the agent idles at the goal
+        let (episode, stopped) = run_policy_episode_until(&mut grid(unknown), (0, 1), (4, 1), &mut FreespacePolicy, &sensor, &[]);
+        assert!(episode.success);
+        assert_eq!(stopped, None);
+    }
+}
@@ -0,0 +1,179 @@
+//! # Replanning Cost
+//! Estimates the expected executed cost of a planner and sensor model on a
+//! partially known map, including the detours caused by discovering blocked
+//! cells. Queries are grouped into buckets by their optimal cost on the ground
+//! truth, in the manner of the MovingAI scenario buckets, so planners can be
+//! compared on short and long queries separately.
+
+use std::collections::BTreeMap;
+
+use crate::domains::bitpackedgrid::BitPackedGrid;
+use crate::search::uninformed::bfs;
+
+use crate::util::preprocessing::par_build_seeded;
+
+use super::{run_episode, Episode, SensorModel};
+
+/// A (start, goal) query
+pub type Query = ((usize, usize), (usize, usize));
+
+/// The replanning statistics of a bucket of queries on a map
+/// ## Fields
+/// * `map` - The name of the map
+/// * `bucket` - The bucket, queries with optimal cost in `[bucket * size, (bucket + 1) * size)`
+/// * `episodes` - The number of queries in the bucket
+/// * `successes` - The number of queries where the agent reached the goal
+/// * `mean_executed` - The mean executed cost of successful queries
+/// * `mean_optimal` - The mean optimal cost of successful queries
+/// * `mean_detour` - The mean executed cost in excess of the optimal cost of successful queries
+/// * `mean_replans` - The mean number of replans of all queries
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplanningRow {
+    pub map: String,
+    pub bucket: usize,
+    pub episodes: usize,
+    pub successes: usize,
+    pub mean_executed: f32,
+    pub mean_optimal: f32,
+    pub mean_detour: f32,
+    pub mean_replans: f32,
+}
+
+/// Evaluates the executed cost of a planner on partially known maps
+/// ## Fields
+/// * `bucket_size` - The width of each bucket of optimal costs
+/// * `max_steps` - The step budget of each episode
+pub struct ReplanningEvaluator {
+    pub bucket_size: usize,
+    pub max_steps: usize,
+}
+
+impl ReplanningEvaluator {
+    pub fn new(bucket_size: usize, max_steps: usize) -> Self {
+        assert!(bucket_size > 0, "Bucket size must be positive");
+        ReplanningEvaluator { bucket_size, max_steps }
+    }
+
+    /// Evaluates a planner on the queries of a map. Every query starts from the
+    /// same initial belief, and queries with no path on the ground truth are skipped.
+    /// ## Arguments
+    /// * `map` - The name of the map, used in the report
+    /// * `ground_truth` - The true map
+    /// * `belief` - The initial belief of the agent
+    /// * `queries` - The (start, goal) pairs to evaluate
+    /// * `planner` - Plans a path on the belief from the agent's cell to the goal
+    /// * `sensor` - The sensor model of the agent
+    /// ## Returns
+    /// A row for each non-empty bucket in increasing order
+    pub fn evaluate<P, S>(
+        &self,
+        map: &str,
+        ground_truth: &BitPackedGrid,
+        belief: &BitPackedGrid,
+        queries: &[Query],
+        mut planner: P,
+        sensor: &S,
+    ) -> Vec<ReplanningRow>
+    where
+        P: FnMut(&BitPackedGrid, (usize, usize), (usize, usize)) -> Option<Vec<(usize, usize)>>,
+        S: SensorModel + ?Sized,
+    {
+        let mut outcomes = Vec::new();
+        for &(start, goal) in queries {
+            let Some((_, optimal)) = bfs(|n| ground_truth.adjacent(*n, false), start, |n| *n == goal) else {
+                continue;
+            };
+            let mut belief = belief.clone();
+            let episode = run_episode(ground_truth, &mut belief, start, goal, &mut planner, sensor, self.max_steps);
+            outcomes.push((optimal, episode));
+        }
+        self.rows(map, outcomes)
+    }
+
+    /// Evaluates a planner on the queries of a map across threads, see
+    /// `evaluate`. Each query runs with the generator seeded from the master
+    /// seed and the index of the query, so randomised planners and sensors give
+    /// the same rows whatever the number of threads.
+    /// ## Arguments
+    /// * `seed` - The master seed of the evaluation, see `evaluate` for the others
+    #[allow(clippy::too_many_arguments)]
+    pub fn evaluate_seeded<P, S>(
+        &self,
+        map: &str,
+        ground_truth: &BitPackedGrid,
+        belief: &BitPackedGrid,
+        queries: &[Query],
+        planner: P,
+        sensor: &S,
+        seed: u64,
+    ) -> Vec<ReplanningRow>
+    where
+        P: Fn(&BitPackedGrid, (usize, usize), (usize, usize)) -> Option<Vec<(usize, usize)>> + Sync + Send,
+        S: SensorModel + Sync + ?Sized,
+    {
+        let (outcomes, _) = par_build_seeded(queries, seed, |&(start, goal)| {
+            let (_, optimal) = bfs(|n| ground_truth.adjacent(*n, false), start, |n| *n == goal)?;
+            let mut belief = belief.clone();
+            let episode = run_episode(ground_truth, &mut belief, start, goal, &planner, sensor, self.max_steps);
+            Some((optimal, episode))
+        });
+        self.rows(map, outcomes.into_iter().flatten())
+    }
+
+    /// Aggregates the (optimal cost, episode) of each query into buckets
+    fn rows(&self, map: &str, outcomes: impl IntoIterator<Item = (usize, Episode)>) -> Vec<ReplanningRow> {
+        // Per bucket: episodes, successes, executed, optimal and replans
+        let mut buckets: BTreeMap<usize, (usize, usize, usize, usize, usize)> = BTreeMap::new();
+        for (optimal, episode) in outcomes {
+            let bucket = buckets.entry(optimal / self.bucket_size).or_default();
+            bucket.0 += 1;
+            bucket.4 += episode.replans;
+            if episode.success {
+                bucket.1 += 1;
+                bucket.2 += episode.cost;
+                bucket.3 += optimal;
+            }
+        }
+        buckets.into_iter()
+            .map(|(bucket, (episodes, successes, executed, optimal, replans))| {
+                let mean = |total: usize, count: usize| total as f32 / count.max(1) as f32;
+                ReplanningRow {
+                    map: map.to_string(),
+                    bucket,
+                    episodes,
+                    successes,
+                    mean_executed: mean(executed, successes),
+                    mean_optimal: mean(optimal, successes),
+                    mean_detour: mean(executed - optimal, successes),
+                    mean_replans: mean(replans, episodes),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{astar_planner, RadiusSensor};
+
+    #[test]
+    fn test_replanning_evaluator() {
+        let ground_truth = BitPackedGrid::new_from_string(".....\n.@@@.\n.@...\n.@.@.\n...@.\n".to_string());
+        let belief = BitPackedGrid::new_from_string(".....\n.....\n.....\n.....\n.....\n".to_string());
+        let queries = [((0, 0), (1, 0)), ((2, 4), (2, 0)), ((0, 0), (1, 1))];
+        let evaluator = ReplanningEvaluator::new(4, 100);
+        let rows = evaluator.evaluate("test", &ground_truth, &belief, &queries, astar_planner, &RadiusSensor { radius: 1 });
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].bucket, 0);
+        assert_eq!(rows[0].episodes, 1);
+        assert_eq!(rows[0].mean_detour, 0.0);
+        assert_eq!(rows[1].map, "test");
+        assert_eq!(rows[1].successes, 1);
+        assert_eq!(rows[1].mean_optimal, 8.0);
+        assert!(rows[1].mean_executed >= rows[1].mean_optimal);
+        assert!(rows[1].mean_replans > 0.0);
+        let seeded = evaluator.evaluate_seeded("test", &ground_truth, &belief, &queries, astar_planner, &RadiusSensor { radius: 1 }, 3);
+        assert_eq!(seeded, rows);
+    }
+}
@@ -0,0 +1,133 @@
+//! # Progressive Reveal
+//! A benchmark mode where the planner starts out seeing only the area around
+//! the start and the map is revealed as the agent moves. The gridmap of a
+//! sampling grid starts fully free, the freespace assumption, and every move
+//! copies the belief of the area within a radius of the agent into it with
+//! `init_gridmap_radius`. The agent replans whenever the rest of its plan runs
+//! into a revealed obstacle, and the harness measures the total executed cost
+//! against the optimal cost with the whole map revealed.
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid, samplegrid::SampleGrid};
+use crate::search::uninformed::bfs;
+
+use super::replanning::Query;
+
+/// The outcome of a query with a progressively revealed map
+/// ## Fields
+/// * `path` - The cells the agent visited, starting with the start
+/// * `executed` - The number of moves taken
+/// * `optimal` - The optimal cost with the whole map revealed, if reachable
+/// * `replans` - The number of plans after the first
+/// * `success` - Whether the agent reached the goal
+#[derive(Clone, Debug, PartialEq)]
+pub struct RevealReport {
+    pub path: Vec<Cell>,
+    pub executed: usize,
+    pub optimal: Option<usize>,
+    pub replans: usize,
+    pub success: bool,
+}
+
+impl RevealReport {
+    /// The executed cost over the optimal cost of a successful query
+    pub fn suboptimality(&self) -> Option<f64> {
+        match (self.success, self.optimal) {
+            (true, Some(optimal)) => Some(self.executed as f64 / optimal.max(1) as f64),
+            _ => None,
+        }
+    }
+}
+
+/// Runs queries on a sampling grid whose map is revealed around the agent
+/// ## Fields
+/// * `radius` - The radius around the agent revealed after every move
+/// * `max_steps` - The number of moves after which a query fails
+pub struct RevealBenchmark {
+    pub radius: usize,
+    pub max_steps: usize,
+}
+
+impl RevealBenchmark {
+    pub fn new(radius: usize, max_steps: usize) -> Self {
+        RevealBenchmark { radius, max_steps }
+    }
+
+    /// Runs a query, leaving the gridmap of the grid as revealed at its end
+    /// ## Arguments
+    /// * `grid` - The sampling grid whose belief is revealed
+    /// * `start` - The start cell
+    /// * `goal` - The goal cell
+    /// * `planner` - Plans a path on the revealed gridmap from the agent to the goal
+    pub fn run_query<P>(&self, grid: &mut SampleGrid, start: Cell, goal: Cell, mut planner: P) -> RevealReport
+    where
+        P: FnMut(&BitPackedGrid, Cell, Cell) -> Option<Vec<Cell>>,
+    {
+        grid.init_gridmap();
+        let optimal = bfs(|n| grid.gridmap().adjacent(*n, false), start, |n| *n == goal).map(|(_, cost)| cost);
+        grid.fill_gridmap();
+        grid.init_gridmap_radius(start, self.radius);
+
+        let mut report = RevealReport { path: vec![start], executed: 0, optimal, replans: 0, success: false };
+        let mut plan: Vec<Cell> = Vec::new();
+        let mut current = start;
+        let mut plans = 0;
+        while current != goal && report.executed < self.max_steps {
+            let blocked = plan.iter().any(|n| !grid.gridmap().get_bit_value(*n));
+            if plan.is_empty() || blocked {
+                match planner(grid.gridmap(), current, goal) {
+                    Some(path) if path.len() > 1 => plan = path.into_iter().skip(1).rev().collect(),
+                    _ => break,
+                }
+                plans += 1;
+            }
+            current = plan.pop().unwrap();
+            grid.init_gridmap_radius(current, self.radius);
+            report.path.push(current);
+            report.executed += 1;
+        }
+        report.replans = plans.max(1) - 1;
+        report.success = current == goal;
+        report
+    }
+
+    /// Runs every query from a fresh reveal
+    /// ## Returns
+    /// The report of each query and the total executed cost of the successful queries
+    pub fn run<P>(&self, grid: &mut SampleGrid, queries: &[Query], mut planner: P) -> (Vec<RevealReport>, usize)
+    where
+        P: FnMut(&BitPackedGrid, Cell, Cell) -> Option<Vec<Cell>>,
+    {
+        let reports = queries.iter()
+            .map(|&(start, goal)| self.run_query(grid, start, goal, &mut planner))
+            .collect::<Vec<_>>();
+        let total = reports.iter().filter(|r| r.success).map(|r| r.executed).sum();
+        (reports, total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::astar_planner;
+
+    #[test]
+    fn test_reveal_benchmark() {
+        let map = ".....\n.@@@.\n...@.\n";
+        let mut grid = SampleGrid::new_from_string(map.to_string());
+        let benchmark = RevealBenchmark::new(1, 100);
+        // The corridor is only found to be a dead end once entered
+        let report = benchmark.run_query(&mut grid, (0, 2), (4, 2), astar_planner);
+        assert!(report.success);
+        assert_eq!(report.optimal, Some(8));
+        assert!(report.executed > 8 && report.replans > 0);
+        assert_eq!(report.path.len(), report.executed + 1);
+        assert!(report.path.iter().all(|n| grid.ground_truth.get_bit_value(*n)));
+        assert!(report.suboptimality().unwrap() > 1.0);
+
+        let (reports, total) = benchmark.run(&mut grid, &[((0, 0), (4, 0)), ((0, 2), (4, 2))], astar_planner);
+        assert_eq!(reports[0].executed, 4);
+        assert_eq!(reports[0].replans, 0);
+        assert_eq!(reports[1], report);
+        assert_eq!(total, 4 + report.executed);
+    }
+}
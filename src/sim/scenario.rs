@@ -0,0 +1,252 @@
+//! # Dynamic Scenarios
+//! Scripts of timed events on a map, such as walls appearing, doors opening
+//! and closing or regions flooding, so dynamic replanning benchmarks can be
+//! rerun identically. A scenario is built once and then played on a copy of
+//! the map, applying the events of each tick in the order they were added.
+//!
+//! ```ignore
+//! let scenario = Scenario::new()
+//!     .wall(5, (3, 0), (3, 4))
+//!     .door((6, 2), 4, 20)
+//!     .flood(10, (8, 8), 3, 2);
+//! let episode = run_scenario_episode(&scenario, &map, &mut belief, start, goal, astar_planner, &sensor, 100);
+//! ```
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+use crate::fov::linedrawing::bresenham;
+
+use super::{simulate, Episode, ReplanTrigger, SensorModel};
+
+/// A change to the map at a tick of a scenario
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// Sets cells free or blocked
+    Set { cells: Vec<Cell>, free: bool },
+    /// Flips every cell between free and blocked
+    Toggle { cells: Vec<Cell> },
+    /// Blocks the cells within `depth` moves of a seed through free or
+    /// previously flooded cells, `flood` identifies the spreading region
+    Flood { flood: usize, seed: Cell, depth: usize },
+}
+
+/// A script of timed events, built with chained calls
+/// ## Fields
+/// * `events` - The events with the tick they happen at, ordered by tick
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scenario {
+    pub events: Vec<(usize, Event)>,
+    floods: usize,
+}
+
+impl Scenario {
+    /// Creates a scenario without events
+    pub fn new() -> Self {
+        Scenario::default()
+    }
+
+    /// Adds an event at a tick, after the events already at that tick
+    pub fn at(mut self, time: usize, event: Event) -> Self {
+        if let Event::Flood { flood, .. } = event {
+            self.floods = self.floods.max(flood + 1);
+        }
+        let index = self.events.partition_point(|(t, _)| *t <= time);
+        self.events.insert(index, (time, event));
+        self
+    }
+
+    /// Raises a wall segment between two cells at a tick
+    pub fn wall(self, time: usize, from: Cell, to: Cell) -> Self {
+        self.at(time, Event::Set { cells: bresenham(from, to, |_, _| true), free: false })
+    }
+
+    /// Clears a segment between two cells at a tick
+    pub fn clear(self, time: usize, from: Cell, to: Cell) -> Self {
+        self.at(time, Event::Set { cells: bresenham(from, to, |_, _| true), free: true })
+    }
+
+    /// Toggles a door every `period` ticks, starting at `period`, until a tick
+    pub fn door(mut self, cell: Cell, period: usize, until: usize) -> Self {
+        assert!(period > 0, "A door must have a positive period");
+        for time in (period..=until).step_by(period) {
+            self = self.at(time, Event::Toggle { cells: vec![cell] });
+        }
+        self
+    }
+
+    /// Floods the region around a seed from a tick, spreading one move every
+    /// `ticks_per_move` ticks until it is `radius` moves across
+    pub fn flood(mut self, time: usize, seed: Cell, radius: usize, ticks_per_move: usize) -> Self {
+        let flood = self.floods;
+        self.floods += 1;
+        for depth in 0..=radius {
+            self = self.at(time + depth * ticks_per_move, Event::Flood { flood, seed, depth });
+        }
+        self
+    }
+
+    /// The last tick with an event
+    pub fn end_time(&self) -> Option<usize> {
+        self.events.last().map(|(t, _)| *t)
+    }
+
+    /// Starts playing the scenario
+    pub fn play(&self) -> ScenarioPlayer<'_> {
+        ScenarioPlayer { scenario: self, next: 0, flooded: HashMap::new() }
+    }
+
+    /// The map after every event up to and including a tick
+    pub fn map_at(&self, map: &BitPackedGrid, time: usize) -> BitPackedGrid {
+        let mut map = map.clone();
+        self.play().advance(&mut map, time);
+        map
+    }
+}
+
+/// The progress of a scenario played on a map
+/// ## Fields
+/// * `next` - The index of the next event to apply
+/// * `flooded` - The cells blocked by each flood so far
+pub struct ScenarioPlayer<'a> {
+    scenario: &'a Scenario,
+    next: usize,
+    flooded: HashMap<usize, HashSet<Cell>>,
+}
+
+impl ScenarioPlayer<'_> {
+    /// Applies every event up to and including a tick not yet applied
+    /// ## Returns
+    /// The cells whose value changed
+    pub fn advance(&mut self, map: &mut BitPackedGrid, time: usize) -> Vec<Cell> {
+        let mut changed = Vec::new();
+        while let Some((_, event)) = self.scenario.events.get(self.next).filter(|(t, _)| *t <= time) {
+            self.next += 1;
+            let mut set = |map: &mut BitPackedGrid, cell: Cell, free: bool| {
+                if map.bounds_check(cell) && map.get_bit_value(cell) != free {
+                    map.set_bit_value(cell, free);
+                    changed.push(cell);
+                }
+            };
+            match event {
+                Event::Set { cells, free } => cells.iter().for_each(|c| set(map, *c, *free)),
+                Event::Toggle { cells } => {
+                    for cell in cells {
+                        if map.bounds_check(*cell) {
+                            let free = !map.get_bit_value(*cell);
+                            set(map, *cell, free);
+                        }
+                    }
+                }
+                Event::Flood { flood, seed, depth } => {
+                    let flooded = self.flooded.entry(*flood).or_default();
+                    let reached = flood_fill(map, flooded, *seed, *depth);
+                    for cell in reached {
+                        flooded.insert(cell);
+                        set(map, cell, false);
+                    }
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// The cells within `depth` moves of a seed through free or flooded cells
+fn flood_fill(map: &BitPackedGrid, flooded: &HashSet<Cell>, seed: Cell, depth: usize) -> Vec<Cell> {
+    let passable = |c: Cell| map.bounds_check(c) && (map.get_bit_value(c) || flooded.contains(&c));
+    if !passable(seed) {
+        return Vec::new();
+    }
+    let mut reached = vec![seed];
+    let mut distances = HashMap::from([(seed, 0)]);
+    let mut queue = VecDeque::from([seed]);
+    while let Some((x, y)) = queue.pop_front() {
+        let distance = distances[&(x, y)];
+        if distance == depth {
+            continue;
+        }
+        for next in [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)] {
+            if passable(next) && !distances.contains_key(&next) {
+                distances.insert(next, distance + 1);
+                reached.push(next);
+                queue.push_back(next);
+            }
+        }
+    }
+    reached
+}
+
+/// Runs a navigation episode while a scenario changes the ground truth,
+/// replanning when the plan is blocked, see `run_episode`
+/// ## Arguments
+/// * `scenario` - The events changing the ground truth, where the events of a
+///   tick are applied before the agent senses after that many moves
+/// * `ground_truth` - The map at the start of the scenario
+/// * `belief` - The belief of the agent, updated with the sensed cells
+/// * `start` - The start cell
+/// * `goal` - The goal cell
+/// * `planner` - A planner on the belief
+/// * `sensor` - The sensor model of the agent
+/// * `max_steps` - The number of moves after which the episode fails
+#[allow(clippy::too_many_arguments)]
+pub fn run_scenario_episode<P, S>(
+    scenario: &Scenario,
+    ground_truth: &BitPackedGrid,
+    belief: &mut BitPackedGrid,
+    start: Cell,
+    goal: Cell,
+    planner: P,
+    sensor: &S,
+    max_steps: usize,
+) -> Episode
+where
+    P: FnMut(&BitPackedGrid, Cell, Cell) -> Option<Vec<Cell>>,
+    S: SensorModel + ?Sized,
+{
+    let mut ground_truth = ground_truth.clone();
+    let mut player = scenario.play();
+    let on_tick = |time: usize, map: &mut BitPackedGrid| {
+        player.advance(map, time);
+    };
+    simulate(&mut ground_truth, belief, start, goal, planner, sensor, max_steps, &[ReplanTrigger::Blocked], on_tick).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{astar_planner, RadiusSensor};
+
+    #[test]
+    fn test_scenario_events() {
+        let map = BitPackedGrid::new_from_string(".....\n.....\n.....\n".to_string());
+        let scenario = Scenario::new()
+            .flood(1, (0, 0), 2, 1)
+            .wall(2, (4, 0), (4, 2))
+            .door((2, 2), 2, 6);
+        assert_eq!(scenario.end_time(), Some(6));
+        assert_eq!(scenario.map_at(&map, 0).print_cells(None), ".....\n.....\n.....\n");
+        assert_eq!(scenario.map_at(&map, 1).print_cells(None), "@....\n.....\n.....\n");
+        assert_eq!(scenario.map_at(&map, 2).print_cells(None), "@@..@\n@...@\n..@.@\n");
+        assert_eq!(scenario.map_at(&map, 4).print_cells(None), "@@@.@\n@@..@\n@...@\n");
+        assert_eq!(scenario.map_at(&map, 6).print_cells(None), "@@@.@\n@@..@\n@.@.@\n");
+
+        let mut player = scenario.play();
+        let mut played = map.clone();
+        assert_eq!(player.advance(&mut played, 1), vec![(0, 0)]);
+        assert!(player.advance(&mut played, 1).is_empty());
+    }
+
+    #[test]
+    fn test_run_scenario_episode() {
+        let map = BitPackedGrid::new_from_string(".....\n.....\n.....\n".to_string());
+        let mut belief = map.clone();
+        // A wall closes the direct route once the agent is on its way
+        let scenario = Scenario::new().wall(1, (2, 0), (2, 1));
+        let episode = run_scenario_episode(&scenario, &map, &mut belief, (0, 0), (4, 0), astar_planner, &RadiusSensor { radius: 1 }, 20);
+        assert!(episode.success);
+        assert_eq!(episode.cost, 8);
+        assert_eq!(episode.replans, 1);
+        assert!(!belief.get_bit_value((2, 1)));
+    }
+}
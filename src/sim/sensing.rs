@@ -0,0 +1,135 @@
+//! # Active Sensing
+//! Scheduling of a limited number of sensing actions on a sampling grid. The
+//! value of sensing a cell is its entropy, the uncertainty of its belief,
+//! weighted by its relevance to the path, which falls off with the distance
+//! from the path of least expected cost. Sensing positions are chosen greedily,
+//! each maximising the relevance-weighted entropy of the cells it observes that
+//! earlier actions do not already cover.
+
+use std::collections::HashSet;
+
+use crate::domains::samplegrid::SampleGrid;
+use crate::heuristics::distance::manhattan_distance;
+use crate::search::astar::astar;
+
+use super::SensorModel;
+
+/// The lowest probability of a cell being free used when computing expected costs
+const MIN_PROBABILITY: f32 = 0.01;
+
+/// The binary entropy in bits of a cell that is free with probability `p`
+pub fn entropy(p: f32) -> f32 {
+    let p = p.clamp(0.0, 1.0);
+    if p == 0.0 || p == 1.0 {
+        return 0.0;
+    }
+    -(p * p.log2() + (1.0 - p) * (1.0 - p).log2())
+}
+
+/// The entropy of the belief of every cell, indexed as `map[x][y]`
+pub fn entropy_map(grid: &SampleGrid) -> Vec<Vec<f32>> {
+    grid.beliefs().into_iter().map(|row| row.into_iter().map(entropy).collect()).collect()
+}
+
+/// The path of least expected cost on the belief, where entering a cell costs
+/// the inverse of its probability of being free scaled by 100
+pub fn expected_path(grid: &SampleGrid, start: (usize, usize), goal: (usize, usize)) -> Option<(Vec<(usize, usize)>, usize)> {
+    let cost = |n: (usize, usize)| (100.0 / grid.belief(n).max(MIN_PROBABILITY)).round() as usize;
+    astar(
+        |n| grid.adjacent(*n, false).map(|n| (n, cost(n))).collect::<Vec<_>>(),
+        start,
+        |n| *n == goal,
+        |n| manhattan_distance(*n, goal) * 100,
+    )
+}
+
+/// Chooses where to spend a budget of sensing actions
+/// ## Arguments
+/// * `grid` - The sampling grid whose belief is planned on
+/// * `sensor` - The sensor model, applied to the believed grid
+/// * `start` - The start cell of the agent
+/// * `goal` - The goal cell of the agent
+/// * `budget` - The number of sensing actions
+/// ## Returns
+/// The sensing positions in the order chosen, fewer than the budget if no
+/// remaining position observes an uncertain cell
+pub fn schedule_observations<S: SensorModel>(
+    grid: &SampleGrid,
+    sensor: &S,
+    start: (usize, usize),
+    goal: (usize, usize),
+    budget: usize,
+) -> Vec<(usize, usize)> {
+    let Some((path, _)) = expected_path(grid, start, goal) else { return Vec::new() };
+    let entropy = entropy_map(grid);
+    let relevance = |n: (usize, usize)| {
+        let distance = path.iter().map(|p| manhattan_distance(*p, n)).min().unwrap_or(0);
+        1.0 / (1.0 + distance as f32)
+    };
+    let value = (0..grid.width)
+        .map(|x| (0..grid.height).map(|y| entropy[x][y] * relevance((x, y))).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let footprints = (0..grid.width)
+        .flat_map(|x| (0..grid.height).map(move |y| (x, y)))
+        .map(|n| (n, sensor.observe(n, grid.gridmap())))
+        .collect::<Vec<_>>();
+    let mut covered = HashSet::new();
+    let mut actions = Vec::new();
+    while actions.len() < budget {
+        let gain = |cells: &[(usize, usize)]| cells.iter().filter(|n| !covered.contains(*n)).map(|&(x, y)| value[x][y]).sum::<f32>();
+        let Some((position, cells)) = footprints.iter()
+            .map(|(n, cells)| (n, cells, gain(cells)))
+            .filter(|(_, _, g)| *g > 0.0)
+            .max_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(n, cells, _)| (*n, cells))
+        else {
+            break;
+        };
+        covered.extend(cells.iter().copied());
+        actions.push(position);
+    }
+    actions
+}
+
+/// Senses the ground truth of every cell observed from a position
+/// ## Arguments
+/// * `covariance` - The covariance of each measurement where 0.0 is a perfect measurement
+pub fn sense<S: SensorModel>(grid: &mut SampleGrid, sensor: &S, position: (usize, usize), covariance: f32) {
+    for cell in sensor.observe(position, grid.gridmap()) {
+        grid.update_node(cell, covariance);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::RadiusSensor;
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(entropy(0.0), 0.0);
+        assert_eq!(entropy(1.0), 0.0);
+        assert!((entropy(0.5) - 1.0).abs() < 1e-6);
+        assert!(entropy(0.9) < entropy(0.7));
+    }
+
+    #[test]
+    fn test_schedule_observations() {
+        let mut grid = SampleGrid::new_from_string("............\n............\n............\n".to_string());
+        for x in 0..12 {
+            for y in 0..3 {
+                grid.set_belief((x, y), if x >= 9 { 0.5 } else { 0.8 });
+            }
+        }
+        let sensor = RadiusSensor { radius: 1 };
+        let actions = schedule_observations(&grid, &sensor, (0, 1), (5, 1), 2);
+        assert_eq!(actions.len(), 2);
+        assert!(actions[0] != actions[1]);
+        assert!(actions.iter().all(|(x, _)| *x <= 6));
+
+        let before = entropy_map(&grid)[2][1];
+        sense(&mut grid, &sensor, actions[0], 0.0);
+        assert!(grid.is_observed(actions[0]));
+        assert!(entropy_map(&grid)[actions[0].0][actions[0].1] < before);
+    }
+}
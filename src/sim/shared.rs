@@ -0,0 +1,256 @@
+//! # Shared Beliefs
+//! A single sampling grid belief shared by a team of agents. Each agent queues
+//! its own observations, which are delivered to the shared belief after a
+//! communication delay and may be lost in transit. Delivered observations are
+//! fused in information form, summing the precision of every measurement of a
+//! cell, so the shared belief does not depend on the order the agents report in.
+//!
+//! Agents exploring with their own local maps instead merge them pairwise,
+//! given the transform between their frames, once they meet.
+
+use std::collections::{HashMap, VecDeque};
+
+use rand::Rng;
+
+use crate::domains::samplegrid::SampleGrid;
+use crate::util::determinism::with_rng;
+use crate::util::filter::KalmanNode;
+use crate::util::history::{ObservationEvent, ObservationLog};
+
+use super::SensorModel;
+
+/// The smallest measurement covariance, so perfect measurements have a finite precision
+const MIN_COVARIANCE: f32 = 1e-6;
+
+/// The communication constraints between the agents and the shared belief
+/// ## Fields
+/// * `delay` - The number of steps between an observation and its delivery
+/// * `loss` - The probability of an observation being lost in transit
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sharing {
+    pub delay: usize,
+    pub loss: f64,
+}
+
+/// Fuses a batch of observations into a sampling grid. The precision of every
+/// measurement of a cell is added to the precision of its belief, so the
+/// result is the same for any order of the observations.
+pub fn fuse_observations(grid: &mut SampleGrid, events: &[ObservationEvent]) {
+    let mut information: HashMap<(usize, usize), (f32, f32)> = HashMap::new();
+    for event in events {
+        let precision = 1.0 / event.covariance.max(MIN_COVARIANCE);
+        let entry = information.entry(event.node).or_default();
+        entry.0 += precision;
+        entry.1 += precision * event.measurement;
+    }
+    for (cell, (precision, weighted)) in information {
+        let node = grid.node(cell);
+        let prior = 1.0 / node.covariance.max(MIN_COVARIANCE);
+        let state = (prior * node.state + weighted) / (prior + precision);
+        grid.set_node(cell, KalmanNode { state, covariance: 1.0 / (prior + precision) });
+    }
+}
+
+/// A rigid transform from the frame of one local map into another, rotating
+/// clockwise by quarter turns within the source map before translating
+/// ## Fields
+/// * `quarter_turns` - The number of clockwise quarter turns
+/// * `translation` - The offset of the rotated map in the target frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Transform {
+    pub quarter_turns: usize,
+    pub translation: (i64, i64),
+}
+
+impl Transform {
+    /// Maps a cell of a source map of a given size into the target frame
+    pub fn apply(&self, (x, y): (usize, usize), (width, height): (usize, usize)) -> (i64, i64) {
+        let (x, y, w, h) = (x as i64, y as i64, width as i64, height as i64);
+        let (x, y) = match self.quarter_turns % 4 {
+            0 => (x, y),
+            1 => (h - 1 - y, x),
+            2 => (w - 1 - x, h - 1 - y),
+            _ => (y, w - 1 - x),
+        };
+        (x + self.translation.0, y + self.translation.1)
+    }
+}
+
+/// Merges the local map of another agent into a map, treating the belief of
+/// every observed cell of the other map as a measurement of the cell it lands
+/// on. Cells that are unobserved or fall outside the map are skipped.
+/// ## Returns
+/// The number of cells merged
+pub fn merge_maps(map: &mut SampleGrid, other: &SampleGrid, transform: Transform) -> usize {
+    let mut merged = 0;
+    for x in 0..other.width {
+        for y in 0..other.height {
+            if !other.is_observed((x, y)) {
+                continue;
+            }
+            let (tx, ty) = transform.apply((x, y), (other.width, other.height));
+            if tx < 0 || ty < 0 || tx as usize >= map.width || ty as usize >= map.height {
+                continue;
+            }
+            let belief = other.node((x, y));
+            map.update_belief((tx as usize, ty as usize), belief.state, belief.covariance);
+            merged += 1;
+        }
+    }
+    map.init_gridmap();
+    merged
+}
+
+/// A sampling grid belief shared by several agents
+/// ## Fields
+/// * `grid` - The shared belief, whose ground truth the agents observe
+/// * `queues` - The observations of each agent waiting to be delivered
+/// * `sharing` - The communication constraints of the agents
+/// * `log` - Every observation delivered to the shared belief
+/// * `dropped` - The number of observations lost in transit
+/// * `time` - The current step
+pub struct SharedBelief {
+    pub grid: SampleGrid,
+    pub queues: Vec<VecDeque<ObservationEvent>>,
+    pub sharing: Sharing,
+    pub log: ObservationLog,
+    pub dropped: usize,
+    pub time: usize,
+}
+
+impl SharedBelief {
+    /// Creates a shared belief for a number of agents
+    pub fn new(grid: SampleGrid, agents: usize, sharing: Sharing) -> Self {
+        SharedBelief {
+            grid,
+            queues: vec![VecDeque::new(); agents],
+            sharing,
+            log: ObservationLog::default(),
+            dropped: 0,
+            time: 0,
+        }
+    }
+
+    /// Queues an observation of a cell of the ground truth by an agent
+    pub fn observe(&mut self, agent: usize, node: (usize, usize), covariance: f32) {
+        let measurement = self.grid.ground_truth.get_bit_value(node) as u8 as f32;
+        self.queues[agent].push_back(ObservationEvent { time: self.time, node, measurement, covariance });
+    }
+
+    /// Queues the observations of every cell a sensor reveals from the position of an agent
+    pub fn sense<S: SensorModel + ?Sized>(&mut self, agent: usize, sensor: &S, position: (usize, usize), covariance: f32) {
+        for node in sensor.observe(position, &self.grid.ground_truth) {
+            self.observe(agent, node, covariance);
+        }
+    }
+
+    /// The number of observations of an agent waiting to be delivered
+    pub fn pending(&self, agent: usize) -> usize {
+        self.queues[agent].len()
+    }
+
+    /// Delivers every queued observation whose delay has passed, fusing those
+    /// that are not lost into the shared belief, then advances to the next step
+    /// ## Returns
+    /// The number of observations fused
+    pub fn step(&mut self) -> usize {
+        let mut delivered = Vec::new();
+        for queue in &mut self.queues {
+            while queue.front().is_some_and(|e| e.time + self.sharing.delay <= self.time) {
+                delivered.extend(queue.pop_front());
+            }
+        }
+        let before = delivered.len();
+        if self.sharing.loss > 0.0 {
+            let loss = self.sharing.loss.min(1.0);
+            delivered.retain(|_| !with_rng(|rng| rng.gen_bool(loss)));
+        }
+        self.dropped += before - delivered.len();
+        fuse_observations(&mut self.grid, &delivered);
+        for event in &delivered {
+            self.log.record(event.time, event.node, event.measurement, event.covariance);
+        }
+        self.time += 1;
+        delivered.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::RadiusSensor;
+
+    #[test]
+    fn test_fuse_observations_commutative() {
+        let grid = SampleGrid::new_from_string("..@\n...\n".to_string());
+        let event = |node, measurement, covariance| ObservationEvent { time: 0, node, measurement, covariance };
+        let mut events = vec![event((0, 0), 1.0, 0.5), event((2, 0), 0.0, 0.25), event((0, 0), 0.0, 2.0)];
+        let mut forward = grid.clone();
+        fuse_observations(&mut forward, &events);
+        events.reverse();
+        let mut backward = grid.clone();
+        fuse_observations(&mut backward, &events);
+        for (x, y) in [(0, 0), (2, 0), (1, 1)] {
+            assert_eq!(forward.belief((x, y)), backward.belief((x, y)));
+            assert_eq!(forward.covariance((x, y)), backward.covariance((x, y)));
+        }
+
+        let mut sequential = grid.clone();
+        sequential.update_belief((0, 0), 1.0, 0.5);
+        sequential.update_belief((0, 0), 0.0, 2.0);
+        assert!((sequential.belief((0, 0)) - forward.belief((0, 0))).abs() < 1e-6);
+        assert!((sequential.covariance((0, 0)) - forward.covariance((0, 0))).abs() < 1e-6);
+        assert_eq!(forward.covariance((1, 1)), 1.0);
+    }
+
+    #[test]
+    fn test_shared_belief_delay_and_loss() {
+        let grid = SampleGrid::new_from_string(".....\n.....\n.....\n".to_string());
+        let mut shared = SharedBelief::new(grid.clone(), 2, Sharing { delay: 1, loss: 0.0 });
+        shared.sense(0, &RadiusSensor { radius: 0 }, (0, 0), 0.5);
+        shared.observe(1, (4, 2), 0.5);
+        assert_eq!((shared.pending(0), shared.pending(1)), (1, 1));
+        assert_eq!(shared.step(), 0);
+        assert_eq!(shared.grid.covariance((0, 0)), 1.0);
+        shared.observe(1, (4, 1), 0.5);
+        assert_eq!(shared.step(), 2);
+        assert!(shared.grid.is_observed((0, 0)) && shared.grid.is_observed((4, 2)));
+        assert!(!shared.grid.is_observed((4, 1)));
+        assert_eq!(shared.step(), 1);
+        assert_eq!(shared.log.len(), 3);
+
+        let mut lossy = SharedBelief::new(grid, 1, Sharing { delay: 0, loss: 1.0 });
+        lossy.observe(0, (1, 1), 0.5);
+        assert_eq!(lossy.step(), 0);
+        assert_eq!(lossy.dropped, 1);
+        assert!(!lossy.grid.is_observed((1, 1)));
+    }
+
+    #[test]
+    fn test_merge_maps() {
+        let transform = Transform { quarter_turns: 1, translation: (1, 0) };
+        assert_eq!(transform.apply((0, 0), (3, 2)), (2, 0));
+        assert_eq!(transform.apply((2, 1), (3, 2)), (1, 2));
+        for turns in 0..4 {
+            let t = Transform { quarter_turns: turns, translation: (0, 0) };
+            let mut cells = (0..3).flat_map(|x| (0..2).map(move |y| t.apply((x, y), (3, 2)))).collect::<Vec<_>>();
+            cells.sort();
+            cells.dedup();
+            assert_eq!(cells.len(), 6);
+            assert!(cells.iter().all(|&(x, y)| x >= 0 && y >= 0 && x < 3 && y < 3));
+        }
+
+        let mut map = SampleGrid::new_from_string("...\n...\n...\n".to_string());
+        let mut other = SampleGrid::new_from_string("..@\n...\n".to_string());
+        other.update_node((2, 0), 0.0);
+        other.update_node((0, 1), 0.5);
+        let merged = merge_maps(&mut map, &other, transform);
+        assert_eq!(merged, 2);
+        assert_eq!(map.belief((2, 2)), 0.0);
+        assert_eq!(map.covariance((2, 2)), 0.0);
+        assert!(!map.gridmap().get_bit_value((2, 2)));
+        assert!(map.is_observed((1, 0)));
+        let outside = Transform { quarter_turns: 0, translation: (2, 2) };
+        assert_eq!(merge_maps(&mut map, &other, outside), 0);
+    }
+}
@@ -0,0 +1,75 @@
+//! # Stopping Criteria
+//! Conditions ending an episode on a sampling grid, checked after sensing at
+//! every step. Navigation episodes usually stop at the goal or when a step
+//! budget runs out, while exploration episodes can instead stop once enough of
+//! the free space is covered or the belief is certain enough. The criterion
+//! that ended an episode is reported alongside it.
+
+use crate::domains::{Cell, samplegrid::SampleGrid};
+
+use super::coverage::coverage;
+use super::sensing::entropy_map;
+
+/// A condition under which an episode stops
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StoppingCriterion {
+    /// Stops when the agent is at its goal
+    GoalReached,
+    /// Stops once `fraction` of the free space has a covariance below `threshold`
+    Coverage { threshold: f32, fraction: f64 },
+    /// Stops once the mean entropy of the belief of every cell is below a
+    /// number of bits
+    Entropy(f32),
+    /// Stops after a number of moves
+    StepBudget(usize),
+}
+
+impl StoppingCriterion {
+    /// Checks if the criterion is met
+    /// ## Arguments
+    /// * `belief` - The sampling grid of the episode
+    /// * `current` - The cell of the agent
+    /// * `goal` - The goal cell of the agent
+    /// * `steps` - The number of moves made
+    pub fn is_met(&self, belief: &SampleGrid, current: Cell, goal: Cell, steps: usize) -> bool {
+        match self {
+            StoppingCriterion::GoalReached => current == goal,
+            StoppingCriterion::Coverage { threshold, fraction } => coverage(belief, *threshold) >= *fraction,
+            StoppingCriterion::Entropy(bits) => mean_entropy(belief) < *bits,
+            StoppingCriterion::StepBudget(budget) => steps >= *budget,
+        }
+    }
+}
+
+/// The mean entropy in bits of the belief of every cell
+pub fn mean_entropy(belief: &SampleGrid) -> f32 {
+    let cells = (belief.width * belief.height).max(1);
+    entropy_map(belief).iter().flatten().sum::<f32>() / cells as f32
+}
+
+/// The first of several criteria that is met
+pub fn first_met(criteria: &[StoppingCriterion], belief: &SampleGrid, current: Cell, goal: Cell, steps: usize) -> Option<StoppingCriterion> {
+    criteria.iter().copied().find(|c| c.is_met(belief, current, goal, steps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stopping_criteria() {
+        let mut grid = SampleGrid::new_from_string("...\n.@.\n".to_string());
+        let coverage = StoppingCriterion::Coverage { threshold: 0.5, fraction: 0.5 };
+        let criteria = [StoppingCriterion::GoalReached, coverage, StoppingCriterion::StepBudget(3)];
+        assert_eq!(first_met(&criteria, &grid, (0, 0), (2, 1), 0), None);
+        assert_eq!(first_met(&criteria, &grid, (2, 1), (2, 1), 5), Some(StoppingCriterion::GoalReached));
+        assert_eq!(first_met(&criteria, &grid, (0, 0), (2, 1), 3), Some(StoppingCriterion::StepBudget(3)));
+        for x in 0..3 {
+            grid.update_node((x, 0), 0.0);
+        }
+        assert_eq!(first_met(&criteria, &grid, (0, 0), (2, 1), 0), Some(coverage));
+        let bits = mean_entropy(&grid);
+        assert!(StoppingCriterion::Entropy(bits + 0.01).is_met(&grid, (0, 0), (2, 1), 0));
+        assert!(!StoppingCriterion::Entropy(bits).is_met(&grid, (0, 0), (2, 1), 0));
+    }
+}
@@ -0,0 +1,112 @@
+//! # Watchman Routes
+//! Routes from which every free cell of a map is observed under a sensor
+//! model, in the spirit of the watchman route problem. Viewpoints are chosen
+//! one at a time by a greedy set cover of the cells each free cell observes,
+//! taking the cell that observes the most unobserved space per step of travel
+//! from the last viewpoint, and are joined by shortest paths. The route is not
+//! optimal, but greedy set cover keeps the number of viewpoints within a
+//! logarithmic factor of the fewest needed.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::analysis::statistics::grid_distances;
+use crate::domains::{Cell, bitpackedgrid::BitPackedGrid};
+use crate::heuristics::distance::manhattan_distance;
+use crate::search::astar::astar;
+
+use super::SensorModel;
+
+/// A route observing the free space of a map
+/// ## Fields
+/// * `viewpoints` - The cells the route observes from, in the order visited
+/// * `path` - The 4-connected path from the start through every viewpoint
+/// * `observed` - The number of free cells observed from the viewpoints
+/// * `free` - The number of free cells reachable from the start
+#[derive(Clone, Debug, PartialEq)]
+pub struct WatchmanRoute {
+    pub viewpoints: Vec<Cell>,
+    pub path: Vec<Cell>,
+    pub observed: usize,
+    pub free: usize,
+}
+
+/// Plans a route from which every free cell reachable from the start is
+/// observed
+/// ## Arguments
+/// * `grid` - The grid map
+/// * `start` - The start cell
+/// * `sensor` - The sensor model observing the map from each cell of the route
+/// ## Returns
+/// The route, whose viewpoints observe every reachable free cell observable
+/// from some reachable cell
+pub fn watchman_route<S: SensorModel + ?Sized>(grid: &BitPackedGrid, start: Cell, sensor: &S) -> WatchmanRoute {
+    let reachable = grid_distances(grid, start);
+    let views: HashMap<Cell, HashSet<Cell>> = reachable.keys()
+        .map(|cell| {
+            let view = sensor.observe(*cell, grid).into_iter().filter(|n| reachable.contains_key(n)).collect();
+            (*cell, view)
+        })
+        .collect();
+    let mut unobserved: HashSet<Cell> = views.values().flatten().copied().collect();
+    let observable = unobserved.len();
+    unobserved.retain(|n| !views[&start].contains(n));
+
+    // Greedy set cover, weighing new cells against the travel from the last viewpoint
+    let mut viewpoints = Vec::new();
+    let mut position = start;
+    while !unobserved.is_empty() {
+        let travel = grid_distances(grid, position);
+        let gain = |cell: &Cell| views[cell].iter().filter(|n| unobserved.contains(n)).count();
+        let best = views.keys()
+            .filter(|cell| gain(cell) > 0)
+            .max_by(|a, b| {
+                let score = |c: &Cell| gain(c) as f64 / (1 + travel[c]) as f64;
+                score(a).total_cmp(&score(b)).then(b.cmp(a))
+            })
+            .copied()
+            .unwrap();
+        unobserved.retain(|n| !views[&best].contains(n));
+        viewpoints.push(best);
+        position = best;
+    }
+
+    let mut path = vec![start];
+    for viewpoint in &viewpoints {
+        let from = *path.last().unwrap();
+        let (leg, _) = astar(
+            |n: &Cell| grid.adjacent1(*n).filter(|(n, _)| grid.bounds_check(*n)).collect::<Vec<_>>(),
+            from,
+            |n| n == viewpoint,
+            |n| manhattan_distance(*n, *viewpoint),
+        )
+        .unwrap();
+        path.extend(leg.into_iter().skip(1));
+    }
+    WatchmanRoute { viewpoints, path, observed: observable, free: reachable.len() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::RaycastSensor;
+
+    #[test]
+    fn test_watchman_route() {
+        let grid = BitPackedGrid::new_from_string(
+            ".......@.....\n.......@.....\n.......@.....\n.............\n".to_string()
+        );
+        let sensor = RaycastSensor { radius: 4 };
+        let route = watchman_route(&grid, (0, 0), &sensor);
+        assert_eq!(route.free, grid.free_cells().count());
+        let observed: HashSet<Cell> = route.path.iter().flat_map(|c| sensor.observe(*c, &grid)).collect();
+        assert!(grid.free_cells().all(|c| observed.contains(&c)));
+        assert!(route.path.windows(2).all(|w| manhattan_distance(w[0], w[1]) == 1));
+        assert!(route.viewpoints.len() < 6);
+
+        // Everything is in view of a large enough sensor from the start
+        let open = BitPackedGrid::new_from_string(".....\n.....\n".to_string());
+        let route = watchman_route(&open, (2, 1), &RaycastSensor { radius: 5 });
+        assert!(route.viewpoints.is_empty());
+        assert_eq!(route.path, vec![(2, 1)]);
+    }
+}
@@ -0,0 +1,59 @@
+//! # Base64
+//! The URL-safe base64 alphabet without padding, used by the compact string
+//! encodings of grids and paths so they can be pasted into issue reports,
+//! unit tests and web viewer URLs without escaping.
+
+/// The URL-safe alphabet, where `-` and `_` replace `+` and `/`
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encodes bytes as URL-safe base64 without padding
+pub fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() * 4).div_ceil(3));
+    for chunk in bytes.chunks(3) {
+        let word = chunk.iter().enumerate().fold(0u32, |w, (i, b)| w | (*b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[(word >> (18 - 6 * i) & 63) as usize] as char);
+        }
+    }
+    encoded
+}
+
+/// Decodes URL-safe base64 without padding
+/// ## Returns
+/// The bytes, `None` if a character is outside the alphabet or the length is
+/// not that of an encoding
+pub fn decode(encoded: &str) -> Option<Vec<u8>> {
+    let digits = encoded.bytes()
+        .map(|c| ALPHABET.iter().position(|a| *a == c).map(|d| d as u32))
+        .collect::<Option<Vec<_>>>()?;
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let word = chunk.iter().enumerate().fold(0u32, |w, (i, d)| w | d << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            bytes.push((word >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(encode(b"Man"), "TWFu");
+        assert_eq!(encode(b"Ma"), "TWE");
+        assert_eq!(encode(&[0xfb, 0xff]), "-_8");
+        for length in 0..8 {
+            let bytes = (0..length as u8).map(|b| b.wrapping_mul(97)).collect::<Vec<_>>();
+            assert_eq!(decode(&encode(&bytes)), Some(bytes));
+        }
+        assert_eq!(decode("TWF"), Some(b"Ma".to_vec()));
+        assert_eq!(decode("TWFuT"), None);
+        assert_eq!(decode("TW=u"), None);
+    }
+}
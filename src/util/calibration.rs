@@ -0,0 +1,149 @@
+//! Calibration of sampling grid beliefs against the ground truth. The state of
+//! a cell is treated as the predicted probability that the cell is free, which
+//! is compared with whether the cell is free in the ground truth.
+
+use crate::domains::samplegrid::SampleGrid;
+
+/// A bin of a calibration curve
+/// ## Fields
+/// * `lower` - The lower bound of predicted probabilities in the bin
+/// * `upper` - The upper bound of predicted probabilities in the bin
+/// * `mean_predicted` - The mean predicted probability of cells in the bin
+/// * `observed_frequency` - The fraction of cells in the bin that are free
+/// * `count` - The number of cells in the bin
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalibrationBin {
+    pub lower: f32,
+    pub upper: f32,
+    pub mean_predicted: f32,
+    pub observed_frequency: f32,
+    pub count: usize,
+}
+
+/// Calibration metrics of a sampling grid
+/// ## Fields
+/// * `brier_score` - The mean squared error of the predictions, 0.0 is perfect
+/// * `curve` - The calibration curve, empty bins are omitted
+/// * `auc` - The area under the ROC curve, 1.0 is perfect and 0.5 is random
+#[derive(Clone, Debug, PartialEq)]
+pub struct Calibration {
+    pub brier_score: f32,
+    pub curve: Vec<CalibrationBin>,
+    pub auc: f32,
+}
+
+impl Calibration {
+    /// Evaluates the calibration of a sampling grid against its ground truth
+    /// ## Arguments
+    /// * `grid` - The sampling grid to evaluate
+    /// * `bins` - The number of equal width bins in the calibration curve
+    pub fn new(grid: &SampleGrid, bins: usize) -> Self {
+        let predictions = (0..grid.width)
+            .flat_map(|x| (0..grid.height).map(move |y| (x, y)))
+            .map(|(x, y)| (grid.belief((x, y)), grid.ground_truth.get_bit_value((x, y))))
+            .collect::<Vec<_>>();
+        Calibration::from_predictions(&predictions, bins)
+    }
+
+    /// Evaluates the calibration of (predicted probability, outcome) pairs
+    pub fn from_predictions(predictions: &[(f32, bool)], bins: usize) -> Self {
+        Calibration {
+            brier_score: brier_score(predictions),
+            curve: calibration_curve(predictions, bins),
+            auc: auc(predictions),
+        }
+    }
+}
+
+/// The mean squared error between predicted probabilities and outcomes
+pub fn brier_score(predictions: &[(f32, bool)]) -> f32 {
+    let sum: f32 = predictions.iter()
+        .map(|(p, o)| (p - *o as u8 as f32).powi(2))
+        .sum();
+    sum / predictions.len().max(1) as f32
+}
+
+/// Groups predictions into equal width bins of predicted probability and
+/// compares the mean prediction of each bin with its observed frequency
+pub fn calibration_curve(predictions: &[(f32, bool)], bins: usize) -> Vec<CalibrationBin> {
+    assert!(bins > 0, "A calibration curve requires at least one bin");
+    let mut sums = vec![(0.0, 0, 0); bins];
+    for (p, o) in predictions {
+        let bin = ((p.clamp(0.0, 1.0) * bins as f32) as usize).min(bins - 1);
+        sums[bin].0 += p;
+        sums[bin].1 += *o as usize;
+        sums[bin].2 += 1;
+    }
+    sums.into_iter()
+        .enumerate()
+        .filter(|(_, (_, _, count))| *count > 0)
+        .map(|(i, (predicted, observed, count))| CalibrationBin {
+            lower: i as f32 / bins as f32,
+            upper: (i + 1) as f32 / bins as f32,
+            mean_predicted: predicted / count as f32,
+            observed_frequency: observed as f32 / count as f32,
+            count,
+        })
+        .collect()
+}
+
+/// The area under the ROC curve, computed as the probability that a random
+/// free cell is predicted higher than a random blocked cell with ties counted as half
+/// ## Complexity
+/// O(n log n) where n is the number of predictions
+pub fn auc(predictions: &[(f32, bool)]) -> f32 {
+    let mut sorted = predictions.to_vec();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let positives = sorted.iter().filter(|(_, o)| *o).count();
+    let negatives = sorted.len() - positives;
+    if positives == 0 || negatives == 0 {
+        return 0.5;
+    }
+    // Sum of ranks of positive predictions, tied predictions share their mean rank
+    let mut rank_sum = 0.0;
+    let mut i = 0;
+    while i < sorted.len() {
+        let j = i + sorted[i..].iter().take_while(|(p, _)| *p == sorted[i].0).count();
+        let mean_rank = (i + j + 1) as f64 / 2.0;
+        rank_sum += sorted[i..j].iter().filter(|(_, o)| *o).count() as f64 * mean_rank;
+        i = j;
+    }
+    let positives = positives as f64;
+    ((rank_sum - positives * (positives + 1.0) / 2.0) / (positives * negatives as f64)) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_brier_score() {
+        assert_eq!(brier_score(&[(1.0, true), (0.0, false)]), 0.0);
+        assert_eq!(brier_score(&[(0.5, true), (0.5, false)]), 0.25);
+    }
+
+    #[test]
+    fn test_calibration_curve() {
+        let curve = calibration_curve(&[(0.1, false), (0.2, true), (0.9, true)], 2);
+        assert_eq!(curve.len(), 2);
+        assert_eq!(curve[0].count, 2);
+        assert_eq!(curve[0].observed_frequency, 0.5);
+        assert_eq!(curve[1].mean_predicted, 0.9);
+        assert_eq!(curve[1].observed_frequency, 1.0);
+    }
+
+    #[test]
+    fn test_auc() {
+        assert_eq!(auc(&[(0.9, true), (0.8, true), (0.1, false)]), 1.0);
+        assert_eq!(auc(&[(0.1, true), (0.9, false)]), 0.0);
+        assert_eq!(auc(&[(0.5, true), (0.5, false)]), 0.5);
+    }
+
+    #[test]
+    fn test_calibration_samplegrid() {
+        let grid = SampleGrid::new_from_string("..@\n.@.\n".to_string());
+        let calibration = Calibration::new(&grid, 10);
+        assert_eq!(calibration.brier_score, 0.0);
+        assert_eq!(calibration.auc, 1.0);
+    }
+}
@@ -0,0 +1,144 @@
+//! Deterministic execution across runs and platforms. Randomised components
+//! draw from a thread-local generator that can be reseeded, and hash containers
+//! whose iteration order reaches results use a configurable hasher. With the
+//! `deterministic` feature the generator starts from a fixed seed and the
+//! hasher has fixed keys, so iteration orders, samples and search traces are
+//! identical between runs. Searches break ties between equal costs by the order
+//! of their binary heap, which only depends on the order nodes are expanded.
+//! Parallel experiments derive a seed for every task from a master seed and
+//! run each task with its own generator, so results do not depend on which
+//! thread runs a task or in what order.
+
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "sampling")]
+use std::cell::RefCell;
+#[cfg(feature = "sampling")]
+use rand::{rngs::StdRng, SeedableRng};
+
+/// The seed used by the generator of each thread with the `deterministic` feature
+pub const SEED: u64 = 0x5eed;
+
+/// The hasher of containers whose iteration order is observable
+#[cfg(feature = "deterministic")]
+pub type HashState = std::hash::BuildHasherDefault<std::collections::hash_map::DefaultHasher>;
+/// The hasher of containers whose iteration order is observable
+#[cfg(not(feature = "deterministic"))]
+pub type HashState = std::collections::hash_map::RandomState;
+
+/// A hash map that iterates in the same order between runs with the `deterministic` feature
+pub type Map<K, V> = HashMap<K, V, HashState>;
+/// A hash set that iterates in the same order between runs with the `deterministic` feature
+pub type Set<T> = HashSet<T, HashState>;
+
+/// Whether the crate was built with the `deterministic` feature
+pub fn is_deterministic() -> bool {
+    cfg!(feature = "deterministic")
+}
+
+#[cfg(feature = "sampling")]
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(if cfg!(feature = "deterministic") {
+        StdRng::seed_from_u64(SEED)
+    } else {
+        StdRng::from_entropy()
+    });
+}
+
+/// Reseeds the generator of the current thread, making every following
+/// random choice on the thread reproducible
+#[cfg(feature = "sampling")]
+pub fn reseed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// Runs a function with the generator of the current thread. Every randomised
+/// component of the crate draws from this generator.
+#[cfg(feature = "sampling")]
+pub fn with_rng<R>(f: impl FnOnce(&mut StdRng) -> R) -> R {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+/// Derives the seed of a task from a master seed with the SplitMix64 finaliser,
+/// so neighbouring tasks get unrelated seeds
+pub fn derive_seed(master: u64, task: u64) -> u64 {
+    let mix = |mut z: u64| {
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    };
+    mix(master.wrapping_add(mix(task.wrapping_add(0x9e3779b97f4a7c15))))
+}
+
+/// Runs a function with the generator of the current thread seeded for a
+/// single task, restoring the previous generator afterwards, so the task is
+/// reproducible whichever thread runs it
+#[cfg(feature = "sampling")]
+pub fn with_seed<R>(seed: u64, f: impl FnOnce() -> R) -> R {
+    let previous = RNG.with(|rng| rng.replace(StdRng::seed_from_u64(seed)));
+    let result = f();
+    RNG.with(|rng| *rng.borrow_mut() = previous);
+    result
+}
+
+#[cfg(all(test, feature = "sampling"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reseed() {
+        use rand::Rng;
+        reseed(3);
+        let first = with_rng(|rng| (0..8).map(|_| rng.gen::<u32>()).collect::<Vec<_>>());
+        reseed(3);
+        assert_eq!(with_rng(|rng| (0..8).map(|_| rng.gen::<u32>()).collect::<Vec<_>>()), first);
+    }
+
+    #[test]
+    fn test_with_seed() {
+        use rand::Rng;
+        assert_ne!(derive_seed(1, 0), derive_seed(1, 1));
+        assert_ne!(derive_seed(1, 0), derive_seed(2, 0));
+        reseed(5);
+        let draw = || with_rng(|rng| rng.gen::<u64>());
+        let task = with_seed(derive_seed(7, 3), draw);
+        // The thread's own sequence continues as if the task never ran
+        let after = draw();
+        reseed(5);
+        assert_eq!(draw(), after);
+        let other = std::thread::spawn(move || with_seed(derive_seed(7, 3), draw)).join().unwrap();
+        assert_eq!(other, task);
+    }
+
+    #[test]
+    fn test_identical_traces() {
+        use crate::analysis::heuristics::{analyze_heuristics, sample_cells, Heuristic};
+        use crate::domains::{bitpackedgrid::BitPackedGrid, csrgraph::{octile_cost, Connectivity}};
+        let grid = BitPackedGrid::new_from_string("......\n.@@.@.\n......\n.@....\n".to_string());
+        let zero = |_, _| 0.0;
+        let heuristics: [Heuristic; 1] = [("zero", &zero)];
+        let trace = |seed| {
+            reseed(seed);
+            let goals = sample_cells(&grid, 3);
+            let reports = analyze_heuristics(&grid, Connectivity::Eight, octile_cost, &heuristics, &goals, 4, 2);
+            format!("{:?} {:?}", goals, reports).into_bytes()
+        };
+        assert_eq!(trace(11), trace(11));
+    }
+
+    #[cfg(feature = "deterministic")]
+    #[test]
+    fn test_stable_hashing() {
+        use std::hash::BuildHasher;
+        assert_eq!(HashState::default().hash_one((3, 4)), HashState::default().hash_one((3, 4)));
+        let build = || (0..100).map(|i| (i, i * 2)).collect::<Map<_, _>>();
+        assert_eq!(build().into_iter().collect::<Vec<_>>(), build().into_iter().collect::<Vec<_>>());
+        let graph = || {
+            let mut graph = crate::domains::adjacencylist::AdjacencyList::new();
+            (0..50).for_each(|n| graph.add_node(n));
+            (0..49).for_each(|n| graph.add_edge(n, n + 1, 1));
+            graph.iter().map(|(n, e)| (*n, e.clone())).collect::<Vec<_>>()
+        };
+        assert_eq!(graph(), graph());
+    }
+}
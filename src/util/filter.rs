@@ -5,6 +5,7 @@ use std::ops::{Add, Mul};
 /// A 1-dimensional Kalman filter node
 /// Adapted from kalmanfilter.net/kalman1d_pn.html
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct KalmanNode {
     pub state: f32,
     pub covariance: f32,
@@ -22,6 +23,57 @@ impl KalmanNode {
         self.covariance *= 1.0 - kalman_gain;
         self.state
     }
+
+    /// Predicts the state after some time under a random walk, growing the
+    /// covariance by the process noise so old observations lose confidence
+    /// ## Arguments
+    /// * `process_noise` - The variance added to the state per unit of time
+    /// * `dt` - The time since the last prediction
+    pub fn predict(&mut self, process_noise: f32, dt: f32) {
+        self.covariance += process_noise * dt.max(0.0);
+    }
+
+    /// Decays the state and covariance towards a prior at an exponential rate,
+    /// so a node that is not observed again returns to being unknown
+    /// ## Arguments
+    /// * `prior` - The belief of a node that has never been observed
+    /// * `rate` - The fraction of the difference to the prior forgotten per
+    ///   unit of time, as the exponent of `exp(-rate * dt)`
+    /// * `dt` - The time since the last decay
+    pub fn decay(&mut self, prior: &KalmanNode, rate: f32, dt: f32) {
+        let retained = (-rate * dt.max(0.0)).exp();
+        self.state = prior.state + retained * (self.state - prior.state);
+        self.covariance = prior.covariance + retained * (self.covariance - prior.covariance);
+    }
+}
+
+/// Rauch-Tung-Striebel smoothing of a sequence of observations of a single node.
+/// The state is modelled as a random walk whose variance grows by the process
+/// noise between observations.
+/// ## Arguments
+/// * `initial` - The belief of the node before the first observation
+/// * `observations` - A sequence of (measurement, measurement covariance) pairs
+/// * `process_noise` - The variance added to the state between observations
+/// ## Returns
+/// The smoothed belief of the node after each observation, given every observation
+pub fn rts_smooth(initial: &KalmanNode, observations: &[(f32, f32)], process_noise: f32) -> Vec<KalmanNode> {
+    let mut node = initial.clone();
+    let mut predicted = Vec::with_capacity(observations.len());
+    let mut filtered = Vec::with_capacity(observations.len());
+    for (measurement, measurement_covariance) in observations {
+        node.covariance += process_noise;
+        predicted.push(node.clone());
+        node.update(*measurement, *measurement_covariance);
+        filtered.push(node.clone());
+    }
+    let mut smoothed = filtered.clone();
+    for k in (0..observations.len().saturating_sub(1)).rev() {
+        let gain = filtered[k].covariance / predicted[k + 1].covariance.max(1e-6);
+        smoothed[k].state = filtered[k].state + gain * (smoothed[k + 1].state - predicted[k + 1].state);
+        smoothed[k].covariance = filtered[k].covariance
+            + gain * gain * (smoothed[k + 1].covariance - predicted[k + 1].covariance);
+    }
+    smoothed
 }
 
 impl Default for KalmanNode {
@@ -68,8 +120,6 @@ impl Mul<f32> for KalmanNode {
 
 #[cfg(test)]
 mod tests {
-    use std::f32::NAN;
-
     use super::*;
     #[test]
     fn test_kalman_filter() {
@@ -91,4 +141,41 @@ mod tests {
         };
         assert_eq!(node.update(0.0, 0.0), 0.0);
     }
+
+    #[test]
+    fn test_kalman_predict_decay() {
+        let mut node = KalmanNode { state: 1.0, covariance: 0.1 };
+        node.predict(0.05, 2.0);
+        assert!((node.covariance - 0.2).abs() < 1e-6 && node.state == 1.0);
+        // A more uncertain node moves further towards a new measurement
+        let mut confident = KalmanNode { state: 1.0, covariance: 0.1 };
+        assert!(node.clone().update(0.0, 0.2) < confident.update(0.0, 0.2));
+        let prior = KalmanNode { state: 0.5, covariance: 1.0 };
+        node.decay(&prior, 1.0, 0.0);
+        assert!(node.state == 1.0 && (node.covariance - 0.2).abs() < 1e-6);
+        node.decay(&prior, 2.0_f32.ln(), 1.0);
+        assert!((node.state - 0.75).abs() < 1e-6 && (node.covariance - 0.6).abs() < 1e-6);
+        node.decay(&prior, 1.0, 100.0);
+        assert!((node.state - 0.5).abs() < 1e-6 && (node.covariance - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_rts_smooth() {
+        let initial = KalmanNode { state: 0.5, covariance: 1.0 };
+        let observations = [(1.0, 1.0), (1.0, 1.0), (0.0, 1.0)];
+        // Without process noise every smoothed belief equals the final filtered belief
+        let smoothed = rts_smooth(&initial, &observations, 0.0);
+        let mut filtered = initial.clone();
+        for (m, c) in observations {
+            filtered.update(m, c);
+        }
+        for node in smoothed.iter() {
+            assert!((node.state - filtered.state).abs() < 1e-5);
+            assert!((node.covariance - filtered.covariance).abs() < 1e-5);
+        }
+        // With process noise earlier beliefs are pulled toward their own observations
+        let smoothed = rts_smooth(&initial, &observations, 0.5);
+        assert!(smoothed[0].state > smoothed[2].state);
+        assert!(smoothed[0].covariance < 1.0);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,597 @@
+//! Recording of sampling grid beliefs over the course of an episode.
+
+use std::{collections::HashMap, fmt::Write, io};
+
+use crate::domains::samplegrid::SampleGrid;
+use crate::util::filter::{rts_smooth, KalmanNode};
+
+/// Per-cell covariance of a sampling grid recorded over time. Only the cells
+/// whose covariance changed are stored for each step after the first.
+/// ## Fields
+/// * `initial` - The covariance of every cell when recording started
+/// * `current` - The covariance of every cell at the last recorded step
+/// * `steps` - The cells whose covariance changed at each recorded step
+pub struct CovarianceHistory {
+    initial: Vec<Vec<f32>>,
+    current: Vec<Vec<f32>>,
+    steps: Vec<HashMap<(usize, usize), f32>>,
+}
+
+impl CovarianceHistory {
+    /// Starts recording the covariance of a sampling grid
+    pub fn new(grid: &SampleGrid) -> Self {
+        let initial = covariances(grid);
+        CovarianceHistory {
+            current: initial.clone(),
+            initial,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Records the covariance of every cell that changed since the last step
+    pub fn record(&mut self, grid: &SampleGrid) {
+        let mut changed = HashMap::new();
+        for x in 0..grid.width {
+            for y in 0..grid.height {
+                let covariance = grid.covariance((x, y));
+                if covariance != self.current[x][y] {
+                    self.current[x][y] = covariance;
+                    changed.insert((x, y), covariance);
+                }
+            }
+        }
+        self.steps.push(changed);
+    }
+
+    /// The number of recorded steps
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Checks if no steps have been recorded
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Reconstructs the covariance of every cell after a given step
+    pub fn covariance_at(&self, step: usize) -> Vec<Vec<f32>> {
+        let mut covariance = self.initial.clone();
+        for changed in self.steps.iter().take(step + 1) {
+            for ((x, y), c) in changed {
+                covariance[*x][*y] = *c;
+            }
+        }
+        covariance
+    }
+
+    /// The reduction in covariance of every cell between the start of the
+    /// recording and a given step, normalised so the largest reduction is 1.0
+    pub fn reduction_at(&self, step: usize) -> Vec<((usize, usize), f64)> {
+        let covariance = self.covariance_at(step);
+        let reduction = self.initial.iter().zip(covariance.iter()).enumerate()
+            .flat_map(|(x, (a, b))| a.iter().zip(b.iter()).enumerate()
+                .map(move |(y, (a, b))| ((x, y), (a - b).max(0.0) as f64)))
+            .filter(|(_, r)| *r > 0.0)
+            .collect::<Vec<_>>();
+        let max = reduction.iter().map(|(_, r)| *r).fold(0.0, f64::max);
+        reduction.into_iter().map(|(n, r)| (n, r / max)).collect()
+    }
+
+    /// Plots the reduction in uncertainty after a given step as a heatmap
+    /// over the sampling grid
+    /// ## Arguments
+    /// * `grid` - The sampling grid that was recorded
+    /// * `step` - The step to plot
+    /// * `output_file` - The output file to save the plot to
+    /// * `path` - An optional path, such as the traversed corridor, to draw over the heatmap
+    #[cfg(feature = "viz")]
+    pub fn plot_cells(&self, grid: &SampleGrid, step: usize, output_file: &str, path: Option<Vec<(usize, usize)>>) {
+        grid.plot_sampling_cells(output_file, path, Some(self.reduction_at(step)));
+    }
+
+    /// Plots a frame for every recorded step, named `{file_path}_covariance_{step}.png`
+    #[cfg(feature = "viz")]
+    pub fn plot_evolution(&self, grid: &SampleGrid, file_path: &str, path: &[(usize, usize)]) {
+        for step in 0..self.len() {
+            let traversed = path.iter().take(step + 2).cloned().collect();
+            self.plot_cells(grid, step, &format!("{}_covariance_{}.png", file_path, step), Some(traversed));
+        }
+    }
+}
+
+/// How much a single observation changed the belief of a cell
+/// ## Fields
+/// * `node` - The observed cell
+/// * `gain` - The Kalman gain of the update, the weight given to the measurement
+/// * `state_change` - The absolute change in the state of the cell
+/// * `covariance_reduction` - The reduction in the covariance of the cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ObservationUpdate {
+    pub node: (usize, usize),
+    pub gain: f32,
+    pub state_change: f32,
+    pub covariance_reduction: f32,
+}
+
+/// The observations applied to each cell of a sampling grid, kept so that
+/// beliefs can be smoothed retrospectively and the information each
+/// observation contributed can be inspected
+/// ## Fields
+/// * `priors` - The belief of each cell before its first observation
+/// * `observations` - The (measurement, measurement covariance) pairs applied to each cell
+/// * `updates` - The change made by every observation in the order applied
+#[derive(Clone, Debug, Default)]
+pub struct ObservationHistory {
+    pub priors: HashMap<(usize, usize), KalmanNode>,
+    pub observations: HashMap<(usize, usize), Vec<(f32, f32)>>,
+    pub updates: Vec<ObservationUpdate>,
+}
+
+impl ObservationHistory {
+    /// Records an observation of a cell given its belief before the observation
+    pub fn record(&mut self, node: (usize, usize), prior: &KalmanNode, measurement: f32, measurement_covariance: f32) {
+        self.priors.entry(node).or_insert_with(|| prior.clone());
+        self.observations.entry(node).or_default().push((measurement, measurement_covariance));
+        let mut posterior = prior.clone();
+        let state = posterior.update(measurement, measurement_covariance);
+        self.updates.push(ObservationUpdate {
+            node,
+            gain: prior.covariance / (prior.covariance + measurement_covariance).max(1e-6),
+            state_change: (state - prior.state).abs(),
+            covariance_reduction: prior.covariance - posterior.covariance,
+        });
+    }
+
+    /// The mean Kalman gain of the observations, near 1.0 when measurements
+    /// dominate the belief and near 0.0 when they barely move it
+    pub fn mean_gain(&self) -> f32 {
+        self.updates.iter().map(|u| u.gain).sum::<f32>() / self.updates.len().max(1) as f32
+    }
+
+    /// The information each cell gained over every observation of it, the sum
+    /// of its state changes normalised so the largest is 1.0
+    pub fn information_gain(&self) -> Vec<((usize, usize), f64)> {
+        let mut gained: HashMap<(usize, usize), f64> = HashMap::new();
+        for update in &self.updates {
+            *gained.entry(update.node).or_default() += update.state_change as f64;
+        }
+        let max = gained.values().copied().fold(0.0, f64::max);
+        let mut gained = gained.into_iter()
+            .filter(|(_, g)| *g > 0.0)
+            .map(|(n, g)| (n, g / max))
+            .collect::<Vec<_>>();
+        gained.sort_by_key(|(n, _)| *n);
+        gained
+    }
+
+    /// Plots the information gained by every cell over an episode as a
+    /// heatmap over the sampling grid
+    /// ## Arguments
+    /// * `grid` - The sampling grid that was observed
+    /// * `output_file` - The output file to save the plot to
+    /// * `path` - An optional path, such as the traversed path, to draw over the heatmap
+    #[cfg(feature = "viz")]
+    pub fn plot_information_gain(&self, grid: &SampleGrid, output_file: &str, path: Option<Vec<(usize, usize)>>) {
+        grid.plot_sampling_cells(output_file, path, Some(self.information_gain()));
+    }
+
+    /// The smoothed belief of a cell after each of its observations
+    pub fn smooth_cell(&self, node: (usize, usize), process_noise: f32) -> Vec<KalmanNode> {
+        match (self.priors.get(&node), self.observations.get(&node)) {
+            (Some(prior), Some(observations)) => rts_smooth(prior, observations, process_noise),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Produces a belief map where every observed cell holds its smoothed belief
+    /// at the time of its first observation, using all later observations.
+    /// Cells that were never observed keep their belief from the grid.
+    pub fn smoothed_grid(&self, grid: &SampleGrid, process_noise: f32) -> Vec<Vec<KalmanNode>> {
+        let mut smoothed = grid.to_nodes();
+        for node in self.observations.keys() {
+            if let Some(first) = self.smooth_cell(*node, process_noise).first() {
+                smoothed[node.0][node.1] = first.clone();
+            }
+        }
+        smoothed
+    }
+}
+
+/// A single observation of a cell
+/// ## Fields
+/// * `time` - The timestep the observation was made at
+/// * `node` - The observed cell
+/// * `measurement` - The measured value, 1.0 for free and 0.0 for blocked
+/// * `covariance` - The covariance of the measurement
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObservationEvent {
+    pub time: usize,
+    pub node: (usize, usize),
+    pub measurement: f32,
+    pub covariance: f32,
+}
+
+/// A log of observations ordered by time which can be saved, loaded and
+/// replayed to reconstruct the belief of a sampling grid at any timestep
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObservationLog {
+    pub events: Vec<ObservationEvent>,
+}
+
+impl ObservationLog {
+    /// Records an observation, keeping the events ordered by time
+    pub fn record(&mut self, time: usize, node: (usize, usize), measurement: f32, covariance: f32) {
+        let index = self.events.partition_point(|e| e.time <= time);
+        self.events.insert(index, ObservationEvent { time, node, measurement, covariance });
+    }
+
+    /// Observes a cell of a sampling grid and records the measurement
+    pub fn observe(&mut self, grid: &mut SampleGrid, time: usize, node: (usize, usize), covariance: f32) {
+        grid.update_node(node, covariance);
+        let measurement = grid.ground_truth.get_bit_value(node) as u8 as f32;
+        self.record(time, node, measurement, covariance);
+    }
+
+    /// The number of recorded observations
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Checks if no observations have been recorded
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The timestep of the last observation
+    pub fn end_time(&self) -> Option<usize> {
+        self.events.last().map(|e| e.time)
+    }
+
+    /// The observations grouped by timestep, in the order they were made
+    pub fn stream(&self) -> impl Iterator<Item = (usize, &[ObservationEvent])> {
+        self.events.chunk_by(|a, b| a.time == b.time).map(|events| (events[0].time, events))
+    }
+
+    /// Reconstructs the belief of a sampling grid after every observation made
+    /// up to and including a timestep
+    /// ## Arguments
+    /// * `grid` - The grid holding the belief before the first observation
+    /// * `time` - The timestep to replay up to
+    pub fn replay(&self, grid: &SampleGrid, time: usize) -> SampleGrid {
+        let mut replayed = grid.clone();
+        for event in self.events.iter().take_while(|e| e.time <= time) {
+            replayed.update_belief(event.node, event.measurement, event.covariance);
+        }
+        replayed
+    }
+
+    /// Parses a log where each line is a tab separated `time x y measurement covariance`
+    pub fn parse(log: &str) -> io::Result<Self> {
+        let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid observation: {}", line));
+        let mut observations = ObservationLog::default();
+        for line in log.lines().filter(|l| !l.trim().is_empty()) {
+            let fields = line.split('\t').collect::<Vec<_>>();
+            if fields.len() != 5 {
+                return Err(invalid(line));
+            }
+            let index = |i: usize| fields[i].parse::<usize>().map_err(|_| invalid(line));
+            let value = |i: usize| fields[i].parse::<f32>().map_err(|_| invalid(line));
+            observations.record(index(0)?, (index(1)?, index(2)?), value(3)?, value(4)?);
+        }
+        Ok(observations)
+    }
+
+    /// Writes the log with one tab separated observation per line
+    pub fn write(&self) -> String {
+        let mut log = String::new();
+        for e in &self.events {
+            writeln!(log, "{}\t{}\t{}\t{}\t{}", e.time, e.node.0, e.node.1, e.measurement, e.covariance).unwrap();
+        }
+        log
+    }
+
+    /// Reads a log from a file
+    pub fn load(filename: &str) -> io::Result<Self> {
+        ObservationLog::parse(&std::fs::read_to_string(filename)?)
+    }
+
+    /// Writes the log to a file
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        std::fs::write(filename, self.write())
+    }
+}
+
+/// Delta-compressed snapshots of the belief of a sampling grid, one per step.
+/// The first snapshot holds every cell and each later snapshot only the cells
+/// whose belief or covariance changed, so long episodes stay small and any
+/// step can be restored for scrubbing through the episode.
+/// ## Fields
+/// * `width` - The width of the recorded grid
+/// * `height` - The height of the recorded grid
+/// * `initial` - The belief of every cell at the first snapshot
+/// * `current` - The belief of every cell at the last snapshot
+/// * `deltas` - The cells that changed at each snapshot after the first
+#[derive(Clone, Debug)]
+pub struct BeliefSnapshots {
+    width: usize,
+    height: usize,
+    initial: Vec<Vec<KalmanNode>>,
+    current: Vec<Vec<KalmanNode>>,
+    deltas: Vec<Vec<((usize, usize), KalmanNode)>>,
+}
+
+impl BeliefSnapshots {
+    /// Takes the first snapshot of a sampling grid
+    pub fn new(grid: &SampleGrid) -> Self {
+        let initial = grid.to_nodes();
+        BeliefSnapshots {
+            width: grid.width,
+            height: grid.height,
+            current: initial.clone(),
+            initial,
+            deltas: Vec::new(),
+        }
+    }
+
+    /// Takes a snapshot of the cells that changed since the last snapshot
+    /// ## Returns
+    /// The step of the snapshot
+    pub fn record(&mut self, grid: &SampleGrid) -> usize {
+        let mut changed = Vec::new();
+        for x in 0..self.width {
+            for y in 0..self.height {
+                let node = grid.node((x, y));
+                let last = &self.current[x][y];
+                if node.state != last.state || node.covariance != last.covariance {
+                    self.current[x][y] = node.clone();
+                    changed.push(((x, y), node.clone()));
+                }
+            }
+        }
+        self.deltas.push(changed);
+        self.deltas.len()
+    }
+
+    /// The number of snapshots, including the first
+    pub fn len(&self) -> usize {
+        self.deltas.len() + 1
+    }
+
+    /// Always false, as the first snapshot is taken on creation
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The cells that changed at a step, every cell for the first step
+    pub fn changed(&self, step: usize) -> Vec<((usize, usize), KalmanNode)> {
+        match step {
+            0 => (0..self.width)
+                .flat_map(|x| (0..self.height).map(move |y| (x, y)))
+                .map(|(x, y)| ((x, y), self.initial[x][y].clone()))
+                .collect(),
+            _ => self.deltas[step - 1].clone(),
+        }
+    }
+
+    /// The number of cells stored across all snapshots
+    pub fn stored_cells(&self) -> usize {
+        self.width * self.height + self.deltas.iter().map(Vec::len).sum::<usize>()
+    }
+
+    /// Reconstructs the belief of every cell at a step
+    pub fn nodes_at(&self, step: usize) -> Vec<Vec<KalmanNode>> {
+        let mut nodes = self.initial.clone();
+        for changed in self.deltas.iter().take(step) {
+            for ((x, y), node) in changed {
+                nodes[*x][*y] = node.clone();
+            }
+        }
+        nodes
+    }
+
+    /// Restores a sampling grid to its belief at a step
+    /// ## Arguments
+    /// * `grid` - A grid of the recorded size, such as the grid that was recorded
+    /// * `step` - The step to restore
+    pub fn restore(&self, grid: &SampleGrid, step: usize) -> SampleGrid {
+        let mut restored = grid.clone();
+        for (x, column) in self.nodes_at(step).into_iter().enumerate() {
+            for (y, node) in column.into_iter().enumerate() {
+                restored.set_node((x, y), node);
+            }
+        }
+        restored
+    }
+
+    /// Parses snapshots written by [`BeliefSnapshots::write`], a header line of
+    /// tab separated `width height snapshots` followed by one tab separated
+    /// `step x y belief covariance` line per stored cell
+    pub fn parse(snapshots: &str) -> io::Result<Self> {
+        let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid snapshot: {}", line));
+        let mut lines = snapshots.lines().filter(|l| !l.trim().is_empty());
+        let header = lines.next().ok_or_else(|| invalid(""))?;
+        let sizes = header.split('\t').map(|f| f.parse::<usize>()).collect::<Result<Vec<_>, _>>();
+        let (width, height, count) = match sizes.as_deref() {
+            Ok([width, height, count]) if *count > 0 => (*width, *height, *count),
+            _ => return Err(invalid(header)),
+        };
+        let mut initial = vec![vec![KalmanNode { state: 0.0, covariance: 0.0 }; height]; width];
+        let mut deltas = vec![Vec::new(); count - 1];
+        let mut seen = 0;
+        for line in lines {
+            let fields = line.split('\t').collect::<Vec<_>>();
+            if fields.len() != 5 {
+                return Err(invalid(line));
+            }
+            let index = |i: usize| fields[i].parse::<usize>().map_err(|_| invalid(line));
+            let value = |i: usize| fields[i].parse::<f32>().map_err(|_| invalid(line));
+            let (step, x, y) = (index(0)?, index(1)?, index(2)?);
+            if step >= count || x >= width || y >= height {
+                return Err(invalid(line));
+            }
+            let node = KalmanNode { state: value(3)?, covariance: value(4)? };
+            match step {
+                0 => {
+                    initial[x][y] = node;
+                    seen += 1;
+                }
+                _ => deltas[step - 1].push(((x, y), node)),
+            }
+        }
+        if seen != width * height {
+            return Err(invalid(header));
+        }
+        let mut current = initial.clone();
+        for ((x, y), node) in deltas.iter().flatten() {
+            current[*x][*y] = node.clone();
+        }
+        Ok(BeliefSnapshots { width, height, initial, current, deltas })
+    }
+
+    /// Writes the snapshots with one tab separated stored cell per line
+    pub fn write(&self) -> String {
+        let mut snapshots = String::new();
+        writeln!(snapshots, "{}\t{}\t{}", self.width, self.height, self.len()).unwrap();
+        for step in 0..self.len() {
+            for ((x, y), node) in self.changed(step) {
+                writeln!(snapshots, "{}\t{}\t{}\t{}\t{}", step, x, y, node.state, node.covariance).unwrap();
+            }
+        }
+        snapshots
+    }
+
+    /// Reads snapshots from a file
+    pub fn load(filename: &str) -> io::Result<Self> {
+        BeliefSnapshots::parse(&std::fs::read_to_string(filename)?)
+    }
+
+    /// Writes the snapshots to a file
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        std::fs::write(filename, self.write())
+    }
+}
+
+fn covariances(grid: &SampleGrid) -> Vec<Vec<f32>> {
+    grid.covariances()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::bitpackedgrid::BitPackedGrid;
+
+    #[test]
+    fn test_covariance_history() {
+        let mut grid = SampleGrid::new_from_string(".....\n.....\n.....\n".to_string());
+        let mut history = CovarianceHistory::new(&grid);
+        grid.update_node((1, 1), 1.0);
+        history.record(&grid);
+        grid.update_node((1, 1), 0.5);
+        grid.update_node((3, 2), 0.0);
+        history.record(&grid);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.covariance_at(0)[1][1], 0.5);
+        assert_eq!(history.covariance_at(0)[3][2], 1.0);
+        assert_eq!(history.covariance_at(1)[1][1], 0.25);
+        assert_eq!(history.covariance_at(1)[3][2], 0.0);
+        let reduction = history.reduction_at(1);
+        assert_eq!(reduction.len(), 2);
+        assert!(reduction.contains(&((3, 2), 1.0)));
+        assert!(reduction.contains(&((1, 1), 0.75)));
+    }
+
+    #[test]
+    fn test_observation_history() {
+        let mut grid = SampleGrid::new_from_string("...\n...\n".to_string());
+        grid.observation_history = Some(ObservationHistory::default());
+        grid.update_node((1, 1), 0.5);
+        grid.update_node((1, 1), 0.5);
+        let history = grid.observation_history.as_ref().unwrap();
+        assert_eq!(history.observations[&(1, 1)], vec![(1.0, 0.5), (1.0, 0.5)]);
+        assert_eq!(history.priors[&(1, 1)].covariance, 1.0);
+        let smoothed = history.smoothed_grid(&grid, 0.0);
+        assert_eq!(smoothed[1][1].covariance, grid.covariance((1, 1)));
+        assert_eq!(smoothed[0][0].covariance, 1.0);
+    }
+
+    #[test]
+    fn test_observation_updates() {
+        let truth = BitPackedGrid::new_from_string("..@\n...\n".to_string());
+        let mut grid = SampleGrid::new_from_grid(vec![vec![0.5; 2]; 3], truth);
+        grid.observation_history = Some(ObservationHistory::default());
+        grid.update_node((2, 0), 1.0);
+        grid.update_node((2, 0), 1.0);
+        grid.update_node((0, 1), 0.0);
+        let history = grid.observation_history.as_ref().unwrap();
+        assert_eq!(history.updates.len(), 3);
+        assert_eq!(history.updates[0], ObservationUpdate { node: (2, 0), gain: 0.5, state_change: 0.25, covariance_reduction: 0.5 });
+        assert!(history.updates[1].gain < history.updates[0].gain);
+        // A certain measurement replaces the belief
+        assert_eq!((history.updates[2].gain, history.updates[2].state_change), (1.0, 0.5));
+        assert!((history.mean_gain() - (0.5 + 1.0 / 3.0 + 1.0) / 3.0).abs() < 1e-6);
+        let gained = history.information_gain();
+        assert_eq!(gained[0].0, (0, 1));
+        assert!((gained[1].1 - 2.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_observation_log_replay() {
+        let mut grid = SampleGrid::new_from_string("..@\n...\n".to_string());
+        let initial = grid.clone();
+        let mut log = ObservationLog::default();
+        log.observe(&mut grid, 0, (0, 0), 0.5);
+        log.observe(&mut grid, 2, (2, 0), 0.0);
+        log.observe(&mut grid, 2, (1, 1), 0.5);
+        log.record(1, (0, 0), 1.0, 0.5);
+        grid.update_node((0, 0), 0.5);
+        assert_eq!(log.len(), 4);
+        assert_eq!(log.end_time(), Some(2));
+        let stream = log.stream().map(|(t, e)| (t, e.len())).collect::<Vec<_>>();
+        assert_eq!(stream, vec![(0, 1), (1, 1), (2, 2)]);
+
+        let before = log.replay(&initial, 0);
+        assert!((before.covariance((0, 0)) - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(before.covariance((2, 0)), 1.0);
+        let after = log.replay(&initial, 2);
+        for x in 0..grid.width {
+            for y in 0..grid.height {
+                assert_eq!(after.belief((x, y)), grid.belief((x, y)));
+                assert_eq!(after.covariance((x, y)), grid.covariance((x, y)));
+            }
+        }
+        assert_eq!(after.belief((2, 0)), 0.0);
+
+        let file = std::env::temp_dir().join("observation_log_test.tsv");
+        log.save(file.to_str().unwrap()).unwrap();
+        assert_eq!(ObservationLog::load(file.to_str().unwrap()).unwrap(), log);
+        assert!(ObservationLog::parse("0\t1\tx\t1\t0.5").is_err());
+    }
+
+    #[test]
+    fn test_belief_snapshots() {
+        let mut grid = SampleGrid::new_from_string("...\n...\n".to_string());
+        let initial = grid.clone();
+        let mut snapshots = BeliefSnapshots::new(&grid);
+        grid.update_node((1, 1), 0.5);
+        assert_eq!(snapshots.record(&grid), 1);
+        snapshots.record(&grid);
+        grid.update_node((2, 0), 0.0);
+        snapshots.record(&grid);
+        assert_eq!(snapshots.len(), 4);
+        assert_eq!(snapshots.changed(1).len(), 1);
+        assert!(snapshots.changed(2).is_empty());
+        assert_eq!(snapshots.stored_cells(), 8);
+
+        let restored = snapshots.restore(&grid, 1);
+        assert_eq!(restored.covariance((1, 1)), grid.covariance((1, 1)));
+        assert_eq!(restored.covariance((2, 0)), initial.covariance((2, 0)));
+        assert_eq!(snapshots.restore(&grid, 0).covariance((1, 1)), initial.covariance((1, 1)));
+        assert_eq!(snapshots.restore(&initial, 3).covariance((2, 0)), 0.0);
+
+        let file = std::env::temp_dir().join("belief_snapshots_test.tsv");
+        snapshots.save(file.to_str().unwrap()).unwrap();
+        let loaded = BeliefSnapshots::load(file.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.write(), snapshots.write());
+        assert!(BeliefSnapshots::parse("3\t2\t1\n0\t0\t0\t1\t1\n").is_err());
+    }
+}
@@ -1,4 +1,13 @@
-//! Matrix utilities.
+//! # Matrix Utilities
+//! Convolutions of matrices indexed as `matrix[x][y]`, such as the beliefs of
+//! a sampling grid or cost layers of a grid map, generic over the element and
+//! kernel types. These include:
+//! * Convolution with a kernel, and dilation taking the largest weighted value
+//!   instead of the sum, under each of the border modes of `ConvResolve`
+//! * Separable convolution with a column and a row kernel, which for a kernel
+//!   of size k costs 2k instead of k² per cell, as for Gaussian blurs of large grids
+//! * Kernels: Gaussian, box, Sobel and distance-weighted inflation around
+//!   obstacles
 
 use std::ops::{Add, Mul};
 
@@ -7,8 +16,8 @@ use std::ops::{Add, Mul};
 /// * `kernel` - The kernel to convolve with.
 /// * `resolution` - The resolution to use when resolving the matrix at the edges.
 pub fn convolve2d<T, K>(
-    matrix: &Vec<Vec<T>>,
-    kernel: &Vec<Vec<K>>,
+    matrix: &[Vec<T>],
+    kernel: &[Vec<K>],
     resolution: ConvResolve<T>
 ) -> Vec<Vec<T>> 
 where 
@@ -34,54 +43,100 @@ where
     result
 }
 
+/// Convolve a matrix with a separable kernel, the outer product of a column
+/// and a row kernel, as one pass along each axis.
+/// * `matrix` - The matrix to convolve.
+/// * `column` - The kernel along `x`, the first index of the matrix.
+/// * `row` - The kernel along `y`, the second index of the matrix.
+/// * `resolution` - The resolution to use when resolving the matrix at the edges.
+pub fn convolve_separable<T, K>(
+    matrix: &[Vec<T>],
+    column: &[K],
+    row: &[K],
+    resolution: ConvResolve<T>,
+) -> Vec<Vec<T>>
+where
+    T: Clone + Default + Add<Output = T> + Mul<K, Output = T>,
+    K: Clone,
+{
+    let along_x = convolve2d(matrix, &column.iter().map(|k| vec![k.clone()]).collect::<Vec<_>>(), resolution.clone());
+    convolve2d(&along_x, &[row.to_vec()], resolution)
+}
+
+/// Dilate a matrix with a kernel, taking at each cell the largest value of
+/// its neighbours weighted by the kernel instead of their sum, such as to
+/// inflate the cost of cells near obstacles with `inflation_kernel`.
+/// * `matrix` - The matrix to dilate.
+/// * `kernel` - The weight of each neighbour.
+/// * `resolution` - The resolution to use when resolving the matrix at the edges.
+pub fn dilate2d<T, K>(
+    matrix: &[Vec<T>],
+    kernel: &[Vec<K>],
+    resolution: ConvResolve<T>,
+) -> Vec<Vec<T>>
+where
+    T: Clone + Default + PartialOrd + Mul<K, Output = T>,
+    K: Clone,
+{
+    let (center_x, center_y) = (kernel.len() / 2, kernel[0].len() / 2);
+    (0..matrix.len()).map(|x| (0..matrix[0].len()).map(|y| {
+        let mut largest = T::default();
+        for (i, column) in kernel.iter().enumerate() {
+            for (j, weight) in column.iter().enumerate() {
+                let value = resolution.resolve(matrix, (x + i) as i64 - center_x as i64, (y + j) as i64 - center_y as i64) * weight.clone();
+                if value > largest {
+                    largest = value;
+                }
+            }
+        }
+        largest
+    }).collect()).collect()
+}
+
 /// Resolution method for resolving the value of a matrix at the edges.
+#[derive(Clone, Debug, PartialEq)]
 pub enum ConvResolve<T: Clone> {
     /// Fill the matrix with the given value. |T T T|a b c|T T T|
     Fill(T),
-    /// Wrap around the matrix. |a b c|a b c|b c|
+    /// Wrap around the matrix. |a b c|a b c|a b c|
     Wrap,
     /// Use the nearest value. |a a a|a b c|c c c|
     Nearest,
-    /// Reflect the matrix. |c b a|a b c|c b a|
-    Reflect
+    /// Reflect the matrix, repeating the edge. |c b a|a b c|c b a|
+    Reflect,
+    /// Mirror the matrix about the edge without repeating it. |c b|a b c|b a|
+    Mirror,
 }
 
 impl<T: Clone> ConvResolve<T> {
-    /// Resolve the value of a matrix at a given position.
-    fn resolve(&self, matrix: &Vec<Vec<T>>, matrix_x: i64, matrix_y: i64) -> T {
-        let (matrix_x, matrix_y) = match self {
-            ConvResolve::Fill(fill) => 
-                if matrix_x >= 0 && matrix_y >= 0 && matrix_x < matrix.len() as i64 && matrix_y < matrix[0].len() as i64 {
-                    (matrix_x, matrix_y)
-                } else {
-                    return fill.clone()
-                },
-            ConvResolve::Wrap => (
-                (matrix_x + matrix.len() as i64) % matrix.len() as i64,
-                (matrix_y + matrix[0].len() as i64) % matrix[0].len() as i64
-            ), 
-            ConvResolve::Nearest => (
-                matrix_x.clamp(0, matrix.len() as i64 - 1),
-                matrix_y.clamp(0, matrix[0].len() as i64 - 1)
-            ),
-            ConvResolve::Reflect => (
-                if matrix_x < 0 {
-                    matrix_x.abs() - 1 
-                } else if matrix_x >= matrix.len() as i64 {
-                    matrix_x - matrix.len() as i64 + 1
-                }else {
-                    matrix_x
-                },
-                if matrix_y < 0 {
-                    matrix_y.abs() - 1 
-                } else if matrix_y >= matrix[0].len() as i64 {
-                    matrix_y - matrix[0].len() as i64 + 1
-                } else { 
-                    matrix_y
-                },
-            ),
+    /// Resolve the index of a position along an axis of a length, `None`
+    /// when the position is filled.
+    fn index(&self, position: i64, length: usize) -> Option<usize> {
+        let length = length as i64;
+        let index = match self {
+            ConvResolve::Fill(_) => return (0..length).contains(&position).then_some(position as usize),
+            ConvResolve::Wrap => position.rem_euclid(length),
+            ConvResolve::Nearest => position.clamp(0, length - 1),
+            ConvResolve::Reflect => {
+                let offset = position.rem_euclid(2 * length);
+                if offset < length { offset } else { 2 * length - 1 - offset }
+            }
+            ConvResolve::Mirror if length == 1 => 0,
+            ConvResolve::Mirror => {
+                let offset = position.rem_euclid(2 * length - 2);
+                if offset < length { offset } else { 2 * length - 2 - offset }
+            }
         };
-        matrix[matrix_x as usize][matrix_y as usize].clone()
+        Some(index as usize)
+    }
+
+    /// Resolve the value of a matrix at a given position.
+    fn resolve(&self, matrix: &[Vec<T>], matrix_x: i64, matrix_y: i64) -> T {
+        match (self.index(matrix_x, matrix.len()), self.index(matrix_y, matrix[0].len()), self) {
+            (Some(x), Some(y), _) => matrix[x][y].clone(),
+            (_, _, ConvResolve::Fill(fill)) => fill.clone(),
+            _ => unreachable!("Only filled positions have no index"),
+        }
     }
 }
 
@@ -130,6 +185,56 @@ pub fn gaussian_kernal(size: usize, sigma: f32) -> Vec<Vec<f32>> {
     kernel
 }
 
+/// Create a one dimensional gaussian kernel, whose outer product with itself
+/// is `gaussian_kernal`, for `convolve_separable`.
+/// * `size` - The size of the kernel.
+/// * `sigma` - The sigma value of the gaussian.
+pub fn gaussian_kernel_1d(size: usize, sigma: f32) -> Vec<f32> {
+    let center = size / 2;
+    let kernel = (0..size)
+        .map(|i| (-((i as f32 - center as f32).powi(2)) / (2.0 * sigma * sigma)).exp())
+        .collect::<Vec<_>>();
+    let sum = kernel.iter().sum::<f32>();
+    kernel.into_iter().map(|k| k / sum).collect()
+}
+
+/// Blur a matrix with a gaussian kernel as a separable convolution.
+/// * `size` - The size of the kernel.
+/// * `sigma` - The sigma value of the gaussian.
+pub fn gaussian_blur<T>(matrix: &[Vec<T>], size: usize, sigma: f32, resolution: ConvResolve<T>) -> Vec<Vec<T>>
+where
+    T: Clone + Default + Add<Output = T> + Mul<f32, Output = T>,
+{
+    let kernel = gaussian_kernel_1d(size, sigma);
+    convolve_separable(matrix, &kernel, &kernel, resolution)
+}
+
+/// Create a box kernel averaging every cell within it.
+/// * `size` - The size of the kernel.
+pub fn box_kernel(size: usize) -> Vec<Vec<f32>> {
+    vec![vec![1.0 / (size * size) as f32; size]; size]
+}
+
+/// Create the 3x3 Sobel kernels of the gradient along `x` and `y`.
+pub fn sobel_kernels() -> (Vec<Vec<f32>>, Vec<Vec<f32>>) {
+    let along_x = vec![vec![-1.0, -2.0, -1.0], vec![0.0, 0.0, 0.0], vec![1.0, 2.0, 1.0]];
+    let along_y = along_x.iter().enumerate().map(|(i, _)| along_x.iter().map(|column| column[i]).collect()).collect();
+    (along_x, along_y)
+}
+
+/// Create a kernel of weights falling off linearly with the euclidean distance
+/// from its center, 1.0 at the center and 0.0 beyond a radius, for inflating
+/// the cost of cells near obstacles with `dilate2d`.
+/// * `radius` - The distance at which the weight reaches 0.0.
+pub fn inflation_kernel(radius: usize) -> Vec<Vec<f32>> {
+    let size = 2 * radius + 1;
+    let falloff = radius as f32 + 1.0;
+    (0..size).map(|i| (0..size).map(|j| {
+        let distance = ((i as f32 - radius as f32).powi(2) + (j as f32 - radius as f32).powi(2)).sqrt();
+        (1.0 - distance / falloff).max(0.0)
+    }).collect()).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -171,10 +276,47 @@ mod tests {
         let kernel = ones(3);
         let result = convolve2d(&matrix, &kernel, ConvResolve::Reflect);
         assert_eq!(result, vec![
-            vec![21, 27, 30],
-            vec![39, 45, 48],
-            vec![48, 54, 57],
+            vec![21, 27, 33],
+            vec![39, 45, 51],
+            vec![57, 63, 69],
+        ]);
+        // Two cells beyond the edge reflect to the cell next to it
+        let result = convolve2d(&matrix, &ones(5), ConvResolve::Reflect);
+        assert_eq!((result[0][0], result[2][2]), (105, 145));
+    }
+
+    #[test]
+    fn test_convolve_mirror() {
+        let matrix = count(3, 3);
+        let result = convolve2d(&matrix, &ones(3), ConvResolve::Mirror);
+        assert_eq!(result, vec![
+            vec![33, 36, 39],
+            vec![42, 45, 48],
+            vec![51, 54, 57],
         ]);
+        // Kernels wider than the matrix wrap around more than once
+        assert_eq!(convolve2d(&[vec![1, 2]], &[vec![1; 5]], ConvResolve::Wrap), vec![vec![7, 8]]);
+    }
+
+    #[test]
+    fn test_separable_and_kernels() {
+        let matrix = (0..12).map(|x| (0..9).map(|y| ((x * 7 + y * 3) % 5) as f32).collect()).collect::<Vec<Vec<f32>>>();
+        let full = convolve2d(&matrix, &gaussian_kernal(5, 1.5), ConvResolve::Reflect);
+        let separable = gaussian_blur(&matrix, 5, 1.5, ConvResolve::Reflect);
+        assert!(full.iter().flatten().zip(separable.iter().flatten()).all(|(a, b)| (a - b).abs() < 1e-5));
+        let averaged = convolve2d(&vec![vec![2.0; 4]; 4], &box_kernel(3), ConvResolve::Nearest);
+        assert!(averaged.iter().flatten().all(|v| (v - 2.0).abs() < 1e-6));
+        // The gradient of a ramp along x
+        let ramp = (0..4).map(|x| vec![x as f32; 4]).collect::<Vec<_>>();
+        let (along_x, along_y) = sobel_kernels();
+        assert_eq!(convolve2d(&ramp, &along_x, ConvResolve::Nearest)[1][1], 8.0);
+        assert_eq!(convolve2d(&ramp, &along_y, ConvResolve::Nearest)[1][1], 0.0);
+        // Inflation around a single obstacle falls off with distance
+        let mut obstacles = vec![vec![0.0; 7]; 7];
+        obstacles[3][3] = 1.0;
+        let inflated = dilate2d(&obstacles, &inflation_kernel(2), ConvResolve::Fill(0.0));
+        assert_eq!((inflated[3][3], inflated[0][3]), (1.0, 0.0));
+        assert!(inflated[3][4] > inflated[3][5] && inflated[3][5] > 0.0 && inflated[4][4] < inflated[3][4]);
     }
 
     #[test]
@@ -3,6 +3,27 @@
 //! * `matrix` - Contains matrix operations
 //! * `filter` - Contains probability filter implementations
 //! * `visualiser` - Contains visualisation functions for grids
+//! * `history` - Contains recordings of sampling grid beliefs over time
+//! * `calibration` - Contains calibration metrics of sampling grid beliefs
+//! * `preprocessing` - Contains parallel builders for preprocessing artifacts
+//! * `notify` - Contains change notifications for mutable domains
+//! * `determinism` - Contains the seeded generator and stable hashers of deterministic runs
+//! * `tuning` - Contains fitting of Kalman filter parameters from logged observations
+//! * `base64` - Contains the URL-safe base64 of compact grid and path strings
+
+#![allow(dead_code)]
 pub mod matrix;
+#[cfg(feature = "sampling")]
 pub mod filter;
+#[cfg(feature = "io")]
 pub mod visualiser;
+#[cfg(feature = "sampling")]
+pub mod history;
+#[cfg(feature = "sampling")]
+pub mod calibration;
+pub mod preprocessing;
+pub mod notify;
+pub mod determinism;
+#[cfg(feature = "sampling")]
+pub mod tuning;
+pub mod base64;
@@ -0,0 +1,118 @@
+//! Change notifications for mutable domains. Mutations are recorded as they
+//! happen and published as batches to every subscriber, so incremental
+//! planners such as D* Lite and caches can find the cells that changed since
+//! they last looked without diffing whole grids.
+
+use std::{collections::HashSet, hash::Hash, sync::mpsc::{channel, Receiver, Sender}};
+
+/// Records changed nodes and publishes them in batches to subscribers
+/// ## Fields
+/// * `pending` - The nodes changed since the last publish
+#[derive(Clone, Debug)]
+pub struct ChangeNotifier<N> {
+    pub pending: Vec<N>,
+    recorded: HashSet<N>,
+    subscribers: Vec<Sender<Vec<N>>>,
+}
+
+impl<N> Default for ChangeNotifier<N> {
+    fn default() -> Self {
+        ChangeNotifier { pending: Vec::new(), recorded: HashSet::new(), subscribers: Vec::new() }
+    }
+}
+
+impl<N: Clone + Hash + Eq> ChangeNotifier<N> {
+    /// Adds a subscriber that receives every batch published after it subscribed
+    pub fn subscribe(&mut self) -> Subscription<N> {
+        let (sender, receiver) = channel();
+        self.subscribers.push(sender);
+        Subscription { receiver }
+    }
+
+    /// Records a changed node for the next batch
+    pub fn record(&mut self, node: N) {
+        if self.recorded.insert(node.clone()) {
+            self.pending.push(node);
+        }
+    }
+
+    /// Sends the pending changes to every subscriber, forgetting subscribers
+    /// that have been dropped
+    /// ## Returns
+    /// The number of nodes in the batch
+    pub fn publish(&mut self) -> usize {
+        if self.pending.is_empty() {
+            return 0;
+        }
+        let batch = std::mem::take(&mut self.pending);
+        self.recorded.clear();
+        self.subscribers.retain(|s| s.send(batch.clone()).is_ok());
+        batch.len()
+    }
+
+    /// Takes the pending changes without publishing them, such as when the
+    /// notifier only tracks which nodes changed
+    pub fn take_pending(&mut self) -> Vec<N> {
+        self.recorded.clear();
+        std::mem::take(&mut self.pending)
+    }
+
+    /// Moves or drops the pending changes, such as after a grid is resized
+    pub fn remap(&mut self, moved: impl Fn(N) -> Option<N>) {
+        let pending = std::mem::take(&mut self.pending);
+        self.recorded.clear();
+        for node in pending.into_iter().filter_map(moved) {
+            self.record(node);
+        }
+    }
+
+    /// The number of subscribers that have not been dropped as of the last publish
+    pub fn subscribers(&self) -> usize {
+        self.subscribers.len()
+    }
+}
+
+/// The receiving end of a change notifier
+pub struct Subscription<N> {
+    receiver: Receiver<Vec<N>>,
+}
+
+impl<N: Hash + Eq + Clone> Subscription<N> {
+    /// Takes every change published since the last call, in publish order and
+    /// without duplicates. This can be used directly as the mutator of D* Lite.
+    pub fn changes(&self) -> Vec<N> {
+        let mut seen = HashSet::new();
+        self.receiver.try_iter().flatten().filter(|n| seen.insert(n.clone())).collect()
+    }
+
+    /// Takes the published batches since the last call
+    pub fn batches(&self) -> Vec<Vec<N>> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_notifier() {
+        let mut notifier = ChangeNotifier::default();
+        let first = notifier.subscribe();
+        notifier.record((0, 0));
+        notifier.record((0, 0));
+        notifier.record((1, 0));
+        let second = notifier.subscribe();
+        assert_eq!(notifier.publish(), 2);
+        assert_eq!(notifier.publish(), 0);
+        notifier.record((1, 0));
+        notifier.publish();
+        assert_eq!(first.batches(), vec![vec![(0, 0), (1, 0)], vec![(1, 0)]]);
+        assert_eq!(second.changes(), vec![(0, 0), (1, 0)]);
+        assert!(second.changes().is_empty());
+        drop(first);
+        notifier.record((2, 2));
+        notifier.publish();
+        assert_eq!(notifier.subscribers(), 1);
+    }
+}
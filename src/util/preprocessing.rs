@@ -0,0 +1,93 @@
+//! Parallel construction of preprocessing artifacts. Independent units of work,
+//! such as the clusters of an abstraction or the blocks of a partition, are
+//! processed across threads with rayon and the build is timed. Without the
+//! `parallel` feature the same builders run on the calling thread. Seeded
+//! builds give each item a generator derived from a master seed, so randomised
+//! work gives the same results whatever the number of threads.
+
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Statistics of a preprocessing build
+/// ## Fields
+/// * `elapsed` - The wall-clock time of the build
+/// * `items` - The number of units of work processed
+/// * `threads` - The number of threads available to the build
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildStats {
+    pub elapsed: Duration,
+    pub items: usize,
+    pub threads: usize,
+}
+
+impl BuildStats {
+    /// The number of units of work processed per second
+    pub fn items_per_second(&self) -> f64 {
+        self.items as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Maps every item in parallel, preserving the order of the items
+/// ## Returns
+/// The mapped items and the statistics of the build
+pub fn par_build<T, R, F>(items: &[T], build: F) -> (Vec<R>, BuildStats)
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync + Send,
+{
+    let start = Instant::now();
+    #[cfg(feature = "parallel")]
+    let (results, threads) = (items.par_iter().map(build).collect::<Vec<_>>(), rayon::current_num_threads());
+    #[cfg(not(feature = "parallel"))]
+    let (results, threads) = (items.iter().map(build).collect::<Vec<_>>(), 1);
+    let stats = BuildStats {
+        elapsed: start.elapsed(),
+        items: items.len(),
+        threads,
+    };
+    (results, stats)
+}
+
+/// Maps every item in parallel like `par_build`, running each with the
+/// thread's generator seeded by `derive_seed(master, index)` of the item
+#[cfg(feature = "sampling")]
+pub fn par_build_seeded<T, R, F>(items: &[T], master: u64, build: F) -> (Vec<R>, BuildStats)
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync + Send,
+{
+    use crate::util::determinism::{derive_seed, with_seed};
+    let indexed = items.iter().enumerate().collect::<Vec<_>>();
+    par_build(&indexed, |(i, item)| with_seed(derive_seed(master, *i as u64), || build(item)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_par_build() {
+        let (results, stats) = par_build(&(0..100).collect::<Vec<_>>(), |x| x * 2);
+        assert_eq!(results, (0..100).map(|x| x * 2).collect::<Vec<_>>());
+        assert_eq!(stats.items, 100);
+        assert!(stats.threads >= 1);
+        assert!(stats.items_per_second() > 0.0);
+    }
+
+    #[cfg(feature = "sampling")]
+    #[test]
+    fn test_par_build_seeded() {
+        use rand::Rng;
+        use crate::util::determinism::with_rng;
+        let draw = |_: &usize| with_rng(|rng| rng.gen::<u64>());
+        let (first, _) = par_build_seeded(&(0..64).collect::<Vec<_>>(), 9, draw);
+        let (second, _) = par_build_seeded(&(0..64).collect::<Vec<_>>(), 9, draw);
+        assert_eq!(first, second);
+        let (prefix, _) = par_build_seeded(&(0..8).collect::<Vec<_>>(), 9, draw);
+        assert_eq!(prefix[..], first[..8]);
+    }
+}
@@ -0,0 +1,143 @@
+//! Offline tuning of the Kalman filter parameters of sampling grids. Logged
+//! observation sequences, each with the prior belief of its cell and whether
+//! the cell is free in the ground truth, are replayed for candidate parameters.
+//! The parameters are chosen to maximise the predictive likelihood of the
+//! ground truth under the belief after each observation, which rewards
+//! accurate beliefs and penalises covariances that are over or under confident.
+
+use std::f64::consts::PI;
+
+use crate::domains::bitpackedgrid::BitPackedGrid;
+use crate::util::filter::KalmanNode;
+use crate::util::history::ObservationHistory;
+
+/// A logged observation sequence of a cell
+/// ## Fields
+/// * `prior` - The belief of the cell before its first observation
+/// * `measurements` - The measurements of the cell in order
+/// * `truth` - Whether the cell is free in the ground truth
+#[derive(Clone, Debug)]
+pub struct Sequence {
+    pub prior: KalmanNode,
+    pub measurements: Vec<f32>,
+    pub truth: bool,
+}
+
+/// Fitted filter parameters
+/// ## Fields
+/// * `measurement_covariance` - The covariance of each measurement
+/// * `process_noise` - The variance added to a belief before each measurement
+/// * `log_likelihood` - The predictive log-likelihood of the ground truth
+#[derive(Clone, Debug, PartialEq)]
+pub struct KalmanParameters {
+    pub measurement_covariance: f32,
+    pub process_noise: f32,
+    pub log_likelihood: f64,
+}
+
+/// Collects the observation sequences of a history with the ground truth of
+/// each cell, ordered by cell
+pub fn sequences(history: &ObservationHistory, ground_truth: &BitPackedGrid) -> Vec<Sequence> {
+    let mut nodes = history.observations.keys().copied().collect::<Vec<_>>();
+    nodes.sort_unstable();
+    nodes.into_iter()
+        .filter_map(|node| Some(Sequence {
+            prior: history.priors.get(&node)?.clone(),
+            measurements: history.observations[&node].iter().map(|(m, _)| *m).collect(),
+            truth: ground_truth.get_bit_value(node),
+        }))
+        .collect()
+}
+
+/// The predictive log-likelihood of the ground truth of every sequence given
+/// filter parameters
+pub fn log_likelihood(sequences: &[Sequence], measurement_covariance: f32, process_noise: f32) -> f64 {
+    let mut total = 0.0;
+    for sequence in sequences {
+        let mut node = sequence.prior.clone();
+        let truth = sequence.truth as u8 as f64;
+        for measurement in &sequence.measurements {
+            node.covariance += process_noise;
+            node.update(*measurement, measurement_covariance);
+            let variance = (node.covariance as f64).max(1e-9);
+            let error = truth - node.state as f64;
+            total -= 0.5 * ((2.0 * PI * variance).ln() + error * error / variance);
+        }
+    }
+    total
+}
+
+/// Fits the measurement covariance and process noise maximising the predictive
+/// log-likelihood, searching a logarithmic grid and then refining around the best
+/// candidate
+/// ## Arguments
+/// * `sequences` - The logged observation sequences
+/// * `range` - The (lowest, highest) values considered for either parameter
+/// * `steps` - The number of candidates per parameter in each round
+pub fn fit(sequences: &[Sequence], range: (f32, f32), steps: usize) -> KalmanParameters {
+    assert!(range.0 > 0.0 && range.0 < range.1 && steps > 1, "Invalid search range");
+    let (mut low, mut high) = ((range.0 as f64).ln(), (range.1 as f64).ln());
+    let (mut q_low, mut q_high) = (low, high);
+    let mut best = KalmanParameters { measurement_covariance: range.0, process_noise: range.0, log_likelihood: f64::NEG_INFINITY };
+    for _ in 0..3 {
+        let (r_step, q_step) = ((high - low) / (steps - 1) as f64, (q_high - q_low) / (steps - 1) as f64);
+        for i in 0..steps {
+            for j in 0..steps {
+                let (r, q) = ((low + r_step * i as f64).exp() as f32, (q_low + q_step * j as f64).exp() as f32);
+                let log_likelihood = log_likelihood(sequences, r, q);
+                if log_likelihood > best.log_likelihood {
+                    best = KalmanParameters { measurement_covariance: r, process_noise: q, log_likelihood };
+                }
+            }
+        }
+        let (r, q) = ((best.measurement_covariance as f64).ln(), (best.process_noise as f64).ln());
+        (low, high) = (r - r_step, r + r_step);
+        (q_low, q_high) = (q - q_step, q + q_step);
+    }
+    best
+}
+
+/// Fits the filter parameters from the observation history of a sampling grid
+pub fn fit_history(history: &ObservationHistory, ground_truth: &BitPackedGrid) -> KalmanParameters {
+    fit(&sequences(history, ground_truth), (1e-4, 1e2), 25)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::determinism::{reseed, with_rng};
+    use rand::Rng;
+
+    #[test]
+    fn test_fit() {
+        reseed(5);
+        // Measurements of the truth with gaussian noise of variance 0.09
+        let noise = || with_rng(|rng| {
+            let (u, v) = (rng.gen::<f32>().max(1e-9), rng.gen::<f32>());
+            (-2.0 * u.ln()).sqrt() * (2.0 * std::f32::consts::PI * v).cos() * 0.3
+        });
+        let logged = (0..200)
+            .map(|i| {
+                let truth = i % 3 != 0;
+                Sequence {
+                    prior: KalmanNode { state: 0.5, covariance: 1.0 },
+                    measurements: (0..5).map(|_| truth as u8 as f32 + noise()).collect(),
+                    truth,
+                }
+            })
+            .collect::<Vec<_>>();
+        let fitted = fit(&logged, (1e-4, 1e2), 25);
+        assert!(fitted.measurement_covariance > 0.03 && fitted.measurement_covariance < 0.3);
+        assert!(fitted.process_noise < 0.01);
+        assert!(fitted.log_likelihood > log_likelihood(&logged, 1.0, 0.1));
+
+        let mut grid = crate::domains::samplegrid::SampleGrid::new_from_string("...\n.@.\n".to_string());
+        grid.observation_history = Some(ObservationHistory::default());
+        grid.update_node((1, 1), 0.5);
+        grid.update_node((0, 0), 0.5);
+        let history = grid.observation_history.as_ref().unwrap();
+        let logged = sequences(history, &grid.ground_truth);
+        assert_eq!(logged.iter().map(|s| s.truth).collect::<Vec<_>>(), vec![true, false]);
+        assert!(fit_history(history, &grid.ground_truth).log_likelihood.is_finite());
+    }
+}
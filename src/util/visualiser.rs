@@ -68,13 +68,7 @@ impl Visualiser {
 }
 
 fn get_sample_grid(grid: &SampleGrid) -> Vec<Vec<f32>> {
-    let mut sample_grid = vec![vec![0.0; grid.height]; grid.width];
-    for x in 0..grid.width {
-        for y in 0..grid.height {
-            sample_grid[x][y] = grid.sample_grid[x][y].state;
-        }
-    }
-    sample_grid
+    grid.beliefs()
 }
 
 fn hashmap_to_adjlist(map: &HashMap<(usize, usize), usize>) -> AdjacencyList<(usize, usize), f32> {